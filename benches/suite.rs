@@ -2,9 +2,16 @@
 extern crate bencher;
 extern crate sprs;
 extern crate alga;
+#[cfg(feature = "half")]
+extern crate half;
 
 use bencher::Bencher;
-use sprs::CsVec;
+use sprs::{CsMat, CsVec, TriMat};
+use sprs::prod::mul_acc_mat_vec_mixed;
+#[cfg(feature = "half")]
+use sprs::prod::csr_mul_csr_mixed;
+#[cfg(feature = "half")]
+use half::f16;
 use alga::general::{Inverse, Additive};
 
 fn csvec_neg(bench: &mut Bencher) {
@@ -21,5 +28,74 @@ fn csvec_additive_inverse(bench: &mut Bencher) {
     });
 }
 
-benchmark_group!(benches, csvec_neg, csvec_additive_inverse);
+fn banded_mat_f32(n: usize) -> CsMat<f32> {
+    let mut tri = TriMat::new((n, n));
+    for i in 0..n {
+        for j in i.saturating_sub(5)..(i + 6).min(n) {
+            tri.add_triplet(i, j, 1. / (1 + i + j) as f32);
+        }
+    }
+    tri.to_csr()
+}
+
+fn csmat_matvec_mixed_f32_f64(bench: &mut Bencher) {
+    let mat = banded_mat_f32(10000);
+    let x = vec![1f64; 10000];
+    let mut y = vec![0.; 10000];
+    bench.iter(|| {
+        mul_acc_mat_vec_mixed(mat.view(), &x, &mut y);
+    });
+}
+
+fn csmat_matvec_mixed_reference(bench: &mut Bencher) {
+    let mat: CsMat<f64> = banded_mat_f32(10000).cast_values();
+    let x = vec![1f64; 10000];
+    let mut y = vec![0.; 10000];
+    bench.iter(|| {
+        mul_acc_mat_vec_mixed(mat.view(), &x, &mut y);
+    });
+}
+
+#[cfg(feature = "half")]
+fn banded_mat_f16(n: usize) -> CsMat<f16> {
+    let mat = banded_mat_f32(n);
+    let data = mat.data().iter().map(|&x| f16::from_f32(x)).collect();
+    CsMat::new((n, n), mat.indptr().to_vec(), mat.indices().to_vec(), data)
+}
+
+#[cfg(feature = "half")]
+fn csmat_matvec_mixed_f16_f32(bench: &mut Bencher) {
+    let mat = banded_mat_f16(10000);
+    let x = vec![1f32; 10000];
+    let mut y = vec![0.; 10000];
+    bench.iter(|| {
+        mul_acc_mat_vec_mixed(mat.view(), &x, &mut y);
+    });
+}
+
+#[cfg(feature = "half")]
+fn csmat_spgemm_mixed_f16_f32(bench: &mut Bencher) {
+    let mat = banded_mat_f16(2000);
+    bench.iter(|| {
+        let res: CsMat<f32> = csr_mul_csr_mixed(mat.view(), mat.view());
+        res
+    });
+}
+
+#[cfg(feature = "half")]
+fn csmat_spgemm_f32_reference(bench: &mut Bencher) {
+    let mat = banded_mat_f32(2000);
+    bench.iter(|| {
+        &mat * &mat
+    });
+}
+
+benchmark_group!(benches, csvec_neg, csvec_additive_inverse,
+                 csmat_matvec_mixed_f32_f64, csmat_matvec_mixed_reference);
+#[cfg(feature = "half")]
+benchmark_group!(half_benches, csmat_matvec_mixed_f16_f32,
+                 csmat_spgemm_mixed_f16_f32, csmat_spgemm_f32_reference);
+#[cfg(not(feature = "half"))]
 benchmark_main!(benches);
+#[cfg(feature = "half")]
+benchmark_main!(benches, half_benches);
@@ -0,0 +1,38 @@
+///! This example times the sparse kernels of sprs on the matrices of
+///! Matrix Market files given on the command line, to help choosing the
+///! storage and the kernels best suited to them.
+///!
+///! It requires the `bench` feature, and the parallel kernels are timed
+///! with the `rayon` feature as well:
+///!
+///! cargo run --release --features "bench rayon" --example bench_mm -- a.mtx
+
+extern crate sprs;
+
+use std::env;
+use std::process;
+use std::time::Duration;
+
+use sprs::bench::report_file;
+
+fn main() {
+    let paths: Vec<_> = env::args().skip(1).collect();
+    if paths.is_empty() {
+        eprintln!("usage: bench_mm FILE.mtx...");
+        process::exit(1);
+    }
+    for path in &paths {
+        match report_file(path, Duration::from_millis(500)) {
+            Ok(reports) => {
+                println!("{}", path);
+                for report in reports {
+                    println!("    {}", report);
+                }
+            },
+            Err(err) => {
+                eprintln!("{}: {}", path, err);
+                process::exit(1);
+            },
+        }
+    }
+}
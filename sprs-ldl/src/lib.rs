@@ -18,6 +18,11 @@
 ///! to precompute part of the factorization by using the `LdlSymbolic` struct.
 ///! This struct can the be converted into a `LdlNumeric` once the non-zero
 ///! values are known, using the `LdlSymbolic::factor` method.
+///!
+///! The diagonal of the inverse of the factorized matrix, or its entries on
+///! the pattern of the factorization, can be computed without inverting
+///! the matrix with `LdlNumeric::inverse_diagonal` and
+///! `LdlNumeric::selected_inverse`.
 
 // This method is adapted from the LDL library by Tim Davis:
 //
@@ -62,10 +67,12 @@ use std::ops::IndexMut;
 use num_traits::Num;
 
 use sprs::{
+    CsMatI,
     CsMatViewI,
     is_symmetric,
     Permutation,
     PermOwnedI,
+    TriMatI,
 };
 use sprs::indexing::SpIndex;
 use sprs::linalg;
@@ -149,6 +156,21 @@ impl<I: SpIndex> LdlSymbolic<I> {
         }
     }
 
+    /// Compute the symbolic decomposition L D L^T = P A P^T
+    /// where the permutation P is computed by the given ordering method.
+    ///
+    /// # Panics
+    ///
+    /// * if mat is not symmetric
+    pub fn new_reordered<N>(mat: CsMatViewI<N, I>,
+                            method: linalg::ordering::ReorderMethod
+                           ) -> LdlSymbolic<I>
+    where N: Copy + PartialEq,
+    {
+        let perm = method.ordering(&mat);
+        LdlSymbolic::new_perm(mat, perm)
+    }
+
     /// The size of the linear system associated with this decomposition
     #[inline]
     pub fn problem_size(&self) -> usize {
@@ -215,6 +237,20 @@ impl<N, I: SpIndex> LdlNumeric<N, I> {
         symbolic.factor(mat)
     }
 
+    /// Compute the numeric decomposition L D L^T = P A P^T
+    /// where the permutation P is computed by the given ordering method.
+    ///
+    /// # Panics
+    ///
+    /// * if mat is not symmetric
+    pub fn new_reordered(mat: CsMatViewI<N, I>,
+                         method: linalg::ordering::ReorderMethod) -> Self
+    where N: Copy + Num + PartialOrd,
+    {
+        let symbolic = LdlSymbolic::new_reordered(mat.view(), method);
+        symbolic.factor(mat)
+    }
+
     /// Update the decomposition with the given matrix. The matrix must
     /// have the same non-zero pattern as the original matrix, otherwise
     /// the result is unspecified.
@@ -273,6 +309,68 @@ impl<N, I: SpIndex> LdlNumeric<N, I> {
         self.symbolic.nnz()
     }
 
+    /// The entries of the inverse of the factorized matrix on the pattern
+    /// of `L + L^T + I`, mapped back to the ordering of the matrix, in CSC
+    /// storage. They are computed by the Takahashi recurrences, see
+    /// `sprs::linalg::selected_inverse`, without forming the inverse.
+    pub fn selected_inverse(&self) -> CsMatI<N, I>
+    where N: Copy + Num,
+    {
+        let n = self.problem_size();
+        let z = linalg::selected_inverse(self.l_view(), &self.diag);
+        let perm = &self.symbolic.perm;
+        let mut tri = TriMatI::with_capacity((n, n), 2 * z.nnz() - n);
+        for (col, vec) in z.outer_iterator().enumerate() {
+            for (row, &val) in vec.iter() {
+                tri.add_triplet(perm.at(row), perm.at(col), val);
+                if row != col {
+                    tri.add_triplet(perm.at(col), perm.at(row), val);
+                }
+            }
+        }
+        tri.to_csc()
+    }
+
+    /// The diagonal of the inverse of the factorized matrix, computed by
+    /// selected inversion.
+    pub fn inverse_diagonal(&self) -> Vec<N>
+    where N: Copy + Num,
+    {
+        let n = self.problem_size();
+        let z = linalg::selected_inverse(self.l_view(), &self.diag);
+        let mut res = vec![N::zero(); n];
+        for (col, vec) in z.outer_iterator().enumerate() {
+            // the diagonal entry comes first in each column
+            res[self.symbolic.perm.at(col)] = vec.data()[0];
+        }
+        res
+    }
+}
+
+impl<N, I> linalg::LinearSolver<N> for LdlNumeric<N, I>
+where N: Copy + Num,
+      I: SpIndex,
+{
+    fn dim(&self) -> usize {
+        self.problem_size()
+    }
+
+    fn solve(&self, rhs: &[N], x: &mut [N]) {
+        if rhs.len() != self.problem_size() || x.len() != self.problem_size() {
+            panic!("Dimension mismatch");
+        }
+        x.copy_from_slice(&LdlNumeric::solve(self, &rhs));
+    }
+
+    /// The factorized matrix is symmetric, so transposed solves are
+    /// regular solves.
+    fn has_transpose_solve(&self) -> bool {
+        true
+    }
+
+    fn solve_transpose(&self, rhs: &[N], x: &mut [N]) {
+        linalg::LinearSolver::solve(self, rhs, x)
+    }
 }
 
 /// Perform a symbolic LDLt decomposition of a symmetric sparse matrix
@@ -299,6 +397,9 @@ where N: Clone + Copy + PartialEq,
 
     let n = mat.rows();
 
+    // outer_iterator_perm only iterates over P A P^T for CSR matrices,
+    // but a symmetric CSC matrix is equal to its CSR transpose
+    let mat = if mat.is_csc() { mat.transpose_view() } else { mat.view() };
     let outer_it = mat.outer_iterator_perm(perm.view());
     // compute the elimination tree of L
     for (k, (_, vec)) in outer_it.enumerate() {
@@ -348,6 +449,7 @@ where N: Clone + Copy + PartialEq + Num + PartialOrd,
       I: SpIndex,
       PStorage: Deref<Target = [I]>
 {
+    let mat = if mat.is_csc() { mat.transpose_view() } else { mat.view() };
     let outer_it = mat.outer_iterator_perm(perm.view());
     for (k, (_, vec)) in outer_it.enumerate() {
 
@@ -629,4 +731,122 @@ mod test {
         let x = ldlt.solve(&b);
         assert_eq!(x, x0);
     }
+
+    #[test]
+    fn csc_and_csr_ldl_solve() {
+        // the factorization of a symmetric matrix does not depend on its
+        // storage, with or without a permutation
+        let mat = test_mat1();
+        assert!(mat.is_csc());
+        let csr = mat.to_csr();
+        let b = test_vec1();
+        let x0 = expected_res1();
+        let perm = Permutation::new(vec![9, 3, 0, 7, 1, 5, 2, 8, 4, 6]);
+        let ldl_csc = super::LdlNumeric::new_perm(mat.view(), perm.clone());
+        let ldl_csr = super::LdlNumeric::new_perm(csr.view(), perm);
+        assert_eq!(ldl_csc.nnz(), ldl_csr.nnz());
+        let x_csc = ldl_csc.solve(&b);
+        let x_csr = ldl_csr.solve(&b);
+        assert_eq!(x_csc, x_csr);
+        for (xi, x0i) in x_csc.iter().zip(&x0) {
+            assert!((xi - x0i).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn reordered_ldl_solve() {
+        use sprs::TriMat;
+        use sprs::linalg::ordering::ReorderMethod;
+        let side = 10;
+        let n = side * side;
+        let mut tri = TriMat::new((n, n));
+        for i in 0..side {
+            for j in 0..side {
+                let node = i * side + j;
+                tri.add_triplet(node, node, 4.);
+                if i + 1 < side {
+                    tri.add_triplet(node, node + side, -1.);
+                    tri.add_triplet(node + side, node, -1.);
+                }
+                if j + 1 < side {
+                    tri.add_triplet(node, node + 1, -1.);
+                    tri.add_triplet(node + 1, node, -1.);
+                }
+            }
+        }
+        let mat: CsMat<f64> = tri.to_csr();
+        let x0: Vec<f64> = (0..n).map(|i| i as f64).collect();
+        let mut b = vec![0.; n];
+        for (col, vec) in mat.outer_iterator().enumerate() {
+            for (row, &val) in vec.iter() {
+                b[row] += val * x0[col];
+            }
+        }
+        let methods = [ReorderMethod::Natural,
+                       ReorderMethod::Amd,
+                       ReorderMethod::ReverseCuthillMcKee,
+                       ReorderMethod::NestedDissection];
+        for &method in &methods {
+            let ldlt = super::LdlNumeric::new_reordered(mat.view(), method);
+            let x = ldlt.solve(&b);
+            for (xi, x0i) in x.iter().zip(&x0) {
+                assert!((xi - x0i).abs() < 1e-10);
+            }
+        }
+        let nnz = |method| {
+            super::LdlSymbolic::new_reordered(mat.view(), method).nnz()
+        };
+        assert!(nnz(ReorderMethod::Amd) < nnz(ReorderMethod::Natural));
+    }
+
+    #[test]
+    fn selected_inverse() {
+        use sprs::linalg::ordering::ReorderMethod;
+        let mat = test_mat1();
+        let n = 10;
+        let methods = [ReorderMethod::Natural, ReorderMethod::Amd];
+        for &method in &methods {
+            let ldlt = super::LdlNumeric::new_reordered(mat.view(), method);
+            let z = ldlt.selected_inverse();
+            let diag = ldlt.inverse_diagonal();
+            for col in 0..n {
+                let mut e = vec![0.; n];
+                e[col] = 1.;
+                let inv_col = ldlt.solve(&e);
+                assert!((diag[col] - inv_col[col]).abs() < 1e-12);
+                let z_col = z.outer_view(col).unwrap();
+                assert!(z_col.get(col).is_some());
+                for (row, &val) in z_col.iter() {
+                    assert!((val - inv_col[row]).abs() < 1e-12);
+                }
+                // the pattern of A is included in the selected entries
+                for (row, _) in mat.outer_view(col).unwrap().iter() {
+                    assert!(z_col.get(row).is_some());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bordered_solve_with_ldl() {
+        use sprs::linalg::bordered::BorderedSolver;
+        let mat = test_mat1();
+        let ldlt = super::LdlNumeric::new(mat.view());
+        let mut col = vec![0.; 10];
+        col[3] = 1.;
+        let mut row = vec![0.; 10];
+        row[0] = 1.;
+        let bordered = BorderedSolver::new(&mat, &ldlt, col, row, 1.).unwrap();
+        // with a zero border component, x solves the original system
+        let x0 = expected_res1();
+        let mut f = test_vec1();
+        f[3] += 1.;
+        let g = x0[0] + 1.;
+        let mut x = vec![0.; 10];
+        let y = bordered.solve_split(&f, g, &mut x);
+        assert!((y - 1.).abs() < 1e-12);
+        for (xi, x0i) in x.iter().zip(&x0) {
+            assert!((xi - x0i).abs() < 1e-12);
+        }
+    }
 }
@@ -0,0 +1,278 @@
+///! Microbenchmarks of the sparse kernels on user matrices
+///!
+///! This module is available with the `bench` feature. The best storage
+///! and kernel for an application depend on the structure of its matrices,
+///! so this module times the matrix-vector and matrix-matrix products on a
+///! given matrix, in CSR and CSC storage, with the traversals of
+///! `prod::MatVecPolicy` and, with the `rayon` feature, in parallel, and
+///! reports their floating point throughput and memory bandwidth.
+///!
+///! The reports are estimated by `report_kernels` from a simple timing
+///! loop, which is quick and enough to compare the variants. For more
+///! accurate measurements, `criterion_benchmarks` registers the same
+///! kernels with `criterion`, reporting their bandwidth.
+///!
+///! The traffic is estimated as the minimal number of bytes the kernels
+///! have to move: the arrays of the matrix are read once, as well as the
+///! dense input vector, and the output vector is read and written. Caches
+///! can make the actual bandwidth lower for matrices of irregular structure.
+///!
+///! The `bench_mm` example runs these benchmarks on Matrix Market files:
+///!
+///! ```text
+///! cargo run --release --features "bench rayon" --example bench_mm -- a.mtx
+///! ```
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::mem;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use criterion::{Benchmark, Criterion, Throughput};
+
+use indexing::SpIndex;
+use io::read_matrix_market;
+use sparse::{CsMat, CsMatI, CsMatViewI};
+use sparse::prod::{self, MatVecPolicy};
+
+/// The timing of a kernel, with its amount of work
+#[derive(Debug, Clone, PartialEq)]
+pub struct KernelReport {
+    /// The name of the kernel, eg `"matvec csr"`
+    pub kernel: String,
+    /// The average duration of a run of the kernel, in seconds
+    pub seconds: f64,
+    /// The number of floating point operations of a run
+    pub flops: f64,
+    /// The estimated number of bytes moved by a run
+    pub bytes: f64,
+}
+
+impl KernelReport {
+    /// The floating point throughput, in GFLOP/s
+    pub fn gflops(&self) -> f64 {
+        self.flops / self.seconds * 1e-9
+    }
+
+    /// The estimated memory bandwidth, in GB/s
+    pub fn bandwidth(&self) -> f64 {
+        self.bytes / self.seconds * 1e-9
+    }
+}
+
+impl fmt::Display for KernelReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:<16} {:>12.3e} s {:>9.3} GFLOP/s {:>9.3} GB/s",
+               self.kernel, self.seconds, self.gflops(), self.bandwidth())
+    }
+}
+
+/// The number of floating point operations of a matrix-vector product
+pub fn matvec_flops<N, I>(mat: CsMatViewI<N, I>) -> f64
+where I: SpIndex
+{
+    2. * mat.nnz() as f64
+}
+
+/// The minimal number of bytes moved by a matrix-vector product
+pub fn matvec_bytes<N, I>(mat: CsMatViewI<N, I>) -> f64
+where I: SpIndex
+{
+    let index_size = mem::size_of::<I>();
+    let scalar_size = mem::size_of::<N>();
+    let matrix = mat.nnz() * (index_size + scalar_size)
+                 + (mat.outer_dims() + 1) * index_size;
+    let vectors = (mat.cols() + 2 * mat.rows()) * scalar_size;
+    (matrix + vectors) as f64
+}
+
+/// The number of floating point operations of the product `lhs * rhs`,
+/// counting the multiplications and additions of the Gustavson algorithm.
+///
+/// # Panics
+///
+/// On dimension mismatch.
+pub fn spgemm_flops<N, I>(lhs: CsMatViewI<N, I>, rhs: CsMatViewI<N, I>) -> f64
+where I: SpIndex
+{
+    if lhs.cols() != rhs.rows() {
+        panic!("Dimension mismatch");
+    }
+    // every pair of non-zeros lhs[i, k], rhs[k, j] is used once
+    let (inner_dim, lhs_t) = (lhs.cols(), lhs.transpose_view());
+    let lhs_col_nnz = inner_nnz(lhs_t, inner_dim);
+    let rhs_row_nnz = inner_nnz(rhs.view(), inner_dim);
+    let products: usize = lhs_col_nnz.iter()
+                                     .zip(&rhs_row_nnz)
+                                     .map(|(l, r)| l * r)
+                                     .sum();
+    2. * products as f64
+}
+
+/// The number of non-zeros of each of the `dim` rows of `mat`
+fn inner_nnz<N, I>(mat: CsMatViewI<N, I>, dim: usize) -> Vec<usize>
+where I: SpIndex
+{
+    let mut nnz = vec![0; dim];
+    for (outer, vec) in mat.outer_iterator().enumerate() {
+        if mat.is_csr() {
+            nnz[outer] += vec.nnz();
+        } else {
+            for &inner in vec.indices() {
+                nnz[inner.index()] += 1;
+            }
+        }
+    }
+    nnz
+}
+
+/// Time `kernel`, running it repeatedly for at least `min_time`, and at
+/// least once.
+pub fn time_kernel<F: FnMut()>(min_time: Duration, mut kernel: F) -> f64 {
+    let start = Instant::now();
+    let mut runs = 0u32;
+    loop {
+        kernel();
+        runs += 1;
+        let elapsed = start.elapsed();
+        if elapsed >= min_time {
+            let seconds = elapsed.as_secs() as f64
+                          + elapsed.subsec_nanos() as f64 * 1e-9;
+            return seconds / runs as f64;
+        }
+    }
+}
+
+/// Time the matrix-vector products of `mat` in both storages, with the
+/// prefetching and blocked traversals, and in parallel with the `rayon`
+/// feature, and the product of `mat` by its transpose in both storages,
+/// running each kernel for at least `min_time`.
+pub fn report_kernels(mat: &CsMat<f64>,
+                      min_time: Duration) -> Vec<KernelReport> {
+    let csr = mat.to_csr();
+    let csc = mat.to_csc();
+    let x = vec![1.; mat.cols()];
+    let mut y = vec![0.; mat.rows()];
+    let mut reports = Vec::new();
+    {
+        let flops = matvec_flops(csr.view());
+        let bytes = matvec_bytes(csr.view());
+        let mut report = |kernel: &str, seconds| {
+            reports.push(KernelReport {
+                kernel: kernel.to_string(),
+                seconds: seconds,
+                flops: flops,
+                bytes: bytes,
+            });
+        };
+        report("matvec csr", time_kernel(min_time, || {
+            prod::mul_acc_mat_vec_csr(csr.view(), &x, &mut y)
+        }));
+        report("matvec csc", time_kernel(min_time, || {
+            prod::mul_acc_mat_vec_csc(csc.view(), &x, &mut y)
+        }));
+        report("matvec prefetch", time_kernel(min_time, || {
+            let policy = MatVecPolicy::Prefetch { distance: 64 };
+            prod::mul_acc_mat_vec_csr_policy(csr.view(), &x, &mut y, policy)
+        }));
+        report("matvec blocked", time_kernel(min_time, || {
+            let policy = MatVecPolicy::for_matrix(csr.view());
+            prod::mul_acc_mat_vec_csr_policy(csr.view(), &x, &mut y, policy)
+        }));
+        #[cfg(feature = "rayon")]
+        report("matvec csr par", time_kernel(min_time, || {
+            prod::par_mul_acc_mat_vec_csr(csr.view(), &x, &mut y)
+        }));
+    }
+
+    let csr_t = csc.transpose_view();
+    let csc_t = csr.transpose_view();
+    let flops = spgemm_flops(csr.view(), csr_t.view());
+    for &(kernel, lhs, rhs) in &[("spgemm csr", &csr, &csr_t),
+                                 ("spgemm csc", &csc, &csc_t)] {
+        let mut nnz = 0;
+        let seconds = time_kernel(min_time, || nnz = (lhs * rhs).nnz());
+        reports.push(spgemm_report(kernel, seconds, flops, nnz, lhs));
+    }
+    reports
+}
+
+/// The report of a product, whose traffic is estimated as reading its
+/// operands, of the same size here, and writing its result
+fn spgemm_report<I: SpIndex>(kernel: &str,
+                             seconds: f64,
+                             flops: f64,
+                             res_nnz: usize,
+                             lhs: &CsMatI<f64, I>) -> KernelReport {
+    let entry_size = mem::size_of::<I>() + mem::size_of::<f64>();
+    let bytes = (2 * lhs.nnz() + res_nnz) * entry_size;
+    KernelReport {
+        kernel: kernel.to_string(),
+        seconds: seconds,
+        flops: flops,
+        bytes: bytes as f64,
+    }
+}
+
+/// Read the Matrix Market file at `path`, and return the reports of its
+/// kernels, see `report_kernels`.
+pub fn report_file<P: AsRef<Path>>(path: P,
+                                   min_time: Duration)
+                                   -> io::Result<Vec<KernelReport>> {
+    let reader = BufReader::new(try!(File::open(path)));
+    let mat = try!(read_matrix_market(reader));
+    Ok(report_kernels(&mat, min_time))
+}
+
+/// Register the matrix-vector products of `mat` with `criterion`, in the
+/// group `name`, reporting their bandwidth.
+pub fn criterion_benchmarks(c: &mut Criterion, name: &str, mat: &CsMat<f64>) {
+    let csr = mat.to_csr();
+    let csc = mat.to_csc();
+    let bytes = matvec_bytes(csr.view()).min(u32::max_value() as f64);
+    let (rows, cols) = mat.shape();
+    let benchmark = Benchmark::new("matvec csr", move |b| {
+        let x = vec![1.; cols];
+        let mut y = vec![0.; rows];
+        b.iter(|| prod::mul_acc_mat_vec_csr(csr.view(), &x, &mut y))
+    });
+    let benchmark = benchmark.with_function("matvec csc", move |b| {
+        let x = vec![1.; cols];
+        let mut y = vec![0.; rows];
+        b.iter(|| prod::mul_acc_mat_vec_csc(csc.view(), &x, &mut y))
+    });
+    #[cfg(feature = "rayon")]
+    let benchmark = {
+        let csr = mat.to_csr();
+        benchmark.with_function("matvec csr par", move |b| {
+            let x = vec![1.; cols];
+            let mut y = vec![0.; rows];
+            b.iter(|| prod::par_mul_acc_mat_vec_csr(csr.view(), &x, &mut y))
+        })
+    };
+    c.bench(name, benchmark.throughput(Throughput::Bytes(bytes as u32)));
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use test_data::{mat1, mat1_csc};
+    use super::{matvec_bytes, matvec_flops, spgemm_flops, report_kernels};
+
+    #[test]
+    fn kernel_work() {
+        let mat = mat1();
+        assert_eq!(matvec_flops(mat.view()), 14.);
+        // 7 entries, 6 indptr elements, 5 + 2 * 5 scalars of vectors
+        assert_eq!(matvec_bytes(mat.view()), (7 * 16 + 6 * 8 + 15 * 8) as f64);
+        // the product of mat1 by itself has 8 scalar products
+        assert_eq!(spgemm_flops(mat.view(), mat.view()), 16.);
+        assert_eq!(spgemm_flops(mat.view(), mat1_csc().view()), 16.);
+
+        let reports = report_kernels(&mat, Duration::from_millis(1));
+        assert!(reports.iter().any(|r| r.kernel == "spgemm csc"));
+        assert!(reports.iter().all(|r| r.seconds > 0. && r.gflops() > 0.));
+    }
+}
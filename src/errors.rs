@@ -1,35 +1,176 @@
-//! Error type for sprs
+//! Error types for sprs
+//!
+//! The error enums are marked `#[non_exhaustive]`: new variants may be
+//! added without a breaking release, so matches on them from outside sprs
+//! need a wildcard arm.
 
+#[cfg(feature = "std")]
 use std::error::Error;
 use std::fmt;
 
-#[derive(PartialEq, Debug)]
-pub enum SprsError {
+use ::Shape;
+
+/// The ways a compressed structure can be invalid
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[non_exhaustive]
+pub enum StructureError {
+    /// The indices of an outer dimension or of a vector are not sorted
     NonSortedIndices,
+    /// The indptr array is not sorted
     UnsortedIndptr,
+    /// The length of indptr does not match the outer dimension
+    BadIndptrLength,
+    /// The lengths of indices and data disagree with indptr
+    BadNnzCount,
+    /// An indptr value exceeds the number of non-zeros
+    OutOfBoundsIndptr,
+    /// An index exceeds the inner dimension
+    OutOfBoundsIndex,
+    /// An entry is not part of the sparsity pattern
+    MissingEntry,
+    /// An index is repeated in a permutation
+    NotAPermutation,
+}
+
+impl StructureError {
+    fn descr(&self) -> &str {
+        use self::StructureError::*;
+        match *self {
+            NonSortedIndices => "a vector's indices are not sorted",
+            UnsortedIndptr => "indptr is not sorted",
+            BadIndptrLength => "indptr length does not match the dimension",
+            BadNnzCount => "indices, data and indptr disagree on the nnz",
+            OutOfBoundsIndptr => "an indptr value is out of bounds",
+            OutOfBoundsIndex => "an index is out of bounds",
+            MissingEntry => "an entry is missing from the sparsity pattern",
+            NotAPermutation => "an index is repeated in a permutation",
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for StructureError {
+    fn description(&self) -> &str {
+        self.descr()
+    }
+}
+
+impl fmt::Display for StructureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.descr().fmt(f)
+    }
+}
+
+/// The errors returned by sprs
+#[derive(PartialEq, Debug, Clone)]
+#[non_exhaustive]
+pub enum SprsError {
+    /// The compressed structure of a matrix or vector is invalid. The
+    /// `StructureError` is also available as the `source` of this error.
+    IllegalStructure(StructureError),
+    /// The shapes of the operands of `op` are incompatible: an operand of
+    /// shape `expected` was required, but one of shape `found` was given
+    DimensionMismatch {
+        op: &'static str,
+        expected: Shape,
+        found: Shape,
+    },
+    /// `op` was given an empty list of operands, or a block row or column
+    /// without any matrix
+    EmptyInput {
+        op: &'static str,
+    },
+    /// An integer operation of `op` overflowed
+    Overflow {
+        op: &'static str,
+    },
+    /// A factorization or a solve met a singular matrix
     SingularMatrix,
+    /// A Cholesky factorization met a matrix which is not positive definite
+    NotPositiveDefinite,
+    /// An iterative method did not reach its tolerance
+    NotConverged,
+    /// A shortest path computation met a negative cycle
+    NegativeCycle,
 }
 
-use self::SprsError::*;
+impl From<StructureError> for SprsError {
+    fn from(err: StructureError) -> SprsError {
+        SprsError::IllegalStructure(err)
+    }
+}
 
 impl SprsError {
     fn descr(&self) -> &str {
         match *self {
-            NonSortedIndices => "a vector's indices are not sorted",
-            UnsortedIndptr => "indptr is not sorted",
-            SingularMatrix => "matrix is singular",
+            SprsError::IllegalStructure(_) => "illegal compressed structure",
+            SprsError::DimensionMismatch { .. } => "dimension mismatch",
+            SprsError::EmptyInput { .. } => "empty input",
+            SprsError::Overflow { .. } => "integer overflow",
+            SprsError::SingularMatrix => "matrix is singular",
+            SprsError::NotPositiveDefinite => "matrix is not positive definite",
+            SprsError::NotConverged => "iterative method did not converge",
+            SprsError::NegativeCycle => "graph contains a negative cycle",
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl Error for SprsError {
     fn description(&self) -> &str {
         self.descr()
     }
+
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            SprsError::IllegalStructure(ref err) => Some(err),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for SprsError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.descr().fmt(f)
+        match *self {
+            SprsError::IllegalStructure(ref err) => {
+                write!(f, "illegal compressed structure: {}", err)
+            },
+            SprsError::DimensionMismatch { op, expected, found } => {
+                write!(f, "dimension mismatch in {}: expected {}x{}, found \
+                           {}x{}",
+                       op, expected.0, expected.1, found.0, found.1)
+            },
+            SprsError::EmptyInput { op } => write!(f, "empty input in {}", op),
+            SprsError::Overflow { op } => {
+                write!(f, "integer overflow in {}", op)
+            },
+            _ => self.descr().fmt(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::error::Error;
+    use super::{SprsError, StructureError};
+
+    #[test]
+    fn error_context() {
+        let err: SprsError = StructureError::UnsortedIndptr.into();
+        assert_eq!(err.to_string(),
+                   "illegal compressed structure: indptr is not sorted");
+        let source = err.source().unwrap();
+        assert_eq!(source.to_string(), "indptr is not sorted");
+
+        let err = SprsError::DimensionMismatch {
+            op: "mul",
+            expected: (3, 1),
+            found: (4, 1),
+        };
+        assert_eq!(err.to_string(),
+                   "dimension mismatch in mul: expected 3x1, found 4x1");
+        assert!(err.source().is_none());
+        assert_eq!(SprsError::SingularMatrix.to_string(),
+                   "matrix is singular");
     }
 }
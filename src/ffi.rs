@@ -0,0 +1,329 @@
+///! C-compatible interface to CSR matrices
+///!
+///! This module is available when the `ffi` feature is enabled. It exposes
+///! `extern "C"` functions operating on CSR matrices of `f64` values with
+///! `size_t` indices, so that sprs can be called from C, C++ or Fortran,
+///! for instance by building this crate as a `cdylib` or `staticlib`.
+///!
+///! Matrices are described by `SprsCsrDescriptor`, a `#[repr(C)]` structure
+///! pointing to the `indptr`, `indices` and `data` buffers of a CSR matrix.
+///! A descriptor can either borrow buffers owned by the caller, which are
+///! then used without copy after a validation of their structure, or
+///! describe a matrix owned by sprs and accessed through an opaque
+///! `SprsCsr` handle. In the other direction, `SprsCsrDescriptor::from_view`
+///! describes a sprs matrix so that it can be passed to a foreign solver.
+///!
+///! All functions returning a status return `SPRS_OK` on success. Invalid
+///! inputs, including null pointers and malformed CSR structures, are
+///! reported through the other status codes, no panic crosses the FFI
+///! boundary.
+///!
+///! # Example
+///!
+///! ```c
+///! size_t indptr[] = {0, 1, 2};
+///! size_t indices[] = {0, 1};
+///! double data[] = {2., 3.};
+///! SprsCsrDescriptor desc = {2, 2, indptr, indices, data};
+///! double x[] = {1., 1.};
+///! double y[2];
+///! int status = sprs_csr_desc_matvec(&desc, x, y);
+///! ```
+
+use std::os::raw::c_int;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+use sparse::{CsMat, CsMatView};
+use sparse::CompressedStorage::CSR;
+
+/// Success
+pub const SPRS_OK: c_int = 0;
+/// A required pointer was null
+pub const SPRS_ERR_NULL_POINTER: c_int = 1;
+/// The described buffers do not form a valid CSR matrix
+pub const SPRS_ERR_INVALID_STRUCTURE: c_int = 2;
+/// The dimensions of the operands do not match
+pub const SPRS_ERR_DIMENSION_MISMATCH: c_int = 3;
+
+/// Description of a CSR matrix of `f64` values through its raw buffers.
+///
+/// `indptr` has `rows + 1` elements, and `indices` and `data` have
+/// `indptr[rows]` elements.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SprsCsrDescriptor {
+    pub rows: usize,
+    pub cols: usize,
+    pub indptr: *const usize,
+    pub indices: *const usize,
+    pub data: *const f64,
+}
+
+/// Opaque handle to a CSR matrix owned by sprs
+pub struct SprsCsr(CsMat<f64>);
+
+impl SprsCsrDescriptor {
+    /// Describe a CSR matrix, eg to pass it to a foreign function. The
+    /// descriptor borrows the buffers of `mat` and must not outlive it.
+    ///
+    /// # Panics
+    ///
+    /// If the matrix is not in CSR storage.
+    pub fn from_view(mat: CsMatView<f64>) -> SprsCsrDescriptor {
+        assert!(mat.is_csr(), "Storage mismatch");
+        SprsCsrDescriptor {
+            rows: mat.rows(),
+            cols: mat.cols(),
+            indptr: mat.indptr().as_ptr(),
+            indices: mat.indices().as_ptr(),
+            data: mat.data().as_ptr(),
+        }
+    }
+
+    /// Borrow the described matrix as a view, after checking its
+    /// structure. Returns the status code describing the failure otherwise.
+    ///
+    /// # Safety
+    ///
+    /// The buffers must be valid for the lengths given by `rows` and
+    /// `indptr[rows]` and must outlive the view and not be mutated while
+    /// it is alive.
+    pub unsafe fn as_view<'a>(&self) -> Result<CsMatView<'a, f64>, c_int> {
+        if self.indptr.is_null() {
+            return Err(SPRS_ERR_NULL_POINTER);
+        }
+        let indptr_len = match self.rows.checked_add(1) {
+            Some(len) => len,
+            None => return Err(SPRS_ERR_INVALID_STRUCTURE),
+        };
+        let indptr = slice::from_raw_parts(self.indptr, indptr_len);
+        let nnz = indptr[self.rows];
+        if nnz > 0 && (self.indices.is_null() || self.data.is_null()) {
+            return Err(SPRS_ERR_NULL_POINTER);
+        }
+        let (indices, data) = if nnz == 0 {
+            (&[][..], &[][..])
+        }
+        else {
+            (slice::from_raw_parts(self.indices, nnz),
+             slice::from_raw_parts(self.data, nnz))
+        };
+        let shape = (self.rows, self.cols);
+        // always fully checked, foreign data cannot be trusted
+        match CsMatView::from_raw_parts(CSR, shape, indptr, indices, data) {
+            Ok(view) if indptr[0] == 0 => Ok(view),
+            _ => Err(SPRS_ERR_INVALID_STRUCTURE),
+        }
+    }
+}
+
+/// Run `f`, converting a panic into `SPRS_ERR_INVALID_STRUCTURE`
+fn guard<F: FnOnce() -> c_int>(f: F) -> c_int {
+    panic::catch_unwind(AssertUnwindSafe(f))
+        .unwrap_or(SPRS_ERR_INVALID_STRUCTURE)
+}
+
+/// Compute `y = A x`, where `x` has `cols` elements and `y` has `rows`
+/// elements
+fn matvec(mat: CsMatView<f64>, x: *const f64, y: *mut f64) -> c_int {
+    if (x.is_null() && mat.cols() > 0) || (y.is_null() && mat.rows() > 0) {
+        return SPRS_ERR_NULL_POINTER;
+    }
+    let (x, y) = unsafe {
+        (if mat.cols() > 0 { slice::from_raw_parts(x, mat.cols()) }
+         else { &[][..] },
+         if mat.rows() > 0 { slice::from_raw_parts_mut(y, mat.rows()) }
+         else { &mut [][..] })
+    };
+    for (yi, row) in y.iter_mut().zip(mat.outer_iterator()) {
+        *yi = row.iter().map(|(col, &val)| val * x[col]).sum();
+    }
+    SPRS_OK
+}
+
+/// Check the structure of the CSR matrix described by `desc`, without
+/// copying it.
+#[no_mangle]
+pub unsafe extern "C" fn sprs_csr_desc_check(desc: *const SprsCsrDescriptor)
+                                             -> c_int {
+    if desc.is_null() {
+        return SPRS_ERR_NULL_POINTER;
+    }
+    guard(|| match (*desc).as_view() {
+        Ok(_) => SPRS_OK,
+        Err(status) => status,
+    })
+}
+
+/// Compute `y = A x` for the matrix described by `desc`, borrowing its
+/// buffers without copy.
+#[no_mangle]
+pub unsafe extern "C" fn sprs_csr_desc_matvec(desc: *const SprsCsrDescriptor,
+                                              x: *const f64,
+                                              y: *mut f64) -> c_int {
+    if desc.is_null() {
+        return SPRS_ERR_NULL_POINTER;
+    }
+    guard(|| match (*desc).as_view() {
+        Ok(mat) => matvec(mat, x, y),
+        Err(status) => status,
+    })
+}
+
+/// Create a matrix owned by sprs by copying the matrix described by `desc`.
+/// On success, `*out` holds a handle to free with `sprs_csr_free`.
+#[no_mangle]
+pub unsafe extern "C" fn sprs_csr_new(desc: *const SprsCsrDescriptor,
+                                      out: *mut *mut SprsCsr) -> c_int {
+    if desc.is_null() || out.is_null() {
+        return SPRS_ERR_NULL_POINTER;
+    }
+    guard(|| match (*desc).as_view() {
+        Ok(mat) => {
+            *out = Box::into_raw(Box::new(SprsCsr(mat.to_owned())));
+            SPRS_OK
+        }
+        Err(status) => status,
+    })
+}
+
+/// Free a matrix created by sprs. Passing a null pointer is a no-op.
+#[no_mangle]
+pub unsafe extern "C" fn sprs_csr_free(mat: *mut SprsCsr) {
+    if !mat.is_null() {
+        drop(Box::from_raw(mat));
+    }
+}
+
+/// Describe a matrix owned by sprs, giving access to its buffers without
+/// copy. The descriptor is valid until the matrix is freed.
+#[no_mangle]
+pub unsafe extern "C" fn sprs_csr_describe(mat: *const SprsCsr,
+                                           desc: *mut SprsCsrDescriptor)
+                                           -> c_int {
+    if mat.is_null() || desc.is_null() {
+        return SPRS_ERR_NULL_POINTER;
+    }
+    *desc = SprsCsrDescriptor::from_view((*mat).0.view());
+    SPRS_OK
+}
+
+/// Compute `y = A x` for a matrix owned by sprs
+#[no_mangle]
+pub unsafe extern "C" fn sprs_csr_matvec(mat: *const SprsCsr,
+                                         x: *const f64,
+                                         y: *mut f64) -> c_int {
+    if mat.is_null() {
+        return SPRS_ERR_NULL_POINTER;
+    }
+    guard(|| matvec((*mat).0.view(), x, y))
+}
+
+/// Compute the product of two matrices owned by sprs. On success, `*out`
+/// holds a handle to free with `sprs_csr_free`.
+#[no_mangle]
+pub unsafe extern "C" fn sprs_csr_mul(lhs: *const SprsCsr,
+                                      rhs: *const SprsCsr,
+                                      out: *mut *mut SprsCsr) -> c_int {
+    if lhs.is_null() || rhs.is_null() || out.is_null() {
+        return SPRS_ERR_NULL_POINTER;
+    }
+    let (lhs, rhs) = (&(*lhs).0, &(*rhs).0);
+    if lhs.cols() != rhs.rows() {
+        return SPRS_ERR_DIMENSION_MISMATCH;
+    }
+    *out = ptr::null_mut();
+    guard(|| {
+        *out = Box::into_raw(Box::new(SprsCsr(lhs * rhs)));
+        SPRS_OK
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::ptr;
+    use test_data::{mat1, mat2, mat1_matprod_mat2};
+    use super::*;
+
+    #[test]
+    fn borrowed_descriptor() {
+        let mat = mat1();
+        let desc = SprsCsrDescriptor::from_view(mat.view());
+        let x = [1.; 5];
+        let mut y = [0.; 5];
+        unsafe {
+            assert_eq!(sprs_csr_desc_check(&desc), SPRS_OK);
+            assert_eq!(sprs_csr_desc_matvec(&desc, x.as_ptr(),
+                                            y.as_mut_ptr()),
+                       SPRS_OK);
+            assert_eq!(desc.as_view().unwrap(), mat.view());
+        }
+        assert_eq!(y, [7., 7., 5., 8., 7.]);
+    }
+
+    #[test]
+    fn invalid_descriptors() {
+        let indptr = [0, 2, 1];
+        let indices = [0, 1];
+        let data = [1., 2.];
+        let mut desc = SprsCsrDescriptor {
+            rows: 2,
+            cols: 2,
+            indptr: indptr.as_ptr(),
+            indices: indices.as_ptr(),
+            data: data.as_ptr(),
+        };
+        unsafe {
+            assert_eq!(sprs_csr_desc_check(&desc),
+                       SPRS_ERR_INVALID_STRUCTURE);
+            let indptr = [0, 1, 2];
+            let indices = [0, 2];
+            desc.indptr = indptr.as_ptr();
+            desc.indices = indices.as_ptr();
+            assert_eq!(sprs_csr_desc_check(&desc),
+                       SPRS_ERR_INVALID_STRUCTURE);
+            desc.indices = ptr::null();
+            assert_eq!(sprs_csr_desc_check(&desc), SPRS_ERR_NULL_POINTER);
+            // the length of indptr would overflow
+            desc.indices = indices.as_ptr();
+            desc.rows = usize::max_value();
+            assert_eq!(sprs_csr_desc_check(&desc),
+                       SPRS_ERR_INVALID_STRUCTURE);
+            let mut handle = ptr::null_mut();
+            assert_eq!(sprs_csr_new(&desc, &mut handle),
+                       SPRS_ERR_INVALID_STRUCTURE);
+            assert!(handle.is_null());
+            assert_eq!(sprs_csr_desc_check(ptr::null()),
+                       SPRS_ERR_NULL_POINTER);
+        }
+    }
+
+    #[test]
+    fn owned_matrices() {
+        let (a, b) = (mat1(), mat2());
+        unsafe {
+            let mut handle_a = ptr::null_mut();
+            let mut handle_b = ptr::null_mut();
+            let desc = SprsCsrDescriptor::from_view(a.view());
+            assert_eq!(sprs_csr_new(&desc, &mut handle_a), SPRS_OK);
+            let desc = SprsCsrDescriptor::from_view(b.view());
+            assert_eq!(sprs_csr_new(&desc, &mut handle_b), SPRS_OK);
+            let mut prod = ptr::null_mut();
+            assert_eq!(sprs_csr_mul(handle_a, handle_b, &mut prod), SPRS_OK);
+            let mut desc = SprsCsrDescriptor::from_view(a.view());
+            assert_eq!(sprs_csr_describe(prod, &mut desc), SPRS_OK);
+            assert_eq!(desc.as_view().unwrap(), mat1_matprod_mat2().view());
+            let mut y = [0.; 5];
+            assert_eq!(sprs_csr_matvec(handle_a, [1.; 5].as_ptr(),
+                                       y.as_mut_ptr()),
+                       SPRS_OK);
+            assert_eq!(y, [7., 7., 5., 8., 7.]);
+            sprs_csr_free(prod);
+            sprs_csr_free(handle_b);
+            sprs_csr_free(handle_a);
+            sprs_csr_free(ptr::null_mut());
+        }
+    }
+}
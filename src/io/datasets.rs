@@ -0,0 +1,182 @@
+///! Named matrices of the SuiteSparse matrix collection
+///!
+///! Benchmarks and examples are most meaningful on matrices coming from
+///! real applications, and reproducible when everyone uses the same ones.
+///! This module loads the matrices of the
+///! [SuiteSparse collection](https://sparse.tamu.edu) by their name, which
+///! is made of their group and of their name in the group, eg
+///! `"HB/bcsstk01"`.
+///!
+///! The matrices are looked for in a cache directory, given by the
+///! `SPRS_DATASETS_DIR` environment variable and defaulting to a `sprs`
+///! directory in the temporary directory of the system, where the file of
+///! `"HB/bcsstk01"` is `HB/bcsstk01.mtx`. The first load of a matrix
+///! converts it to the native binary format of sprs, which is much faster
+///! to read than the Matrix Market text format, and stored next to it as
+///! `HB/bcsstk01.sprs`.
+///!
+///! With the `datasets` feature, missing matrices are downloaded from the
+///! collection into the cache. This relies on the `curl` and `tar` programs
+///! being installed, which avoids pulling an HTTP client and decompression
+///! libraries in the dependencies. Without it, the Matrix Market files
+///! have to be placed in the cache by hand.
+///!
+///! # Example
+///!
+///! ```rust,no_run
+///! use sprs::io::datasets;
+///! let mat = datasets::load("HB/bcsstk01").unwrap();
+///! assert_eq!(mat.shape(), (48, 48));
+///! ```
+
+use std::env;
+use std::fs::File;
+#[cfg(feature = "datasets")]
+use std::fs;
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+#[cfg(feature = "datasets")]
+use std::process::Command;
+
+use sparse::CsMat;
+use super::{read_binary, write_binary, read_matrix_market};
+
+/// The root URL of the Matrix Market files of the SuiteSparse collection
+pub const SUITESPARSE_MM_URL: &'static str = "https://sparse.tamu.edu/MM";
+
+/// The cache directory of the datasets, see the module documentation
+pub fn cache_dir() -> PathBuf {
+    match env::var_os("SPRS_DATASETS_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => env::temp_dir().join("sprs"),
+    }
+}
+
+/// Load the matrix of the SuiteSparse collection named `name`, eg
+/// `"HB/bcsstk01"`, as a CSR matrix, from the default cache directory.
+///
+/// # Errors
+///
+/// An error of kind `InvalidInput` is returned if the name is not of the
+/// form `group/matrix`, and of kind `NotFound` if the matrix is not in the
+/// cache and the `datasets` feature is not enabled. The errors of the
+/// download, and of the reading of the files, are propagated.
+pub fn load(name: &str) -> io::Result<CsMat<f64>> {
+    load_from(cache_dir(), name)
+}
+
+/// Load the matrix of the SuiteSparse collection named `name` from the
+/// cache directory `cache`, see `load`.
+pub fn load_from<P: AsRef<Path>>(cache: P,
+                                 name: &str) -> io::Result<CsMat<f64>> {
+    let (group, matrix) = try!(split_name(name));
+    let group_dir = cache.as_ref().join(group);
+    let binary_path = group_dir.join(format!("{}.sprs", matrix));
+    if binary_path.is_file() {
+        let mut reader = BufReader::new(try!(File::open(&binary_path)));
+        return read_binary(&mut reader);
+    }
+    let mm_path = group_dir.join(format!("{}.mtx", matrix));
+    if !mm_path.is_file() {
+        try!(download(&group_dir, group, matrix));
+    }
+    let reader = BufReader::new(try!(File::open(&mm_path)));
+    let mat = try!(read_matrix_market(reader));
+    let mut writer = BufWriter::new(try!(File::create(&binary_path)));
+    try!(write_binary(&mut writer, mat.view()));
+    Ok(mat)
+}
+
+/// Split a dataset name into its group and matrix names, which must not
+/// be able to escape the cache directory.
+fn split_name(name: &str) -> io::Result<(&str, &str)> {
+    let is_valid = |part: &str| {
+        !part.is_empty() && !part.starts_with('.')
+        && part.chars().all(|c| c.is_ascii_alphanumeric()
+                                || c == '_' || c == '-' || c == '.')
+    };
+    let mut parts = name.split('/');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(group), Some(matrix), None)
+            if is_valid(group) && is_valid(matrix) => Ok((group, matrix)),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                "dataset names are of the form group/matrix")),
+    }
+}
+
+/// Download a matrix of the collection, and extract its Matrix Market file
+/// into `group_dir`.
+#[cfg(feature = "datasets")]
+fn download(group_dir: &Path, group: &str, matrix: &str) -> io::Result<()> {
+    try!(fs::create_dir_all(group_dir));
+    let url = format!("{}/{}/{}.tar.gz", SUITESPARSE_MM_URL, group, matrix);
+    let archive = group_dir.join(format!("{}.tar.gz", matrix));
+    try!(run(Command::new("curl").arg("-fsSL").arg("-o").arg(&archive)
+                                 .arg(&url)));
+    // the archive contains the directory matrix/ with matrix.mtx, and
+    // possibly other files such as right hand sides
+    let extracted = run(Command::new("tar").arg("-xzf").arg(&archive)
+                                           .arg("-C").arg(group_dir));
+    try!(fs::remove_file(&archive));
+    try!(extracted);
+    let matrix_dir = group_dir.join(matrix);
+    try!(fs::rename(matrix_dir.join(format!("{}.mtx", matrix)),
+                    group_dir.join(format!("{}.mtx", matrix))));
+    fs::remove_dir_all(matrix_dir)
+}
+
+#[cfg(feature = "datasets")]
+fn run(command: &mut Command) -> io::Result<()> {
+    let status = try!(command.status());
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::new(io::ErrorKind::Other,
+                           format!("{:?} failed with {}", command, status)))
+    }
+}
+
+#[cfg(not(feature = "datasets"))]
+fn download(group_dir: &Path, _: &str, matrix: &str) -> io::Result<()> {
+    let msg = format!("{}.mtx is not in {}, and downloading requires the \
+                       datasets feature",
+                      matrix, group_dir.display());
+    Err(io::Error::new(io::ErrorKind::NotFound, msg))
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::{self, File};
+    use std::io::Write;
+    use sparse::CsMat;
+    use test_data::temp_path;
+    use super::{load_from, split_name};
+
+    #[test]
+    fn dataset_names() {
+        assert_eq!(split_name("HB/bcsstk01").unwrap(), ("HB", "bcsstk01"));
+        assert!(split_name("bcsstk01").is_err());
+        assert!(split_name("HB/bcsstk01/x").is_err());
+        assert!(split_name("../bcsstk01").is_err());
+        assert!(split_name("HB/").is_err());
+    }
+
+    #[test]
+    fn load_cached_dataset() {
+        let cache = temp_path("sprs_datasets_cache");
+        let group_dir = cache.join("Test");
+        fs::create_dir_all(&group_dir).unwrap();
+        let mut file = File::create(group_dir.join("diag.mtx")).unwrap();
+        file.write_all(b"%%MatrixMarket matrix coordinate real general\n\
+                         2 2 2\n1 1 1.5\n2 2 -2\n").unwrap();
+        drop(file);
+        let expected = CsMat::new((2, 2), vec![0, 1, 2], vec![0, 1],
+                                  vec![1.5, -2.]);
+        assert_eq!(load_from(&cache, "Test/diag").unwrap(), expected);
+        // the second load reads the binary cache
+        assert!(group_dir.join("diag.sprs").is_file());
+        fs::remove_file(group_dir.join("diag.mtx")).unwrap();
+        assert_eq!(load_from(&cache, "Test/diag").unwrap(), expected);
+        fs::remove_dir_all(&cache).unwrap();
+    }
+}
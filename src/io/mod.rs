@@ -0,0 +1,439 @@
+///! Serialization of sparse matrices
+///!
+///! This module is available when the `std` feature is enabled. It defines
+///! the native binary format of sprs for `f64` CSR matrices, which stores
+///! the raw compressed arrays and can thus be written and read without any
+///! parsing:
+///!
+///! - the magic bytes `SPRSCSR1`;
+///! - the number of rows, of columns and of non-zeros, as little endian
+///!   `u64`;
+///! - the `indptr` and `indices` arrays, as little endian `u64`;
+///! - the `data` array, as little endian IEEE 754 `f64`.
+///!
+///! Matrices in the Matrix Market coordinate format, used notably by the
+///! SuiteSparse collection, can be read with `read_matrix_market`, or with
+///! `read_matrix_market_complex` for complex matrices, and
+///! named matrices of this collection are loaded by the `datasets` module.
+///!
+///! With the `plot` feature, the sparsity structure of matrices can also be
+///! exported as images with `spy_to_svg` and `spy_to_png`, re-exported from
+///! the `plot` module, see `plot::SpyOptions` for the available
+///! downsampling and coloring parameters.
+///!
+///! # Example
+///!
+///! ```rust
+///! use sprs::CsMat;
+///! use sprs::io::{write_binary, read_binary};
+///! let eye: CsMat<f64> = CsMat::eye(3);
+///! let mut buf = Vec::new();
+///! write_binary(&mut buf, eye.view()).unwrap();
+///! assert_eq!(read_binary(&mut &buf[..]).unwrap(), eye);
+///! ```
+
+use std::io::{self, BufRead, Read, Write};
+use std::str::FromStr;
+
+use num_complex::Complex64;
+
+use sparse::{CsMat, CsMatView, TriMat};
+use sparse::complex::RealOrComplex;
+
+pub mod oocsr;
+pub mod datasets;
+
+#[cfg(feature = "plot")]
+pub use plot::{spy_to_png, spy_to_svg};
+
+const MAGIC: &'static [u8; 8] = b"SPRSCSR1";
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn write_u64<W: Write>(writer: &mut W, val: u64) -> io::Result<()> {
+    let mut bytes = [0u8; 8];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = (val >> (8 * i)) as u8;
+    }
+    writer.write_all(&bytes)
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    try!(reader.read_exact(&mut bytes));
+    Ok(bytes.iter().rev().fold(0, |acc, &byte| (acc << 8) | byte as u64))
+}
+
+fn read_usize<R: Read>(reader: &mut R) -> io::Result<usize> {
+    let val = try!(read_u64(reader));
+    if val > usize::max_value() as u64 {
+        return Err(invalid_data("value does not fit in usize"));
+    }
+    Ok(val as usize)
+}
+
+/// Capacity reserved upfront when reading arrays, to avoid huge allocations
+/// on corrupted lengths
+const MAX_RESERVE: usize = 1 << 20;
+
+fn read_usizes<R: Read>(reader: &mut R, len: usize) -> io::Result<Vec<usize>> {
+    let mut res = Vec::with_capacity(len.min(MAX_RESERVE));
+    for _ in 0..len {
+        res.push(try!(read_usize(reader)));
+    }
+    Ok(res)
+}
+
+/// Write a CSR matrix in the native binary format.
+///
+/// # Panics
+///
+/// If the matrix is not in CSR storage.
+pub fn write_binary<W: Write>(writer: &mut W,
+                              mat: CsMatView<f64>) -> io::Result<()> {
+    assert!(mat.is_csr(), "Storage mismatch");
+    try!(writer.write_all(MAGIC));
+    try!(write_u64(writer, mat.rows() as u64));
+    try!(write_u64(writer, mat.cols() as u64));
+    try!(write_u64(writer, mat.nnz() as u64));
+    for &i in mat.indptr().iter().chain(mat.indices()) {
+        try!(write_u64(writer, i as u64));
+    }
+    for &val in mat.data() {
+        try!(write_u64(writer, val.to_bits()));
+    }
+    Ok(())
+}
+
+/// Read a CSR matrix in the native binary format.
+///
+/// # Errors
+///
+/// An error of kind `InvalidData` is returned if the input is not in the
+/// native binary format or does not describe a valid CSR matrix.
+pub fn read_binary<R: Read>(reader: &mut R) -> io::Result<CsMat<f64>> {
+    let mut magic = [0u8; 8];
+    try!(reader.read_exact(&mut magic));
+    if &magic != MAGIC {
+        return Err(invalid_data("not a sprs binary matrix"));
+    }
+    let rows = try!(read_usize(reader));
+    let cols = try!(read_usize(reader));
+    let nnz = try!(read_usize(reader));
+    let indptr = try!(read_usizes(reader, rows.saturating_add(1)));
+    let indices = try!(read_usizes(reader, nnz));
+    let mut data = Vec::with_capacity(nnz.min(MAX_RESERVE));
+    for _ in 0..nnz {
+        data.push(f64::from_bits(try!(read_u64(reader))));
+    }
+    let valid = CsMatView::from_raw_parts(::CSR, (rows, cols),
+                                          &indptr, &indices, &data).is_ok();
+    if !valid {
+        return Err(invalid_data("invalid CSR structure"));
+    }
+    Ok(CsMat::new((rows, cols), indptr, indices, data))
+}
+
+/// Read a real matrix in the coordinate Matrix Market format.
+///
+/// The `real`, `integer` and `pattern` fields are supported, the values
+/// of pattern matrices being set to one, along with the `general`,
+/// `symmetric` and `skew-symmetric` symmetries, whose lower triangle is
+/// mirrored. Duplicate entries are summed.
+///
+/// # Errors
+///
+/// An error of kind `InvalidData` is returned if the input is not a
+/// supported Matrix Market file, or if an entry is out of bounds.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::io::read_matrix_market;
+/// let file = "%%MatrixMarket matrix coordinate real symmetric\n\
+///             % a comment\n\
+///             2 2 2\n\
+///             1 1 4.\n\
+///             2 1 -1.\n";
+/// let mat = read_matrix_market(file.as_bytes()).unwrap();
+/// assert_eq!(mat, CsMat::new((2, 2), vec![0, 2, 3], vec![0, 1, 0],
+///                            vec![4., -1., -1.]));
+/// ```
+pub fn read_matrix_market<R: BufRead>(reader: R) -> io::Result<CsMat<f64>> {
+    read_coordinates(reader)
+}
+
+/// Read a complex matrix in the coordinate Matrix Market format.
+///
+/// On top of the fields and symmetries supported by `read_matrix_market`,
+/// the `complex` field and the `hermitian` symmetry, whose lower triangle
+/// is mirrored and conjugated, are supported.
+///
+/// # Errors
+///
+/// An error of kind `InvalidData` is returned if the input is not a
+/// supported Matrix Market file, or if an entry is out of bounds.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate num_complex;
+/// extern crate sprs;
+/// use num_complex::Complex64;
+/// use sprs::io::read_matrix_market_complex;
+/// # fn main() {
+/// let file = "%%MatrixMarket matrix coordinate complex hermitian\n\
+///             2 2 2\n\
+///             1 1 4. 0.\n\
+///             2 1 1. 2.\n";
+/// let mat = read_matrix_market_complex(file.as_bytes()).unwrap();
+/// assert_eq!(mat.get(0, 1), Some(&Complex64::new(1., -2.)));
+/// assert_eq!(mat.get(1, 0), Some(&Complex64::new(1., 2.)));
+/// # }
+/// ```
+pub fn read_matrix_market_complex<R: BufRead>(reader: R)
+                                              -> io::Result<CsMat<Complex64>> {
+    read_coordinates(reader)
+}
+
+/// The scalars which can be read from Matrix Market files
+trait MarketScalar: RealOrComplex + Default {
+    /// Whether the `complex` field and `hermitian` symmetry can be read
+    fn is_complex() -> bool;
+
+    /// The scalar of real and imaginary parts `re` and `im`
+    fn from_parts(re: f64, im: f64) -> Self;
+}
+
+impl MarketScalar for f64 {
+    fn is_complex() -> bool {
+        false
+    }
+
+    fn from_parts(re: f64, _im: f64) -> f64 {
+        re
+    }
+}
+
+impl MarketScalar for Complex64 {
+    fn is_complex() -> bool {
+        true
+    }
+
+    fn from_parts(re: f64, im: f64) -> Complex64 {
+        Complex64::new(re, im)
+    }
+}
+
+/// The symmetries of Matrix Market files
+#[derive(Clone, Copy, PartialEq)]
+enum Symmetry {
+    General,
+    Symmetric,
+    SkewSymmetric,
+    Hermitian,
+}
+
+fn read_coordinates<R, N>(reader: R) -> io::Result<CsMat<N>>
+where R: BufRead,
+      N: MarketScalar,
+{
+    let mut lines = reader.lines();
+    let header = match lines.next() {
+        Some(line) => try!(line).to_lowercase(),
+        None => return Err(invalid_data("empty Matrix Market file")),
+    };
+    let header: Vec<_> = header.split_whitespace().collect();
+    if header.len() != 5 || header[0] != "%%matrixmarket"
+        || header[1] != "matrix" || header[2] != "coordinate"
+    {
+        return Err(invalid_data("not a coordinate Matrix Market file"));
+    }
+    let (pattern, complex) = match header[3] {
+        "real" | "integer" => (false, false),
+        "pattern" => (true, false),
+        "complex" if N::is_complex() => (false, true),
+        _ => return Err(invalid_data("unsupported Matrix Market field")),
+    };
+    let symmetry = match header[4] {
+        "general" => Symmetry::General,
+        "symmetric" => Symmetry::Symmetric,
+        "skew-symmetric" => Symmetry::SkewSymmetric,
+        "hermitian" if N::is_complex() => Symmetry::Hermitian,
+        _ => return Err(invalid_data("unsupported Matrix Market symmetry")),
+    };
+
+    let mut data_lines = lines.filter(|line| match *line {
+        Ok(ref line) => {
+            let line = line.trim();
+            !line.is_empty() && !line.starts_with('%')
+        },
+        Err(_) => true,
+    });
+    let size_line = match data_lines.next() {
+        Some(line) => try!(line),
+        None => return Err(invalid_data("missing Matrix Market size line")),
+    };
+    let mut tokens = size_line.split_whitespace();
+    let rows: usize = try!(parse_token(tokens.next()));
+    let cols: usize = try!(parse_token(tokens.next()));
+    let nnz: usize = try!(parse_token(tokens.next()));
+    let general = symmetry == Symmetry::General;
+    let cap = if general { nnz } else { 2 * nnz };
+    let mut tri = TriMat::with_capacity((rows, cols), cap.min(MAX_RESERVE));
+    let mut nb_entries = 0;
+    for line in data_lines {
+        let line = try!(line);
+        let mut tokens = line.split_whitespace();
+        let row: usize = try!(parse_token(tokens.next()));
+        let col: usize = try!(parse_token(tokens.next()));
+        let val = if pattern {
+            N::one()
+        } else {
+            let re: f64 = try!(parse_token(tokens.next()));
+            let im: f64 = if complex {
+                try!(parse_token(tokens.next()))
+            } else {
+                0.
+            };
+            N::from_parts(re, im)
+        };
+        if row == 0 || col == 0 || row > rows || col > cols {
+            return Err(invalid_data("out of bounds Matrix Market entry"));
+        }
+        tri.add_triplet(row - 1, col - 1, val);
+        if !general && row != col {
+            let mirrored = match symmetry {
+                Symmetry::SkewSymmetric => N::zero() - val,
+                Symmetry::Hermitian => val.conj(),
+                _ => val,
+            };
+            tri.add_triplet(col - 1, row - 1, mirrored);
+        }
+        nb_entries += 1;
+    }
+    if nb_entries != nnz {
+        return Err(invalid_data("wrong number of Matrix Market entries"));
+    }
+    Ok(tri.to_csr())
+}
+
+fn parse_token<T: FromStr>(token: Option<&str>) -> io::Result<T> {
+    token.and_then(|tok| tok.parse().ok())
+         .ok_or_else(|| invalid_data("invalid Matrix Market entry"))
+}
+
+#[cfg(test)]
+mod test {
+    use num_complex::Complex64;
+    use test_data::mat1;
+    use sparse::CsMat;
+    use super::{write_binary, read_binary, read_matrix_market};
+    use super::read_matrix_market_complex;
+
+    #[test]
+    fn binary_roundtrip() {
+        let mut buf = Vec::new();
+        write_binary(&mut buf, mat1().view()).unwrap();
+        assert_eq!(buf.len(), 8 * (4 + 6 + 7 + 7));
+        assert_eq!(read_binary(&mut &buf[..]).unwrap(), mat1());
+        assert!(read_binary(&mut &buf[..20]).is_err());
+        buf[0] = b'X';
+        assert!(read_binary(&mut &buf[..]).is_err());
+    }
+
+    #[test]
+    fn matrix_market() {
+        let file = "%%MatrixMarket matrix coordinate pattern skew-symmetric\n\
+                    3 3 2\n\
+                    \n\
+                    2 1\n\
+                    3 2\n";
+        let mat = read_matrix_market(file.as_bytes()).unwrap();
+        let expected = CsMat::new((3, 3), vec![0, 1, 3, 4],
+                                  vec![1, 0, 2, 1], vec![-1., 1., -1., 1.]);
+        assert_eq!(mat, expected);
+
+        let file = "%%MatrixMarket matrix coordinate integer general\n\
+                    2 3 3\n1 3 2\n2 1 -1\n1 3 1\n";
+        let mat = read_matrix_market(file.as_bytes()).unwrap();
+        let expected = CsMat::new((2, 3), vec![0, 1, 2],
+                                  vec![2, 0], vec![3., -1.]);
+        assert_eq!(mat, expected);
+
+        let invalid = [
+            "%%MatrixMarket matrix array real general\n2 1\n1.\n2.\n",
+            "%%MatrixMarket matrix coordinate real general\n2 2 1\n3 1 1.\n",
+            "%%MatrixMarket matrix coordinate real general\n2 2 2\n1 1 1.\n",
+            "%%MatrixMarket matrix coordinate real general\n2 2 1\n1 1\n",
+        ];
+        for file in &invalid {
+            assert!(read_matrix_market(file.as_bytes()).is_err());
+        }
+    }
+
+    #[test]
+    fn matrix_market_complex() {
+        let file = "%%MatrixMarket matrix coordinate complex hermitian\n\
+                    2 2 2\n1 1 3. 0.\n2 1 1. -1.\n";
+        let mat = read_matrix_market_complex(file.as_bytes()).unwrap();
+        let expected = CsMat::new((2, 2), vec![0, 2, 3], vec![0, 1, 0],
+                                  vec![Complex64::new(3., 0.),
+                                       Complex64::new(1., 1.),
+                                       Complex64::new(1., -1.)]);
+        assert_eq!(mat, expected);
+        assert!(read_matrix_market(file.as_bytes()).is_err());
+
+        let file = "%%MatrixMarket matrix coordinate real skew-symmetric\n\
+                    2 2 1\n2 1 2.\n";
+        let mat = read_matrix_market_complex(file.as_bytes()).unwrap();
+        assert_eq!(mat.get(0, 1), Some(&Complex64::new(-2., 0.)));
+        assert_eq!(mat.get(1, 0), Some(&Complex64::new(2., 0.)));
+
+        let file = "%%MatrixMarket matrix coordinate complex general\n\
+                    1 1 1\n1 1 1.\n";
+        assert!(read_matrix_market_complex(file.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn matrix_market_complex_files() {
+        let c = Complex64::new;
+        let file = include_str!("../../data/complex_hermitian.mtx");
+        let mat = read_matrix_market_complex(file.as_bytes()).unwrap();
+        let expected = CsMat::new((3, 3),
+                                  vec![0, 2, 5, 7],
+                                  vec![0, 1, 0, 1, 2, 1, 2],
+                                  vec![c(2., 0.), c(1., -1.),
+                                       c(1., 1.), c(3., 0.), c(0., -2.),
+                                       c(0., 2.), c(4., 0.)]);
+        assert_eq!(mat, expected);
+        assert_eq!(mat, mat.adjoint());
+
+        let file = include_str!("../../data/complex_general.mtx");
+        let mat = read_matrix_market_complex(file.as_bytes()).unwrap();
+        let expected = CsMat::new((2, 3),
+                                  vec![0, 2, 3],
+                                  vec![0, 2, 1],
+                                  vec![c(1., 0.5), c(-2., 0.),
+                                       c(0., 3.)]);
+        assert_eq!(mat, expected);
+        assert!(read_matrix_market(file.as_bytes()).is_err());
+    }
+
+    #[cfg(feature = "plot")]
+    #[test]
+    fn spy_svg_file() {
+        use std::fs;
+        use plot::SpyOptions;
+        use test_data::temp_path;
+        let path = temp_path("sprs_spy_svg").with_extension("svg");
+        let options = SpyOptions { max_dim: 5, by_magnitude: true };
+        super::spy_to_svg(&mat1(), &path, options).unwrap();
+        let svg = fs::read_to_string(&path).unwrap();
+        assert_eq!(svg.matches("<rect x=").count(), mat1().nnz());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,308 @@
+///! Out-of-core CSR matrices
+///!
+///! An `OocCsr` matrix is stored on disk as a directory of chunks of
+///! consecutive rows, each chunk being a CSR matrix in the native binary
+///! format (see `io::write_binary`). Only one chunk is loaded in memory at a
+///! time when iterating over the rows or computing matrix-vector products,
+///! so the memory usage is bounded by the size of the largest chunk plus the
+///! size of the dense vectors, whatever the size of the matrix.
+///!
+///! Matrices larger than the memory are built row by row with an
+///! `OocCsrWriter`, which flushes a chunk to disk as soon as it holds the
+///! requested number of non-zeros.
+///!
+///! The directory holds an `index.bin` file, containing the magic bytes
+///! `SPRSOOC1`, the number of rows, of columns and of chunks, then the
+///! offsets of the rows of the chunks, as little endian `u64`, and the
+///! chunks themselves as `chunk_<k>.bin`.
+///!
+///! # Example
+///!
+///! ```rust,no_run
+///! use sprs::io::oocsr::OocCsrWriter;
+///! let mut writer = OocCsrWriter::new("/tmp/big_matrix", 1000, 1 << 20)
+///!                               .unwrap();
+///! for row in 0..1000 {
+///!     writer.push_row(&[row], &[2.]).unwrap();
+///! }
+///! let mat = writer.finish().unwrap();
+///! let y = mat.mul_vec(&vec![1.; 1000]).unwrap();
+///! assert_eq!(y, vec![2.; 1000]);
+///! ```
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use sparse::{CsMat, CsMatView, CsVecView};
+use super::{write_u64, read_usize, read_usizes, invalid_data,
+            write_binary, read_binary};
+
+const INDEX_MAGIC: &'static [u8; 8] = b"SPRSOOC1";
+
+fn chunk_path(dir: &Path, chunk: usize) -> PathBuf {
+    dir.join(format!("chunk_{}.bin", chunk))
+}
+
+/// A CSR matrix stored on disk in chunks of rows
+#[derive(Debug, Clone, PartialEq)]
+pub struct OocCsr {
+    dir: PathBuf,
+    cols: usize,
+    row_offsets: Vec<usize>,
+}
+
+impl OocCsr {
+    /// Open a matrix previously written in `dir`
+    pub fn open<P: AsRef<Path>>(dir: P) -> io::Result<OocCsr> {
+        let dir = dir.as_ref().to_path_buf();
+        let mut reader = BufReader::new(try!(File::open(dir.join("index.bin"))));
+        let mut magic = [0u8; 8];
+        try!(io::Read::read_exact(&mut reader, &mut magic));
+        if &magic != INDEX_MAGIC {
+            return Err(invalid_data("not a sprs out-of-core matrix"));
+        }
+        let rows = try!(read_usize(&mut reader));
+        let cols = try!(read_usize(&mut reader));
+        let nb_chunks = try!(read_usize(&mut reader));
+        let row_offsets = try!(read_usizes(&mut reader,
+                                           nb_chunks.saturating_add(1)));
+        let valid = row_offsets[0] == 0
+                    && row_offsets[nb_chunks] == rows
+                    && row_offsets.windows(2).all(|w| w[0] <= w[1]);
+        if !valid {
+            return Err(invalid_data("invalid chunk offsets"));
+        }
+        Ok(OocCsr {
+            dir: dir,
+            cols: cols,
+            row_offsets: row_offsets,
+        })
+    }
+
+    /// Write an in-memory CSR matrix to `dir`, in chunks holding about
+    /// `chunk_nnz` non-zeros.
+    pub fn from_csr<P: AsRef<Path>>(dir: P,
+                                    mat: CsMatView<f64>,
+                                    chunk_nnz: usize) -> io::Result<OocCsr> {
+        let mat = if mat.is_csr() { mat.to_owned() } else { mat.to_csr() };
+        let mut writer = try!(OocCsrWriter::new(dir, mat.cols(), chunk_nnz));
+        for row in mat.outer_iterator() {
+            try!(writer.push_row(row.indices(), row.data()));
+        }
+        writer.finish()
+    }
+
+    /// The number of rows
+    pub fn rows(&self) -> usize {
+        *self.row_offsets.last().unwrap()
+    }
+
+    /// The number of columns
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// The number of chunks
+    pub fn nb_chunks(&self) -> usize {
+        self.row_offsets.len() - 1
+    }
+
+    /// Load the chunk `chunk`, returning the index of its first row and its
+    /// rows as a CSR matrix.
+    ///
+    /// # Panics
+    ///
+    /// If `chunk >= self.nb_chunks()`.
+    pub fn load_chunk(&self, chunk: usize) -> io::Result<(usize, CsMat<f64>)> {
+        let path = chunk_path(&self.dir, chunk);
+        let mut reader = BufReader::new(try!(File::open(path)));
+        let mat = try!(read_binary(&mut reader));
+        let first_row = self.row_offsets[chunk];
+        let nb_rows = self.row_offsets[chunk + 1] - first_row;
+        if mat.rows() != nb_rows || mat.cols() != self.cols {
+            return Err(invalid_data("chunk shape does not match the index"));
+        }
+        Ok((first_row, mat))
+    }
+
+    /// Iterate over the chunks, loading them one at a time
+    pub fn chunks<'a>(&'a self)
+                      -> Box<dyn Iterator<Item=io::Result<(usize, CsMat<f64>)>>
+                             + 'a> {
+        Box::new((0..self.nb_chunks()).map(move |k| self.load_chunk(k)))
+    }
+
+    /// Call `f(row, vec)` on each row, in order, loading a chunk at a time
+    pub fn for_each_row<F>(&self, mut f: F) -> io::Result<()>
+    where F: FnMut(usize, CsVecView<f64>)
+    {
+        for chunk in self.chunks() {
+            let (first_row, mat) = try!(chunk);
+            for (i, row) in mat.outer_iterator().enumerate() {
+                f(first_row + i, row);
+            }
+        }
+        Ok(())
+    }
+
+    /// Compute `y = A x`, streaming the chunks from disk
+    ///
+    /// # Panics
+    ///
+    /// If `x.len() != self.cols()`.
+    pub fn mul_vec(&self, x: &[f64]) -> io::Result<Vec<f64>> {
+        if x.len() != self.cols {
+            panic!("Dimension mismatch");
+        }
+        let mut y = vec![0.; self.rows()];
+        try!(self.for_each_row(|row, vec| {
+            y[row] = vec.iter().map(|(col, &val)| val * x[col]).sum();
+        }));
+        Ok(y)
+    }
+}
+
+/// Row by row writer of an out-of-core CSR matrix
+pub struct OocCsrWriter {
+    dir: PathBuf,
+    cols: usize,
+    chunk_nnz: usize,
+    row_offsets: Vec<usize>,
+    indptr: Vec<usize>,
+    indices: Vec<usize>,
+    data: Vec<f64>,
+}
+
+impl OocCsrWriter {
+    /// Start writing a matrix with `cols` columns in `dir`, which is created
+    /// if needed. A chunk is written to disk as soon as it holds at least
+    /// `chunk_nnz` non-zeros.
+    pub fn new<P: AsRef<Path>>(dir: P,
+                               cols: usize,
+                               chunk_nnz: usize) -> io::Result<OocCsrWriter> {
+        let dir = dir.as_ref().to_path_buf();
+        try!(fs::create_dir_all(&dir));
+        Ok(OocCsrWriter {
+            dir: dir,
+            cols: cols,
+            chunk_nnz: chunk_nnz.max(1),
+            row_offsets: vec![0],
+            indptr: vec![0],
+            indices: Vec::new(),
+            data: Vec::new(),
+        })
+    }
+
+    /// Append a row, given by its sorted column indices and its values
+    ///
+    /// # Errors
+    ///
+    /// An error of kind `InvalidInput` is returned if the indices are not
+    /// sorted, are out of bounds, or do not match the values.
+    pub fn push_row(&mut self, indices: &[usize], data: &[f64])
+                    -> io::Result<()> {
+        let valid = indices.len() == data.len()
+                    && indices.windows(2).all(|w| w[0] < w[1])
+                    && indices.last().map_or(true, |&j| j < self.cols);
+        if !valid {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "invalid sparse row"));
+        }
+        self.indices.extend_from_slice(indices);
+        self.data.extend_from_slice(data);
+        self.indptr.push(self.indices.len());
+        if self.indices.len() >= self.chunk_nnz {
+            try!(self.flush_chunk());
+        }
+        Ok(())
+    }
+
+    fn flush_chunk(&mut self) -> io::Result<()> {
+        let nb_rows = self.indptr.len() - 1;
+        if nb_rows == 0 {
+            return Ok(());
+        }
+        {
+            let mat = CsMatView::new_view(::CSR,
+                                          (nb_rows, self.cols),
+                                          &self.indptr,
+                                          &self.indices,
+                                          &self.data).unwrap();
+            let path = chunk_path(&self.dir, self.row_offsets.len() - 1);
+            let mut writer = BufWriter::new(try!(File::create(path)));
+            try!(write_binary(&mut writer, mat));
+            try!(writer.flush());
+        }
+        let last = *self.row_offsets.last().unwrap();
+        self.row_offsets.push(last + nb_rows);
+        self.indptr.truncate(1);
+        self.indices.clear();
+        self.data.clear();
+        Ok(())
+    }
+
+    /// Flush the last chunk and write the index, returning the written
+    /// matrix.
+    pub fn finish(mut self) -> io::Result<OocCsr> {
+        try!(self.flush_chunk());
+        let path = self.dir.join("index.bin");
+        let mut writer = BufWriter::new(try!(File::create(path)));
+        try!(writer.write_all(INDEX_MAGIC));
+        let rows = *self.row_offsets.last().unwrap();
+        try!(write_u64(&mut writer, rows as u64));
+        try!(write_u64(&mut writer, self.cols as u64));
+        try!(write_u64(&mut writer, (self.row_offsets.len() - 1) as u64));
+        for &offset in &self.row_offsets {
+            try!(write_u64(&mut writer, offset as u64));
+        }
+        try!(writer.flush());
+        Ok(OocCsr {
+            dir: self.dir,
+            cols: self.cols,
+            row_offsets: self.row_offsets,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use test_data::{mat1, mat1_csc, temp_path};
+    use super::{OocCsr, OocCsrWriter};
+
+    #[test]
+    fn out_of_core_matvec() {
+        let dir = temp_path("sprs_oocsr_matvec");
+        let mat = OocCsr::from_csr(&dir, mat1_csc().view(), 3).unwrap();
+        assert_eq!(mat.nb_chunks(), 2);
+        assert_eq!((mat.rows(), mat.cols()), (5, 5));
+        let x = vec![1., 2., 3., 4., 5.];
+        let expected = &mat1() * &::ndarray::arr1(&x);
+        assert_eq!(mat.mul_vec(&x).unwrap(), expected.to_vec());
+
+        let reopened = OocCsr::open(&dir).unwrap();
+        assert_eq!(reopened, mat);
+        let mut rows = Vec::new();
+        reopened.for_each_row(|row, vec| rows.push((row, vec.to_owned())))
+                .unwrap();
+        assert_eq!(rows.len(), 5);
+        for (row, vec) in rows {
+            assert_eq!(vec, mat1().outer_view(row).unwrap().to_owned());
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn writer_rejects_invalid_rows() {
+        let dir = temp_path("sprs_oocsr_invalid");
+        let mut writer = OocCsrWriter::new(&dir, 3, 10).unwrap();
+        assert!(writer.push_row(&[1, 0], &[1., 1.]).is_err());
+        assert!(writer.push_row(&[3], &[1.]).is_err());
+        assert!(writer.push_row(&[0, 2], &[1.]).is_err());
+        writer.push_row(&[0, 2], &[1., 2.]).unwrap();
+        let mat = writer.finish().unwrap();
+        assert_eq!(mat.mul_vec(&[1., 1., 1.]).unwrap(), vec![3.]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
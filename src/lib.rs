@@ -56,24 +56,111 @@ let b = &eye * &a;
 assert_eq!(a, b.to_csc());
 ```
 
+## Cargo features
+
+- `std` (enabled by default) gates everything relying on the standard
+  library beyond memory allocation, including the dense interop through
+  `ndarray`. Without it sprs is a `no_std` crate only needing `alloc`,
+  which keeps the sparse formats (`CsMat`, `CsVec`, `TriMat`), their
+  products and binary operations, permutations and the sparse triangular
+  solves. The other features enable it.
+- `plot` enables rendering the sparsity pattern of matrices to PNG files.
+- `nalgebra` enables conversions to and from nalgebra types.
+- `ffi` exposes a C-compatible interface to CSR matrices.
+- `num-rational` brings the rationals of `num-rational` in scope, to be
+  used as exact scalars, eg with `linalg::ExactLu`.
+- `rug` provides `rug_interop::Rational`, wrapping the arbitrary precision
+  rationals of `rug` to be used as exact scalars.
+- `half` enables the `f16` type of the `half` crate as a storage type for
+  the mixed precision kernels of the `prod` module.
+- `mkl` enables dispatching some kernels to Intel MKL's sparse BLAS.
+- `suitesparse` enables dispatching the factorizations of the `backend`
+  module to SuiteSparse's UMFPACK and CHOLMOD.
+- `cuda` enables offloading products to the GPU with cuSPARSE, see the
+  `cuda` module.
+- `rayon` enables the parallel constructors, such as
+  `par_csr_from_dense_with`, and the parallel kernels of the `parallel`
+  module, which can be made bitwise reproducible.
+- `proptest` exposes the `strategies` module, generating arbitrary sparse
+  matrices and vectors for property based testing.
+- `bench` exposes the `bench` module, timing the kernels on user matrices.
+- `datasets` enables downloading the matrices of the SuiteSparse collection
+  loaded by `io::datasets`.
+
 */
 
 #![deny(warnings)]
+#![cfg_attr(not(feature = "std"), no_std)]
 
+#[cfg(not(feature = "std"))]
+#[macro_use]
+extern crate alloc;
 extern crate num_traits;
+#[cfg(feature = "std")]
+extern crate num_complex;
+#[cfg(feature = "std")]
 extern crate ndarray;
 #[cfg(feature = "alga")]
 extern crate alga;
+#[cfg(feature = "plot")]
+extern crate png;
+#[cfg(feature = "nalgebra")]
+extern crate nalgebra;
+#[cfg(feature = "half")]
+extern crate half;
+#[cfg(feature = "num-rational")]
+extern crate num_rational;
+#[cfg(feature = "rug")]
+extern crate rug;
+#[cfg(feature = "suitesparse")]
+extern crate suitesparse_umfpack_sys;
+#[cfg(feature = "suitesparse")]
+extern crate suitesparse_cholmod_sys;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "rayon")]
+#[macro_use]
+extern crate lazy_static;
+#[cfg(feature = "proptest")]
+extern crate proptest;
+#[cfg(feature = "bench")]
+extern crate criterion;
 
 mod sparse;
 pub mod errors;
 pub mod stack;
 pub mod indexing;
 pub mod array_backend;
+#[cfg(feature = "std")]
+pub mod io;
+#[cfg(feature = "plot")]
+pub mod plot;
+#[cfg(feature = "nalgebra")]
+pub mod nalgebra_interop;
+#[cfg(feature = "rug")]
+pub mod rug_interop;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "proptest")]
+pub mod strategies;
+#[cfg(feature = "bench")]
+pub mod bench;
+
+/// The parts of the standard library used by the core formats and kernels,
+/// provided by `core` and `alloc` without the `std` feature, so that they
+/// can keep importing them from `std`
+#[cfg(not(feature = "std"))]
+mod std {
+    pub use core::{arch, cmp, convert, default, iter, marker, mem, ops};
+    pub use alloc::{fmt, slice, sync, vec};
+}
 
 /// Deprecated type alias, will be removed on next breaking change
+#[cfg(feature = "std")]
 pub type Ix_ = ndarray::Ix1;
+#[cfg(feature = "std")]
 pub type Ix1 = ndarray::Ix1;
+#[cfg(feature = "std")]
 pub type Ix2 = ndarray::Ix2;
 
 pub use indexing::SpIndex;
@@ -87,6 +174,10 @@ pub use sparse::{
     CsMatViewMut,
     CsMatViewMutI,
     CsMatVecView,
+    CsMatBlockMut,
+    CsMatBlockMutI,
+    CsMatShared,
+    CsMatSharedI,
     CsVecBase,
     CsVecView,
     CsVecViewI,
@@ -103,11 +194,39 @@ pub use sparse::{
     TriMatViewMutI,
 };
 
+#[cfg(feature = "std")]
+pub use sparse::typed::{
+    CsrMat,
+    CscMat,
+};
+
+pub use sparse::triplet::TriMatWorkspace;
 
+pub use sparse::triplet_iter::{
+    TriMatIter,
+    TripletStats,
+};
+
+
+#[cfg(feature = "std")]
 pub use sparse::symmetric::{
     is_symmetric,
 };
 
+#[cfg(feature = "std")]
+pub use sparse::norms::NormType;
+
+#[cfg(feature = "std")]
+pub use sparse::complex::RealOrComplex;
+
+pub use sparse::accumulator::SparseAccumulator;
+
+#[cfg(feature = "std")]
+pub use sparse::pattern::{
+    PatternMat,
+    PatternMatI,
+};
+
 pub use sparse::permutation::{
     Permutation,
     PermView,
@@ -125,6 +244,29 @@ pub use sparse::CompressedStorage::{
 pub use sparse::linalg;
 pub use sparse::prod;
 pub use sparse::binop;
+#[cfg(feature = "std")]
+pub use sparse::shards;
+#[cfg(feature = "std")]
+pub use sparse::partition;
+#[cfg(feature = "std")]
+pub use sparse::assemble;
+#[cfg(feature = "std")]
+pub use sparse::graph;
+#[cfg(feature = "std")]
+pub use sparse::mutation_log;
+#[cfg(feature = "std")]
+pub use sparse::backend;
+pub use sparse::validation;
+#[cfg(feature = "std")]
+pub use sparse::checked;
+#[cfg(feature = "std")]
+pub use sparse::stochastic;
+#[cfg(feature = "rayon")]
+pub use sparse::parallel;
+#[cfg(feature = "rayon")]
+pub use sparse::parallel::{set_thread_pool, with_num_threads};
+#[cfg(feature = "cuda")]
+pub use sparse::cuda;
 
 pub mod vec {
     pub use sparse::{
@@ -143,17 +285,37 @@ pub mod vec {
         IntoSparseVecIter,
         NnzOrZip,
         NnzEither,
+        CsVecBuilder,
+        CsVecBuilderI,
     };
 }
 
+#[cfg(feature = "std")]
 pub use sparse::construct::{
     vstack,
     hstack,
     bmat,
+    try_vstack,
+    try_hstack,
+    try_bmat,
+    stack,
+    stack_vecs,
     csr_from_dense,
     csc_from_dense,
+    csr_from_dense_with,
+    csc_from_dense_with,
+    csr_from_dense_relative,
+    csc_from_dense_relative,
+    Magnitude,
+};
+
+#[cfg(feature = "rayon")]
+pub use sparse::construct::{
+    par_csr_from_dense_with,
+    par_csc_from_dense_with,
 };
 
+#[cfg(feature = "std")]
 pub use sparse::to_dense::{
     assign_to_dense,
 };
@@ -0,0 +1,260 @@
+///! Interoperability with nalgebra
+///!
+///! This module is available when the `nalgebra` feature is enabled. It
+///! provides conversions between the sparse types of sprs and nalgebra's
+///! dense `DMatrix` and `DVector` types as well as its sparse `CsMatrix`
+///! type, and products of sparse matrices with nalgebra's dense types.
+///!
+///! # Example
+///!
+///! ```rust
+///! extern crate nalgebra;
+///! extern crate sprs;
+///! use nalgebra::{DMatrix, DVector};
+///! use sprs::CsMat;
+///! # fn main() {
+///! let eye: CsMat<f64> = CsMat::eye(3);
+///! let dense = DMatrix::from(&eye);
+///! assert_eq!(dense, DMatrix::identity(3, 3));
+///! let x = DVector::from_element(3, 2.);
+///! assert_eq!(&eye * &x, x);
+///! assert_eq!(CsMat::from(&dense), eye);
+///! # }
+///! ```
+
+use std::ops::{Deref, Mul};
+
+use nalgebra::{DMatrix, DVector, Scalar};
+use nalgebra::sparse::{CsMatrix, CsStorageIter};
+use num_traits::Num;
+
+use indexing::SpIndex;
+use sparse::{CsMatBase, CsMatI, CsVecBase, CsVecI, TriMatI};
+
+/// Call `f(row, col, val)` on each non-zero of `mat`, in storage order
+fn for_each_nnz<N, I, IpS, IS, DS, F>(mat: &CsMatBase<N, I, IpS, IS, DS>,
+                                      mut f: F)
+where N: Copy,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+      F: FnMut(usize, usize, N)
+{
+    let is_csr = mat.is_csr();
+    for (outer, vec) in mat.outer_iterator().enumerate() {
+        for (inner, &val) in vec.iter() {
+            if is_csr {
+                f(outer, inner, val);
+            }
+            else {
+                f(inner, outer, val);
+            }
+        }
+    }
+}
+
+impl<'a, N, I, IpS, IS, DS> From<&'a CsMatBase<N, I, IpS, IS, DS>>
+for DMatrix<N>
+where N: Scalar + Num,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    fn from(mat: &'a CsMatBase<N, I, IpS, IS, DS>) -> DMatrix<N> {
+        let mut res = DMatrix::from_element(mat.rows(), mat.cols(),
+                                            N::zero());
+        for_each_nnz(mat, |row, col, val| res[(row, col)] = val);
+        res
+    }
+}
+
+impl<'a, N, I, IS, DS> From<&'a CsVecBase<IS, DS>> for DVector<N>
+where N: Scalar + Num,
+      I: SpIndex,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    fn from(vec: &'a CsVecBase<IS, DS>) -> DVector<N> {
+        let mut res = DVector::from_element(vec.dim(), N::zero());
+        for (ind, &val) in vec.iter() {
+            res[ind] = val;
+        }
+        res
+    }
+}
+
+impl<'a, N, I, IpS, IS, DS> From<&'a CsMatBase<N, I, IpS, IS, DS>>
+for CsMatrix<N>
+where N: Scalar + Num,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    fn from(mat: &'a CsMatBase<N, I, IpS, IS, DS>) -> CsMatrix<N> {
+        let mut rows = Vec::with_capacity(mat.nnz());
+        let mut cols = Vec::with_capacity(mat.nnz());
+        let mut vals = Vec::with_capacity(mat.nnz());
+        for_each_nnz(mat, |row, col, val| {
+            rows.push(row);
+            cols.push(col);
+            vals.push(val);
+        });
+        CsMatrix::from_triplet(mat.rows(), mat.cols(), &rows, &cols, &vals)
+    }
+}
+
+/// Conversion from a nalgebra sparse matrix, giving a CSC matrix like
+/// nalgebra's storage.
+impl<'a, N, I> From<&'a CsMatrix<N>> for CsMatI<N, I>
+where N: Scalar + Num + Default,
+      I: SpIndex,
+{
+    fn from(mat: &'a CsMatrix<N>) -> CsMatI<N, I> {
+        let mut tri = TriMatI::with_capacity((mat.nrows(), mat.ncols()),
+                                             mat.len());
+        for col in 0..mat.ncols() {
+            for (row, val) in mat.data.column_entries(col) {
+                tri.add_triplet(row, col, val);
+            }
+        }
+        tri.to_csc()
+    }
+}
+
+/// Conversion from a dense nalgebra matrix, giving a CSR matrix holding its
+/// non-zero elements.
+impl<'a, N, I> From<&'a DMatrix<N>> for CsMatI<N, I>
+where N: Scalar + Num,
+      I: SpIndex,
+{
+    fn from(mat: &'a DMatrix<N>) -> CsMatI<N, I> {
+        let (rows, cols) = mat.shape();
+        let mut indptr = Vec::with_capacity(rows + 1);
+        indptr.push(I::zero());
+        let mut indices = Vec::new();
+        let mut data = Vec::new();
+        for row in 0..rows {
+            for col in 0..cols {
+                let val = mat[(row, col)];
+                if val != N::zero() {
+                    indices.push(I::from_usize(col));
+                    data.push(val);
+                }
+            }
+            indptr.push(I::from_usize(indices.len()));
+        }
+        CsMatI::new((rows, cols), indptr, indices, data)
+    }
+}
+
+/// Conversion from a dense nalgebra vector, giving a sparse vector holding
+/// its non-zero elements.
+impl<'a, N, I> From<&'a DVector<N>> for CsVecI<N, I>
+where N: Scalar + Num,
+      I: SpIndex,
+{
+    fn from(vec: &'a DVector<N>) -> CsVecI<N, I> {
+        let mut res = CsVecI::empty(vec.len());
+        for (ind, &val) in vec.iter().enumerate() {
+            if val != N::zero() {
+                res.append(ind, val);
+            }
+        }
+        res
+    }
+}
+
+impl<'a, 'b, N, I, IpS, IS, DS> Mul<&'b DMatrix<N>>
+for &'a CsMatBase<N, I, IpS, IS, DS>
+where N: Scalar + Num,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    type Output = DMatrix<N>;
+
+    fn mul(self, rhs: &'b DMatrix<N>) -> DMatrix<N> {
+        assert_eq!(self.cols(), rhs.nrows(), "Dimension mismatch");
+        let mut res = DMatrix::from_element(self.rows(), rhs.ncols(),
+                                            N::zero());
+        // nalgebra's storage is column major, hence the outer loop on
+        // the columns of the right hand side
+        for k in 0..rhs.ncols() {
+            for_each_nnz(self, |row, col, val| {
+                res[(row, k)] = res[(row, k)] + val * rhs[(col, k)];
+            });
+        }
+        res
+    }
+}
+
+impl<'a, 'b, N, I, IpS, IS, DS> Mul<&'b DVector<N>>
+for &'a CsMatBase<N, I, IpS, IS, DS>
+where N: Scalar + Num,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    type Output = DVector<N>;
+
+    fn mul(self, rhs: &'b DVector<N>) -> DVector<N> {
+        assert_eq!(self.cols(), rhs.len(), "Dimension mismatch");
+        let mut res = DVector::from_element(self.rows(), N::zero());
+        for_each_nnz(self, |row, col, val| {
+            res[row] = res[row] + val * rhs[col];
+        });
+        res
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::{DMatrix, DVector};
+    use nalgebra::sparse::CsMatrix;
+    use sparse::{CsMat, CsVec};
+    use test_data::{mat1, mat1_csc};
+
+    #[test]
+    fn dense_conversions() {
+        let dense = DMatrix::from(&mat1());
+        assert_eq!(dense.shape(), (5, 5));
+        assert_eq!(dense[(0, 2)], 3.);
+        assert_eq!(dense[(3, 1)], 8.);
+        assert_eq!(dense[(0, 0)], 0.);
+        assert_eq!(DMatrix::from(&mat1_csc()), dense);
+        let back: CsMat<f64> = CsMat::from(&dense);
+        assert_eq!(back, mat1());
+
+        let vec = CsVec::new(5, vec![1, 3], vec![2., 4.]);
+        let dense = DVector::from(&vec);
+        assert_eq!(dense, DVector::from_iterator(5, vec![0., 2., 0., 4., 0.]));
+        let back: CsVec<f64> = CsVec::from(&dense);
+        assert_eq!(back, vec);
+    }
+
+    #[test]
+    fn sparse_conversions() {
+        let sparse = CsMatrix::from(&mat1());
+        assert_eq!(DMatrix::from(sparse.clone()), DMatrix::from(&mat1()));
+        let back: CsMat<f64> = CsMat::from(&sparse);
+        assert_eq!(back, mat1_csc());
+    }
+
+    #[test]
+    fn products() {
+        let rhs = DMatrix::from_fn(5, 3, |i, j| (i * 3 + j) as f64);
+        let expected = DMatrix::from(&mat1()) * &rhs;
+        assert_eq!(&mat1() * &rhs, expected);
+        assert_eq!(&mat1_csc() * &rhs, expected);
+
+        let rhs = DVector::from_fn(5, |i, _| i as f64 + 1.);
+        let expected = DMatrix::from(&mat1()) * &rhs;
+        assert_eq!(&mat1() * &rhs, expected);
+        assert_eq!(&mat1_csc() * &rhs, expected);
+    }
+}
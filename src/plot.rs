@@ -0,0 +1,443 @@
+///! Visualization of the sparsity structure of matrices (spy plots)
+///!
+///! This module is available when the `plot` feature is enabled. It renders
+///! the non-zero pattern of a sparse matrix into a PNG or SVG image, without
+///! needing to go through an external plotting tool.
+///!
+///! Matrices larger than the requested image size are downsampled: each pixel
+///! then covers a block of the matrix, and is drawn as soon as this block
+///! contains a non-zero. The pattern can optionally be color-coded by the
+///! magnitude of the values, in which case each pixel displays the largest
+///! magnitude of its block, on a logarithmic scale going from blue (smallest
+///! magnitude of the matrix) to red (largest magnitude of the matrix).
+///!
+///! # Example
+///!
+///! ```rust,no_run
+///! use sprs::CsMat;
+///! use sprs::plot::{spy_to_png, spy_to_svg, SpyOptions};
+///! let eye: CsMat<f64> = CsMat::eye(1000);
+///! spy_to_png(&eye, "eye.png", 512).unwrap();
+///! let options = SpyOptions { max_dim: 256, by_magnitude: true };
+///! spy_to_svg(&eye, "eye.svg", options).unwrap();
+///! ```
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::Deref;
+use std::path::Path;
+
+use num_traits::ToPrimitive;
+use png;
+
+use indexing::SpIndex;
+use sparse::CsMatBase;
+
+/// Parameters of a spy plot
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpyOptions {
+    /// The maximum number of pixels along each dimension. Larger matrices
+    /// are downsampled so that a pixel covers a square block of entries.
+    pub max_dim: usize,
+    /// Color-code the pixels by the largest magnitude of their block, on a
+    /// logarithmic scale from blue to red, instead of drawing the pattern
+    pub by_magnitude: bool,
+}
+
+impl Default for SpyOptions {
+    fn default() -> SpyOptions {
+        SpyOptions {
+            max_dim: 512,
+            by_magnitude: false,
+        }
+    }
+}
+
+/// The default options with the given maximum number of pixels
+impl From<usize> for SpyOptions {
+    fn from(max_dim: usize) -> SpyOptions {
+        SpyOptions {
+            max_dim: max_dim,
+            ..SpyOptions::default()
+        }
+    }
+}
+
+/// A downsampled rendering of the sparsity structure of a matrix.
+///
+/// Each cell of the grid covers a block of `block_size x block_size` entries
+/// of the matrix, and stores an intensity: `None` if the block does not
+/// contain any non-zero, and a value in `[0, 1]` otherwise.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpyGrid {
+    rows: usize,
+    cols: usize,
+    block_size: usize,
+    cells: Vec<Option<f64>>,
+}
+
+impl SpyGrid {
+    /// Compute the spy grid of the pattern of `mat`, with at most `max_dim`
+    /// cells along each dimension. All non-empty cells have intensity `1`.
+    ///
+    /// # Panics
+    ///
+    /// If `max_dim` is zero.
+    pub fn pattern<N, I, IpS, IS, DS>(mat: &CsMatBase<N, I, IpS, IS, DS>,
+                                      max_dim: usize) -> SpyGrid
+    where I: SpIndex,
+          IpS: Deref<Target=[I]>,
+          IS: Deref<Target=[I]>,
+          DS: Deref<Target=[N]>,
+    {
+        let mut grid = SpyGrid::empty(mat.shape(), max_dim);
+        for (outer, vec) in mat.outer_iterator().enumerate() {
+            for &inner in vec.indices() {
+                let (row, col) = oriented(mat.is_csr(), outer, inner.index());
+                let cell = grid.cell_index(row, col);
+                grid.cells[cell] = Some(1.);
+            }
+        }
+        grid
+    }
+
+    /// Compute the spy grid of `mat`, color-coded by the magnitude of its
+    /// values, with at most `max_dim` cells along each dimension.
+    ///
+    /// The intensity of a cell is the largest magnitude of its block, on a
+    /// logarithmic scale normalized by the smallest and largest non-zero
+    /// magnitudes of the matrix. Explicitly stored zeros get intensity `0`.
+    ///
+    /// # Panics
+    ///
+    /// If `max_dim` is zero.
+    pub fn magnitude<N, I, IpS, IS, DS>(mat: &CsMatBase<N, I, IpS, IS, DS>,
+                                        max_dim: usize) -> SpyGrid
+    where N: ToPrimitive,
+          I: SpIndex,
+          IpS: Deref<Target=[I]>,
+          IS: Deref<Target=[I]>,
+          DS: Deref<Target=[N]>,
+    {
+        let mut grid = SpyGrid::empty(mat.shape(), max_dim);
+        let mut min_mag = ::std::f64::INFINITY;
+        let mut max_mag: f64 = 0.;
+        for (outer, vec) in mat.outer_iterator().enumerate() {
+            for (inner, val) in vec.iter() {
+                let mag = val.to_f64().map_or(0., |x| x.abs());
+                if mag > 0. {
+                    min_mag = min_mag.min(mag);
+                    max_mag = max_mag.max(mag);
+                }
+                let (row, col) = oriented(mat.is_csr(), outer, inner);
+                let cell = grid.cell_index(row, col);
+                let cur = grid.cells[cell].unwrap_or(0.);
+                grid.cells[cell] = Some(cur.max(mag));
+            }
+        }
+        let log_min = min_mag.ln();
+        let log_range = max_mag.ln() - log_min;
+        for cell in grid.cells.iter_mut() {
+            if let Some(mag) = *cell {
+                let intensity = if mag == 0. {
+                    0.
+                }
+                else if log_range > 0. {
+                    (mag.ln() - log_min) / log_range
+                }
+                else {
+                    1.
+                };
+                *cell = Some(intensity);
+            }
+        }
+        grid
+    }
+
+    /// Compute the spy grid of `mat` following `options`, ie with
+    /// `SpyGrid::magnitude` if `options.by_magnitude` is set, and with
+    /// `SpyGrid::pattern` otherwise.
+    ///
+    /// # Panics
+    ///
+    /// If `options.max_dim` is zero.
+    pub fn with_options<N, I, IpS, IS, DS>(mat: &CsMatBase<N, I, IpS, IS, DS>,
+                                           options: &SpyOptions) -> SpyGrid
+    where N: ToPrimitive,
+          I: SpIndex,
+          IpS: Deref<Target=[I]>,
+          IS: Deref<Target=[I]>,
+          DS: Deref<Target=[N]>,
+    {
+        if options.by_magnitude {
+            SpyGrid::magnitude(mat, options.max_dim)
+        }
+        else {
+            SpyGrid::pattern(mat, options.max_dim)
+        }
+    }
+
+    fn empty(shape: (usize, usize), max_dim: usize) -> SpyGrid {
+        assert!(max_dim > 0, "spy plots need at least one pixel");
+        let (rows, cols) = shape;
+        let largest = if rows > cols { rows } else { cols };
+        let block_size = if largest > max_dim {
+            (largest + max_dim - 1) / max_dim
+        }
+        else {
+            1
+        };
+        let grid_rows = (rows + block_size - 1) / block_size;
+        let grid_cols = (cols + block_size - 1) / block_size;
+        SpyGrid {
+            rows: grid_rows,
+            cols: grid_cols,
+            block_size: block_size,
+            cells: vec![None; grid_rows * grid_cols],
+        }
+    }
+
+    fn cell_index(&self, row: usize, col: usize) -> usize {
+        (row / self.block_size) * self.cols + col / self.block_size
+    }
+
+    /// The shape of the grid, as a `(rows, cols)` tuple
+    pub fn shape(&self) -> (usize, usize) {
+        (self.rows, self.cols)
+    }
+
+    /// The number of matrix rows and columns covered by a cell
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// The intensity of the cell at `(row, col)`, or `None` if the
+    /// corresponding block of the matrix has no non-zero.
+    ///
+    /// # Panics
+    ///
+    /// On out of bounds access.
+    pub fn intensity(&self, row: usize, col: usize) -> Option<f64> {
+        assert!(row < self.rows && col < self.cols, "Out of bounds index");
+        self.cells[row * self.cols + col]
+    }
+
+    /// The RGB color of the cell at `(row, col)`: white for empty cells,
+    /// and a blue to red ramp following the intensity otherwise.
+    pub fn color(&self, row: usize, col: usize) -> [u8; 3] {
+        match self.intensity(row, col) {
+            None => [255, 255, 255],
+            Some(intensity) => {
+                let t = intensity.max(0.).min(1.);
+                let red = (255. * t).round() as u8;
+                let blue = (255. * (1. - t)).round() as u8;
+                [red, 0, blue]
+            }
+        }
+    }
+
+    /// Encode this grid as a PNG image, one pixel per cell.
+    pub fn write_png<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut pixels = Vec::with_capacity(3 * self.rows * self.cols);
+        for row in 0..self.rows {
+            for col in 0..self.cols {
+                pixels.extend_from_slice(&self.color(row, col));
+            }
+        }
+        let mut encoder = png::Encoder::new(writer,
+                                            self.cols as u32,
+                                            self.rows as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut png_writer = try!(encoder.write_header().map_err(png_to_io));
+        png_writer.write_image_data(&pixels).map_err(png_to_io)
+    }
+
+    /// Encode this grid as an SVG image, one unit square per cell.
+    pub fn write_svg<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        try!(writeln!(writer,
+                      "<svg xmlns=\"http://www.w3.org/2000/svg\" \
+                       width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" \
+                       shape-rendering=\"crispEdges\">",
+                      w = self.cols,
+                      h = self.rows));
+        try!(writeln!(writer,
+                      "<rect width=\"{}\" height=\"{}\" fill=\"white\"/>",
+                      self.cols,
+                      self.rows));
+        for row in 0..self.rows {
+            for col in 0..self.cols {
+                if self.intensity(row, col).is_none() {
+                    continue;
+                }
+                let [r, g, b] = self.color(row, col);
+                try!(writeln!(writer,
+                              "<rect x=\"{}\" y=\"{}\" width=\"1\" \
+                               height=\"1\" fill=\"#{:02x}{:02x}{:02x}\"/>",
+                              col, row, r, g, b));
+            }
+        }
+        writeln!(writer, "</svg>")
+    }
+}
+
+fn oriented(is_csr: bool, outer: usize, inner: usize) -> (usize, usize) {
+    if is_csr { (outer, inner) } else { (inner, outer) }
+}
+
+fn png_to_io(err: png::EncodingError) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, err)
+}
+
+fn create<P: AsRef<Path>>(path: P) -> io::Result<BufWriter<File>> {
+    File::create(path).map(BufWriter::new)
+}
+
+/// Render the sparsity pattern of `mat` into a PNG file, following
+/// `options`, which can be a `SpyOptions` or the maximum number of pixels
+/// along each dimension.
+pub fn spy_to_png<N, I, IpS, IS, DS, P, O>(mat: &CsMatBase<N, I, IpS, IS, DS>,
+                                           path: P,
+                                           options: O) -> io::Result<()>
+where N: ToPrimitive,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+      P: AsRef<Path>,
+      O: Into<SpyOptions>,
+{
+    let grid = SpyGrid::with_options(mat, &options.into());
+    grid.write_png(try!(create(path)))
+}
+
+/// Render the sparsity pattern of `mat` into an SVG file, following
+/// `options`, which can be a `SpyOptions` or the maximum number of units
+/// along each dimension.
+pub fn spy_to_svg<N, I, IpS, IS, DS, P, O>(mat: &CsMatBase<N, I, IpS, IS, DS>,
+                                           path: P,
+                                           options: O) -> io::Result<()>
+where N: ToPrimitive,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+      P: AsRef<Path>,
+      O: Into<SpyOptions>,
+{
+    let grid = SpyGrid::with_options(mat, &options.into());
+    grid.write_svg(try!(create(path)))
+}
+
+/// Render `mat` into a PNG file, at most `max_dim` pixels wide and high,
+/// color-coding the non-zeros by magnitude.
+pub fn spy_to_png_by_magnitude<N, I, IpS, IS, DS, P>(
+    mat: &CsMatBase<N, I, IpS, IS, DS>,
+    path: P,
+    max_dim: usize) -> io::Result<()>
+where N: ToPrimitive,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+      P: AsRef<Path>,
+{
+    SpyGrid::magnitude(mat, max_dim).write_png(try!(create(path)))
+}
+
+/// Render `mat` into an SVG file, at most `max_dim` units wide and high,
+/// color-coding the non-zeros by magnitude.
+pub fn spy_to_svg_by_magnitude<N, I, IpS, IS, DS, P>(
+    mat: &CsMatBase<N, I, IpS, IS, DS>,
+    path: P,
+    max_dim: usize) -> io::Result<()>
+where N: ToPrimitive,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+      P: AsRef<Path>,
+{
+    SpyGrid::magnitude(mat, max_dim).write_svg(try!(create(path)))
+}
+
+#[cfg(test)]
+mod test {
+    use sparse::CsMat;
+    use test_data::mat1;
+    use super::{SpyGrid, SpyOptions};
+
+    #[test]
+    fn pattern_grid_full_resolution() {
+        let mat = mat1();
+        let grid = SpyGrid::pattern(&mat, 10);
+        assert_eq!(grid.shape(), (5, 5));
+        assert_eq!(grid.block_size(), 1);
+        assert_eq!(grid.intensity(0, 2), Some(1.));
+        assert_eq!(grid.intensity(3, 1), Some(1.));
+        assert_eq!(grid.intensity(0, 0), None);
+        // storage order should not matter
+        assert_eq!(SpyGrid::pattern(&mat.to_csc(), 10), grid);
+    }
+
+    #[test]
+    fn pattern_grid_downsampled() {
+        let eye: CsMat<f64> = CsMat::eye(10);
+        let grid = SpyGrid::pattern(&eye, 3);
+        assert_eq!(grid.block_size(), 4);
+        assert_eq!(grid.shape(), (3, 3));
+        for i in 0..3 {
+            for j in 0..3 {
+                assert_eq!(grid.intensity(i, j).is_some(), i == j);
+            }
+        }
+    }
+
+    #[test]
+    fn magnitude_grid() {
+        let mat = CsMat::new((2, 2),
+                             vec![0, 1, 3],
+                             vec![0, 0, 1],
+                             vec![1., -100., 10.]);
+        let grid = SpyGrid::magnitude(&mat, 2);
+        assert_eq!(grid.intensity(0, 0), Some(0.));
+        assert_eq!(grid.intensity(1, 0), Some(1.));
+        assert_eq!(grid.intensity(1, 1), Some(0.5));
+        assert_eq!(grid.intensity(0, 1), None);
+        assert_eq!(grid.color(1, 0), [255, 0, 0]);
+        assert_eq!(grid.color(0, 0), [0, 0, 255]);
+        assert_eq!(grid.color(0, 1), [255, 255, 255]);
+    }
+
+    #[test]
+    fn grid_with_options() {
+        let mat = mat1();
+        let mut options = SpyOptions::default();
+        assert_eq!(SpyGrid::with_options(&mat, &options),
+                   SpyGrid::pattern(&mat, 512));
+        options.max_dim = 2;
+        options.by_magnitude = true;
+        assert_eq!(SpyGrid::with_options(&mat, &options),
+                   SpyGrid::magnitude(&mat, 2));
+    }
+
+    #[test]
+    fn svg_output() {
+        let eye: CsMat<f64> = CsMat::eye(2);
+        let mut out = Vec::new();
+        SpyGrid::pattern(&eye, 2).write_svg(&mut out).unwrap();
+        let svg = String::from_utf8(out).unwrap();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("<rect x=\"1\" y=\"1\" width=\"1\" height=\"1\" \
+                              fill=\"#ff0000\"/>"));
+        assert_eq!(svg.matches("<rect x=").count(), 2);
+    }
+
+    #[test]
+    fn png_output() {
+        let eye: CsMat<f64> = CsMat::eye(4);
+        let mut out = Vec::new();
+        SpyGrid::pattern(&eye, 4).write_png(&mut out).unwrap();
+        assert_eq!(&out[1..4], b"PNG");
+    }
+}
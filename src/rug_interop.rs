@@ -0,0 +1,170 @@
+///! Interoperability with rug
+///!
+///! This module is available when the `rug` feature is enabled. The
+///! arbitrary precision rationals of rug cannot be used directly as the
+///! scalars of sprs, as they do not implement the traits of `num-traits`,
+///! so this module wraps them in the `Rational` type, which does. Its
+///! arithmetic is exact, which makes it suitable for the exact
+///! factorization `linalg::ExactLu`, and as it is not `Copy`, it can only
+///! be used with the kernels requiring `Clone` scalars.
+///!
+///! # Example
+///!
+///! ```rust
+///! use sprs::CsMat;
+///! use sprs::prod::mul_acc_mat_vec_csr;
+///! use sprs::rug_interop::Rational;
+///! let mat = CsMat::new((2, 2), vec![0, 2, 3], vec![0, 1, 1],
+///!                      vec![Rational::from((1, 3)),
+///!                           Rational::from(1),
+///!                           Rational::from((2, 7))]);
+///! let x = vec![Rational::from(3), Rational::from(7)];
+///! let mut y = vec![Rational::default(), Rational::default()];
+///! mul_acc_mat_vec_csr(mat.view(), &x, &mut y);
+///! assert_eq!(y, vec![Rational::from(8), Rational::from(2)]);
+///! ```
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
+
+use num_traits::{Num, One, Zero};
+use rug;
+
+/// An arbitrary precision rational number, wrapping the rationals of rug
+/// to use them as the scalars of sparse matrices
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Rational(pub rug::Rational);
+
+impl From<rug::Rational> for Rational {
+    fn from(val: rug::Rational) -> Rational {
+        Rational(val)
+    }
+}
+
+/// Conversions from integers, and from `(num, den)` pairs of integers
+/// giving the rational `num / den`, which panic if `den` is zero
+macro_rules! rational_from {
+    ($($from: ty),*) => {
+        $(
+            impl From<$from> for Rational {
+                fn from(val: $from) -> Rational {
+                    Rational(rug::Rational::from(val))
+                }
+            }
+        )*
+    }
+}
+
+rational_from!(i32, i64, (i32, i32), (i64, i64));
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+macro_rules! rational_binop {
+    ($trait: ident, $method: ident) => {
+        impl $trait for Rational {
+            type Output = Rational;
+
+            fn $method(self, rhs: Rational) -> Rational {
+                Rational((self.0).$method(rhs.0))
+            }
+        }
+    }
+}
+
+rational_binop!(Add, add);
+rational_binop!(Sub, sub);
+rational_binop!(Mul, mul);
+rational_binop!(Div, div);
+
+/// The remainder of the division truncated towards zero, as for the
+/// primitive types, ie `lhs - rhs * trunc(lhs / rhs)`.
+impl Rem for Rational {
+    type Output = Rational;
+
+    fn rem(self, rhs: Rational) -> Rational {
+        let quotient = (self.0.clone() / rhs.0.clone()).trunc();
+        Rational(self.0 - rhs.0 * quotient)
+    }
+}
+
+impl Neg for Rational {
+    type Output = Rational;
+
+    fn neg(self) -> Rational {
+        Rational(-self.0)
+    }
+}
+
+impl Zero for Rational {
+    fn zero() -> Rational {
+        Rational(rug::Rational::new())
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.cmp0() == Ordering::Equal
+    }
+}
+
+impl One for Rational {
+    fn one() -> Rational {
+        Rational(rug::Rational::from(1))
+    }
+}
+
+impl Num for Rational {
+    type FromStrRadixErr = rug::rational::ParseRationalError;
+
+    /// Parse a rational written as `num/den` or as an integer
+    fn from_str_radix(src: &str, radix: u32)
+                      -> Result<Rational, Self::FromStrRadixErr> {
+        rug::Rational::from_str_radix(src, radix as i32).map(Rational)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use num_traits::{Num, Zero};
+    use sparse::{CsMat, TriMat};
+    use sparse::linalg::ExactLu;
+    use sparse::prod::mul_acc_mat_vec_csr;
+    use super::Rational;
+
+    #[test]
+    fn rational_arithmetic() {
+        let third = Rational::from((1, 3));
+        let half = Rational::from((1, 2));
+        assert_eq!(third.clone() + half.clone(), Rational::from((5, 6)));
+        assert_eq!(third.clone() - half.clone(), Rational::from((-1, 6)));
+        assert_eq!(third.clone() / half.clone(), Rational::from((2, 3)));
+        assert_eq!(Rational::from((7, 2)) % Rational::from(1), half.clone());
+        assert_eq!(Rational::from((-7, 2)) % Rational::from(1), -half);
+        assert!((third.clone() - third).is_zero());
+        assert_eq!(Rational::from_str_radix("-3/12", 10).unwrap(),
+                   Rational::from((-1, 4)));
+    }
+
+    #[test]
+    fn exact_lu_rug_rationals() {
+        // the Hilbert matrix, notoriously ill conditioned
+        let dim = 6;
+        let mut tri = TriMat::new((dim, dim));
+        for i in 0..dim {
+            for j in 0..dim {
+                tri.add_triplet(i, j, Rational::from((1, (i + j + 1) as i64)));
+            }
+        }
+        let mat: CsMat<Rational> = tri.to_csr();
+        let lu = ExactLu::factorize(&mat).unwrap();
+        let x: Vec<_> = (0..dim).map(|i| Rational::from(i as i64 + 1))
+                                .collect();
+        let mut rhs = vec![Rational::zero(); dim];
+        mul_acc_mat_vec_csr(mat.view(), &x, &mut rhs);
+        assert_eq!(lu.solve_vec(&rhs), x);
+        assert_eq!(&mat * &CsMat::eye(dim), mat);
+    }
+}
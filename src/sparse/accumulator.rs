@@ -0,0 +1,229 @@
+///! Sparse accumulator, the dense scratch space of sparse kernels
+///!
+///! Combining sparse vectors, eg in the Gustavson algorithm for the product
+///! of sparse matrices, is best done by scattering their values in a dense
+///! buffer, then gathering the touched entries back to a sparse vector.
+///! `SparseAccumulator` packages this technique so that custom kernels
+///! get the same machinery: scattering a value and clearing the
+///! accumulator are constant time operations, whatever its dimension, and
+///! gathering costs only the sort of the touched indices.
+///!
+///! # Example
+///!
+///! Linear combination of the rows of a matrix:
+///!
+///! ```rust
+///! use sprs::{CsMat, CsVec, SparseAccumulator};
+///! let mat = CsMat::new((3, 4), vec![0, 2, 3, 5], vec![0, 3, 1, 0, 2],
+///!                      vec![1., 2., 3., 4., 5.]);
+///! let mut spa = SparseAccumulator::new(mat.cols());
+///! for &(row, coeff) in &[(2, 1.), (0, -4.)] {
+///!     spa.scatter_csvec(coeff, mat.outer_view(row).unwrap());
+///! }
+///! let combination: CsVec<f64> = spa.gather_csvec();
+///! assert_eq!(combination, CsVec::new(4, vec![0, 2, 3], vec![0., 5., -8.]));
+///! assert!(spa.is_empty());
+///! ```
+
+use std::vec::Vec;
+use num_traits::Num;
+
+use indexing::SpIndex;
+use sparse::prelude::*;
+
+/// A dense accumulator for the values of a sparse vector of dimension
+/// `dim`, tracking which entries have been touched since it was last
+/// cleared.
+///
+/// The touched entries are recognized by a stamp, which is bumped when
+/// clearing, so that `clear_fast` does not need to reset the values.
+#[derive(Debug, Clone)]
+pub struct SparseAccumulator<N> {
+    values: Vec<N>,
+    stamps: Vec<usize>,
+    stamp: usize,
+    touched: Vec<usize>,
+}
+
+impl<N: Num + Clone> SparseAccumulator<N> {
+    /// Create an empty accumulator for vectors of dimension `dim`
+    pub fn new(dim: usize) -> SparseAccumulator<N> {
+        SparseAccumulator {
+            values: vec![N::zero(); dim],
+            stamps: vec![0; dim],
+            stamp: 1,
+            touched: Vec::new(),
+        }
+    }
+
+    /// The dimension of the accumulated vectors
+    pub fn dim(&self) -> usize {
+        self.values.len()
+    }
+
+    /// The number of entries touched since the last clear
+    pub fn nnz(&self) -> usize {
+        self.touched.len()
+    }
+
+    /// Whether no entry has been touched since the last clear
+    pub fn is_empty(&self) -> bool {
+        self.touched.is_empty()
+    }
+
+    /// The indices touched since the last clear, in the order they were
+    /// first touched
+    pub fn indices(&self) -> &[usize] {
+        &self.touched[..]
+    }
+
+    /// Whether the entry at `index` has been touched since the last clear
+    pub fn contains(&self, index: usize) -> bool {
+        index < self.dim() && self.stamps[index] == self.stamp
+    }
+
+    /// The accumulated value at `index`, if it has been touched
+    pub fn get(&self, index: usize) -> Option<&N> {
+        if self.contains(index) {
+            Some(&self.values[index])
+        } else {
+            None
+        }
+    }
+
+    /// Add `value` to the entry at `index`.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is not smaller than the dimension.
+    pub fn scatter(&mut self, index: usize, value: N) {
+        if index >= self.dim() {
+            panic!("Out of bounds index");
+        }
+        if self.stamps[index] == self.stamp {
+            self.values[index] = self.values[index].clone() + value;
+        } else {
+            self.stamps[index] = self.stamp;
+            self.values[index] = value;
+            self.touched.push(index);
+        }
+    }
+
+    /// Add `alpha * vec` to the accumulator.
+    ///
+    /// # Panics
+    ///
+    /// On dimension mismatch.
+    pub fn scatter_csvec<I>(&mut self, alpha: N, vec: CsVecViewI<N, I>)
+    where I: SpIndex
+    {
+        if vec.dim() != self.dim() {
+            panic!("Dimension mismatch");
+        }
+        for (index, value) in vec.iter() {
+            self.scatter(index, alpha.clone() * value.clone());
+        }
+    }
+
+    /// Append the touched entries to `indices` and `data`, by increasing
+    /// index, then clear the accumulator.
+    ///
+    /// Touched entries are gathered even if their value is zero, eg after
+    /// a cancellation, which keeps the structure of symbolic computations.
+    pub fn gather_sorted<I>(&mut self,
+                            indices: &mut Vec<I>,
+                            data: &mut Vec<N>)
+    where I: SpIndex
+    {
+        self.touched.sort();
+        indices.reserve(self.touched.len());
+        data.reserve(self.touched.len());
+        for &index in &self.touched {
+            indices.push(I::from_usize(index));
+            data.push(self.values[index].clone());
+        }
+        self.clear_fast();
+    }
+
+    /// Gather the touched entries into a sparse vector, then clear the
+    /// accumulator.
+    pub fn gather_csvec<I>(&mut self) -> CsVecI<N, I>
+    where I: SpIndex
+    {
+        let mut indices = Vec::new();
+        let mut data = Vec::new();
+        self.gather_sorted(&mut indices, &mut data);
+        CsVecI {
+            dim: self.dim(),
+            indices: indices,
+            data: data,
+        }
+    }
+
+    /// Forget the touched entries, in constant time.
+    pub fn clear_fast(&mut self) {
+        self.touched.clear();
+        if self.stamp == usize::max_value() {
+            // the stamps could collide after wrapping around
+            for stamp in self.stamps.iter_mut() {
+                *stamp = 0;
+            }
+            self.stamp = 1;
+        } else {
+            self.stamp += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SparseAccumulator;
+    use sparse::CsVec;
+
+    #[test]
+    fn scatter_gather() {
+        let mut spa = SparseAccumulator::new(6);
+        spa.scatter(4, 1.);
+        spa.scatter(1, 2.);
+        spa.scatter(4, -1.);
+        assert_eq!(spa.nnz(), 2);
+        assert_eq!(spa.indices(), &[4, 1]);
+        assert_eq!(spa.get(1), Some(&2.));
+        assert_eq!(spa.get(0), None);
+        let mut indices: Vec<usize> = vec![0];
+        let mut data = vec![7.];
+        spa.gather_sorted(&mut indices, &mut data);
+        assert_eq!(indices, vec![0, 1, 4]);
+        assert_eq!(data, vec![7., 2., 0.]);
+        assert!(spa.is_empty());
+        assert!(!spa.contains(4));
+
+        // stale values are not accumulated after a clear
+        spa.scatter(4, 3.);
+        let vec = CsVec::new(6, vec![0, 4], vec![1., 1.]);
+        spa.scatter_csvec(2., vec.view());
+        let res: CsVec<f64> = spa.gather_csvec();
+        assert_eq!(res, CsVec::new(6, vec![0, 4], vec![2., 5.]));
+    }
+
+    #[test]
+    fn clear_fast_wrapping() {
+        let mut spa = SparseAccumulator::new(3);
+        spa.scatter(2, 1);
+        spa.stamp = usize::max_value();
+        spa.stamps[0] = usize::max_value();
+        assert!(spa.contains(0));
+        spa.clear_fast();
+        assert!(!spa.contains(0));
+        assert!(!spa.contains(2));
+        spa.scatter(0, 5);
+        assert_eq!(spa.get(0), Some(&5));
+    }
+
+    #[test]
+    #[should_panic]
+    fn scatter_out_of_bounds() {
+        let mut spa = SparseAccumulator::new(3);
+        spa.scatter(3, 1.);
+    }
+}
@@ -0,0 +1,297 @@
+///! Assembly of finite element matrices on a fixed sparsity pattern
+///!
+///! In finite element codes, the global matrix is the sum of small dense
+///! element matrices, each scattered on the degrees of freedom of its
+///! element. The sparsity pattern only depends on the connectivity of the
+///! mesh, and is thus usually computed once, while the values are
+///! re-assembled at every time step or non-linear iteration.
+///!
+///! An `Assembler` holds such a pattern and a buffer of values, and
+///! scatter-adds element matrices directly into the compressed storage,
+///! without going through an intermediate triplet matrix. Concurrent
+///! assembly uses per-worker buffers: `worker` creates an assembler sharing
+///! the pattern with its own zeroed values, which can be sent to another
+///! thread, and whose contributions are summed back with `merge`.
+///!
+///! # Example
+///!
+///! ```rust
+///! use std::thread;
+///! use sprs::assemble::Assembler;
+///! // a 1D mesh of 4 linear elements
+///! let elements: Vec<Vec<usize>> = (0..4).map(|e| vec![e, e + 1]).collect();
+///! let mut assembler: Assembler<f64, usize> =
+///!     Assembler::from_elements(5, &elements);
+///! let stiffness = [1., -1., -1., 1.];
+///! let workers: Vec<_> = elements.chunks(2).map(|chunk| {
+///!     let mut worker = assembler.worker();
+///!     let chunk = chunk.to_vec();
+///!     thread::spawn(move || {
+///!         for dofs in &chunk {
+///!             worker.add_element(dofs, &stiffness);
+///!         }
+///!         worker
+///!     })
+///! }).collect();
+///! for worker in workers {
+///!     assembler.merge(&worker.join().unwrap());
+///! }
+///! let mat = assembler.to_csmat();
+///! assert_eq!(mat.get(2, 2), Some(&2.));
+///! assert_eq!(mat.get(2, 3), Some(&-1.));
+///! ```
+
+use std::ops::Deref;
+use std::sync::Arc;
+use num_traits::Num;
+
+use indexing::SpIndex;
+use sparse::prelude::*;
+use sparse::csmat::CompressedStorage::CSR;
+use ::Shape;
+
+/// A CSR sparsity pattern
+#[derive(Debug, PartialEq)]
+struct Pattern<I> {
+    shape: Shape,
+    indptr: Vec<I>,
+    indices: Vec<I>,
+}
+
+impl<I: SpIndex> Pattern<I> {
+    /// The position in the storage of the entry `(row, col)`
+    fn position(&self, row: usize, col: usize) -> Option<usize> {
+        let start = self.indptr[row].index();
+        let end = self.indptr[row + 1].index();
+        self.indices[start..end].binary_search(&I::from_usize(col))
+                                .ok()
+                                .map(|pos| start + pos)
+    }
+}
+
+/// Scatter-add accumulator of element matrices on a fixed CSR pattern
+#[derive(Debug, Clone)]
+pub struct Assembler<N, I: SpIndex> {
+    pattern: Arc<Pattern<I>>,
+    data: Vec<N>,
+}
+
+impl<N, I> Assembler<N, I>
+where N: Num + Copy + Default,
+      I: SpIndex,
+{
+    /// Create the assembler of a square matrix of dimension `nb_dofs` from
+    /// the connectivity of the elements: each element couples all the
+    /// degrees of freedom it lists.
+    ///
+    /// # Panics
+    ///
+    /// If a degree of freedom is out of bounds.
+    pub fn from_elements<E>(nb_dofs: usize, elements: &[E]) -> Assembler<N, I>
+    where E: AsRef<[usize]>
+    {
+        // dof to element adjacency, then the pattern of each row is the
+        // union of the dofs of its elements
+        let mut dof_elements: Vec<Vec<usize>> = vec![Vec::new(); nb_dofs];
+        for (elem, dofs) in elements.iter().enumerate() {
+            for &dof in dofs.as_ref() {
+                assert!(dof < nb_dofs, "Out of bounds degree of freedom");
+                dof_elements[dof].push(elem);
+            }
+        }
+        let mut indptr = Vec::with_capacity(nb_dofs + 1);
+        indptr.push(I::zero());
+        let mut indices = Vec::new();
+        let mut row = Vec::new();
+        for elems in &dof_elements {
+            row.clear();
+            for &elem in elems {
+                row.extend_from_slice(elements[elem].as_ref());
+            }
+            row.sort();
+            row.dedup();
+            indices.extend(row.iter().map(|&col| I::from_usize(col)));
+            indptr.push(I::from_usize(indices.len()));
+        }
+        Assembler::from_parts((nb_dofs, nb_dofs), indptr, indices)
+    }
+
+    /// Create an assembler on the sparsity pattern of a matrix, whose
+    /// values are ignored.
+    pub fn from_pattern<IpS, IS, DS>(mat: &CsMatBase<N, I, IpS, IS, DS>)
+                                     -> Assembler<N, I>
+    where IpS: Deref<Target=[I]>,
+          IS: Deref<Target=[I]>,
+          DS: Deref<Target=[N]>,
+    {
+        let csr;
+        let mat = if mat.is_csr() { mat.view() } else {
+            csr = mat.to_csr();
+            csr.view()
+        };
+        Assembler::from_parts(mat.shape(),
+                              mat.indptr().to_vec(),
+                              mat.indices().to_vec())
+    }
+
+    fn from_parts(shape: Shape, indptr: Vec<I>, indices: Vec<I>)
+                  -> Assembler<N, I> {
+        let nnz = indices.len();
+        Assembler {
+            pattern: Arc::new(Pattern {
+                shape: shape,
+                indptr: indptr,
+                indices: indices,
+            }),
+            data: vec![N::zero(); nnz],
+        }
+    }
+
+    /// The shape of the assembled matrix
+    pub fn shape(&self) -> Shape {
+        self.pattern.shape
+    }
+
+    /// The number of entries of the pattern
+    pub fn nnz(&self) -> usize {
+        self.data.len()
+    }
+
+    /// The accumulated values, in the CSR order of the pattern
+    pub fn data(&self) -> &[N] {
+        &self.data
+    }
+
+    /// Add `val` at `(row, col)`.
+    ///
+    /// # Panics
+    ///
+    /// If `(row, col)` is not in the pattern.
+    pub fn add(&mut self, row: usize, col: usize, val: N) {
+        assert!(row < self.pattern.shape.0, "Out of bounds index");
+        let pos = self.pattern.position(row, col)
+                              .expect("Entry outside of the assembly pattern");
+        self.data[pos] = self.data[pos] + val;
+    }
+
+    /// Scatter-add a dense element matrix, stored in row major order, on
+    /// the degrees of freedom `dofs`: `elem[i * dofs.len() + j]` is added
+    /// at `(dofs[i], dofs[j])`.
+    ///
+    /// # Panics
+    ///
+    /// If `elem.len() != dofs.len()^2` or if an entry is not in the
+    /// pattern.
+    pub fn add_element(&mut self, dofs: &[usize], elem: &[N]) {
+        self.add_element_rect(dofs, dofs, elem);
+    }
+
+    /// Scatter-add a dense rectangular element matrix, stored in row major
+    /// order, at the rows `rows` and columns `cols`.
+    ///
+    /// # Panics
+    ///
+    /// If `elem.len() != rows.len() * cols.len()` or if an entry is not in
+    /// the pattern.
+    pub fn add_element_rect(&mut self,
+                            rows: &[usize],
+                            cols: &[usize],
+                            elem: &[N]) {
+        assert_eq!(elem.len(), rows.len() * cols.len(), "Dimension mismatch");
+        for (&row, elem_row) in rows.iter().zip(elem.chunks(cols.len().max(1))) {
+            for (&col, &val) in cols.iter().zip(elem_row) {
+                self.add(row, col, val);
+            }
+        }
+    }
+
+    /// Reset the values to zero, eg before assembling a new time step
+    pub fn reset(&mut self) {
+        for val in &mut self.data {
+            *val = N::zero();
+        }
+    }
+
+    /// An assembler sharing the pattern of this one, with zero values, to
+    /// accumulate contributions on another thread
+    pub fn worker(&self) -> Assembler<N, I> {
+        Assembler {
+            pattern: self.pattern.clone(),
+            data: vec![N::zero(); self.data.len()],
+        }
+    }
+
+    /// Add the values accumulated by another assembler on the same pattern
+    ///
+    /// # Panics
+    ///
+    /// If the patterns differ.
+    pub fn merge(&mut self, other: &Assembler<N, I>) {
+        assert!(Arc::ptr_eq(&self.pattern, &other.pattern)
+                || self.pattern == other.pattern,
+                "Assemblers do not share the same pattern");
+        for (val, &other) in self.data.iter_mut().zip(&other.data) {
+            *val = *val + other;
+        }
+    }
+
+    /// The assembled matrix, in CSR storage. Entries of the pattern which
+    /// received no contribution are stored as explicit zeros.
+    pub fn to_csmat(&self) -> CsMatI<N, I> {
+        CsMatI {
+            storage: CSR,
+            nrows: self.pattern.shape.0,
+            ncols: self.pattern.shape.1,
+            indptr: self.pattern.indptr.clone(),
+            indices: self.pattern.indices.clone(),
+            data: self.data.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sparse::{CsMat, TriMat};
+    use test_data::mat1;
+    use super::Assembler;
+
+    #[test]
+    fn assemble_matches_triplets() {
+        let elements = vec![vec![0, 2, 3], vec![1, 3], vec![3, 4, 0]];
+        let mut assembler = Assembler::from_elements(5, &elements);
+        let mut tri = TriMat::new((5, 5));
+        for (e, dofs) in elements.iter().enumerate() {
+            let n = dofs.len();
+            let elem: Vec<f64> = (0..n * n).map(|k| (k + e) as f64).collect();
+            assembler.add_element(dofs, &elem);
+            for i in 0..n {
+                for j in 0..n {
+                    tri.add_triplet(dofs[i], dofs[j], elem[i * n + j]);
+                }
+            }
+        }
+        let expected: CsMat<f64> = tri.to_csr();
+        assert_eq!(assembler.to_csmat(), expected);
+
+        assembler.reset();
+        assert!(assembler.data().iter().all(|&x| x == 0.));
+    }
+
+    #[test]
+    fn workers_and_pattern() {
+        let mut assembler = Assembler::from_pattern(&mat1());
+        let mut worker = assembler.worker();
+        worker.add(0, 2, 3.);
+        assembler.add(0, 2, 1.);
+        assembler.merge(&worker);
+        assert_eq!(assembler.to_csmat().get(0, 2), Some(&4.));
+        assert_eq!(assembler.nnz(), mat1().nnz());
+    }
+
+    #[test]
+    #[should_panic]
+    fn outside_pattern() {
+        let mut assembler = Assembler::from_pattern(&mat1());
+        assembler.add(0, 0, 1.);
+    }
+}
@@ -0,0 +1,1094 @@
+///! Dispatch of sparse kernels to optimized external libraries
+///!
+///! The matrix-vector and matrix-matrix products can be computed either by
+///! the native Rust kernels of sprs, or by Intel MKL's sparse BLAS when the
+///! `mkl` feature is enabled. The backend is selected per call with the
+///! `*_with` functions, or through a global policy set with
+///! `set_default_backend`, used by the other functions.
+///!
+///! External backends only support some scalar and index types (`f32` and
+///! `f64` values with `i32` indices for MKL), other types as well as
+///! unavailable backends fall back to the native kernels.
+///!
+///! The `BackendMat` trait abstracts over where the products of a matrix
+///! are computed: it is implemented by the host matrices `CsMatI`, and by
+///! the device matrices `cuda::CudaCsMat` when the `cuda` feature is
+///! enabled, so that code written against it runs on either.
+///!
+///! Sparse factorizations are computed by `factorize_with`, which dispatches
+///! them to SuiteSparse's UMFPACK and CHOLMOD when the `suitesparse` feature
+///! is enabled, for `f64` matrices. The resulting factorizations implement
+///! `linalg::LinearSolver` and can thus be used wherever a native solver is
+///! accepted.
+
+use std::any::Any;
+#[cfg(feature = "mkl")]
+use std::any::TypeId;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use num_traits::{Num, Signed};
+use ndarray::Array2;
+
+use indexing::SpIndex;
+use sparse::prelude::*;
+use sparse::prod;
+use sparse::linalg::{ExactLu, LinearSolver};
+use ::{Shape, SpRes};
+
+/// The implementation used to compute sparse kernels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// The pure Rust kernels of sprs
+    Native,
+    /// Intel MKL's sparse BLAS, requires the `mkl` feature
+    Mkl,
+    /// SuiteSparse's UMFPACK and CHOLMOD, for the factorizations, requires
+    /// the `suitesparse` feature
+    SuiteSparse,
+}
+
+impl Backend {
+    /// Whether this backend has been compiled in
+    pub fn is_available(&self) -> bool {
+        match *self {
+            Backend::Native => true,
+            Backend::Mkl => cfg!(feature = "mkl"),
+            Backend::SuiteSparse => cfg!(feature = "suitesparse"),
+        }
+    }
+
+    fn to_usize(self) -> usize {
+        match self {
+            Backend::Native => 0,
+            Backend::Mkl => 1,
+            Backend::SuiteSparse => 2,
+        }
+    }
+
+    fn from_usize(backend: usize) -> Backend {
+        match backend {
+            1 => Backend::Mkl,
+            2 => Backend::SuiteSparse,
+            _ => Backend::Native,
+        }
+    }
+}
+
+static DEFAULT_BACKEND: AtomicUsize = AtomicUsize::new(0);
+
+/// Set the backend used by the functions of this module which do not take
+/// an explicit backend. The default is `Backend::Native`.
+pub fn set_default_backend(backend: Backend) {
+    DEFAULT_BACKEND.store(backend.to_usize(), Ordering::SeqCst);
+}
+
+/// The backend currently used by default
+pub fn default_backend() -> Backend {
+    Backend::from_usize(DEFAULT_BACKEND.load(Ordering::SeqCst))
+}
+
+/// Reinterpret a slice of `T` as a slice of `U`, if both are the same type
+#[cfg(any(feature = "mkl", feature = "suitesparse"))]
+fn cast_slice<T: Any, U: Any>(slice: &[T]) -> Option<&[U]> {
+    if TypeId::of::<T>() == TypeId::of::<U>() {
+        Some(unsafe { &*(slice as *const [T] as *const [U]) })
+    }
+    else {
+        None
+    }
+}
+
+/// Mutable version of `cast_slice`
+#[cfg(feature = "mkl")]
+fn cast_slice_mut<T: Any, U: Any>(slice: &mut [T]) -> Option<&mut [U]> {
+    if TypeId::of::<T>() == TypeId::of::<U>() {
+        Some(unsafe { &mut *(slice as *mut [T] as *mut [U]) })
+    }
+    else {
+        None
+    }
+}
+
+/// Compute `out = mat * x` with the default backend.
+///
+/// # Panics
+///
+/// On dimension mismatch.
+pub fn mat_vec<N, I>(mat: CsMatViewI<N, I>, x: &[N], out: &mut [N])
+where N: 'static + Num + Copy,
+      I: 'static + SpIndex,
+{
+    mat_vec_with(default_backend(), mat, x, out)
+}
+
+/// Compute `out = mat * x` with the requested backend.
+///
+/// # Panics
+///
+/// On dimension mismatch.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::backend::{mat_vec_with, Backend};
+/// let mat: CsMat<f64> = CsMat::eye(3);
+/// let x = [1., 2., 3.];
+/// let mut out = [0.; 3];
+/// // falls back to the native kernels without the mkl feature
+/// mat_vec_with(Backend::Mkl, mat.view(), &x, &mut out);
+/// assert_eq!(out, x);
+/// ```
+pub fn mat_vec_with<N, I>(backend: Backend,
+                          mat: CsMatViewI<N, I>,
+                          x: &[N],
+                          out: &mut [N])
+where N: 'static + Num + Copy,
+      I: 'static + SpIndex,
+{
+    if mat.cols() != x.len() || mat.rows() != out.len() {
+        panic!("Dimension mismatch");
+    }
+    if backend == Backend::Mkl && mkl_mat_vec(mat.view(), x, out) {
+        return;
+    }
+    for val in out.iter_mut() {
+        *val = N::zero();
+    }
+    if mat.is_csr() {
+        prod::mul_acc_mat_vec_csr(mat, x, out);
+    }
+    else {
+        prod::mul_acc_mat_vec_csc(mat, x, out);
+    }
+}
+
+/// Sparse matrix product with the default backend. The result has the
+/// storage of `lhs`.
+///
+/// # Panics
+///
+/// On dimension mismatch.
+pub fn spgemm<N, I>(lhs: CsMatViewI<N, I>,
+                    rhs: CsMatViewI<N, I>) -> CsMatI<N, I>
+where N: 'static + Num + Copy + Default,
+      I: 'static + SpIndex,
+{
+    spgemm_with(default_backend(), lhs, rhs)
+}
+
+/// Sparse matrix product with the requested backend. The result has the
+/// storage of `lhs`.
+///
+/// # Panics
+///
+/// On dimension mismatch.
+pub fn spgemm_with<N, I>(backend: Backend,
+                         lhs: CsMatViewI<N, I>,
+                         rhs: CsMatViewI<N, I>) -> CsMatI<N, I>
+where N: 'static + Num + Copy + Default,
+      I: 'static + SpIndex,
+{
+    if lhs.cols() != rhs.rows() {
+        panic!("Dimension mismatch");
+    }
+    if backend == Backend::Mkl && lhs.is_csr() {
+        if let Some(res) = mkl_spgemm(lhs.view(), rhs.to_csr().view()) {
+            return res;
+        }
+    }
+    &lhs * &rhs
+}
+
+/// A sparse matrix whose products are computed by a given backend, on
+/// vectors and dense matrices stored where this backend computes.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::backend::BackendMat;
+/// // runs on the GPU when given a `cuda::CudaCsMat`
+/// fn square_apply<M: BackendMat<f64>>(mat: &M, x: &[f64]) -> Vec<f64> {
+///     let square = mat.spgemm(mat);
+///     let x = M::upload_vec(x);
+///     let mut y = M::upload_vec(&vec![0.; mat.shape().0]);
+///     square.mul_vec(&x, &mut y);
+///     M::download_vec(&y)
+/// }
+/// let mat: CsMat<f64> = CsMat::eye(3);
+/// assert_eq!(square_apply(&mat, &[1., 2., 3.]), vec![1., 2., 3.]);
+/// ```
+pub trait BackendMat<N>: Sized {
+    /// The dense vectors multiplied by this matrix
+    type DenseVec;
+    /// The dense matrices multiplied by this matrix
+    type DenseMat;
+
+    /// Transfer a host vector to the backend
+    fn upload_vec(data: &[N]) -> Self::DenseVec;
+
+    /// Transfer a vector of the backend to the host
+    fn download_vec(vec: &Self::DenseVec) -> Vec<N>;
+
+    /// The shape of the matrix
+    fn shape(&self) -> Shape;
+
+    /// The number of non-zero values
+    fn nnz(&self) -> usize;
+
+    /// Compute `y = self * x`
+    ///
+    /// # Panics
+    ///
+    /// On dimension mismatch.
+    fn mul_vec(&self, x: &Self::DenseVec, y: &mut Self::DenseVec);
+
+    /// Compute `out = self * rhs`
+    ///
+    /// # Panics
+    ///
+    /// On dimension mismatch.
+    fn mul_dense(&self, rhs: &Self::DenseMat, out: &mut Self::DenseMat);
+
+    /// Compute the sparse product `self * rhs`
+    ///
+    /// # Panics
+    ///
+    /// On dimension mismatch.
+    fn spgemm(&self, rhs: &Self) -> Self;
+}
+
+/// Products computed on the host by the native kernels
+impl<N, I> BackendMat<N> for CsMatI<N, I>
+where N: Num + Clone + Default,
+      I: SpIndex,
+{
+    type DenseVec = Vec<N>;
+    type DenseMat = Array2<N>;
+
+    fn upload_vec(data: &[N]) -> Vec<N> {
+        data.to_vec()
+    }
+
+    fn download_vec(vec: &Vec<N>) -> Vec<N> {
+        vec.clone()
+    }
+
+    fn shape(&self) -> Shape {
+        CsMatBase::shape(self)
+    }
+
+    fn nnz(&self) -> usize {
+        CsMatBase::nnz(self)
+    }
+
+    fn mul_vec(&self, x: &Vec<N>, y: &mut Vec<N>) {
+        for val in y.iter_mut() {
+            *val = N::zero();
+        }
+        if self.is_csr() {
+            prod::mul_acc_mat_vec_csr(self.view(), x, y);
+        }
+        else {
+            prod::mul_acc_mat_vec_csc(self.view(), x, y);
+        }
+    }
+
+    fn mul_dense(&self, rhs: &Array2<N>, out: &mut Array2<N>) {
+        if out.shape() != [self.rows(), rhs.shape()[1]] {
+            panic!("Dimension mismatch");
+        }
+        out.assign(&(self * rhs));
+    }
+
+    fn spgemm(&self, rhs: &CsMatI<N, I>) -> CsMatI<N, I> {
+        self * rhs
+    }
+}
+
+/// The factorizations available through `factorize_with`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Factorization {
+    /// LU factorization of a general square matrix
+    Lu,
+    /// Cholesky factorization of a symmetric positive definite matrix, of
+    /// which only the lower triangle is read
+    Cholesky,
+}
+
+/// Factorize a square matrix with the default backend, see
+/// `factorize_with`.
+pub fn factorize<N, I>(kind: Factorization,
+                       mat: CsMatViewI<N, I>)
+                       -> SpRes<Box<dyn LinearSolver<N>>>
+where N: 'static + Num + Clone + Signed + PartialOrd,
+      I: 'static + SpIndex,
+{
+    factorize_with(default_backend(), kind, mat)
+}
+
+/// Factorize a square matrix with the requested backend, giving a solver
+/// for the linear systems involving this matrix.
+///
+/// With `Backend::SuiteSparse`, `f64` matrices are factorized by UMFPACK
+/// for `Factorization::Lu` and by CHOLMOD for `Factorization::Cholesky`.
+/// The other backends and scalar types fall back to the native
+/// `linalg::ExactLu::factorize_partial_pivoting`, which selects its pivots
+/// by magnitude.
+///
+/// # Errors
+///
+/// - `SingularMatrix` if the matrix is singular
+/// - `NotPositiveDefinite` if CHOLMOD meets a matrix which is not positive
+///   definite
+///
+/// # Panics
+///
+/// If the matrix is not square.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::backend::{factorize_with, Backend, Factorization};
+/// use sprs::linalg::LinearSolver;
+/// // lower triangle of | 4 1 |
+/// //                   | 1 3 |
+/// let lower = CsMat::new((2, 2), vec![0, 1, 3], vec![0, 0, 1],
+///                        vec![4f64, 1., 3.]);
+/// // falls back to the native factorization without the suitesparse
+/// // feature
+/// let chol = factorize_with(Backend::SuiteSparse, Factorization::Cholesky,
+///                           lower.view()).unwrap();
+/// let mut x = [0.; 2];
+/// chol.solve(&[5., 4.], &mut x);
+/// assert!((x[0] - 1.).abs() < 1e-12 && (x[1] - 1.).abs() < 1e-12);
+/// ```
+pub fn factorize_with<N, I>(backend: Backend,
+                            kind: Factorization,
+                            mat: CsMatViewI<N, I>)
+                            -> SpRes<Box<dyn LinearSolver<N>>>
+where N: 'static + Num + Clone + Signed + PartialOrd,
+      I: 'static + SpIndex,
+{
+    if mat.rows() != mat.cols() {
+        panic!("Dimension mismatch");
+    }
+    if backend == Backend::SuiteSparse {
+        if let Some(res) = suitesparse_factorize(kind, mat.view()) {
+            return res;
+        }
+    }
+    let lu = match kind {
+        Factorization::Lu => try!(ExactLu::factorize_partial_pivoting(&mat)),
+        Factorization::Cholesky => {
+            let sym = symmetric_from_lower(mat);
+            try!(ExactLu::factorize_partial_pivoting(&sym))
+        },
+    };
+    Ok(Box::new(lu))
+}
+
+/// The symmetric matrix whose lower triangle is the one of `lower`
+fn symmetric_from_lower<N, I>(lower: CsMatViewI<N, I>) -> CsMatI<N, I>
+where N: Num + Clone,
+      I: SpIndex,
+{
+    let mut tri = TriMatI::with_capacity(lower.shape(), 2 * lower.nnz());
+    for (row, col, val) in lower.iter() {
+        if col <= row {
+            tri.add_triplet(row, col, val.clone());
+        }
+        if col < row {
+            tri.add_triplet(col, row, val.clone());
+        }
+    }
+    if lower.is_csr() {
+        tri.to_csr()
+    }
+    else {
+        tri.to_csc()
+    }
+}
+
+#[cfg(not(feature = "mkl"))]
+fn mkl_mat_vec<N, I>(_: CsMatViewI<N, I>, _: &[N], _: &mut [N]) -> bool
+where N: Any,
+      I: Any + SpIndex,
+{
+    false
+}
+
+#[cfg(not(feature = "mkl"))]
+fn mkl_spgemm<N, I>(_: CsMatViewI<N, I>, _: CsMatViewI<N, I>)
+                    -> Option<CsMatI<N, I>>
+where N: Any,
+      I: Any + SpIndex,
+{
+    None
+}
+
+#[cfg(not(feature = "suitesparse"))]
+fn suitesparse_factorize<N, I>(_: Factorization, _: CsMatViewI<N, I>)
+                               -> Option<SpRes<Box<dyn LinearSolver<N>>>>
+where N: Any,
+      I: Any + SpIndex,
+{
+    None
+}
+
+/// Foreign code does not check the bounds of its accesses, and the
+/// structure of a matrix may not have been validated by its constructor, so
+/// it is fully checked before being handed to it.
+#[cfg(any(feature = "mkl", feature = "suitesparse"))]
+fn ensure_valid<N, I: SpIndex>(mat: &CsMatViewI<N, I>) {
+    mat.check_compressed_structure().unwrap();
+}
+
+/// Matrix-vector product through MKL, returning false if the types are not
+/// supported
+#[cfg(feature = "mkl")]
+fn mkl_mat_vec<N, I>(mat: CsMatViewI<N, I>, x: &[N], out: &mut [N]) -> bool
+where N: 'static + Copy,
+      I: 'static + SpIndex,
+{
+    let indptr = match cast_slice::<I, i32>(mat.indptr()) {
+        Some(indptr) => indptr,
+        None => return false,
+    };
+    ensure_valid(&mat);
+    let indices = cast_slice::<I, i32>(mat.indices()).unwrap();
+    if let (Some(data), Some(x), Some(out)) = (
+        cast_slice::<N, f64>(mat.data()),
+        cast_slice::<N, f64>(x),
+        cast_slice_mut::<N, f64>(out))
+    {
+        mkl::mat_vec_f64(mat.is_csr(), mat.shape(), indptr, indices, data,
+                         x, out);
+        return true;
+    }
+    if let (Some(data), Some(x), Some(out)) = (
+        cast_slice::<N, f32>(mat.data()),
+        cast_slice::<N, f32>(x),
+        cast_slice_mut::<N, f32>(out))
+    {
+        mkl::mat_vec_f32(mat.is_csr(), mat.shape(), indptr, indices, data,
+                         x, out);
+        return true;
+    }
+    false
+}
+
+/// CSR matrix product through MKL, returning None if the types are not
+/// supported
+#[cfg(feature = "mkl")]
+fn mkl_spgemm<N, I>(lhs: CsMatViewI<N, I>, rhs: CsMatViewI<N, I>)
+                    -> Option<CsMatI<N, I>>
+where N: 'static + Copy,
+      I: 'static + SpIndex,
+{
+    if TypeId::of::<N>() != TypeId::of::<f64>()
+       || TypeId::of::<I>() != TypeId::of::<i32>() {
+        return None;
+    }
+    ensure_valid(&lhs);
+    ensure_valid(&rhs);
+    let lhs_slices = (cast_slice::<I, i32>(lhs.indptr()).unwrap(),
+                      cast_slice::<I, i32>(lhs.indices()).unwrap(),
+                      cast_slice::<N, f64>(lhs.data()).unwrap());
+    let rhs_slices = (cast_slice::<I, i32>(rhs.indptr()).unwrap(),
+                      cast_slice::<I, i32>(rhs.indices()).unwrap(),
+                      cast_slice::<N, f64>(rhs.data()).unwrap());
+    let (indptr, indices, data) = mkl::spgemm_f64((lhs.rows(), lhs.cols()),
+                                                  lhs_slices,
+                                                  rhs.cols(),
+                                                  rhs_slices);
+    let mut res = Some(CsMatI::new((lhs.rows(), rhs.cols()),
+                                   indptr, indices, data));
+    (&mut res as &mut dyn Any).downcast_mut::<Option<CsMatI<N, I>>>()
+                              .and_then(|res| res.take())
+}
+
+/// Factorization through SuiteSparse, returning None if the types are not
+/// supported
+#[cfg(feature = "suitesparse")]
+fn suitesparse_factorize<N, I>(kind: Factorization, mat: CsMatViewI<N, I>)
+                               -> Option<SpRes<Box<dyn LinearSolver<N>>>>
+where N: 'static + Clone,
+      I: 'static + SpIndex,
+{
+    use std::os::raw::c_int;
+    let data = match cast_slice::<N, f64>(mat.data()) {
+        Some(data) => data,
+        None => return None,
+    };
+    if mat.nnz() > c_int::max_value() as usize {
+        return None;
+    }
+    ensure_valid(&mat);
+    let indptr: Vec<c_int> = mat.indptr().iter()
+                                         .map(|i| i.index() as c_int)
+                                         .collect();
+    let indices: Vec<c_int> = mat.indices().iter()
+                                           .map(|i| i.index() as c_int)
+                                           .collect();
+    let res = match kind {
+        Factorization::Lu => {
+            suitesparse::umfpack_lu(mat.is_csr(), mat.rows(), indptr,
+                                    indices, data.to_vec())
+                        .map(|lu| Box::new(lu) as Box<dyn LinearSolver<f64>>)
+        },
+        Factorization::Cholesky => {
+            suitesparse::cholmod_cholesky(mat.is_csr(), mat.rows(), &indptr,
+                                          &indices, data)
+                        .map(|chol| {
+                            Box::new(chol) as Box<dyn LinearSolver<f64>>
+                        })
+        },
+    };
+    let mut res = Some(res);
+    (&mut res as &mut dyn Any)
+        .downcast_mut::<Option<SpRes<Box<dyn LinearSolver<N>>>>>()
+        .and_then(|res| res.take())
+}
+
+#[cfg(feature = "suitesparse")]
+mod suitesparse {
+    //! Factorizations through UMFPACK and CHOLMOD, with `int` indices. The
+    //! CSR arrays of a matrix are the CSC arrays of its transpose, so both
+    //! storages are handed to these CSC libraries without conversion.
+    use std::cell::RefCell;
+    use std::os::raw::{c_int, c_void};
+    use std::ptr;
+    use std::slice;
+
+    use suitesparse_umfpack_sys as umfpack;
+    use suitesparse_cholmod_sys as cholmod;
+
+    use errors::SprsError;
+    use sparse::linalg::LinearSolver;
+    use ::SpRes;
+
+    /// LU factorization by UMFPACK. The matrix is needed by UMFPACK to
+    /// solve systems, so its arrays are kept along its factorization.
+    pub struct UmfpackLu {
+        dim: usize,
+        /// whether the arrays are the CSC arrays of the transpose
+        transposed: bool,
+        indptr: Vec<c_int>,
+        indices: Vec<c_int>,
+        data: Vec<f64>,
+        numeric: *mut c_void,
+    }
+
+    impl Drop for UmfpackLu {
+        fn drop(&mut self) {
+            unsafe {
+                umfpack::umfpack_di_free_numeric(&mut self.numeric);
+            }
+        }
+    }
+
+    pub fn umfpack_lu(is_csr: bool,
+                      dim: usize,
+                      indptr: Vec<c_int>,
+                      indices: Vec<c_int>,
+                      data: Vec<f64>) -> SpRes<UmfpackLu> {
+        let n = dim as c_int;
+        let mut info = [0.; umfpack::UMFPACK_INFO];
+        let mut symbolic = ptr::null_mut();
+        let mut numeric = ptr::null_mut();
+        let status = unsafe {
+            let status = umfpack::umfpack_di_symbolic(n, n,
+                                                      indptr.as_ptr(),
+                                                      indices.as_ptr(),
+                                                      data.as_ptr(),
+                                                      &mut symbolic,
+                                                      ptr::null(),
+                                                      info.as_mut_ptr());
+            if status == umfpack::UMFPACK_OK {
+                let status = umfpack::umfpack_di_numeric(indptr.as_ptr(),
+                                                         indices.as_ptr(),
+                                                         data.as_ptr(),
+                                                         symbolic,
+                                                         &mut numeric,
+                                                         ptr::null(),
+                                                         info.as_mut_ptr());
+                umfpack::umfpack_di_free_symbolic(&mut symbolic);
+                status
+            }
+            else {
+                status
+            }
+        };
+        // built before checking the status, to free the numeric
+        // factorization on errors
+        let lu = UmfpackLu {
+            dim: dim,
+            transposed: is_csr,
+            indptr: indptr,
+            indices: indices,
+            data: data,
+            numeric: numeric,
+        };
+        match status {
+            umfpack::UMFPACK_OK => Ok(lu),
+            umfpack::UMFPACK_WARNING_SINGULAR_MATRIX => {
+                Err(SprsError::SingularMatrix)
+            },
+            _ => panic!("UMFPACK error {}", status),
+        }
+    }
+
+    impl UmfpackLu {
+        fn solve_system(&self, transpose: bool, rhs: &[f64], x: &mut [f64]) {
+            if rhs.len() != self.dim || x.len() != self.dim {
+                panic!("Dimension mismatch");
+            }
+            let sys = if transpose != self.transposed { umfpack::UMFPACK_AT }
+                      else { umfpack::UMFPACK_A };
+            let mut info = [0.; umfpack::UMFPACK_INFO];
+            let status = unsafe {
+                umfpack::umfpack_di_solve(sys,
+                                          self.indptr.as_ptr(),
+                                          self.indices.as_ptr(),
+                                          self.data.as_ptr(),
+                                          x.as_mut_ptr(),
+                                          rhs.as_ptr(),
+                                          self.numeric,
+                                          ptr::null(),
+                                          info.as_mut_ptr())
+            };
+            assert_eq!(status, umfpack::UMFPACK_OK, "UMFPACK error");
+        }
+    }
+
+    impl LinearSolver<f64> for UmfpackLu {
+        fn dim(&self) -> usize {
+            self.dim
+        }
+
+        fn solve(&self, rhs: &[f64], x: &mut [f64]) {
+            self.solve_system(false, rhs, x);
+        }
+
+        fn has_transpose_solve(&self) -> bool {
+            true
+        }
+
+        fn solve_transpose(&self, rhs: &[f64], x: &mut [f64]) {
+            self.solve_system(true, rhs, x);
+        }
+    }
+
+    /// Cholesky factorization by CHOLMOD
+    pub struct CholmodCholesky {
+        dim: usize,
+        factor: *mut cholmod::cholmod_factor,
+        /// CHOLMOD uses its common as a workspace when solving
+        common: RefCell<Box<cholmod::cholmod_common>>,
+    }
+
+    impl Drop for CholmodCholesky {
+        fn drop(&mut self) {
+            let common = &mut **self.common.get_mut();
+            unsafe {
+                cholmod::cholmod_free_factor(&mut self.factor, common);
+                cholmod::cholmod_finish(common);
+            }
+        }
+    }
+
+    /// Factorize the matrix whose lower triangle is given, which is the
+    /// upper triangle of its transpose for CSR arrays.
+    pub fn cholmod_cholesky(is_csr: bool,
+                            dim: usize,
+                            indptr: &[c_int],
+                            indices: &[c_int],
+                            data: &[f64]) -> SpRes<CholmodCholesky> {
+        // CHOLMOD does not modify the matrix it factorizes
+        let mut mat = cholmod::cholmod_sparse {
+            nrow: dim,
+            ncol: dim,
+            nzmax: data.len(),
+            p: indptr.as_ptr() as *mut c_void,
+            i: indices.as_ptr() as *mut c_void,
+            nz: ptr::null_mut(),
+            x: data.as_ptr() as *mut c_void,
+            z: ptr::null_mut(),
+            stype: if is_csr { 1 } else { -1 },
+            itype: cholmod::CHOLMOD_INT,
+            xtype: cholmod::CHOLMOD_REAL,
+            dtype: cholmod::CHOLMOD_DOUBLE,
+            sorted: 1,
+            packed: 1,
+        };
+        let mut common = Box::new(cholmod::cholmod_common::default());
+        unsafe {
+            assert!(cholmod::cholmod_start(&mut *common) != 0,
+                    "CHOLMOD error");
+            let factor = cholmod::cholmod_analyze(&mut mat, &mut *common);
+            let mut chol = CholmodCholesky {
+                dim: dim,
+                factor: factor,
+                common: RefCell::new(common),
+            };
+            assert!(!factor.is_null(), "CHOLMOD error");
+            cholmod::cholmod_factorize(&mut mat, factor,
+                                       &mut **chol.common.get_mut());
+            if (*factor).minor < dim {
+                return Err(SprsError::NotPositiveDefinite);
+            }
+            Ok(chol)
+        }
+    }
+
+    impl LinearSolver<f64> for CholmodCholesky {
+        fn dim(&self) -> usize {
+            self.dim
+        }
+
+        fn solve(&self, rhs: &[f64], x: &mut [f64]) {
+            if rhs.len() != self.dim || x.len() != self.dim {
+                panic!("Dimension mismatch");
+            }
+            let mut common = self.common.borrow_mut();
+            // CHOLMOD does not modify the right hand side
+            let mut rhs = cholmod::cholmod_dense {
+                nrow: self.dim,
+                ncol: 1,
+                nzmax: self.dim,
+                d: self.dim,
+                x: rhs.as_ptr() as *mut c_void,
+                z: ptr::null_mut(),
+                xtype: cholmod::CHOLMOD_REAL,
+                dtype: cholmod::CHOLMOD_DOUBLE,
+            };
+            unsafe {
+                let mut sol = cholmod::cholmod_solve(cholmod::CHOLMOD_A,
+                                                     self.factor,
+                                                     &mut rhs,
+                                                     &mut **common);
+                assert!(!sol.is_null(), "CHOLMOD error");
+                x.copy_from_slice(slice::from_raw_parts((*sol).x as *const f64,
+                                                        self.dim));
+                cholmod::cholmod_free_dense(&mut sol, &mut **common);
+            }
+        }
+
+        fn has_transpose_solve(&self) -> bool {
+            true
+        }
+
+        fn solve_transpose(&self, rhs: &[f64], x: &mut [f64]) {
+            // the factorized matrix is symmetric
+            self.solve(rhs, x);
+        }
+    }
+}
+
+#[cfg(feature = "mkl")]
+mod mkl {
+    //! Bindings to the inspector-executor sparse BLAS of MKL, for the
+    //! `LP64` interface with 32 bits integers.
+    use std::os::raw::c_int;
+    use std::ptr;
+    use std::slice;
+
+    #[allow(non_camel_case_types)]
+    type sparse_matrix_t = *mut u8;
+
+    const SPARSE_STATUS_SUCCESS: c_int = 0;
+    const SPARSE_INDEX_BASE_ZERO: c_int = 0;
+    const SPARSE_OPERATION_NON_TRANSPOSE: c_int = 10;
+    const SPARSE_OPERATION_TRANSPOSE: c_int = 11;
+    const SPARSE_MATRIX_TYPE_GENERAL: c_int = 20;
+    const SPARSE_FILL_MODE_FULL: c_int = 42;
+    const SPARSE_DIAG_NON_UNIT: c_int = 50;
+
+    #[repr(C)]
+    #[allow(non_camel_case_types)]
+    struct matrix_descr {
+        kind: c_int,
+        mode: c_int,
+        diag: c_int,
+    }
+
+    #[link(name = "mkl_rt")]
+    extern "C" {
+        fn mkl_sparse_d_create_csr(a: *mut sparse_matrix_t,
+                                   indexing: c_int,
+                                   rows: c_int,
+                                   cols: c_int,
+                                   rows_start: *const c_int,
+                                   rows_end: *const c_int,
+                                   col_indx: *const c_int,
+                                   values: *const f64) -> c_int;
+        fn mkl_sparse_s_create_csr(a: *mut sparse_matrix_t,
+                                   indexing: c_int,
+                                   rows: c_int,
+                                   cols: c_int,
+                                   rows_start: *const c_int,
+                                   rows_end: *const c_int,
+                                   col_indx: *const c_int,
+                                   values: *const f32) -> c_int;
+        fn mkl_sparse_d_mv(operation: c_int,
+                           alpha: f64,
+                           a: sparse_matrix_t,
+                           descr: matrix_descr,
+                           x: *const f64,
+                           beta: f64,
+                           y: *mut f64) -> c_int;
+        fn mkl_sparse_s_mv(operation: c_int,
+                           alpha: f32,
+                           a: sparse_matrix_t,
+                           descr: matrix_descr,
+                           x: *const f32,
+                           beta: f32,
+                           y: *mut f32) -> c_int;
+        fn mkl_sparse_spmm(operation: c_int,
+                           a: sparse_matrix_t,
+                           b: sparse_matrix_t,
+                           c: *mut sparse_matrix_t) -> c_int;
+        fn mkl_sparse_order(a: sparse_matrix_t) -> c_int;
+        fn mkl_sparse_d_export_csr(source: sparse_matrix_t,
+                                   indexing: *mut c_int,
+                                   rows: *mut c_int,
+                                   cols: *mut c_int,
+                                   rows_start: *mut *mut c_int,
+                                   rows_end: *mut *mut c_int,
+                                   col_indx: *mut *mut c_int,
+                                   values: *mut *mut f64) -> c_int;
+        fn mkl_sparse_destroy(a: sparse_matrix_t) -> c_int;
+    }
+
+    fn check(status: c_int) {
+        assert_eq!(status, SPARSE_STATUS_SUCCESS, "MKL sparse BLAS error");
+    }
+
+    fn general() -> matrix_descr {
+        matrix_descr {
+            kind: SPARSE_MATRIX_TYPE_GENERAL,
+            mode: SPARSE_FILL_MODE_FULL,
+            diag: SPARSE_DIAG_NON_UNIT,
+        }
+    }
+
+    /// The MKL handle of a compressed matrix, and the operation to apply
+    /// to it, CSC matrices being handled as transposed CSR matrices
+    macro_rules! create_handle {
+        ($create: ident, $is_csr: expr, $shape: expr, $indptr: expr,
+         $indices: expr, $data: expr) => ({
+            let (outer, inner) = if $is_csr { $shape }
+                                 else { ($shape.1, $shape.0) };
+            let mut handle = ptr::null_mut();
+            check($create(&mut handle,
+                          SPARSE_INDEX_BASE_ZERO,
+                          outer as c_int,
+                          inner as c_int,
+                          $indptr.as_ptr(),
+                          $indptr[1..].as_ptr(),
+                          $indices.as_ptr(),
+                          $data.as_ptr()));
+            let op = if $is_csr { SPARSE_OPERATION_NON_TRANSPOSE }
+                     else { SPARSE_OPERATION_TRANSPOSE };
+            (handle, op)
+        })
+    }
+
+    pub fn mat_vec_f64(is_csr: bool, shape: (usize, usize),
+                       indptr: &[i32], indices: &[i32], data: &[f64],
+                       x: &[f64], out: &mut [f64]) {
+        unsafe {
+            let (handle, op) = create_handle!(mkl_sparse_d_create_csr,
+                                              is_csr, shape, indptr,
+                                              indices, data);
+            let status = mkl_sparse_d_mv(op, 1., handle, general(),
+                                         x.as_ptr(), 0., out.as_mut_ptr());
+            mkl_sparse_destroy(handle);
+            check(status);
+        }
+    }
+
+    pub fn mat_vec_f32(is_csr: bool, shape: (usize, usize),
+                       indptr: &[i32], indices: &[i32], data: &[f32],
+                       x: &[f32], out: &mut [f32]) {
+        unsafe {
+            let (handle, op) = create_handle!(mkl_sparse_s_create_csr,
+                                              is_csr, shape, indptr,
+                                              indices, data);
+            let status = mkl_sparse_s_mv(op, 1., handle, general(),
+                                         x.as_ptr(), 0., out.as_mut_ptr());
+            mkl_sparse_destroy(handle);
+            check(status);
+        }
+    }
+
+    /// Product of two CSR matrices, returning the CSR structure of the
+    /// result with sorted indices
+    pub fn spgemm_f64(lhs_shape: (usize, usize),
+                      lhs: (&[i32], &[i32], &[f64]),
+                      rhs_cols: usize,
+                      rhs: (&[i32], &[i32], &[f64]))
+                      -> (Vec<i32>, Vec<i32>, Vec<f64>) {
+        unsafe {
+            let (a, _) = create_handle!(mkl_sparse_d_create_csr, true,
+                                        lhs_shape, lhs.0, lhs.1, lhs.2);
+            let (b, _) = create_handle!(mkl_sparse_d_create_csr, true,
+                                        (lhs_shape.1, rhs_cols),
+                                        rhs.0, rhs.1, rhs.2);
+            let mut c = ptr::null_mut();
+            check(mkl_sparse_spmm(SPARSE_OPERATION_NON_TRANSPOSE, a, b,
+                                  &mut c));
+            check(mkl_sparse_order(c));
+            let (mut indexing, mut rows, mut cols) = (0, 0, 0);
+            let (mut starts, mut ends) = (ptr::null_mut(), ptr::null_mut());
+            let (mut indices, mut values) = (ptr::null_mut(),
+                                             ptr::null_mut());
+            check(mkl_sparse_d_export_csr(c, &mut indexing, &mut rows,
+                                          &mut cols, &mut starts, &mut ends,
+                                          &mut indices, &mut values));
+            let rows = rows as usize;
+            let starts = slice::from_raw_parts(starts, rows);
+            let ends = slice::from_raw_parts(ends, rows);
+            // the exported rows are not necessarily contiguous
+            let mut res_indptr = Vec::with_capacity(rows + 1);
+            res_indptr.push(0);
+            let mut res_indices = Vec::new();
+            let mut res_data = Vec::new();
+            for (&start, &end) in starts.iter().zip(ends.iter()) {
+                let len = (end - start) as usize;
+                let start = start as isize;
+                res_indices.extend_from_slice(
+                    slice::from_raw_parts(indices.offset(start), len));
+                res_data.extend_from_slice(
+                    slice::from_raw_parts(values.offset(start), len));
+                res_indptr.push(res_indices.len() as i32);
+            }
+            mkl_sparse_destroy(c);
+            mkl_sparse_destroy(b);
+            mkl_sparse_destroy(a);
+            (res_indptr, res_indices, res_data)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sparse::{CsMat, CsMatI, TriMat};
+    use errors::SprsError;
+    use test_data::{mat1, mat2, mat1_csc, mat1_matprod_mat2, laplacian_1d};
+    use super::{Backend, BackendMat, Factorization, mat_vec_with,
+                spgemm_with, factorize_with};
+
+    #[test]
+    fn backends_agree() {
+        let x = [1., -2., 3., 0.5, 2.];
+        let mut expected = [0.; 5];
+        mat_vec_with(Backend::Native, mat1().view(), &x, &mut expected);
+        let mut out = [1.; 5];
+        mat_vec_with(Backend::Mkl, mat1_csc().view(), &x, &mut out);
+        assert_eq!(out, expected);
+
+        let mat1_i32: CsMatI<f64, i32> = mat1().to_other_types();
+        let mat2_i32: CsMatI<f64, i32> = mat2().to_other_types();
+        let prod = spgemm_with(Backend::Mkl, mat1_i32.view(), mat2_i32.view());
+        assert_eq!(prod, mat1_matprod_mat2().to_other_types());
+        let prod = spgemm_with(Backend::Native, mat1().view(), mat2().view());
+        assert_eq!(prod, mat1_matprod_mat2());
+    }
+
+    #[test]
+    fn unavailable_backends_fall_back() {
+        assert!(Backend::Native.is_available());
+        assert_eq!(Backend::Mkl.is_available(), cfg!(feature = "mkl"));
+        assert_eq!(Backend::SuiteSparse.is_available(),
+                   cfg!(feature = "suitesparse"));
+        for &backend in &[Backend::Native, Backend::Mkl,
+                          Backend::SuiteSparse] {
+            let mut out = [0.; 5];
+            mat_vec_with(backend, mat1().view(), &[1.; 5], &mut out);
+            assert_eq!(out, [7., 7., 5., 8., 7.]);
+        }
+    }
+
+    /// `mat * mat * x`, computed wherever `M` computes
+    fn square_apply<M: BackendMat<f64>>(mat: &M, x: &[f64]) -> Vec<f64> {
+        let square = mat.spgemm(mat);
+        assert!(square.nnz() >= mat.nnz());
+        let x = M::upload_vec(x);
+        let mut y = M::upload_vec(&vec![1.; mat.shape().0]);
+        square.mul_vec(&x, &mut y);
+        M::download_vec(&y)
+    }
+
+    #[test]
+    fn host_backend_mat() {
+        let mat = mat1();
+        let x = [1., -2., 3., 0.5, 2.];
+        let mut expected = [0.; 5];
+        let square = &mat * &mat;
+        mat_vec_with(Backend::Native, square.view(), &x, &mut expected);
+        assert_eq!(square_apply(&mat, &x), expected.to_vec());
+        assert_eq!(square_apply(&mat.to_csc(), &x), expected.to_vec());
+
+        let rhs = ::ndarray::Array2::from_shape_fn((5, 2), |(i, j)| {
+            (i + 3 * j) as f64
+        });
+        let mut out = ::ndarray::Array2::ones((5, 2));
+        BackendMat::mul_dense(&mat, &rhs, &mut out);
+        assert_eq!(out, &mat * &rhs);
+    }
+
+    #[test]
+    fn factorizations() {
+        let mat = laplacian_1d(6);
+        let x = [1., -2., 3., 0.5, 2., -1.];
+        let mut rhs = [0.; 6];
+        mat_vec_with(Backend::Native, mat.view(), &x, &mut rhs);
+        let mut lower = TriMat::new((6, 6));
+        for (row, col, &val) in mat.iter() {
+            if col <= row {
+                lower.add_triplet(row, col, val);
+            }
+        }
+        let factorized = [
+            (Factorization::Lu, mat.clone()),
+            (Factorization::Lu, mat.to_csc()),
+            (Factorization::Cholesky, lower.to_csr()),
+            (Factorization::Cholesky, lower.to_csc()),
+        ];
+        for &backend in &[Backend::Native, Backend::SuiteSparse] {
+            for &(kind, ref mat) in &factorized {
+                let solver = factorize_with(backend, kind, mat.view())
+                    .unwrap();
+                assert_eq!(solver.dim(), 6);
+                let mut sol = [0.; 6];
+                solver.solve(&rhs, &mut sol);
+                for (s, e) in sol.iter().zip(x.iter()) {
+                    assert!((s - e).abs() < 1e-12);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn factorization_errors() {
+        // | 1 2 |
+        // | 2 4 |
+        let singular = CsMat::new((2, 2), vec![0, 2, 4], vec![0, 1, 0, 1],
+                                  vec![1., 2., 2., 4.]);
+        for &backend in &[Backend::Native, Backend::SuiteSparse] {
+            let res = factorize_with(backend, Factorization::Lu,
+                                     singular.view());
+            assert_eq!(res.err(), Some(SprsError::SingularMatrix));
+        }
+        if cfg!(feature = "suitesparse") {
+            let res = factorize_with(Backend::SuiteSparse,
+                                     Factorization::Cholesky,
+                                     singular.view());
+            assert_eq!(res.err(), Some(SprsError::NotPositiveDefinite));
+        }
+    }
+}
@@ -1,12 +1,15 @@
 ///! Sparse matrix addition, subtraction
 
+use std::vec::Vec;
 use indexing::SpIndex;
+#[cfg(feature = "std")]
 use sparse::csmat::CompressedStorage;
 use sparse::prelude::*;
 use num_traits::Num;
 use sparse::vec::NnzEither::{Left, Right, Both};
 use sparse::vec::SparseIterTools;
 use sparse::compressed::SpMatView;
+#[cfg(feature = "std")]
 use ndarray::{
     self,
     Array,
@@ -17,8 +20,10 @@ use ndarray::{
     ShapeBuilder,
 };
 
+#[cfg(feature = "std")]
 use ::Ix2;
 use ::SpRes;
+use errors::{SprsError, StructureError};
 
 /// Sparse matrix addition, with matrices sharing the same storage type
 pub fn add_mat_same_storage<N, I, Mat1, Mat2>(lhs: &Mat1,
@@ -56,6 +61,110 @@ where N: Num + Copy,
     csmat_binop(lhs.view(), rhs.view(), |&x, &y| x * y)
 }
 
+/// Compute the linear combination `alpha * lhs + beta * rhs` of two sparse
+/// matrices in a single merge of their sorted indices, without the two
+/// temporaries created by scaling the matrices before adding them.
+///
+/// The result has the storage of `lhs`, `rhs` being converted if needed.
+/// Zeros resulting from cancellations are not stored.
+///
+/// # Panics
+///
+/// On incompatible dimensions.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::binop::add_scaled;
+/// let a: CsMat<f64> = CsMat::eye(2);
+/// let b = CsMat::new((2, 2), vec![0, 1, 2], vec![1, 1], vec![1., 2.]);
+/// let c = add_scaled(2., &a, -1., &b);
+/// assert_eq!(c, CsMat::new((2, 2), vec![0, 2, 2], vec![0, 1],
+///                          vec![2., -1.]));
+/// ```
+pub fn add_scaled<N, I, Mat1, Mat2>(alpha: N,
+                                    lhs: &Mat1,
+                                    beta: N,
+                                    rhs: &Mat2) -> CsMatI<N, I>
+where N: Num + Copy + Default,
+      I: SpIndex,
+      Mat1: SpMatView<N, I>,
+      Mat2: SpMatView<N, I>
+{
+    let lhs = lhs.view();
+    let rhs = rhs.view();
+    let rhs_conv;
+    let rhs = if rhs.storage() == lhs.storage() { rhs } else {
+        rhs_conv = rhs.to_other_storage();
+        rhs_conv.view()
+    };
+    csmat_binop(lhs, rhs, |&x, &y| alpha * x + beta * y)
+}
+
+/// Compute the linear combination `alpha * lhs + beta * rhs` into the
+/// values of `out`, whose sparsity pattern must contain the union of the
+/// patterns of `lhs` and `rhs`. This avoids any allocation when the same
+/// combination is computed repeatedly, eg `A - sigma B` for several shifts.
+///
+/// The entries of `out` outside of the patterns of `lhs` and `rhs` are set
+/// to zero.
+///
+/// # Errors
+///
+/// `IllegalStructure(MissingEntry)` if an entry of `lhs` or `rhs` is not
+/// part of the pattern of `out`. The values of `out` are then unspecified.
+///
+/// # Panics
+///
+/// - on incompatible dimensions
+/// - if the three matrices do not share the same storage
+pub fn add_scaled_into<N, I>(alpha: N,
+                             lhs: CsMatViewI<N, I>,
+                             beta: N,
+                             rhs: CsMatViewI<N, I>,
+                             mut out: CsMatViewMutI<N, I>
+                            ) -> Result<(), SprsError>
+where N: Num + Copy,
+      I: SpIndex,
+{
+    if lhs.shape() != rhs.shape() || lhs.shape() != out.shape() {
+        panic!("Dimension mismatch");
+    }
+    if lhs.storage() != rhs.storage() || lhs.storage() != out.storage() {
+        panic!("Storage mismatch");
+    }
+    let iter = lhs.outer_iterator()
+                  .zip(rhs.outer_iterator())
+                  .zip(out.outer_iterator_mut());
+    for ((lvec, rvec), mut ovec) in iter {
+        let (lind, ldata) = (lvec.indices(), lvec.data());
+        let (rind, rdata) = (rvec.indices(), rvec.data());
+        let (mut li, mut ri) = (0, 0);
+        for (ind, val) in ovec.iter_mut() {
+            let ind = I::from_usize(ind);
+            if (li < lind.len() && lind[li] < ind)
+                || (ri < rind.len() && rind[ri] < ind) {
+                return Err(StructureError::MissingEntry.into());
+            }
+            let mut res = N::zero();
+            if li < lind.len() && lind[li] == ind {
+                res = res + alpha * ldata[li];
+                li += 1;
+            }
+            if ri < rind.len() && rind[ri] == ind {
+                res = res + beta * rdata[ri];
+                ri += 1;
+            }
+            *val = res;
+        }
+        if li < lind.len() || ri < rind.len() {
+            return Err(StructureError::MissingEntry.into());
+        }
+    }
+    Ok(())
+}
+
 /// Sparse matrix multiplication by a scalar
 pub fn scalar_mul_mat<N, I, Mat>(mat: &Mat,
                                  val: N
@@ -97,6 +206,14 @@ where N: Num,
         panic!("Storage mismatch");
     }
 
+    if same_pattern(lhs.view(), rhs.view()) {
+        // the merge is not needed, unless cancellations have to be removed
+        let res = csmat_binop_same_pattern(lhs.view(), rhs.view(), &binop);
+        if res.data().iter().all(|x| *x != N::zero()) {
+            return res;
+        }
+    }
+
     let max_nnz = lhs.nnz() + rhs.nnz();
     let mut out_indptr = vec![I::zero(); lhs.outer_dims() + 1];
     let mut out_indices = vec![I::zero(); max_nnz];
@@ -126,6 +243,113 @@ where N: Num,
 }
 
 
+/// Check whether two matrices have the same sparsity structure, ie the
+/// same shape, storage and non-zero locations. Matrices sharing their
+/// `indptr` and `indices` arrays, eg views of the same matrix, are
+/// recognized without comparing these arrays.
+pub fn same_pattern<N, I>(lhs: CsMatViewI<N, I>, rhs: CsMatViewI<N, I>) -> bool
+where I: SpIndex
+{
+    if lhs.shape() != rhs.shape() || lhs.storage() != rhs.storage()
+        || lhs.nnz() != rhs.nnz()
+    {
+        return false;
+    }
+    let (lindptr, rindptr) = (lhs.indptr(), rhs.indptr());
+    let (lstart, rstart) = (lindptr[0], rindptr[0]);
+    if lindptr.as_ptr() == rindptr.as_ptr()
+        && lhs.indices().as_ptr() == rhs.indices().as_ptr()
+    {
+        return true;
+    }
+    // views of some outer dimensions have an offset indptr
+    let same_indptr = lindptr.iter().zip(rindptr)
+                             .all(|(&l, &r)| l - lstart == r - rstart);
+    let lindices = &lhs.indices()[lstart.index()..lstart.index() + lhs.nnz()];
+    let rindices = &rhs.indices()[rstart.index()..rstart.index() + rhs.nnz()];
+    same_indptr && lindices == rindices
+}
+
+/// Apply a binary operation to the non-zero values of two matrices sharing
+/// the same sparsity structure, as is common eg between the matrices of
+/// the steps of a time integration.
+///
+/// This is a single loop over the values, without the merge of the
+/// indices of `csmat_binop`, and the structure is kept as is: zeros
+/// resulting from the operation are stored. `csmat_binop` detects matrices
+/// of the same structure by itself, this function avoids the cost of the
+/// detection when the structures are known to be the same.
+///
+/// # Panics
+///
+/// If the matrices do not have the same shape, storage and number of
+/// non-zeros. The non-zero locations are only checked in debug builds.
+pub fn csmat_binop_same_pattern<N, I, F>(lhs: CsMatViewI<N, I>,
+                                         rhs: CsMatViewI<N, I>,
+                                         binop: F) -> CsMatI<N, I>
+where I: SpIndex,
+      F: Fn(&N, &N) -> N
+{
+    if lhs.shape() != rhs.shape() || lhs.nnz() != rhs.nnz() {
+        panic!("Dimension mismatch");
+    }
+    if lhs.storage() != rhs.storage() {
+        panic!("Storage mismatch");
+    }
+    debug_assert!(same_pattern(lhs.view(), rhs.view()), "Structure mismatch");
+    let lstart = lhs.indptr()[0];
+    let rstart = rhs.indptr()[0].index();
+    let range = lstart.index()..lstart.index() + lhs.nnz();
+    let rdata = &rhs.data()[rstart..rstart + rhs.nnz()];
+    CsMatI {
+        storage: lhs.storage(),
+        nrows: lhs.rows(),
+        ncols: lhs.cols(),
+        indptr: lhs.indptr().iter().map(|&i| i - lstart).collect(),
+        indices: lhs.indices()[range.clone()].to_vec(),
+        data: lhs.data()[range].iter()
+                               .zip(rdata)
+                               .map(|(l, r)| binop(l, r))
+                               .collect(),
+    }
+}
+
+/// Sparse matrix addition for matrices sharing the same sparsity
+/// structure, see `csmat_binop_same_pattern`.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::binop::add_same_pattern;
+/// let a = CsMat::new((2, 2), vec![0, 1, 2], vec![0, 1], vec![1., 2.]);
+/// let b = CsMat::new((2, 2), vec![0, 1, 2], vec![0, 1], vec![3., -2.]);
+/// let c = add_same_pattern(&a, &b);
+/// // the cancellation is stored
+/// assert_eq!(c.data(), &[4., 0.]);
+/// ```
+pub fn add_same_pattern<N, I, Mat1, Mat2>(lhs: &Mat1,
+                                          rhs: &Mat2) -> CsMatI<N, I>
+where N: Num + Copy,
+      I: SpIndex,
+      Mat1: SpMatView<N, I>,
+      Mat2: SpMatView<N, I>
+{
+    csmat_binop_same_pattern(lhs.view(), rhs.view(), |&x, &y| x + y)
+}
+
+/// Sparse matrix subtraction for matrices sharing the same sparsity
+/// structure, see `csmat_binop_same_pattern`.
+pub fn sub_same_pattern<N, I, Mat1, Mat2>(lhs: &Mat1,
+                                          rhs: &Mat2) -> CsMatI<N, I>
+where N: Num + Copy,
+      I: SpIndex,
+      Mat1: SpMatView<N, I>,
+      Mat2: SpMatView<N, I>
+{
+    csmat_binop_same_pattern(lhs.view(), rhs.view(), |&x, &y| x - y)
+}
+
 /// Raw implementation of scalar binary operation for compressed sparse matrices
 /// sharing the same storage. The output arrays are assumed to be preallocated
 ///
@@ -171,12 +395,13 @@ where N: Num,
 
 /// Compute alpha * lhs + beta * rhs with lhs a sparse matrix and rhs dense
 /// and alpha and beta scalars
+#[cfg(feature = "std")]
 pub fn add_dense_mat_same_ordering<N, I, Mat, D>(lhs: &Mat,
                                                  rhs: &ArrayBase<D, Ix2>,
                                                  alpha: N,
                                                  beta: N
                                                 ) -> Array<N, Ix2>
-where N: Num + Copy,
+where N: Num + Clone,
       I: SpIndex,
       Mat: SpMatView<N, I>,
       D: ndarray::Data<Elem=N>
@@ -188,18 +413,22 @@ where N: Num + Copy,
     };
     csmat_binop_dense_raw(lhs.view(),
                           rhs.view(),
-                          |&x, &y| alpha * x + beta * y,
+                          |x, y| {
+                              alpha.clone() * x.clone()
+                              + beta.clone() * y.clone()
+                          },
                           res.view_mut());
     res
 }
 
 /// Compute coeff wise `alpha * lhs * rhs` with `lhs` a sparse matrix,
 /// `rhs` a dense matrix, and `alpha` a scalar
+#[cfg(feature = "std")]
 pub fn mul_dense_mat_same_ordering<N, I, Mat, D>(lhs: &Mat,
                                                  rhs: &ArrayBase<D, Ix2>,
                                                  alpha: N
                                                 ) -> Array<N, Ix2>
-where N: Num + Copy,
+where N: Num + Clone,
       I: SpIndex,
       Mat: SpMatView<N, I>,
       D: ndarray::Data<Elem=N>
@@ -211,7 +440,7 @@ where N: Num + Copy,
     };
     csmat_binop_dense_raw(lhs.view(),
                           rhs.view(),
-                          |&x, &y| alpha * x * y,
+                          |x, y| alpha.clone() * x.clone() * y.clone(),
                           res.view_mut());
     res
 }
@@ -219,6 +448,7 @@ where N: Num + Copy,
 
 /// Raw implementation of sparse/dense binary operations with the same
 /// ordering
+#[cfg(feature = "std")]
 pub fn csmat_binop_dense_raw<'a, N, I, F>(lhs: CsMatViewI<'a, N, I>,
                                           rhs: ArrayView<'a, N, Ix2>,
                                           binop: F,
@@ -287,6 +517,35 @@ where N: Num,
     Ok(res)
 }
 
+/// Binary operations for CsVec restricted to the intersection of the
+/// sparsity patterns
+///
+/// This function iterates the non-zero locations of `lhs` and `rhs`
+/// and applies the function `binop` to the elements present in both
+/// vectors, other locations being absent from the result.
+///
+/// The function thus has a correct behavior iff `binop(x, 0) == 0` and
+/// `binop(0, x) == 0`, which is the case for multiplication.
+pub fn csvec_binop_intersection<N, I, F>(mut lhs: CsVecViewI<N, I>,
+                                         mut rhs: CsVecViewI<N, I>,
+                                         binop: F
+                                        ) -> SpRes<CsVecI<N, I>>
+where N: Num,
+      F: Fn(&N, &N) -> N,
+      I: SpIndex,
+{
+    csvec_fix_zeros(&mut lhs, &mut rhs);
+    if lhs.dim() != rhs.dim() {
+        panic!("Dimension mismatch");
+    }
+    let mut res = CsVecI::empty(lhs.dim());
+    res.reserve_exact(::std::cmp::min(lhs.nnz(), rhs.nnz()));
+    for (ind, lval, rval) in lhs.iter().nnz_zip(rhs.iter()) {
+        res.append(ind, binop(lval, rval));
+    }
+    Ok(res)
+}
+
 fn csvec_fix_zeros<N, I: SpIndex>(lhs: &mut CsVecViewI<N, I>, rhs: &mut CsVecViewI<N, I>) {
     if rhs.dim() == 0 {
         rhs.dim = lhs.dim;
@@ -300,9 +559,75 @@ fn csvec_fix_zeros<N, I: SpIndex>(lhs: &mut CsVecViewI<N, I>, rhs: &mut CsVecVie
 mod test {
     use sparse::CsMat;
     use sparse::CsVec;
-    use test_data::{mat1, mat2, mat1_times_2, mat_dense1};
+    use test_data::{mat1, mat1_csc, mat2, mat1_times_2, mat_dense1};
     use ndarray::{arr2, Array};
 
+    #[test]
+    fn same_pattern_binops() {
+        let a = mat1();
+        let b = super::scalar_mul_mat(&a, -0.5);
+        assert!(super::same_pattern(a.view(), b.view()));
+        assert!(super::same_pattern(a.view(), a.view()));
+        assert!(!super::same_pattern(a.view(), mat2().view()));
+        assert!(!super::same_pattern(a.view(), mat1_csc().view()));
+        assert_eq!(super::add_same_pattern(&a, &b),
+                   super::scalar_mul_mat(&a, 0.5));
+        assert_eq!(super::sub_same_pattern(&a, &b),
+                   super::scalar_mul_mat(&a, 1.5));
+        // the fast path of csmat_binop does not keep cancellations
+        let minus_a = super::add_same_pattern(&b, &b);
+        let cancelled = super::add_same_pattern(&a, &minus_a);
+        assert_eq!(cancelled.nnz(), a.nnz());
+        assert!(cancelled.data().iter().all(|&x| x == 0.));
+        assert_eq!(&a + &minus_a, CsMat::zero((5, 5)));
+        assert_eq!(&a + &b, super::scalar_mul_mat(&a, 0.5));
+
+        // views of some rows have offset structures
+        let view = a.view().middle_outer_views(1, 2);
+        let other = CsMat::new((2, 5), vec![0, 2, 3], vec![3, 4, 2],
+                               vec![1., 1., 1.]);
+        assert!(super::same_pattern(view.view(), other.view()));
+        let expected = CsMat::new((2, 5), vec![0, 2, 3], vec![3, 4, 2],
+                                  vec![3., 6., 6.]);
+        assert_eq!(super::add_same_pattern(&view, &other), expected);
+        assert_eq!(&view + &other, expected);
+    }
+
+    #[test]
+    fn add_scaled() {
+        let a = mat1();
+        let b = mat2();
+        let expected = &super::scalar_mul_mat(&a, 2.)
+                       + &super::scalar_mul_mat(&b, -3.);
+        assert_eq!(super::add_scaled(2., &a, -3., &b), expected);
+        assert_eq!(super::add_scaled(2., &a, -3., &b.to_csc()), expected);
+        // cancellations are not stored
+        assert_eq!(super::add_scaled(1., &a, -1., &a).nnz(), 0);
+    }
+
+    #[test]
+    fn add_scaled_into() {
+        use errors::{SprsError, StructureError};
+        let a = mat1();
+        let b = mat2();
+        let mut out = &a + &b;
+        let expected = &super::scalar_mul_mat(&a, 2.)
+                       + &super::scalar_mul_mat(&b, -3.);
+        super::add_scaled_into(2., a.view(), -3., b.view(), out.view_mut())
+              .unwrap();
+        assert_eq!(out, expected);
+        // a cancelled entry is kept in the preallocated pattern
+        super::add_scaled_into(1., a.view(), -1., a.view(), out.view_mut())
+              .unwrap();
+        assert_eq!(out.nnz(), (&a + &b).nnz());
+        assert!(out.data().iter().all(|&x| x == 0.));
+
+        let mut small = a.to_owned();
+        let res = super::add_scaled_into(1., a.view(), 1., b.view(),
+                                         small.view_mut());
+        assert_eq!(res, Err(SprsError::from(StructureError::MissingEntry)));
+    }
+
     fn mat1_plus_mat2() -> CsMat<f64> {
         let indptr = vec![0,  5,  8,  9, 12, 15];
         let indices = vec![0, 1, 2, 3, 4, 0, 3, 4, 2, 1, 2, 3, 1, 2, 3];
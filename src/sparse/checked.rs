@@ -0,0 +1,246 @@
+///! Overflow-checked kernels for integer matrices
+///!
+///! The generic kernels of sprs use the arithmetic operators of the scalar
+///! type, which silently wrap on integer overflow in release builds. This
+///! is a problem for graph algorithms, where path counts or weights stored
+///! as small integers can overflow when multiplied.
+///!
+///! The kernels of this module check every product and every accumulation,
+///! and either report the overflow as `SprsError::Overflow` or saturate at
+///! the bounds of the integer type, depending on the `OverflowPolicy`.
+///! Saturation is applied to each operation: once an accumulation reached a
+///! bound, adding terms of the opposite sign moves it back, so saturated
+///! results are only meaningful when all the terms have the same sign, eg
+///! for non-negative weights.
+///!
+///! # Example
+///!
+///! ```rust
+///! use sprs::CsMat;
+///! use sprs::checked::{self, OverflowPolicy};
+///! let mat = CsMat::new((1, 1), vec![0, 1], vec![0], vec![100i8]);
+///! let square = checked::spgemm(mat.view(), mat.view(),
+///!                              OverflowPolicy::Saturate).unwrap();
+///! assert_eq!(square.data(), &[127]);
+///! assert!(checked::spgemm(mat.view(), mat.view(),
+///!                         OverflowPolicy::Error).is_err());
+///! ```
+
+use num_traits::Zero;
+
+use indexing::SpIndex;
+use sparse::prelude::*;
+use sparse::csmat::CompressedStorage::CSR;
+use sparse::vec::SparseIterTools;
+use errors::SprsError;
+
+/// Integer scalars supporting checked and saturating arithmetic
+pub trait CheckedScalar: Copy + Zero {
+    /// `self + rhs`, or `None` on overflow
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+    /// `self * rhs`, or `None` on overflow
+    fn checked_mul(self, rhs: Self) -> Option<Self>;
+    /// `self + rhs`, saturating at the bounds of the type
+    fn saturating_add(self, rhs: Self) -> Self;
+    /// `self * rhs`, saturating at the bounds of the type
+    fn saturating_mul(self, rhs: Self) -> Self;
+}
+
+macro_rules! checked_scalar {
+    ($scalar: ty) => (
+        impl CheckedScalar for $scalar {
+            fn checked_add(self, rhs: $scalar) -> Option<$scalar> {
+                <$scalar>::checked_add(self, rhs)
+            }
+            fn checked_mul(self, rhs: $scalar) -> Option<$scalar> {
+                <$scalar>::checked_mul(self, rhs)
+            }
+            fn saturating_add(self, rhs: $scalar) -> $scalar {
+                <$scalar>::saturating_add(self, rhs)
+            }
+            fn saturating_mul(self, rhs: $scalar) -> $scalar {
+                <$scalar>::saturating_mul(self, rhs)
+            }
+        }
+    )
+}
+
+checked_scalar!(i8);
+checked_scalar!(u8);
+checked_scalar!(i16);
+checked_scalar!(u16);
+checked_scalar!(i32);
+checked_scalar!(u32);
+checked_scalar!(i64);
+checked_scalar!(u64);
+checked_scalar!(isize);
+checked_scalar!(usize);
+
+/// The behavior of the checked kernels on overflow
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Return `SprsError::Overflow`
+    Error,
+    /// Saturate at the bounds of the scalar type
+    Saturate,
+}
+
+/// `acc + a * b` following `policy`
+fn mul_add<N: CheckedScalar>(op: &'static str,
+                             acc: N,
+                             a: N,
+                             b: N,
+                             policy: OverflowPolicy) -> Result<N, SprsError> {
+    match policy {
+        OverflowPolicy::Saturate => {
+            Ok(acc.saturating_add(a.saturating_mul(b)))
+        },
+        OverflowPolicy::Error => {
+            a.checked_mul(b)
+             .and_then(|prod| acc.checked_add(prod))
+             .ok_or(SprsError::Overflow { op: op })
+        },
+    }
+}
+
+/// Dot product of two sparse vectors
+///
+/// # Panics
+///
+/// If the dimensions of the vectors differ.
+pub fn dot<N, I>(lhs: CsVecViewI<N, I>,
+                 rhs: CsVecViewI<N, I>,
+                 policy: OverflowPolicy) -> Result<N, SprsError>
+where N: CheckedScalar,
+      I: SpIndex,
+{
+    assert_eq!(lhs.dim(), rhs.dim(), "Dimension mismatch");
+    let mut acc = N::zero();
+    for (_, &lval, &rval) in lhs.iter().nnz_zip(rhs.iter()) {
+        acc = try!(mul_add("dot", acc, lval, rval, policy));
+    }
+    Ok(acc)
+}
+
+/// Product of a sparse matrix, in any storage, and a dense vector
+///
+/// # Panics
+///
+/// If `x.len() != mat.cols()`.
+pub fn mul_vec<N, I>(mat: CsMatViewI<N, I>,
+                     x: &[N],
+                     policy: OverflowPolicy) -> Result<Vec<N>, SprsError>
+where N: CheckedScalar,
+      I: SpIndex,
+{
+    assert_eq!(x.len(), mat.cols(), "Dimension mismatch");
+    let mut res = vec![N::zero(); mat.rows()];
+    for (outer, vec) in mat.outer_iterator().enumerate() {
+        for (inner, &val) in vec.iter() {
+            let (row, col) = if mat.is_csr() { (outer, inner) }
+                             else { (inner, outer) };
+            res[row] = try!(mul_add("mul_vec", res[row], val, x[col],
+                                    policy));
+        }
+    }
+    Ok(res)
+}
+
+/// Product of two sparse matrices, in any storage. The result is in CSR
+/// storage, and only stores the non-zero entries.
+///
+/// # Panics
+///
+/// If `lhs.cols() != rhs.rows()`.
+pub fn spgemm<N, I>(lhs: CsMatViewI<N, I>,
+                    rhs: CsMatViewI<N, I>,
+                    policy: OverflowPolicy) -> Result<CsMatI<N, I>, SprsError>
+where N: CheckedScalar + Default,
+      I: SpIndex,
+{
+    assert_eq!(lhs.cols(), rhs.rows(), "Dimension mismatch");
+    let (lhs_csr, rhs_csr);
+    let lhs = if lhs.is_csr() { lhs } else {
+        lhs_csr = lhs.to_csr();
+        lhs_csr.view()
+    };
+    let rhs = if rhs.is_csr() { rhs } else {
+        rhs_csr = rhs.to_csr();
+        rhs_csr.view()
+    };
+    // Gustavson's algorithm, with a dense accumulator for the current row
+    let mut acc = vec![N::zero(); rhs.cols()];
+    let mut touched = vec![false; rhs.cols()];
+    let mut cols = Vec::new();
+    let mut indptr = Vec::with_capacity(lhs.rows() + 1);
+    indptr.push(I::zero());
+    let mut indices = Vec::new();
+    let mut data = Vec::new();
+    for lrow in lhs.outer_iterator() {
+        for (k, &lval) in lrow.iter() {
+            for (j, &rval) in rhs.outer_view(k).unwrap().iter() {
+                acc[j] = try!(mul_add("spgemm", acc[j], lval, rval, policy));
+                if !touched[j] {
+                    touched[j] = true;
+                    cols.push(j);
+                }
+            }
+        }
+        cols.sort();
+        for &j in &cols {
+            if !acc[j].is_zero() {
+                indices.push(I::from_usize(j));
+                data.push(acc[j]);
+            }
+            acc[j] = N::zero();
+            touched[j] = false;
+        }
+        cols.clear();
+        indptr.push(I::from_usize(indices.len()));
+    }
+    Ok(CsMatI {
+        storage: CSR,
+        nrows: lhs.rows(),
+        ncols: rhs.cols(),
+        indptr: indptr,
+        indices: indices,
+        data: data,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use sparse::{CsMat, CsMatI, CsVec};
+    use errors::SprsError;
+    use super::{dot, mul_vec, spgemm, OverflowPolicy};
+
+    #[test]
+    fn checked_matches_unchecked() {
+        let a = CsMat::new((3, 3), vec![0, 2, 3, 5], vec![0, 2, 1, 0, 2],
+                           vec![1i32, 2, 3, 4, 5]);
+        let b = a.to_csc();
+        let prod = spgemm(a.view(), b.view(), OverflowPolicy::Error);
+        assert_eq!(prod.unwrap(), &a * &a);
+        let y = mul_vec(b.view(), &[1, 2, 3], OverflowPolicy::Error);
+        assert_eq!(y.unwrap(), vec![7, 6, 19]);
+    }
+
+    #[test]
+    fn overflow_detection() {
+        let v = CsVec::new(3, vec![0, 2], vec![100i8, 100]);
+        assert_eq!(dot(v.view(), v.view(), OverflowPolicy::Error),
+                   Err(SprsError::Overflow { op: "dot" }));
+        assert_eq!(dot(v.view(), v.view(), OverflowPolicy::Saturate),
+                   Ok(127));
+        // the accumulation overflows even though the products do not
+        let w = CsVec::new(3, vec![0, 2], vec![10i8, 10]);
+        assert!(dot(v.view(), w.view(), OverflowPolicy::Error).is_err());
+
+        let mat: CsMatI<u8, usize> = CsMat::new((1, 2), vec![0, 2],
+                                                vec![0, 1], vec![200, 100]);
+        assert_eq!(mul_vec(mat.view(), &[1, 1], OverflowPolicy::Error),
+                   Err(SprsError::Overflow { op: "mul_vec" }));
+        assert_eq!(mul_vec(mat.view(), &[1, 1], OverflowPolicy::Saturate),
+                   Ok(vec![255]));
+    }
+}
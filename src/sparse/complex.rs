@@ -0,0 +1,232 @@
+///! Complex scalars
+///!
+///! The kernels of sprs only require their scalars to be numbers, so they
+///! work with the complex numbers of `num_complex` as well as with reals.
+///! The operations which are different for complex numbers, such as the
+///! norms, which use the modulus of the values, or the inner products,
+///! which conjugate one of their operands, are expressed with the
+///! `RealOrComplex` trait, implemented for the real and complex floats.
+///!
+///! # Example
+///!
+///! ```rust
+///! extern crate num_complex;
+///! extern crate sprs;
+///! use num_complex::Complex64;
+///! use sprs::CsMat;
+///! # fn main() {
+///! let i = Complex64::i();
+///! let mat = CsMat::new((2, 2), vec![0, 1, 2], vec![1, 0],
+///!                      vec![i, Complex64::new(2., 0.)]);
+///! let adj = mat.adjoint();
+///! assert_eq!(adj.get(0, 1), Some(&-i));
+///! let prod = &adj * &mat;
+///! assert_eq!(prod.get(0, 0), Some(&Complex64::new(4., 0.)));
+///! # }
+///! ```
+
+use std::ops::Deref;
+use num_complex::Complex;
+use num_traits::{Float, Num, Zero};
+
+use indexing::SpIndex;
+use sparse::prelude::*;
+use sparse::vec::{IntoSparseVecIter, SparseIterTools};
+
+/// Real or complex floating point scalars
+pub trait RealOrComplex: Num + Copy {
+    /// The type of the modulus of the scalar
+    type Real: Float;
+
+    /// The complex conjugate, the scalar itself for reals
+    fn conj(&self) -> Self;
+
+    /// The modulus, ie the absolute value for reals
+    fn modulus(&self) -> Self::Real;
+
+    /// The square of the modulus
+    fn modulus_sqr(&self) -> Self::Real;
+
+    /// The scalar equal to a real number
+    fn from_real(real: Self::Real) -> Self;
+}
+
+macro_rules! real_scalar {
+    ($($scalar: ty),*) => {
+        $(
+            impl RealOrComplex for $scalar {
+                type Real = $scalar;
+
+                fn conj(&self) -> $scalar {
+                    *self
+                }
+
+                fn modulus(&self) -> $scalar {
+                    self.abs()
+                }
+
+                fn modulus_sqr(&self) -> $scalar {
+                    self * self
+                }
+
+                fn from_real(real: $scalar) -> $scalar {
+                    real
+                }
+            }
+        )*
+    }
+}
+
+real_scalar!(f32, f64);
+
+impl<T: Float> RealOrComplex for Complex<T> {
+    type Real = T;
+
+    fn conj(&self) -> Complex<T> {
+        Complex::conj(self)
+    }
+
+    fn modulus(&self) -> T {
+        self.norm()
+    }
+
+    fn modulus_sqr(&self) -> T {
+        self.norm_sqr()
+    }
+
+    fn from_real(real: T) -> Complex<T> {
+        Complex::new(real, T::zero())
+    }
+}
+
+/// # Methods for matrices of complex scalars
+impl<N, I, IpS, IS, DS> CsMatBase<N, I, IpS, IS, DS>
+where N: RealOrComplex,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    /// The matrix whose values are the conjugates of the values of this one
+    pub fn conj(&self) -> CsMatI<N, I> {
+        self.map(|x| x.conj())
+    }
+
+    /// The conjugate transpose of this matrix, in the same storage, which
+    /// is the transpose for real matrices.
+    pub fn adjoint(&self) -> CsMatI<N, I>
+    where N: Default
+    {
+        self.transpose_view().conj().to_other_storage()
+    }
+}
+
+/// # Methods for vectors of complex scalars
+impl<N, I, IS, DS> CsVecBase<IS, DS>
+where N: RealOrComplex,
+      I: SpIndex,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    /// The hermitian inner product of this vector with `rhs`, conjugating
+    /// this vector, ie `sum(conj(self[i]) * rhs[i])`, which is the dot
+    /// product for real vectors.
+    ///
+    /// # Panics
+    ///
+    /// If the dimension of the vectors do not match.
+    pub fn dotc<'b, T: IntoSparseVecIter<&'b N>>(&'b self, rhs: T) -> N
+    where N: 'b,
+          I: 'b,
+          <T as IntoSparseVecIter<&'b N>>::IterType: Iterator<Item=(usize, &'b N)>
+    {
+        assert_eq!(self.dim(), rhs.dim());
+        self.iter().nnz_zip(rhs.into_sparse_vec_iter())
+                   .map(|(_, lval, &rval)| lval.conj() * rval)
+                   .fold(N::zero(), |x, y| x + y)
+    }
+
+    /// The euclidean norm of this vector
+    pub fn l2_norm(&self) -> N::Real {
+        self.data().iter()
+                   .fold(<N::Real as Zero>::zero(),
+                         |acc, x| acc + x.modulus_sqr())
+                   .sqrt()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use num_complex::Complex64;
+    use ndarray::arr1;
+    use sparse::{CsMat, CsVec, TriMat};
+    use sparse::linalg::trisolve;
+    use NormType;
+    use super::RealOrComplex;
+
+    fn c(re: f64, im: f64) -> Complex64 {
+        Complex64::new(re, im)
+    }
+
+    /// The lower triangular matrix
+    /// | 1 + i       |
+    /// | 2     -i    |
+    /// |   3i     4  |
+    fn lower() -> CsMat<Complex64> {
+        CsMat::new((3, 3), vec![0, 1, 3, 5], vec![0, 0, 1, 1, 2],
+                   vec![c(1., 1.), c(2., 0.), c(0., -1.), c(0., 3.),
+                        c(4., 0.)])
+    }
+
+    #[test]
+    fn complex_kernels() {
+        let mat = lower();
+        let x = vec![c(1., 0.), c(0., 1.), c(-1., 2.)];
+        let b = &mat * &arr1(&x);
+        assert_eq!(b, arr1(&[c(1., 1.), c(3., 0.), c(-7., 8.)]));
+        assert_eq!(&mat.to_csc() * &arr1(&x), b);
+
+        let mut sol = b.to_vec();
+        trisolve::lsolve_csr_dense_rhs(mat.view(), &mut sol).unwrap();
+        assert_eq!(sol, x);
+
+        let sum = &mat + &mat.adjoint();
+        assert_eq!(sum.get(0, 0), Some(&c(2., 0.)));
+        assert_eq!(sum.get(0, 1), Some(&c(2., 0.)));
+        assert_eq!(sum.get(1, 2), Some(&c(0., -3.)));
+        let prod = &mat * &mat;
+        assert_eq!(prod.get(0, 0), Some(&c(0., 2.)));
+
+        let mut tri = TriMat::new((2, 2));
+        tri.add_triplet(0, 1, c(1., 1.));
+        tri.add_triplet(0, 1, c(0., -1.));
+        assert_eq!(tri.to_csr().data(), &[c(1., 0.)]);
+    }
+
+    #[test]
+    fn complex_norms_and_products() {
+        let v = CsVec::new(3, vec![0, 2], vec![c(3., 4.), c(0., 1.)]);
+        let w = CsVec::new(3, vec![0, 1], vec![c(0., 1.), c(2., 0.)]);
+        assert_eq!(v.dotc(&v), c(26., 0.));
+        assert_eq!(v.dotc(&w), c(4., 3.));
+        assert_eq!(v.dot(&w), c(-4., 3.));
+        assert_eq!(v.l2_norm(), 26f64.sqrt());
+
+        let mat = lower();
+        let close = |norms: &[f64], expected: &[f64]| {
+            norms.iter().zip(expected).all(|(x, y)| (x - y).abs() < 1e-12)
+        };
+        let sqrt2 = 2f64.sqrt();
+        let l1 = mat.outer_norms(NormType::L1);
+        assert!(close(l1.as_slice().unwrap(), &[sqrt2, 3., 7.]));
+        let linf = mat.outer_norms(NormType::Linf);
+        assert!(close(linf.as_slice().unwrap(), &[sqrt2, 2., 4.]));
+        let mut normalized = mat.to_owned();
+        normalized.normalize_outer(NormType::L2);
+        for row in normalized.outer_iterator() {
+            let norm_sqr: f64 = row.data().iter().map(|x| x.modulus_sqr()).sum();
+            assert!((norm_sqr - 1.).abs() < 1e-12);
+        }
+        assert_eq!(2f64.conj(), 2.);
+    }
+}
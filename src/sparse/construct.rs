@@ -4,8 +4,11 @@ use std::default::Default;
 use std::cmp;
 use sparse::prelude::*;
 use sparse::csmat::CompressedStorage;
-use ndarray::ArrayView;
-use num_traits::{Num, Signed};
+use sparse::csmat::CompressedStorage::{CSR, CSC};
+use indexing::SpIndex;
+use ndarray::{ArrayView, Axis};
+use num_traits::Num;
+use errors::SprsError;
 use ::Ix2;
 
 /// Stack the given matrices into a new one, using the most efficient stacking
@@ -42,32 +45,275 @@ where N: 'a + Clone,
     res
 }
 
-/// Construct a sparse matrix by vertically stacking other matrices
-pub fn vstack<'a, N, MatArray>(mats: &MatArray) -> CsMat<N>
+/// Stack the given matrices along `axis`: `Axis(0)` stacks them vertically,
+/// `Axis(1)` horizontally.
+///
+/// The storage of the result, and the stacking strategy, are chosen to
+/// avoid copying the inputs:
+///
+/// - stacking CSR matrices vertically, or CSC matrices horizontally,
+///   concatenates their outer dimensions, and gives a matrix in the same
+///   storage;
+/// - stacking matrices which are all in the other storage, eg CSC matrices
+///   vertically, merges their outer dimensions in a single pass, and
+///   also preserves their storage;
+/// - otherwise, the matrices which are not in the storage of the fast path
+///   (CSR for a vertical stack, CSC for an horizontal one) are converted
+///   first.
+///
+/// # Panics
+///
+/// - if `mats` is empty
+/// - if the matrices do not have the same number of columns (resp. rows)
+///   when stacking vertically (resp. horizontally)
+/// - if `axis` is neither `Axis(0)` nor `Axis(1)`
+///
+/// # Example
+///
+/// ```rust
+/// extern crate ndarray;
+/// extern crate sprs;
+/// use ndarray::Axis;
+/// use sprs::{CsMat, stack};
+/// # fn main() {
+/// let eye: CsMat<f64> = CsMat::eye_csc(2);
+/// let res = stack(Axis(0), &[eye.view(), eye.view()]);
+/// assert_eq!(res.shape(), (4, 2));
+/// assert!(res.is_csc());
+/// assert_eq!(res.to_csr(), CsMat::new((4, 2), vec![0, 1, 2, 3, 4],
+///                                     vec![0, 1, 0, 1], vec![1.; 4]));
+/// # }
+/// ```
+pub fn stack<'a, N, I, MatArray>(axis: Axis, mats: &MatArray) -> CsMatI<N, I>
 where N: 'a + Clone + Default,
-      MatArray: AsRef<[CsMatView<'a, N>]> {
+      I: 'a + SpIndex,
+      MatArray: AsRef<[CsMatViewI<'a, N, I>]> {
     let mats = mats.as_ref();
-    if mats.iter().all(|x| x.is_csr()) {
-        return same_storage_fast_stack(&mats);
+    if mats.len() == 0 {
+        panic!("Empty stacking list");
+    }
+    let vertical = match axis {
+        Axis(0) => true,
+        Axis(1) => false,
+        _ => panic!("Out of bounds index"),
+    };
+    let fast_storage = if vertical { CSR } else { CSC };
+    let storage = if mats.iter().all(|x| x.storage() != fast_storage) {
+        fast_storage.other_storage()
+    } else {
+        fast_storage
+    };
+    let converted: Vec<_> = mats.iter().map(|x| {
+        if x.storage() == storage { None } else { Some(x.to_other_storage()) }
+    }).collect();
+    let views: Vec<_> = mats.iter().zip(&converted).map(|(x, conv)| {
+        conv.as_ref().map_or(x.view(), |conv| conv.view())
+    }).collect();
+    if storage == fast_storage {
+        stack_outer(storage, &views)
+    }
+    else {
+        stack_inner(storage, &views)
+    }
+}
+
+/// Stack matrices of the same storage by concatenating their outer dims
+fn stack_outer<N, I>(storage: CompressedStorage,
+                     mats: &[CsMatViewI<N, I>]) -> CsMatI<N, I>
+where N: Clone,
+      I: SpIndex {
+    let inner_dim = mats[0].inner_dims();
+    if ! mats.iter().all(|x| x.inner_dims() == inner_dim) {
+        panic!("Dimension mismatch");
+    }
+    let outer_dim = mats.iter().map(|x| x.outer_dims()).fold(0, |x, y| x + y);
+    let nnz = mats.iter().map(|x| x.nnz()).fold(0, |x, y| x + y);
+
+    let mut res = CsMatI::empty(storage, inner_dim);
+    res.reserve_outer_dim_exact(outer_dim);
+    res.reserve_nnz_exact(nnz);
+    for mat in mats {
+        for vec in mat.outer_iterator() {
+            res = res.append_outer_csvec(vec);
+        }
+    }
+    res
+}
+
+/// Stack matrices of the same storage along their inner dimension, by
+/// merging their outer dims
+fn stack_inner<N, I>(storage: CompressedStorage,
+                     mats: &[CsMatViewI<N, I>]) -> CsMatI<N, I>
+where N: Clone,
+      I: SpIndex {
+    let outer_dim = mats[0].outer_dims();
+    if ! mats.iter().all(|x| x.outer_dims() == outer_dim) {
+        panic!("Dimension mismatch");
+    }
+    let mut offsets = Vec::with_capacity(mats.len());
+    let mut inner_dim = 0;
+    for mat in mats {
+        offsets.push(inner_dim);
+        inner_dim += mat.inner_dims();
+    }
+    let nnz = mats.iter().map(|x| x.nnz()).fold(0, |x, y| x + y);
+
+    let mut indptr = Vec::with_capacity(outer_dim + 1);
+    let mut indices = Vec::with_capacity(nnz);
+    let mut data = Vec::with_capacity(nnz);
+    indptr.push(I::zero());
+    for outer in 0..outer_dim {
+        for (mat, &offset) in mats.iter().zip(&offsets) {
+            for (inner, val) in mat.outer_view(outer).unwrap().iter() {
+                indices.push(I::from_usize(inner + offset));
+                data.push(val.clone());
+            }
+        }
+        indptr.push(I::from_usize(indices.len()));
+    }
+    let (nrows, ncols) = match storage {
+        CSR => (outer_dim, inner_dim),
+        CSC => (inner_dim, outer_dim),
+    };
+    CsMatI {
+        storage: storage,
+        nrows: nrows,
+        ncols: ncols,
+        indptr: indptr,
+        indices: indices,
+        data: data,
+    }
+}
+
+/// Stack sparse vectors into a matrix: `Axis(0)` stacks them as the rows of
+/// a CSR matrix, `Axis(1)` as the columns of a CSC matrix. This does not
+/// need any conversion.
+///
+/// # Panics
+///
+/// - if `vecs` is empty
+/// - if the vectors do not have the same dimension
+/// - if `axis` is neither `Axis(0)` nor `Axis(1)`
+///
+/// # Example
+///
+/// ```rust
+/// extern crate ndarray;
+/// extern crate sprs;
+/// use ndarray::Axis;
+/// use sprs::{CsMat, CsVec, stack_vecs};
+/// # fn main() {
+/// let a = CsVec::new(3, vec![0, 2], vec![1., 2.]);
+/// let b = CsVec::new(3, vec![1], vec![3.]);
+/// let rows = stack_vecs(Axis(0), &[a.view(), b.view()]);
+/// assert_eq!(rows, CsMat::new((2, 3), vec![0, 2, 3], vec![0, 2, 1],
+///                             vec![1., 2., 3.]));
+/// let cols = stack_vecs(Axis(1), &[a.view(), b.view()]);
+/// assert_eq!(cols, rows.transpose_into());
+/// # }
+/// ```
+pub fn stack_vecs<'a, N, I, VecArray>(axis: Axis,
+                                      vecs: &VecArray) -> CsMatI<N, I>
+where N: 'a + Clone,
+      I: 'a + SpIndex,
+      VecArray: AsRef<[CsVecViewI<'a, N, I>]> {
+    let vecs = vecs.as_ref();
+    if vecs.len() == 0 {
+        panic!("Empty stacking list");
+    }
+    let storage = match axis {
+        Axis(0) => CSR,
+        Axis(1) => CSC,
+        _ => panic!("Out of bounds index"),
+    };
+    let dim = vecs[0].dim();
+    if ! vecs.iter().all(|x| x.dim() == dim) {
+        panic!("Dimension mismatch");
     }
+    let nnz = vecs.iter().map(|x| x.nnz()).fold(0, |x, y| x + y);
+    let mut res = CsMatI::empty(storage, dim);
+    res.reserve_outer_dim_exact(vecs.len());
+    res.reserve_nnz_exact(nnz);
+    for vec in vecs {
+        res = res.append_outer_csvec(vec.view());
+    }
+    res
+}
 
-    let mats_csr: Vec<_> = mats.iter().map(|x| x.to_csr()).collect();
-    let mats_csr_views: Vec<_> = mats_csr.iter().map(|x| x.view()).collect();
-    same_storage_fast_stack(&mats_csr_views)
+/// Construct a sparse matrix by vertically stacking other matrices
+///
+/// The result is in CSR storage, unless all the matrices are in CSC
+/// storage, see `stack`.
+pub fn vstack<'a, N, MatArray>(mats: &MatArray) -> CsMat<N>
+where N: 'a + Clone + Default,
+      MatArray: AsRef<[CsMatView<'a, N>]> {
+    stack(Axis(0), mats)
 }
 
 /// Construct a sparse matrix by horizontally stacking other matrices
+///
+/// The result is in CSC storage, unless all the matrices are in CSR
+/// storage, see `stack`.
 pub fn hstack<'a, N, MatArray>(mats: &MatArray) -> CsMat<N>
 where N: 'a + Clone + Default,
       MatArray: AsRef<[CsMatView<'a, N>]> {
-    let mats = mats.as_ref();
-    if mats.iter().all(|x| x.is_csc()) {
-        return same_storage_fast_stack(&mats);
+    stack(Axis(1), mats)
+}
+
+/// Check that matrices can be stacked along an axis, ie that they agree on
+/// the other dimension
+fn check_stack<N>(op: &'static str,
+                  mats: &[CsMatView<N>],
+                  vertical: bool) -> Result<(), SprsError> {
+    let first = match mats.first() {
+        Some(first) => first,
+        None => return Err(SprsError::EmptyInput { op: op }),
+    };
+    for mat in mats {
+        let (rows, cols) = mat.shape();
+        let expected = if vertical { (rows, first.cols()) }
+                       else { (first.rows(), cols) };
+        if expected != (rows, cols) {
+            return Err(SprsError::DimensionMismatch {
+                op: op,
+                expected: expected,
+                found: (rows, cols),
+            });
+        }
     }
+    Ok(())
+}
 
-    let mats_csc: Vec<_> = mats.iter().map(|x| x.to_csc()).collect();
-    let mats_csc_views: Vec<_> = mats_csc.iter().map(|x| x.view()).collect();
-    same_storage_fast_stack(&mats_csc_views)
+/// Fallible version of `vstack`, returning an error instead of panicking
+/// when the list is empty or when the matrices do not have the same number
+/// of columns.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::{CsMat, try_vstack};
+/// let a = CsMat::<f64>::eye(3);
+/// let b = CsMat::<f64>::eye(4);
+/// assert!(try_vstack(&[a.view(), a.view()]).is_ok());
+/// assert!(try_vstack(&[a.view(), b.view()]).is_err());
+/// ```
+pub fn try_vstack<'a, N, MatArray>(mats: &MatArray)
+                                   -> Result<CsMat<N>, SprsError>
+where N: 'a + Clone + Default,
+      MatArray: AsRef<[CsMatView<'a, N>]> {
+    try!(check_stack("vstack", mats.as_ref(), true));
+    Ok(vstack(mats))
+}
+
+/// Fallible version of `hstack`, returning an error instead of panicking
+/// when the list is empty or when the matrices do not have the same number
+/// of rows.
+pub fn try_hstack<'a, N, MatArray>(mats: &MatArray)
+                                   -> Result<CsMat<N>, SprsError>
+where N: 'a + Clone + Default,
+      MatArray: AsRef<[CsMatView<'a, N>]> {
+    try!(check_stack("hstack", mats.as_ref(), false));
+    Ok(hstack(mats))
 }
 
 /// Specify a sparse matrix by constructing it from blocks of other matrices
@@ -134,21 +380,270 @@ where N: 'a + Clone + Default,
     vstack(&borrows)
 }
 
+/// Fallible version of `bmat`, returning an error instead of panicking
+/// when the grid of blocks is empty or ragged, has a block row or column
+/// without any matrix, or when the blocks of a block row (resp. column) do
+/// not have the same number of rows (resp. columns).
+pub fn try_bmat<'a, N, OuterArray, InnerArray>(mats: &OuterArray)
+                                               -> Result<CsMat<N>, SprsError>
+where N: 'a + Clone + Default,
+      OuterArray: 'a + AsRef<[InnerArray]>,
+      InnerArray: 'a + AsRef<[Option<CsMatView<'a, N>>]> {
+    let grid = mats.as_ref();
+    let super_cols = match grid.first() {
+        Some(row) if row.as_ref().len() > 0 => row.as_ref().len(),
+        _ => return Err(SprsError::EmptyInput { op: "bmat" }),
+    };
+    for row in grid {
+        if row.as_ref().len() != super_cols {
+            return Err(SprsError::DimensionMismatch {
+                op: "bmat",
+                expected: (grid.len(), super_cols),
+                found: (grid.len(), row.as_ref().len()),
+            });
+        }
+    }
+    // every block row and column needs a matrix defining its size, and the
+    // other matrices must agree with it
+    for row in grid {
+        let blocks: Vec<_> = row.as_ref().iter().filter_map(|m| m.as_ref())
+                                .map(|m| m.view()).collect();
+        if blocks.is_empty() {
+            return Err(SprsError::EmptyInput { op: "bmat" });
+        }
+        try!(check_stack("bmat", &blocks, false));
+    }
+    for j in 0..super_cols {
+        let blocks: Vec<_> = grid.iter()
+                                 .filter_map(|row| row.as_ref()[j].as_ref())
+                                 .map(|m| m.view())
+                                 .collect();
+        if blocks.is_empty() {
+            return Err(SprsError::EmptyInput { op: "bmat" });
+        }
+        try!(check_stack("bmat", &blocks, true));
+    }
+    Ok(bmat(mats))
+}
+
+/// Scalars which can be sparsified by comparing their magnitude to a
+/// threshold, see `csr_from_dense`.
+///
+/// The magnitude of a signed scalar is its absolute value, while unsigned
+/// integers and booleans are their own magnitude, so that count matrices
+/// and masks can be sparsified as well, their exact zeros being dropped
+/// with a zero threshold.
+pub trait Magnitude: Clone + PartialOrd {
+    /// The magnitude of this scalar
+    fn magnitude(&self) -> Self;
+
+    /// The magnitude of zero
+    fn zero_magnitude() -> Self;
+}
+
+macro_rules! signed_magnitude {
+    ($($scalar: ty),*) => {
+        $(
+            impl Magnitude for $scalar {
+                fn magnitude(&self) -> $scalar {
+                    self.abs()
+                }
+
+                fn zero_magnitude() -> $scalar {
+                    0 as $scalar
+                }
+            }
+        )*
+    }
+}
+
+macro_rules! unsigned_magnitude {
+    ($($scalar: ty),*) => {
+        $(
+            impl Magnitude for $scalar {
+                fn magnitude(&self) -> $scalar {
+                    *self
+                }
+
+                fn zero_magnitude() -> $scalar {
+                    0
+                }
+            }
+        )*
+    }
+}
+
+signed_magnitude!(i8, i16, i32, i64, isize, f32, f64);
+unsigned_magnitude!(u8, u16, u32, u64, usize);
+
+impl Magnitude for bool {
+    fn magnitude(&self) -> bool {
+        *self
+    }
+
+    fn zero_magnitude() -> bool {
+        false
+    }
+}
+
+#[cfg(feature = "num-rational")]
+impl<T> Magnitude for ::num_rational::Ratio<T>
+where ::num_rational::Ratio<T>: ::num_traits::Signed + Clone + PartialOrd
+{
+    fn magnitude(&self) -> ::num_rational::Ratio<T> {
+        ::num_traits::Signed::abs(self)
+    }
+
+    fn zero_magnitude() -> ::num_rational::Ratio<T> {
+        ::num_traits::Zero::zero()
+    }
+}
+
 /// Create a CSR matrix from a dense matrix, ignoring elements
-/// lower than `epsilon`.
+/// whose magnitude is not greater than `epsilon`.
 ///
-/// If epsilon is negative, it will be clamped to zero.
+/// If epsilon is negative, it will be clamped to zero, so that only the
+/// zeros are ignored.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate ndarray;
+/// extern crate sprs;
+/// use ndarray::arr2;
+/// use sprs::{CsMat, csr_from_dense};
+/// # fn main() {
+/// let counts = arr2(&[[0u32, 3], [1, 0]]);
+/// let mat = csr_from_dense(counts.view(), 0);
+/// assert_eq!(mat, CsMat::new((2, 2), vec![0, 1, 2], vec![1, 0], vec![3, 1]));
+/// let mask = arr2(&[[false, true], [false, false]]);
+/// assert_eq!(csr_from_dense(mask.view(), false).nnz(), 1);
+/// # }
+/// ```
 pub fn csr_from_dense<N>(m: ArrayView<N, Ix2>, epsilon: N) -> CsMat<N>
-where N: Num + Clone + cmp::PartialOrd + Signed
+where N: Magnitude
+{
+    let zero = N::zero_magnitude();
+    let epsilon = if epsilon > zero { epsilon } else { zero };
+    csr_from_dense_with(m, |x| x.magnitude() > epsilon)
+}
+
+/// Create a CSC matrix from a dense matrix, ignoring elements
+/// whose magnitude is not greater than `epsilon`.
+///
+/// If epsilon is negative, it will be clamped to zero.
+pub fn csc_from_dense<N>(m: ArrayView<N, Ix2>,
+                         epsilon: N
+                        ) -> CsMat<N>
+where N: Magnitude
+{
+    csr_from_dense(m.reversed_axes(), epsilon).transpose_into()
+}
+
+/// Create a CSR matrix from a dense matrix, ignoring the elements which
+/// are small relative to their row, ie whose absolute value is not greater
+/// than `rel_tol` times the largest absolute value of their row.
+///
+/// This is better suited than an absolute threshold to sparsify dense
+/// operators coming out of numerical computations, whose rows can have
+/// very different scales. Zero elements are always ignored, and if
+/// `rel_tol` is negative, it will be clamped to zero.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate ndarray;
+/// extern crate sprs;
+/// use ndarray::arr2;
+/// use sprs::{CsMat, csr_from_dense_relative};
+/// # fn main() {
+/// let dense = arr2(&[[1e3, 1e-9, 2.], [1e-6, -3e-6, 0.]]);
+/// let mat = csr_from_dense_relative(dense.view(), 1e-5);
+/// assert_eq!(mat, CsMat::new((2, 3), vec![0, 2, 4], vec![0, 2, 0, 1],
+///                            vec![1e3, 2., 1e-6, -3e-6]));
+/// # }
+/// ```
+pub fn csr_from_dense_relative<N>(m: ArrayView<N, Ix2>,
+                                  rel_tol: N) -> CsMat<N>
+where N: Num + Magnitude
+{
+    let rel_tol = if rel_tol > N::zero() { rel_tol } else { N::zero() };
+    let rows = m.shape()[0];
+    let cols = m.shape()[1];
+
+    let thresholds: Vec<N> = m.outer_iter().map(|row| {
+        let max = row.iter().fold(N::zero(), |max, x| {
+            let abs = x.magnitude();
+            if abs > max { abs } else { max }
+        });
+        max * rel_tol.clone()
+    }).collect();
+
+    let mut indptr = Vec::with_capacity(rows + 1);
+    indptr.push(0);
+    let mut indices = Vec::new();
+    let mut data = Vec::new();
+    for (row, threshold) in m.outer_iter().zip(&thresholds) {
+        for (col_ind, x) in row.iter().enumerate() {
+            if !x.is_zero() && x.magnitude() > *threshold {
+                indices.push(col_ind);
+                data.push(x.clone());
+            }
+        }
+        indptr.push(indices.len());
+    }
+    CsMat {
+        storage: CompressedStorage::CSR,
+        nrows: rows,
+        ncols: cols,
+        indptr: indptr,
+        indices: indices,
+        data: data
+    }
+}
+
+/// Create a CSC matrix from a dense matrix, ignoring the elements which
+/// are small relative to their column, see `csr_from_dense_relative`.
+pub fn csc_from_dense_relative<N>(m: ArrayView<N, Ix2>,
+                                  rel_tol: N) -> CsMat<N>
+where N: Num + Magnitude
+{
+    csr_from_dense_relative(m.reversed_axes(), rel_tol).transpose_into()
+}
+
+/// Create a CSR matrix from a dense matrix, keeping the elements for which
+/// `keep` returns true.
+///
+/// Unlike `csr_from_dense`, this does not require a numeric scalar, so
+/// boolean or integer matrices, and custom masking rules, are supported.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate ndarray;
+/// extern crate sprs;
+/// use ndarray::arr2;
+/// use sprs::{CsMat, csr_from_dense_with};
+/// # fn main() {
+/// let mask = arr2(&[[true, false], [false, false]]);
+/// let mat = csr_from_dense_with(mask.view(), |&x| x);
+/// assert_eq!(mat, CsMat::new((2, 2), vec![0, 1, 1], vec![0], vec![true]));
+/// let ints = arr2(&[[1, 2], [3, 4]]);
+/// let even = csr_from_dense_with(ints.view(), |&x| x % 2 == 0);
+/// assert_eq!(even.data(), &[2, 4]);
+/// # }
+/// ```
+pub fn csr_from_dense_with<N, F>(m: ArrayView<N, Ix2>, keep: F) -> CsMat<N>
+where N: Clone,
+      F: Fn(&N) -> bool
 {
-    let epsilon = if epsilon > N::zero() { epsilon } else { N::zero() };
     let rows = m.shape()[0];
     let cols = m.shape()[1];
 
     let mut indptr = vec![0; rows + 1];
     let mut nnz = 0;
     for (row, row_count) in m.outer_iter().zip(&mut indptr[1..]) {
-        nnz += row.iter().filter(|&x| x.abs() > epsilon).count();
+        nnz += row.iter().filter(|x| keep(*x)).count();
         *row_count = nnz;
     }
 
@@ -156,7 +651,7 @@ where N: Num + Clone + cmp::PartialOrd + Signed
     let mut data = Vec::with_capacity(nnz);
     for row in m.outer_iter() {
         for (col_ind, x) in row.iter().enumerate() {
-            if x.abs() > epsilon {
+            if keep(x) {
                 indices.push(col_ind);
                 data.push(x.clone());
             }
@@ -172,16 +667,73 @@ where N: Num + Clone + cmp::PartialOrd + Signed
     }
 }
 
-/// Create a CSC matrix from a dense matrix, ignoring elements
-/// lower than `epsilon`.
-///
-/// If epsilon is negative, it will be clamped to zero.
-pub fn csc_from_dense<N>(m: ArrayView<N, Ix2>,
-                         epsilon: N
-                        ) -> CsMat<N>
-where N: Num + Clone + cmp::PartialOrd + Signed
+/// Create a CSC matrix from a dense matrix, keeping the elements for which
+/// `keep` returns true.
+pub fn csc_from_dense_with<N, F>(m: ArrayView<N, Ix2>, keep: F) -> CsMat<N>
+where N: Clone,
+      F: Fn(&N) -> bool
 {
-    csr_from_dense(m.reversed_axes(), epsilon).transpose_into()
+    csr_from_dense_with(m.reversed_axes(), keep).transpose_into()
+}
+
+/// Parallel version of `csr_from_dense_with`, available with the `rayon`
+/// feature. The rows are filtered in parallel, and then concatenated.
+#[cfg(feature = "rayon")]
+pub fn par_csr_from_dense_with<N, F>(m: ArrayView<N, Ix2>,
+                                     keep: F) -> CsMat<N>
+where N: Clone + Send + Sync,
+      F: Fn(&N) -> bool + Sync
+{
+    use rayon::prelude::*;
+    use sparse::parallel::install;
+    let rows = m.shape()[0];
+    let cols = m.shape()[1];
+    let filtered: Vec<(Vec<usize>, Vec<N>)> = install(|| {
+        (0..rows).into_par_iter().map(|i| {
+            let mut indices = Vec::new();
+            let mut data = Vec::new();
+            for (col_ind, x) in m.row(i).iter().enumerate() {
+                if keep(x) {
+                    indices.push(col_ind);
+                    data.push(x.clone());
+                }
+            }
+            (indices, data)
+        }).collect()
+    });
+
+    let mut indptr = Vec::with_capacity(rows + 1);
+    indptr.push(0);
+    let mut nnz = 0;
+    for &(ref row_indices, _) in &filtered {
+        nnz += row_indices.len();
+        indptr.push(nnz);
+    }
+    let mut indices = Vec::with_capacity(nnz);
+    let mut data = Vec::with_capacity(nnz);
+    for (row_indices, row_data) in filtered {
+        indices.extend(row_indices);
+        data.extend(row_data);
+    }
+    CsMat {
+        storage: CompressedStorage::CSR,
+        nrows: rows,
+        ncols: cols,
+        indptr: indptr,
+        indices: indices,
+        data: data
+    }
+}
+
+/// Parallel version of `csc_from_dense_with`, available with the `rayon`
+/// feature.
+#[cfg(feature = "rayon")]
+pub fn par_csc_from_dense_with<N, F>(m: ArrayView<N, Ix2>,
+                                     keep: F) -> CsMat<N>
+where N: Clone + Send + Sync,
+      F: Fn(&N) -> bool + Sync
+{
+    par_csr_from_dense_with(m.reversed_axes(), keep).transpose_into()
 }
 
 #[cfg(test)]
@@ -256,6 +808,87 @@ mod test {
         assert_eq!(res, expected);
     }
 
+    #[test]
+    fn stack_storages() {
+        use ndarray::Axis;
+        let a = mat1();
+        let b = mat2();
+        let expected = mat1_vstack_mat2();
+        // vertical stack of CSC matrices keeps the CSC storage
+        let res = super::stack(Axis(0), &[a.to_csc().view(),
+                                          b.to_csc().view()]);
+        assert!(res.is_csc());
+        assert_eq!(res, expected.to_csc());
+        let res = super::stack(Axis(1), &[a.view(), b.view()]);
+        assert!(res.is_csr());
+        assert_eq!(res.to_csc(), super::hstack(&[a.to_csc().view(),
+                                                 b.to_csc().view()]));
+        assert_eq!(res.shape(), (5, 10));
+        assert_eq!(res.outer_view(0).unwrap().indices(), &[2, 3, 5, 6, 7, 9]);
+        let res = super::stack(Axis(1), &[a.view(), b.to_csc().view()]);
+        assert!(res.is_csc());
+    }
+
+    #[test]
+    fn stack_vecs() {
+        use ndarray::Axis;
+        let rows: Vec<_> = mat1().outer_iterator()
+                                 .map(|v| v.to_owned())
+                                 .collect();
+        let views: Vec<_> = rows.iter().map(|v| v.view()).collect();
+        assert_eq!(super::stack_vecs(Axis(0), &views), mat1());
+        assert_eq!(super::stack_vecs(Axis(1), &views),
+                   mat1().transpose_into());
+    }
+
+    #[test]
+    #[should_panic]
+    fn stack_fail_dim_mismatch() {
+        use ndarray::Axis;
+        let a = mat1();
+        let c = mat3();
+        let _ = super::stack(Axis(0), &[a.view(), c.view()]);
+    }
+
+    #[test]
+    fn from_dense_with_predicate() {
+        let dense = mat1().to_dense();
+        let csr = super::csr_from_dense_with(dense.view(), |&x| x > 4.);
+        assert_eq!(csr, CsMat::new((5, 5), vec![0, 0, 1, 2, 3, 4],
+                                   vec![4, 2, 1, 3], vec![5., 5., 8., 7.]));
+        let csc = super::csc_from_dense_with(dense.view(), |&x| x > 4.);
+        assert_eq!(csc, csr.to_csc());
+        let all = super::csr_from_dense_with(dense.view(), |&x| x != 0.);
+        assert_eq!(all, super::csr_from_dense(dense.view(), 0.));
+    }
+
+    #[test]
+    fn from_dense_relative() {
+        let dense = arr2(&[[10., -1., 0.5],
+                           [0., 0., 0.],
+                           [0.01, 0.02, -0.1]]);
+        let csr = super::csr_from_dense_relative(dense.view(), 0.1);
+        assert_eq!(csr, CsMat::new((3, 3), vec![0, 1, 1, 3],
+                                   vec![0, 1, 2], vec![10., 0.02, -0.1]));
+        let csc = super::csc_from_dense_relative(dense.view(), 0.1);
+        assert_eq!(csc, CsMat::new_csc((3, 3), vec![0, 1, 2, 4],
+                                       vec![0, 0, 0, 2],
+                                       vec![10., -1., 0.5, -0.1]));
+        let all = super::csr_from_dense_relative(dense.view(), -1.);
+        assert_eq!(all, super::csr_from_dense(dense.view(), 0.));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_from_dense_with_predicate() {
+        let dense = mat2().to_dense();
+        let keep = |x: &f64| *x != 0. && *x != 4.;
+        assert_eq!(super::par_csr_from_dense_with(dense.view(), keep),
+                   super::csr_from_dense_with(dense.view(), keep));
+        assert_eq!(super::par_csc_from_dense_with(dense.view(), keep),
+                   super::csc_from_dense_with(dense.view(), keep));
+    }
+
     #[test]
     #[should_panic]
     fn bmat_fail_shapes() {
@@ -287,6 +920,36 @@ mod test {
                                                [Some(a.view()), None]]);
     }
 
+    #[test]
+    fn fallible_stacking() {
+        use errors::SprsError;
+        let a = mat1();
+        let b = CsMat::<f64>::eye(4);
+        assert_eq!(super::try_vstack(&[a.view(), mat2().view()]),
+                   Ok(super::vstack(&[a.view(), mat2().view()])));
+        assert_eq!(super::try_hstack(&[a.view(), b.view()]),
+                   Err(SprsError::DimensionMismatch {
+                       op: "hstack",
+                       expected: (5, 4),
+                       found: (4, 4),
+                   }));
+        let empty: [::CsMatView<f64>; 0] = [];
+        assert_eq!(super::try_vstack(&empty),
+                   Err(SprsError::EmptyInput { op: "vstack" }));
+
+        let res: Result<CsMat<f64>, _> = super::try_bmat(
+            &vec![vec![None, None], vec![None]]);
+        assert!(res.is_err());
+        let res: Result<CsMat<f64>, _> = super::try_bmat(
+            &[[None, None], [Some(a.view()), Some(a.view())]]);
+        assert_eq!(res, Err(SprsError::EmptyInput { op: "bmat" }));
+        let res = super::try_bmat(&[[Some(a.view()), Some(b.view())]]);
+        assert!(res.is_err());
+        let res = super::try_bmat(&[[Some(a.view()), None],
+                                    [None, Some(b.view())]]);
+        assert_eq!(res.unwrap().shape(), (9, 9));
+    }
+
     #[test]
     fn bmat_simple() {
         let a = CsMat::<f64>::eye(5);
@@ -370,4 +1033,25 @@ mod test {
 
         assert_eq!(m_sparse, expected_output);
     }
+
+    #[test]
+    fn from_dense_unsigned_and_masks() {
+        let counts = arr2(&[[0u64, 2, 0],
+                            [5, 0, 1]]);
+        let expected = CsMat::new((2, 3), vec![0, 1, 3], vec![1, 0, 2],
+                                  vec![2, 5, 1]);
+        assert_eq!(super::csr_from_dense(counts.view(), 0), expected);
+        assert_eq!(super::csc_from_dense(counts.view(), 1), CsMat::new_csc(
+            (2, 3), vec![0, 1, 2, 2], vec![1, 0], vec![5, 2]));
+        let rel = super::csr_from_dense_relative(counts.view(), 0);
+        assert_eq!(rel, expected);
+
+        let mask = counts.map(|&x| x > 1);
+        let mat = super::csr_from_dense(mask.view(), false);
+        assert_eq!(mat.indices(), &[1, 0]);
+        assert_eq!(mat.data(), &[true, true]);
+
+        let signed = arr2(&[[-3i32, 0], [1, -1]]);
+        assert_eq!(super::csr_from_dense(signed.view(), 1).data(), &[-3]);
+    }
 }
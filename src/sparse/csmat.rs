@@ -12,22 +12,31 @@ use std::default::Default;
 use std::slice::{self, Windows};
 use std::ops::{Deref, DerefMut, Add, Sub, Mul, Range, Index, IndexMut};
 use std::mem;
+use std::sync::Arc;
+use std::vec::Vec;
 use num_traits::{Num, Zero};
 
+#[cfg(feature = "std")]
 use ndarray::{self, ArrayBase, Array, ShapeBuilder};
-use ::{Ix1, Ix2, Shape};
+#[cfg(feature = "std")]
+use ::{Ix1, Ix2};
+use ::Shape;
 
 use indexing::SpIndex;
 use array_backend::Array2;
 
 use sparse::prelude::*;
 use sparse::permutation::PermViewI;
-use sparse::vec;
+use sparse::vec::{self, IntoSparseVecIter, NnzEither, NnzOrZip};
+use sparse::vec::{SparseIterTools, VectorIterator};
 use sparse::compressed::SpMatView;
 use sparse::binop;
 use sparse::prod;
+use sparse::accumulator::SparseAccumulator;
 use sparse::utils;
-use errors::SprsError;
+use sparse::validation::ValidationLevel;
+use errors::{SprsError, StructureError};
+#[cfg(feature = "std")]
 use sparse::to_dense::assign_to_dense;
 
 /// Describe the storage of a CsMat
@@ -107,6 +116,249 @@ pub struct OuterIteratorMut<'iter, N: 'iter, I: 'iter> {
     data: &'iter mut [N],
 }
 
+/// Iterator on the non-zeros of an inner dimension of a compressed matrix
+/// (eg a column of a CSR matrix), yielding the outer index and the value
+/// of each non-zero. The non-zero of each outer dimension is found by a
+/// binary search.
+pub struct InnerAxisIterator<'iter, N: 'iter, I: 'iter> {
+    inner: I,
+    outer_ind_iter: Range<usize>,
+    indptr: &'iter [I],
+    indices: &'iter [I],
+    data: &'iter [N],
+}
+
+/// Iterator on the non-zeros of a compressed matrix, yielding
+/// `(row, col, &value)` tuples in storage order, ie row by row for a CSR
+/// matrix and column by column for a CSC matrix.
+pub struct CsMatIter<'iter, N: 'iter, I: 'iter> {
+    storage: CompressedStorage,
+    outer: usize,
+    pos: usize,
+    indptr: &'iter [I],
+    indices: &'iter [I],
+    data: &'iter [N],
+}
+
+/// Iterator on the non-zeros of a compressed matrix, yielding
+/// `((row, col), &value)` tuples in storage order, like ndarray's
+/// `indexed_iter`.
+pub struct CsMatIndexedIter<'iter, N: 'iter, I: 'iter> {
+    iter: CsMatIter<'iter, N, I>,
+}
+
+/// Iterator consuming an owned compressed matrix, yielding
+/// `(row, col, value)` tuples in storage order.
+pub struct CsMatIntoIter<N, I> {
+    storage: CompressedStorage,
+    outer: usize,
+    pos: usize,
+    indptr: Vec<I>,
+    indices: ::std::vec::IntoIter<I>,
+    data: ::std::vec::IntoIter<N>,
+}
+
+/// Find the outer dimension holding the non-zero at position `pos`, starting
+/// the search from `outer`, and return the location of the non-zero
+fn nnz_location<I: SpIndex>(storage: CompressedStorage,
+                            indptr: &[I],
+                            outer: &mut usize,
+                            pos: usize,
+                            inner: I) -> (usize, usize) {
+    while indptr[*outer + 1].index() <= pos {
+        *outer += 1;
+    }
+    match storage {
+        CSR => (*outer, inner.index()),
+        CSC => (inner.index(), *outer),
+    }
+}
+
+impl <'iter, N: 'iter, I: 'iter + SpIndex>
+Iterator
+for CsMatIter<'iter, N, I> {
+    type Item = (usize, usize, &'iter N);
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        if self.pos >= self.indptr[self.indptr.len() - 1].index() {
+            return None;
+        }
+        let pos = self.pos;
+        self.pos += 1;
+        let (row, col) = nnz_location(self.storage, self.indptr,
+                                      &mut self.outer, pos, self.indices[pos]);
+        Some((row, col, &self.data[pos]))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.indptr[self.indptr.len() - 1].index() - self.pos;
+        (len, Some(len))
+    }
+}
+
+impl <'iter, N: 'iter, I: 'iter + SpIndex>
+ExactSizeIterator
+for CsMatIter<'iter, N, I> {}
+
+impl <'iter, N: 'iter, I: 'iter + SpIndex>
+Iterator
+for CsMatIndexedIter<'iter, N, I> {
+    type Item = ((usize, usize), &'iter N);
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        self.iter.next().map(|(row, col, val)| ((row, col), val))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl <'iter, N: 'iter, I: 'iter + SpIndex>
+ExactSizeIterator
+for CsMatIndexedIter<'iter, N, I> {}
+
+impl<N, I: SpIndex> Iterator for CsMatIntoIter<N, I> {
+    type Item = (usize, usize, N);
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        match (self.indices.next(), self.data.next()) {
+            (Some(inner), Some(val)) => {
+                let pos = self.pos;
+                self.pos += 1;
+                let (row, col) = nnz_location(self.storage, &self.indptr,
+                                              &mut self.outer, pos, inner);
+                Some((row, col, val))
+            }
+            _ => None,
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.data.size_hint()
+    }
+}
+
+impl<N, I: SpIndex> ExactSizeIterator for CsMatIntoIter<N, I> {}
+
+impl<N, I: SpIndex> IntoIterator for CsMatBase<N, I, Vec<I>, Vec<I>, Vec<N>> {
+    type Item = (usize, usize, N);
+    type IntoIter = CsMatIntoIter<N, I>;
+
+    /// Consume the matrix, iterating over its `(row, col, value)` non-zeros
+    /// in storage order
+    fn into_iter(self) -> CsMatIntoIter<N, I> {
+        let start = self.indptr[0].index();
+        let mut indices = self.indices.into_iter();
+        let mut data = self.data.into_iter();
+        // skip the values before the first outer dimension
+        for _ in 0..start {
+            indices.next();
+            data.next();
+        }
+        CsMatIntoIter {
+            storage: self.storage,
+            outer: 0,
+            pos: start,
+            indptr: self.indptr,
+            indices: indices,
+            data: data,
+        }
+    }
+}
+
+impl<'a, N, I, IpS, IS, DS> IntoIterator for &'a CsMatBase<N, I, IpS, IS, DS>
+where I: 'a + SpIndex,
+      N: 'a,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    type Item = (usize, usize, &'a N);
+    type IntoIter = CsMatIter<'a, N, I>;
+
+    fn into_iter(self) -> CsMatIter<'a, N, I> {
+        self.iter()
+    }
+}
+
+/// Iterator on the outer dimensions of two compressed matrices, yielding
+/// the outer index and an iterator over the union of the non-zeros of
+/// both matrices in this outer dimension, see `CsMatBase::zip_outer`.
+pub struct ZipOuter<'iter, N1: 'iter, N2: 'iter, I: 'iter> {
+    outer: usize,
+    left: OuterIterator<'iter, N1, I>,
+    right: OuterIterator<'iter, N2, I>,
+}
+
+/// Iterator over the union of the non-zeros of an outer dimension of two
+/// matrices, yielding `(inner_ind, Option<&lhs_val>, Option<&rhs_val>)`
+/// tuples by increasing inner index.
+pub struct ZipInner<'iter, N1: 'iter, N2: 'iter, I: 'iter + SpIndex> {
+    nnz_or_zip: NnzOrZip<'iter,
+                         VectorIterator<'iter, N1, I>,
+                         VectorIterator<'iter, N2, I>,
+                         N1, N2>,
+}
+
+impl <'iter, N1: 'iter, N2: 'iter, I: 'iter + SpIndex>
+Iterator
+for ZipOuter<'iter, N1, N2, I> {
+    type Item = (usize, ZipInner<'iter, N1, N2, I>);
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        match (self.left.next(), self.right.next()) {
+            (Some(lhs), Some(rhs)) => {
+                let outer = self.outer;
+                self.outer += 1;
+                let lhs_iter = lhs.into_sparse_vec_iter();
+                let rhs_iter = rhs.into_sparse_vec_iter();
+                Some((outer, ZipInner {
+                    nnz_or_zip: lhs_iter.nnz_or_zip(rhs_iter),
+                }))
+            }
+            _ => None,
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.left.size_hint()
+    }
+}
+
+impl <'iter, N1: 'iter, N2: 'iter, I: 'iter + SpIndex>
+Iterator
+for ZipInner<'iter, N1, N2, I> {
+    type Item = (usize, Option<&'iter N1>, Option<&'iter N2>);
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        self.nnz_or_zip.next().map(|nnz| match nnz {
+            NnzEither::Both((ind, lval, rval)) => (ind, Some(lval), Some(rval)),
+            NnzEither::Left((ind, lval)) => (ind, Some(lval), None),
+            NnzEither::Right((ind, rval)) => (ind, None, Some(rval)),
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.nnz_or_zip.size_hint()
+    }
+}
+
+impl <'iter, N: 'iter, I: 'iter + SpIndex>
+Iterator
+for InnerAxisIterator<'iter, N, I> {
+    type Item = (usize, &'iter N);
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        while let Some(outer_ind) = self.outer_ind_iter.next() {
+            let start = self.indptr[outer_ind].index();
+            let stop = self.indptr[outer_ind + 1].index();
+            let indices = &self.indices[start..stop];
+            if let Ok(pos) = indices.binary_search(&self.inner) {
+                return Some((outer_ind, &self.data[start + pos]));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.outer_ind_iter.size_hint().1)
+    }
+}
 
 /// Outer iteration on a compressed matrix yields
 /// a tuple consisting of the outer index and of a sparse vector
@@ -264,18 +516,20 @@ impl<N, I: SpIndex> CsMatBase<N, I, Vec<I>, Vec<I>, Vec<N>> {
     pub fn eye(dim: usize) -> CsMat<N>
     where N: Num + Clone
     {
-        let n = dim;
-        let indptr = (0..n+1).collect();
-        let indices = (0..n).collect();
-        let data = vec![N::one(); n];
-        CsMat {
-            storage: CSR,
-            nrows: n,
-            ncols: n,
-            indptr: indptr,
-            indices: indices,
-            data: data,
-        }
+        CsMat::eye_k((dim, dim), 0)
+    }
+
+    /// Identity matrix with indices of type `I`, stored as a CSR matrix.
+    ///
+    /// ```rust
+    /// use sprs::CsMatI;
+    /// let eye: CsMatI<f64, u32> = CsMatI::eye_i(3);
+    /// assert_eq!(eye.indptr(), &[0, 1, 2, 3]);
+    /// ```
+    pub fn eye_i(dim: usize) -> CsMatI<N, I>
+    where N: Num + Clone
+    {
+        CsMatI::eye_k((dim, dim), 0)
     }
 
     /// Identity matrix, stored as a CSC matrix.
@@ -291,19 +545,77 @@ impl<N, I: SpIndex> CsMatBase<N, I, Vec<I>, Vec<I>, Vec<N>> {
     pub fn eye_csc(dim: usize) -> CsMat<N>
     where N: Num + Clone
     {
-        let n = dim;
-        let indptr = (0..n+1).collect();
-        let indices = (0..n).collect();
-        let data = vec![N::one(); n];
-        CsMat {
-            storage: CSC,
-            nrows: n,
-            ncols: n,
+        CsMat::eye_k((dim, dim), 0).transpose_into()
+    }
+
+    /// Identity matrix with indices of type `I`, stored as a CSC matrix.
+    pub fn eye_csc_i(dim: usize) -> CsMatI<N, I>
+    where N: Num + Clone
+    {
+        CsMatI::eye_k((dim, dim), 0).transpose_into()
+    }
+
+    /// Matrix of shape `shape` with ones on its `k`-th diagonal, ie at the
+    /// positions `(i, i + k)`, stored as a CSR matrix. The main diagonal is
+    /// `k = 0`, the upper diagonals have a positive `k` and the lower
+    /// diagonals a negative `k`. With `k = 0` and a rectangular shape, this
+    /// is the rectangular identity.
+    ///
+    /// ```rust
+    /// use sprs::CsMat;
+    /// let upper: CsMat<f64> = CsMat::eye_k((2, 3), 1);
+    /// assert_eq!(upper, CsMat::new((2, 3), vec![0, 1, 2], vec![1, 2],
+    ///                              vec![1., 1.]));
+    /// let lower: CsMat<f64> = CsMat::eye_k((3, 2), -1);
+    /// assert_eq!(lower.indptr(), &[0, 0, 1, 2]);
+    /// ```
+    pub fn eye_k(shape: Shape, k: isize) -> CsMatI<N, I>
+    where N: Num + Clone
+    {
+        CsMatI::scalar_diag(shape, k, N::one())
+    }
+
+    /// Matrix of shape `shape` with `alpha` on its main diagonal, ie
+    /// `alpha` times the (rectangular) identity, stored as a CSR matrix.
+    ///
+    /// ```rust
+    /// use sprs::CsMat;
+    /// let mat = CsMat::scalar_mat((2, 2), 3.);
+    /// assert_eq!(mat, CsMat::new((2, 2), vec![0, 1, 2], vec![0, 1],
+    ///                            vec![3., 3.]));
+    /// ```
+    pub fn scalar_mat(shape: Shape, alpha: N) -> CsMatI<N, I>
+    where N: Clone
+    {
+        CsMatI::scalar_diag(shape, 0, alpha)
+    }
+
+    /// CSR matrix with `alpha` at the positions `(i, i + k)`
+    fn scalar_diag(shape: Shape, k: isize, alpha: N) -> CsMatI<N, I>
+    where N: Clone
+    {
+        let (rows, cols) = shape;
+        let mut indptr = Vec::with_capacity(rows + 1);
+        let mut indices = Vec::with_capacity(rows.min(cols));
+        indptr.push(I::zero());
+        for row in 0..rows {
+            let col = row as isize + k;
+            if col >= 0 && (col as usize) < cols {
+                indices.push(I::from_usize(col as usize));
+            }
+            indptr.push(I::from_usize(indices.len()));
+        }
+        let data = vec![alpha; indices.len()];
+        CsMatI {
+            storage: CSR,
+            nrows: rows,
+            ncols: cols,
             indptr: indptr,
             indices: indices,
             data: data,
         }
     }
+
     /// Create an empty CsMat for building purposes
     pub fn empty(storage: CompressedStorage,
                  inner_size: usize
@@ -325,12 +637,17 @@ impl<N, I: SpIndex> CsMatBase<N, I, Vec<I>, Vec<I>, Vec<N>> {
     /// Create a new CsMat representing the zero matrix.
     /// Hence it has no non-zero elements.
     pub fn zero(shape: Shape) -> CsMat<N> {
+        CsMat::zero_i(shape)
+    }
+
+    /// Create the zero matrix with indices of type `I`.
+    pub fn zero_i(shape: Shape) -> CsMatI<N, I> {
         let (rows, cols) = shape;
-        CsMat {
+        CsMatI {
             storage: CSR,
             nrows: rows,
             ncols: cols,
-            indptr: vec![0; rows + 1],
+            indptr: vec![I::zero(); rows + 1],
             indices: Vec::new(),
             data: Vec::new(),
         }
@@ -382,7 +699,7 @@ impl<N, I: SpIndex> CsMatBase<N, I, Vec<I>, Vec<I>, Vec<N>> {
                indices : Vec<I>,
                data : Vec<N>
               ) -> CsMatI<N, I>
-    where N: Copy
+    where N: Clone
     {
         CsMatI::new_(CSR, shape, indptr, indices, data).unwrap()
     }
@@ -411,18 +728,42 @@ impl<N, I: SpIndex> CsMatBase<N, I, Vec<I>, Vec<I>, Vec<N>> {
                    indices : Vec<I>,
                    data : Vec<N>
                   ) -> CsMatI<N, I>
-    where N: Copy
+    where N: Clone
     {
         CsMatI::new_(CSC, shape, indptr, indices, data).unwrap()
     }
 
+    /// Create an owned matrix from moved data, checking their validity as
+    /// specified by `level`, see the `validation` module.
+    ///
+    /// This constructor is meant for data coming from a trusted source, so
+    /// contrary to `new`, the indices are not sorted, and the checks which
+    /// are performed return an `Err` on malformed data, as `new_view`.
+    pub fn new_with_validation(storage: CompressedStorage,
+                               shape: Shape,
+                               indptr: Vec<I>,
+                               indices: Vec<I>,
+                               data: Vec<N>,
+                               level: ValidationLevel
+                              ) -> Result<CsMatI<N, I>, SprsError> {
+        let m = CsMatI {
+            storage: storage,
+            nrows: shape.0,
+            ncols: shape.1,
+            indptr: indptr,
+            indices: indices,
+            data: data,
+        };
+        m.validate(level).and(Ok(m))
+    }
+
     fn new_(storage: CompressedStorage,
             shape: Shape,
             indptr : Vec<I>,
             indices : Vec<I>,
             data : Vec<N>
            ) -> Result<CsMatI<N, I>, SprsError>
-    where N: Copy
+    where N: Clone
     {
         let mut m = CsMatI {
             storage: storage,
@@ -437,7 +778,7 @@ impl<N, I: SpIndex> CsMatBase<N, I, Vec<I>, Vec<I>, Vec<N>> {
     }
 
     fn sort_indices(&mut self)
-    where N: Copy
+    where N: Clone
     {
         let mut buf = Vec::new();
         for start_stop in self.indptr.windows(2) {
@@ -489,6 +830,139 @@ impl<N, I: SpIndex> CsMatBase<N, I, Vec<I>, Vec<I>, Vec<N>> {
         self
     }
 
+    /// Append an outer dim to an existing matrix, provided as an iterator
+    /// of `(inner_index, value)` pairs, which need not be sorted.
+    ///
+    /// This enables streaming the construction of a matrix, eg a CSR
+    /// matrix can be built one sample row at a time without an intermediate
+    /// sparse vector.
+    ///
+    /// # Panics
+    ///
+    /// - if an inner index is out of bounds
+    /// - if an inner index is repeated
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::{CsMat, CompressedStorage};
+    /// let mat = CsMat::empty(CompressedStorage::CSR, 3)
+    ///                 .append_outer_from_iter(vec![(2, 1.), (0, 2.)])
+    ///                 .append_outer_from_iter(vec![(1, 3.)]);
+    /// assert_eq!(mat, CsMat::new((2, 3), vec![0, 2, 3], vec![0, 2, 1],
+    ///                            vec![2., 1., 3.]));
+    /// ```
+    pub fn append_outer_from_iter<It>(mut self, iter: It) -> Self
+    where N: Clone,
+          It: IntoIterator<Item=(usize, N)>,
+    {
+        let start = self.indices.len();
+        for (ind, val) in iter {
+            if ind >= self.inner_dims() {
+                panic!("Out of bounds index");
+            }
+            self.indices.push(I::from_usize(ind));
+            self.data.push(val);
+        }
+        let mut buf = Vec::new();
+        utils::sort_indices_data_slices(&mut self.indices[start..],
+                                        &mut self.data[start..],
+                                        &mut buf);
+        if ! self.indices[start..].windows(2).all(|x| x[0] < x[1]) {
+            panic!("Repeated inner index");
+        }
+        let nnz = I::from_usize(self.indices.len());
+        self.indptr.push(nnz);
+        let outer_dims = self.outer_dims();
+        self.set_outer_dims(outer_dims + 1);
+        self
+    }
+
+    /// Remove the last outer dim of the matrix, eg its last row for a CSR
+    /// matrix, and return it as a sparse vector. Returns `None` if the
+    /// matrix has no outer dim.
+    pub fn pop_outer(&mut self) -> Option<CsVecI<N, I>> {
+        let outer_dims = self.outer_dims();
+        if outer_dims == 0 {
+            return None;
+        }
+        Some(self.remove_outer(outer_dims - 1))
+    }
+
+    /// Remove the outer dim `i` of the matrix, eg its row `i` for a CSR
+    /// matrix, and return it as a sparse vector. The following outer dims
+    /// are shifted down.
+    ///
+    /// This is linear in the number of non-zeros following the removed
+    /// outer dim. Use `retain_outer` to remove several outer dims at once.
+    ///
+    /// # Panics
+    ///
+    /// If `i` is out of bounds.
+    pub fn remove_outer(&mut self, i: usize) -> CsVecI<N, I> {
+        let outer_dims = self.outer_dims();
+        if i >= outer_dims {
+            panic!("Out of bounds index");
+        }
+        let start = self.indptr[i].index();
+        let stop = self.indptr[i + 1].index();
+        let indices = self.indices.drain(start..stop).collect();
+        let data = self.data.drain(start..stop).collect();
+        let removed = I::from_usize(stop - start);
+        self.indptr.remove(i + 1);
+        for ptr in &mut self.indptr[i + 1..] {
+            *ptr = *ptr - removed;
+        }
+        self.set_outer_dims(outer_dims - 1);
+        CsVecBase {
+            dim: self.inner_dims(),
+            indices: indices,
+            data: data,
+        }
+    }
+
+    /// Only keep the outer dims for which `f(outer_index)` returns true,
+    /// eg filter the rows of a CSR matrix. The kept outer dims keep their
+    /// order, and the matrix is compacted in place in a single pass.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::CsMat;
+    /// let mut eye: CsMat<f64> = CsMat::eye(4);
+    /// eye.retain_outer(|i| i % 2 == 0);
+    /// assert_eq!(eye, CsMat::new((2, 4), vec![0, 1, 2], vec![0, 2],
+    ///                            vec![1., 1.]));
+    /// ```
+    pub fn retain_outer<F>(&mut self, mut f: F)
+    where F: FnMut(usize) -> bool
+    {
+        let outer_dims = self.outer_dims();
+        let mut write = self.indptr[0].index();
+        let mut kept = 0;
+        for outer in 0..outer_dims {
+            let start = self.indptr[outer].index();
+            let stop = self.indptr[outer + 1].index();
+            if ! f(outer) {
+                continue;
+            }
+            // write <= start, so the entries still to be read are safe
+            for read in start..stop {
+                self.indices.swap(write, read);
+                self.data.swap(write, read);
+                write += 1;
+            }
+            kept += 1;
+            // kept <= outer + 1, and if equal nothing has moved, so this
+            // does not overwrite the bounds of the outer dims still to read
+            self.indptr[kept] = I::from_usize(write);
+        }
+        self.indptr.truncate(kept + 1);
+        self.indices.truncate(write);
+        self.data.truncate(write);
+        self.set_outer_dims(kept);
+    }
+
     /// Insert an element in the matrix. If the element is already present,
     /// its value is overwritten.
     ///
@@ -498,6 +972,12 @@ impl<N, I: SpIndex> CsMatBase<N, I, Vec<I>, Vec<I>, Vec<N>> {
     /// The insertion will be efficient, however, if the elements are inserted
     /// according to the matrix's order, eg following the row order for a CSR
     /// matrix.
+    ///
+    /// More precisely, inserting an element shifts the indices and data
+    /// stored after it, and the remainder of indptr, so its cost is linear
+    /// in the number of non-zeros following the insertion spot, and is
+    /// amortized constant when appending. Use `insert_many` to insert
+    /// several elements in arbitrary order.
     pub fn insert(&mut self, row: usize, col: usize, val: N) {
         match self.storage() {
             CSR => self.insert_outer_inner(row, col, val),
@@ -505,33 +985,293 @@ impl<N, I: SpIndex> CsMatBase<N, I, Vec<I>, Vec<I>, Vec<N>> {
         }
     }
 
-    fn insert_outer_inner(&mut self,
-                          outer_ind: usize,
-                          inner_ind: usize,
-                          val: N,
-                         ) {
-        let outer_dims = self.outer_dims();
-        let inner_ind_idx = I::from_usize(inner_ind);
-        if outer_ind >= outer_dims {
-            // we need to add a new outer dimension
-            let last_nnz = *self.indptr.last().unwrap(); // indptr never empty
-            self.indptr.reserve(1 + outer_ind - outer_dims);
-            for _ in outer_dims..outer_ind {
-                self.indptr.push(last_nnz);
-            }
-            self.set_outer_dims(outer_ind + 1);
-            self.indptr.push(last_nnz + I::one());
-            self.indices.push(inner_ind_idx);
-            self.data.push(val);
+    /// Remove the element at `(row, col)` from the matrix, returning its
+    /// value, or `None` if it was not stored. The shape of the matrix is
+    /// unchanged.
+    ///
+    /// Like `insert`, this shifts the indices and data stored after the
+    /// element, and costs time linear in their number.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::CsMat;
+    /// let mut eye: CsMat<f64> = CsMat::eye(3);
+    /// assert_eq!(eye.remove_entry(1, 1), Some(1.));
+    /// assert_eq!(eye.remove_entry(1, 1), None);
+    /// assert_eq!(eye.nnz(), 2);
+    /// ```
+    pub fn remove_entry(&mut self, row: usize, col: usize) -> Option<N> {
+        let (outer_ind, inner_ind) = match self.storage() {
+            CSR => (row, col),
+            CSC => (col, row),
+        };
+        if outer_ind >= self.outer_dims() {
+            return None;
         }
-        else {
-            // we need to search for an insertion spot
-            let start = self.indptr[outer_ind].index();
-            let stop = self.indptr[outer_ind + 1].index();
-            let location = self.indices[start..stop]
-                         .binary_search(&inner_ind_idx);
-            match location {
-                Ok(ind) => {
+        let start = self.indptr[outer_ind].index();
+        let stop = self.indptr[outer_ind + 1].index();
+        let location = self.indices[start..stop]
+                           .binary_search(&I::from_usize(inner_ind));
+        match location {
+            Ok(ind) => {
+                let ind = start + ind;
+                self.indices.remove(ind);
+                for ptr in &mut self.indptr[outer_ind + 1..] {
+                    *ptr = *ptr - I::one();
+                }
+                Some(self.data.remove(ind))
+            },
+            Err(_) => None,
+        }
+    }
+
+    /// Add `alpha` to the diagonal of the matrix, ie compute `A += alpha I`,
+    /// for rectangular matrices as well. The diagonal entries which are not
+    /// stored are inserted, in a single pass over the matrix when some are
+    /// missing, and without any allocation otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::CsMat;
+    /// let mut mat = CsMat::new((2, 2), vec![0, 1, 2], vec![0, 0],
+    ///                          vec![1., 2.]);
+    /// mat.add_diag_assign(0.5);
+    /// assert_eq!(mat, CsMat::new((2, 2), vec![0, 1, 3], vec![0, 0, 1],
+    ///                            vec![1.5, 2., 0.5]));
+    /// ```
+    pub fn add_diag_assign(&mut self, alpha: N)
+    where N: Num + Copy
+    {
+        let diag_len = self.rows().min(self.cols());
+        let mut missing = Vec::new();
+        for i in 0..diag_len {
+            let start = self.indptr[i].index();
+            let stop = self.indptr[i + 1].index();
+            match self.indices[start..stop].binary_search(&I::from_usize(i)) {
+                Ok(pos) => {
+                    let val = &mut self.data[start + pos];
+                    *val = *val + alpha;
+                },
+                Err(_) => missing.push((i, i, alpha)),
+            }
+        }
+        if !missing.is_empty() {
+            self.insert_many(missing);
+        }
+    }
+
+    /// Insert several elements in the matrix, given as `(row, col, value)`
+    /// triplets in any order. Elements already present are overwritten, and
+    /// if an element is repeated in `entries`, its last value is kept. Like
+    /// `insert`, the matrix is grown to fit the new elements.
+    ///
+    /// The new elements are sorted and then merged with the existing ones
+    /// in a single pass, so the cost is `O(nnz + k log k)` for `k` new
+    /// elements, instead of `O(k nnz)` for repeated calls to `insert`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::CsMat;
+    /// let mut eye: CsMat<f64> = CsMat::eye(2);
+    /// eye.insert_many(vec![(1, 0, 2.), (0, 0, 3.), (2, 2, 4.)]);
+    /// assert_eq!(eye, CsMat::new((3, 3), vec![0, 1, 3, 4], vec![0, 0, 1, 2],
+    ///                            vec![3., 2., 1., 4.]));
+    /// ```
+    pub fn insert_many<It>(&mut self, entries: It)
+    where It: IntoIterator<Item=(usize, usize, N)>
+    {
+        let is_csr = self.is_csr();
+        let mut new_entries: Vec<_> = entries.into_iter().map(|(i, j, v)| {
+            if is_csr { (i, j, v) } else { (j, i, v) }
+        }).collect();
+        // the sort is stable, so repeated elements keep their order
+        new_entries.sort_by_key(|&(outer, inner, _)| (outer, inner));
+        let old_outer_dims = self.outer_dims();
+        let mut outer_dims = old_outer_dims;
+        let mut inner_dims = self.inner_dims();
+        for &(outer, inner, _) in &new_entries {
+            outer_dims = outer_dims.max(outer + 1);
+            inner_dims = inner_dims.max(inner + 1);
+        }
+        let capacity = self.nnz() + new_entries.len();
+        let old_indptr = mem::replace(&mut self.indptr,
+                                      Vec::with_capacity(outer_dims + 1));
+        let old_indices = mem::replace(&mut self.indices,
+                                       Vec::with_capacity(capacity));
+        let old_data = mem::replace(&mut self.data,
+                                    Vec::with_capacity(capacity));
+        let mut old = old_indices.into_iter()
+                                 .zip(old_data.into_iter())
+                                 .skip(old_indptr[0].index());
+        let mut new_iter = new_entries.into_iter().peekable();
+        self.indptr.push(I::zero());
+        for outer in 0..outer_dims {
+            let count = if outer < old_outer_dims {
+                old_indptr[outer + 1].index() - old_indptr[outer].index()
+            } else {
+                0
+            };
+            let mut old_row = old.by_ref().take(count).peekable();
+            loop {
+                let next_old = old_row.peek().map(|&(i, _)| i.index());
+                let next_new = match new_iter.peek() {
+                    Some(&(o, j, _)) if o == outer => Some(j),
+                    _ => None,
+                };
+                let take_old = match (next_old, next_new) {
+                    (None, None) => break,
+                    (Some(_), None) => true,
+                    (None, Some(_)) => false,
+                    (Some(i), Some(j)) => {
+                        if i == j {
+                            // overwritten by the new element
+                            old_row.next();
+                        }
+                        i < j
+                    },
+                };
+                if take_old {
+                    let (i, v) = old_row.next().unwrap();
+                    self.indices.push(i);
+                    self.data.push(v);
+                }
+                else {
+                    let (_, j, mut v) = new_iter.next().unwrap();
+                    while new_iter.peek().map_or(false, |&(o, j2, _)| {
+                        o == outer && j2 == j
+                    }) {
+                        v = new_iter.next().unwrap().2;
+                    }
+                    self.indices.push(I::from_usize(j));
+                    self.data.push(v);
+                }
+            }
+            self.indptr.push(I::from_usize(self.indices.len()));
+        }
+        self.set_outer_dims(outer_dims);
+        self.set_inner_dims(inner_dims);
+    }
+
+    /// Overwrite the block of this matrix starting at row `row_offset` and
+    /// column `col_offset` with the matrix `block`: the elements of this
+    /// matrix in the block are replaced by the non-zeros of `block`.
+    ///
+    /// Only the outer dimensions crossing the block are rebuilt, the other
+    /// ones being moved at most once, so composing an operator block by
+    /// block does not require rebuilding it with `bmat`.
+    ///
+    /// # Panics
+    ///
+    /// If the block does not fit in the matrix.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::CsMat;
+    /// let mut mat: CsMat<f64> = CsMat::eye(3);
+    /// let block = CsMat::new((2, 1), vec![0, 1, 1], vec![0], vec![5.]);
+    /// mat.set_block(1, 1, &block);
+    /// assert_eq!(mat, CsMat::new((3, 3), vec![0, 1, 2, 3], vec![0, 1, 2],
+    ///                            vec![1., 5., 1.]));
+    /// ```
+    pub fn set_block<IpS, IS, DS>(&mut self,
+                                  row_offset: usize,
+                                  col_offset: usize,
+                                  block: &CsMatBase<N, I, IpS, IS, DS>)
+    where N: Clone + Default,
+          IpS: Deref<Target=[I]>,
+          IS: Deref<Target=[I]>,
+          DS: Deref<Target=[N]>,
+    {
+        if row_offset + block.rows() > self.rows()
+            || col_offset + block.cols() > self.cols() {
+            panic!("Out of bounds index");
+        }
+        let converted;
+        let block = if block.storage() == self.storage() {
+            block.view()
+        } else {
+            converted = block.to_other_storage();
+            converted.view()
+        };
+        let (outer_offset, inner_offset) = match self.storage {
+            CSR => (row_offset, col_offset),
+            CSC => (col_offset, row_offset),
+        };
+        let outer_end = outer_offset + block.outer_dims();
+        let inner_end = inner_offset + block.inner_dims();
+        let start = self.indptr[outer_offset].index();
+        let stop = self.indptr[outer_end].index();
+
+        // the new contents of the outer dimensions crossing the block
+        let capacity = stop - start + block.nnz();
+        let mut indices = Vec::with_capacity(capacity);
+        let mut data = Vec::with_capacity(capacity);
+        let mut lens = Vec::with_capacity(block.outer_dims());
+        for (k, block_vec) in block.outer_iterator().enumerate() {
+            let outer_start = self.indptr[outer_offset + k].index();
+            let outer_stop = self.indptr[outer_offset + k + 1].index();
+            let inds = &self.indices[outer_start..outer_stop];
+            let before = outer_start + inds.iter()
+                                           .position(|i| i.index() >= inner_offset)
+                                           .unwrap_or(inds.len());
+            let after = outer_start + inds.iter()
+                                          .position(|i| i.index() >= inner_end)
+                                          .unwrap_or(inds.len());
+            let len = indices.len();
+            indices.extend_from_slice(&self.indices[outer_start..before]);
+            data.extend_from_slice(&self.data[outer_start..before]);
+            for (inner, val) in block_vec.iter() {
+                indices.push(I::from_usize(inner + inner_offset));
+                data.push(val.clone());
+            }
+            indices.extend_from_slice(&self.indices[after..outer_stop]);
+            data.extend_from_slice(&self.data[after..outer_stop]);
+            lens.push(indices.len() - len);
+        }
+
+        let new_stop = start + indices.len();
+        self.indices.splice(start..stop, indices);
+        self.data.splice(start..stop, data);
+        for (k, len) in lens.into_iter().enumerate() {
+            let outer_start = self.indptr[outer_offset + k];
+            self.indptr[outer_offset + k + 1] = outer_start + I::from_usize(len);
+        }
+        for ptr in &mut self.indptr[outer_end + 1..] {
+            *ptr = I::from_usize(ptr.index() - stop + new_stop);
+        }
+    }
+
+    fn insert_outer_inner(&mut self,
+                          outer_ind: usize,
+                          inner_ind: usize,
+                          val: N,
+                         ) {
+        let outer_dims = self.outer_dims();
+        let inner_ind_idx = I::from_usize(inner_ind);
+        if outer_ind >= outer_dims {
+            // we need to add a new outer dimension
+            let last_nnz = *self.indptr.last().unwrap(); // indptr never empty
+            self.indptr.reserve(1 + outer_ind - outer_dims);
+            for _ in outer_dims..outer_ind {
+                self.indptr.push(last_nnz);
+            }
+            self.set_outer_dims(outer_ind + 1);
+            self.indptr.push(last_nnz + I::one());
+            self.indices.push(inner_ind_idx);
+            self.data.push(val);
+        }
+        else {
+            // we need to search for an insertion spot
+            let start = self.indptr[outer_ind].index();
+            let stop = self.indptr[outer_ind + 1].index();
+            let location = self.indices[start..stop]
+                         .binary_search(&inner_ind_idx);
+            match location {
+                Ok(ind) => {
                     let ind = start + ind.index();
                     self.data[ind] = val;
                     return;
@@ -566,6 +1306,129 @@ impl<N, I: SpIndex> CsMatBase<N, I, Vec<I>, Vec<I>, Vec<N>> {
         }
     }
 
+    /// Convert this matrix into a matrix sharing its structure, which can
+    /// be cloned without copying its index arrays.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::CsMat;
+    /// let jac = CsMat::new((2, 2), vec![0, 1, 2], vec![0, 1], vec![1., 2.])
+    ///     .into_shared();
+    /// let mut jacs = vec![jac.clone(), jac.clone()];
+    /// jacs[1].scale(3.);
+    /// assert!(jacs[0].shares_structure_with(&jacs[1]));
+    /// assert_eq!(jacs[1].data(), &[3., 6.]);
+    /// ```
+    pub fn into_shared(self) -> CsMatSharedI<N, I> {
+        CsMatSharedI {
+            storage: self.storage,
+            nrows: self.nrows,
+            ncols: self.ncols,
+            indptr: self.indptr.into(),
+            indices: self.indices.into(),
+            data: self.data,
+        }
+    }
+
+}
+
+/// Check that the given buffers describe a valid compressed matrix,
+/// reporting every failure as an error instead of panicking.
+fn check_raw_parts<I: SpIndex>(storage: CompressedStorage, shape: Shape,
+                               indptr: &[I], indices: &[I], nnz: usize)
+                               -> Result<(), SprsError> {
+    let (outer, inner) = match storage {
+        CSR => shape,
+        CSC => (shape.1, shape.0),
+    };
+    if indptr.len() != outer + 1 {
+        return Err(StructureError::BadIndptrLength.into());
+    }
+    if indices.len() != nnz || indptr[outer].index() != nnz {
+        return Err(StructureError::BadNnzCount.into());
+    }
+    if ! indptr.windows(2).all(|x| x[0] <= x[1]) {
+        return Err(StructureError::UnsortedIndptr.into());
+    }
+    if indptr[0].index() > nnz {
+        return Err(StructureError::OutOfBoundsIndptr.into());
+    }
+    for window in indptr.windows(2) {
+        let inds = &indices[window[0].index()..window[1].index()];
+        if ! inds.windows(2).all(|x| x[0] < x[1]) {
+            return Err(StructureError::NonSortedIndices.into());
+        }
+        if inds.last().map_or(false, |i| i.index() >= inner) {
+            return Err(StructureError::OutOfBoundsIndex.into());
+        }
+    }
+    Ok(())
+}
+
+/// # Methods for sparse matrices using any container
+///
+/// The arrays of a matrix can be stored in any container dereferencing to a
+/// slice, such as `Vec`, `Box<[T]>`, `Arc<[T]>`, slices, or containers
+/// using a custom allocator or shared memory.
+impl<N, I, IptrStorage, IndStorage, DataStorage>
+CsMatBase<N, I, IptrStorage, IndStorage, DataStorage>
+where I: SpIndex,
+      IptrStorage: Deref<Target=[I]>,
+      IndStorage: Deref<Target=[I]>,
+      DataStorage: Deref<Target=[N]> {
+    /// Create a matrix from its arrays stored in any containers, with the
+    /// same checks as `CsMatView::from_raw_parts`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::{CsMatBase, CompressedStorage};
+    /// let indptr: Box<[usize]> = vec![0, 1, 2].into_boxed_slice();
+    /// let indices: Box<[usize]> = vec![1, 0].into_boxed_slice();
+    /// let data: Box<[f64]> = vec![1., 2.].into_boxed_slice();
+    /// let mat = CsMatBase::from_containers(CompressedStorage::CSR, (2, 2),
+    ///                                      indptr, indices, data).unwrap();
+    /// assert_eq!(mat.get(1, 0), Some(&2.));
+    /// ```
+    pub fn from_containers(storage: CompressedStorage, shape: Shape,
+                           indptr: IptrStorage, indices: IndStorage,
+                           data: DataStorage) -> Result<Self, SprsError> {
+        try!(check_raw_parts(storage, shape, &indptr[..], &indices[..],
+                             data.len()));
+        Ok(CsMatBase {
+            storage: storage,
+            nrows: shape.0,
+            ncols: shape.1,
+            indptr: indptr,
+            indices: indices,
+            data: data,
+        })
+    }
+
+    /// Return the containers of the index pointers, of the indices and of
+    /// the values of this matrix.
+    pub fn into_containers(self) -> (IptrStorage, IndStorage, DataStorage) {
+        (self.indptr, self.indices, self.data)
+    }
+
+    /// Move the arrays of this matrix into other containers, eg from `Vec`
+    /// into `Box<[T]>` or `Arc<[T]>`.
+    pub fn convert_containers<IptrS2, IndS2, DataS2>(self)
+        -> CsMatBase<N, I, IptrS2, IndS2, DataS2>
+    where IptrS2: Deref<Target=[I]> + From<IptrStorage>,
+          IndS2: Deref<Target=[I]> + From<IndStorage>,
+          DataS2: Deref<Target=[N]> + From<DataStorage>,
+    {
+        CsMatBase {
+            storage: self.storage,
+            nrows: self.nrows,
+            ncols: self.ncols,
+            indptr: self.indptr.into(),
+            indices: self.indices.into(),
+            data: self.data.into(),
+        }
+    }
 }
 
 /// # Constructor methods for sparse matrix views
@@ -591,6 +1454,61 @@ impl<'a, N:'a, I: 'a + SpIndex> CsMatBase<N, I, &'a [I], &'a [I], &'a [N]> {
         m.check_compressed_structure().and(Ok(m))
     }
 
+    /// Create a borrowed CsMat matrix from sliced data, checking their
+    /// validity as specified by `level`, see the `validation` module.
+    pub fn new_view_with_validation(storage: CompressedStorage, shape: Shape,
+                                    indptr: &'a [I], indices: &'a [I],
+                                    data: &'a [N], level: ValidationLevel)
+                                    -> Result<CsMatViewI<'a, N, I>, SprsError>
+    {
+        let m = CsMatViewI {
+            storage: storage,
+            nrows: shape.0,
+            ncols: shape.1,
+            indptr: indptr,
+            indices: indices,
+            data: data,
+        };
+        m.validate(level).and(Ok(m))
+    }
+
+    /// Create a borrowed CsMat matrix from slices owned elsewhere, eg
+    /// memory mapped files or buffers allocated by a foreign library,
+    /// without copying them.
+    ///
+    /// Unlike `new_view`, this never panics: any inconsistency between
+    /// the shape and the slices, unsorted indices or out of bounds indices
+    /// are reported as errors.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::{CsMatView, CompressedStorage};
+    /// let indptr = [0, 1, 3];
+    /// let indices = [1, 0, 2];
+    /// let data = [1., 2., 3.];
+    /// let mat = CsMatView::from_raw_parts(CompressedStorage::CSR, (2, 3),
+    ///                                     &indptr, &indices, &data)
+    ///                     .unwrap();
+    /// assert_eq!(mat.get(1, 2), Some(&3.));
+    /// let bad = CsMatView::from_raw_parts(CompressedStorage::CSR, (2, 2),
+    ///                                     &indptr, &indices, &data);
+    /// assert!(bad.is_err());
+    /// ```
+    pub fn from_raw_parts(storage: CompressedStorage, shape: Shape,
+                          indptr: &'a [I], indices: &'a [I], data: &'a [N])
+                          -> Result<CsMatViewI<'a, N, I>, SprsError> {
+        try!(check_raw_parts(storage, shape, indptr, indices, data.len()));
+        Ok(CsMatViewI {
+            storage: storage,
+            nrows: shape.0,
+            ncols: shape.1,
+            indptr: indptr,
+            indices: indices,
+            data: data,
+        })
+    }
+
     /// Create a borrowed CsMat matrix from raw data,
     /// without checking their validity
     ///
@@ -633,10 +1551,14 @@ impl<'a, N:'a, I: 'a + SpIndex> CsMatBase<N, I, &'a [I], &'a [I], &'a [N]> {
         if i >= self.outer_dims() || iend > self.outer_dims() {
             panic!("Out of bounds index");
         }
+        let (nrows, ncols) = match self.storage {
+            CSR => (count, self.cols()),
+            CSC => (self.rows(), count),
+        };
         CsMatViewI {
             storage: self.storage,
-            nrows: count,
-            ncols: self.cols(),
+            nrows: nrows,
+            ncols: ncols,
             indptr: &self.indptr[i..(iend+1)],
             indices: &self.indices[..],
             data: &self.data[..],
@@ -646,6 +1568,90 @@ impl<'a, N:'a, I: 'a + SpIndex> CsMatBase<N, I, &'a [I], &'a [I], &'a [N]> {
 }
 
 
+/// # Methods for sparse matrices sharing their structure
+///
+/// The index arrays of these matrices are reference counted, so that
+/// matrices with the same sparsity pattern do not duplicate them in memory.
+impl<N, I: SpIndex> CsMatBase<N, I, Arc<[I]>, Arc<[I]>, Vec<N>> {
+    /// Create a matrix with the same structure as this one, sharing it,
+    /// and the values `data`.
+    ///
+    /// # Panics
+    ///
+    /// If `data` does not have `nnz` elements.
+    pub fn with_data(&self, data: Vec<N>) -> CsMatSharedI<N, I> {
+        if data.len() != self.nnz() {
+            panic!("Dimension mismatch");
+        }
+        CsMatSharedI {
+            storage: self.storage,
+            nrows: self.nrows,
+            ncols: self.ncols,
+            indptr: self.indptr.clone(),
+            indices: self.indices.clone(),
+            data: data,
+        }
+    }
+
+    /// Check whether this matrix shares the structure of `other`, ie
+    /// whether both matrices were cloned from the same matrix or created
+    /// by `with_data`.
+    pub fn shares_structure_with(&self, other: &CsMatSharedI<N, I>) -> bool {
+        self.storage == other.storage
+            && Arc::ptr_eq(&self.indptr, &other.indptr)
+            && Arc::ptr_eq(&self.indices, &other.indices)
+    }
+
+    /// Return the values of this matrix, dropping its share of the
+    /// structure.
+    pub fn into_data(self) -> Vec<N> {
+        self.data
+    }
+}
+
+impl<N: Clone, I: SpIndex> Clone for CsMatBase<N, I, Arc<[I]>, Arc<[I]>, Vec<N>> {
+    fn clone(&self) -> Self {
+        self.with_data(self.data.clone())
+    }
+}
+
+impl<N: Clone, I: SpIndex> Clone for CsMatI<N, I> {
+    fn clone(&self) -> Self {
+        CsMatI {
+            storage: self.storage,
+            nrows: self.nrows,
+            ncols: self.ncols,
+            indptr: self.indptr.clone(),
+            indices: self.indices.clone(),
+            data: self.data.clone(),
+        }
+    }
+}
+
+/// # Constructor methods for mutable sparse matrix views
+///
+/// A mutable view can change the values of a matrix whose structure is
+/// borrowed immutably, for instance to update the values of a matrix
+/// stored in buffers owned elsewhere.
+impl<'a, N:'a, I: 'a + SpIndex> CsMatBase<N, I, &'a [I], &'a [I], &'a mut [N]> {
+    /// Create a mutably borrowed CsMat matrix from slices owned elsewhere,
+    /// with the same checks as `CsMatView::from_raw_parts`.
+    pub fn from_raw_parts_mut(storage: CompressedStorage, shape: Shape,
+                              indptr: &'a [I], indices: &'a [I],
+                              data: &'a mut [N])
+                              -> Result<CsMatViewMutI<'a, N, I>, SprsError> {
+        try!(check_raw_parts(storage, shape, indptr, indices, data.len()));
+        Ok(CsMatViewMutI {
+            storage: storage,
+            nrows: shape.0,
+            ncols: shape.1,
+            indptr: indptr,
+            indices: indices,
+            data: data,
+        })
+    }
+}
+
 /// # Common methods for all variants of compressed sparse matrices.
 impl<N, I, IptrStorage, IndStorage, DataStorage>
 CsMatBase<N, I, IptrStorage, IndStorage, DataStorage>
@@ -678,8 +1684,26 @@ where I: SpIndex,
     /// The number of non-zero elements this matrix stores.
     /// This is often relevant for the complexity of most sparse matrix
     /// algorithms, which are often linear in the number of non-zeros.
+    ///
+    /// For a view over a range of outer dimensions (see `outer_block_view`),
+    /// only the non-zeros of this range are counted.
     pub fn nnz(&self) -> usize {
-        self.indptr.last().unwrap().index()
+        let first = self.indptr.first().unwrap().index();
+        self.indptr.last().unwrap().index() - first
+    }
+
+    /// The range of `indices()` and `data()` holding the non-zeros of this
+    /// matrix, which does not start at zero for a view over a range of
+    /// outer dimensions
+    fn nnz_range(&self) -> Range<usize> {
+        let start = self.indptr[0].index();
+        start..(start + self.nnz())
+    }
+
+    /// The `indptr` of this matrix shifted to start at zero
+    fn rebased_indptr(&self) -> Vec<I> {
+        let start = self.indptr[0];
+        self.indptr.iter().map(|&i| i - start).collect()
     }
 
     /// Number of outer dimensions, that ie equal to self.rows() for a CSR
@@ -788,13 +1812,30 @@ where I: SpIndex,
     pub fn to_owned(&self) -> CsMatI<N, I>
     where N: Clone
     {
+        let range = self.nnz_range();
         CsMatI {
             storage: self.storage,
             nrows: self.nrows,
             ncols: self.ncols,
-            indptr: self.indptr.to_vec(),
-            indices: self.indices.to_vec(),
-            data: self.data.to_vec(),
+            indptr: self.rebased_indptr(),
+            indices: self.indices[range.clone()].to_vec(),
+            data: self.data[range].to_vec(),
+        }
+    }
+
+    /// Clone the matrix with another scalar type for its values, eg to
+    /// widen `f16` values to `f32` or `f32` values to `f64`.
+    pub fn cast_values<M>(&self) -> CsMatI<M, I>
+    where N: Clone + Into<M>,
+    {
+        let range = self.nnz_range();
+        CsMatI {
+            storage: self.storage,
+            nrows: self.nrows,
+            ncols: self.ncols,
+            indptr: self.rebased_indptr(),
+            indices: self.indices[range.clone()].to_vec(),
+            data: self.data[range].iter().map(|x| x.clone().into()).collect(),
         }
     }
 
@@ -808,15 +1849,18 @@ where I: SpIndex,
     where N: Clone + Into<N2>,
           I2: SpIndex,
     {
+        let range = self.nnz_range();
         let indptr = self.indptr.iter()
-                                .map(|i| I2::from_usize(i.index()))
+                                .map(|i| i.index() - range.start)
+                                .map(I2::from_usize)
                                 .collect();
-        let indices = self.indices.iter()
-                                  .map(|i| I2::from_usize(i.index()))
-                                  .collect();
-        let data = self.data.iter()
-                            .map(|x| x.clone().into())
-                            .collect();
+        let indices = self.indices[range.clone()]
+                          .iter()
+                          .map(|i| I2::from_usize(i.index()))
+                          .collect();
+        let data = self.data[range].iter()
+                                   .map(|x| x.clone().into())
+                                   .collect();
         CsMatI {
             storage: self.storage,
             nrows: self.nrows,
@@ -839,6 +1883,7 @@ where I: SpIndex,
         }
     }
 
+    #[cfg(feature = "std")]
     pub fn to_dense(&self) -> Array<N, Ix2>
     where N: Clone + Zero
     {
@@ -847,6 +1892,209 @@ where I: SpIndex,
         res
     }
 
+    /// Extract the rectangular block `(row_range, col_range)` of this matrix
+    /// into the caller-provided dense buffer `out`, which is overwritten:
+    /// the entries of the block that are not stored are set to zero.
+    ///
+    /// # Panics
+    ///
+    /// If the ranges are out of bounds or if the shape of `out` does not
+    /// match the shape of the block.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate ndarray;
+    /// extern crate sprs;
+    /// use sprs::CsMat;
+    /// use ndarray::{Array, arr2};
+    /// # fn main() {
+    /// let eye: CsMat<f64> = CsMat::eye(4);
+    /// let mut block = Array::from_elem((2, 3), 7.);
+    /// eye.to_dense_block((1..3, 0..3), block.view_mut());
+    /// assert_eq!(block, arr2(&[[0., 1., 0.], [0., 0., 1.]]));
+    /// # }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn to_dense_block(&self,
+                          (row_range, col_range): (Range<usize>, Range<usize>),
+                          mut out: ndarray::ArrayViewMut<N, Ix2>)
+    where N: Clone + Zero
+    {
+        if row_range.start > row_range.end || row_range.end > self.rows()
+            || col_range.start > col_range.end || col_range.end > self.cols()
+        {
+            panic!("Out of bounds index");
+        }
+        let block_shape = (row_range.end - row_range.start,
+                           col_range.end - col_range.start);
+        if out.shape() != &[block_shape.0, block_shape.1] {
+            panic!("Dimension mismatch");
+        }
+        out.fill(N::zero());
+        let (outer_range, inner_range) = match self.storage {
+            CSR => (row_range, col_range),
+            CSC => (col_range, row_range),
+        };
+        let inner_start = I::from_usize(inner_range.start);
+        for outer in outer_range.clone() {
+            let start = self.indptr[outer].index();
+            let stop = self.indptr[outer + 1].index();
+            let indices = &self.indices[start..stop];
+            let data = &self.data[start..stop];
+            let first = match indices.binary_search(&inner_start) {
+                Ok(pos) | Err(pos) => pos,
+            };
+            let local_outer = outer - outer_range.start;
+            for (inner, val) in indices[first..].iter().zip(&data[first..]) {
+                let inner = inner.index();
+                if inner >= inner_range.end {
+                    break;
+                }
+                let local_inner = inner - inner_range.start;
+                let pos = match self.storage {
+                    CSR => [local_outer, local_inner],
+                    CSC => [local_inner, local_outer],
+                };
+                out[pos] = val.clone();
+            }
+        }
+    }
+
+    /// The linear combination of the rows of this matrix given by
+    /// `(row, coefficient)` pairs, computed in a single pass over the
+    /// selected rows for CSR matrices, and over the whole matrix for CSC
+    /// ones. A row can be selected several times, its coefficients are
+    /// then summed.
+    ///
+    /// Columns are stored in the result as soon as a selected row has an
+    /// entry there, even if the contributions cancel out, so that the
+    /// structure does not depend on the values.
+    ///
+    /// # Panics
+    ///
+    /// If a row is out of bounds.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::{CsMat, CsVec};
+    /// let mat = CsMat::new((3, 3), vec![0, 2, 3, 4], vec![0, 2, 1, 2],
+    ///                      vec![1., 2., 3., 4.]);
+    /// let combination = mat.combine_rows(&[(0, 2.), (2, -1.)]);
+    /// assert_eq!(combination, CsVec::new(3, vec![0, 2], vec![2., 0.]));
+    /// ```
+    pub fn combine_rows(&self, coeffs: &[(usize, N)]) -> CsVecI<N, I>
+    where N: Num + Copy
+    {
+        if coeffs.iter().any(|&(row, _)| row >= self.rows()) {
+            panic!("Out of bounds index");
+        }
+        match self.storage {
+            CSR => {
+                let mut spa = SparseAccumulator::new(self.cols());
+                for &(row, coeff) in coeffs {
+                    spa.scatter_csvec(coeff, self.outer_view(row).unwrap());
+                }
+                spa.gather_csvec()
+            },
+            CSC => {
+                let mut row_coeffs = SparseAccumulator::new(self.rows());
+                for &(row, coeff) in coeffs {
+                    row_coeffs.scatter(row, coeff);
+                }
+                let mut indices = Vec::new();
+                let mut data = Vec::new();
+                for (col, vec) in self.outer_iterator().enumerate() {
+                    let mut acc = None;
+                    for (row, &val) in vec.iter() {
+                        if let Some(&coeff) = row_coeffs.get(row) {
+                            let sum = acc.unwrap_or(N::zero());
+                            acc = Some(sum + coeff * val);
+                        }
+                    }
+                    if let Some(val) = acc {
+                        indices.push(I::from_usize(col));
+                        data.push(val);
+                    }
+                }
+                CsVecI {
+                    dim: self.cols(),
+                    indices: indices,
+                    data: data,
+                }
+            },
+        }
+    }
+
+    /// The matrix `A + alpha I`, where `I` is the (rectangular) identity.
+    /// See `add_diag_assign` to update an owned matrix in place.
+    pub fn shift(&self, alpha: N) -> CsMatI<N, I>
+    where N: Num + Copy
+    {
+        let mut res = self.to_owned();
+        res.add_diag_assign(alpha);
+        res
+    }
+
+    /// Assign this matrix into the caller-provided dense buffer `out`,
+    /// which avoids allocating a new array as `to_dense` does.
+    ///
+    /// As for the free function `assign_to_dense`, `out` is not zeroed
+    /// beforehand: the entries which are not stored keep their value in
+    /// `out`. Use `to_dense_block` on the whole shape to overwrite them.
+    ///
+    /// # Panics
+    ///
+    /// If the shape of `out` does not match the shape of the matrix.
+    #[cfg(feature = "std")]
+    pub fn assign_to_dense(&self, out: ndarray::ArrayViewMut<N, Ix2>)
+    where N: Clone
+    {
+        assign_to_dense(out, self.view());
+    }
+
+    /// Iterate over the rows of this matrix by chunks of `chunk_rows` rows,
+    /// yielding the index of the first row of each chunk along with the
+    /// dense form of the chunk. The last chunk may have fewer rows.
+    ///
+    /// This allows processing matrices whose dense form is too large to be
+    /// allocated at once, eg by handing the tiles to a dense BLAS routine.
+    /// Both CSR and CSC matrices are supported.
+    ///
+    /// # Panics
+    ///
+    /// If `chunk_rows` is zero.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate ndarray;
+    /// extern crate sprs;
+    /// use sprs::CsMat;
+    /// use ndarray::arr2;
+    /// # fn main() {
+    /// let eye: CsMat<f64> = CsMat::eye(3);
+    /// let chunks: Vec<_> = eye.dense_row_chunks(2).collect();
+    /// assert_eq!(chunks.len(), 2);
+    /// assert_eq!(chunks[0].0, 0);
+    /// assert_eq!(chunks[0].1, arr2(&[[1., 0., 0.], [0., 1., 0.]]));
+    /// assert_eq!(chunks[1].0, 2);
+    /// assert_eq!(chunks[1].1, arr2(&[[0., 0., 1.]]));
+    /// # }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn dense_row_chunks(&self, chunk_rows: usize)
+                            -> DenseRowChunks<N, I>
+    {
+        assert!(chunk_rows > 0, "chunks need at least one row");
+        DenseRowChunks {
+            mat: self.view(),
+            chunk_rows: chunk_rows,
+            next_row: 0,
+        }
+    }
+
     /// Return an outer iterator for the matrix
     ///
     /// This can be used for iterating over the rows (resp. cols) of
@@ -874,6 +2122,88 @@ where I: SpIndex,
         }
     }
 
+    /// Iterate over the non-zeros of this matrix, yielding
+    /// `(row, col, &value)` tuples in storage order, whatever the storage.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::CsMat;
+    /// let mat = CsMat::new_csc((2, 2), vec![0, 1, 2], vec![1, 0], vec![3, 4]);
+    /// let triplets: Vec<_> = mat.iter().collect();
+    /// assert_eq!(triplets, vec![(1, 0, &3), (0, 1, &4)]);
+    /// ```
+    pub fn iter(&self) -> CsMatIter<N, I> {
+        CsMatIter {
+            storage: self.storage,
+            outer: 0,
+            pos: self.indptr[0].index(),
+            indptr: &self.indptr[..],
+            indices: &self.indices[..],
+            data: &self.data[..],
+        }
+    }
+
+    /// Iterate over the non-zeros of this matrix, yielding
+    /// `((row, col), &value)` tuples in storage order, following the
+    /// convention of ndarray's `indexed_iter`.
+    pub fn indexed_iter(&self) -> CsMatIndexedIter<N, I> {
+        CsMatIndexedIter {
+            iter: self.iter(),
+        }
+    }
+
+    /// Iterate jointly over the outer dimensions of this matrix and of
+    /// `other`, yielding for each outer index an iterator over the union of
+    /// the non-zeros of both matrices, as
+    /// `(inner_ind, Option<&lhs_val>, Option<&rhs_val>)` tuples sorted by
+    /// inner index.
+    ///
+    /// This is the building block of binary operations on the sparsity
+    /// patterns of two matrices.
+    ///
+    /// # Panics
+    ///
+    /// - on dimension mismatch
+    /// - if the matrices do not have the same storage, use
+    ///   `to_other_storage` to convert one of them
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::CsMat;
+    /// let a = CsMat::new((2, 3), vec![0, 2, 3], vec![0, 2, 1], vec![1, 2, 3]);
+    /// let b = CsMat::new((2, 3), vec![0, 1, 1], vec![2], vec![5]);
+    /// // the largest value of each non-zero, absent values counting as zero
+    /// let mut max = Vec::new();
+    /// for (row, entries) in a.zip_outer(&b) {
+    ///     for (col, lhs, rhs) in entries {
+    ///         let val = *lhs.unwrap_or(&0).max(rhs.unwrap_or(&0));
+    ///         max.push((row, col, val));
+    ///     }
+    /// }
+    /// assert_eq!(max, vec![(0, 0, 1), (0, 2, 5), (1, 1, 3)]);
+    /// ```
+    pub fn zip_outer<'a, N2, IpS2, IS2, DS2>(
+        &'a self, other: &'a CsMatBase<N2, I, IpS2, IS2, DS2>)
+    -> ZipOuter<'a, N, N2, I>
+    where IpS2: Deref<Target=[I]>,
+          IS2: Deref<Target=[I]>,
+          DS2: Deref<Target=[N2]>,
+    {
+        if self.shape() != other.shape() {
+            panic!("Dimension mismatch");
+        }
+        if self.storage() != other.storage() {
+            panic!("Storage mismatch");
+        }
+        ZipOuter {
+            outer: 0,
+            left: self.outer_iterator(),
+            right: other.outer_iterator(),
+        }
+    }
+
     /// Return an outer iterator over P*A, as well as the proper permutation
     /// for iterating over the inner dimension of P*A*P^T
     /// Unstable
@@ -911,6 +2241,84 @@ where I: SpIndex,
         })
     }
 
+    /// Iterate over the non-zeros of the i-th inner dimension (eg i-th
+    /// column for a CSR matrix), yielding their outer index and value in
+    /// increasing outer order.
+    ///
+    /// This does not require converting the matrix to the other storage,
+    /// but costs a binary search per outer dimension. When many inner
+    /// dimensions need to be traversed, `to_other_storage` is cheaper.
+    ///
+    /// # Panics
+    ///
+    /// If `i >= self.inner_dims()`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::CsMat;
+    /// let mat = CsMat::new((3, 3), vec![0, 2, 3, 4],
+    ///                      vec![0, 2, 2, 1], vec![1., 2., 3., 4.]);
+    /// let col: Vec<_> = mat.inner_axis_iterator(2).collect();
+    /// assert_eq!(col, vec![(0, &2.), (1, &3.)]);
+    /// ```
+    pub fn inner_axis_iterator(&self, i: usize) -> InnerAxisIterator<N, I> {
+        if i >= self.inner_dims() {
+            panic!("Out of bounds index");
+        }
+        InnerAxisIterator {
+            inner: I::from_usize(i),
+            outer_ind_iter: 0..self.outer_dims(),
+            indptr: &self.indptr[..],
+            indices: &self.indices[..],
+            data: &self.data[..],
+        }
+    }
+
+    /// Get a view into the contiguous range of outer dimensions `range`
+    /// (eg a block of rows for a CSR matrix), without copying.
+    ///
+    /// The view borrows the `indptr` of this matrix from `range.start`, so
+    /// its `indptr()` starts at the offset `indptr[range.start]` instead of
+    /// zero. Its `indices()` and `data()` are the prefixes of the ones of
+    /// this matrix ending with the block, and are indexed by the values of
+    /// its `indptr()`. Row-block partitions for parallel algorithms are thus
+    /// cheap to build. Use `to_owned` to get a standalone copy of the block.
+    ///
+    /// # Panics
+    ///
+    /// If `range.start > range.end` or `range.end > self.outer_dims()`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::CsMat;
+    /// let eye: CsMat<f64> = CsMat::eye(5);
+    /// let block = eye.outer_block_view(1..3);
+    /// assert_eq!(block.shape(), (2, 5));
+    /// assert_eq!(block.nnz(), 2);
+    /// assert_eq!(block.get(0, 1), Some(&1.));
+    /// ```
+    pub fn outer_block_view(&self, range: Range<usize>) -> CsMatViewI<N, I> {
+        if range.start > range.end || range.end > self.outer_dims() {
+            panic!("Out of bounds index");
+        }
+        let count = range.end - range.start;
+        let (nrows, ncols) = match self.storage {
+            CSR => (count, self.ncols),
+            CSC => (self.nrows, count),
+        };
+        let end = self.indptr[range.end].index();
+        CsMatViewI {
+            storage: self.storage,
+            nrows: nrows,
+            ncols: ncols,
+            indptr: &self.indptr[range.start..(range.end + 1)],
+            indices: &self.indices[..end],
+            data: &self.data[..end],
+        }
+    }
+
     /// Iteration on outer blocks of size block_size
     pub fn outer_block_iter(&self, block_size: usize
                            ) -> ChunkOuterBlocks<N, I> {
@@ -929,15 +2337,245 @@ where I: SpIndex,
         }
     }
 
-    pub fn map<F>(&self, f: F) -> CsMatI<N, I>
-    where F: FnMut(&N) -> N,
-          N: Clone
-    {
-        let mut res = self.to_owned();
-        res.map_inplace(f);
-        res
-    }
-
+    /// Sum of two matrices, returning an error instead of panicking if
+    /// their shapes differ, eg when the operands come from untrusted input.
+    pub fn try_add<IpS2, IS2, DS2>(&self,
+                                   rhs: &CsMatBase<N, I, IpS2, IS2, DS2>)
+                                   -> Result<CsMatI<N, I>, SprsError>
+    where N: Copy + Num + Default,
+          IpS2: Deref<Target=[I]>,
+          IS2: Deref<Target=[I]>,
+          DS2: Deref<Target=[N]>,
+    {
+        try!(self.check_same_shape("add", rhs.shape()));
+        Ok(self + rhs)
+    }
+
+    /// Difference of two matrices, returning an error instead of panicking
+    /// if their shapes differ.
+    pub fn try_sub<IpS2, IS2, DS2>(&self,
+                                   rhs: &CsMatBase<N, I, IpS2, IS2, DS2>)
+                                   -> Result<CsMatI<N, I>, SprsError>
+    where N: Copy + Num + Default,
+          IpS2: Deref<Target=[I]>,
+          IS2: Deref<Target=[I]>,
+          DS2: Deref<Target=[N]>,
+    {
+        try!(self.check_same_shape("sub", rhs.shape()));
+        Ok(self - rhs)
+    }
+
+    /// Product of two matrices, returning an error instead of panicking if
+    /// the number of columns of this matrix differs from the number of rows
+    /// of `rhs`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::CsMat;
+    /// use sprs::errors::SprsError;
+    /// let a: CsMat<f64> = CsMat::eye(3);
+    /// let b: CsMat<f64> = CsMat::eye(4);
+    /// assert_eq!(a.try_mul(&b),
+    ///            Err(SprsError::DimensionMismatch {
+    ///                op: "mul",
+    ///                expected: (3, 4),
+    ///                found: (4, 4),
+    ///            }));
+    /// ```
+    pub fn try_mul<IpS2, IS2, DS2>(&self,
+                                   rhs: &CsMatBase<N, I, IpS2, IS2, DS2>)
+                                   -> Result<CsMatI<N, I>, SprsError>
+    where N: Clone + Num + Default,
+          IpS2: Deref<Target=[I]>,
+          IS2: Deref<Target=[I]>,
+          DS2: Deref<Target=[N]>,
+    {
+        if self.cols() != rhs.rows() {
+            return Err(SprsError::DimensionMismatch {
+                op: "mul",
+                expected: (self.cols(), rhs.cols()),
+                found: rhs.shape(),
+            });
+        }
+        Ok(self * rhs)
+    }
+
+    fn check_same_shape(&self, op: &'static str, shape: Shape)
+                        -> Result<(), SprsError> {
+        if self.shape() != shape {
+            return Err(SprsError::DimensionMismatch {
+                op: op,
+                expected: self.shape(),
+                found: shape,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn map<F>(&self, f: F) -> CsMatI<N, I>
+    where F: FnMut(&N) -> N,
+          N: Clone
+    {
+        let mut res = self.to_owned();
+        res.map_inplace(f);
+        res
+    }
+
+    /// The union of the sparsity patterns of two matrices, as a matrix
+    /// holding `true` at each location where either matrix has a non-zero.
+    /// The result has the same storage as this matrix.
+    ///
+    /// # Panics
+    ///
+    /// If the shapes of the matrices do not match.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::CsMat;
+    /// let eye: CsMat<f64> = CsMat::eye(3);
+    /// let upper = CsMat::new((3, 3), vec![0, 2, 3, 3],
+    ///                        vec![1, 2, 2], vec![1, 2, 3]);
+    /// let union = eye.union_pattern(&upper);
+    /// assert_eq!(union.indptr(), &[0, 3, 5, 6]);
+    /// assert_eq!(eye.intersect_pattern(&upper).nnz(), 0);
+    /// assert_eq!(union.difference_pattern(&eye).indices(), &[1, 2, 2]);
+    /// ```
+    pub fn union_pattern<N2, IpS2, IS2, DS2>(&self,
+                                             rhs: &CsMatBase<N2, I, IpS2,
+                                                             IS2, DS2>)
+                                             -> CsMatI<bool, I>
+    where IpS2: Deref<Target=[I]>,
+          IS2: Deref<Target=[I]>,
+          DS2: Deref<Target=[N2]>
+    {
+        self.pattern_op(rhs, |in_lhs, in_rhs| in_lhs || in_rhs)
+    }
+
+    /// The intersection of the sparsity patterns of two matrices, as a
+    /// matrix holding `true` at each location where both matrices have a
+    /// non-zero. The result has the same storage as this matrix.
+    ///
+    /// # Panics
+    ///
+    /// If the shapes of the matrices do not match.
+    pub fn intersect_pattern<N2, IpS2, IS2, DS2>(&self,
+                                                 rhs: &CsMatBase<N2, I, IpS2,
+                                                                 IS2, DS2>)
+                                                 -> CsMatI<bool, I>
+    where IpS2: Deref<Target=[I]>,
+          IS2: Deref<Target=[I]>,
+          DS2: Deref<Target=[N2]>
+    {
+        self.pattern_op(rhs, |in_lhs, in_rhs| in_lhs && in_rhs)
+    }
+
+    /// The difference of the sparsity patterns of two matrices, as a
+    /// matrix holding `true` at each location where this matrix has a
+    /// non-zero but `rhs` has not. The result has the same storage as this
+    /// matrix.
+    ///
+    /// # Panics
+    ///
+    /// If the shapes of the matrices do not match.
+    pub fn difference_pattern<N2, IpS2, IS2, DS2>(&self,
+                                                  rhs: &CsMatBase<N2, I, IpS2,
+                                                                  IS2, DS2>)
+                                                  -> CsMatI<bool, I>
+    where IpS2: Deref<Target=[I]>,
+          IS2: Deref<Target=[I]>,
+          DS2: Deref<Target=[N2]>
+    {
+        self.pattern_op(rhs, |in_lhs, in_rhs| in_lhs && !in_rhs)
+    }
+
+    fn pattern_op<N2, IpS2, IS2, DS2, F>(&self,
+                                         rhs: &CsMatBase<N2, I, IpS2, IS2,
+                                                         DS2>,
+                                         keep: F)
+                                         -> CsMatI<bool, I>
+    where IpS2: Deref<Target=[I]>,
+          IS2: Deref<Target=[I]>,
+          DS2: Deref<Target=[N2]>,
+          F: Fn(bool, bool) -> bool
+    {
+        assert_eq!(self.shape(), rhs.shape(), "Dimension mismatch");
+        if self.storage() != rhs.storage() {
+            let rhs_pattern = CsMatBase {
+                storage: rhs.storage,
+                nrows: rhs.nrows,
+                ncols: rhs.ncols,
+                indptr: &rhs.indptr[..],
+                indices: &rhs.indices[..],
+                data: vec![true; rhs.nnz()],
+            };
+            return self.pattern_op(&rhs_pattern.to_other_storage(), keep);
+        }
+        let mut indptr = Vec::with_capacity(self.outer_dims() + 1);
+        indptr.push(I::zero());
+        let mut indices = Vec::new();
+        for (lhs_vec, rhs_vec) in self.outer_iterator()
+                                      .zip(rhs.outer_iterator()) {
+            utils::merge_patterns(lhs_vec.indices(), rhs_vec.indices(),
+                                  &mut indices, &keep);
+            indptr.push(I::from_usize(indices.len()));
+        }
+        CsMatI {
+            storage: self.storage,
+            nrows: self.nrows,
+            ncols: self.ncols,
+            indptr: indptr,
+            data: vec![true; indices.len()],
+            indices: indices,
+        }
+    }
+
+    /// Keep only the `k` largest elements of each row, implicit zeros
+    /// included (see `CsVecBase::top_k`). The result has the same storage
+    /// as this matrix.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::CsMat;
+    /// let scores = CsMat::new((2, 4),
+    ///                         vec![0, 3, 5],
+    ///                         vec![0, 1, 3, 1, 2],
+    ///                         vec![0.5, 0.9, 0.7, 0.2, 0.1]);
+    /// let best = scores.top_k_per_row(2);
+    /// assert_eq!(best, CsMat::new((2, 4),
+    ///                             vec![0, 2, 4],
+    ///                             vec![1, 3, 1, 2],
+    ///                             vec![0.9, 0.7, 0.2, 0.1]));
+    /// assert_eq!(scores.to_csc().top_k_per_row(2), best.to_csc());
+    /// ```
+    pub fn top_k_per_row(&self, k: usize) -> CsMatI<N, I>
+    where N: Num + Copy + PartialOrd + Default
+    {
+        if self.is_csc() {
+            return self.to_csr().top_k_per_row(k).to_csc();
+        }
+        let mut indptr = Vec::with_capacity(self.rows() + 1);
+        indptr.push(I::zero());
+        let mut indices = Vec::new();
+        let mut data = Vec::new();
+        for row in self.outer_iterator() {
+            let best = row.top_k(k);
+            indices.extend_from_slice(best.indices());
+            data.extend_from_slice(best.data());
+            indptr.push(I::from_usize(indices.len()));
+        }
+        CsMatI {
+            storage: CSR,
+            nrows: self.nrows,
+            ncols: self.ncols,
+            indptr: indptr,
+            indices: indices,
+            data: data,
+        }
+    }
+
     /// Access an element given its outer_ind and inner_ind.
     /// Will return None if there is no non-zero element at this location.
     ///
@@ -993,6 +2631,39 @@ where I: SpIndex,
     /// * indices is sorted for each outer slice
     /// * indices are lower than inner_dims()
     pub fn check_compressed_structure(&self) -> Result<(), SprsError> {
+        try!(self.check_indptr_structure());
+
+        // check that the indices are sorted for each row
+        for vec in self.outer_iterator() {
+            try!(vec.check_structure());
+        }
+
+        Ok(())
+    }
+
+    /// Check the structure of the matrix as specified by `level`, see the
+    /// `validation` module. With `ValidationLevel::Full`, this is
+    /// `check_compressed_structure`.
+    pub fn validate(&self, level: ValidationLevel) -> Result<(), SprsError> {
+        match level {
+            ValidationLevel::Full => self.check_compressed_structure(),
+            ValidationLevel::CheapOnly => {
+                try!(self.check_indptr_structure());
+                debug_assert!(self.check_compressed_structure().is_ok(),
+                              "Invalid compressed structure");
+                Ok(())
+            },
+            ValidationLevel::None => {
+                debug_assert!(self.check_compressed_structure().is_ok(),
+                              "Invalid compressed structure");
+                Ok(())
+            },
+        }
+    }
+
+    /// The checks of `check_compressed_structure` whose cost does not
+    /// depend on the number of non-zeros
+    fn check_indptr_structure(&self) -> Result<(), SprsError> {
         let outer = self.outer_dims();
 
         if self.indptr.len() != outer + 1 {
@@ -1002,7 +2673,7 @@ where I: SpIndex,
             panic!("Indices and data lengths do not match");
         }
         let nnz = self.indices.len();
-        if nnz != self.nnz() {
+        if nnz != self.indptr[outer].index() {
             panic!("Indices length and inpdtr's nnz do not match");
         }
         if let Some(&max_indptr) = self.indptr.iter().max() {
@@ -1025,12 +2696,7 @@ where I: SpIndex,
         if ! self.indptr.deref()
                         .windows(2)
                         .all(|x| x[0].index() <= x[1].index()) {
-            return Err(SprsError::UnsortedIndptr);
-        }
-
-        // check that the indices are sorted for each row
-        for vec in self.outer_iterator() {
-            try!(vec.check_structure());
+            return Err(StructureError::UnsortedIndptr.into());
         }
 
         Ok(())
@@ -1109,6 +2775,76 @@ DataStorage: DerefMut<Target=[N]> {
         &mut self.data[..]
     }
 
+    /// Get a mutable view into this matrix, allowing to change its values
+    /// but not its structure
+    pub fn view_mut(&mut self) -> CsMatViewMutI<N, I> {
+        CsMatViewMutI {
+            storage: self.storage,
+            nrows: self.nrows,
+            ncols: self.ncols,
+            indptr: &self.indptr[..],
+            indices: &self.indices[..],
+            data: &mut self.data[..],
+        }
+    }
+
+    /// Set all the stored values to zero, keeping the sparsity pattern
+    pub fn zero_values(&mut self) where N: Zero {
+        for val in self.data_mut() {
+            *val = N::zero();
+        }
+    }
+
+    /// Refill the values of the matrix from triplets, keeping its sparsity
+    /// pattern, eg to update a matrix whose structure does not change
+    /// between the time steps of a simulation.
+    ///
+    /// The stored values not listed in the triplets are set to zero, and
+    /// the values of repeated triplets are summed, as for `TriMat`.
+    ///
+    /// # Errors
+    ///
+    /// `IllegalStructure(MissingEntry)` if a triplet is not part of the
+    /// sparsity pattern, in which case the matrix is left unchanged.
+    ///
+    /// # Panics
+    ///
+    /// If the triplet arrays do not have the same length.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::CsMat;
+    /// let mut mat: CsMat<f64> = CsMat::eye(3);
+    /// mat.assign_values_from_triplets(&[0, 2, 2], &[0, 2, 2], &[4., 1., 2.])
+    ///    .unwrap();
+    /// assert_eq!(mat.data(), &[4., 0., 3.]);
+    /// assert!(mat.assign_values_from_triplets(&[0], &[1], &[1.]).is_err());
+    /// ```
+    pub fn assign_values_from_triplets(&mut self,
+                                       rows: &[usize],
+                                       cols: &[usize],
+                                       vals: &[N])
+                                       -> Result<(), SprsError>
+    where N: Num + Copy
+    {
+        assert_eq!(rows.len(), cols.len(), "Dimension mismatch");
+        assert_eq!(rows.len(), vals.len(), "Dimension mismatch");
+        let mut positions = Vec::with_capacity(rows.len());
+        for (&row, &col) in rows.iter().zip(cols.iter()) {
+            match self.nnz_index(row, col) {
+                Some(NnzIndex(pos)) => positions.push(pos),
+                None => return Err(StructureError::MissingEntry.into()),
+            }
+        }
+        self.zero_values();
+        let data = self.data_mut();
+        for (pos, &val) in positions.into_iter().zip(vals.iter()) {
+            data[pos] = data[pos] + val;
+        }
+        Ok(())
+    }
+
     /// Sparse matrix self-multiplication by a scalar
     pub fn scale(&mut self, val: N) where N: Num + Copy {
         for data in self.data_mut() {
@@ -1209,6 +2945,76 @@ DataStorage: DerefMut<Target=[N]> {
         }
     }
 
+    /// Split the matrix into two blocks of outer dimensions, the first one
+    /// holding the outer dimensions before `at`, and the second one the
+    /// remaining ones, eg the rows `0..at` and `at..rows` of a CSR matrix.
+    ///
+    /// The values of the blocks are disjoint mutable borrows, so the blocks
+    /// can be modified concurrently, for instance in different threads.
+    /// The structure is still borrowed immutably, except for the index
+    /// pointers which are copied to start at zero in each block. The blocks
+    /// can themselves be split further.
+    ///
+    /// # Panics
+    ///
+    /// If `at` is greater than the outer dimension.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::CsMat;
+    /// let mut mat = CsMat::new((3, 3),
+    ///                          vec![0, 2, 3, 4],
+    ///                          vec![0, 2, 1, 2],
+    ///                          vec![1., 2., 3., 4.]);
+    /// {
+    ///     let (mut top, mut bottom) = mat.split_outer_mut(1);
+    ///     top.scale(2.);
+    ///     bottom.scale(-1.);
+    /// }
+    /// assert_eq!(mat.data(), &[2., 4., -3., -4.]);
+    /// ```
+    pub fn split_outer_mut(&mut self, at: usize)
+                           -> (CsMatBlockMutI<N, I>, CsMatBlockMutI<N, I>) {
+        let outer_dims = self.outer_dims();
+        if at > outer_dims {
+            panic!("Out of bounds index");
+        }
+        let start = self.indptr[0].index();
+        let mid = self.indptr[at].index();
+        let end = self.indptr[outer_dims].index();
+        let rebase = |indptr: &[I], offset: usize| -> Vec<I> {
+            indptr.iter().map(|&i| I::from_usize(i.index() - offset)).collect()
+        };
+        let first_indptr = rebase(&self.indptr[..at + 1], start);
+        let second_indptr = rebase(&self.indptr[at..], mid);
+        let (first_shape, second_shape) = match self.storage {
+            CSR => ((at, self.ncols), (self.nrows - at, self.ncols)),
+            CSC => ((self.nrows, at), (self.nrows, self.ncols - at)),
+        };
+        let (first_indices, second_indices) =
+            self.indices[start..end].split_at(mid - start);
+        let (first_data, second_data) =
+            self.data[start..end].split_at_mut(mid - start);
+        let first = CsMatBlockMutI {
+            storage: self.storage,
+            nrows: first_shape.0,
+            ncols: first_shape.1,
+            indptr: first_indptr,
+            indices: first_indices,
+            data: first_data,
+        };
+        let second = CsMatBlockMutI {
+            storage: self.storage,
+            nrows: second_shape.0,
+            ncols: second_shape.1,
+            indptr: second_indptr,
+            indices: second_indices,
+            data: second_data,
+        };
+        (first, second)
+    }
+
 }
 
 impl<N, I, IptrStorage, IndStorage, DataStorage>
@@ -1319,8 +3125,8 @@ pub mod raw {
                                                      indices: &mut[I],
                                                      data: &mut [N]) {
         assert_eq!(indptr.len(), mat.inner_dims() + 1);
-        assert_eq!(indices.len(), mat.indices().len());
-        assert_eq!(data.len(), mat.data().len());
+        assert_eq!(indices.len(), mat.nnz());
+        assert_eq!(data.len(), mat.nnz());
 
         assert!(indptr.iter().all(|x| *x == I::zero()));
 
@@ -1452,7 +3258,7 @@ sparse_scalar_mul!(f64);
 impl<'a, 'b, N, I, IpS1, IS1, DS1, IpS2, IS2, DS2>
 Mul<&'b CsMatBase<N, I, IpS2, IS2, DS2>>
 for &'a CsMatBase<N, I, IpS1, IS1, DS1>
-where N: 'a + Copy + Num + Default,
+where N: 'a + Clone + Num + Default,
       I: 'a + SpIndex,
       IpS1: 'a + Deref<Target=[I]>,
       IS1: 'a + Deref<Target=[I]>,
@@ -1487,10 +3293,11 @@ where N: 'a + Copy + Num + Default,
     }
 }
 
+#[cfg(feature = "std")]
 impl<'a, 'b, N, I, IpS, IS, DS, DS2>
 Add<&'b ArrayBase<DS2, Ix2>>
 for &'a CsMatBase<N, I, IpS, IS, DS>
-where N: 'a + Copy + Num + Default,
+where N: 'a + Clone + Num + Default,
       I: 'a + SpIndex,
       IpS: 'a + Deref<Target=[I]>,
       IS: 'a + Deref<Target=[I]>,
@@ -1534,10 +3341,11 @@ where N: 'a + Copy + Num + Default,
     }
 }
 
+#[cfg(feature = "std")]
 impl<'a, 'b, N, I, IpS, IS, DS, DS2>
 Mul<&'b ArrayBase<DS2, Ix2>>
 for &'a CsMatBase<N, I, IpS, IS, DS>
-where N: 'a + Copy + Num + Default,
+where N: 'a + Clone + Num + Default,
       I: 'a + SpIndex,
       IpS: 'a + Deref<Target=[I]>,
       IS: 'a + Deref<Target=[I]>,
@@ -1585,10 +3393,11 @@ where N: 'a + Copy + Num + Default,
     }
 }
 
+#[cfg(feature = "std")]
 impl<'a, 'b, N, I, IpS, IS, DS, DS2>
 Mul<&'b ArrayBase<DS2, Ix1>>
 for &'a CsMatBase<N, I, IpS, IS, DS>
-where N: 'a + Copy + Num + Default,
+where N: 'a + Clone + Num + Default,
       I: 'a + SpIndex,
       IpS: 'a + Deref<Target=[I]>,
       IS: 'a + Deref<Target=[I]>,
@@ -1597,28 +3406,95 @@ where N: 'a + Copy + Num + Default,
     type Output = Array<N, Ix1>;
 
     fn mul(self, rhs: &'b ArrayBase<DS2, Ix1>) -> Array<N, Ix1> {
-        let rows = self.rows();
-        let cols = rhs.shape()[0];
-        let rhs_reshape = rhs.view().into_shape((cols, 1)).unwrap();
-        let mut res = Array::zeros(rows);
-        {
-            let res_reshape = res.view_mut().into_shape((rows, 1)).unwrap();
-            match self.storage() {
-                CSR => {
-                    prod::csr_mulacc_dense_rowmaj(self.view(),
-                                                  rhs_reshape,
-                                                  res_reshape
-                                                 );
-                }
-                CSC => {
-                    prod::csc_mulacc_dense_rowmaj(self.view(),
-                                                  rhs_reshape,
-                                                  res_reshape
-                                                 );
-                }
+        let owned: Vec<N>;
+        let rhs = match rhs.as_slice() {
+            Some(rhs) => rhs,
+            None => {
+                owned = rhs.iter().cloned().collect();
+                &owned[..]
+            }
+        };
+        let mut res = vec![N::zero(); self.rows()];
+        match self.storage() {
+            CSR => prod::mul_acc_mat_vec_csr(self.view(), rhs, &mut res),
+            CSC => prod::mul_acc_mat_vec_csc(self.view(), rhs, &mut res),
+        }
+        Array::from_vec(res)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a, 'b, N, I, IpS, IS, DS, DS2>
+Mul<&'b CsMatBase<N, I, IpS, IS, DS>>
+for &'a ArrayBase<DS2, Ix2>
+where N: 'a + Clone + Num + Default,
+      I: 'b + SpIndex,
+      IpS: 'b + Deref<Target=[I]>,
+      IS: 'b + Deref<Target=[I]>,
+      DS: 'b + Deref<Target=[N]>,
+      DS2: 'a + ndarray::Data<Elem=N> {
+    type Output = Array<N, Ix2>;
+
+    fn mul(self, rhs: &'b CsMatBase<N, I, IpS, IS, DS>) -> Array<N, Ix2> {
+        // (lhs * rhs)^T = rhs^T * lhs^T, where rhs^T has the other storage
+        // and lhs^T the other memory layout, so the sparse-dense kernels
+        // can be used by writing into the transposed result.
+        let rows = self.shape()[0];
+        let cols = rhs.cols();
+        let rhs_t = rhs.transpose_view();
+        let lhs_t = self.t();
+        match (rhs_t.storage(), lhs_t.is_standard_layout()) {
+            (CSR, true) => {
+                let mut res = Array::zeros((rows, cols).f());
+                prod::csr_mulacc_dense_rowmaj(rhs_t,
+                                              lhs_t,
+                                              res.view_mut().reversed_axes()
+                                             );
+                res
+            }
+            (CSR, false) => {
+                let mut res = Array::zeros((rows, cols));
+                prod::csr_mulacc_dense_colmaj(rhs_t,
+                                              lhs_t,
+                                              res.view_mut().reversed_axes()
+                                             );
+                res
+            }
+            (CSC, true) => {
+                let mut res = Array::zeros((rows, cols).f());
+                prod::csc_mulacc_dense_rowmaj(rhs_t,
+                                              lhs_t,
+                                              res.view_mut().reversed_axes()
+                                             );
+                res
+            }
+            (CSC, false) => {
+                let mut res = Array::zeros((rows, cols));
+                prod::csc_mulacc_dense_colmaj(rhs_t,
+                                              lhs_t,
+                                              res.view_mut().reversed_axes()
+                                             );
+                res
             }
         }
-        res
+    }
+}
+
+/// Product of a dense row vector by a sparse matrix, `x^T A`.
+#[cfg(feature = "std")]
+impl<'a, 'b, N, I, IpS, IS, DS, DS2>
+Mul<&'b CsMatBase<N, I, IpS, IS, DS>>
+for &'a ArrayBase<DS2, Ix1>
+where N: 'a + Clone + Num + Default,
+      I: 'b + SpIndex,
+      IpS: 'b + Deref<Target=[I]>,
+      IS: 'b + Deref<Target=[I]>,
+      DS: 'b + Deref<Target=[N]>,
+      DS2: 'a + ndarray::Data<Elem=N> {
+    type Output = Array<N, Ix1>;
+
+    fn mul(self, rhs: &'b CsMatBase<N, I, IpS, IS, DS>) -> Array<N, Ix1> {
+        &rhs.transpose_view() * self
     }
 }
 
@@ -1707,13 +3583,43 @@ impl<'a, N: 'a, I: 'a + SpIndex> Iterator for ChunkOuterBlocks<'a, N, I> {
     }
 }
 
+/// An iterator over the dense forms of chunks of consecutive rows of a
+/// matrix, see `CsMatBase::dense_row_chunks`
+#[cfg(feature = "std")]
+pub struct DenseRowChunks<'a, N: 'a, I: 'a + SpIndex> {
+    mat: CsMatViewI<'a, N, I>,
+    chunk_rows: usize,
+    next_row: usize,
+}
+
+#[cfg(feature = "std")]
+impl<'a, N, I> Iterator for DenseRowChunks<'a, N, I>
+where N: 'a + Clone + Zero,
+      I: 'a + SpIndex,
+{
+    type Item = (usize, Array<N, Ix2>);
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        let start = self.next_row;
+        if start >= self.mat.rows() {
+            return None;
+        }
+        let end = self.mat.rows().min(start + self.chunk_rows);
+        let mut chunk = Array::zeros((end - start, self.mat.cols()));
+        self.mat.to_dense_block((start..end, 0..self.mat.cols()),
+                                chunk.view_mut());
+        self.next_row = end;
+        Some((start, chunk))
+    }
+}
 
 #[cfg(test)]
 mod test {
-    use sparse::{CsMatView, CsMat, CsMatI};
+    use std::sync::Arc;
+    use sparse::{CsMatBase, CsMatView, CsMatViewMut, CsMat, CsMatI, CsVec};
     use super::CompressedStorage::{CSC, CSR};
-    use errors::SprsError;
-    use test_data::{mat1, mat1_csc, mat1_times_2};
+    use errors::StructureError;
+    use test_data::{mat1, mat1_csc, mat1_times_2, mat2};
+    use ndarray::{Array, ShapeBuilder, arr2};
 
     #[test]
     fn test_new_csr_success() {
@@ -1724,6 +3630,76 @@ mod test {
         assert!(m.is_ok());
     }
 
+    #[test]
+    fn from_raw_parts() {
+        let indptr: &[usize] = &[0, 1, 2, 3];
+        let indices: &[usize] = &[0, 1, 2];
+        let data: &[f64] = &[1., 1., 1.];
+        let m = CsMatView::from_raw_parts(CSR, (3, 3), indptr, indices, data);
+        assert_eq!(m, CsMatView::new_view(CSR, (3, 3), indptr, indices, data));
+
+        let res = CsMatView::from_raw_parts(CSR, (3, 3), &[0, 1, 2],
+                                            indices, data);
+        assert_eq!(res, Err(StructureError::BadIndptrLength.into()));
+        let res = CsMatView::from_raw_parts(CSC, (3, 3), &[0, 1, 2, 4],
+                                            indices, data);
+        assert_eq!(res, Err(StructureError::BadNnzCount.into()));
+        let res = CsMatView::from_raw_parts(CSR, (3, 3), indptr,
+                                            indices, &[1., 1.]);
+        assert_eq!(res, Err(StructureError::BadNnzCount.into()));
+        let res = CsMatView::from_raw_parts(CSR, (3, 3), indptr,
+                                            &[0, 1, 3], data);
+        assert_eq!(res, Err(StructureError::OutOfBoundsIndex.into()));
+        let res = CsMatView::from_raw_parts(CSR, (3, 3), &[0, 2, 2, 3],
+                                            &[1, 0, 2], data);
+        assert_eq!(res, Err(StructureError::NonSortedIndices.into()));
+        let res = CsMatView::from_raw_parts(CSR, (3, 3), &[0, 2, 1, 3],
+                                            indices, data);
+        assert_eq!(res, Err(StructureError::UnsortedIndptr.into()));
+    }
+
+    #[test]
+    fn assign_values_from_triplets() {
+        let mut mat = mat1_csc();
+        let res = mat.assign_values_from_triplets(&[0, 3, 0], &[2, 1, 3],
+                                                  &[1., 2., 4.]);
+        assert_eq!(res, Ok(()));
+        assert_eq!(mat.get(0, 2), Some(&1.));
+        assert_eq!(mat.get(3, 1), Some(&2.));
+        assert_eq!(mat.get(0, 3), Some(&4.));
+        assert_eq!(mat.get(1, 4), Some(&0.));
+        assert_eq!(mat.nnz(), 7);
+
+        let before = mat.clone();
+        let res = mat.assign_values_from_triplets(&[0, 0], &[2, 0],
+                                                  &[1., 1.]);
+        assert_eq!(res, Err(StructureError::MissingEntry.into()));
+        assert_eq!(mat, before);
+
+        mat.zero_values();
+        assert!(mat.data().iter().all(|&x| x == 0.));
+        assert_eq!(mat.indices(), mat1_csc().indices());
+    }
+
+    #[test]
+    fn mutable_views() {
+        let indptr: &[usize] = &[0, 1, 2, 3];
+        let indices: &[usize] = &[0, 1, 2];
+        let mut data = [1., 1., 1.];
+        {
+            let mut m = CsMatViewMut::from_raw_parts_mut(CSR, (3, 3), indptr,
+                                                         indices, &mut data)
+                                     .unwrap();
+            m.scale(2.);
+            assert_eq!(m.get(1, 1), Some(&2.));
+        }
+        assert_eq!(data, [2., 2., 2.]);
+
+        let mut mat = mat1();
+        mat.view_mut().scale(2.);
+        assert_eq!(mat, mat1_times_2());
+    }
+
     #[test]
     #[should_panic]
     fn test_new_csr_bad_indptr_length() {
@@ -1818,7 +3794,7 @@ mod test {
                                        indptr_fail3,
                                        indices_ok,
                                        data_ok),
-                   Err(SprsError::UnsortedIndptr));
+                   Err(StructureError::UnsortedIndptr.into()));
     }
 
     #[test]
@@ -1834,7 +3810,7 @@ mod test {
                                        indptr,
                                        indices,
                                        data),
-                   Err(SprsError::NonSortedIndices));
+                   Err(StructureError::NonSortedIndices.into()));
     }
 
     #[test]
@@ -1946,6 +3922,163 @@ mod test {
         assert_eq!(block_iter.next(), None);
     }
 
+    #[test]
+    fn fallible_arithmetic() {
+        use errors::SprsError;
+        let eye4: CsMat<f64> = CsMat::eye(4);
+        assert_eq!(mat1().try_add(&mat2()), Ok(&mat1() + &mat2()));
+        assert_eq!(mat1().try_sub(&mat1_csc()).unwrap().nnz(), 0);
+        assert_eq!(mat1().try_mul(&mat2()), Ok(&mat1() * &mat2()));
+        assert_eq!(mat1().try_add(&eye4),
+                   Err(SprsError::DimensionMismatch {
+                       op: "add",
+                       expected: (5, 5),
+                       found: (4, 4),
+                   }));
+        assert!(mat1().try_sub(&eye4).is_err());
+        assert!(mat1().try_mul(&eye4).is_err());
+    }
+
+    #[test]
+    fn validation_levels() {
+        use sparse::validation::ValidationLevel;
+        // unsorted indices are only detected by the full checks
+        let indptr = [0, 2, 2];
+        let indices = [1, 0];
+        let data = [1., 2.];
+        let mat = CsMatView {
+            storage: CSR,
+            nrows: 2,
+            ncols: 2,
+            indptr: &indptr[..],
+            indices: &indices[..],
+            data: &data[..],
+        };
+        assert_eq!(mat.validate(ValidationLevel::Full),
+                   Err(StructureError::NonSortedIndices.into()));
+        if !cfg!(debug_assertions) {
+            assert_eq!(mat.validate(ValidationLevel::CheapOnly), Ok(()));
+            assert_eq!(mat.validate(ValidationLevel::None), Ok(()));
+        }
+        let res = CsMatView::new_view_with_validation(CSR, (2, 2), &indptr,
+                                                      &indices, &data,
+                                                      ValidationLevel::Full);
+        assert_eq!(res, Err(StructureError::NonSortedIndices.into()));
+        let res = CsMat::new_with_validation(CSR, (2, 2), vec![0, 1, 2],
+                                             vec![1, 0], vec![1., 2.],
+                                             ValidationLevel::CheapOnly);
+        assert_eq!(res.unwrap().get(1, 0), Some(&2.));
+        // the indptr is always checked by the cheap checks
+        let indptr = [0, 2, 1, 2];
+        let mat = CsMatView { nrows: 3, indptr: &indptr[..], ..mat };
+        assert_eq!(mat.validate(ValidationLevel::CheapOnly),
+                   Err(StructureError::UnsortedIndptr.into()));
+    }
+
+    #[test]
+    fn to_dense_block() {
+        let expected = arr2(&[[0., 2., 5.],
+                              [5., 0., 0.],
+                              [0., 0., 0.]]);
+        for mat in &[mat1(), mat1_csc()] {
+            let mut block = Array::from_elem((3, 3), 1.);
+            mat.to_dense_block((1..4, 2..5), block.view_mut());
+            assert_eq!(block, expected);
+        }
+        let mut empty = Array::zeros((0, 2));
+        mat1().to_dense_block((2..2, 0..2), empty.view_mut());
+    }
+
+    #[test]
+    fn dense_row_chunks() {
+        for mat in &[mat1(), mat1_csc()] {
+            let dense = mat.to_dense();
+            let mut rows = 0;
+            for (start, chunk) in mat.dense_row_chunks(2) {
+                assert_eq!(start, rows);
+                for (i, row) in chunk.outer_iter().enumerate() {
+                    assert_eq!(row, dense.row(start + i));
+                }
+                rows += chunk.rows();
+            }
+            assert_eq!(rows, 5);
+        }
+
+        let mut buf = Array::from_elem((5, 5), 0.);
+        mat1_csc().assign_to_dense(buf.view_mut());
+        assert_eq!(buf, mat1().to_dense());
+    }
+
+    #[test]
+    #[should_panic]
+    fn to_dense_block_fail() {
+        let mut block = Array::zeros((2, 2));
+        mat1().to_dense_block((0..3, 0..2), block.view_mut());
+    }
+
+    #[test]
+    fn inner_axis_iterator() {
+        let mat = mat1();
+        let csc = mat1_csc();
+        for j in 0..5 {
+            let expected: Vec<_> = csc.outer_view(j).unwrap()
+                                      .iter()
+                                      .map(|(i, &v)| (i, v))
+                                      .collect();
+            let col: Vec<_> = mat.inner_axis_iterator(j)
+                                 .map(|(i, &v)| (i, v))
+                                 .collect();
+            assert_eq!(col, expected);
+        }
+        let row: Vec<_> = csc.inner_axis_iterator(1).collect();
+        assert_eq!(row, vec![(3, &2.), (4, &5.)]);
+    }
+
+    #[test]
+    fn outer_block_view() {
+        let mat = mat1();
+        let block = mat.outer_block_view(1..4);
+        assert_eq!(block.shape(), (3, 5));
+        assert_eq!(block.nnz(), 4);
+        assert_eq!(block.indptr(), &[2, 4, 5, 6][..]);
+        for (i, row) in block.outer_iterator().enumerate() {
+            assert_eq!(row, mat.outer_view(i + 1).unwrap());
+        }
+        let owned = block.to_owned();
+        assert_eq!(owned.indptr(), &[0, 2, 3, 4][..]);
+        assert_eq!(owned.indices(), &[3, 4, 2, 1][..]);
+        assert_eq!(owned.data(), &[2., 5., 5., 8.][..]);
+        assert_eq!(mat.outer_block_view(2..2).nnz(), 0);
+
+        let csc = mat1_csc();
+        assert_eq!(csc.outer_block_view(0..2).shape(), (5, 2));
+    }
+
+    #[test]
+    fn outer_block_view_conversions() {
+        use sparse::validation::ValidationLevel;
+        let mat = mat1();
+        let block = mat.outer_block_view(1..4);
+        let owned = block.to_owned();
+        assert!(block.check_compressed_structure().is_ok());
+        assert!(block.validate(ValidationLevel::CheapOnly).is_ok());
+        assert_eq!(block.to_csc(), owned.to_csc());
+        assert_eq!(block.to_csc().to_csr(), owned);
+        let doubled = block.map(|&x| 2. * x);
+        assert_eq!(doubled.indptr(), &[0, 2, 3, 4][..]);
+        assert_eq!(doubled.data(), &[4., 10., 10., 16.][..]);
+        assert_eq!(block.cast_values::<f64>(), owned);
+        let narrow: CsMatI<f64, u32> = block.to_other_types();
+        assert_eq!(narrow.indptr(), &[0, 2, 3, 4][..]);
+        assert_eq!(narrow.indices(), &[3, 4, 2, 1][..]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn outer_block_view_fail() {
+        mat1().outer_block_view(3..6);
+    }
+
     #[test]
     fn nnz_index() {
         let mat : CsMat<f64> = CsMat::eye(11);
@@ -2076,6 +4209,133 @@ mod test {
         assert_eq!(mat, expected);
     }
 
+    #[test]
+    fn eye_k_scalar_mat() {
+        let eye: CsMat<f64> = CsMat::eye_k((3, 4), 0);
+        assert_eq!(eye.to_dense(), arr2(&[[1., 0., 0., 0.],
+                                          [0., 1., 0., 0.],
+                                          [0., 0., 1., 0.]]));
+        let lower: CsMat<f64> = CsMat::eye_k((4, 3), -2);
+        assert_eq!(lower.to_dense(), arr2(&[[0., 0., 0.],
+                                            [0., 0., 0.],
+                                            [1., 0., 0.],
+                                            [0., 1., 0.]]));
+        let empty: CsMat<f64> = CsMat::eye_k((2, 2), 5);
+        assert_eq!(empty.nnz(), 0);
+        let small: CsMatI<f64, u32> = CsMatI::scalar_mat((2, 3), 2.);
+        assert_eq!(small.indptr(), &[0, 1, 2]);
+        assert_eq!(small.data(), &[2., 2.]);
+        let eye_csc: CsMat<f64> = CsMat::eye_csc(3);
+        assert_eq!(eye_csc, CsMat::eye(3).to_csc());
+        let eye_i: CsMatI<f64, u32> = CsMatI::eye_i(3);
+        assert_eq!(eye_i.indices(), &[0, 1, 2]);
+        let eye_csc_i: CsMatI<f64, u32> = CsMatI::eye_csc_i(3);
+        assert!(eye_csc_i.is_csc());
+        assert_eq!(eye_csc_i.indptr(), &[0, 1, 2, 3]);
+        let zero_i: CsMatI<f64, u16> = CsMatI::zero_i((2, 3));
+        assert_eq!(zero_i.indptr(), &[0, 0, 0]);
+    }
+
+    #[test]
+    fn combine_rows() {
+        let coeffs = [(3, 2.), (0, 1.), (2, -1.), (3, 0.5)];
+        // rows 0: {2: 3, 3: 4}, 2: {2: 5}, 3: {1: 8}
+        let expected = CsVec::new(5, vec![1, 2, 3], vec![20., -2., 4.]);
+        assert_eq!(mat1().combine_rows(&coeffs), expected);
+        assert_eq!(mat1_csc().combine_rows(&coeffs), expected);
+        assert_eq!(mat1().combine_rows(&[]), CsVec::empty(5));
+    }
+
+    #[test]
+    fn diagonal_shift() {
+        // mat1 stores the diagonal entries (2, 2) and (3, 3)
+        let shifted = mat1().shift(2.);
+        let expected = &mat1() + &CsMat::scalar_mat((5, 5), 2.);
+        assert_eq!(shifted, expected);
+        assert_eq!(mat1_csc().shift(2.), expected.to_csc());
+        // no structural change when the diagonal is stored
+        let mut mat = shifted;
+        let indices = mat.indices().to_vec();
+        mat.add_diag_assign(-1.);
+        assert_eq!(mat.indices(), &indices[..]);
+        assert_eq!(mat.get(0, 0), Some(&1.));
+
+        let mut rect: CsMat<f64> = CsMat::zero((2, 3));
+        rect.add_diag_assign(1.);
+        assert_eq!(rect, CsMat::eye_k((2, 3), 0));
+    }
+
+    #[test]
+    fn insert_many_remove_entry() {
+        let mut mat = mat1_csc();
+        mat.insert_many(vec![(4, 4, 1.), (0, 0, 2.), (1, 4, 3.), (4, 4, 6.),
+                             (2, 0, 9.)]);
+        let mut expected = mat1_csc();
+        expected.insert(0, 0, 2.);
+        expected.insert(4, 4, 6.);
+        expected.insert(1, 4, 3.);
+        expected.insert(2, 0, 9.);
+        assert_eq!(mat, expected);
+        assert_eq!(mat.nnz(), 10);
+
+        assert_eq!(mat.remove_entry(2, 0), Some(9.));
+        assert_eq!(mat.remove_entry(4, 4), Some(6.));
+        assert_eq!(mat.remove_entry(0, 0), Some(2.));
+        assert_eq!(mat.remove_entry(1, 4), Some(3.));
+        mat.insert(1, 4, 5.);
+        assert_eq!(mat.remove_entry(0, 0), None);
+        assert_eq!(mat.remove_entry(7, 0), None);
+        assert_eq!(mat, mat1_csc());
+
+        let mut mat = CsMat::empty(CSR, 0);
+        mat.insert_many(vec![(1, 2, 1.), (0, 1, 2.)]);
+        assert_eq!(mat, CsMat::new((2, 3), vec![0, 1, 2], vec![1, 2],
+                                   vec![2., 1.]));
+    }
+
+    #[test]
+    fn append_remove_outer() {
+        let mut mat = CsMat::empty(CSR, 5);
+        for row in mat1().outer_iterator() {
+            let mut entries: Vec<_> = row.iter().map(|(i, &v)| (i, v))
+                                         .collect();
+            entries.reverse();
+            mat = mat.append_outer_from_iter(entries);
+        }
+        assert_eq!(mat, mat1());
+
+        // | 0 0 3 4 0 |
+        // | 0 0 0 2 5 |
+        // | 0 0 5 0 0 |
+        // | 0 8 0 0 0 |
+        // | 0 0 0 7 0 |
+        let row = mat.remove_outer(1);
+        assert_eq!(row.indices(), &[3, 4]);
+        assert_eq!(row.data(), &[2., 5.]);
+        let last = mat.pop_outer().unwrap();
+        assert_eq!(last.indices(), &[3]);
+        let expected = CsMat::new((3, 5), vec![0, 2, 3, 4],
+                                  vec![2, 3, 2, 1], vec![3., 4., 5., 8.]);
+        assert_eq!(mat, expected);
+
+        let mut mat = mat1_csc();
+        mat.retain_outer(|j| j != 0 && j != 3);
+        let expected = CsMat::new_csc((5, 3), vec![0, 1, 3, 4],
+                                      vec![3, 0, 2, 1], vec![8., 3., 5., 5.]);
+        assert_eq!(mat, expected);
+        mat.retain_outer(|_| false);
+        assert_eq!(mat.shape(), (5, 0));
+        assert_eq!(mat.nnz(), 0);
+        assert!(mat.pop_outer().is_none());
+    }
+
+    #[test]
+    #[should_panic]
+    fn append_outer_from_iter_fail() {
+        let mat: CsMat<f64> = CsMat::empty(CSR, 3);
+        mat.append_outer_from_iter(vec![(1, 1.), (1, 2.)]);
+    }
+
     #[test]
     fn iter_mut() {
         // | 0 1 0 |
@@ -2132,4 +4392,217 @@ mod test {
         assert_eq!(mat_.indptr(), &[0, 1, 3, 4]);
         assert_eq!(mat_.data(), &[1.0f32, 1., 1., 1.]);
     }
+
+    #[test]
+    fn pattern_ops() {
+        let (a, b) = (mat1(), mat2());
+        let union = a.union_pattern(&b);
+        assert_eq!(union.indptr(), &[0, 5, 8, 9, 12, 15]);
+        assert_eq!(union.indices(),
+                   &[0, 1, 2, 3, 4, 0, 3, 4, 2, 1, 2, 3, 1, 2, 3]);
+        assert!(union.data().iter().all(|&x| x));
+        let inter = a.intersect_pattern(&b);
+        assert_eq!(inter.indptr(), &[0, 1, 2, 2, 2, 2]);
+        assert_eq!(inter.indices(), &[2, 3]);
+        let diff = a.difference_pattern(&b);
+        assert_eq!(diff.indices(), &[3, 4, 2, 1, 3]);
+
+        // mixed storages follow the storage of the left hand side
+        assert_eq!(a.union_pattern(&b.to_csc()), union);
+        assert_eq!(mat1_csc().union_pattern(&b), union.to_csc());
+        assert_eq!(mat1_csc().difference_pattern(&b.to_csc()),
+                   diff.to_csc());
+    }
+
+    #[test]
+    fn dense_sparse_products() {
+        let dense = Array::from_shape_fn((3, 5), |(i, j)| (i * 5 + j) as f64);
+        let expected = dense.dot(&mat1().to_dense());
+        assert_eq!(&dense * &mat1(), expected);
+        assert_eq!(&dense * &mat1_csc(), expected);
+        let dense_f = Array::from_shape_fn((3, 5).f(),
+                                           |(i, j)| (i * 5 + j) as f64);
+        assert_eq!(&dense_f * &mat1(), expected);
+        assert_eq!(&dense_f * &mat1_csc(), expected);
+
+        let expected = mat1().to_dense().dot(&dense.t());
+        assert_eq!(&mat1() * &dense.t(), expected);
+        assert_eq!(&mat1_csc() * &dense.t(), expected);
+    }
+
+    #[test]
+    fn dense_vector_products() {
+        let x = Array::from_shape_fn(5, |i| i as f64 + 1.);
+        let expected = mat1().to_dense().dot(&x);
+        assert_eq!(&mat1() * &x, expected);
+        assert_eq!(&mat1_csc() * &x, expected);
+        // non contiguous vectors
+        let dense = Array::from_shape_fn((5, 2), |(i, _)| i as f64 + 1.);
+        assert_eq!(&mat1() * &dense.column(0), expected);
+
+        let expected = x.dot(&mat1().to_dense());
+        assert_eq!(&x * &mat1(), expected);
+        assert_eq!(&x * &mat1_csc(), expected);
+        assert_eq!(&dense.column(1) * &mat1_csc(), expected);
+    }
+
+    #[test]
+    fn split_outer_mut() {
+        let mut mat = mat1();
+        {
+            let (mut top, mut bottom) = mat.split_outer_mut(2);
+            assert_eq!(top.shape(), (2, 5));
+            assert_eq!(bottom.shape(), (3, 5));
+            assert_eq!(bottom.indptr(), &[0, 1, 2, 3]);
+            assert_eq!(bottom.get(1, 1), Some(&8.));
+            top.scale(2.);
+            {
+                let (mut middle, mut last) = bottom.split_outer_mut(1);
+                middle.scale(-1.);
+                *last.get_mut(1, 3).unwrap() = 0.;
+            }
+        }
+        assert_eq!(mat.data(), &[6., 8., 4., 10., -5., 8., 0.]);
+
+        let mut mat = mat1_csc();
+        {
+            let (empty, mut all) = mat.split_outer_mut(0);
+            assert_eq!(empty.shape(), (5, 0));
+            assert_eq!(empty.nnz(), 0);
+            assert_eq!(all.view(), mat1_csc().view());
+            all.scale(2.);
+        }
+        assert_eq!(mat, mat1_csc().map(|&x| 2. * x));
+    }
+
+    #[test]
+    fn shared_structure() {
+        let jac = mat1().into_shared();
+        assert_eq!(jac.view(), mat1().view());
+        let mut other = jac.clone();
+        other.scale(2.);
+        assert!(jac.shares_structure_with(&other));
+        assert_eq!(other.view(), mat1_times_2().view());
+        assert_eq!(jac.data(), mat1().data());
+
+        let zeros = jac.with_data(vec![0.; 7]);
+        assert!(zeros.shares_structure_with(&jac));
+        assert_eq!(zeros.to_owned().nnz(), 7);
+        assert_eq!(other.into_data(), mat1_times_2().data());
+
+        let copy = mat1().into_shared();
+        assert!(!copy.shares_structure_with(&jac));
+    }
+
+    #[test]
+    fn generic_containers() {
+        let mat = mat1();
+        let boxed: CsMatBase<f64, usize, Box<[usize]>, Box<[usize]>, Box<[f64]>>
+            = mat1().convert_containers();
+        assert_eq!(boxed.view(), mat.view());
+        let (indptr, indices, data) = boxed.into_containers();
+        let shared: CsMatBase<f64, usize, Arc<[usize]>, Box<[usize]>, &[f64]>
+            = CsMatBase::from_containers(CSR, (5, 5), indptr.into(), indices,
+                                         &data[..]).unwrap();
+        assert_eq!(shared.view(), mat.view());
+        let bad = CsMatBase::from_containers(CSC, (5, 4), mat.indptr(),
+                                             mat.indices(), mat.data());
+        assert_eq!(bad, Err(StructureError::BadIndptrLength.into()));
+    }
+
+    #[test]
+    fn nnz_iterators() {
+        let expected = vec![(0, 2, 3.), (0, 3, 4.), (1, 3, 2.), (1, 4, 5.),
+                            (2, 2, 5.), (3, 1, 8.), (4, 3, 7.)];
+        let mat = mat1();
+        let triplets: Vec<_> = mat.iter().map(|(i, j, &v)| (i, j, v))
+                                         .collect();
+        assert_eq!(triplets, expected);
+        assert_eq!(mat.iter().len(), 7);
+        let csc = mat1_csc();
+        let mut triplets: Vec<_> = csc.indexed_iter()
+                                      .map(|((i, j), &v)| (i, j, v))
+                                      .collect();
+        triplets.sort_by_key(|&(i, j, _)| (i, j));
+        assert_eq!(triplets, expected);
+        let mut count = 0;
+        for (row, col, &val) in &mat {
+            assert_eq!(mat.get(row, col), Some(&val));
+            count += 1;
+        }
+        assert_eq!(count, 7);
+
+        // views starting in the middle of their arrays
+        let view = mat.view();
+        let middle = view.middle_outer_views(1, 2);
+        let triplets: Vec<_> = middle.iter().map(|(i, j, &v)| (i, j, v))
+                                            .collect();
+        assert_eq!(triplets, vec![(0, 3, 2.), (0, 4, 5.), (1, 2, 5.)]);
+
+        assert_eq!(mat1().into_iter().collect::<Vec<_>>(), expected);
+        let empty: CsMat<f64> = CsMat::zero((3, 0));
+        assert_eq!(empty.iter().next(), None);
+    }
+
+    #[test]
+    fn zip_outer() {
+        let lhs = mat1();
+        let rhs = CsMat::new((5, 5),
+                             vec![0, 1, 1, 2, 3, 3],
+                             vec![0, 2, 1],
+                             vec![1., 2., 3.]);
+        let mut entries = Vec::new();
+        for (row, zipped) in lhs.zip_outer(&rhs) {
+            for (col, l, r) in zipped {
+                entries.push((row, col, l.cloned(), r.cloned()));
+            }
+        }
+        assert_eq!(entries, vec![(0, 0, None, Some(1.)),
+                                 (0, 2, Some(3.), None),
+                                 (0, 3, Some(4.), None),
+                                 (1, 3, Some(2.), None),
+                                 (1, 4, Some(5.), None),
+                                 (2, 2, Some(5.), Some(2.)),
+                                 (3, 1, Some(8.), Some(3.)),
+                                 (4, 3, Some(7.), None)]);
+        assert_eq!(lhs.zip_outer(&rhs).count(), 5);
+    }
+
+    #[test]
+    #[should_panic]
+    fn zip_outer_storage_mismatch() {
+        mat1().zip_outer(&mat1_csc());
+    }
+
+    #[test]
+    fn set_block() {
+        let block = CsMat::new((2, 3),
+                               vec![0, 2, 2],
+                               vec![0, 2],
+                               vec![-1., -2.]);
+        let mut expected = mat1().to_dense();
+        for i in 0..2 {
+            for j in 0..3 {
+                expected[[i + 1, j + 2]] = 0.;
+            }
+        }
+        expected[[1, 2]] = -1.;
+        expected[[1, 4]] = -2.;
+
+        let mut mat = mat1();
+        mat.set_block(1, 2, &block);
+        assert_eq!(mat.to_dense(), expected);
+        assert_eq!(mat.nnz(), 6);
+        let mut mat = mat1_csc();
+        mat.set_block(1, 2, &block);
+        assert_eq!(mat.to_dense(), expected);
+
+        // a block of the other storage, shifting the following rows
+        let mut mat = mat1();
+        let block = CsMat::new_csc((1, 2), vec![0, 1, 2], vec![0, 0],
+                                   vec![1., 2.]);
+        mat.set_block(0, 0, &block);
+        assert_eq!(mat.indptr(), &[0, 4, 6, 7, 8, 9]);
+        assert_eq!(mat.indices(), &[0, 1, 2, 3, 3, 4, 2, 1, 3]);
+    }
 }
@@ -0,0 +1,725 @@
+///! GPU offload of sparse kernels through CUDA
+///!
+///! This module is available when the `cuda` feature is enabled, and links
+///! to the CUDA runtime and to cuSPARSE. It provides device resident
+///! buffers, `DeviceVec` and `DeviceDenseMat`, and CSR matrices,
+///! `CudaCsMat`, which are uploaded from and downloaded to their host
+///! counterparts, and whose products are computed by cuSPARSE. The
+///! cuSPARSE handle is created on the first product of each thread, and
+///! reused by its subsequent products.
+///!
+///! Only `f32` and `f64` values with `i32` indices are supported, as these
+///! are the types handled by cuSPARSE's generic API.
+///!
+///! `CudaCsMat` implements `linalg::LinearOperator`, the products with host
+///! vectors transferring their operands, so that the iterative solvers of
+///! sprs can run their matrix-vector products on the GPU. Keeping the
+///! vectors on the device with `mul_vec` avoids these transfers.
+///!
+///! `CudaCsMat` also implements `backend::BackendMat`, like the host
+///! matrices, so that code generic over this trait can keep all its
+///! operands on the device.
+///!
+///! # Example
+///!
+///! ```rust,no_run
+///! use sprs::CsMatI;
+///! use sprs::cuda::{CudaCsMat, DeviceVec};
+///! let mat: CsMatI<f64, i32> = CsMatI::new((3, 3),
+///!                                          vec![0, 1, 2, 3],
+///!                                          vec![0, 1, 2],
+///!                                          vec![1., 1., 1.]);
+///! let gpu_mat = CudaCsMat::upload(mat.view());
+///! let x = DeviceVec::upload(&[1., 2., 3.]);
+///! let mut y = DeviceVec::zeros(3);
+///! gpu_mat.mul_vec(&x, &mut y);
+///! assert_eq!(y.download(), vec![1., 2., 3.]);
+///! ```
+
+use std::marker::PhantomData;
+use std::mem;
+use std::os::raw::{c_int, c_void};
+use std::ptr;
+
+use sparse::prelude::*;
+use sparse::backend::BackendMat;
+use sparse::linalg::LinearOperator;
+use ::Shape;
+
+/// The scalar types supported by cuSPARSE
+pub trait CudaScalar: Copy + Default + 'static {
+    #[doc(hidden)]
+    fn data_type() -> c_int;
+    #[doc(hidden)]
+    fn one() -> Self;
+}
+
+impl CudaScalar for f32 {
+    fn data_type() -> c_int {
+        ffi::CUDA_R_32F
+    }
+
+    fn one() -> f32 {
+        1.
+    }
+}
+
+impl CudaScalar for f64 {
+    fn data_type() -> c_int {
+        ffi::CUDA_R_64F
+    }
+
+    fn one() -> f64 {
+        1.
+    }
+}
+
+/// A buffer allocated on the device
+struct DeviceBuffer<T> {
+    ptr: *mut c_void,
+    len: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Copy + Default> DeviceBuffer<T> {
+    /// An uninitialized buffer of `len` elements
+    fn alloc(len: usize) -> DeviceBuffer<T> {
+        let bytes = len * mem::size_of::<T>();
+        let mut ptr = ptr::null_mut();
+        if bytes > 0 {
+            unsafe {
+                ffi::check_cuda(ffi::cudaMalloc(&mut ptr, bytes));
+            }
+        }
+        DeviceBuffer {
+            ptr: ptr,
+            len: len,
+            _marker: PhantomData,
+        }
+    }
+
+    fn upload(data: &[T]) -> DeviceBuffer<T> {
+        let bytes = data.len() * mem::size_of::<T>();
+        let mut ptr = ptr::null_mut();
+        unsafe {
+            if bytes > 0 {
+                ffi::check_cuda(ffi::cudaMalloc(&mut ptr, bytes));
+                ffi::check_cuda(ffi::cudaMemcpy(
+                    ptr, data.as_ptr() as *const c_void, bytes,
+                    ffi::CUDA_MEMCPY_HOST_TO_DEVICE));
+            }
+        }
+        DeviceBuffer {
+            ptr: ptr,
+            len: data.len(),
+            _marker: PhantomData,
+        }
+    }
+
+    fn download(&self, out: &mut [T]) {
+        assert_eq!(out.len(), self.len);
+        let bytes = self.len * mem::size_of::<T>();
+        if bytes > 0 {
+            unsafe {
+                ffi::check_cuda(ffi::cudaMemcpy(
+                    out.as_mut_ptr() as *mut c_void, self.ptr, bytes,
+                    ffi::CUDA_MEMCPY_DEVICE_TO_HOST));
+            }
+        }
+    }
+
+    fn to_vec(&self) -> Vec<T> {
+        let mut res = vec![T::default(); self.len];
+        self.download(&mut res);
+        res
+    }
+}
+
+impl<T> Drop for DeviceBuffer<T> {
+    fn drop(&mut self) {
+        if !self.ptr.is_null() {
+            unsafe {
+                ffi::cudaFree(self.ptr);
+            }
+        }
+    }
+}
+
+/// A dense vector stored on the device
+pub struct DeviceVec<N: CudaScalar> {
+    buf: DeviceBuffer<N>,
+}
+
+impl<N: CudaScalar> DeviceVec<N> {
+    /// Copy a host vector to the device
+    pub fn upload(data: &[N]) -> DeviceVec<N> {
+        DeviceVec {
+            buf: DeviceBuffer::upload(data),
+        }
+    }
+
+    /// A device vector of length `len` filled with zeros
+    pub fn zeros(len: usize) -> DeviceVec<N> {
+        DeviceVec::upload(&vec![N::default(); len])
+    }
+
+    /// Copy this vector back to the host
+    pub fn download(&self) -> Vec<N> {
+        self.buf.to_vec()
+    }
+
+    /// Copy this vector into an existing host buffer
+    ///
+    /// # Panics
+    ///
+    /// If `out.len() != self.len()`.
+    pub fn download_into(&self, out: &mut [N]) {
+        self.buf.download(out);
+    }
+
+    /// The length of the vector
+    pub fn len(&self) -> usize {
+        self.buf.len
+    }
+
+    /// Whether the vector is empty
+    pub fn is_empty(&self) -> bool {
+        self.buf.len == 0
+    }
+}
+
+/// A dense matrix stored on the device in column major order
+pub struct DeviceDenseMat<N: CudaScalar> {
+    rows: usize,
+    cols: usize,
+    buf: DeviceBuffer<N>,
+}
+
+impl<N: CudaScalar> DeviceDenseMat<N> {
+    /// Copy a host matrix, given in column major order, to the device
+    ///
+    /// # Panics
+    ///
+    /// If `data.len() != shape.0 * shape.1`.
+    pub fn upload(shape: Shape, data: &[N]) -> DeviceDenseMat<N> {
+        assert_eq!(data.len(), shape.0 * shape.1, "Dimension mismatch");
+        DeviceDenseMat {
+            rows: shape.0,
+            cols: shape.1,
+            buf: DeviceBuffer::upload(data),
+        }
+    }
+
+    /// A device matrix filled with zeros
+    pub fn zeros(shape: Shape) -> DeviceDenseMat<N> {
+        DeviceDenseMat::upload(shape, &vec![N::default(); shape.0 * shape.1])
+    }
+
+    /// Copy this matrix back to the host, in column major order
+    pub fn download(&self) -> Vec<N> {
+        self.buf.to_vec()
+    }
+
+    /// The shape of the matrix
+    pub fn shape(&self) -> Shape {
+        (self.rows, self.cols)
+    }
+}
+
+/// A CSR matrix stored on the device
+pub struct CudaCsMat<N: CudaScalar> {
+    rows: usize,
+    cols: usize,
+    indptr: DeviceBuffer<i32>,
+    indices: DeviceBuffer<i32>,
+    data: DeviceBuffer<N>,
+}
+
+impl<N: CudaScalar> CudaCsMat<N> {
+    /// Copy a host matrix to the device. CSC matrices are converted to CSR
+    /// storage on the host before the transfer.
+    pub fn upload(mat: CsMatViewI<N, i32>) -> CudaCsMat<N> {
+        if !mat.is_csr() {
+            return CudaCsMat::upload(mat.to_csr().view());
+        }
+        // the device kernels do not check the bounds of their accesses
+        mat.check_compressed_structure().unwrap();
+        CudaCsMat {
+            rows: mat.rows(),
+            cols: mat.cols(),
+            indptr: DeviceBuffer::upload(mat.indptr()),
+            indices: DeviceBuffer::upload(mat.indices()),
+            data: DeviceBuffer::upload(mat.data()),
+        }
+    }
+
+    /// Copy this matrix back to the host
+    pub fn download(&self) -> CsMatI<N, i32> {
+        CsMatI::new((self.rows, self.cols),
+                    self.indptr.to_vec(),
+                    self.indices.to_vec(),
+                    self.data.to_vec())
+    }
+
+    /// The shape of the matrix
+    pub fn shape(&self) -> Shape {
+        (self.rows, self.cols)
+    }
+
+    /// The number of non-zero values
+    pub fn nnz(&self) -> usize {
+        self.data.len
+    }
+
+    /// Compute `y = self * x` on the device
+    ///
+    /// # Panics
+    ///
+    /// On dimension mismatch, or if a CUDA call fails.
+    pub fn mul_vec(&self, x: &DeviceVec<N>, y: &mut DeviceVec<N>) {
+        if x.len() != self.cols || y.len() != self.rows {
+            panic!("Dimension mismatch");
+        }
+        ffi::with_handle(|handle| unsafe {
+            let mat = self.descr();
+            let mut x_descr = ptr::null_mut();
+            let mut y_descr = ptr::null_mut();
+            ffi::check(ffi::cusparseCreateDnVec(&mut x_descr, x.len() as i64,
+                                                x.buf.ptr, N::data_type()));
+            ffi::check(ffi::cusparseCreateDnVec(&mut y_descr, y.len() as i64,
+                                                y.buf.ptr, N::data_type()));
+            let (alpha, beta) = (N::one(), N::default());
+            let alpha = &alpha as *const N as *const c_void;
+            let beta = &beta as *const N as *const c_void;
+            let mut size = 0;
+            ffi::check(ffi::cusparseSpMV_bufferSize(
+                handle, ffi::CUSPARSE_OPERATION_NON_TRANSPOSE, alpha,
+                mat, x_descr, beta, y_descr, N::data_type(),
+                ffi::CUSPARSE_SPMV_ALG_DEFAULT, &mut size));
+            let workspace = DeviceBuffer::<u8>::upload(&vec![0; size]);
+            ffi::check(ffi::cusparseSpMV(
+                handle, ffi::CUSPARSE_OPERATION_NON_TRANSPOSE, alpha,
+                mat, x_descr, beta, y_descr, N::data_type(),
+                ffi::CUSPARSE_SPMV_ALG_DEFAULT, workspace.ptr));
+            ffi::cusparseDestroyDnVec(y_descr);
+            ffi::cusparseDestroyDnVec(x_descr);
+            ffi::cusparseDestroySpMat(mat);
+        })
+    }
+
+    /// Compute `out = self * rhs` on the device, for a dense right hand
+    /// side
+    ///
+    /// # Panics
+    ///
+    /// On dimension mismatch, or if a CUDA call fails.
+    pub fn mul_dense(&self,
+                     rhs: &DeviceDenseMat<N>,
+                     out: &mut DeviceDenseMat<N>) {
+        if rhs.rows != self.cols || out.rows != self.rows
+           || out.cols != rhs.cols {
+            panic!("Dimension mismatch");
+        }
+        ffi::with_handle(|handle| unsafe {
+            let mat = self.descr();
+            let rhs_descr = dense_descr(rhs);
+            let out_descr = dense_descr(out);
+            let (alpha, beta) = (N::one(), N::default());
+            let alpha = &alpha as *const N as *const c_void;
+            let beta = &beta as *const N as *const c_void;
+            let mut size = 0;
+            ffi::check(ffi::cusparseSpMM_bufferSize(
+                handle, ffi::CUSPARSE_OPERATION_NON_TRANSPOSE,
+                ffi::CUSPARSE_OPERATION_NON_TRANSPOSE, alpha, mat, rhs_descr,
+                beta, out_descr, N::data_type(),
+                ffi::CUSPARSE_SPMM_ALG_DEFAULT, &mut size));
+            let workspace = DeviceBuffer::<u8>::upload(&vec![0; size]);
+            ffi::check(ffi::cusparseSpMM(
+                handle, ffi::CUSPARSE_OPERATION_NON_TRANSPOSE,
+                ffi::CUSPARSE_OPERATION_NON_TRANSPOSE, alpha, mat, rhs_descr,
+                beta, out_descr, N::data_type(),
+                ffi::CUSPARSE_SPMM_ALG_DEFAULT, workspace.ptr));
+            ffi::cusparseDestroyDnMat(out_descr);
+            ffi::cusparseDestroyDnMat(rhs_descr);
+            ffi::cusparseDestroySpMat(mat);
+        })
+    }
+
+    /// Compute the sparse product `self * rhs` on the device
+    ///
+    /// # Panics
+    ///
+    /// On dimension mismatch, or if a CUDA call fails.
+    pub fn spgemm(&self, rhs: &CudaCsMat<N>) -> CudaCsMat<N> {
+        if self.cols != rhs.rows {
+            panic!("Dimension mismatch");
+        }
+        let (rows, cols) = (self.rows, rhs.cols);
+        let indptr = DeviceBuffer::<i32>::alloc(rows + 1);
+        ffi::with_handle(|handle| unsafe {
+            let lhs_descr = self.descr();
+            let rhs_descr = rhs.descr();
+            let mut res_descr = ptr::null_mut();
+            ffi::check(ffi::cusparseCreateCsr(
+                &mut res_descr, rows as i64, cols as i64, 0, indptr.ptr,
+                ptr::null_mut(), ptr::null_mut(), ffi::CUSPARSE_INDEX_32I,
+                ffi::CUSPARSE_INDEX_32I, ffi::CUSPARSE_INDEX_BASE_ZERO,
+                N::data_type()));
+            let mut spgemm_descr = ptr::null_mut();
+            ffi::check(ffi::cusparseSpGEMM_createDescr(&mut spgemm_descr));
+            let (alpha, beta) = (N::one(), N::default());
+            let alpha = &alpha as *const N as *const c_void;
+            let beta = &beta as *const N as *const c_void;
+            let op = ffi::CUSPARSE_OPERATION_NON_TRANSPOSE;
+            // each phase is first called to query the size of its workspace
+            let mut size = 0;
+            ffi::check(ffi::cusparseSpGEMM_workEstimation(
+                handle, op, op, alpha, lhs_descr, rhs_descr, beta, res_descr,
+                N::data_type(), ffi::CUSPARSE_SPGEMM_DEFAULT, spgemm_descr,
+                &mut size, ptr::null_mut()));
+            let estimation_workspace = DeviceBuffer::<u8>::alloc(size);
+            ffi::check(ffi::cusparseSpGEMM_workEstimation(
+                handle, op, op, alpha, lhs_descr, rhs_descr, beta, res_descr,
+                N::data_type(), ffi::CUSPARSE_SPGEMM_DEFAULT, spgemm_descr,
+                &mut size, estimation_workspace.ptr));
+            let mut size = 0;
+            ffi::check(ffi::cusparseSpGEMM_compute(
+                handle, op, op, alpha, lhs_descr, rhs_descr, beta, res_descr,
+                N::data_type(), ffi::CUSPARSE_SPGEMM_DEFAULT, spgemm_descr,
+                &mut size, ptr::null_mut()));
+            let compute_workspace = DeviceBuffer::<u8>::alloc(size);
+            ffi::check(ffi::cusparseSpGEMM_compute(
+                handle, op, op, alpha, lhs_descr, rhs_descr, beta, res_descr,
+                N::data_type(), ffi::CUSPARSE_SPGEMM_DEFAULT, spgemm_descr,
+                &mut size, compute_workspace.ptr));
+            let (mut res_rows, mut res_cols, mut nnz) = (0, 0, 0);
+            ffi::check(ffi::cusparseSpMatGetSize(res_descr, &mut res_rows,
+                                                 &mut res_cols, &mut nnz));
+            let indices = DeviceBuffer::<i32>::alloc(nnz as usize);
+            let data = DeviceBuffer::<N>::alloc(nnz as usize);
+            ffi::check(ffi::cusparseCsrSetPointers(res_descr, indptr.ptr,
+                                                   indices.ptr, data.ptr));
+            ffi::check(ffi::cusparseSpGEMM_copy(
+                handle, op, op, alpha, lhs_descr, rhs_descr, beta, res_descr,
+                N::data_type(), ffi::CUSPARSE_SPGEMM_DEFAULT, spgemm_descr));
+            ffi::cusparseSpGEMM_destroyDescr(spgemm_descr);
+            ffi::cusparseDestroySpMat(res_descr);
+            ffi::cusparseDestroySpMat(rhs_descr);
+            ffi::cusparseDestroySpMat(lhs_descr);
+            CudaCsMat {
+                rows: rows,
+                cols: cols,
+                indptr: indptr,
+                indices: indices,
+                data: data,
+            }
+        })
+    }
+
+    /// The cuSPARSE descriptor of this matrix, to destroy after use
+    unsafe fn descr(&self) -> ffi::SpMatDescr {
+        let mut descr = ptr::null_mut();
+        ffi::check(ffi::cusparseCreateCsr(
+            &mut descr, self.rows as i64, self.cols as i64,
+            self.nnz() as i64, self.indptr.ptr, self.indices.ptr,
+            self.data.ptr, ffi::CUSPARSE_INDEX_32I, ffi::CUSPARSE_INDEX_32I,
+            ffi::CUSPARSE_INDEX_BASE_ZERO, N::data_type()));
+        descr
+    }
+}
+
+/// The cuSPARSE descriptor of a dense matrix, to destroy after use
+unsafe fn dense_descr<N: CudaScalar>(mat: &DeviceDenseMat<N>)
+                                     -> ffi::DnMatDescr {
+    let mut descr = ptr::null_mut();
+    ffi::check(ffi::cusparseCreateDnMat(
+        &mut descr, mat.rows as i64, mat.cols as i64, mat.rows as i64,
+        mat.buf.ptr, N::data_type(), ffi::CUSPARSE_ORDER_COL));
+    descr
+}
+
+/// Products computed on the device by cuSPARSE
+impl<N: CudaScalar> BackendMat<N> for CudaCsMat<N> {
+    type DenseVec = DeviceVec<N>;
+    type DenseMat = DeviceDenseMat<N>;
+
+    fn upload_vec(data: &[N]) -> DeviceVec<N> {
+        DeviceVec::upload(data)
+    }
+
+    fn download_vec(vec: &DeviceVec<N>) -> Vec<N> {
+        vec.download()
+    }
+
+    fn shape(&self) -> Shape {
+        CudaCsMat::shape(self)
+    }
+
+    fn nnz(&self) -> usize {
+        CudaCsMat::nnz(self)
+    }
+
+    fn mul_vec(&self, x: &DeviceVec<N>, y: &mut DeviceVec<N>) {
+        CudaCsMat::mul_vec(self, x, y)
+    }
+
+    fn mul_dense(&self, rhs: &DeviceDenseMat<N>, out: &mut DeviceDenseMat<N>) {
+        CudaCsMat::mul_dense(self, rhs, out)
+    }
+
+    fn spgemm(&self, rhs: &CudaCsMat<N>) -> CudaCsMat<N> {
+        CudaCsMat::spgemm(self, rhs)
+    }
+}
+
+impl<N: CudaScalar> LinearOperator<N> for CudaCsMat<N> {
+    fn shape(&self) -> Shape {
+        CudaCsMat::shape(self)
+    }
+
+    fn apply(&self, x: &[N], y: &mut [N]) {
+        if x.len() != self.cols || y.len() != self.rows {
+            panic!("Dimension mismatch");
+        }
+        let x = DeviceVec::upload(x);
+        let mut res = DeviceVec::zeros(self.rows);
+        self.mul_vec(&x, &mut res);
+        res.download_into(y);
+    }
+}
+
+#[allow(non_snake_case)]
+mod ffi {
+    //! Bindings to the CUDA runtime and to the generic API of cuSPARSE
+    use std::os::raw::{c_int, c_void};
+    use std::ptr;
+
+    pub type SpMatDescr = *mut c_void;
+    pub type SpGEMMDescr = *mut c_void;
+    pub type DnVecDescr = *mut c_void;
+    pub type DnMatDescr = *mut c_void;
+
+    pub const CUDA_R_32F: c_int = 0;
+    pub const CUDA_R_64F: c_int = 1;
+    pub const CUDA_MEMCPY_HOST_TO_DEVICE: c_int = 1;
+    pub const CUDA_MEMCPY_DEVICE_TO_HOST: c_int = 2;
+    pub const CUSPARSE_OPERATION_NON_TRANSPOSE: c_int = 0;
+    pub const CUSPARSE_INDEX_32I: c_int = 2;
+    pub const CUSPARSE_INDEX_BASE_ZERO: c_int = 0;
+    pub const CUSPARSE_ORDER_COL: c_int = 1;
+    pub const CUSPARSE_SPMV_ALG_DEFAULT: c_int = 0;
+    pub const CUSPARSE_SPMM_ALG_DEFAULT: c_int = 0;
+    pub const CUSPARSE_SPGEMM_DEFAULT: c_int = 0;
+
+    #[link(name = "cudart")]
+    extern "C" {
+        pub fn cudaMalloc(ptr: *mut *mut c_void, size: usize) -> c_int;
+        pub fn cudaFree(ptr: *mut c_void) -> c_int;
+        pub fn cudaMemcpy(dst: *mut c_void, src: *const c_void,
+                          count: usize, kind: c_int) -> c_int;
+    }
+
+    #[link(name = "cusparse")]
+    extern "C" {
+        pub fn cusparseCreate(handle: *mut *mut c_void) -> c_int;
+        pub fn cusparseDestroy(handle: *mut c_void) -> c_int;
+        pub fn cusparseCreateCsr(descr: *mut SpMatDescr, rows: i64,
+                                 cols: i64, nnz: i64,
+                                 row_offsets: *mut c_void,
+                                 col_ind: *mut c_void,
+                                 values: *mut c_void,
+                                 row_offsets_type: c_int,
+                                 col_ind_type: c_int,
+                                 idx_base: c_int,
+                                 value_type: c_int) -> c_int;
+        pub fn cusparseDestroySpMat(descr: SpMatDescr) -> c_int;
+        pub fn cusparseCreateDnVec(descr: *mut DnVecDescr, size: i64,
+                                   values: *mut c_void,
+                                   value_type: c_int) -> c_int;
+        pub fn cusparseDestroyDnVec(descr: DnVecDescr) -> c_int;
+        pub fn cusparseCreateDnMat(descr: *mut DnMatDescr, rows: i64,
+                                   cols: i64, ld: i64, values: *mut c_void,
+                                   value_type: c_int, order: c_int) -> c_int;
+        pub fn cusparseDestroyDnMat(descr: DnMatDescr) -> c_int;
+        pub fn cusparseSpMV_bufferSize(handle: *mut c_void, op: c_int,
+                                       alpha: *const c_void,
+                                       mat: SpMatDescr, x: DnVecDescr,
+                                       beta: *const c_void, y: DnVecDescr,
+                                       compute_type: c_int, alg: c_int,
+                                       size: *mut usize) -> c_int;
+        pub fn cusparseSpMV(handle: *mut c_void, op: c_int,
+                            alpha: *const c_void, mat: SpMatDescr,
+                            x: DnVecDescr, beta: *const c_void,
+                            y: DnVecDescr, compute_type: c_int, alg: c_int,
+                            buffer: *mut c_void) -> c_int;
+        pub fn cusparseSpMM_bufferSize(handle: *mut c_void, op_a: c_int,
+                                       op_b: c_int, alpha: *const c_void,
+                                       mat_a: SpMatDescr, mat_b: DnMatDescr,
+                                       beta: *const c_void,
+                                       mat_c: DnMatDescr,
+                                       compute_type: c_int, alg: c_int,
+                                       size: *mut usize) -> c_int;
+        pub fn cusparseSpMM(handle: *mut c_void, op_a: c_int, op_b: c_int,
+                            alpha: *const c_void, mat_a: SpMatDescr,
+                            mat_b: DnMatDescr, beta: *const c_void,
+                            mat_c: DnMatDescr, compute_type: c_int,
+                            alg: c_int, buffer: *mut c_void) -> c_int;
+        pub fn cusparseSpMatGetSize(descr: SpMatDescr, rows: *mut i64,
+                                    cols: *mut i64, nnz: *mut i64) -> c_int;
+        pub fn cusparseCsrSetPointers(descr: SpMatDescr,
+                                      row_offsets: *mut c_void,
+                                      col_ind: *mut c_void,
+                                      values: *mut c_void) -> c_int;
+        pub fn cusparseSpGEMM_createDescr(descr: *mut SpGEMMDescr) -> c_int;
+        pub fn cusparseSpGEMM_destroyDescr(descr: SpGEMMDescr) -> c_int;
+        pub fn cusparseSpGEMM_workEstimation(handle: *mut c_void,
+                                             op_a: c_int, op_b: c_int,
+                                             alpha: *const c_void,
+                                             mat_a: SpMatDescr,
+                                             mat_b: SpMatDescr,
+                                             beta: *const c_void,
+                                             mat_c: SpMatDescr,
+                                             compute_type: c_int, alg: c_int,
+                                             spgemm_descr: SpGEMMDescr,
+                                             size: *mut usize,
+                                             buffer: *mut c_void) -> c_int;
+        pub fn cusparseSpGEMM_compute(handle: *mut c_void,
+                                      op_a: c_int, op_b: c_int,
+                                      alpha: *const c_void,
+                                      mat_a: SpMatDescr, mat_b: SpMatDescr,
+                                      beta: *const c_void,
+                                      mat_c: SpMatDescr,
+                                      compute_type: c_int, alg: c_int,
+                                      spgemm_descr: SpGEMMDescr,
+                                      size: *mut usize,
+                                      buffer: *mut c_void) -> c_int;
+        pub fn cusparseSpGEMM_copy(handle: *mut c_void,
+                                   op_a: c_int, op_b: c_int,
+                                   alpha: *const c_void,
+                                   mat_a: SpMatDescr, mat_b: SpMatDescr,
+                                   beta: *const c_void, mat_c: SpMatDescr,
+                                   compute_type: c_int, alg: c_int,
+                                   spgemm_descr: SpGEMMDescr) -> c_int;
+    }
+
+    pub fn check_cuda(status: c_int) {
+        assert_eq!(status, 0, "CUDA runtime error");
+    }
+
+    pub fn check(status: c_int) {
+        assert_eq!(status, 0, "cuSPARSE error");
+    }
+
+    /// A cuSPARSE library handle, destroyed on drop
+    struct Handle(*mut c_void);
+
+    impl Handle {
+        fn new() -> Handle {
+            let mut handle = ptr::null_mut();
+            unsafe {
+                check(cusparseCreate(&mut handle));
+            }
+            Handle(handle)
+        }
+    }
+
+    impl Drop for Handle {
+        fn drop(&mut self) {
+            unsafe {
+                cusparseDestroy(self.0);
+            }
+        }
+    }
+
+    thread_local! {
+        // handles are costly to create, and are not meant to be shared
+        // between threads
+        static HANDLE: Handle = Handle::new();
+    }
+
+    /// Call `f` with the cuSPARSE handle of the current thread
+    pub fn with_handle<F, R>(f: F) -> R
+    where F: FnOnce(*mut c_void) -> R
+    {
+        HANDLE.with(|handle| f(handle.0))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sparse::{CsMat, CsMatI};
+    use sparse::backend::BackendMat;
+    use sparse::linalg::LinearOperator;
+    use test_data::{mat1, mat2, mat1_matprod_mat2};
+    use super::{CudaCsMat, DeviceDenseMat, DeviceVec};
+
+    fn mat1_i32() -> CsMatI<f64, i32> {
+        mat1().to_other_types()
+    }
+
+    #[test]
+    fn round_trips() {
+        let mat = mat1_i32();
+        assert_eq!(CudaCsMat::upload(mat.view()).download(), mat);
+        let gpu_mat = CudaCsMat::upload(mat.to_csc().view());
+        assert_eq!(gpu_mat.shape(), (5, 5));
+        assert_eq!(gpu_mat.nnz(), mat.nnz());
+        assert_eq!(gpu_mat.download(), mat);
+
+        let empty: CsMatI<f32, i32> = CsMatI::zero_i((3, 2));
+        assert_eq!(CudaCsMat::upload(empty.view()).download(), empty);
+
+        let x = [1., -2., 3., 0.5, 2.];
+        assert_eq!(DeviceVec::upload(&x).download(), x.to_vec());
+        let dense = [1., 2., 3., 4., 5., 6.];
+        let gpu_dense = DeviceDenseMat::upload((3, 2), &dense);
+        assert_eq!(gpu_dense.shape(), (3, 2));
+        assert_eq!(gpu_dense.download(), dense.to_vec());
+    }
+
+    #[test]
+    fn products() {
+        let mat = mat1_i32();
+        let gpu_mat = CudaCsMat::upload(mat.view());
+        let x = [1., -2., 3., 0.5, 2.];
+        let expected = &mat * &::ndarray::arr1(&x);
+        let mut y = vec![0.; 5];
+        gpu_mat.apply(&x, &mut y);
+        assert_eq!(y, expected.to_vec());
+
+        // column major 5x2 right hand side
+        let rhs: Vec<f64> = (0..10).map(|i| i as f64).collect();
+        let host_rhs = ::ndarray::Array2::from_shape_vec((2, 5), rhs.clone())
+                                         .unwrap()
+                                         .reversed_axes();
+        let mut out = DeviceDenseMat::zeros((5, 2));
+        gpu_mat.mul_dense(&DeviceDenseMat::upload((5, 2), &rhs), &mut out);
+        let expected = &mat * &host_rhs;
+        let expected: Vec<f64> = expected.t().iter().cloned().collect();
+        assert_eq!(out.download(), expected);
+
+        let sparse_rhs: CsMatI<f64, i32> = mat2().to_other_types();
+        let gpu_rhs = CudaCsMat::upload(sparse_rhs.view());
+        let prod: CsMat<f64> = gpu_mat.spgemm(&gpu_rhs)
+                                      .download()
+                                      .to_other_types();
+        assert_eq!(prod, mat1_matprod_mat2());
+    }
+
+    /// `mat * mat * x`, computed wherever `M` computes
+    fn square_apply<M: BackendMat<f64>>(mat: &M, x: &[f64]) -> Vec<f64> {
+        let square = mat.spgemm(mat);
+        let x = M::upload_vec(x);
+        let mut y = M::upload_vec(&vec![0.; mat.shape().0]);
+        square.mul_vec(&x, &mut y);
+        M::download_vec(&y)
+    }
+
+    #[test]
+    fn generic_over_backends() {
+        let mat = mat1_i32();
+        let x = [1., -2., 3., 0.5, 2.];
+        assert_eq!(square_apply(&CudaCsMat::upload(mat.view()), &x),
+                   square_apply(&mat, &x));
+    }
+}
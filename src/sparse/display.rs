@@ -0,0 +1,195 @@
+///! Textual rendering of sparse matrices
+///!
+///! Small matrices can be printed in a dense layout, where the non-stored
+///! entries are shown as dots, to eyeball their values in tests and logs.
+///! The `Display` implementation of `CsMatBase` uses this layout, truncated
+///! to the top left corner of large matrices.
+///!
+///! For larger matrices, `spy_string` renders the sparsity pattern as an
+///! ASCII density plot. Image outputs (PNG and SVG) are available in the
+///! `plot` module behind the `plot` feature.
+
+use std::fmt;
+use std::ops::Deref;
+
+use indexing::SpIndex;
+use sparse::prelude::*;
+
+/// The maximum number of rows and columns printed by `Display`
+const DISPLAY_MAX_SIZE: usize = 10;
+
+/// The characters of the density plot, by increasing density
+const SPY_LEVELS: [char; 4] = ['.', '+', '*', '#'];
+
+impl<N, I, IpS, IS, DS> CsMatBase<N, I, IpS, IS, DS>
+where I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    /// Render the top left `max_size x max_size` corner of this matrix in a
+    /// dense layout, one line per row, with the non-stored entries shown as
+    /// `.`. Truncated rows and columns are replaced by `...`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::CsMat;
+    /// let eye: CsMat<f64> = CsMat::eye(3);
+    /// assert_eq!(eye.to_string_dense(2), "1 . ...\n. 1 ...\n...");
+    /// ```
+    pub fn to_string_dense(&self, max_size: usize) -> String
+    where N: fmt::Display
+    {
+        let shown_rows = self.rows().min(max_size);
+        let shown_cols = self.cols().min(max_size);
+        let mut cells = vec![None; shown_rows * shown_cols];
+        let mut width = 1;
+        for (outer, vec) in self.outer_iterator().enumerate() {
+            for (inner, val) in vec.iter() {
+                let (row, col) = if self.is_csr() { (outer, inner) }
+                                 else { (inner, outer) };
+                if row < shown_rows && col < shown_cols {
+                    let repr = val.to_string();
+                    width = width.max(repr.chars().count());
+                    cells[row * shown_cols + col] = Some(repr);
+                }
+            }
+        }
+        let mut lines = Vec::with_capacity(shown_rows + 1);
+        for row in 0..shown_rows {
+            let mut line: Vec<String> = cells[row * shown_cols
+                                              ..(row + 1) * shown_cols]
+                .iter()
+                .map(|cell| {
+                    let repr = cell.as_ref().map_or(".", |s| &s[..]);
+                    format!("{:>w$}", repr, w=width)
+                })
+                .collect();
+            if shown_cols < self.cols() {
+                line.push("...".to_string());
+            }
+            lines.push(line.join(" "));
+        }
+        if shown_rows < self.rows() {
+            lines.push("...".to_string());
+        }
+        lines.join("\n")
+    }
+
+    /// Render the sparsity pattern of this matrix as an ASCII density plot
+    /// of at most `width` characters per line and `height` lines.
+    ///
+    /// Each character covers a block of the matrix, and shows the fraction
+    /// of its entries which are stored: `.` for an empty block, then `+`,
+    /// `*` and `#` for blocks filled up to a quarter, up to a half, and
+    /// above. Matrices smaller than the plot get one character per entry.
+    ///
+    /// # Panics
+    ///
+    /// If `width` or `height` is zero.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::CsMat;
+    /// let eye: CsMat<f64> = CsMat::eye(4);
+    /// assert_eq!(eye.spy_string(2, 2), "*.\n.*");
+    /// ```
+    pub fn spy_string(&self, width: usize, height: usize) -> String {
+        assert!(width > 0 && height > 0, "spy plots need at least one cell");
+        let block_rows = ((self.rows() + height - 1) / height).max(1);
+        let block_cols = ((self.cols() + width - 1) / width).max(1);
+        let grid_rows = (self.rows() + block_rows - 1) / block_rows;
+        let grid_cols = (self.cols() + block_cols - 1) / block_cols;
+        let mut counts = vec![0usize; grid_rows * grid_cols];
+        for (outer, vec) in self.outer_iterator().enumerate() {
+            for &inner in vec.indices() {
+                let (row, col) = if self.is_csr() { (outer, inner.index()) }
+                                 else { (inner.index(), outer) };
+                counts[(row / block_rows) * grid_cols + col / block_cols] += 1;
+            }
+        }
+        let mut lines = Vec::with_capacity(grid_rows);
+        for grid_row in 0..grid_rows {
+            let nrows = block_rows.min(self.rows() - grid_row * block_rows);
+            let line: String = (0..grid_cols).map(|grid_col| {
+                let ncols = block_cols.min(self.cols()
+                                           - grid_col * block_cols);
+                let count = counts[grid_row * grid_cols + grid_col];
+                let density = count as f64 / (nrows * ncols) as f64;
+                if count == 0 {
+                    SPY_LEVELS[0]
+                }
+                else if density <= 0.25 {
+                    SPY_LEVELS[1]
+                }
+                else if density <= 0.5 {
+                    SPY_LEVELS[2]
+                }
+                else {
+                    SPY_LEVELS[3]
+                }
+            }).collect();
+            lines.push(line);
+        }
+        lines.join("\n")
+    }
+}
+
+/// Print the shape, storage and number of non-zeros of the matrix, followed
+/// by its dense layout, truncated to its top left `10 x 10` corner.
+impl<N, I, IpS, IS, DS> fmt::Display for CsMatBase<N, I, IpS, IS, DS>
+where N: fmt::Display,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let storage = if self.is_csr() { "CSR" } else { "CSC" };
+        try!(writeln!(f, "{}x{} {} matrix with {} non-zeros",
+                      self.rows(), self.cols(), storage, self.nnz()));
+        write!(f, "{}", self.to_string_dense(DISPLAY_MAX_SIZE))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sparse::CsMat;
+    use test_data::{mat1, mat1_csc};
+
+    #[test]
+    fn dense_layout() {
+        let expected = ". . 3 4 .\n\
+                        . . . 2 5\n\
+                        . . 5 . .\n\
+                        . 8 . . .\n\
+                        . . . 7 .";
+        assert_eq!(mat1().to_string_dense(5), expected);
+        assert_eq!(mat1_csc().to_string_dense(5), expected);
+        assert_eq!(mat1().to_string_dense(3),
+                   ". . 3 ...\n. . . ...\n. . 5 ...\n...");
+        let mat = CsMat::new((2, 2), vec![0, 1, 2], vec![0, 1],
+                             vec![-1.5, 10.]);
+        assert_eq!(mat.to_string_dense(2), "-1.5    .\n   .   10");
+    }
+
+    #[test]
+    fn display() {
+        let eye: CsMat<i32> = CsMat::eye(2);
+        assert_eq!(format!("{}", eye),
+                   "2x2 CSR matrix with 2 non-zeros\n1 .\n. 1");
+    }
+
+    #[test]
+    fn spy_string() {
+        assert_eq!(mat1().spy_string(10, 10),
+                   "..##.\n...##\n..#..\n.#...\n...#.");
+        assert_eq!(mat1().spy_string(3, 3), ".#*\n++.\n.*.");
+        assert_eq!(mat1_csc().spy_string(3, 3), mat1().spy_string(3, 3));
+        let eye: CsMat<f64> = CsMat::eye(8);
+        assert_eq!(eye.spy_string(2, 2), "+.\n.+");
+        assert_eq!(eye.spy_string(8, 2), "++++....\n....++++");
+    }
+}
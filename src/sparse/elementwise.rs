@@ -0,0 +1,190 @@
+///! Element-wise maps fused over the non-zeros of a matrix
+///!
+///! Sparse inference pipelines apply scalar functions, such as activations
+///! or quantization, to every entry of a matrix. When a function maps zero
+///! to zero, it only needs to be applied to the stored entries and the
+///! result stays sparse: this is the case of `relu`, `clip` with bounds
+///! around zero, or symmetric quantization. Otherwise the implicit zeros of
+///! the input become non-zeros and the result is returned as a dense
+///! array, eg for `sigmoid` or quantization with a non-zero zero point.
+///!
+///! The sparse results do not store the zeros produced by the maps.
+///!
+///! # Example
+///!
+///! ```rust
+///! use sprs::CsMat;
+///! let mat = CsMat::new((2, 2), vec![0, 2, 3], vec![0, 1, 1],
+///!                      vec![-1., 0.26, 1.5]);
+///! let activated = mat.relu();
+///! assert_eq!(activated.data(), &[0.26, 1.5]);
+///! let quantized = activated.round_to_i8(0.01);
+///! assert_eq!(quantized.data(), &[26, 127]);
+///! ```
+
+use std::ops::Deref;
+use num_traits::Float;
+use ndarray::Array;
+
+use indexing::SpIndex;
+use sparse::prelude::*;
+use ::Ix2;
+
+impl<N, I, IpS, IS, DS> CsMatBase<N, I, IpS, IS, DS>
+where I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    /// The rectified linear unit `max(x, 0)` of each entry
+    pub fn relu(&self) -> CsMatI<N, I>
+    where N: Float
+    {
+        self.filter_map_nnz(|&x| if x > N::zero() { Some(x) } else { None })
+    }
+
+    /// Clamp each entry to the interval `[min, max]`
+    ///
+    /// # Panics
+    ///
+    /// If the interval does not contain zero, as the implicit zeros would
+    /// then be changed. Use `to_dense` and `ndarray` for such clipping.
+    pub fn clip(&self, min: N, max: N) -> CsMatI<N, I>
+    where N: Float
+    {
+        assert!(min <= N::zero() && N::zero() <= max,
+                "the clipping interval must contain zero");
+        self.filter_map_nnz(|&x| {
+            let clipped = x.max(min).min(max);
+            if clipped.is_zero() { None } else { Some(clipped) }
+        })
+    }
+
+    /// Symmetric quantization of the entries to `i8`, ie
+    /// `round(x / scale)` saturated to `[-128, 127]`. The zero point is
+    /// zero, so the result stays sparse.
+    pub fn round_to_i8(&self, scale: N) -> CsMatI<i8, I>
+    where N: Float
+    {
+        self.filter_map_nnz(|&x| {
+            let quantized = quantize_i8(x, scale, 0);
+            if quantized == 0 { None } else { Some(quantized) }
+        })
+    }
+
+    /// Affine quantization of the entries to `i8`, ie
+    /// `round(x / scale) + zero_point` saturated to `[-128, 127]`. As the
+    /// implicit zeros are mapped to `zero_point`, the result is dense.
+    pub fn round_to_i8_dense(&self, scale: N, zero_point: i8)
+                             -> Array<i8, Ix2>
+    where N: Float
+    {
+        self.map_to_dense(zero_point, |x| quantize_i8(x, scale, zero_point))
+    }
+
+    /// The logistic sigmoid `1 / (1 + exp(-x))` of each entry. As the
+    /// implicit zeros are mapped to `0.5`, the result is dense.
+    pub fn sigmoid(&self) -> Array<N, Ix2>
+    where N: Float
+    {
+        let half = N::one() / (N::one() + N::one());
+        self.map_to_dense(half, |x| N::one() / (N::one() + (-x).exp()))
+    }
+
+    /// Apply `f` to the stored entries, keeping the entries mapped to
+    /// `Some` value
+    fn filter_map_nnz<M, F>(&self, mut f: F) -> CsMatI<M, I>
+    where F: FnMut(&N) -> Option<M>
+    {
+        let mut indptr = Vec::with_capacity(self.outer_dims() + 1);
+        indptr.push(I::zero());
+        let mut indices = Vec::new();
+        let mut data = Vec::new();
+        for vec in self.outer_iterator() {
+            for (inner, val) in vec.iter() {
+                if let Some(mapped) = f(val) {
+                    indices.push(I::from_usize(inner));
+                    data.push(mapped);
+                }
+            }
+            indptr.push(I::from_usize(indices.len()));
+        }
+        CsMatI {
+            storage: self.storage(),
+            nrows: self.rows(),
+            ncols: self.cols(),
+            indptr: indptr,
+            indices: indices,
+            data: data,
+        }
+    }
+
+    /// Dense result of a map sending the implicit zeros to `zero_image`
+    fn map_to_dense<M, F>(&self, zero_image: M, mut f: F) -> Array<M, Ix2>
+    where N: Copy,
+          M: Clone,
+          F: FnMut(N) -> M
+    {
+        let mut res = Array::from_elem(self.shape(), zero_image);
+        for (outer, vec) in self.outer_iterator().enumerate() {
+            for (inner, &val) in vec.iter() {
+                let pos = if self.is_csr() { [outer, inner] }
+                          else { [inner, outer] };
+                res[pos] = f(val);
+            }
+        }
+        res
+    }
+}
+
+/// `round(x / scale) + zero_point`, saturated to the range of `i8`
+fn quantize_i8<N: Float>(x: N, scale: N, zero_point: i8) -> i8 {
+    let shifted = (x / scale).round() + N::from(zero_point).unwrap();
+    let min = N::from(i8::min_value()).unwrap();
+    let max = N::from(i8::max_value()).unwrap();
+    // NaN is mapped to the zero point
+    if shifted.is_nan() {
+        return zero_point;
+    }
+    shifted.max(min).min(max).to_i8().unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::arr2;
+    use sparse::CsMat;
+    use test_data::{mat1, mat1_csc};
+
+    #[test]
+    fn sparse_maps() {
+        let mat = CsMat::new((2, 3), vec![0, 2, 4], vec![0, 2, 1, 2],
+                             vec![-2., 0.5, 3., -0.]);
+        assert_eq!(mat.relu(), CsMat::new((2, 3), vec![0, 1, 2], vec![2, 1],
+                                          vec![0.5, 3.]));
+        assert_eq!(mat.clip(-1., 1.),
+                   CsMat::new((2, 3), vec![0, 2, 3], vec![0, 2, 1],
+                              vec![-1., 0.5, 1.]));
+        assert_eq!(mat.clip(0., 1.), mat.relu().clip(-1., 1.));
+        assert_eq!(mat.round_to_i8(0.01).data(), &[-128, 50, 127]);
+        assert_eq!(mat.round_to_i8(1.).data(), &[-2, 1, 3]);
+        assert_eq!(mat1_csc().relu(), mat1_csc());
+    }
+
+    #[test]
+    #[should_panic]
+    fn clip_without_zero() {
+        mat1().clip(1., 2.);
+    }
+
+    #[test]
+    fn dense_maps() {
+        let mat = CsMat::new((1, 3), vec![0, 2], vec![0, 2], vec![-1f64, 1.]);
+        assert_eq!(mat.round_to_i8_dense(0.5, 10), arr2(&[[8, 10, 12]]));
+        assert_eq!(mat.to_csc().round_to_i8_dense(0.5, 10),
+                   arr2(&[[8, 10, 12]]));
+        let sig = mat.sigmoid();
+        assert_eq!(sig[[0, 1]], 0.5);
+        assert!((sig[[0, 0]] + sig[[0, 2]] - 1.).abs() < 1e-12);
+        assert!((sig[[0, 2]] - 1. / (1. + (-1f64).exp())).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,2109 @@
+///! Graph algorithms on adjacency matrices
+///!
+///! A sparse matrix can be seen as the weighted adjacency matrix of a graph,
+///! where the entry `a_ij` is the weight of the edge from node `i` to node
+///! `j`. This module builds the Laplacian matrices of such graphs, as used
+///! in spectral clustering, graph partitioning or diffusion processes, and
+///! analyzes their connectivity using only the sparsity pattern.
+///!
+///! Diagonal entries of the adjacency matrix (self loops) do not contribute
+///! to Laplacians, and the degree of a node is computed from its row, ie
+///! as an out-degree for directed graphs.
+///!
+///! All Laplacians can be normalized in the same ways, see `Normalization`.
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::ops::Deref;
+use num_traits::Float;
+
+use indexing::SpIndex;
+use errors::SprsError;
+use sparse::prelude::*;
+use sparse::prod;
+use ::SpRes;
+
+/// The normalization applied to a Laplacian `L = D - A`, where `D` is the
+/// diagonal matrix of node degrees.
+///
+/// Nodes with a zero degree have an empty row (and column for the symmetric
+/// normalization) in normalized Laplacians.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normalization {
+    /// `L = D - A`
+    Unnormalized,
+    /// `L = D^-1/2 (D - A) D^-1/2 = I - D^-1/2 A D^-1/2`
+    Symmetric,
+    /// `L = D^-1 (D - A) = I - D^-1 A`
+    RandomWalk,
+}
+
+/// Build the Laplacian of the graph with adjacency `adj`, given the degree
+/// of each node. The result is in CSR storage.
+fn laplacian_with_degrees<N, I>(adj: &CsMatI<N, I>,
+                                degrees: &[N],
+                                normalization: Normalization) -> CsMatI<N, I>
+where N: Float + Default,
+      I: SpIndex,
+{
+    let n = adj.rows();
+    let inv_sqrt: Vec<N> = degrees.iter().map(|&d| {
+        if d.is_zero() { N::zero() } else { N::one() / d.abs().sqrt() }
+    }).collect();
+    let scale = |row: usize, col: usize| match normalization {
+        Normalization::Unnormalized => N::one(),
+        Normalization::Symmetric => inv_sqrt[row] * inv_sqrt[col],
+        Normalization::RandomWalk => inv_sqrt[row] * inv_sqrt[row],
+    };
+    let mut lap = TriMatI::with_capacity((n, n), adj.nnz() + n);
+    for (row, &degree) in degrees.iter().enumerate() {
+        let val = degree * scale(row, row);
+        if !val.is_zero() {
+            lap.add_triplet(row, row, val);
+        }
+    }
+    for (row, vec) in adj.outer_iterator().enumerate() {
+        for (col, &val) in vec.iter() {
+            if row != col {
+                lap.add_triplet(row, col, -val * scale(row, col));
+            }
+        }
+    }
+    lap.to_csr()
+}
+
+fn check_adjacency<N, I, IpS, IS, DS>(adj: &CsMatBase<N, I, IpS, IS, DS>)
+where I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    if adj.rows() != adj.cols() {
+        panic!("Non square adjacency matrix");
+    }
+}
+
+/// Build the Laplacian of the graph with adjacency `adj`, the degrees being
+/// the sums of the weights of the outgoing edges
+fn weighted_laplacian<N, I, IpS, IS, DS>(adj: &CsMatBase<N, I, IpS, IS, DS>,
+                                         normalization: Normalization)
+                                         -> CsMatI<N, I>
+where N: Float + Default,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    check_adjacency(adj);
+    let adj = adj.to_csr();
+    let degrees: Vec<N> = adj.outer_iterator().enumerate().map(|(row, vec)| {
+        vec.iter().filter(|&(col, _)| col != row)
+                  .fold(N::zero(), |acc, (_, &val)| acc + val)
+    }).collect();
+    laplacian_with_degrees(&adj, &degrees, normalization)
+}
+
+/// The Laplacian `L = D - A` of the graph with adjacency `A`, where the
+/// degree `d_i = sum_j a_ij` sums the weights of the edges leaving `i`.
+///
+/// The adjacency of an unweighted graph should have unit entries. For an
+/// undirected graph, ie a symmetric adjacency matrix, the Laplacian is
+/// symmetric positive semi-definite, and the multiplicity of its zero
+/// eigenvalue is the number of connected components. For a directed graph,
+/// the rows of the Laplacian sum to zero, but it is not symmetric.
+///
+/// # Panics
+///
+/// If the adjacency matrix is not square.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::graph::laplacian;
+/// // the path 0 -- 1 -- 2
+/// let adj = CsMat::new((3, 3),
+///                      vec![0, 1, 3, 4],
+///                      vec![1, 0, 2, 1],
+///                      vec![1., 1., 1., 1.]);
+/// let lap = laplacian(&adj);
+/// let expected = CsMat::new((3, 3),
+///                           vec![0, 2, 5, 7],
+///                           vec![0, 1, 0, 1, 2, 1, 2],
+///                           vec![1., -1., -1., 2., -1., -1., 1.]);
+/// assert_eq!(lap, expected);
+/// ```
+pub fn laplacian<N, I, IpS, IS, DS>(adj: &CsMatBase<N, I, IpS, IS, DS>)
+                                    -> CsMatI<N, I>
+where N: Float + Default,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    weighted_laplacian(adj, Normalization::Unnormalized)
+}
+
+/// The symmetric normalized Laplacian `I - D^-1/2 A D^-1/2` of the graph
+/// with adjacency `A`, where the degree `d_i = sum_j a_ij` sums the weights
+/// of the edges leaving `i`.
+///
+/// For an undirected graph, the eigenvalues of the normalized Laplacian
+/// lie in `[0, 2]`, which makes it the usual choice of spectral clustering.
+/// Isolated nodes have an empty row and column.
+///
+/// # Panics
+///
+/// If the adjacency matrix is not square.
+pub fn normalized_laplacian<N, I, IpS, IS, DS>(adj: &CsMatBase<N, I, IpS,
+                                                               IS, DS>)
+                                               -> CsMatI<N, I>
+where N: Float + Default,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    weighted_laplacian(adj, Normalization::Symmetric)
+}
+
+/// The random walk Laplacian `I - D^-1 A` of the graph with adjacency `A`,
+/// where the degree `d_i = sum_j a_ij` sums the weights of the edges
+/// leaving `i`.
+///
+/// `D^-1 A` is the transition matrix of a random walk on the graph, whose
+/// rows sum to one, so the rows of the random walk Laplacian sum to zero,
+/// for directed graphs too. Isolated nodes have an empty row.
+///
+/// # Panics
+///
+/// If the adjacency matrix is not square.
+pub fn random_walk_laplacian<N, I, IpS, IS, DS>(adj: &CsMatBase<N, I, IpS,
+                                                                IS, DS>)
+                                                -> CsMatI<N, I>
+where N: Float + Default,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    weighted_laplacian(adj, Normalization::RandomWalk)
+}
+
+/// The signed Laplacian `L = D - A` of a graph whose edges may have
+/// negative weights, where the signed degree `d_i = sum_j |a_ij|` sums
+/// the absolute values of the weights.
+///
+/// Contrary to the plain Laplacian, the signed Laplacian stays positive
+/// semi-definite for symmetric signed adjacency matrices, and is singular
+/// only for structurally balanced graphs.
+///
+/// # Panics
+///
+/// If the adjacency matrix is not square.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::graph::{signed_laplacian, Normalization};
+/// // a friend (1.) and a foe (-1.)
+/// let adj = CsMat::new((3, 3),
+///                      vec![0, 2, 3, 4],
+///                      vec![1, 2, 0, 0],
+///                      vec![1., -1., 1., -1.]);
+/// let lap = signed_laplacian(&adj, Normalization::Unnormalized);
+/// assert_eq!(lap.get(0, 0), Some(&2.));
+/// assert_eq!(lap.get(0, 1), Some(&-1.));
+/// assert_eq!(lap.get(0, 2), Some(&1.));
+/// ```
+pub fn signed_laplacian<N, I, IpS, IS, DS>(adj: &CsMatBase<N, I, IpS, IS, DS>,
+                                           normalization: Normalization)
+                                           -> CsMatI<N, I>
+where N: Float + Default,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    check_adjacency(adj);
+    let adj = adj.to_csr();
+    let degrees: Vec<N> = adj.outer_iterator().enumerate().map(|(row, vec)| {
+        vec.iter().filter(|&(col, _)| col != row)
+                  .fold(N::zero(), |acc, (_, val)| acc + val.abs())
+    }).collect();
+    laplacian_with_degrees(&adj, &degrees, normalization)
+}
+
+/// The Laplacian of the bipartite graph described by the biadjacency
+/// (incidence) matrix `biadj`, of shape `(m, n)`, where `biadj[i, j]` is
+/// the weight of the edge between the node `i` of the first set and the
+/// node `j` of the second set.
+///
+/// The result is the `(m + n) x (m + n)` Laplacian of the graph whose
+/// adjacency matrix is `[[0, B], [B^T, 0]]`: the nodes of the first set
+/// come first, followed by the nodes of the second set. Degrees are the
+/// row sums of `B` for the first set and its column sums for the second.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::graph::{bipartite_laplacian, Normalization};
+/// // two users rating three items
+/// let ratings = CsMat::new((2, 3),
+///                          vec![0, 2, 4],
+///                          vec![0, 1, 1, 2],
+///                          vec![1., 1., 1., 1.]);
+/// let lap = bipartite_laplacian(&ratings, Normalization::Unnormalized);
+/// assert_eq!(lap.shape(), (5, 5));
+/// assert_eq!(lap.get(0, 0), Some(&2.));
+/// assert_eq!(lap.get(3, 3), Some(&2.));
+/// assert_eq!(lap.get(0, 2), Some(&-1.));
+/// assert_eq!(lap.get(2, 0), Some(&-1.));
+/// ```
+pub fn bipartite_laplacian<N, I, IpS, IS, DS>(biadj: &CsMatBase<N, I, IpS,
+                                                                IS, DS>,
+                                              normalization: Normalization)
+                                              -> CsMatI<N, I>
+where N: Float + Default,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    let (m, n) = biadj.shape();
+    let mut adj = TriMatI::with_capacity((m + n, m + n), 2 * biadj.nnz());
+    let mut degrees = vec![N::zero(); m + n];
+    for (row, vec) in biadj.to_csr().outer_iterator().enumerate() {
+        for (col, &val) in vec.iter() {
+            adj.add_triplet(row, m + col, val);
+            adj.add_triplet(m + col, row, val);
+            degrees[row] = degrees[row] + val;
+            degrees[m + col] = degrees[m + col] + val;
+        }
+    }
+    let adj: CsMatI<N, I> = adj.to_csr();
+    laplacian_with_degrees(&adj, &degrees, normalization)
+}
+
+/// The sparsity pattern of a matrix in CSR form, giving the successors of
+/// each node, ie the columns of each row. The structure of a CSR matrix is
+/// borrowed.
+struct Pattern<'a, I: 'a + Clone> {
+    indptr: Cow<'a, [I]>,
+    indices: Cow<'a, [I]>,
+}
+
+impl<'a, I: 'a + SpIndex> Pattern<'a, I> {
+    fn new<N, IpS, IS, DS>(adj: &'a CsMatBase<N, I, IpS, IS, DS>)
+                           -> Pattern<'a, I>
+    where IpS: Deref<Target=[I]>,
+          IS: Deref<Target=[I]>,
+          DS: Deref<Target=[N]>,
+    {
+        let n = adj.rows();
+        if adj.is_csr() {
+            return Pattern {
+                indptr: Cow::Borrowed(adj.indptr()),
+                indices: Cow::Borrowed(adj.indices()),
+            };
+        }
+        let mut indptr = vec![0; n + 1];
+        for &row in adj.indices() {
+            indptr[row.index() + 1] += 1;
+        }
+        for row in 0..n {
+            indptr[row + 1] += indptr[row];
+        }
+        let mut next = indptr.clone();
+        let mut indices = vec![I::zero(); adj.nnz()];
+        for (col, vec) in adj.outer_iterator().enumerate() {
+            for (row, _) in vec.iter() {
+                indices[next[row]] = I::from_usize(col);
+                next[row] += 1;
+            }
+        }
+        let indptr = indptr.into_iter().map(I::from_usize).collect();
+        Pattern {
+            indptr: Cow::Owned(indptr),
+            indices: Cow::Owned(indices),
+        }
+    }
+
+    fn nb_nodes(&self) -> usize {
+        self.indptr.len() - 1
+    }
+
+    fn successors(&self, node: usize) -> &[I] {
+        let start = self.indptr[node].index();
+        let end = self.indptr[node + 1].index();
+        &self.indices[start..end]
+    }
+}
+
+/// Find the root of the set containing `node`, compressing the path
+fn find_root(parents: &mut [usize], node: usize) -> usize {
+    let mut root = node;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    let mut node = node;
+    while parents[node] != root {
+        let next = parents[node];
+        parents[node] = root;
+        node = next;
+    }
+    root
+}
+
+/// The connected components of the graph with adjacency `adj`, ignoring
+/// the direction of the edges (ie the weakly connected components for a
+/// directed graph).
+///
+/// Returns the number of components and the component of each node.
+/// Components are numbered in the order of their first node.
+///
+/// # Panics
+///
+/// If the adjacency matrix is not square.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::graph::connected_components;
+/// // the edges 0 -> 2 and 3 -> 1
+/// let adj = CsMat::new((4, 4),
+///                      vec![0, 1, 1, 1, 2],
+///                      vec![2, 1],
+///                      vec![1., 1.]);
+/// let (nb_components, labels) = connected_components(&adj);
+/// assert_eq!(nb_components, 2);
+/// assert_eq!(labels, vec![0, 1, 0, 1]);
+/// ```
+pub fn connected_components<N, I, IpS, IS, DS>(adj: &CsMatBase<N, I, IpS,
+                                                               IS, DS>)
+                                               -> (usize, Vec<usize>)
+where I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    check_adjacency(adj);
+    let n = adj.rows();
+    let mut parents: Vec<usize> = (0..n).collect();
+    for (outer, vec) in adj.outer_iterator().enumerate() {
+        for (inner, _) in vec.iter() {
+            let outer_root = find_root(&mut parents, outer);
+            let inner_root = find_root(&mut parents, inner);
+            // the smallest node of a component is its root
+            if outer_root < inner_root {
+                parents[inner_root] = outer_root;
+            }
+            else {
+                parents[outer_root] = inner_root;
+            }
+        }
+    }
+    let mut labels = vec![0; n];
+    let mut nb_components = 0;
+    for node in 0..n {
+        let root = find_root(&mut parents, node);
+        if root == node {
+            labels[node] = nb_components;
+            nb_components += 1;
+        }
+        else {
+            labels[node] = labels[root];
+        }
+    }
+    (nb_components, labels)
+}
+
+/// The strongly connected components of the directed graph with adjacency
+/// `adj`, computed with Tarjan's algorithm.
+///
+/// Returns the number of components and the component of each node.
+/// Components are numbered in a topological order: an edge always goes from
+/// a component to a component with a greater or equal number. Permuting the
+/// adjacency matrix symmetrically so that the component numbers increase
+/// thus gives a block upper triangular matrix.
+///
+/// # Panics
+///
+/// If the adjacency matrix is not square.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::graph::strongly_connected_components;
+/// // the cycle 1 -> 2 -> 1, reached from 0
+/// let adj = CsMat::new((3, 3),
+///                      vec![0, 1, 2, 3],
+///                      vec![1, 2, 1],
+///                      vec![1., 1., 1.]);
+/// let (nb_components, labels) = strongly_connected_components(&adj);
+/// assert_eq!(nb_components, 2);
+/// assert_eq!(labels, vec![0, 1, 1]);
+/// ```
+pub fn strongly_connected_components<N, I, IpS, IS, DS>(
+    adj: &CsMatBase<N, I, IpS, IS, DS>
+) -> (usize, Vec<usize>)
+where I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    check_adjacency(adj);
+    let pattern = Pattern::new(adj);
+    let n = pattern.nb_nodes();
+    let unvisited = usize::max_value();
+    let mut index = vec![unvisited; n];
+    let mut lowlink = vec![0; n];
+    let mut on_stack = vec![false; n];
+    let mut stack = Vec::new();
+    let mut labels = vec![0; n];
+    let mut nb_components = 0;
+    let mut counter = 0;
+    // the call stack of the depth first search, holding each node along
+    // with the position of its next successor to explore
+    let mut calls: Vec<(usize, usize)> = Vec::new();
+    for root in 0..n {
+        if index[root] != unvisited {
+            continue;
+        }
+        index[root] = counter;
+        lowlink[root] = counter;
+        counter += 1;
+        stack.push(root);
+        on_stack[root] = true;
+        calls.push((root, 0));
+        while let Some(&(node, next)) = calls.last() {
+            let successors = pattern.successors(node);
+            if next < successors.len() {
+                calls.last_mut().unwrap().1 += 1;
+                let succ = successors[next].index();
+                if index[succ] == unvisited {
+                    index[succ] = counter;
+                    lowlink[succ] = counter;
+                    counter += 1;
+                    stack.push(succ);
+                    on_stack[succ] = true;
+                    calls.push((succ, 0));
+                }
+                else if on_stack[succ] {
+                    lowlink[node] = lowlink[node].min(index[succ]);
+                }
+                continue;
+            }
+            calls.pop();
+            if let Some(&(parent, _)) = calls.last() {
+                lowlink[parent] = lowlink[parent].min(lowlink[node]);
+            }
+            if lowlink[node] == index[node] {
+                loop {
+                    let member = stack.pop().unwrap();
+                    on_stack[member] = false;
+                    labels[member] = nb_components;
+                    if member == node {
+                        break;
+                    }
+                }
+                nb_components += 1;
+            }
+        }
+    }
+    // Tarjan's algorithm finds the components in reverse topological order
+    for label in &mut labels {
+        *label = nb_components - 1 - *label;
+    }
+    (nb_components, labels)
+}
+
+/// The tree built by a traversal of a graph from a source node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchTree {
+    /// The nodes reached from the source, in the order of their visit
+    pub order: Vec<usize>,
+    /// The depth of each node in the tree, `None` for unreached nodes. The
+    /// depths of a breadth first search are the distances to the source.
+    pub levels: Vec<Option<usize>>,
+    /// The parent of each node in the tree, `None` for the source and the
+    /// unreached nodes
+    pub parents: Vec<Option<usize>>,
+}
+
+impl SearchTree {
+    fn new(nb_nodes: usize, source: usize) -> SearchTree {
+        assert!(source < nb_nodes, "source node out of bounds");
+        let mut levels = vec![None; nb_nodes];
+        levels[source] = Some(0);
+        SearchTree {
+            order: vec![source],
+            levels: levels,
+            parents: vec![None; nb_nodes],
+        }
+    }
+
+    fn is_visited(&self, node: usize) -> bool {
+        self.levels[node].is_some()
+    }
+
+    fn visit(&mut self, node: usize, parent: usize) {
+        self.levels[node] = self.levels[parent].map(|level| level + 1);
+        self.parents[node] = Some(parent);
+        self.order.push(node);
+    }
+
+    /// The path from the source to `node` along the tree, or `None` if
+    /// `node` was not reached
+    pub fn path_to(&self, node: usize) -> Option<Vec<usize>> {
+        if !self.is_visited(node) {
+            return None;
+        }
+        Some(tree_path(&self.parents, node))
+    }
+}
+
+/// The path from the root of a tree to `node`, given the parent of each
+/// node
+fn tree_path(parents: &[Option<usize>], node: usize) -> Vec<usize> {
+    let mut path = vec![node];
+    let mut node = node;
+    while let Some(parent) = parents[node] {
+        path.push(parent);
+        node = parent;
+    }
+    path.reverse();
+    path
+}
+
+/// Breadth first search of the graph with adjacency `adj` from the node
+/// `source`, following the edges from rows to columns.
+///
+/// Successors are visited in the order of the indices of a CSR matrix,
+/// whose structure is used directly. A CSC matrix is converted first.
+///
+/// # Panics
+///
+/// - if the adjacency matrix is not square
+/// - if `source` is not a node of the graph
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::graph::bfs;
+/// // the edges 0 -> 1, 0 -> 2, 1 -> 3 and 2 -> 3
+/// let adj = CsMat::new((5, 5),
+///                      vec![0, 2, 3, 4, 4, 4],
+///                      vec![1, 2, 3, 3],
+///                      vec![1., 1., 1., 1.]);
+/// let tree = bfs(&adj, 0);
+/// assert_eq!(tree.order, vec![0, 1, 2, 3]);
+/// assert_eq!(tree.levels, vec![Some(0), Some(1), Some(1), Some(2), None]);
+/// assert_eq!(tree.path_to(3), Some(vec![0, 1, 3]));
+/// ```
+pub fn bfs<N, I, IpS, IS, DS>(adj: &CsMatBase<N, I, IpS, IS, DS>,
+                              source: usize) -> SearchTree
+where I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    check_adjacency(adj);
+    let pattern = Pattern::new(adj);
+    let mut tree = SearchTree::new(pattern.nb_nodes(), source);
+    let mut head = 0;
+    while head < tree.order.len() {
+        let node = tree.order[head];
+        head += 1;
+        for succ in pattern.successors(node) {
+            if !tree.is_visited(succ.index()) {
+                tree.visit(succ.index(), node);
+            }
+        }
+    }
+    tree
+}
+
+/// Depth first search of the graph with adjacency `adj` from the node
+/// `source`, following the edges from rows to columns.
+///
+/// The nodes are ordered by their first visit (preorder), successors being
+/// explored in increasing order. The search is not recursive, so it can
+/// handle arbitrarily deep trees.
+///
+/// # Panics
+///
+/// - if the adjacency matrix is not square
+/// - if `source` is not a node of the graph
+pub fn dfs<N, I, IpS, IS, DS>(adj: &CsMatBase<N, I, IpS, IS, DS>,
+                              source: usize) -> SearchTree
+where I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    check_adjacency(adj);
+    let pattern = Pattern::new(adj);
+    let mut tree = SearchTree::new(pattern.nb_nodes(), source);
+    let mut calls = vec![(source, 0)];
+    while let Some(&(node, next)) = calls.last() {
+        let successors = pattern.successors(node);
+        if next == successors.len() {
+            calls.pop();
+            continue;
+        }
+        calls.last_mut().unwrap().1 += 1;
+        let succ = successors[next].index();
+        if !tree.is_visited(succ) {
+            tree.visit(succ, node);
+            calls.push((succ, 0));
+        }
+    }
+    tree
+}
+
+/// The product `A^T f` of the transposed adjacency matrix with the
+/// frontier `f`, restricted to the unvisited nodes. The product is taken
+/// in the semiring where `a_ij * f_i = i` and where the sum is the minimum,
+/// so that each node of the result holds its smallest predecessor in the
+/// frontier.
+fn masked_spmv<I: SpIndex>(pattern: &Pattern<I>,
+                           frontier: &CsVec<usize>,
+                           levels: &[Option<usize>]) -> CsVec<usize> {
+    let mut products = Vec::new();
+    for (node, _) in frontier.iter() {
+        for succ in pattern.successors(node) {
+            if levels[succ.index()].is_none() {
+                products.push((succ.index(), node));
+            }
+        }
+    }
+    // the sort is stable and the frontier is sorted, so the first product
+    // of each node has the smallest predecessor
+    products.sort_by_key(|&(succ, _)| succ);
+    products.dedup_by_key(|&mut (succ, _)| succ);
+    let (indices, data) = products.into_iter().unzip();
+    CsVec::new(frontier.dim(), indices, data)
+}
+
+/// Breadth first search of the graph with adjacency `adj` from the node
+/// `source`, expressed as sparse matrix - sparse vector products.
+///
+/// Each level of the search is a sparse vector, the frontier, and the next
+/// level is obtained by multiplying it with the adjacency matrix, masked by
+/// the visited nodes. The work per level is proportional to the number of
+/// edges leaving the frontier, and no dense queue is maintained, which
+/// suits large graphs with few levels.
+///
+/// The levels are the same as those of `bfs`, but the nodes of a level are
+/// visited in increasing order, and the parent of a node is its smallest
+/// predecessor in the previous level.
+///
+/// # Panics
+///
+/// - if the adjacency matrix is not square
+/// - if `source` is not a node of the graph
+pub fn bfs_frontier<N, I, IpS, IS, DS>(adj: &CsMatBase<N, I, IpS, IS, DS>,
+                                       source: usize) -> SearchTree
+where I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    check_adjacency(adj);
+    let pattern = Pattern::new(adj);
+    let n = pattern.nb_nodes();
+    let mut tree = SearchTree::new(n, source);
+    let mut frontier = CsVec::new(n, vec![source], vec![source]);
+    while frontier.nnz() > 0 {
+        frontier = masked_spmv(&pattern, &frontier, &tree.levels);
+        for (node, &parent) in frontier.iter() {
+            tree.visit(node, parent);
+        }
+    }
+    tree
+}
+
+/// Shortest paths from a source node to all the nodes of a weighted graph
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShortestPaths<N> {
+    /// The length of the shortest path from the source to each node, or
+    /// infinity for the unreachable nodes
+    pub distances: Vec<N>,
+    /// The predecessor of each node on its shortest path, `None` for the
+    /// source and the unreachable nodes
+    pub predecessors: Vec<Option<usize>>,
+}
+
+impl<N: Float> ShortestPaths<N> {
+    fn new(nb_nodes: usize, source: usize) -> ShortestPaths<N> {
+        assert!(source < nb_nodes, "source node out of bounds");
+        let mut distances = vec![N::infinity(); nb_nodes];
+        distances[source] = N::zero();
+        ShortestPaths {
+            distances: distances,
+            predecessors: vec![None; nb_nodes],
+        }
+    }
+
+    /// The shortest path from the source to `node`, or `None` if `node`
+    /// is not reachable
+    pub fn path_to(&self, node: usize) -> Option<Vec<usize>> {
+        if self.distances[node] == N::infinity() {
+            return None;
+        }
+        Some(tree_path(&self.predecessors, node))
+    }
+}
+
+/// An entry of the priority queue of Dijkstra's algorithm, ordered by
+/// decreasing distance so that `BinaryHeap` pops the closest node first
+#[derive(PartialEq)]
+struct QueueEntry<N: PartialOrd>(N, usize);
+
+impl<N: PartialOrd> Eq for QueueEntry<N> {}
+
+impl<N: PartialOrd> PartialOrd for QueueEntry<N> {
+    fn partial_cmp(&self, other: &QueueEntry<N>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<N: PartialOrd> Ord for QueueEntry<N> {
+    fn cmp(&self, other: &QueueEntry<N>) -> Ordering {
+        other.0.partial_cmp(&self.0).unwrap_or(Ordering::Equal)
+                                    .then_with(|| other.1.cmp(&self.1))
+    }
+}
+
+/// Shortest paths from `source` in the graph with adjacency `adj`, whose
+/// entry `a_ij` is the length of the edge from `i` to `j`, using
+/// Dijkstra's algorithm.
+///
+/// The complexity is `O(E log V)` for a graph with `E` edges and `V`
+/// nodes. Negative lengths are not supported, see `bellman_ford`.
+///
+/// # Panics
+///
+/// - if the adjacency matrix is not square
+/// - if `source` is not a node of the graph
+/// - if an edge reachable from `source` has a negative length
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::graph::dijkstra;
+/// // the edges 0 -> 1 (4.), 0 -> 2 (1.) and 2 -> 1 (2.)
+/// let adj = CsMat::new((3, 3),
+///                      vec![0, 2, 2, 3],
+///                      vec![1, 2, 1],
+///                      vec![4., 1., 2.]);
+/// let paths = dijkstra(&adj, 0);
+/// assert_eq!(paths.distances, vec![0., 3., 1.]);
+/// assert_eq!(paths.path_to(1), Some(vec![0, 2, 1]));
+/// ```
+pub fn dijkstra<N, I, IpS, IS, DS>(adj: &CsMatBase<N, I, IpS, IS, DS>,
+                                   source: usize) -> ShortestPaths<N>
+where N: Float + Default,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    check_adjacency(adj);
+    let adj = adj.to_csr();
+    let mut paths = ShortestPaths::new(adj.rows(), source);
+    let mut done = vec![false; adj.rows()];
+    let mut queue = BinaryHeap::new();
+    queue.push(QueueEntry(N::zero(), source));
+    while let Some(QueueEntry(dist, node)) = queue.pop() {
+        if done[node] {
+            continue;
+        }
+        done[node] = true;
+        for (succ, &length) in adj.outer_view(node).unwrap().iter() {
+            assert!(length >= N::zero(), "negative edge length");
+            let succ_dist = dist + length;
+            if succ_dist < paths.distances[succ] {
+                paths.distances[succ] = succ_dist;
+                paths.predecessors[succ] = Some(node);
+                queue.push(QueueEntry(succ_dist, succ));
+            }
+        }
+    }
+    paths
+}
+
+/// Shortest paths from `source` in the graph with adjacency `adj`, whose
+/// entry `a_ij` is the length of the edge from `i` to `j`, using the
+/// Bellman-Ford algorithm.
+///
+/// Contrary to `dijkstra`, negative lengths are allowed, at the price of a
+/// complexity of `O(E V)` for a graph with `E` edges and `V` nodes.
+///
+/// # Errors
+///
+/// `NegativeCycle` if a cycle of negative length is reachable from
+/// `source`, in which case shortest paths are not defined.
+///
+/// # Panics
+///
+/// - if the adjacency matrix is not square
+/// - if `source` is not a node of the graph
+pub fn bellman_ford<N, I, IpS, IS, DS>(adj: &CsMatBase<N, I, IpS, IS, DS>,
+                                       source: usize)
+                                       -> Result<ShortestPaths<N>, SprsError>
+where N: Float + Default,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    check_adjacency(adj);
+    let adj = adj.to_csr();
+    let n = adj.rows();
+    let mut paths = ShortestPaths::new(n, source);
+    // without negative cycles, shortest paths have at most n - 1 edges, so
+    // a relaxation still improving a distance at round n reveals a cycle
+    for _ in 0..n {
+        let mut improved = false;
+        for (node, vec) in adj.outer_iterator().enumerate() {
+            let dist = paths.distances[node];
+            if dist == N::infinity() {
+                continue;
+            }
+            for (succ, &length) in vec.iter() {
+                if dist + length < paths.distances[succ] {
+                    paths.distances[succ] = dist + length;
+                    paths.predecessors[succ] = Some(node);
+                    improved = true;
+                }
+            }
+        }
+        if !improved {
+            return Ok(paths);
+        }
+    }
+    Err(SprsError::NegativeCycle)
+}
+
+/// The lengths of the shortest paths between all pairs of nodes of the
+/// graph with adjacency `adj`, whose entry `a_ij` is the length of the
+/// edge from `i` to `j`.
+///
+/// The distances are computed by repeatedly squaring the adjacency matrix
+/// with the min-plus product (see `prod::csr_mul_csr_semiring`), which
+/// takes `log2(V)` sparse products for a graph with `V` nodes. The entry
+/// `(i, j)` of the result is stored if and only if `j` is reachable from
+/// `i`, and the diagonal is always stored. This is suited to graphs whose
+/// connected components are small, as the result can be much denser than
+/// the adjacency matrix.
+///
+/// # Errors
+///
+/// `NegativeCycle` if the graph contains a cycle of negative length.
+///
+/// # Panics
+///
+/// If the adjacency matrix is not square.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::graph::all_pairs_shortest_paths;
+/// // the path 0 -> 1 -> 2, with lengths 1. and 2.
+/// let adj = CsMat::new((3, 3),
+///                      vec![0, 1, 2, 2],
+///                      vec![1, 2],
+///                      vec![1., 2.]);
+/// let distances = all_pairs_shortest_paths(&adj).unwrap();
+/// assert_eq!(distances.get(0, 2), Some(&3.));
+/// assert_eq!(distances.get(2, 0), None);
+/// assert_eq!(distances.get(1, 1), Some(&0.));
+/// ```
+pub fn all_pairs_shortest_paths<N, I, IpS, IS, DS>(adj: &CsMatBase<N, I, IpS,
+                                                                   IS, DS>)
+                                                   -> Result<CsMatI<N, I>,
+                                                             SprsError>
+where N: Float + Default,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    check_adjacency(adj);
+    let n = adj.rows();
+    // paths of at most one edge, including the empty paths on the diagonal
+    let mut dist = TriMatI::with_capacity((n, n), adj.nnz() + n);
+    let mut diag = vec![N::zero(); n];
+    for (row, vec) in adj.to_csr().outer_iterator().enumerate() {
+        for (col, &length) in vec.iter() {
+            if row == col {
+                diag[row] = diag[row].min(length);
+            }
+            else {
+                dist.add_triplet(row, col, length);
+            }
+        }
+    }
+    for (node, &length) in diag.iter().enumerate() {
+        dist.add_triplet(node, node, length);
+    }
+    let mut dist: CsMatI<N, I> = dist.to_csr();
+    loop {
+        if diag.iter().any(|&length| length < N::zero()) {
+            return Err(SprsError::NegativeCycle);
+        }
+        let squared = prod::csr_mul_csr_semiring(dist.view(), dist.view(),
+                                                 |x: N, y| x.min(y),
+                                                 |x, y| x + y);
+        if squared == dist {
+            return Ok(dist);
+        }
+        for (node, length) in diag.iter_mut().enumerate() {
+            *length = *squared.get(node, node).unwrap();
+        }
+        dist = squared;
+    }
+}
+
+/// The maximum number of iterations of `pagerank`
+const PAGERANK_MAX_ITER: usize = 1000;
+
+/// The PageRank of the nodes of the graph with adjacency `adj`, whose entry
+/// `a_ij` is the nonnegative weight of the edge from `i` to `j`.
+///
+/// The PageRank is the stationary distribution of a random walker who
+/// follows an edge, chosen with a probability proportional to its weight,
+/// with probability `damping`, and otherwise jumps to a node chosen
+/// uniformly. Walkers on nodes without outgoing edges always jump. The
+/// ranks sum to one, and are computed by the power iteration, which stops
+/// when the ranks change by at most `tol` in the 1-norm. Each iteration
+/// is one sparse matrix - vector product.
+///
+/// # Errors
+///
+/// `NotConverged` if the ranks did not converge in 1000 iterations. The
+/// error is reduced by a factor `damping` at each iteration, so this only
+/// happens for a damping factor very close to one.
+///
+/// # Panics
+///
+/// - if the adjacency matrix is not square
+/// - unless `0 <= damping < 1`
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::graph::pagerank;
+/// // the nodes 1 and 2 point to the node 0, which points to the node 1
+/// let adj = CsMat::new((3, 3),
+///                      vec![0, 1, 2, 3],
+///                      vec![1, 0, 0],
+///                      vec![1., 1., 1.]);
+/// let ranks = pagerank(&adj, 0.85, 1e-12).unwrap();
+/// assert!((ranks.iter().sum::<f64>() - 1.).abs() < 1e-12);
+/// assert!(ranks[0] > ranks[1] && ranks[1] > ranks[2]);
+/// ```
+pub fn pagerank<N, I, IpS, IS, DS>(adj: &CsMatBase<N, I, IpS, IS, DS>,
+                                   damping: N,
+                                   tol: N) -> SpRes<Vec<N>>
+where N: Float,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    check_adjacency(adj);
+    assert!(damping >= N::zero() && damping < N::one(),
+            "damping factor out of [0, 1)");
+    let n = adj.rows();
+    if n == 0 {
+        return Ok(Vec::new());
+    }
+    let mut out_weights = vec![N::zero(); n];
+    let is_csr = adj.is_csr();
+    for (outer, vec) in adj.outer_iterator().enumerate() {
+        for (inner, &weight) in vec.iter() {
+            let row = if is_csr { outer } else { inner };
+            out_weights[row] = out_weights[row] + weight;
+        }
+    }
+    let nb_nodes = N::from(n).unwrap();
+    let transposed = adj.transpose_view();
+    let mut ranks = vec![N::one() / nb_nodes; n];
+    let mut walkers = vec![N::zero(); n];
+    let mut next = vec![N::zero(); n];
+    for _ in 0..PAGERANK_MAX_ITER {
+        // the share of rank following each edge, and the rank of the nodes
+        // without outgoing edges, which is spread uniformly
+        let mut dangling = N::zero();
+        for ((walker, &rank), &weight) in walkers.iter_mut()
+                                                 .zip(&ranks)
+                                                 .zip(&out_weights) {
+            if weight.is_zero() {
+                dangling = dangling + rank;
+                *walker = N::zero();
+            }
+            else {
+                *walker = damping * rank / weight;
+            }
+        }
+        let jump = (N::one() - damping + damping * dangling) / nb_nodes;
+        for val in &mut next {
+            *val = jump;
+        }
+        if transposed.is_csr() {
+            prod::mul_acc_mat_vec_csr(transposed.view(), &walkers, &mut next);
+        }
+        else {
+            prod::mul_acc_mat_vec_csc(transposed.view(), &walkers, &mut next);
+        }
+        let change = ranks.iter().zip(&next).fold(N::zero(), |acc, (&r, &s)| {
+            acc + (r - s).abs()
+        });
+        ::std::mem::swap(&mut ranks, &mut next);
+        if change <= tol {
+            return Ok(ranks);
+        }
+    }
+    Err(SprsError::NotConverged)
+}
+
+/// The pattern of the undirected graph underlying the adjacency `adj`, as a
+/// CSR matrix with unit values: nodes `i` and `j` are neighbors if `a_ij`
+/// or `a_ji` is a non-zero. Self loops are dropped, and the edges `(i, j)`
+/// with `i < j` are dropped if `lower` is true.
+fn undirected_pattern<N, I, IpS, IS, DS>(adj: &CsMatBase<N, I, IpS, IS, DS>,
+                                         lower: bool) -> CsMat<usize>
+where I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    check_adjacency(adj);
+    let n = adj.rows();
+    let mut edges = Vec::with_capacity(2 * adj.nnz());
+    for (outer, vec) in adj.outer_iterator().enumerate() {
+        for (inner, _) in vec.iter() {
+            if inner != outer {
+                edges.push((outer.max(inner), outer.min(inner)));
+                if !lower {
+                    edges.push((outer.min(inner), outer.max(inner)));
+                }
+            }
+        }
+    }
+    edges.sort();
+    edges.dedup();
+    let mut indptr = vec![0; n + 1];
+    for &(row, _) in &edges {
+        indptr[row + 1] += 1;
+    }
+    for row in 0..n {
+        indptr[row + 1] += indptr[row];
+    }
+    let nnz = edges.len();
+    let indices = edges.into_iter().map(|(_, col)| col).collect();
+    CsMat::new((n, n), indptr, indices, vec![1; nnz])
+}
+
+/// The number of triangles of the undirected graph underlying the
+/// adjacency `adj`, where nodes `i` and `j` are neighbors if `a_ij` or
+/// `a_ji` is a non-zero. Self loops are ignored.
+///
+/// With `L` the strictly lower triangular part of the adjacency pattern,
+/// each triangle `i > k > j` is counted once by the entry `(i, j)` of the
+/// masked product `L L .* L` (see `prod::csr_mul_csr_masked`), which only
+/// computes the entries of `L L` at the edges of the graph.
+///
+/// # Panics
+///
+/// If the adjacency matrix is not square.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::graph::count_triangles;
+/// // the triangle 0 -> 1 -> 2 -> 0, and the edge 2 -> 3
+/// let adj = CsMat::new((4, 4),
+///                      vec![0, 1, 2, 4, 4],
+///                      vec![1, 2, 0, 3],
+///                      vec![1., 1., 1., 1.]);
+/// assert_eq!(count_triangles(&adj), 1);
+/// ```
+pub fn count_triangles<N, I, IpS, IS, DS>(adj: &CsMatBase<N, I, IpS, IS, DS>)
+                                          -> usize
+where I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    let lower = undirected_pattern(adj, true);
+    let wedges = prod::csr_mul_csr_masked(lower.view(), lower.view(),
+                                          lower.view());
+    wedges.data().iter().sum()
+}
+
+/// The local clustering coefficient of each node of the undirected graph
+/// underlying the adjacency `adj`, where nodes `i` and `j` are neighbors if
+/// `a_ij` or `a_ji` is a non-zero. Self loops are ignored.
+///
+/// The clustering coefficient of a node with `d` neighbors belonging to
+/// `t` triangles is `2 t / (d (d - 1))`, ie the fraction of the pairs of
+/// its neighbors that are neighbors themselves. It is zero for nodes with
+/// less than two neighbors. The triangles of each node are counted with
+/// the masked product `A A .* A` (see `prod::csr_mul_csr_masked`).
+///
+/// # Panics
+///
+/// If the adjacency matrix is not square.
+pub fn clustering_coefficients<N, I, IpS, IS, DS>(adj: &CsMatBase<N, I, IpS,
+                                                                  IS, DS>)
+                                                  -> Vec<f64>
+where I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    let pattern = undirected_pattern(adj, false);
+    // the entry (i, j) counts the common neighbors of the neighbors i and j
+    let common = prod::csr_mul_csr_masked(pattern.view(), pattern.view(),
+                                          pattern.view());
+    pattern.outer_iterator().zip(common.outer_iterator()).map(|(nbrs, vec)| {
+        let degree = nbrs.nnz() as f64;
+        let triangles = vec.data().iter().sum::<usize>() as f64 / 2.;
+        if degree < 2. {
+            0.
+        }
+        else {
+            2. * triangles / (degree * (degree - 1.))
+        }
+    }).collect()
+}
+
+/// A matching between the rows and the columns of a matrix, ie a set of
+/// non-zero positions sharing no row and no column
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BipartiteMatching {
+    /// The column matched to each row
+    pub row_matches: Vec<Option<usize>>,
+    /// The row matched to each column
+    pub col_matches: Vec<Option<usize>>,
+}
+
+impl BipartiteMatching {
+    /// The number of matched pairs
+    pub fn size(&self) -> usize {
+        self.row_matches.iter().filter(|m| m.is_some()).count()
+    }
+}
+
+/// A maximum matching of the bipartite graph whose two sets of nodes are
+/// the rows and the columns of `mat`, a row and a column being linked if
+/// there is a non-zero at their intersection. The values of `mat` are
+/// ignored.
+///
+/// The matching is computed by the Hopcroft-Karp algorithm, which augments
+/// the matching along a maximal set of shortest disjoint augmenting paths
+/// at each phase, in `O(E sqrt(V))` operations for a matrix with `E`
+/// non-zeros and `V` rows and columns. The size of a maximum matching is
+/// the structural rank of the matrix.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::graph::max_bipartite_matching;
+/// // the rows 0 and 1 can only be matched to the column 0
+/// let mat = CsMat::new((3, 3),
+///                      vec![0, 1, 2, 4],
+///                      vec![0, 0, 0, 2],
+///                      vec![1., 1., 1., 1.]);
+/// let matching = max_bipartite_matching(&mat);
+/// assert_eq!(matching.size(), 2);
+/// assert_eq!(matching.row_matches[2], Some(2));
+/// ```
+pub fn max_bipartite_matching<N, I, IpS, IS, DS>(mat: &CsMatBase<N, I, IpS,
+                                                                 IS, DS>)
+                                                 -> BipartiteMatching
+where I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    let pattern = Pattern::new(mat);
+    let (nb_rows, nb_cols) = mat.shape();
+    let mut row_matches = vec![None; nb_rows];
+    let mut col_matches: Vec<Option<usize>> = vec![None; nb_cols];
+    let unreached = usize::max_value();
+    let mut dist = vec![unreached; nb_rows];
+    let mut next = vec![0; nb_rows];
+    let mut queue = Vec::with_capacity(nb_rows);
+    loop {
+        // layer the rows by their distance to a free row along alternating
+        // paths, stopping at the layer where a free column is found
+        queue.clear();
+        for row in 0..nb_rows {
+            if row_matches[row].is_none() {
+                dist[row] = 0;
+                queue.push(row);
+            }
+            else {
+                dist[row] = unreached;
+            }
+        }
+        let mut free_dist = unreached;
+        let mut head = 0;
+        while head < queue.len() {
+            let row = queue[head];
+            head += 1;
+            if dist[row] >= free_dist {
+                break;
+            }
+            for col in pattern.successors(row) {
+                match col_matches[col.index()] {
+                    None => free_dist = dist[row] + 1,
+                    Some(matched) if dist[matched] == unreached => {
+                        dist[matched] = dist[row] + 1;
+                        queue.push(matched);
+                    }
+                    Some(_) => (),
+                }
+            }
+        }
+        if free_dist == unreached {
+            break;
+        }
+        // augment along disjoint shortest paths found by depth first
+        // searches in the layered graph
+        for elt in &mut next {
+            *elt = 0;
+        }
+        let mut path_rows = Vec::new();
+        let mut path_cols = Vec::new();
+        for start in 0..nb_rows {
+            if row_matches[start].is_some() {
+                continue;
+            }
+            path_rows.clear();
+            path_cols.clear();
+            path_rows.push(start);
+            while let Some(&row) = path_rows.last() {
+                let successors = pattern.successors(row);
+                if next[row] == successors.len() {
+                    // a dead end, never explored again in this phase
+                    dist[row] = unreached;
+                    path_rows.pop();
+                    path_cols.pop();
+                    continue;
+                }
+                let col = successors[next[row]].index();
+                next[row] += 1;
+                match col_matches[col] {
+                    None if dist[row] + 1 == free_dist => {
+                        path_cols.push(col);
+                        for (&row, &col) in path_rows.iter().zip(&path_cols) {
+                            row_matches[row] = Some(col);
+                            col_matches[col] = Some(row);
+                        }
+                        break;
+                    }
+                    Some(matched) if dist[matched] == dist[row] + 1 => {
+                        path_cols.push(col);
+                        path_rows.push(matched);
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+    BipartiteMatching {
+        row_matches: row_matches,
+        col_matches: col_matches,
+    }
+}
+
+/// An assignment of each row of a cost matrix to a distinct column
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assignment<N> {
+    /// The column assigned to each row
+    pub cols: Vec<usize>,
+    /// The total cost of the assignment
+    pub cost: N,
+}
+
+/// Assign each row of the sparse cost matrix `costs` to a distinct column,
+/// minimizing the sum of the costs of the assigned entries. Only the
+/// non-zeros of `costs` can be assigned, explicit zeros being free
+/// assignments, so that the problem is a minimum weight full matching of
+/// the bipartite graph of the rows and the columns.
+///
+/// The assignment is computed with the Hungarian method, in its shortest
+/// augmenting path form: each row is assigned in turn by a Dijkstra search
+/// over the sparse structure, with the reduced costs given by dual
+/// potentials. The complexity is `O(V E log V)` for a matrix with `E`
+/// non-zeros and `V` rows and columns. Negative costs are supported.
+///
+/// # Errors
+///
+/// `SingularMatrix` if there are more rows than columns, or if the matrix
+/// is structurally singular, ie if no assignment of all the rows exists.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::graph::min_cost_assignment;
+/// let costs = CsMat::new((3, 3),
+///                        vec![0, 2, 5, 7],
+///                        vec![0, 1, 0, 1, 2, 0, 2],
+///                        vec![4., 1., 2., 0., 5., 3., 2.]);
+/// let assignment = min_cost_assignment(&costs).unwrap();
+/// assert_eq!(assignment.cols, vec![1, 0, 2]);
+/// assert_eq!(assignment.cost, 5.);
+/// ```
+pub fn min_cost_assignment<N, I, IpS, IS, DS>(costs: &CsMatBase<N, I, IpS,
+                                                                IS, DS>)
+                                              -> SpRes<Assignment<N>>
+where N: Float + Default,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    let (nb_rows, nb_cols) = costs.shape();
+    if nb_rows > nb_cols {
+        return Err(SprsError::SingularMatrix);
+    }
+    let costs = costs.to_csr();
+    // dual potentials keeping the reduced costs c_ij - u_i - v_j
+    // nonnegative, and zero on the assigned entries
+    let mut row_potentials = Vec::with_capacity(nb_rows);
+    for vec in costs.outer_iterator() {
+        if vec.nnz() == 0 {
+            return Err(SprsError::SingularMatrix);
+        }
+        let min = vec.data().iter().fold(N::infinity(), |acc, &c| acc.min(c));
+        row_potentials.push(min);
+    }
+    let mut col_potentials = vec![N::zero(); nb_cols];
+    let mut row_matches: Vec<Option<usize>> = vec![None; nb_rows];
+    let mut col_matches: Vec<Option<usize>> = vec![None; nb_cols];
+    let mut dist = vec![N::infinity(); nb_cols];
+    let mut pred = vec![0; nb_cols];
+    let mut done = vec![false; nb_cols];
+    let mut done_cols = Vec::new();
+    let mut reached_cols = Vec::new();
+    for start in 0..nb_rows {
+        // Dijkstra search of the closest free column from the start row,
+        // the path continuing from a matched column through its row
+        let mut queue = BinaryHeap::new();
+        let mut row = start;
+        let mut row_dist = N::zero();
+        let free_col;
+        loop {
+            for (col, &cost) in costs.outer_view(row).unwrap().iter() {
+                if done[col] {
+                    continue;
+                }
+                let reduced = cost - row_potentials[row] - col_potentials[col];
+                let col_dist = row_dist + reduced.max(N::zero());
+                if col_dist < dist[col] {
+                    if dist[col] == N::infinity() {
+                        reached_cols.push(col);
+                    }
+                    dist[col] = col_dist;
+                    pred[col] = row;
+                    queue.push(QueueEntry(col_dist, col));
+                }
+            }
+            let col = loop {
+                match queue.pop() {
+                    Some(QueueEntry(_, col)) if done[col] => continue,
+                    Some(QueueEntry(_, col)) => break Some(col),
+                    None => break None,
+                }
+            };
+            let col = match col {
+                Some(col) => col,
+                None => return Err(SprsError::SingularMatrix),
+            };
+            done[col] = true;
+            done_cols.push(col);
+            match col_matches[col] {
+                Some(matched) => {
+                    row = matched;
+                    row_dist = dist[col];
+                }
+                None => {
+                    free_col = col;
+                    break;
+                }
+            }
+        }
+        // update the potentials of the rows and columns closer than the
+        // free column, which keeps the reduced costs nonnegative
+        let shortest = dist[free_col];
+        row_potentials[start] = row_potentials[start] + shortest;
+        for &col in &done_cols {
+            let gain = shortest - dist[col];
+            col_potentials[col] = col_potentials[col] - gain;
+            if let Some(matched) = col_matches[col] {
+                row_potentials[matched] = row_potentials[matched] + gain;
+            }
+        }
+        // augment along the path to the free column
+        let mut col = free_col;
+        loop {
+            let row = pred[col];
+            let prev_col = row_matches[row];
+            row_matches[row] = Some(col);
+            col_matches[col] = Some(row);
+            match prev_col {
+                Some(prev_col) if row != start => col = prev_col,
+                _ => break,
+            }
+        }
+        for &col in &reached_cols {
+            dist[col] = N::infinity();
+            done[col] = false;
+        }
+        reached_cols.clear();
+        done_cols.clear();
+    }
+    let cols: Vec<usize> = row_matches.into_iter().map(|m| m.unwrap())
+                                                  .collect();
+    let cost = cols.iter().enumerate().fold(N::zero(), |acc, (row, &col)| {
+        acc + *costs.get(row, col).unwrap()
+    });
+    Ok(Assignment {
+        cols: cols,
+        cost: cost,
+    })
+}
+
+/// A minimum spanning tree of the undirected graph with adjacency `adj`,
+/// whose entry `a_ij` is the weight of the edge between `i` and `j`.
+///
+/// The direction of the edges is ignored: if both `a_ij` and `a_ji` are
+/// stored, the edge has the smallest of both weights. For a disconnected
+/// graph, the result is a minimum spanning forest, with a tree for each
+/// connected component. As in `scipy.sparse.csgraph`, the result is the
+/// `n x n` CSR matrix holding the weights of the edges of the tree, each
+/// edge being stored once at its position in `adj`. Explicitly stored
+/// zeros are edges of zero weight, and self loops are ignored.
+///
+/// The tree is computed with Kruskal's algorithm, in `O(E log E)`
+/// operations for a graph with `E` edges.
+///
+/// # Panics
+///
+/// If the adjacency matrix is not square.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::graph::minimum_spanning_tree;
+/// // the triangle 0 -- 1 -- 2 -- 0, whose heaviest edge is 0 -- 2
+/// let adj = CsMat::new((3, 3),
+///                      vec![0, 2, 3, 3],
+///                      vec![1, 2, 2],
+///                      vec![1., 3., 2.]);
+/// let tree = minimum_spanning_tree(&adj);
+/// let expected = CsMat::new((3, 3),
+///                           vec![0, 1, 2, 2],
+///                           vec![1, 2],
+///                           vec![1., 2.]);
+/// assert_eq!(tree, expected);
+/// ```
+pub fn minimum_spanning_tree<N, I, IpS, IS, DS>(adj: &CsMatBase<N, I, IpS,
+                                                                IS, DS>)
+                                                -> CsMatI<N, I>
+where N: Float + Default,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    check_adjacency(adj);
+    let n = adj.rows();
+    let is_csr = adj.is_csr();
+    let mut edges = Vec::with_capacity(adj.nnz());
+    for (outer, vec) in adj.outer_iterator().enumerate() {
+        for (inner, &weight) in vec.iter() {
+            let (row, col) = if is_csr { (outer, inner) }
+                             else { (inner, outer) };
+            if row != col {
+                edges.push((weight, row, col));
+            }
+        }
+    }
+    // the sort is stable, so ties are broken by the storage order
+    edges.sort_by(|&(w1, _, _), &(w2, _, _)| {
+        w1.partial_cmp(&w2).unwrap_or(Ordering::Equal)
+    });
+    let mut parents: Vec<usize> = (0..n).collect();
+    let mut tree = TriMatI::with_capacity((n, n), n.saturating_sub(1));
+    for (weight, row, col) in edges {
+        let row_root = find_root(&mut parents, row);
+        let col_root = find_root(&mut parents, col);
+        if row_root != col_root {
+            parents[row_root] = col_root;
+            tree.add_triplet(row, col, weight);
+        }
+    }
+    tree.to_csr()
+}
+
+#[cfg(test)]
+mod test {
+    use sparse::{CsMat, TriMat};
+    use super::{signed_laplacian, bipartite_laplacian, Normalization,
+                laplacian, normalized_laplacian, random_walk_laplacian,
+                connected_components, strongly_connected_components,
+                bfs, dfs, bfs_frontier, dijkstra, bellman_ford,
+                all_pairs_shortest_paths, pagerank, count_triangles,
+                clustering_coefficients, max_bipartite_matching,
+                min_cost_assignment, minimum_spanning_tree};
+    use errors::SprsError;
+
+    fn signed_triangle() -> CsMat<f64> {
+        // 0 -- 1 friends, 1 -- 2 foes, 0 -- 2 foes: a balanced triangle
+        let mut tri = TriMat::new((3, 3));
+        for &(i, j, w) in &[(0, 1, 2.), (1, 2, -1.), (0, 2, -3.)] {
+            tri.add_triplet(i, j, w);
+            tri.add_triplet(j, i, w);
+        }
+        tri.to_csr()
+    }
+
+    #[test]
+    fn signed_laplacian_unnormalized() {
+        let lap = signed_laplacian(&signed_triangle(),
+                                   Normalization::Unnormalized);
+        let expected = CsMat::new((3, 3),
+                                  vec![0, 3, 6, 9],
+                                  vec![0, 1, 2, 0, 1, 2, 0, 1, 2],
+                                  vec![5., -2., 3.,
+                                       -2., 3., 1.,
+                                       3., 1., 4.]);
+        assert_eq!(lap, expected);
+        // balanced graph: the signed indicator vector is in the kernel
+        let x = [1., 1., -1.];
+        let y = &lap * &::ndarray::arr1(&x);
+        assert!(y.iter().all(|v| v.abs() < 1e-12));
+    }
+
+    #[test]
+    fn signed_laplacian_matches_plain_for_positive_weights() {
+        let mut tri = TriMat::new((3, 3));
+        tri.add_triplet(0, 1, 1.);
+        tri.add_triplet(1, 0, 1.);
+        tri.add_triplet(1, 2, 2.);
+        tri.add_triplet(2, 1, 2.);
+        tri.add_triplet(2, 2, 7.); // self loops are ignored
+        let adj: CsMat<f64> = tri.to_csr();
+        let lap = signed_laplacian(&adj, Normalization::Unnormalized);
+        for row in lap.outer_iterator() {
+            let sum: f64 = row.data().iter().sum();
+            assert_eq!(sum, 0.);
+        }
+    }
+
+    #[test]
+    fn normalizations() {
+        let adj = signed_triangle();
+        let sym = signed_laplacian(&adj, Normalization::Symmetric);
+        let rw = signed_laplacian(&adj, Normalization::RandomWalk);
+        let degrees = [5., 3., 4.];
+        for i in 0..3 {
+            assert!((sym.get(i, i).unwrap() - 1.).abs() < 1e-12);
+            assert!((rw.get(i, i).unwrap() - 1.).abs() < 1e-12);
+        }
+        let expected_sym = -2. / (degrees[0] * degrees[1] as f64).sqrt();
+        assert!((sym.get(0, 1).unwrap() - expected_sym).abs() < 1e-12);
+        assert!((sym.get(1, 0).unwrap() - expected_sym).abs() < 1e-12);
+        assert!((rw.get(0, 1).unwrap() + 2. / 5.).abs() < 1e-12);
+        assert!((rw.get(1, 0).unwrap() + 2. / 3.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn bipartite_isolated_nodes() {
+        // the second item is not connected
+        let biadj: CsMat<f64> = CsMat::new((2, 3),
+                                           vec![0, 1, 3],
+                                           vec![0, 0, 2],
+                                           vec![2., 1., 1.]);
+        let lap = bipartite_laplacian(&biadj, Normalization::Symmetric);
+        assert_eq!(lap.shape(), (5, 5));
+        assert_eq!(lap.outer_view(3).unwrap().nnz(), 0);
+        assert!((lap.get(0, 0).unwrap() - 1.).abs() < 1e-12);
+        // node 0 has degree 2, item 0 (node 2) has degree 3
+        let expected = -2. / 6f64.sqrt();
+        assert!((lap.get(0, 2).unwrap() - expected).abs() < 1e-12);
+        assert!((lap.get(2, 0).unwrap() - expected).abs() < 1e-12);
+
+        let lap = bipartite_laplacian(&biadj, Normalization::Unnormalized);
+        for row in lap.outer_iterator() {
+            let sum: f64 = row.data().iter().sum();
+            assert_eq!(sum, 0.);
+        }
+    }
+
+    #[test]
+    fn components() {
+        // two weakly connected components {0, 2, 4} and {1, 3}, with the
+        // cycle 0 -> 2 -> 4 -> 0 and the edge 3 -> 1
+        let mut tri = TriMat::new((6, 6));
+        for &(i, j) in &[(0, 2), (2, 4), (4, 0), (3, 1), (4, 4)] {
+            tri.add_triplet(i, j, 1.);
+        }
+        let adj: CsMat<f64> = tri.to_csr();
+        let (nb_components, labels) = connected_components(&adj);
+        assert_eq!(nb_components, 3);
+        assert_eq!(labels, vec![0, 1, 0, 1, 0, 2]);
+        assert_eq!(connected_components(&adj.to_csc()).1, labels);
+
+        let (nb_components, labels) = strongly_connected_components(&adj);
+        assert_eq!(nb_components, 4);
+        assert_eq!(labels[0], labels[2]);
+        assert_eq!(labels[0], labels[4]);
+        // 3 comes before 1 in a topological order
+        assert!(labels[3] < labels[1]);
+        let mut distinct = vec![labels[0], labels[1], labels[3], labels[5]];
+        distinct.sort();
+        assert_eq!(distinct, vec![0, 1, 2, 3]);
+        assert_eq!(strongly_connected_components(&adj.to_csc()).1, labels);
+    }
+
+    #[test]
+    fn strong_components_topological_order() {
+        // a chain of cycles 0 <-> 5 -> 3 <-> 1 -> 4 -> 2 <-> 4
+        let mut tri = TriMat::new((6, 6));
+        for &(i, j) in &[(0, 5), (5, 0), (5, 3), (3, 1), (1, 3), (1, 4),
+                         (4, 2), (2, 4)] {
+            tri.add_triplet(i, j, 1.);
+        }
+        let adj: CsMat<f64> = tri.to_csr();
+        let (nb_components, labels) = strongly_connected_components(&adj);
+        assert_eq!(nb_components, 3);
+        assert_eq!(labels, vec![0, 1, 2, 1, 2, 0]);
+        for (row, vec) in adj.outer_iterator().enumerate() {
+            for (col, _) in vec.iter() {
+                assert!(labels[row] <= labels[col]);
+            }
+        }
+    }
+
+    #[test]
+    fn plain_laplacians() {
+        // the weighted path 0 -- 1 -- 2 and an isolated node 3
+        let mut tri = TriMat::new((4, 4));
+        for &(i, j, w) in &[(0, 1, 1.), (1, 2, 2.)] {
+            tri.add_triplet(i, j, w);
+            tri.add_triplet(j, i, w);
+        }
+        tri.add_triplet(2, 2, 5.); // self loops are ignored
+        let adj: CsMat<f64> = tri.to_csr();
+        let lap = laplacian(&adj);
+        let expected = CsMat::new((4, 4),
+                                  vec![0, 2, 5, 7, 7],
+                                  vec![0, 1, 0, 1, 2, 1, 2],
+                                  vec![1., -1., -1., 3., -2., -2., 2.]);
+        assert_eq!(lap, expected);
+        assert_eq!(laplacian(&adj.to_csc()), expected);
+
+        let sym = normalized_laplacian(&adj);
+        let degrees = [1., 3., 2.];
+        for (row, vec) in expected.outer_iterator().enumerate() {
+            for (col, &val) in vec.iter() {
+                let scaled = val / (degrees[row] * degrees[col] as f64).sqrt();
+                assert!((sym.get(row, col).unwrap() - scaled).abs() < 1e-12);
+            }
+        }
+        assert_eq!(sym.nnz(), expected.nnz());
+
+        let rw = random_walk_laplacian(&adj);
+        assert!((rw.get(1, 2).unwrap() + 2. / 3.).abs() < 1e-12);
+        assert!((rw.get(2, 1).unwrap() + 1.).abs() < 1e-12);
+
+        // a directed cycle with different weights
+        let directed = CsMat::new((3, 3),
+                                  vec![0, 2, 3, 4],
+                                  vec![1, 2, 2, 0],
+                                  vec![1., 3., 2., 1.]);
+        for lap in &[laplacian(&directed), random_walk_laplacian(&directed)] {
+            for row in lap.outer_iterator() {
+                let sum: f64 = row.data().iter().sum();
+                assert!(sum.abs() < 1e-12);
+            }
+        }
+        let lap = laplacian(&directed);
+        assert_eq!(lap.get(0, 0), Some(&4.));
+        assert_eq!(lap.get(2, 0), Some(&-1.));
+        assert_eq!(lap.get(0, 2), Some(&-3.));
+    }
+
+    fn directed_graph() -> CsMat<f64> {
+        // 0 -> 1 -> 3 -> 4 -> 1, 0 -> 2 -> 3, 5 -> 0 and an isolated node 6
+        let mut tri = TriMat::new((7, 7));
+        for &(i, j) in &[(0, 1), (0, 2), (1, 3), (2, 3), (3, 4), (4, 1),
+                         (5, 0)] {
+            tri.add_triplet(i, j, 1.);
+        }
+        tri.to_csr()
+    }
+
+    #[test]
+    fn searches() {
+        let adj = directed_graph();
+        let levels = vec![Some(0), Some(1), Some(1), Some(2), Some(3),
+                          None, None];
+        let parents = vec![None, Some(0), Some(0), Some(1), Some(3),
+                           None, None];
+
+        let tree = bfs(&adj, 0);
+        assert_eq!(tree.order, vec![0, 1, 2, 3, 4]);
+        assert_eq!(tree.levels, levels);
+        assert_eq!(tree.parents, parents);
+        assert_eq!(tree.path_to(4), Some(vec![0, 1, 3, 4]));
+        assert_eq!(tree.path_to(5), None);
+        assert_eq!(bfs(&adj.to_csc(), 0), tree);
+
+        let tree = dfs(&adj, 0);
+        assert_eq!(tree.order, vec![0, 1, 3, 4, 2]);
+        assert_eq!(tree.levels, levels);
+        assert_eq!(tree.parents, parents);
+        assert_eq!(dfs(&adj.to_csc(), 0), tree);
+
+        let tree = bfs_frontier(&adj, 0);
+        assert_eq!(tree, bfs(&adj, 0));
+
+        let tree = dfs(&adj, 5);
+        assert_eq!(tree.order, vec![5, 0, 1, 3, 4, 2]);
+        assert_eq!(tree.levels[4], Some(4));
+        assert_eq!(dfs(&adj, 6).order, vec![6]);
+    }
+
+    #[test]
+    fn frontier_bfs_matches_bfs() {
+        // a pseudo random directed graph
+        let n = 200;
+        let mut tri = TriMat::new((n, n));
+        for i in 0..n {
+            for k in 1..4 {
+                tri.add_triplet(i, (i * 37 * k + 11 * k) % n, 1.);
+            }
+        }
+        let adj: CsMat<f64> = tri.to_csr();
+        let tree = bfs(&adj, 3);
+        let frontier_tree = bfs_frontier(&adj, 3);
+        assert_eq!(frontier_tree.levels, tree.levels);
+        let mut order = tree.order.clone();
+        let mut frontier_order = frontier_tree.order.clone();
+        order.sort();
+        frontier_order.sort();
+        assert_eq!(order, frontier_order);
+        for (node, parent) in frontier_tree.parents.iter().enumerate() {
+            if let Some(parent) = *parent {
+                assert!(adj.get(parent, node).is_some());
+                assert_eq!(tree.levels[parent].unwrap() + 1,
+                           tree.levels[node].unwrap());
+            }
+        }
+        // levels are visited one after the other, in increasing order
+        for pair in frontier_tree.order.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            let (level_a, level_b) = (tree.levels[a], tree.levels[b]);
+            assert!(level_a < level_b || (level_a == level_b && a < b));
+        }
+    }
+
+    fn weighted_graph() -> CsMat<f64> {
+        let mut tri = TriMat::new((6, 6));
+        for &(i, j, w) in &[(0, 1, 7.), (0, 2, 9.), (0, 5, 14.), (1, 2, 10.),
+                            (1, 3, 15.), (2, 3, 11.), (2, 5, 2.),
+                            (3, 4, 6.), (5, 4, 9.)] {
+            tri.add_triplet(i, j, w);
+        }
+        tri.to_csr()
+    }
+
+    #[test]
+    fn single_source_shortest_paths() {
+        let adj = weighted_graph();
+        let paths = dijkstra(&adj, 0);
+        assert_eq!(paths.distances, vec![0., 7., 9., 20., 20., 11.]);
+        assert_eq!(paths.path_to(4), Some(vec![0, 2, 5, 4]));
+        assert_eq!(paths.path_to(3), Some(vec![0, 2, 3]));
+        assert_eq!(bellman_ford(&adj, 0).unwrap(), paths);
+        assert_eq!(dijkstra(&adj.to_csc(), 0), paths);
+
+        // node 0 is not reachable from node 3
+        let paths = dijkstra(&adj, 3);
+        assert_eq!(paths.distances[0], ::std::f64::INFINITY);
+        assert_eq!(paths.path_to(0), None);
+        assert_eq!(paths.path_to(3), Some(vec![3]));
+    }
+
+    #[test]
+    fn negative_lengths() {
+        let mut tri = TriMat::new((4, 4));
+        for &(i, j, w) in &[(0, 1, 4.), (0, 2, 2.), (1, 3, -3.), (2, 3, 0.),
+                            (2, 1, 1.)] {
+            tri.add_triplet(i, j, w);
+        }
+        let adj: CsMat<f64> = tri.to_csr();
+        let paths = bellman_ford(&adj, 0).unwrap();
+        assert_eq!(paths.distances, vec![0., 3., 2., 0.]);
+        assert_eq!(paths.path_to(3), Some(vec![0, 2, 1, 3]));
+
+        // 1 -> 3 -> 2 -> 1 has length -2
+        let mut tri = TriMat::new((4, 4));
+        for &(i, j, w) in &[(0, 1, 4.), (1, 3, -3.), (3, 2, 0.), (2, 1, 1.)] {
+            tri.add_triplet(i, j, w);
+        }
+        let adj: CsMat<f64> = tri.to_csr();
+        assert_eq!(bellman_ford(&adj, 0), Err(SprsError::NegativeCycle));
+        assert_eq!(all_pairs_shortest_paths(&adj),
+                   Err(SprsError::NegativeCycle));
+        // the cycle is not reachable from 0 if the edge 0 -> 1 is reversed
+        let mut tri = TriMat::new((4, 4));
+        for &(i, j, w) in &[(1, 0, 4.), (1, 3, -3.), (3, 2, 0.), (2, 1, 1.)] {
+            tri.add_triplet(i, j, w);
+        }
+        let adj: CsMat<f64> = tri.to_csr();
+        let paths = bellman_ford(&adj, 0).unwrap();
+        assert_eq!(paths.path_to(1), None);
+    }
+
+    #[test]
+    fn all_pairs() {
+        let adj = weighted_graph();
+        let dist = all_pairs_shortest_paths(&adj).unwrap();
+        for source in 0..6 {
+            let paths = dijkstra(&adj, source);
+            for (node, &expected) in paths.distances.iter().enumerate() {
+                if expected == ::std::f64::INFINITY {
+                    assert_eq!(dist.get(source, node), None);
+                }
+                else {
+                    assert_eq!(dist.get(source, node), Some(&expected));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pagerank_fixed_point() {
+        // a directed graph with a dangling node 3 and weighted edges
+        let mut tri = TriMat::new((4, 4));
+        for &(i, j, w) in &[(0, 1, 1.), (0, 2, 3.), (1, 2, 1.), (2, 0, 1.),
+                            (2, 3, 1.)] {
+            tri.add_triplet(i, j, w);
+        }
+        let adj: CsMat<f64> = tri.to_csr();
+        let damping = 0.85;
+        let ranks = pagerank(&adj, damping, 1e-14).unwrap();
+        assert!((ranks.iter().sum::<f64>() - 1.).abs() < 1e-12);
+        assert_eq!(pagerank(&adj.to_csc(), damping, 1e-14).unwrap(), ranks);
+
+        // the ranks are a fixed point of the walk
+        let out_weights = [4., 1., 2., 0.];
+        let jump = (1. - damping + damping * ranks[3]) / 4.;
+        let mut expected = vec![jump; 4];
+        for (row, vec) in adj.outer_iterator().enumerate() {
+            for (col, &w) in vec.iter() {
+                expected[col] += damping * ranks[row] * w / out_weights[row];
+            }
+        }
+        for (rank, expected) in ranks.iter().zip(&expected) {
+            assert!((rank - expected).abs() < 1e-13);
+        }
+
+        // a cycle has uniform ranks
+        let cycle: CsMat<f64> = CsMat::new((3, 3),
+                                           vec![0, 1, 2, 3],
+                                           vec![1, 2, 0],
+                                           vec![1., 1., 1.]);
+        let ranks = pagerank(&cycle, 0.5, 1e-14).unwrap();
+        assert!(ranks.iter().all(|&r| (r - 1. / 3.).abs() < 1e-14));
+    }
+
+    #[test]
+    fn triangles() {
+        // two triangles {0, 1, 2} and {1, 2, 3} sharing the edge 1 -- 2,
+        // with some edges given in both directions, a self loop and a
+        // pendant node 4
+        let mut tri = TriMat::new((5, 5));
+        for &(i, j) in &[(0, 1), (1, 0), (1, 2), (2, 0), (3, 1), (2, 3),
+                         (3, 2), (3, 3), (4, 3)] {
+            tri.add_triplet(i, j, 1.);
+        }
+        let adj: CsMat<f64> = tri.to_csr();
+        assert_eq!(count_triangles(&adj), 2);
+        assert_eq!(count_triangles(&adj.to_csc()), 2);
+
+        let coeffs = clustering_coefficients(&adj);
+        // node 3 has the neighbors 1, 2 and 4, of which only 1 -- 2 is an
+        // edge
+        let expected = [1., 2. / 3., 2. / 3., 1. / 3., 0.];
+        for (c, e) in coeffs.iter().zip(&expected) {
+            assert!((c - e).abs() < 1e-12);
+        }
+
+        // a complete graph on 5 nodes has 10 triangles
+        let mut tri = TriMat::new((5, 5));
+        for i in 0..5 {
+            for j in 0..5 {
+                tri.add_triplet(i, j, 1.);
+            }
+        }
+        let complete: CsMat<f64> = tri.to_csr();
+        assert_eq!(count_triangles(&complete), 10);
+        assert!(clustering_coefficients(&complete).iter().all(|&c| c == 1.));
+    }
+
+    /// A pseudo random rectangular matrix with about `density * m * n`
+    /// non-zeros, with values in `[-1, 1]`
+    fn random_mat(shape: (usize, usize), density: f64,
+                  seed: u64) -> CsMat<f64> {
+        let mut state = seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 11) as f64 / (1u64 << 53) as f64
+        };
+        let mut tri = TriMat::new(shape);
+        for i in 0..shape.0 {
+            for j in 0..shape.1 {
+                if next() < density {
+                    tri.add_triplet(i, j, 2. * next() - 1.);
+                }
+            }
+        }
+        tri.to_csr()
+    }
+
+    /// The size of a maximum matching, by exhaustive search
+    fn brute_force_matching(mat: &CsMat<f64>, row: usize,
+                            used: &mut Vec<bool>) -> usize {
+        if row == mat.rows() {
+            return 0;
+        }
+        let mut best = brute_force_matching(mat, row + 1, used);
+        for (col, _) in mat.outer_view(row).unwrap().iter() {
+            if !used[col] {
+                used[col] = true;
+                best = best.max(1 + brute_force_matching(mat, row + 1, used));
+                used[col] = false;
+            }
+        }
+        best
+    }
+
+    #[test]
+    fn hopcroft_karp() {
+        for seed in 0..20 {
+            let shape = (6 + seed as usize % 3, 7);
+            let mat = random_mat(shape, 0.2, seed);
+            let matching = max_bipartite_matching(&mat);
+            let mut used = vec![false; shape.1];
+            let expected = brute_force_matching(&mat, 0, &mut used);
+            assert_eq!(matching.size(), expected);
+            for (row, col) in matching.row_matches.iter().enumerate() {
+                if let Some(col) = *col {
+                    assert!(mat.get(row, col).is_some());
+                    assert_eq!(matching.col_matches[col], Some(row));
+                }
+            }
+            let csc_matching = max_bipartite_matching(&mat.to_csc());
+            assert_eq!(csc_matching.size(), expected);
+        }
+    }
+
+    /// The cost of an optimal assignment, by exhaustive search
+    fn brute_force_assignment(costs: &CsMat<f64>, row: usize,
+                              used: &mut Vec<bool>) -> f64 {
+        if row == costs.rows() {
+            return 0.;
+        }
+        let mut best = ::std::f64::INFINITY;
+        for (col, &cost) in costs.outer_view(row).unwrap().iter() {
+            if !used[col] {
+                used[col] = true;
+                let rest = brute_force_assignment(costs, row + 1, used);
+                best = best.min(cost + rest);
+                used[col] = false;
+            }
+        }
+        best
+    }
+
+    #[test]
+    fn assignment() {
+        let mut nb_feasible = 0;
+        for seed in 0..30 {
+            let shape = (5 + seed as usize % 2, 6);
+            let costs = random_mat(shape, 0.5, seed);
+            let mut used = vec![false; shape.1];
+            let expected = brute_force_assignment(&costs, 0, &mut used);
+            match min_cost_assignment(&costs) {
+                Ok(assignment) => {
+                    nb_feasible += 1;
+                    assert!((assignment.cost - expected).abs() < 1e-12);
+                    let mut cols = assignment.cols.clone();
+                    cols.sort();
+                    cols.dedup();
+                    assert_eq!(cols.len(), shape.0);
+                }
+                Err(err) => {
+                    assert_eq!(err, SprsError::SingularMatrix);
+                    assert_eq!(expected, ::std::f64::INFINITY);
+                }
+            }
+        }
+        assert!(nb_feasible > 10);
+
+        // more rows than columns
+        let costs = random_mat((4, 3), 1., 0);
+        assert_eq!(min_cost_assignment(&costs),
+                   Err(SprsError::SingularMatrix));
+    }
+
+    #[test]
+    fn spanning_tree() {
+        // two components: a weighted square with a diagonal, 0 -- 1 -- 2 --
+        // 3 -- 0 and 0 -- 2, given in both directions with different
+        // weights for 0 -- 2, and the edge 4 -- 5
+        let mut tri = TriMat::new((7, 7));
+        for &(i, j, w) in &[(0, 1, 4.), (1, 2, 1.), (2, 3, 5.), (3, 0, 3.),
+                            (0, 2, 6.), (2, 0, 2.), (5, 4, 7.), (1, 1, -1.)] {
+            tri.add_triplet(i, j, w);
+        }
+        let adj: CsMat<f64> = tri.to_csr();
+        let tree = minimum_spanning_tree(&adj);
+        let expected = CsMat::new((7, 7),
+                                  vec![0, 0, 1, 2, 3, 3, 4, 4],
+                                  vec![2, 0, 0, 4],
+                                  vec![1., 2., 3., 7.]);
+        assert_eq!(tree, expected);
+        assert_eq!(minimum_spanning_tree(&adj.to_csc()), expected);
+        // a spanning forest has one edge less than nodes per component
+        let (nb_components, _) = connected_components(&adj);
+        assert_eq!(tree.nnz(), 7 - nb_components);
+    }
+}
@@ -0,0 +1,675 @@
+///! Smoothed aggregation algebraic multigrid
+///!
+///! Algebraic multigrid (AMG) builds a hierarchy of coarser and coarser
+///! operators from the matrix alone, and combines cheap smoothing on each
+///! level with an exact solve on the coarsest one. For discretized elliptic
+///! problems this gives a convergence rate independent of the problem size.
+///!
+///! This module implements the smoothed aggregation variant:
+///!
+///! - strongly connected unknowns are grouped in aggregates, each aggregate
+///!   giving one coarse unknown;
+///! - the piecewise constant tentative prolongator associated to the
+///!   aggregates is smoothed by one damped Jacobi step;
+///! - coarse operators are given by the Galerkin triple product `P^T A P`.
+///!
+///! The resulting `Amg` hierarchy can be used as a stand-alone solver by
+///! iterating V-cycles, or as a `Preconditioner` for the conjugate gradient
+///! method, since a V-cycle with symmetric smoothing is a symmetric positive
+///! definite operator for symmetric positive definite matrices.
+
+use std::ops::Deref;
+use num_traits::Float;
+
+use indexing::SpIndex;
+use sparse::prelude::*;
+use sparse::prod;
+use sparse::csmat::CompressedStorage::CSR;
+use sparse::linalg::precond::Preconditioner;
+use sparse::linalg::dense::DenseLu;
+use sparse::linalg::Convergence;
+use sparse::linalg::relax::Smoother;
+use errors::SprsError;
+use ::SpRes;
+
+/// Parameters controlling the construction of an AMG hierarchy and its
+/// V-cycle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AmgOptions<N> {
+    /// The off diagonal entry `a_ij` is a strong connection if
+    /// `|a_ij| >= strength_threshold * sqrt(|a_ii a_jj|)`
+    pub strength_threshold: N,
+    /// The maximum number of levels, the finest one included
+    pub max_levels: usize,
+    /// Stop coarsening once a level has at most this many unknowns. The
+    /// coarsest level is solved with a dense factorization.
+    pub max_coarse_size: usize,
+    /// The number of smoothing sweeps before the coarse correction
+    pub pre_smoothing: usize,
+    /// The number of smoothing sweeps after the coarse correction
+    pub post_smoothing: usize,
+    /// The relaxation method used for smoothing. The default, `None`, is
+    /// the Jacobi method damped by `4 / (3 rho(D^-1 A))`. Post-smoothing
+    /// uses the transpose of the method, so that the V-cycle stays
+    /// symmetric.
+    pub smoother: Option<Smoother<N>>,
+}
+
+impl<N: Float> Default for AmgOptions<N> {
+    fn default() -> AmgOptions<N> {
+        AmgOptions {
+            strength_threshold: N::from(0.08).unwrap(),
+            max_levels: 10,
+            max_coarse_size: 50,
+            pre_smoothing: 1,
+            post_smoothing: 1,
+            smoother: None,
+        }
+    }
+}
+
+/// One level of the hierarchy, except for the coarsest one
+#[derive(Debug)]
+struct Level<N, I: SpIndex> {
+    mat: CsMatI<N, I>,
+    prolongation: CsMatI<N, I>,
+    restriction: CsMatI<N, I>,
+    inv_diag: Vec<N>,
+    omega: N,
+    spectral_radius: N,
+}
+
+/// A smoothed aggregation AMG hierarchy.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::linalg::amg::{Amg, AmgOptions};
+/// use sprs::linalg::cg::pcg;
+/// // 1D Laplacian
+/// let n = 200;
+/// let mut indptr = vec![0];
+/// let mut indices = Vec::new();
+/// let mut data = Vec::new();
+/// for i in 0..n {
+///     if i > 0 { indices.push(i - 1); data.push(-1.); }
+///     indices.push(i); data.push(2.);
+///     if i + 1 < n { indices.push(i + 1); data.push(-1.); }
+///     indptr.push(indices.len());
+/// }
+/// let mat = CsMat::new((n, n), indptr, indices, data);
+/// let amg = Amg::new(&mat, AmgOptions::default()).unwrap();
+/// assert!(amg.nb_levels() > 1);
+/// let b = vec![1.; n];
+/// let mut x = vec![0.; n];
+/// let res = pcg(&mat, &amg, &b, &mut x, 1e-8, 50);
+/// assert!(res.converged);
+/// ```
+#[derive(Debug)]
+pub struct Amg<N, I: SpIndex> {
+    levels: Vec<Level<N, I>>,
+    coarse_mat: CsMatI<N, I>,
+    coarse: DenseLu<N>,
+    pre_smoothing: usize,
+    post_smoothing: usize,
+    smoother: Option<Smoother<N>>,
+}
+
+impl<N, I> Amg<N, I>
+where N: Float + Default,
+      I: SpIndex,
+{
+    /// Build the AMG hierarchy of a square matrix.
+    ///
+    /// Coarsening stops when the maximum number of levels is reached, when
+    /// a level is small enough, or when aggregation fails to reduce the
+    /// number of unknowns.
+    ///
+    /// # Errors
+    ///
+    /// `SingularMatrix` if a diagonal entry of a level is zero or missing,
+    /// or if the coarsest operator is singular.
+    ///
+    /// # Panics
+    ///
+    /// If the matrix is not square.
+    pub fn new<IpS, IS, DS>(mat: &CsMatBase<N, I, IpS, IS, DS>,
+                            options: AmgOptions<N>) -> SpRes<Amg<N, I>>
+    where IpS: Deref<Target=[I]>,
+          IS: Deref<Target=[I]>,
+          DS: Deref<Target=[N]>,
+    {
+        if mat.rows() != mat.cols() {
+            panic!("Non square matrix passed to AMG");
+        }
+        let mut levels = Vec::new();
+        let mut current = mat.to_csr();
+        loop {
+            let n = current.rows();
+            if n <= options.max_coarse_size
+               || levels.len() + 1 >= options.max_levels {
+                break;
+            }
+            let inv_diag = try!(inverse_diagonal(&current));
+            let (aggregates, nb_aggregates) = aggregate(
+                &current, options.strength_threshold);
+            if nb_aggregates == 0 || nb_aggregates >= n {
+                break;
+            }
+            let four_thirds = N::from(4. / 3.).unwrap();
+            let rho = spectral_radius(&current, &inv_diag);
+            let omega = four_thirds / rho;
+            let tentative = tentative_prolongation::<N, I>(&aggregates,
+                                                           nb_aggregates);
+            let prolongation = smooth_prolongation(&current,
+                                                   &inv_diag,
+                                                   omega,
+                                                   &tentative);
+            let restriction = prolongation.transpose_view().to_csr();
+            let coarse = galerkin_product(&restriction,
+                                          &current,
+                                          &prolongation);
+            levels.push(Level {
+                mat: current,
+                prolongation: prolongation,
+                restriction: restriction,
+                inv_diag: inv_diag,
+                omega: omega,
+                spectral_radius: rho,
+            });
+            current = coarse;
+        }
+        let coarse_dim = current.rows();
+        let mut dense = vec![N::zero(); coarse_dim * coarse_dim];
+        for (row, vec) in current.outer_iterator().enumerate() {
+            for (col, &val) in vec.iter() {
+                dense[row * coarse_dim + col] = val;
+            }
+        }
+        let coarse = try!(DenseLu::factorize(coarse_dim, dense));
+        Ok(Amg {
+            levels: levels,
+            coarse_mat: current,
+            coarse: coarse,
+            pre_smoothing: options.pre_smoothing,
+            post_smoothing: options.post_smoothing,
+            smoother: options.smoother,
+        })
+    }
+}
+
+impl<N, I> Amg<N, I>
+where N: Float,
+      I: SpIndex,
+{
+    /// The number of levels of the hierarchy, the finest and the coarsest
+    /// ones included
+    pub fn nb_levels(&self) -> usize {
+        self.levels.len() + 1
+    }
+
+    /// The number of unknowns of each level, from finest to coarsest
+    pub fn level_dims(&self) -> Vec<usize> {
+        self.levels.iter()
+                   .map(|level| level.mat.rows())
+                   .chain(Some(self.coarse_mat.rows()))
+                   .collect()
+    }
+
+    /// The operator complexity, ie the total number of non-zeros of the
+    /// operators of all levels divided by the number of non-zeros of the
+    /// finest operator. This measures the memory and work overhead of a
+    /// V-cycle compared to a matrix vector product.
+    pub fn operator_complexity(&self) -> f64 {
+        let total = self.levels.iter().fold(self.coarse_mat.nnz(), |acc, l| {
+            acc + l.mat.nnz()
+        });
+        total as f64 / self.finest_mat().nnz() as f64
+    }
+
+    /// The dimension of the finest level
+    pub fn dim(&self) -> usize {
+        self.finest_mat().rows()
+    }
+
+    fn finest_mat(&self) -> &CsMatI<N, I> {
+        self.levels.first().map_or(&self.coarse_mat, |level| &level.mat)
+    }
+
+    /// Perform one V-cycle for the system `A x = b`, improving the
+    /// approximate solution `x` in place.
+    ///
+    /// # Panics
+    ///
+    /// If `b` or `x` do not have length `self.dim()`.
+    pub fn v_cycle(&self, b: &[N], x: &mut [N]) {
+        if b.len() != self.dim() || x.len() != self.dim() {
+            panic!("Dimension mismatch");
+        }
+        self.cycle(0, b, x);
+    }
+
+    /// Solve `A x = b` by iterating V-cycles.
+    ///
+    /// `x` holds the initial guess on entry and the solution on exit. The
+    /// iteration stops when the euclidean norm of the residual falls below
+    /// `tol` times the norm of `b`, or after `max_iter` cycles.
+    ///
+    /// # Panics
+    ///
+    /// If `b` or `x` do not have length `self.dim()`.
+    pub fn solve(&self,
+                 b: &[N],
+                 x: &mut [N],
+                 tol: N,
+                 max_iter: usize) -> Convergence<N> {
+        if b.len() != self.dim() || x.len() != self.dim() {
+            panic!("Dimension mismatch");
+        }
+        let b_norm = norm(b);
+        let threshold = if b_norm > N::zero() { tol * b_norm } else { tol };
+        let mut res = vec![N::zero(); b.len()];
+        let mut iterations = 0;
+        residual(self.finest_mat(), b, x, &mut res);
+        let mut res_norm = norm(&res);
+        while res_norm > threshold && iterations < max_iter {
+            self.cycle(0, b, x);
+            iterations += 1;
+            residual(self.finest_mat(), b, x, &mut res);
+            res_norm = norm(&res);
+        }
+        Convergence {
+            iterations: iterations,
+            residual_norm: res_norm,
+            converged: res_norm <= threshold,
+        }
+    }
+
+    fn cycle(&self, level_ind: usize, b: &[N], x: &mut [N]) {
+        let level = match self.levels.get(level_ind) {
+            Some(level) => level,
+            None => {
+                x.copy_from_slice(b);
+                self.coarse.solve(x);
+                return;
+            }
+        };
+        let mut res = vec![N::zero(); b.len()];
+        for _ in 0..self.pre_smoothing {
+            self.smooth(level, self.smoother, b, x, &mut res);
+        }
+        residual(&level.mat, b, x, &mut res);
+        let coarse_dim = level.restriction.rows();
+        let mut coarse_b = vec![N::zero(); coarse_dim];
+        prod::mul_acc_mat_vec_csr(level.restriction.view(),
+                                  &res,
+                                  &mut coarse_b);
+        let mut coarse_x = vec![N::zero(); coarse_dim];
+        self.cycle(level_ind + 1, &coarse_b, &mut coarse_x);
+        prod::mul_acc_mat_vec_csr(level.prolongation.view(), &coarse_x, x);
+        let post_smoother = self.smoother.map(|smoother| {
+            smoother.transposed()
+        });
+        for _ in 0..self.post_smoothing {
+            self.smooth(level, post_smoother, b, x, &mut res);
+        }
+    }
+
+    fn smooth(&self,
+              level: &Level<N, I>,
+              smoother: Option<Smoother<N>>,
+              b: &[N],
+              x: &mut [N],
+              res: &mut [N]) {
+        match smoother {
+            None => jacobi_sweep(level, b, x, res),
+            Some(smoother) => {
+                smoother.sweep_with_bound(&level.mat,
+                                          x,
+                                          b,
+                                          level.spectral_radius)
+                        .expect("diagonal checked when building the levels")
+            }
+        }
+    }
+}
+
+impl<N: Float, I: SpIndex> Preconditioner<N> for Amg<N, I> {
+    fn dim(&self) -> usize {
+        Amg::dim(self)
+    }
+
+    fn apply(&self, r: &[N], z: &mut [N]) {
+        for zi in z.iter_mut() {
+            *zi = N::zero();
+        }
+        self.v_cycle(r, z);
+    }
+}
+
+fn norm<N: Float>(x: &[N]) -> N {
+    x.iter().fold(N::zero(), |acc, &xi| acc + xi * xi).sqrt()
+}
+
+/// Compute `res = b - A x`
+fn residual<N: Float, I: SpIndex>(mat: &CsMatI<N, I>,
+                                  b: &[N],
+                                  x: &[N],
+                                  res: &mut [N]) {
+    for ri in res.iter_mut() {
+        *ri = N::zero();
+    }
+    prod::mul_acc_mat_vec_csr(mat.view(), x, res);
+    for (ri, &bi) in res.iter_mut().zip(b) {
+        *ri = bi - *ri;
+    }
+}
+
+/// One damped Jacobi sweep, `x += omega D^-1 (b - A x)`
+fn jacobi_sweep<N: Float, I: SpIndex>(level: &Level<N, I>,
+                                      b: &[N],
+                                      x: &mut [N],
+                                      res: &mut [N]) {
+    residual(&level.mat, b, x, res);
+    for ((xi, &ri), &di) in x.iter_mut().zip(res.iter()).zip(&level.inv_diag) {
+        *xi = *xi + level.omega * di * ri;
+    }
+}
+
+fn inverse_diagonal<N: Float, I: SpIndex>(mat: &CsMatI<N, I>)
+                                          -> SpRes<Vec<N>> {
+    let mut inv_diag = Vec::with_capacity(mat.rows());
+    for (row, vec) in mat.outer_iterator().enumerate() {
+        match vec.get(row) {
+            Some(&d) if !d.is_zero() => inv_diag.push(N::one() / d),
+            _ => return Err(SprsError::SingularMatrix),
+        }
+    }
+    Ok(inv_diag)
+}
+
+/// Estimate the spectral radius of `D^-1 A` with a few power iterations
+fn spectral_radius<N: Float, I: SpIndex>(mat: &CsMatI<N, I>,
+                                         inv_diag: &[N]) -> N {
+    let n = mat.rows();
+    // a deterministic, non smooth starting vector
+    let mut x: Vec<N> = (0..n).map(|i| N::from(1 + i % 7).unwrap()).collect();
+    let mut y = vec![N::zero(); n];
+    let mut rho = N::zero();
+    for _ in 0..20 {
+        let x_norm = norm(&x);
+        if x_norm.is_zero() {
+            break;
+        }
+        for yi in y.iter_mut() {
+            *yi = N::zero();
+        }
+        prod::mul_acc_mat_vec_csr(mat.view(), &x, &mut y);
+        for (yi, &di) in y.iter_mut().zip(inv_diag) {
+            *yi = *yi * di;
+        }
+        rho = norm(&y) / x_norm;
+        for (xi, &yi) in x.iter_mut().zip(&y) {
+            *xi = yi / x_norm;
+        }
+    }
+    if rho.is_zero() { N::one() } else { rho }
+}
+
+/// Group the unknowns in aggregates of strongly connected neighbours.
+///
+/// Returns the aggregate of each unknown and the number of aggregates.
+fn aggregate<N: Float, I: SpIndex>(mat: &CsMatI<N, I>,
+                                   theta: N) -> (Vec<usize>, usize) {
+    let n = mat.rows();
+    let diag: Vec<N> = mat.outer_iterator().enumerate().map(|(row, vec)| {
+        vec.get(row).map_or(N::zero(), |d| d.abs())
+    }).collect();
+    let strong: Vec<Vec<usize>> = mat.outer_iterator().enumerate()
+                                     .map(|(row, vec)| {
+        vec.iter().filter(|&(col, val)| {
+            col != row && val.abs() >= theta * (diag[row] * diag[col]).sqrt()
+        }).map(|(col, _)| col).collect()
+    }).collect();
+
+    let unassigned = n;
+    let mut aggregates = vec![unassigned; n];
+    let mut nb_aggregates = 0;
+    // first pass: build aggregates around unknowns whose strong neighbours
+    // are all free
+    for i in 0..n {
+        if aggregates[i] != unassigned || strong[i].is_empty() {
+            continue;
+        }
+        if strong[i].iter().all(|&j| aggregates[j] == unassigned) {
+            aggregates[i] = nb_aggregates;
+            for &j in &strong[i] {
+                aggregates[j] = nb_aggregates;
+            }
+            nb_aggregates += 1;
+        }
+    }
+    // second pass: attach remaining unknowns to a neighbouring aggregate
+    let first_pass = aggregates.clone();
+    for i in 0..n {
+        if aggregates[i] != unassigned {
+            continue;
+        }
+        if let Some(&j) = strong[i].iter()
+                                   .find(|&&j| first_pass[j] != unassigned) {
+            aggregates[i] = first_pass[j];
+        }
+    }
+    // third pass: leftover unknowns form their own aggregates
+    for i in 0..n {
+        if aggregates[i] != unassigned {
+            continue;
+        }
+        aggregates[i] = nb_aggregates;
+        for &j in &strong[i] {
+            if aggregates[j] == unassigned {
+                aggregates[j] = nb_aggregates;
+            }
+        }
+        nb_aggregates += 1;
+    }
+    (aggregates, nb_aggregates)
+}
+
+/// The piecewise constant prolongator of the aggregates, with columns
+/// normalized in the euclidean norm.
+fn tentative_prolongation<N: Float, I: SpIndex>(aggregates: &[usize],
+                                                nb_aggregates: usize)
+                                                -> CsMatI<N, I> {
+    let mut sizes = vec![0usize; nb_aggregates];
+    for &agg in aggregates {
+        sizes[agg] += 1;
+    }
+    let indptr = (0..aggregates.len() + 1).map(I::from_usize).collect();
+    let indices = aggregates.iter().map(|&agg| I::from_usize(agg)).collect();
+    let data = aggregates.iter().map(|&agg| {
+        N::one() / N::from(sizes[agg]).unwrap().sqrt()
+    }).collect();
+    CsMatI {
+        storage: CSR,
+        nrows: aggregates.len(),
+        ncols: nb_aggregates,
+        indptr: indptr,
+        indices: indices,
+        data: data,
+    }
+}
+
+/// Smooth the tentative prolongator, `P = (I - omega D^-1 A) T`
+fn smooth_prolongation<N, I>(mat: &CsMatI<N, I>,
+                             inv_diag: &[N],
+                             omega: N,
+                             tentative: &CsMatI<N, I>) -> CsMatI<N, I>
+where N: Float + Default,
+      I: SpIndex,
+{
+    let mut workspace = prod::workspace_csr(mat, tentative);
+    let mut scaled = prod::csr_mul_csr(mat, tentative, &mut workspace);
+    for (row, mut vec) in scaled.outer_iterator_mut().enumerate() {
+        let factor = omega * inv_diag[row];
+        for (_, val) in vec.iter_mut() {
+            *val = *val * factor;
+        }
+    }
+    tentative - &scaled
+}
+
+/// The Galerkin coarse operator `R A P`
+fn galerkin_product<N, I>(restriction: &CsMatI<N, I>,
+                          mat: &CsMatI<N, I>,
+                          prolongation: &CsMatI<N, I>) -> CsMatI<N, I>
+where N: Float + Default,
+      I: SpIndex,
+{
+    let mut workspace = prod::workspace_csr(mat, prolongation);
+    let ap = prod::csr_mul_csr(mat, prolongation, &mut workspace);
+    let mut workspace = prod::workspace_csr(restriction, &ap);
+    prod::csr_mul_csr(restriction, &ap, &mut workspace)
+}
+
+#[cfg(test)]
+mod test {
+    use sparse::{CsMat, TriMat};
+    use sparse::linalg::cg::pcg;
+    use sparse::linalg::precond::Jacobi;
+    use sparse::linalg::relax::{Smoother, SweepDirection};
+    use test_data::laplacian_2d;
+    use super::{Amg, AmgOptions};
+
+    #[test]
+    fn hierarchy_coarsens() {
+        let mat = laplacian_2d(30);
+        let amg = Amg::new(&mat, AmgOptions::default()).unwrap();
+        let dims = amg.level_dims();
+        assert!(dims.len() > 2);
+        assert_eq!(dims[0], 900);
+        assert!(dims.windows(2).all(|w| w[1] < w[0]));
+        assert!(*dims.last().unwrap() <= 50);
+        assert!(amg.operator_complexity() < 2.);
+    }
+
+    #[test]
+    fn coarse_operators_are_symmetric() {
+        let mat = laplacian_2d(12);
+        let options = AmgOptions {
+            max_coarse_size: 10,
+            ..AmgOptions::default()
+        };
+        let amg = Amg::new(&mat, options).unwrap();
+        for level in &amg.levels {
+            let transposed = level.mat.transpose_view().to_csr();
+            for (row, vec) in level.mat.outer_iterator().enumerate() {
+                for (col, &val) in vec.iter() {
+                    let t_val = *transposed.get(row, col).unwrap();
+                    assert!((val - t_val).abs() < 1e-12);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn stand_alone_solve() {
+        let mat = laplacian_2d(30);
+        let amg = Amg::new(&mat, AmgOptions::default()).unwrap();
+        let b = vec![1.; 900];
+        let mut x = vec![0.; 900];
+        let res = amg.solve(&b, &mut x, 1e-8, 100);
+        assert!(res.converged);
+        assert!(res.iterations < 40);
+    }
+
+    #[test]
+    fn amg_preconditioned_cg() {
+        let mat = laplacian_2d(40);
+        let amg = Amg::new(&mat, AmgOptions::default()).unwrap();
+        let jacobi = Jacobi::new(&mat).unwrap();
+        let b: Vec<f64> = (0..1600).map(|i| (i % 13) as f64).collect();
+
+        let mut x = vec![0.; 1600];
+        let with_amg = pcg(&mat, &amg, &b, &mut x, 1e-10, 200);
+        assert!(with_amg.converged);
+
+        let mut y = vec![0.; 1600];
+        let with_jacobi = pcg(&mat, &jacobi, &b, &mut y, 1e-10, 200);
+        assert!(with_jacobi.converged);
+        assert!(with_amg.iterations * 3 < with_jacobi.iterations);
+
+        for (xi, yi) in x.iter().zip(&y) {
+            assert!((xi - yi).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn gauss_seidel_smoothing() {
+        let mat = laplacian_2d(30);
+        let options = AmgOptions {
+            smoother: Some(Smoother::GaussSeidel(1., SweepDirection::Forward)),
+            ..AmgOptions::default()
+        };
+        let amg = Amg::new(&mat, options).unwrap();
+        let b = vec![1.; 900];
+        let mut x = vec![0.; 900];
+        let res = amg.solve(&b, &mut x, 1e-8, 100);
+        assert!(res.converged);
+        let mut jacobi_x = vec![0.; 900];
+        let jacobi = Amg::new(&mat, AmgOptions::default()).unwrap();
+        let jacobi_res = jacobi.solve(&b, &mut jacobi_x, 1e-8, 100);
+        assert!(res.iterations < jacobi_res.iterations);
+        // forward pre-smoothing and backward post-smoothing give a
+        // symmetric preconditioner
+        let mut y = vec![0.; 900];
+        assert!(pcg(&mat, &amg, &b, &mut y, 1e-10, 30).converged);
+    }
+
+    #[test]
+    fn chebyshev_smoothing() {
+        let mat = laplacian_2d(30);
+        let options = AmgOptions {
+            smoother: Some(Smoother::Chebyshev(2)),
+            ..AmgOptions::default()
+        };
+        let amg = Amg::new(&mat, options).unwrap();
+        let b = vec![1.; 900];
+        let mut x = vec![0.; 900];
+        let res = amg.solve(&b, &mut x, 1e-8, 100);
+        assert!(res.converged);
+        let mut y = vec![0.; 900];
+        assert!(pcg(&mat, &amg, &b, &mut y, 1e-10, 30).converged);
+    }
+
+    #[test]
+    fn small_matrix_is_solved_directly() {
+        let mat = laplacian_2d(3);
+        let amg = Amg::new(&mat, AmgOptions::default()).unwrap();
+        assert_eq!(amg.nb_levels(), 1);
+        let b = vec![1.; 9];
+        let mut x = vec![0.; 9];
+        let res = amg.solve(&b, &mut x, 1e-12, 5);
+        assert!(res.converged);
+        assert_eq!(res.iterations, 1);
+        let mut ax = vec![0.; 9];
+        ::sparse::prod::mul_acc_mat_vec_csr(mat.view(), &x, &mut ax);
+        for axi in &ax {
+            assert!((axi - 1.).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn zero_diagonal_is_an_error() {
+        let mut tri = TriMat::new((60, 60));
+        for i in 0..59 {
+            tri.add_triplet(i, i, 2.);
+            tri.add_triplet(i, i + 1, -1.);
+            tri.add_triplet(i + 1, i, -1.);
+        }
+        let mat: CsMat<f64> = tri.to_csr();
+        let res = Amg::new(&mat, AmgOptions::default());
+        assert_eq!(res.err(), Some(::errors::SprsError::SingularMatrix));
+    }
+}
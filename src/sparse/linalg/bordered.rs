@@ -0,0 +1,268 @@
+///! Bordered linear systems
+///!
+///! A bordered system extends a square system `A x = f` by one row and one
+///! column:
+///!
+///! ```text
+///! [ A    b ] [ x ]   [ f ]
+///! [ c^T  d ] [ y ] = [ g ]
+///! ```
+///!
+///! Such systems arise in pseudo-arclength continuation and bifurcation
+///! analysis, where `A` is a Jacobian matrix, `b` the derivative with
+///! respect to the continuation parameter and `c` the tangent direction.
+///! Instead of assembling and factorizing the bordered matrix, the
+///! factorization of `A` is reused and the border is accounted for by a
+///! rank one correction through the scalar Schur complement
+///! `s = d - c^T A^-1 b`.
+
+use num_traits::Num;
+
+use sparse::linalg::operator::LinearOperator;
+use sparse::linalg::solver::LinearSolver;
+use errors::SprsError;
+use ::SpRes;
+
+fn dot<N: Num + Copy>(lhs: &[N], rhs: &[N]) -> N {
+    lhs.iter().zip(rhs).fold(N::zero(), |acc, (&l, &r)| acc + l * r)
+}
+
+/// Solver for bordered systems, reusing a solver for the matrix `A`.
+///
+/// Solves are performed by block elimination followed by one step of
+/// iterative refinement on the whole bordered system. Block elimination
+/// alone loses accuracy when `A` is close to singular, as happens at the
+/// turning points of continuation problems; the refinement step restores
+/// it while only requiring the operator `A` and its solver.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::linalg::bordered::BorderedSolver;
+/// use sprs::linalg::LinearSolver;
+/// # struct DiagSolver(Vec<f64>);
+/// # impl LinearSolver<f64> for DiagSolver {
+/// #     fn dim(&self) -> usize { self.0.len() }
+/// #     fn solve(&self, rhs: &[f64], x: &mut [f64]) {
+/// #         for ((xi, ri), di) in x.iter_mut().zip(rhs).zip(&self.0) {
+/// #             *xi = ri / di;
+/// #         }
+/// #     }
+/// # }
+/// let mat = CsMat::new((2, 2), vec![0, 1, 2], vec![0, 1], vec![2., 4.]);
+/// // any factorization of mat can be used as a solver
+/// let solver = DiagSolver(vec![2., 4.]);
+/// let bordered = BorderedSolver::new(&mat, solver,
+///                                    vec![1., 0.], vec![0., 1.], 1.)
+///                                   .unwrap();
+/// // [2 0 1] [x0]   [3]
+/// // [0 4 0] [x1] = [4]
+/// // [0 1 1] [y ]   [2]
+/// let mut x = [0.; 2];
+/// let y = bordered.solve_split(&[3., 4.], 2., &mut x);
+/// assert_eq!(x, [1., 1.]);
+/// assert_eq!(y, 1.);
+/// ```
+#[derive(Debug, Clone)]
+pub struct BorderedSolver<N, Op, S> {
+    op: Op,
+    solver: S,
+    col: Vec<N>,
+    row: Vec<N>,
+    corner: N,
+    solved_col: Vec<N>,
+    schur: N,
+}
+
+impl<N, Op, S> BorderedSolver<N, Op, S>
+where N: Num + Copy,
+      Op: LinearOperator<N>,
+      S: LinearSolver<N>,
+{
+    /// Prepare the solution of the bordered system with matrix `A`, given
+    /// as the operator `op` along with a solver for it, border column
+    /// `col`, border row `row` and corner `corner`.
+    ///
+    /// This performs one solve with `A`.
+    ///
+    /// # Errors
+    ///
+    /// `SingularMatrix` if the Schur complement `corner - row^T A^-1 col`
+    /// is zero, in which case the bordered matrix is singular.
+    ///
+    /// # Panics
+    ///
+    /// If the dimensions of `op`, `solver`, `col` and `row` don't match.
+    pub fn new(op: Op,
+               solver: S,
+               col: Vec<N>,
+               row: Vec<N>,
+               corner: N) -> SpRes<BorderedSolver<N, Op, S>> {
+        let n = solver.dim();
+        if op.shape() != (n, n) || col.len() != n || row.len() != n {
+            panic!("Dimension mismatch");
+        }
+        let mut solved_col = vec![N::zero(); n];
+        solver.solve(&col, &mut solved_col);
+        let schur = corner - dot(&row, &solved_col);
+        if schur.is_zero() {
+            return Err(SprsError::SingularMatrix);
+        }
+        Ok(BorderedSolver {
+            op: op,
+            solver: solver,
+            col: col,
+            row: row,
+            corner: corner,
+            solved_col: solved_col,
+            schur: schur,
+        })
+    }
+
+    /// The scalar Schur complement `d - c^T A^-1 b` of the bordered system.
+    /// It changes sign when the continuation path crosses a fold.
+    pub fn schur_complement(&self) -> N {
+        self.schur
+    }
+
+    /// Block elimination for the bordered system
+    fn eliminate(&self, f: &[N], g: N, x: &mut [N]) -> N {
+        self.solver.solve(f, x);
+        let y = (g - dot(&self.row, x)) / self.schur;
+        for (xi, &wi) in x.iter_mut().zip(&self.solved_col) {
+            *xi = *xi - y * wi;
+        }
+        y
+    }
+
+    /// Solve the bordered system with right hand side `[f, g]`, storing
+    /// the first `n` components of the solution in `x` and returning the
+    /// last one.
+    ///
+    /// # Panics
+    ///
+    /// If `f` or `x` do not have the dimension of `A`.
+    pub fn solve_split(&self, f: &[N], g: N, x: &mut [N]) -> N {
+        let n = self.solver.dim();
+        if f.len() != n || x.len() != n {
+            panic!("Dimension mismatch");
+        }
+        let mut y = self.eliminate(f, g, x);
+        // one step of iterative refinement on the bordered residual
+        let mut res = vec![N::zero(); n];
+        self.op.apply(x, &mut res);
+        for ((ri, &fi), &ci) in res.iter_mut().zip(f).zip(&self.col) {
+            *ri = fi - *ri - ci * y;
+        }
+        let res_last = g - dot(&self.row, x) - self.corner * y;
+        let mut correction = vec![N::zero(); n];
+        let y_correction = self.eliminate(&res, res_last, &mut correction);
+        for (xi, &ci) in x.iter_mut().zip(&correction) {
+            *xi = *xi + ci;
+        }
+        y = y + y_correction;
+        y
+    }
+}
+
+impl<N, Op, S> LinearSolver<N> for BorderedSolver<N, Op, S>
+where N: Num + Copy,
+      Op: LinearOperator<N>,
+      S: LinearSolver<N>,
+{
+    fn dim(&self) -> usize {
+        self.solver.dim() + 1
+    }
+
+    fn solve(&self, rhs: &[N], x: &mut [N]) {
+        if rhs.len() != self.dim() || x.len() != self.dim() {
+            panic!("Dimension mismatch");
+        }
+        let n = self.solver.dim();
+        let (x_head, x_last) = x.split_at_mut(n);
+        x_last[0] = self.solve_split(&rhs[..n], rhs[n], x_head);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sparse::CsMat;
+    use sparse::linalg::dense::DenseLu;
+    use sparse::linalg::solver::LinearSolver;
+    use errors::SprsError;
+    use super::BorderedSolver;
+
+    fn dense_lu(mat: &CsMat<f64>) -> DenseLu<f64> {
+        let n = mat.rows();
+        let mut dense = vec![0.; n * n];
+        for (row, vec) in mat.outer_iterator().enumerate() {
+            for (col, &val) in vec.iter() {
+                dense[row * n + col] = val;
+            }
+        }
+        DenseLu::factorize(n, dense).unwrap()
+    }
+
+    #[test]
+    fn matches_full_system() {
+        let mat = CsMat::new((3, 3),
+                             vec![0, 2, 4, 6],
+                             vec![0, 1, 1, 2, 0, 2],
+                             vec![4., 1., 3., -1., 2., 5.]);
+        let col = vec![1., -2., 0.5];
+        let row = vec![0., 3., 1.];
+        let corner = 2.;
+        let bordered = BorderedSolver::new(&mat, dense_lu(&mat),
+                                           col.clone(), row.clone(),
+                                           corner).unwrap();
+        assert_eq!(bordered.dim(), 4);
+
+        // solve the assembled bordered system densely
+        let mut full = vec![0.; 16];
+        for (i, vec) in mat.outer_iterator().enumerate() {
+            for (j, &val) in vec.iter() {
+                full[i * 4 + j] = val;
+            }
+            full[i * 4 + 3] = col[i];
+            full[12 + i] = row[i];
+        }
+        full[15] = corner;
+        let full_lu = DenseLu::factorize(4, full).unwrap();
+        let rhs = [1., 2., 3., 4.];
+        let mut expected = rhs;
+        full_lu.solve(&mut expected);
+
+        let mut x = [0.; 4];
+        LinearSolver::solve(&bordered, &rhs, &mut x);
+        for (xi, ei) in x.iter().zip(&expected) {
+            assert!((xi - ei).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn nearly_singular_block() {
+        // A is close to singular, but the bordered system is well posed
+        let eps = 1e-12;
+        let mat = CsMat::new((2, 2), vec![0, 1, 2], vec![0, 1], vec![1., eps]);
+        let bordered = BorderedSolver::new(&mat, dense_lu(&mat),
+                                           vec![0., 1.], vec![0., 1.],
+                                           0.).unwrap();
+        // [1 0 0] [x0]   [1]
+        // [0 e 1] [x1] = [1]
+        // [0 1 0] [y ]   [2]
+        let mut x = [0.; 2];
+        let y = bordered.solve_split(&[1., 1.], 2., &mut x);
+        assert!((x[0] - 1.).abs() < 1e-12);
+        assert!((x[1] - 2.).abs() < 1e-10);
+        assert!((y - (1. - 2. * eps)).abs() < 1e-10);
+    }
+
+    #[test]
+    fn singular_bordered_matrix() {
+        let mat = CsMat::new((2, 2), vec![0, 1, 2], vec![0, 1], vec![1., 2.]);
+        let res = BorderedSolver::new(&mat, dense_lu(&mat),
+                                      vec![1., 2.], vec![1., 0.], 1.);
+        assert_eq!(res.err(), Some(SprsError::SingularMatrix));
+    }
+}
@@ -0,0 +1,136 @@
+///! Application of boundary conditions to assembled systems
+///!
+///! Finite element systems are usually assembled without taking the
+///! Dirichlet boundary conditions into account, which are then imposed on
+///! the assembled matrix by replacing the equations of the constrained
+///! unknowns. Doing so by only zeroing the constrained rows would break
+///! the symmetry of the matrix, preventing the use of eg the conjugate
+///! gradient or a Cholesky factorization, so the constrained columns are
+///! eliminated as well, moving their contributions to the right hand side.
+
+use num_traits::Num;
+
+use indexing::SpIndex;
+use sparse::prelude::*;
+use sparse::CompressedStorage::CSR;
+
+/// Impose the Dirichlet conditions `x[constrained[k]] = values[k]` on the
+/// square system `a x = b`, preserving the symmetry of `a`.
+///
+/// The constrained rows and columns of `a` are zeroed, except for their
+/// diagonal entries which are set to one, and `b` is adjusted so that the
+/// solution is unchanged: the contributions of the constrained columns
+/// are moved to the right hand side, and the constrained elements of `b`
+/// are set to the imposed values.
+///
+/// The zeroed entries stay stored, so the sparsity structure of `a` is not
+/// changed, which allows reusing a symbolic factorization between
+/// systems with the same constraints. Missing diagonal entries of the
+/// constrained rows are inserted. If an index is repeated in
+/// `constrained`, its last value is used.
+///
+/// # Panics
+///
+/// - if `a` is not square, or if the length of `b` is not its dimension
+/// - if `constrained` and `values` do not have the same length
+/// - if a constrained index is out of bounds
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::linalg::boundary::apply_dirichlet;
+/// let mut a = CsMat::new((3, 3), vec![0, 2, 5, 7],
+///                        vec![0, 1, 0, 1, 2, 1, 2],
+///                        vec![2., -1., -1., 2., -1., -1., 2.]);
+/// let mut b = vec![1., 1., 1.];
+/// apply_dirichlet(&mut a, &mut b, &[0], &[4.]);
+/// assert_eq!(a.data(), &[1., 0., 0., 2., -1., -1., 2.]);
+/// assert_eq!(b, vec![4., 5., 1.]);
+/// ```
+pub fn apply_dirichlet<N, I>(a: &mut CsMatI<N, I>,
+                             b: &mut [N],
+                             constrained: &[usize],
+                             values: &[N])
+where N: Num + Copy,
+      I: SpIndex,
+{
+    let n = a.rows();
+    if a.cols() != n || b.len() != n || constrained.len() != values.len() {
+        panic!("Dimension mismatch");
+    }
+    let mut imposed = vec![None; n];
+    for (&ind, &val) in constrained.iter().zip(values) {
+        if ind >= n {
+            panic!("Out of bounds index");
+        }
+        imposed[ind] = Some(val);
+    }
+    let missing_diag: Vec<_> = constrained.iter()
+                                          .filter(|&&i| a.get(i, i).is_none())
+                                          .map(|&i| (i, i, N::one()))
+                                          .collect();
+    if !missing_diag.is_empty() {
+        a.insert_many(missing_diag);
+    }
+
+    let is_csr = a.storage() == CSR;
+    for (outer, mut vec) in a.outer_iterator_mut().enumerate() {
+        for (inner, val) in vec.iter_mut() {
+            let (row, col) = if is_csr {
+                (outer, inner)
+            } else {
+                (inner, outer)
+            };
+            if imposed[row].is_some() {
+                *val = if row == col { N::one() } else { N::zero() };
+            } else if let Some(col_val) = imposed[col] {
+                b[row] = b[row] - *val * col_val;
+                *val = N::zero();
+            }
+        }
+    }
+    for (bi, imposed) in b.iter_mut().zip(imposed) {
+        if let Some(val) = imposed {
+            *bi = val;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sparse::CsMat;
+    use super::apply_dirichlet;
+
+    #[test]
+    fn dirichlet_symmetric() {
+        // 1D laplacian with 4 unknowns, the ends being constrained, and
+        // without the diagonal of the last row
+        let a = CsMat::new((4, 4), vec![0, 2, 5, 8, 9],
+                           vec![0, 1, 0, 1, 2, 1, 2, 3, 2],
+                           vec![2., -1., -1., 2., -1., -1., 2., -1., -1.]);
+        let mut b = vec![0., 1., 1., 0.];
+        let mut a_csr = a.to_owned();
+        apply_dirichlet(&mut a_csr, &mut b, &[3, 0], &[2., 1.]);
+        let expected = CsMat::new((4, 4), vec![0, 2, 5, 8, 10],
+                                  vec![0, 1, 0, 1, 2, 1, 2, 3, 2, 3],
+                                  vec![1., 0., 0., 2., -1., -1., 2., 0.,
+                                       0., 1.]);
+        assert_eq!(a_csr, expected);
+        assert_eq!(b, vec![1., 2., 3., 2.]);
+
+        let mut a_csc = a.to_csc();
+        let mut b = vec![0., 1., 1., 0.];
+        apply_dirichlet(&mut a_csc, &mut b, &[0, 3], &[1., 2.]);
+        assert_eq!(a_csc, expected.to_csc());
+        assert_eq!(b, vec![1., 2., 3., 2.]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn dirichlet_out_of_bounds() {
+        let mut a = CsMat::<f64>::eye(2);
+        let mut b = vec![0.; 2];
+        apply_dirichlet(&mut a, &mut b, &[2], &[1.]);
+    }
+}
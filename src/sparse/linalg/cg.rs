@@ -0,0 +1,136 @@
+///! Preconditioned conjugate gradient
+///!
+///! The conjugate gradient method solves `A x = b` for a symmetric positive
+///! definite operator `A`. Only the action of `A` and of the preconditioner
+///! are required, so any `LinearOperator` and any `Preconditioner` can be
+///! combined.
+
+use num_traits::Float;
+
+use sparse::linalg::operator::LinearOperator;
+use sparse::linalg::precond::Preconditioner;
+use sparse::linalg::Convergence;
+
+fn dot<N: Float>(lhs: &[N], rhs: &[N]) -> N {
+    lhs.iter().zip(rhs).fold(N::zero(), |acc, (&l, &r)| acc + l * r)
+}
+
+/// Solve `op x = b` with the preconditioned conjugate gradient method.
+///
+/// `x` holds the initial guess on entry and the solution on exit. The
+/// iteration stops when the euclidean norm of the residual falls below
+/// `tol` times the norm of `b`, or after `max_iter` iterations.
+///
+/// Both `op` and `precond` should be symmetric positive definite.
+///
+/// # Panics
+///
+/// - if `op` is not square
+/// - if the dimensions of `precond`, `b` and `x` don't match those of `op`
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::linalg::cg::pcg;
+/// use sprs::linalg::precond::Jacobi;
+/// let mat = CsMat::new((3, 3),
+///                      vec![0, 2, 5, 7],
+///                      vec![0, 1, 0, 1, 2, 1, 2],
+///                      vec![4., -1., -1., 4., -1., -1., 4.]);
+/// let precond = Jacobi::new(&mat).unwrap();
+/// let b = [3., 2., 3.];
+/// let mut x = [0f64; 3];
+/// let res = pcg(&mat, &precond, &b, &mut x, 1e-10, 10);
+/// assert!(res.converged);
+/// assert!(x.iter().all(|&xi| (xi - 1.).abs() < 1e-8));
+/// ```
+pub fn pcg<N, Op, P>(op: &Op,
+                     precond: &P,
+                     b: &[N],
+                     x: &mut [N],
+                     tol: N,
+                     max_iter: usize) -> Convergence<N>
+where N: Float,
+      Op: LinearOperator<N> + ?Sized,
+      P: Preconditioner<N> + ?Sized,
+{
+    let n = op.rows();
+    assert_eq!(n, op.cols(), "Non square operator");
+    if precond.dim() != n || b.len() != n || x.len() != n {
+        panic!("Dimension mismatch");
+    }
+    let b_norm = dot(b, b).sqrt();
+    let threshold = if b_norm > N::zero() { tol * b_norm } else { tol };
+
+    let mut res = vec![N::zero(); n];
+    op.apply(x, &mut res);
+    for (ri, &bi) in res.iter_mut().zip(b) {
+        *ri = bi - *ri;
+    }
+    let mut res_norm = dot(&res, &res).sqrt();
+    let mut z = vec![N::zero(); n];
+    precond.apply(&res, &mut z);
+    let mut dir = z.clone();
+    let mut a_dir = vec![N::zero(); n];
+    let mut rz = dot(&res, &z);
+
+    let mut iterations = 0;
+    while res_norm > threshold && iterations < max_iter {
+        op.apply(&dir, &mut a_dir);
+        let alpha = rz / dot(&dir, &a_dir);
+        for ((xi, &di), (ri, &adi)) in x.iter_mut().zip(&dir)
+                                        .zip(res.iter_mut().zip(&a_dir)) {
+            *xi = *xi + alpha * di;
+            *ri = *ri - alpha * adi;
+        }
+        res_norm = dot(&res, &res).sqrt();
+        iterations += 1;
+        precond.apply(&res, &mut z);
+        let rz_next = dot(&res, &z);
+        let beta = rz_next / rz;
+        rz = rz_next;
+        for (di, &zi) in dir.iter_mut().zip(&z) {
+            *di = zi + beta * *di;
+        }
+    }
+
+    Convergence {
+        iterations: iterations,
+        residual_norm: res_norm,
+        converged: res_norm <= threshold,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use test_data::laplacian_1d;
+    use sparse::linalg::precond::{Identity, Jacobi};
+    use super::pcg;
+
+    #[test]
+    fn cg_converges_in_n_steps() {
+        let mat = laplacian_1d(10);
+        let b: Vec<f64> = (0..10).map(|i| i as f64).collect();
+        let mut x = vec![0.; 10];
+        let res = pcg(&mat, &Identity::new(10), &b, &mut x, 1e-12, 20);
+        assert!(res.converged);
+        assert!(res.iterations <= 10);
+        let mut ax = vec![0.; 10];
+        ::sparse::prod::mul_acc_mat_vec_csr(mat.view(), &x, &mut ax);
+        for (axi, bi) in ax.iter().zip(&b) {
+            assert!((axi - bi).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn cg_reports_non_convergence() {
+        let mat = laplacian_1d(50);
+        let b = vec![1.; 50];
+        let mut x = vec![0.; 50];
+        let precond = Jacobi::new(&mat).unwrap();
+        let res = pcg(&mat, &precond, &b, &mut x, 1e-12, 3);
+        assert!(!res.converged);
+        assert_eq!(res.iterations, 3);
+    }
+}
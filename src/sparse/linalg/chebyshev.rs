@@ -0,0 +1,223 @@
+///! Chebyshev semi-iteration
+///!
+///! Given an interval `[lambda_min, lambda_max]` enclosing the eigenvalues
+///! of a (preconditioned) symmetric positive definite operator, the
+///! Chebyshev iteration minimizes the worst case error over this interval
+///! with a three term recurrence. Unlike the conjugate gradient method, it
+///! computes no inner product, which makes it attractive as a multigrid
+///! smoother or as a polynomial preconditioner (see
+///! `precond::Chebyshev`), and on architectures where reductions are
+///! expensive.
+///!
+///! The eigenvalue bounds must be known beforehand. `estimate_max_eigenvalue`
+///! gives an estimate of the upper bound with a few power iterations.
+
+use num_traits::Float;
+
+use sparse::linalg::operator::LinearOperator;
+use sparse::linalg::precond::Preconditioner;
+use sparse::linalg::Convergence;
+
+fn norm<N: Float>(x: &[N]) -> N {
+    x.iter().fold(N::zero(), |acc, &xi| acc + xi * xi).sqrt()
+}
+
+/// Estimate the largest eigenvalue of `M^-1 A`, where `A` is the operator
+/// `op` and `M` the preconditioner `precond`, with `nb_iter` iterations of
+/// the power method.
+///
+/// The estimate approaches the largest eigenvalue from below, so it should
+/// be enlarged by a safety factor, eg 10 percent, before being used as the
+/// upper bound of a Chebyshev iteration.
+///
+/// # Panics
+///
+/// - if `op` is not square
+/// - if `precond` does not have the dimension of `op`
+pub fn estimate_max_eigenvalue<N, Op, P>(op: &Op,
+                                         precond: &P,
+                                         nb_iter: usize) -> N
+where N: Float,
+      Op: LinearOperator<N> + ?Sized,
+      P: Preconditioner<N> + ?Sized,
+{
+    let n = op.rows();
+    assert_eq!(n, op.cols(), "Non square operator");
+    if precond.dim() != n {
+        panic!("Dimension mismatch");
+    }
+    // a deterministic, non smooth starting vector
+    let mut x: Vec<N> = (0..n).map(|i| N::from(1 + i % 7).unwrap()).collect();
+    let mut ax = vec![N::zero(); n];
+    let mut y = vec![N::zero(); n];
+    let mut lambda = N::zero();
+    for _ in 0..nb_iter {
+        let x_norm = norm(&x);
+        if x_norm.is_zero() {
+            break;
+        }
+        op.apply(&x, &mut ax);
+        precond.apply(&ax, &mut y);
+        lambda = norm(&y) / x_norm;
+        for (xi, &yi) in x.iter_mut().zip(&y) {
+            *xi = yi / x_norm;
+        }
+    }
+    lambda
+}
+
+/// Solve `op x = b` with the preconditioned Chebyshev iteration.
+///
+/// The eigenvalues of `M^-1 A`, where `A` is the operator `op` and `M` the
+/// preconditioner `precond`, should lie in `[lambda_min, lambda_max]`.
+/// Components of the error associated to eigenvalues inside this interval
+/// are damped uniformly, which is used by smoothers targeting only the
+/// upper part of the spectrum.
+///
+/// `x` holds the initial guess on entry and the solution on exit. The
+/// iteration stops when the euclidean norm of the residual falls below
+/// `tol` times the norm of `b`, or after `max_iter` iterations. A smoother
+/// performs a fixed number of iterations by passing a zero tolerance.
+///
+/// # Panics
+///
+/// - if `op` is not square
+/// - if the dimensions of `precond`, `b` and `x` don't match those of `op`
+/// - unless `0 < lambda_min < lambda_max`
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::linalg::chebyshev::{chebyshev, estimate_max_eigenvalue};
+/// use sprs::linalg::precond::Jacobi;
+/// let mat = CsMat::new((3, 3),
+///                      vec![0, 2, 5, 7],
+///                      vec![0, 1, 0, 1, 2, 1, 2],
+///                      vec![4., -1., -1., 4., -1., -1., 4.]);
+/// let jacobi = Jacobi::new(&mat).unwrap();
+/// let lambda_max = 1.1 * estimate_max_eigenvalue(&mat, &jacobi, 20);
+/// let b = [3., 2., 3.];
+/// let mut x = [0f64; 3];
+/// let res = chebyshev(&mat, &jacobi, &b, &mut x, 0.5, lambda_max,
+///                     1e-10, 100);
+/// assert!(res.converged);
+/// assert!(x.iter().all(|&xi| (xi - 1.).abs() < 1e-8));
+/// ```
+pub fn chebyshev<N, Op, P>(op: &Op,
+                           precond: &P,
+                           b: &[N],
+                           x: &mut [N],
+                           lambda_min: N,
+                           lambda_max: N,
+                           tol: N,
+                           max_iter: usize) -> Convergence<N>
+where N: Float,
+      Op: LinearOperator<N> + ?Sized,
+      P: Preconditioner<N> + ?Sized,
+{
+    let n = op.rows();
+    assert_eq!(n, op.cols(), "Non square operator");
+    if precond.dim() != n || b.len() != n || x.len() != n {
+        panic!("Dimension mismatch");
+    }
+    assert!(lambda_min > N::zero() && lambda_min < lambda_max,
+            "invalid eigenvalue bounds");
+    let b_norm = norm(b);
+    let threshold = if b_norm > N::zero() { tol * b_norm } else { tol };
+
+    let two = N::one() + N::one();
+    let theta = (lambda_max + lambda_min) / two;
+    let delta = (lambda_max - lambda_min) / two;
+    let sigma = theta / delta;
+    let mut rho = N::one() / sigma;
+
+    let mut res = vec![N::zero(); n];
+    op.apply(x, &mut res);
+    for (ri, &bi) in res.iter_mut().zip(b) {
+        *ri = bi - *ri;
+    }
+    let mut res_norm = norm(&res);
+    let mut z = vec![N::zero(); n];
+    precond.apply(&res, &mut z);
+    let mut dir: Vec<N> = z.iter().map(|&zi| zi / theta).collect();
+    let mut a_dir = vec![N::zero(); n];
+
+    let mut iterations = 0;
+    while res_norm > threshold && iterations < max_iter {
+        op.apply(&dir, &mut a_dir);
+        for ((xi, &di), (ri, &adi)) in x.iter_mut().zip(&dir)
+                                        .zip(res.iter_mut().zip(&a_dir)) {
+            *xi = *xi + di;
+            *ri = *ri - adi;
+        }
+        res_norm = norm(&res);
+        iterations += 1;
+        precond.apply(&res, &mut z);
+        let rho_next = N::one() / (two * sigma - rho);
+        for (di, &zi) in dir.iter_mut().zip(&z) {
+            *di = rho_next * rho * *di + two * rho_next / delta * zi;
+        }
+        rho = rho_next;
+    }
+
+    Convergence {
+        iterations: iterations,
+        residual_norm: res_norm,
+        converged: res_norm <= threshold,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use test_data::laplacian_1d;
+    use sparse::linalg::precond::Identity;
+    use super::{chebyshev, estimate_max_eigenvalue};
+
+    #[test]
+    fn chebyshev_with_exact_bounds() {
+        let n = 20;
+        let mat = laplacian_1d(n);
+        let pi = ::std::f64::consts::PI;
+        let eig = |k: usize| {
+            2. - 2. * (k as f64 * pi / (n as f64 + 1.)).cos()
+        };
+        let (lambda_min, lambda_max) = (eig(1), eig(n));
+        let identity = Identity::new(n);
+        let estimate = estimate_max_eigenvalue(&mat, &identity, 200);
+        assert!(estimate <= lambda_max * (1. + 1e-12));
+        assert!(estimate > 0.95 * lambda_max);
+
+        let b: Vec<f64> = (0..n).map(|i| (i as f64).sin()).collect();
+        let mut x = vec![0.; n];
+        let res = chebyshev(&mat, &identity, &b, &mut x,
+                            lambda_min, lambda_max, 1e-10, 500);
+        assert!(res.converged);
+        // the convergence rate is given by the condition number
+        let cond: f64 = lambda_max / lambda_min;
+        let rate = (cond.sqrt() - 1.) / (cond.sqrt() + 1.);
+        let bound = (1e-10f64 / 2.).ln() / rate.ln();
+        assert!((res.iterations as f64) <= bound.ceil() + 1.);
+
+        // a smoother damps the upper part of the spectrum only
+        let mut smooth = vec![0.; n];
+        for (i, si) in smooth.iter_mut().enumerate() {
+            *si = (pi * (i + 1) as f64 / (n as f64 + 1.)).sin();
+        }
+        let mut oscillating = vec![0.; n];
+        for (i, oi) in oscillating.iter_mut().enumerate() {
+            *oi = (pi * (i + 1) as f64 * n as f64 / (n as f64 + 1.)).sin();
+        }
+        let zero = vec![0.; n];
+        let res = chebyshev(&mat, &identity, &zero, &mut smooth,
+                            lambda_max / 30., lambda_max, 0., 3);
+        assert_eq!(res.iterations, 3);
+        chebyshev(&mat, &identity, &zero, &mut oscillating,
+                  lambda_max / 30., lambda_max, 0., 3);
+        let norm = |x: &[f64]| x.iter().map(|v| v * v).sum::<f64>().sqrt();
+        // the damping factor is 1 / T_3((1 + 1 / 30) / (1 - 1 / 30))
+        let initial_norm = ((n as f64 + 1.) / 2.).sqrt();
+        assert!(norm(&oscillating) < 0.6 * initial_norm);
+        assert!(norm(&smooth) > 0.9 * initial_norm);
+    }
+}
@@ -0,0 +1,411 @@
+///! Estimation of sparse derivative matrices by graph coloring
+///!
+///! A Jacobian matrix `J` is usually obtained through products `J s` with
+///! directional derivatives, computed by automatic differentiation or by
+///! finite differences `(f(x + h s) - f(x)) / h`. When `J` is sparse, columns
+///! without non-zeros in a common row can share a product: with a coloring
+///! of the columns such that structurally orthogonal columns share a color,
+///! the product `B = J S` with the seed matrix `S`, whose column `c` is the
+///! indicator of the columns of color `c`, determines all the non-zeros of
+///! `J` with as many products as colors.
+///!
+///! For symmetric matrices such as Hessians, the symmetry allows recovering
+///! an entry from either of its two positions, and a star coloring, which
+///! only requires the paths of four nodes of the adjacency graph to use at
+///! least three colors, usually needs fewer colors.
+
+use std::ops::Deref;
+use ndarray::ArrayView;
+use num_traits::Num;
+
+use indexing::SpIndex;
+use sparse::prelude::*;
+use ::{Ix2, Shape};
+
+/// Row and column adjacency lists of the sparsity pattern of a matrix,
+/// sorted by increasing index
+fn adjacency<N, I, IpS, IS, DS>(mat: &CsMatBase<N, I, IpS, IS, DS>)
+                                -> (Vec<Vec<usize>>, Vec<Vec<usize>>)
+where I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    let mut row_adj = vec![Vec::new(); mat.rows()];
+    let mut col_adj = vec![Vec::new(); mat.cols()];
+    let is_csr = mat.is_csr();
+    for (outer, vec) in mat.outer_iterator().enumerate() {
+        for (inner, _) in vec.iter() {
+            let (row, col) = if is_csr { (outer, inner) }
+                             else { (inner, outer) };
+            row_adj[row].push(col);
+            col_adj[col].push(row);
+        }
+    }
+    (row_adj, col_adj)
+}
+
+/// The smallest color not forbidden for `node`, ie whose entry in
+/// `forbidden` is not `node`
+fn first_allowed(forbidden: &mut Vec<usize>, node: usize) -> usize {
+    let color = forbidden.iter().position(|&f| f != node)
+                                .unwrap_or(forbidden.len());
+    if color == forbidden.len() {
+        forbidden.push(usize::max_value());
+    }
+    color
+}
+
+/// Nodes by decreasing degree, the largest first ordering which tends to
+/// reduce the number of colors of greedy colorings
+fn largest_first(adj: &[Vec<usize>]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..adj.len()).collect();
+    order.sort_by_key(|&node| ::std::cmp::Reverse(adj[node].len()));
+    order
+}
+
+/// Color the columns of a matrix such that columns with a non-zero in a
+/// common row have different colors, using a greedy coloring in the
+/// largest first order.
+///
+/// Returns the number of colors and the color of each column. This is a
+/// distance-2 coloring of the bipartite graph of the rows and columns, ie
+/// the coloring of Curtis, Powell and Reid.
+pub fn column_coloring<N, I, IpS, IS, DS>(pattern: &CsMatBase<N, I, IpS,
+                                                              IS, DS>)
+                                          -> (usize, Vec<usize>)
+where I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    let (row_adj, col_adj) = adjacency(pattern);
+    let unset = usize::max_value();
+    let mut colors = vec![unset; pattern.cols()];
+    let mut forbidden = Vec::new();
+    for col in largest_first(&col_adj) {
+        for &row in &col_adj[col] {
+            for &other in &row_adj[row] {
+                if colors[other] != unset {
+                    forbidden[colors[other]] = col;
+                }
+            }
+        }
+        colors[col] = first_allowed(&mut forbidden, col);
+    }
+    (forbidden.len(), colors)
+}
+
+/// Star coloring of the adjacency graph of a symmetric matrix, using the
+/// greedy algorithm of Gebremedhin, Manne and Pothen in the largest first
+/// order: adjacent nodes have different colors, and every path of four
+/// nodes uses at least three colors.
+///
+/// Returns the number of colors and the color of each node. Diagonal
+/// entries are ignored.
+///
+/// # Panics
+///
+/// If the matrix is not square.
+pub fn star_coloring<N, I, IpS, IS, DS>(pattern: &CsMatBase<N, I, IpS,
+                                                            IS, DS>)
+                                        -> (usize, Vec<usize>)
+where I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    assert_eq!(pattern.rows(), pattern.cols(), "Non square matrix");
+    let (mut adj, _) = adjacency(pattern);
+    for (node, nbrs) in adj.iter_mut().enumerate() {
+        nbrs.retain(|&nbr| nbr != node);
+    }
+    let unset = usize::max_value();
+    let mut colors = vec![unset; adj.len()];
+    let mut forbidden = Vec::new();
+    for node in largest_first(&adj) {
+        for &nbr in &adj[node] {
+            if colors[nbr] != unset {
+                forbidden[colors[nbr]] = node;
+            }
+        }
+        for &nbr in &adj[node] {
+            for &other in &adj[nbr] {
+                if other == node || colors[other] == unset {
+                    continue;
+                }
+                if colors[nbr] == unset {
+                    forbidden[colors[other]] = node;
+                    continue;
+                }
+                // the path node - nbr - other - next would be bicolored if
+                // node took the color of other
+                let bicolored = adj[other].iter().any(|&next| {
+                    next != nbr && colors[next] == colors[nbr]
+                });
+                if bicolored {
+                    forbidden[colors[other]] = node;
+                }
+            }
+        }
+        colors[node] = first_allowed(&mut forbidden, node);
+    }
+    (forbidden.len(), colors)
+}
+
+/// The compression of a sparse derivative matrix by a coloring of its
+/// columns, giving the seed matrix of the products to evaluate and
+/// recovering the matrix from these products.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::linalg::jacobian_compression;
+/// // a tridiagonal Jacobian needs three products instead of five
+/// let jac: CsMat<f64> = CsMat::new((5, 5),
+///                                  vec![0, 2, 5, 8, 11, 13],
+///                                  vec![0, 1, 0, 1, 2, 1, 2, 3, 2, 3, 4,
+///                                       3, 4],
+///                                  vec![1., 2., 3., 4., 5., 6., 7., 8., 9.,
+///                                       10., 11., 12., 13.]);
+/// let compression = jacobian_compression(&jac);
+/// assert_eq!(compression.nb_colors(), 3);
+/// // the products J S, eg computed by finite differences
+/// let seed = compression.seed_matrix::<f64>();
+/// let products = &jac * &seed.to_dense();
+/// let recovered = compression.recover(products.view());
+/// assert_eq!(recovered, jac);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Compression<I> {
+    shape: Shape,
+    indptr: Vec<I>,
+    indices: Vec<I>,
+    nb_colors: usize,
+    colors: Vec<usize>,
+    symmetric: bool,
+}
+
+impl<I: SpIndex> Compression<I> {
+    fn new(row_adj: Vec<Vec<usize>>,
+           shape: Shape,
+           coloring: (usize, Vec<usize>),
+           symmetric: bool) -> Compression<I> {
+        let mut indptr = vec![I::zero()];
+        let mut indices = Vec::new();
+        for cols in row_adj {
+            indices.extend(cols.into_iter().map(I::from_usize));
+            indptr.push(I::from_usize(indices.len()));
+        }
+        Compression {
+            shape: shape,
+            indptr: indptr,
+            indices: indices,
+            nb_colors: coloring.0,
+            colors: coloring.1,
+            symmetric: symmetric,
+        }
+    }
+
+    /// The number of colors, ie the number of products needed to recover
+    /// the matrix
+    pub fn nb_colors(&self) -> usize {
+        self.nb_colors
+    }
+
+    /// The color of each column
+    pub fn colors(&self) -> &[usize] {
+        &self.colors
+    }
+
+    /// The seed matrix `S`, of shape `(cols, nb_colors)`, whose entry
+    /// `(j, c)` is one if the column `j` has the color `c`. The column `c`
+    /// of `S` is the direction of the `c`-th product with the matrix.
+    pub fn seed_matrix<N: Num + Copy>(&self) -> CsMatI<N, I> {
+        let cols = self.shape.1;
+        CsMatI::new((cols, self.nb_colors),
+                    (0..cols + 1).map(I::from_usize).collect(),
+                    self.colors.iter().map(|&c| I::from_usize(c)).collect(),
+                    vec![N::one(); cols])
+    }
+
+    /// Recover the matrix `J`, in CSR storage, from the products
+    /// `B = J S` with the seed matrix.
+    ///
+    /// # Panics
+    ///
+    /// If `products` does not have the shape `(rows, nb_colors)`.
+    pub fn recover<N: Num + Copy>(&self,
+                                  products: ArrayView<N, Ix2>)
+                                  -> CsMatI<N, I> {
+        if products.shape() != &[self.shape.0, self.nb_colors] {
+            panic!("Dimension mismatch");
+        }
+        let mut data = Vec::with_capacity(self.indices.len());
+        // number of non-zeros of each color in the current row
+        let mut counts = vec![0; self.nb_colors];
+        for (row, range) in self.indptr.windows(2).enumerate() {
+            let cols = &self.indices[range[0].index()..range[1].index()];
+            if self.symmetric {
+                for &col in cols {
+                    counts[self.colors[col.index()]] += 1;
+                }
+            }
+            for &col in cols {
+                let (col, color) = (col.index(), self.colors[col.index()]);
+                // with a star coloring, an entry that shares its color with
+                // another entry of its row is alone in its column
+                if self.symmetric && counts[color] > 1 {
+                    data.push(products[[col, self.colors[row]]]);
+                }
+                else {
+                    data.push(products[[row, color]]);
+                }
+            }
+            if self.symmetric {
+                for &col in cols {
+                    counts[self.colors[col.index()]] = 0;
+                }
+            }
+        }
+        CsMatI::new(self.shape, self.indptr.clone(), self.indices.clone(),
+                    data)
+    }
+}
+
+/// The compression of a sparse Jacobian with the sparsity pattern of
+/// `pattern`, whose values are ignored, by a coloring of its columns (see
+/// `column_coloring`).
+pub fn jacobian_compression<N, I, IpS, IS, DS>(pattern: &CsMatBase<N, I,
+                                                                   IpS, IS,
+                                                                   DS>)
+                                               -> Compression<I>
+where I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    let (row_adj, _) = adjacency(pattern);
+    Compression::new(row_adj, pattern.shape(), column_coloring(pattern),
+                     false)
+}
+
+/// The compression of a sparse Hessian, or of any symmetric matrix, with
+/// the sparsity pattern of `pattern`, whose values are ignored, by a star
+/// coloring of its adjacency graph (see `star_coloring`).
+///
+/// The pattern should be symmetric. An off-diagonal entry `h_ij` is
+/// recovered from the product of the color of `j`, unless another entry of
+/// the row `i` has this color, in which case it is recovered from the
+/// product of the color of `i`, as `h_ji`.
+///
+/// # Panics
+///
+/// If the matrix is not square.
+pub fn hessian_compression<N, I, IpS, IS, DS>(pattern: &CsMatBase<N, I,
+                                                                  IpS, IS,
+                                                                  DS>)
+                                              -> Compression<I>
+where I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    let coloring = star_coloring(pattern);
+    let (row_adj, _) = adjacency(pattern);
+    Compression::new(row_adj, pattern.shape(), coloring, true)
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::Array2;
+    use sparse::{CsMat, TriMat};
+    use super::{jacobian_compression, hessian_compression, column_coloring,
+                star_coloring};
+
+    /// The products `mat * seed` as a dense matrix
+    fn products(mat: &CsMat<f64>, seed: &CsMat<f64>) -> Array2<f64> {
+        let mut res = Array2::zeros((mat.rows(), seed.cols()));
+        for (row, vec) in mat.outer_iterator().enumerate() {
+            for (col, &val) in vec.iter() {
+                for (color, &s) in seed.outer_view(col).unwrap().iter() {
+                    res[[row, color]] += val * s;
+                }
+            }
+        }
+        res
+    }
+
+    #[test]
+    fn jacobian_recovery() {
+        // a banded Jacobian with an additional dense last row
+        let (m, n) = (30, 20);
+        let mut tri = TriMat::new((m, n));
+        for i in 0..m - 1 {
+            for j in i.saturating_sub(1)..(i + 2).min(n) {
+                tri.add_triplet(i, j, (i * n + j) as f64 + 1.);
+            }
+        }
+        tri.add_triplet(m - 1, 0, 1.);
+        let jac: CsMat<f64> = tri.to_csr();
+        let compression = jacobian_compression(&jac);
+        assert_eq!(compression.nb_colors(), 3);
+        let (nb_colors, colors) = column_coloring(&jac);
+        assert_eq!(nb_colors, 3);
+        assert_eq!(colors, compression.colors());
+        let seed = compression.seed_matrix();
+        let recovered = compression.recover(products(&jac, &seed).view());
+        assert_eq!(recovered, jac);
+        let compression = jacobian_compression(&jac.to_csc());
+        assert_eq!(compression.recover(products(&jac, &seed).view()), jac);
+    }
+
+    #[test]
+    fn hessian_recovery() {
+        // an arrowhead matrix: a column coloring needs n colors, a star
+        // coloring only two
+        let n = 10;
+        let mut tri = TriMat::new((n, n));
+        for i in 0..n {
+            tri.add_triplet(i, i, 2. + i as f64);
+            if i > 0 {
+                tri.add_triplet(0, i, i as f64);
+                tri.add_triplet(i, 0, i as f64);
+            }
+        }
+        let hess: CsMat<f64> = tri.to_csr();
+        assert_eq!(column_coloring(&hess).0, n);
+        let (nb_colors, colors) = star_coloring(&hess);
+        assert_eq!(nb_colors, 2);
+        assert!(colors[1..].iter().all(|&c| c != colors[0]));
+        let compression = hessian_compression(&hess);
+        let seed = compression.seed_matrix();
+        let recovered = compression.recover(products(&hess, &seed).view());
+        assert_eq!(recovered, hess);
+
+        // a 2d laplacian on a grid
+        let side = 6;
+        let mut tri = TriMat::new((side * side, side * side));
+        for i in 0..side {
+            for j in 0..side {
+                let node = i * side + j;
+                tri.add_triplet(node, node, 4. + node as f64);
+                if i + 1 < side {
+                    tri.add_triplet(node, node + side, -1. - node as f64);
+                    tri.add_triplet(node + side, node, -1. - node as f64);
+                }
+                if j + 1 < side {
+                    tri.add_triplet(node, node + 1, -2. - node as f64);
+                    tri.add_triplet(node + 1, node, -2. - node as f64);
+                }
+            }
+        }
+        let hess: CsMat<f64> = tri.to_csr();
+        let compression = hessian_compression(&hess);
+        assert!(compression.nb_colors() <= column_coloring(&hess).0);
+        let seed = compression.seed_matrix();
+        let recovered = compression.recover(products(&hess, &seed).view());
+        assert_eq!(recovered, hess);
+    }
+}
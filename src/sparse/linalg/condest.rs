@@ -0,0 +1,364 @@
+///! Condition number estimation
+///!
+///! The accuracy of the solution of a linear system `A x = b` depends on the
+///! condition number `||A|| ||A^-1||` of its matrix: a relative perturbation
+///! of the data, such as rounding errors, can be amplified by this factor in
+///! the solution. The inverse of a sparse matrix is usually dense and is
+///! never formed, so its norm is estimated from a few solves with an
+///! existing factorization of `A`.
+
+use std::cmp::Ordering;
+use std::ops::Deref;
+use num_traits::Float;
+
+use indexing::SpIndex;
+use sparse::prelude::*;
+use sparse::linalg::{LinearOperator, LinearSolver};
+use ::Shape;
+
+/// Number of vectors iterated simultaneously by `onenormest`
+const NB_COLUMNS: usize = 2;
+
+/// Maximum number of iterations of `onenormest`
+const MAX_ITER: usize = 5;
+
+/// Deterministic generator of vectors with random `+1` and `-1` entries
+struct SignGenerator {
+    state: u64,
+}
+
+impl SignGenerator {
+    fn signs<N: Float>(&mut self, dim: usize) -> Vec<N> {
+        (0..dim).map(|_| {
+            self.state ^= self.state << 13;
+            self.state ^= self.state >> 7;
+            self.state ^= self.state << 17;
+            if self.state >> 63 == 0 { N::one() } else { -N::one() }
+        }).collect()
+    }
+}
+
+/// Two vectors of `+1` and `-1` entries are parallel if they are equal or
+/// opposite
+fn parallel<N: Float>(x: &[N], y: &[N]) -> bool {
+    let dot = x.iter().zip(y).fold(N::zero(), |acc, (&a, &b)| acc + a * b);
+    dot.abs() == N::from(x.len()).unwrap()
+}
+
+fn one_norm<N: Float>(x: &[N]) -> N {
+    x.iter().fold(N::zero(), |acc, v| acc + v.abs())
+}
+
+/// Estimate the 1-norm of a square linear operator.
+///
+/// This is the block algorithm of Higham and Tisseur, which iterates over
+/// a few vectors to find a column of the operator with a large 1-norm. It
+/// only requires a handful of products with the operator and its
+/// transpose, and is therefore suited to operators which are not stored
+/// explicitly, such as the inverse of a factorized matrix. The returned
+/// value is a lower bound of the norm, which is exact in most cases and
+/// rarely more than 3 times too small.
+///
+/// # Panics
+///
+/// - if the operator is not square
+/// - if the operator does not support transposed products
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::linalg::onenormest;
+/// let mat: CsMat<f64> = CsMat::new((3, 3),
+///                                  vec![0, 2, 3, 5],
+///                                  vec![0, 2, 1, 0, 2],
+///                                  vec![1., -4., 2., 3., 1.]);
+/// // the largest column sum is |-4| + |1|
+/// assert_eq!(onenormest(&mat), 5.);
+/// ```
+pub fn onenormest<N, Op>(op: &Op) -> N
+where N: Float,
+      Op: LinearOperator<N>,
+{
+    let n = op.rows();
+    assert_eq!(n, op.cols(), "Non square operator");
+    assert!(op.has_transpose(), "onenormest requires transposed products");
+    if n == 0 {
+        return N::zero();
+    }
+    let nb_cols = if n < NB_COLUMNS { n } else { NB_COLUMNS };
+    let mut rng = SignGenerator { state: 0x2545_f491_4f6c_dd1d };
+
+    // the first starting vector is constant, the others are random but
+    // not parallel to the previous ones
+    let mut x: Vec<Vec<N>> = vec![vec![N::one(); n]];
+    while x.len() < nb_cols {
+        let candidate = rng.signs(n);
+        if !x.iter().any(|xj| parallel(xj, &candidate)) {
+            x.push(candidate);
+        }
+    }
+    let scale = N::one() / N::from(n).unwrap();
+    for xj in &mut x {
+        for v in xj.iter_mut() {
+            *v = *v * scale;
+        }
+    }
+
+    let mut est = N::zero();
+    let mut best_ind = None;
+    let mut indices: Vec<usize> = Vec::new();
+    let mut used = vec![false; n];
+    let mut signs_old: Vec<Vec<N>> = Vec::new();
+    let mut z = vec![N::zero(); n];
+    for iter in 0..MAX_ITER {
+        let y: Vec<Vec<N>> = x.iter().map(|xj| {
+            let mut yj = vec![N::zero(); n];
+            op.apply(xj, &mut yj);
+            yj
+        }).collect();
+        let (j_best, est_iter) = y.iter().map(|yj| one_norm(yj))
+                                  .enumerate()
+                                  .fold((0, N::zero()), |best, (j, norm)| {
+                                      if norm > best.1 { (j, norm) }
+                                      else { best }
+                                  });
+        if iter > 0 && est_iter <= est {
+            break;
+        }
+        est = est_iter;
+        if iter > 0 {
+            best_ind = Some(indices[j_best]);
+        }
+        if iter == MAX_ITER - 1 {
+            break;
+        }
+
+        let mut signs: Vec<Vec<N>> = y.iter().map(|yj| {
+            yj.iter().map(|&v| {
+                if v >= N::zero() { N::one() } else { -N::one() }
+            }).collect()
+        }).collect();
+        if signs.iter().all(|s| signs_old.iter().any(|so| parallel(s, so))) {
+            // the iteration would repeat itself
+            break;
+        }
+        // columns parallel to other ones give no information
+        for j in 0..signs.len() {
+            while signs_old.iter().chain(&signs[..j])
+                           .any(|s| parallel(s, &signs[j])) {
+                signs[j] = rng.signs(n);
+            }
+        }
+
+        // the entries of A^T sign(A x) give the columns of A most likely
+        // to have a large norm
+        let mut h = vec![N::zero(); n];
+        for s in &signs {
+            op.apply_transpose(s, &mut z);
+            for (hi, zi) in h.iter_mut().zip(&z) {
+                *hi = hi.max(zi.abs());
+            }
+        }
+        signs_old = signs;
+        let h_max = h.iter().fold(N::zero(), |acc, &v| acc.max(v));
+        if best_ind.map_or(false, |ind| h[ind] == h_max) {
+            break;
+        }
+        let mut order: Vec<usize> = (0..n).collect();
+        order.sort_by(|&a, &b| {
+            h[b].partial_cmp(&h[a]).unwrap_or(Ordering::Equal)
+        });
+        if order[..nb_cols].iter().all(|&i| used[i]) {
+            break;
+        }
+        indices = order.into_iter()
+                       .filter(|&i| !used[i])
+                       .take(nb_cols)
+                       .collect();
+        x = indices.iter().map(|&i| {
+            used[i] = true;
+            let mut unit = vec![N::zero(); n];
+            unit[i] = N::one();
+            unit
+        }).collect();
+    }
+    est
+}
+
+/// The inverse of a matrix, as an operator performing solves
+struct Inverse<'a, S: 'a> {
+    solver: &'a S,
+}
+
+impl<'a, N, S> LinearOperator<N> for Inverse<'a, S>
+where S: LinearSolver<N>,
+{
+    fn shape(&self) -> Shape {
+        (self.solver.dim(), self.solver.dim())
+    }
+
+    fn apply(&self, x: &[N], y: &mut [N]) {
+        self.solver.solve(x, y)
+    }
+
+    fn has_transpose(&self) -> bool {
+        self.solver.has_transpose_solve()
+    }
+
+    fn apply_transpose(&self, x: &[N], y: &mut [N]) {
+        self.solver.solve_transpose(x, y)
+    }
+}
+
+/// Estimate the condition number in the 1-norm, `||A||_1 ||A^-1||_1`, of
+/// the matrix `mat`, given a solver for linear systems involving this
+/// matrix, such as its LU or Cholesky factorization.
+///
+/// The norm of `mat` is computed exactly, while the norm of its inverse is
+/// estimated by `onenormest`, with a few solves. As a rule of thumb, a
+/// solution computed with the solver loses about `log10(condest)` digits of
+/// accuracy.
+///
+/// # Panics
+///
+/// - if `mat` is not square
+/// - if `solver` does not have the dimension of `mat`
+/// - if `solver` does not support transposed solves
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::linalg::{condest, LinearSolver};
+/// // a solver for diagonal systems
+/// struct Diag(Vec<f64>);
+/// impl LinearSolver<f64> for Diag {
+///     fn dim(&self) -> usize { self.0.len() }
+///     fn solve(&self, rhs: &[f64], x: &mut [f64]) {
+///         for ((xi, bi), di) in x.iter_mut().zip(rhs).zip(&self.0) {
+///             *xi = bi / di;
+///         }
+///     }
+///     fn has_transpose_solve(&self) -> bool { true }
+///     fn solve_transpose(&self, rhs: &[f64], x: &mut [f64]) {
+///         self.solve(rhs, x)
+///     }
+/// }
+/// let diag = vec![1e-4, 1., 2.];
+/// let mat = CsMat::new((3, 3), vec![0, 1, 2, 3], vec![0, 1, 2],
+///                      diag.clone());
+/// let cond = condest(&mat, &Diag(diag));
+/// assert!((cond - 2e4).abs() < 1e-8);
+/// ```
+pub fn condest<N, I, IpS, IS, DS, S>(mat: &CsMatBase<N, I, IpS, IS, DS>,
+                                     solver: &S) -> N
+where N: Float,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+      S: LinearSolver<N>,
+{
+    let n = mat.rows();
+    assert_eq!(n, mat.cols(), "Non square matrix");
+    if solver.dim() != n {
+        panic!("Dimension mismatch");
+    }
+    let mut col_sums = vec![N::zero(); n];
+    for (outer, vec) in mat.outer_iterator().enumerate() {
+        for (inner, val) in vec.iter() {
+            let col = if mat.is_csr() { inner } else { outer };
+            col_sums[col] = col_sums[col] + val.abs();
+        }
+    }
+    let norm = col_sums.iter().fold(N::zero(), |acc, &v| acc.max(v));
+    norm * onenormest(&Inverse { solver: solver })
+}
+
+#[cfg(test)]
+mod test {
+    use sparse::{CsMat, TriMat};
+    use sparse::linalg::dense::DenseLu;
+    use super::{onenormest, condest};
+
+    fn to_dense(mat: &CsMat<f64>) -> Vec<f64> {
+        let n = mat.cols();
+        let mut dense = vec![0.; mat.rows() * n];
+        for (row, vec) in mat.to_csr().outer_iterator().enumerate() {
+            for (col, &val) in vec.iter() {
+                dense[row * n + col] = val;
+            }
+        }
+        dense
+    }
+
+    fn exact_one_norm(dense: &[f64], n: usize) -> f64 {
+        (0..n).map(|j| {
+            (0..n).map(|i| dense[i * n + j].abs()).sum::<f64>()
+        }).fold(0., f64::max)
+    }
+
+    /// A non symmetric matrix with a norm dominated by a single column
+    fn test_mat(n: usize) -> CsMat<f64> {
+        let mut tri = TriMat::new((n, n));
+        for i in 0..n {
+            tri.add_triplet(i, i, 3. + (i % 5) as f64);
+            if i + 1 < n {
+                tri.add_triplet(i, i + 1, -1.);
+                tri.add_triplet(i + 1, i, 0.5 * (i % 3) as f64);
+            }
+            if i != n / 3 {
+                tri.add_triplet(i, n / 3, 0.25);
+            }
+        }
+        tri.to_csc()
+    }
+
+    #[test]
+    fn onenormest_matrix() {
+        for &n in &[1, 2, 7, 50] {
+            let mat = test_mat(n);
+            let exact = exact_one_norm(&to_dense(&mat), n);
+            let est = onenormest(&mat);
+            assert!(est <= exact * (1. + 1e-12));
+            assert!(est >= exact / 3.);
+        }
+    }
+
+    #[test]
+    fn condest_lu() {
+        let n = 40;
+        let mat = test_mat(n);
+        let dense = to_dense(&mat);
+        let lu = DenseLu::factorize(n, dense.clone()).unwrap();
+        // exact inverse, column by column
+        let mut inv = vec![0.; n * n];
+        for j in 0..n {
+            let mut col = vec![0.; n];
+            col[j] = 1.;
+            lu.solve(&mut col);
+            for i in 0..n {
+                inv[i * n + j] = col[i];
+            }
+        }
+        let exact = exact_one_norm(&dense, n) * exact_one_norm(&inv, n);
+        let cond = condest(&mat, &lu);
+        assert!(cond <= exact * (1. + 1e-10));
+        assert!(cond >= exact / 3.);
+
+        // the transposed solve is consistent with the solve
+        let rhs: Vec<f64> = (0..n).map(|i| (i as f64).sin()).collect();
+        let mut x = rhs.clone();
+        lu.solve_transpose(&mut x);
+        let mut res = vec![0.; n];
+        for (col, vec) in mat.outer_iterator().enumerate() {
+            for (row, &val) in vec.iter() {
+                res[col] += val * x[row];
+            }
+        }
+        for (r, b) in res.iter().zip(&rhs) {
+            assert!((r - b).abs() < 1e-12);
+        }
+    }
+}
@@ -0,0 +1,659 @@
+///! Small dense kernels used inside the sparse solvers, eg to factorize
+///! diagonal blocks or coarse level operators.
+
+use num_traits::Float;
+use num_complex::Complex;
+
+use errors::SprsError;
+use ::SpRes;
+
+/// Dense LU factorization with partial pivoting of a small square matrix,
+/// stored in row major order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DenseLu<N> {
+    dim: usize,
+    lu: Vec<N>,
+    pivots: Vec<usize>,
+}
+
+impl<N: Float> DenseLu<N> {
+    /// Factorize the row major square matrix `lu` of dimension `dim`
+    ///
+    /// # Errors
+    ///
+    /// `SingularMatrix` if a zero pivot is encountered.
+    pub fn factorize(dim: usize, mut lu: Vec<N>) -> SpRes<DenseLu<N>> {
+        assert_eq!(lu.len(), dim * dim);
+        let mut pivots = Vec::with_capacity(dim);
+        for k in 0..dim {
+            let mut pivot = k;
+            for i in (k + 1)..dim {
+                if lu[i * dim + k].abs() > lu[pivot * dim + k].abs() {
+                    pivot = i;
+                }
+            }
+            if lu[pivot * dim + k].is_zero() {
+                return Err(SprsError::SingularMatrix);
+            }
+            if pivot != k {
+                for j in 0..dim {
+                    lu.swap(k * dim + j, pivot * dim + j);
+                }
+            }
+            pivots.push(pivot);
+            let diag = lu[k * dim + k];
+            for i in (k + 1)..dim {
+                let factor = lu[i * dim + k] / diag;
+                lu[i * dim + k] = factor;
+                for j in (k + 1)..dim {
+                    let val = lu[k * dim + j];
+                    lu[i * dim + j] = lu[i * dim + j] - factor * val;
+                }
+            }
+        }
+        Ok(DenseLu {
+            dim: dim,
+            lu: lu,
+            pivots: pivots,
+        })
+    }
+
+    /// The dimension of the factorized matrix
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    /// Solve the system in place, `x` holding the right hand side on entry
+    /// and the solution on exit.
+    pub fn solve(&self, x: &mut [N]) {
+        let dim = self.dim;
+        for (k, &pivot) in self.pivots.iter().enumerate() {
+            x.swap(k, pivot);
+        }
+        for i in 0..dim {
+            for j in 0..i {
+                x[i] = x[i] - self.lu[i * dim + j] * x[j];
+            }
+        }
+        for i in (0..dim).rev() {
+            for j in (i + 1)..dim {
+                x[i] = x[i] - self.lu[i * dim + j] * x[j];
+            }
+            x[i] = x[i] / self.lu[i * dim + i];
+        }
+    }
+
+    /// Solve the transposed system in place, `x` holding the right hand
+    /// side on entry and the solution on exit.
+    pub fn solve_transpose(&self, x: &mut [N]) {
+        let dim = self.dim;
+        for i in 0..dim {
+            for j in 0..i {
+                x[i] = x[i] - self.lu[j * dim + i] * x[j];
+            }
+            x[i] = x[i] / self.lu[i * dim + i];
+        }
+        for i in (0..dim).rev() {
+            for j in (i + 1)..dim {
+                x[i] = x[i] - self.lu[j * dim + i] * x[j];
+            }
+        }
+        for (k, &pivot) in self.pivots.iter().enumerate().rev() {
+            x.swap(k, pivot);
+        }
+    }
+}
+
+/// Compute all the eigenvalues and eigenvectors of a small dense symmetric
+/// matrix, stored in row major order, using the cyclic Jacobi method.
+///
+/// Returns the eigenvalues, in no particular order, and the eigenvectors
+/// as the columns of a row major matrix, ie the component `i` of the
+/// eigenvector `j` is at index `i * dim + j`.
+pub fn symmetric_eigen<N: Float>(dim: usize, mut mat: Vec<N>)
+                                 -> (Vec<N>, Vec<N>) {
+    assert_eq!(mat.len(), dim * dim);
+    let mut vecs = vec![N::zero(); dim * dim];
+    for i in 0..dim {
+        vecs[i * dim + i] = N::one();
+    }
+    let two = N::one() + N::one();
+    for _ in 0..100 {
+        let mut off_diag = N::zero();
+        let mut total = N::zero();
+        for i in 0..dim {
+            for j in 0..dim {
+                let sq = mat[i * dim + j] * mat[i * dim + j];
+                total = total + sq;
+                if i != j {
+                    off_diag = off_diag + sq;
+                }
+            }
+        }
+        if off_diag <= N::epsilon() * N::epsilon() * total {
+            break;
+        }
+        for p in 0..dim {
+            for q in (p + 1)..dim {
+                let apq = mat[p * dim + q];
+                if apq.is_zero() {
+                    continue;
+                }
+                let app = mat[p * dim + p];
+                let aqq = mat[q * dim + q];
+                let theta = (aqq - app) / (two * apq);
+                let root = (theta * theta + N::one()).sqrt();
+                let t = if theta < N::zero() {
+                    -N::one() / (root - theta)
+                }
+                else {
+                    N::one() / (theta + root)
+                };
+                let c = N::one() / (t * t + N::one()).sqrt();
+                let s = t * c;
+                for k in 0..dim {
+                    let akp = mat[k * dim + p];
+                    let akq = mat[k * dim + q];
+                    mat[k * dim + p] = c * akp - s * akq;
+                    mat[k * dim + q] = s * akp + c * akq;
+                }
+                for k in 0..dim {
+                    let apk = mat[p * dim + k];
+                    let aqk = mat[q * dim + k];
+                    mat[p * dim + k] = c * apk - s * aqk;
+                    mat[q * dim + k] = s * apk + c * aqk;
+                }
+                for k in 0..dim {
+                    let vkp = vecs[k * dim + p];
+                    let vkq = vecs[k * dim + q];
+                    vecs[k * dim + p] = c * vkp - s * vkq;
+                    vecs[k * dim + q] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+    let vals = (0..dim).map(|i| mat[i * dim + i]).collect();
+    (vals, vecs)
+}
+
+/// Reduce a square row major matrix to upper Hessenberg form by
+/// Householder similarity transformations, which are accumulated in the
+/// row major orthogonal matrix `q`.
+fn hessenberg<N: Float>(dim: usize, mat: &mut [N], q: &mut [N]) {
+    let two = N::one() + N::one();
+    for k in 0..dim.saturating_sub(2) {
+        let norm = ((k + 1)..dim).fold(N::zero(), |acc, i| {
+            acc + mat[i * dim + k] * mat[i * dim + k]
+        }).sqrt();
+        if norm.is_zero() {
+            continue;
+        }
+        let x0 = mat[(k + 1) * dim + k];
+        let alpha = if x0 > N::zero() { -norm } else { norm };
+        let mut v: Vec<N> = ((k + 1)..dim).map(|i| mat[i * dim + k])
+                                          .collect();
+        v[0] = v[0] - alpha;
+        let v_sq = v.iter().fold(N::zero(), |acc, &x| acc + x * x);
+        if v_sq.is_zero() {
+            continue;
+        }
+        // mat = (I - 2 v v^T / v^T v) mat
+        for j in 0..dim {
+            let proj = v.iter().enumerate().fold(N::zero(), |acc, (l, &vl)| {
+                acc + vl * mat[(k + 1 + l) * dim + j]
+            });
+            let factor = two * proj / v_sq;
+            for (l, &vl) in v.iter().enumerate() {
+                let ind = (k + 1 + l) * dim + j;
+                mat[ind] = mat[ind] - factor * vl;
+            }
+        }
+        // mat = mat (I - 2 v v^T / v^T v)
+        for i in 0..dim {
+            let proj = v.iter().enumerate().fold(N::zero(), |acc, (l, &vl)| {
+                acc + vl * mat[i * dim + k + 1 + l]
+            });
+            let factor = two * proj / v_sq;
+            for (l, &vl) in v.iter().enumerate() {
+                let ind = i * dim + k + 1 + l;
+                mat[ind] = mat[ind] - factor * vl;
+            }
+            // q = q (I - 2 v v^T / v^T v)
+            let proj = v.iter().enumerate().fold(N::zero(), |acc, (l, &vl)| {
+                acc + vl * q[i * dim + k + 1 + l]
+            });
+            let factor = two * proj / v_sq;
+            for (l, &vl) in v.iter().enumerate() {
+                let ind = i * dim + k + 1 + l;
+                q[ind] = q[ind] - factor * vl;
+            }
+        }
+    }
+}
+
+/// Reduce an upper Hessenberg matrix, given in complex form, to upper
+/// triangular Schur form with the shifted QR algorithm. The unitary
+/// transformations are accumulated in `q`.
+fn hessenberg_schur<N: Float>(dim: usize,
+                              h: &mut [Complex<N>],
+                              q: &mut [Complex<N>]) -> SpRes<()> {
+    let zero = Complex::new(N::zero(), N::zero());
+    let two = N::one() + N::one();
+    let mut rotations = Vec::with_capacity(dim);
+    let mut hi = dim;
+    let mut iter = 0;
+    while hi > 1 {
+        let last = hi - 1;
+        // look for a negligible subdiagonal entry to deflate
+        let mut lo = last;
+        while lo > 0 {
+            let sub = h[lo * dim + lo - 1].norm();
+            let diag = h[(lo - 1) * dim + lo - 1].norm()
+                       + h[lo * dim + lo].norm();
+            if sub <= N::epsilon() * diag {
+                h[lo * dim + lo - 1] = zero;
+                break;
+            }
+            lo -= 1;
+        }
+        if lo == last {
+            hi -= 1;
+            iter = 0;
+            continue;
+        }
+        iter += 1;
+        if iter > 30 * dim {
+            return Err(SprsError::NotConverged);
+        }
+        // Wilkinson shift, or an exceptional shift to break cycles
+        let a = h[(last - 1) * dim + last - 1];
+        let b = h[(last - 1) * dim + last];
+        let c = h[last * dim + last - 1];
+        let d = h[last * dim + last];
+        let shift = if iter % 11 == 10 {
+            d + Complex::new(c.norm(), N::zero())
+        }
+        else {
+            let half_diff = (a - d) / two;
+            let disc = (half_diff * half_diff + b * c).sqrt();
+            let mean = (a + d) / two;
+            let (l1, l2) = (mean + disc, mean - disc);
+            if (l1 - d).norm() < (l2 - d).norm() { l1 } else { l2 }
+        };
+        for i in lo..hi {
+            h[i * dim + i] = h[i * dim + i] - shift;
+        }
+        rotations.clear();
+        for k in lo..last {
+            let x = h[k * dim + k];
+            let y = h[(k + 1) * dim + k];
+            let r = (x.norm_sqr() + y.norm_sqr()).sqrt();
+            let (cs, sn) = if r.is_zero() {
+                (Complex::new(N::one(), N::zero()), zero)
+            }
+            else {
+                (x / r, y / r)
+            };
+            for j in k..dim {
+                let t1 = h[k * dim + j];
+                let t2 = h[(k + 1) * dim + j];
+                h[k * dim + j] = cs.conj() * t1 + sn.conj() * t2;
+                h[(k + 1) * dim + j] = cs * t2 - sn * t1;
+            }
+            rotations.push((cs, sn));
+        }
+        for (k, &(cs, sn)) in (lo..last).zip(&rotations) {
+            let max_row = if k + 2 < hi { k + 2 } else { last };
+            for i in 0..(max_row + 1) {
+                let t1 = h[i * dim + k];
+                let t2 = h[i * dim + k + 1];
+                h[i * dim + k] = t1 * cs + t2 * sn;
+                h[i * dim + k + 1] = t2 * cs.conj() - t1 * sn.conj();
+            }
+            for i in 0..dim {
+                let t1 = q[i * dim + k];
+                let t2 = q[i * dim + k + 1];
+                q[i * dim + k] = t1 * cs + t2 * sn;
+                q[i * dim + k + 1] = t2 * cs.conj() - t1 * sn.conj();
+            }
+        }
+        for i in lo..hi {
+            h[i * dim + i] = h[i * dim + i] + shift;
+        }
+    }
+    Ok(())
+}
+
+/// Compute the complex Schur decomposition `mat = Q T Q^H` of a small dense
+/// real matrix, stored in row major order, where `T` is upper triangular
+/// and `Q` is unitary. Returns `T` and `Q` in row major order.
+///
+/// The eigenvalues of `mat` are the diagonal entries of `T`.
+///
+/// # Errors
+///
+/// `NotConverged` if the QR algorithm failed to converge.
+pub fn complex_schur<N: Float>(dim: usize, mut mat: Vec<N>)
+                               -> SpRes<(Vec<Complex<N>>, Vec<Complex<N>>)> {
+    assert_eq!(mat.len(), dim * dim);
+    let mut q: Vec<N> = (0..dim * dim).map(|ind| {
+        if ind / dim == ind % dim { N::one() } else { N::zero() }
+    }).collect();
+    hessenberg(dim, &mut mat, &mut q);
+    let mut t: Vec<_> = mat.into_iter()
+                           .map(|x| Complex::new(x, N::zero()))
+                           .collect();
+    let mut q: Vec<_> = q.into_iter()
+                         .map(|x| Complex::new(x, N::zero()))
+                         .collect();
+    try!(hessenberg_schur(dim, &mut t, &mut q));
+    // clean the rounding errors left below the diagonal
+    for i in 0..dim {
+        for j in 0..i {
+            t[i * dim + j] = Complex::new(N::zero(), N::zero());
+        }
+    }
+    Ok((t, q))
+}
+
+/// Reorder a complex Schur decomposition `Q T Q^H` so that the eigenvalues
+/// `T[j, j]` for which `select[j]` is true come first on the diagonal of
+/// `T`, keeping their relative order. The leading columns of `Q` then span
+/// the invariant subspace associated to the selected eigenvalues.
+///
+/// # Panics
+///
+/// If `select` does not have length `dim`.
+pub fn reorder_schur<N: Float>(dim: usize,
+                               t: &mut [Complex<N>],
+                               q: &mut [Complex<N>],
+                               select: &[bool]) {
+    assert_eq!(select.len(), dim);
+    let mut nb_placed = 0;
+    for (j, _) in select.iter().enumerate().filter(|&(_, &sel)| sel) {
+        // bubble the eigenvalue up to its place by swapping adjacent
+        // diagonal entries with Givens rotations
+        for k in (nb_placed..j).rev() {
+            swap_schur(dim, t, q, k);
+        }
+        nb_placed += 1;
+    }
+}
+
+/// Swap the diagonal entries `k` and `k + 1` of a complex Schur form.
+fn swap_schur<N: Float>(dim: usize,
+                        t: &mut [Complex<N>],
+                        q: &mut [Complex<N>],
+                        k: usize) {
+    let t11 = t[k * dim + k];
+    let t22 = t[(k + 1) * dim + k + 1];
+    // rotation such that [cs sn; -conj(sn) cs] [t12; t22 - t11] = [r; 0]
+    let f = t[k * dim + k + 1];
+    let g = t22 - t11;
+    let norm = (f.norm_sqr() + g.norm_sqr()).sqrt();
+    if norm.is_zero() {
+        return;
+    }
+    let (cs, sn) = if f.norm().is_zero() {
+        (N::zero(), g.conj() / g.norm())
+    }
+    else {
+        (f.norm() / norm, f / f.norm() * g.conj() / norm)
+    };
+    for j in (k + 2)..dim {
+        let t1 = t[k * dim + j];
+        let t2 = t[(k + 1) * dim + j];
+        t[k * dim + j] = t1 * cs + sn * t2;
+        t[(k + 1) * dim + j] = t2 * cs - sn.conj() * t1;
+    }
+    for i in 0..k {
+        let t1 = t[i * dim + k];
+        let t2 = t[i * dim + k + 1];
+        t[i * dim + k] = t1 * cs + sn.conj() * t2;
+        t[i * dim + k + 1] = t2 * cs - sn * t1;
+    }
+    t[k * dim + k] = t22;
+    t[(k + 1) * dim + k + 1] = t11;
+    for i in 0..dim {
+        let q1 = q[i * dim + k];
+        let q2 = q[i * dim + k + 1];
+        q[i * dim + k] = q1 * cs + sn.conj() * q2;
+        q[i * dim + k + 1] = q2 * cs - sn * q1;
+    }
+}
+
+/// Solve a small dense complex system by Gaussian elimination with partial
+/// pivoting. Zero pivots are replaced by `tiny`, as needed by inverse
+/// iteration.
+fn complex_solve<N: Float>(dim: usize,
+                           mut mat: Vec<Complex<N>>,
+                           mut rhs: Vec<Complex<N>>,
+                           tiny: N) -> Vec<Complex<N>> {
+    for k in 0..dim {
+        let mut pivot = k;
+        for i in (k + 1)..dim {
+            if mat[i * dim + k].norm() > mat[pivot * dim + k].norm() {
+                pivot = i;
+            }
+        }
+        if pivot != k {
+            for j in 0..dim {
+                mat.swap(k * dim + j, pivot * dim + j);
+            }
+            rhs.swap(k, pivot);
+        }
+        if mat[k * dim + k].norm() <= tiny {
+            mat[k * dim + k] = Complex::new(tiny, N::zero());
+        }
+        let diag = mat[k * dim + k];
+        for i in (k + 1)..dim {
+            let factor = mat[i * dim + k] / diag;
+            for j in k..dim {
+                let val = mat[k * dim + j];
+                mat[i * dim + j] = mat[i * dim + j] - factor * val;
+            }
+            let val = rhs[k];
+            rhs[i] = rhs[i] - factor * val;
+        }
+    }
+    for i in (0..dim).rev() {
+        let mut acc = rhs[i];
+        for j in (i + 1)..dim {
+            acc = acc - mat[i * dim + j] * rhs[j];
+        }
+        rhs[i] = acc / mat[i * dim + i];
+    }
+    rhs
+}
+
+/// Compute all the eigenvalues and eigenvectors of a small dense real
+/// matrix, stored in row major order.
+///
+/// Eigenvalues are computed by reduction to Hessenberg form followed by
+/// the shifted QR algorithm, and eigenvectors by inverse iteration. The
+/// eigenvectors are returned as the columns of a row major matrix, ie the
+/// component `i` of the eigenvector `j` is at index `i * dim + j`, and have
+/// a unit euclidean norm. Their largest component is real, so that the
+/// eigenvectors of real eigenvalues are real.
+///
+/// # Errors
+///
+/// `NotConverged` if the QR algorithm failed to converge.
+pub fn general_eigen<N: Float>(dim: usize, mat: Vec<N>)
+                               -> SpRes<(Vec<Complex<N>>, Vec<Complex<N>>)> {
+    assert_eq!(mat.len(), dim * dim);
+    let zero = Complex::new(N::zero(), N::zero());
+    let (schur, _) = try!(complex_schur(dim, mat.clone()));
+    let vals: Vec<_> = (0..dim).map(|i| schur[i * dim + i]).collect();
+
+    let scale = mat.iter().fold(N::zero(), |acc, x| acc.max(x.abs()));
+    let tiny = if scale.is_zero() { N::epsilon() }
+               else { N::epsilon() * scale };
+    let mut vecs = vec![zero; dim * dim];
+    for (j, &val) in vals.iter().enumerate() {
+        // perturb the shift so that the shifted matrix is not exactly
+        // singular
+        let shift = val + Complex::new(tiny, tiny);
+        let shifted: Vec<Complex<N>> = mat.iter().enumerate().map(|(ind, &x)| {
+            if ind / dim == ind % dim {
+                Complex::new(x, N::zero()) - shift
+            }
+            else {
+                Complex::new(x, N::zero())
+            }
+        }).collect();
+        let mut vec = vec![Complex::new(N::one(), N::zero()); dim];
+        for _ in 0..3 {
+            vec = complex_solve(dim, shifted.clone(), vec, tiny);
+            let norm = vec.iter()
+                          .fold(N::zero(), |acc, x| acc + x.norm_sqr())
+                          .sqrt();
+            if norm.is_zero() || !norm.is_finite() {
+                break;
+            }
+            for x in vec.iter_mut() {
+                *x = *x / norm;
+            }
+        }
+        // fix the phase so that the largest component is real positive,
+        // making the eigenvectors of real eigenvalues real
+        let largest = vec.iter().fold(zero, |acc, &x| {
+            if x.norm() > acc.norm() { x } else { acc }
+        });
+        let phase = if largest.norm().is_zero() {
+            Complex::new(N::one(), N::zero())
+        }
+        else {
+            largest.conj() / largest.norm()
+        };
+        for (i, &x) in vec.iter().enumerate() {
+            vecs[i * dim + j] = x * phase;
+        }
+    }
+    Ok((vals, vecs))
+}
+
+#[cfg(test)]
+mod test {
+    use num_complex::Complex;
+    use super::{DenseLu, symmetric_eigen, general_eigen};
+    use super::{complex_schur, reorder_schur};
+
+    #[test]
+    fn lu_solve() {
+        let lu = DenseLu::factorize(2, vec![0., 2., 1., 1.]).unwrap();
+        let mut x = [4., 3.];
+        lu.solve(&mut x);
+        assert_eq!(x, [1., 2.]);
+    }
+
+    #[test]
+    fn jacobi_eigen() {
+        let mat = vec![2., -1., 0.,
+                       -1., 2., -1.,
+                       0., -1., 2.];
+        let (vals, vecs) = symmetric_eigen(3, mat.clone());
+        let mut sorted = vals.clone();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let sqrt2 = 2f64.sqrt();
+        let expected = [2. - sqrt2, 2., 2. + sqrt2];
+        for (val, exp) in sorted.iter().zip(&expected) {
+            assert!((val - exp).abs() < 1e-12);
+        }
+        for j in 0..3 {
+            for i in 0..3 {
+                let mv: f64 = (0..3).map(|k| mat[i * 3 + k] * vecs[k * 3 + j])
+                                    .sum();
+                assert!((mv - vals[j] * vecs[i * 3 + j]).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn general_eigen_rotation() {
+        // rotation-like matrix with eigenvalues 1 +- 2i, and a real one
+        let mat = vec![1., -2., 0.,
+                       2., 1., 0.,
+                       3., 1., 5.];
+        let (vals, vecs) = general_eigen(3, mat.clone()).unwrap();
+        let mut found = [false; 3];
+        let expected = [Complex::new(1., 2.),
+                        Complex::new(1., -2.),
+                        Complex::new(5., 0.)];
+        for val in &vals {
+            for (f, exp) in found.iter_mut().zip(&expected) {
+                if (val - exp).norm() < 1e-10 {
+                    *f = true;
+                }
+            }
+        }
+        assert!(found.iter().all(|&f| f));
+        for j in 0..3 {
+            for i in 0..3 {
+                let mv = (0..3).fold(Complex::new(0., 0.), |acc, k| {
+                    acc + vecs[k * 3 + j] * mat[i * 3 + k]
+                });
+                assert!((mv - vals[j] * vecs[i * 3 + j]).norm() < 1e-10);
+            }
+        }
+    }
+
+    #[test]
+    fn general_eigen_nonnormal() {
+        let n = 6;
+        let mut mat = vec![0.; n * n];
+        for i in 0..n {
+            mat[i * n + i] = (i + 1) as f64;
+            for j in (i + 1)..n {
+                mat[i * n + j] = ((i * j) % 5) as f64 - 2.;
+            }
+        }
+        // mix with a similarity to destroy the triangular structure
+        mat[(n - 1) * n] = 0.5;
+        let (vals, vecs) = general_eigen(n, mat.clone()).unwrap();
+        for j in 0..n {
+            for i in 0..n {
+                let mv = (0..n).fold(Complex::new(0., 0.), |acc, k| {
+                    acc + vecs[k * n + j] * mat[i * n + k]
+                });
+                assert!((mv - vals[j] * vecs[i * n + j]).norm() < 1e-8);
+            }
+        }
+    }
+
+    #[test]
+    fn schur_reordering() {
+        let mat = vec![1., -2., 0., 4.,
+                       2., 1., 0., -1.,
+                       3., 1., 5., 2.,
+                       0., 1., 1., -3.];
+        let (mut t, mut q) = complex_schur(4, mat.clone()).unwrap();
+        // move the eigenvalue of largest real part in front
+        let largest = (0..4).fold(0, |best, i| {
+            if t[i * 4 + i].re > t[best * 4 + best].re { i } else { best }
+        });
+        let expected = t[largest * 4 + largest];
+        let mut select = [false; 4];
+        select[largest] = true;
+        reorder_schur(4, &mut t, &mut q, &select);
+        assert!((t[0] - expected).norm() < 1e-12);
+        for i in 0..4 {
+            for j in 0..i {
+                assert!(t[i * 4 + j].norm() < 1e-12);
+            }
+        }
+        // Q T Q^H still gives back the matrix
+        for i in 0..4 {
+            for j in 0..4 {
+                let mut acc = Complex::new(0., 0.);
+                for k in 0..4 {
+                    for l in 0..4 {
+                        acc = acc + q[i * 4 + k] * t[k * 4 + l]
+                                    * q[j * 4 + l].conj();
+                    }
+                }
+                assert!((acc - mat[i * 4 + j]).norm() < 1e-12);
+            }
+        }
+    }
+}
@@ -0,0 +1,397 @@
+///! Dulmage-Mendelsohn decomposition
+///!
+///! The Dulmage-Mendelsohn decomposition permutes the rows and columns of a
+///! sparse matrix into a block upper triangular form, using only its
+///! sparsity pattern. It is computed from a maximum matching between rows
+///! and columns, whose size is the structural rank of the matrix, ie the
+///! largest rank it can have for any values of its non-zero elements.
+///!
+///! The coarse decomposition splits the matrix into an underdetermined part,
+///! with more columns than rows, a square part, and an overdetermined part,
+///! with more rows than columns. A square matrix is structurally singular
+///! if and only if one of the non-square parts is not empty. The square part
+///! is further split into the strongly connected components of its graph,
+///! which is the fine decomposition. A linear system with a block
+///! triangular matrix can be solved by factorizing the diagonal blocks only.
+
+use std::ops::{Deref, Range};
+
+use indexing::SpIndex;
+use sparse::prelude::*;
+use sparse::permutation::PermOwnedI;
+use sparse::graph::{strongly_connected_components, max_bipartite_matching};
+
+/// Block triangular form of a matrix, such that `A(p, q)` is block upper
+/// triangular, where `p` is the row permutation and `q` the column
+/// permutation.
+///
+/// The row `row_perm.at(k)` of `A` is the row `k` of `A(p, q)`, and
+/// similarly for the columns. The block `b` of `A(p, q)` spans the rows
+/// `row_blocks[b]..row_blocks[b + 1]` and the columns
+/// `col_blocks[b]..col_blocks[b + 1]`.
+#[derive(Debug, Clone)]
+pub struct Dmperm<I: SpIndex> {
+    /// The row permutation `p`
+    pub row_perm: PermOwnedI<I>,
+    /// The column permutation `q`
+    pub col_perm: PermOwnedI<I>,
+    /// The boundaries of the row blocks
+    pub row_blocks: Vec<usize>,
+    /// The boundaries of the column blocks
+    pub col_blocks: Vec<usize>,
+    /// The boundaries of the coarse row sets `R1`, `R2`, `R3` and `R0`:
+    /// `A(R1, [C0 C1])` is the underdetermined part, `A(R2, C2)` the square
+    /// part and `A([R3 R0], C3)` the overdetermined part
+    pub coarse_rows: [usize; 5],
+    /// The boundaries of the coarse column sets `C0`, `C1`, `C2` and `C3`
+    pub coarse_cols: [usize; 5],
+}
+
+impl<I: SpIndex> Dmperm<I> {
+    /// The number of blocks of the block triangular form
+    pub fn nb_blocks(&self) -> usize {
+        self.row_blocks.len() - 1
+    }
+
+    /// The rows and columns of `A(p, q)` spanned by the block `b`
+    ///
+    /// # Panics
+    ///
+    /// If `b >= self.nb_blocks()`
+    pub fn block(&self, b: usize) -> (Range<usize>, Range<usize>) {
+        (self.row_blocks[b]..self.row_blocks[b + 1],
+         self.col_blocks[b]..self.col_blocks[b + 1])
+    }
+
+    /// The structural rank of the matrix, ie the size of a maximum matching
+    pub fn structural_rank(&self) -> usize {
+        self.coarse_rows[3]
+    }
+
+    /// Whether the matrix is square and structurally nonsingular, in which
+    /// case all the blocks are square with a zero-free diagonal
+    pub fn is_structurally_nonsingular(&self) -> bool {
+        let rows = self.coarse_rows[4];
+        rows == self.coarse_cols[4] && self.structural_rank() == rows
+    }
+}
+
+/// Row and column adjacency lists of the sparsity pattern of a matrix
+fn adjacency<N, I, IpS, IS, DS>(mat: &CsMatBase<N, I, IpS, IS, DS>)
+                                -> (Vec<Vec<usize>>, Vec<Vec<usize>>)
+where I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    let mut row_adj = vec![Vec::new(); mat.rows()];
+    let mut col_adj = vec![Vec::new(); mat.cols()];
+    let is_csr = mat.is_csr();
+    for (outer, vec) in mat.outer_iterator().enumerate() {
+        for (inner, _) in vec.iter() {
+            let (row, col) = if is_csr { (outer, inner) }
+                             else { (inner, outer) };
+            row_adj[row].push(col);
+            col_adj[col].push(row);
+        }
+    }
+    (row_adj, col_adj)
+}
+
+/// Compute a maximum matching between the rows and the columns of a matrix,
+/// considering only its sparsity pattern. Returns the row matched to each
+/// column, if any.
+///
+/// The number of matched columns is the structural rank of the matrix. See
+/// `graph::max_bipartite_matching` for the matched column of each row.
+pub fn maximum_matching<N, I, IpS, IS, DS>(mat: &CsMatBase<N, I, IpS,
+                                                           IS, DS>)
+                                           -> Vec<Option<usize>>
+where I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    max_bipartite_matching(mat).col_matches
+}
+
+/// Compute the Dulmage-Mendelsohn decomposition of a matrix, which gives a
+/// block upper triangular form of the matrix.
+///
+/// The first block is the underdetermined part `A(R1, [C0 C1])`, if it is
+/// not empty, and the last block is the overdetermined part
+/// `A([R3 R0], C3)`, if it is not empty. The blocks in between are the
+/// strongly connected components of the square part `A(R2, C2)`, and have
+/// a zero-free diagonal.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::linalg::dmperm;
+/// // the second row and the second column are structurally empty
+/// let mat = CsMat::new((3, 3),
+///                      vec![0, 2, 2, 4],
+///                      vec![0, 2, 0, 2],
+///                      vec![1., 2., 3., 4.]);
+/// let dm = dmperm(&mat);
+/// assert_eq!(dm.structural_rank(), 2);
+/// assert!(!dm.is_structurally_nonsingular());
+/// // the empty column forms the underdetermined part
+/// assert_eq!(dm.col_perm.at(0), 1);
+/// assert_eq!(dm.coarse_cols, [0, 1, 1, 3, 3]);
+/// // the empty row forms the overdetermined part
+/// assert_eq!(dm.row_perm.at(2), 1);
+/// assert_eq!(dm.coarse_rows, [0, 0, 2, 2, 3]);
+/// ```
+pub fn dmperm<N, I, IpS, IS, DS>(mat: &CsMatBase<N, I, IpS, IS, DS>)
+                                 -> Dmperm<I>
+where I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    let (nb_rows, nb_cols) = (mat.rows(), mat.cols());
+    let (row_adj, col_adj) = adjacency(mat);
+    let matching = max_bipartite_matching(mat);
+    let (col_match, row_match) = (matching.col_matches, matching.row_matches);
+
+    // coarse decomposition: the columns reachable from unmatched columns by
+    // alternating paths belong to the set 1, and the rows reachable from
+    // unmatched rows to the set 3. Unmarked rows and columns form the set 2.
+    let mut row_set = vec![2; nb_rows];
+    let mut col_set = vec![2; nb_cols];
+    let mut queue: Vec<usize> = (0..nb_cols).filter(|&j| {
+        col_match[j].is_none()
+    }).collect();
+    for &col in &queue {
+        col_set[col] = 1;
+    }
+    let mut head = 0;
+    while head < queue.len() {
+        let col = queue[head];
+        head += 1;
+        for &row in &col_adj[col] {
+            if row_set[row] != 2 {
+                continue;
+            }
+            row_set[row] = 1;
+            let matched = row_match[row].expect("maximum matching");
+            if col_set[matched] == 2 {
+                col_set[matched] = 1;
+                queue.push(matched);
+            }
+        }
+    }
+    let mut queue: Vec<usize> = (0..nb_rows).filter(|&i| {
+        row_match[i].is_none()
+    }).collect();
+    for &row in &queue {
+        row_set[row] = 3;
+    }
+    let mut head = 0;
+    while head < queue.len() {
+        let row = queue[head];
+        head += 1;
+        for &col in &row_adj[row] {
+            if col_set[col] != 2 {
+                continue;
+            }
+            col_set[col] = 3;
+            let matched = col_match[col].expect("maximum matching");
+            if row_set[matched] == 2 {
+                row_set[matched] = 3;
+                queue.push(matched);
+            }
+        }
+    }
+
+    // matched columns are ordered along with their rows, so that the
+    // matching lies on the diagonal of each set
+    let mut row_perm = Vec::with_capacity(nb_rows);
+    let mut col_perm: Vec<usize> = (0..nb_cols).filter(|&j| {
+        col_match[j].is_none()
+    }).collect();
+    let mut coarse_rows = [0; 5];
+    let mut coarse_cols = [0; 5];
+    coarse_cols[1] = col_perm.len();
+    for set in 1..4 {
+        for col in 0..nb_cols {
+            if col_set[col] == set {
+                if let Some(row) = col_match[col] {
+                    row_perm.push(row);
+                    col_perm.push(col);
+                }
+            }
+        }
+        coarse_rows[set] = row_perm.len();
+        coarse_cols[set + 1] = col_perm.len();
+    }
+    row_perm.extend((0..nb_rows).filter(|&i| row_match[i].is_none()));
+    coarse_rows[4] = nb_rows;
+
+    // fine decomposition: the strongly connected components of the square
+    // part, the node k standing for its k-th row and column
+    let (row_start, col_start) = (coarse_rows[1], coarse_cols[2]);
+    let nb_square = coarse_rows[2] - row_start;
+    let mut position = vec![usize::max_value(); nb_cols];
+    for k in 0..nb_square {
+        position[col_perm[col_start + k]] = k;
+    }
+    let mut indptr = vec![0];
+    let mut indices = Vec::new();
+    for k in 0..nb_square {
+        let row = row_perm[row_start + k];
+        indices.extend(row_adj[row].iter().filter(|&&col| col_set[col] == 2)
+                                         .map(|&col| position[col]));
+        indptr.push(indices.len());
+    }
+    let nnz = indices.len();
+    let square = CsMat::new((nb_square, nb_square), indptr, indices,
+                            vec![true; nnz]);
+    let (nb_components, labels) = strongly_connected_components(&square);
+    // ordering the components topologically puts the edges above the
+    // diagonal
+    let mut order: Vec<usize> = (0..nb_square).collect();
+    order.sort_by_key(|&k| labels[k]);
+    let square_rows: Vec<usize> = order.iter()
+                                       .map(|&k| row_perm[row_start + k])
+                                       .collect();
+    let square_cols: Vec<usize> = order.iter()
+                                       .map(|&k| col_perm[col_start + k])
+                                       .collect();
+    row_perm[row_start..row_start + nb_square].copy_from_slice(&square_rows);
+    col_perm[col_start..col_start + nb_square].copy_from_slice(&square_cols);
+
+    let mut row_blocks = vec![0];
+    let mut col_blocks = vec![0];
+    if col_start > 0 {
+        row_blocks.push(row_start);
+        col_blocks.push(col_start);
+    }
+    let mut block_size = vec![0; nb_components];
+    for &label in &labels {
+        block_size[label] += 1;
+    }
+    let mut offset = 0;
+    for size in block_size {
+        offset += size;
+        row_blocks.push(row_start + offset);
+        col_blocks.push(col_start + offset);
+    }
+    if coarse_rows[2] < nb_rows {
+        row_blocks.push(nb_rows);
+        col_blocks.push(nb_cols);
+    }
+
+    let to_index = |perm: Vec<usize>| {
+        perm.into_iter().map(I::from_usize).collect()
+    };
+    Dmperm {
+        row_perm: PermOwnedI::new(to_index(row_perm)),
+        col_perm: PermOwnedI::new(to_index(col_perm)),
+        row_blocks: row_blocks,
+        col_blocks: col_blocks,
+        coarse_rows: coarse_rows,
+        coarse_cols: coarse_cols,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sparse::{CsMat, TriMat};
+    use super::{dmperm, maximum_matching, Dmperm};
+
+    /// Check that `A(p, q)` is block upper triangular, and that the blocks
+    /// of the square part have a zero-free diagonal
+    fn check_block_triangular(mat: &CsMat<f64>, dm: &Dmperm<usize>) {
+        let block_of = |blocks: &[usize], pos: usize| {
+            blocks.windows(2).position(|w| w[0] <= pos && pos < w[1])
+                  .unwrap()
+        };
+        for (row, vec) in mat.outer_iterator().enumerate() {
+            for (col, _) in vec.iter() {
+                let row_block = block_of(&dm.row_blocks,
+                                         dm.row_perm.at_inv(row));
+                let col_block = block_of(&dm.col_blocks,
+                                         dm.col_perm.at_inv(col));
+                assert!(row_block <= col_block);
+            }
+        }
+        let nb_square = dm.coarse_rows[2] - dm.coarse_rows[1];
+        for k in 0..nb_square {
+            let row = dm.row_perm.at(dm.coarse_rows[1] + k);
+            let col = dm.col_perm.at(dm.coarse_cols[2] + k);
+            assert!(mat.get(row, col).is_some());
+        }
+    }
+
+    #[test]
+    fn dmperm_nonsingular() {
+        // the blocks {0, 1}, {2} and {3, 4, 5} of a block upper triangular
+        // matrix, with scrambled rows and columns
+        let entries = [(0, 0), (0, 1), (1, 0), (1, 1), (0, 3), (1, 2),
+                       (2, 2), (2, 4), (3, 3), (3, 4), (4, 4), (4, 5),
+                       (5, 5), (5, 3)];
+        let rows = [4, 2, 0, 5, 1, 3];
+        let cols = [1, 5, 3, 0, 2, 4];
+        let mut tri = TriMat::new((6, 6));
+        for &(i, j) in &entries {
+            tri.add_triplet(rows[i], cols[j], 1.);
+        }
+        let mat: CsMat<f64> = tri.to_csr();
+        let dm = dmperm(&mat);
+        assert!(dm.is_structurally_nonsingular());
+        assert_eq!(dm.structural_rank(), 6);
+        assert_eq!(dm.coarse_rows, [0, 0, 6, 6, 6]);
+        assert_eq!(dm.coarse_cols, [0, 0, 0, 6, 6]);
+        assert_eq!(dm.nb_blocks(), 3);
+        let mut sizes: Vec<usize> = (0..3).map(|b| dm.block(b).0.len())
+                                          .collect();
+        sizes.sort();
+        assert_eq!(sizes, vec![1, 2, 3]);
+        check_block_triangular(&mat, &dm);
+        // the same decomposition is found from the CSC storage
+        let dm_csc = dmperm(&mat.to_csc());
+        assert_eq!(dm_csc.row_blocks, dm.row_blocks);
+        check_block_triangular(&mat, &dm_csc);
+    }
+
+    #[test]
+    fn dmperm_structurally_singular() {
+        // the rows 0 and 1 only have a value in the column 0, and the
+        // column 3 is empty
+        let mut tri = TriMat::new((4, 4));
+        for &(i, j) in &[(0, 0), (1, 0), (2, 1), (2, 2), (3, 2)] {
+            tri.add_triplet(i, j, 1.);
+        }
+        let mat: CsMat<f64> = tri.to_csr();
+        let matching = maximum_matching(&mat);
+        assert_eq!(matching.iter().filter(|m| m.is_some()).count(), 3);
+        assert_eq!(matching[3], None);
+
+        let dm = dmperm(&mat);
+        assert_eq!(dm.structural_rank(), 3);
+        assert!(!dm.is_structurally_nonsingular());
+        assert_eq!(dm.coarse_cols, [0, 1, 1, 3, 4]);
+        assert_eq!(dm.coarse_rows, [0, 0, 2, 3, 4]);
+        assert_eq!(dm.row_blocks, vec![0, 0, 1, 2, 4]);
+        assert_eq!(dm.col_blocks, vec![0, 1, 2, 3, 4]);
+        assert_eq!(dm.col_perm.at(0), 3);
+        assert_eq!(dm.col_perm.at(3), 0);
+        assert_eq!(dm.block(1), (0..1, 1..2));
+        check_block_triangular(&mat, &dm);
+
+        // a rectangular matrix
+        let mut tri = TriMat::new((2, 5));
+        for &(i, j) in &[(0, 0), (0, 4), (1, 4), (1, 2)] {
+            tri.add_triplet(i, j, 1.);
+        }
+        let mat: CsMat<f64> = tri.to_csr();
+        let dm = dmperm(&mat);
+        assert_eq!(dm.structural_rank(), 2);
+        assert_eq!(dm.coarse_cols, [0, 3, 5, 5, 5]);
+        assert_eq!(dm.nb_blocks(), 1);
+        check_block_triangular(&mat, &dm);
+    }
+}
@@ -0,0 +1,1018 @@
+///! Eigensolvers for sparse matrices
+///!
+///! These solvers compute a few eigenpairs of a large sparse matrix, only
+///! requiring products of the matrix with vectors. They build a Krylov
+///! subspace and extract approximate eigenpairs by projecting the matrix on
+///! this subspace (Rayleigh-Ritz procedure). The subspace is kept small by
+///! restarting from the most promising approximations.
+///!
+///! `eigsh` handles symmetric matrices with the thick restart Lanczos
+///! method, while `eigs` handles general matrices, whose eigenpairs may be
+///! complex, with the Krylov-Schur variant of the implicitly restarted
+///! Arnoldi method. The simpler power iteration, `power_iteration`, only
+///! finds the dominant eigenpair, but needs a single vector of storage.
+
+use std::cmp::{self, Ordering};
+use std::ops::Deref;
+use ndarray::Array2;
+use num_traits::Float;
+use num_complex::Complex;
+
+use indexing::SpIndex;
+use sparse::prelude::*;
+use sparse::linalg::operator::LinearOperator;
+use sparse::linalg::precond::Jacobi;
+use sparse::linalg::cg::pcg;
+use sparse::linalg::solver::LinearSolver;
+use sparse::linalg::dense::{symmetric_eigen, general_eigen, complex_schur,
+                            reorder_schur};
+use errors::SprsError;
+use ::SpRes;
+
+/// The maximum number of restarts before giving up on convergence
+const MAX_RESTARTS: usize = 1000;
+
+/// Selects the part of the spectrum an eigensolver should compute
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Which {
+    /// Eigenvalues of largest absolute value
+    LargestMagnitude,
+    /// Eigenvalues of smallest absolute value
+    SmallestMagnitude,
+    /// Largest eigenvalues, or largest real parts for complex eigenvalues
+    LargestAlgebraic,
+    /// Smallest eigenvalues, or smallest real parts for complex eigenvalues
+    SmallestAlgebraic,
+}
+
+impl Which {
+    /// Order eigenvalues so that the wanted ones come first
+    fn order<N: Float>(&self, lhs: N, rhs: N) -> Ordering {
+        let ord = match *self {
+            Which::LargestMagnitude => rhs.abs().partial_cmp(&lhs.abs()),
+            Which::SmallestMagnitude => lhs.abs().partial_cmp(&rhs.abs()),
+            Which::LargestAlgebraic => rhs.partial_cmp(&lhs),
+            Which::SmallestAlgebraic => lhs.partial_cmp(&rhs),
+        };
+        ord.unwrap_or(Ordering::Equal)
+    }
+
+    /// Order complex eigenvalues so that the wanted ones come first
+    fn order_complex<N: Float>(&self,
+                               lhs: Complex<N>,
+                               rhs: Complex<N>) -> Ordering {
+        let ord = match *self {
+            Which::LargestMagnitude => rhs.norm().partial_cmp(&lhs.norm()),
+            Which::SmallestMagnitude => lhs.norm().partial_cmp(&rhs.norm()),
+            Which::LargestAlgebraic => rhs.re.partial_cmp(&lhs.re),
+            Which::SmallestAlgebraic => lhs.re.partial_cmp(&rhs.re),
+        };
+        ord.unwrap_or(Ordering::Equal)
+    }
+}
+
+/// A set of eigenvalues along with their eigenvectors
+#[derive(Debug, Clone, PartialEq)]
+pub struct EigenPairs<N> {
+    /// The eigenvalues, wanted ones first as specified by `Which`
+    pub values: Vec<N>,
+    /// The eigenvectors, stored as the columns of this array, in the
+    /// same order as the eigenvalues
+    pub vectors: Array2<N>,
+}
+
+/// A set of possibly complex eigenvalues along with their eigenvectors
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComplexEigenPairs<N> {
+    /// The eigenvalues, wanted ones first as specified by `Which`
+    pub values: Vec<Complex<N>>,
+    /// The eigenvectors, stored as the columns of this array, in the
+    /// same order as the eigenvalues
+    pub vectors: Array2<Complex<N>>,
+}
+
+/// The eigenpair of largest magnitude found by the power iteration
+#[derive(Debug, Clone, PartialEq)]
+pub struct DominantEigenPair<N> {
+    /// The dominant eigenvalue
+    pub value: N,
+    /// The normalized eigenvector, whose entry of largest magnitude is
+    /// positive
+    pub vector: Vec<N>,
+    /// The number of iterations performed
+    pub iterations: usize,
+}
+
+fn dot<N: Float>(lhs: &[N], rhs: &[N]) -> N {
+    lhs.iter().zip(rhs).fold(N::zero(), |acc, (&l, &r)| acc + l * r)
+}
+
+/// A deterministic pseudo random vector with entries in `[-1, 1]`
+fn pseudo_random<N: Float>(dim: usize, seed: usize) -> Vec<N> {
+    let mut state = 0x2545_f491_4f6c_dd1d_u64 ^ (seed as u64 + 1);
+    (0..dim).map(|_| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let unit = (state >> 11) as f64 / (1u64 << 53) as f64;
+        N::from(2. * unit - 1.).unwrap()
+    }).collect()
+}
+
+/// Basis of vectors orthonormal for the inner product defined by `B`,
+/// along with the images of the vectors by `B`.
+struct Basis<N> {
+    vecs: Vec<Vec<N>>,
+    b_vecs: Vec<Vec<N>>,
+}
+
+impl<N: Float> Basis<N> {
+    fn len(&self) -> usize {
+        self.vecs.len()
+    }
+
+    /// Orthonormalize `u` against the basis and append it. Returns false
+    /// if `u` is numerically in the span of the basis.
+    fn push<BA>(&mut self, mut u: Vec<N>, apply_b: &BA) -> bool
+    where BA: Fn(&[N], &mut [N])
+    {
+        let mut bu = vec![N::zero(); u.len()];
+        apply_b(&u, &mut bu);
+        let init_norm = dot(&u, &bu).sqrt();
+        // orthogonalize twice for numerical stability
+        for _ in 0..2 {
+            for (v, bv) in self.vecs.iter().zip(&self.b_vecs) {
+                let coeff = dot(bv, &u);
+                for (ui, &vi) in u.iter_mut().zip(v) {
+                    *ui = *ui - coeff * vi;
+                }
+            }
+        }
+        apply_b(&u, &mut bu);
+        let norm = dot(&u, &bu).sqrt();
+        if !(norm > init_norm * N::epsilon().sqrt()) {
+            return false;
+        }
+        for (ui, bui) in u.iter_mut().zip(bu.iter_mut()) {
+            *ui = *ui / norm;
+            *bui = *bui / norm;
+        }
+        self.vecs.push(u);
+        self.b_vecs.push(bu);
+        true
+    }
+
+    fn pop(&mut self) -> Option<(Vec<N>, Vec<N>)> {
+        match (self.vecs.pop(), self.b_vecs.pop()) {
+            (Some(v), Some(bv)) => Some((v, bv)),
+            _ => None,
+        }
+    }
+}
+
+/// Compute the linear combinations `sum_i coeffs[i * ncoeffs + j] vecs[i]`
+/// for each selected `j`, where `coeffs` has `ncoeffs` columns.
+fn combine_rect<N: Float>(vecs: &[Vec<N>],
+                          coeffs: &[N],
+                          ncoeffs: usize,
+                          selected: &[usize]) -> Vec<Vec<N>> {
+    selected.iter().map(|&j| {
+        let mut res = vec![N::zero(); vecs[0].len()];
+        for (i, vec) in vecs.iter().enumerate() {
+            let coeff = coeffs[i * ncoeffs + j];
+            for (ri, &vi) in res.iter_mut().zip(vec) {
+                *ri = *ri + coeff * vi;
+            }
+        }
+        res
+    }).collect()
+}
+
+/// Compute the linear combinations `sum_i coeffs[i * dim + j] vecs[i]`
+/// for each selected `j`, where `dim` is the number of vectors.
+fn combine<N: Float>(vecs: &[Vec<N>],
+                     coeffs: &[N],
+                     selected: &[usize]) -> Vec<Vec<N>> {
+    combine_rect(vecs, coeffs, vecs.len(), selected)
+}
+
+/// Thick restart Lanczos iteration for the symmetric definite problem
+/// `A x = lambda B x`, with `B` positive definite.
+///
+/// `solve_b` should return false if it failed to solve with `B`.
+fn thick_restart_lanczos<N, A, BA, BS>(dim: usize,
+                                       k: usize,
+                                       which: Which,
+                                       tol: N,
+                                       apply_a: A,
+                                       apply_b: BA,
+                                       solve_b: BS) -> SpRes<EigenPairs<N>>
+where N: Float,
+      A: Fn(&[N], &mut [N]),
+      BA: Fn(&[N], &mut [N]),
+      BS: Fn(&[N], &mut [N]) -> bool,
+{
+    assert!(k > 0 && k <= dim, "invalid number of eigenpairs requested");
+    let max_size = cmp::min(dim, cmp::max(2 * k + 1, k + 20));
+    let mut basis = Basis {
+        vecs: Vec::with_capacity(max_size + 1),
+        b_vecs: Vec::with_capacity(max_size + 1),
+    };
+    let mut seed = 0;
+    while !basis.push(pseudo_random(dim, seed), &apply_b) {
+        seed += 1;
+    }
+    // images of the basis vectors by A
+    let mut images: Vec<Vec<N>> = Vec::with_capacity(max_size);
+    let mut cand = vec![N::zero(); dim];
+    for _ in 0..MAX_RESTARTS {
+        // expand the Krylov subspace of B^-1 A
+        while images.len() < max_size {
+            let j = images.len();
+            if j == basis.len() {
+                if !solve_b(&images[j - 1], &mut cand) {
+                    return Err(SprsError::NotConverged);
+                }
+                if !basis.push(cand.clone(), &apply_b) {
+                    // invariant subspace found, continue in a new direction
+                    let mut pushed = false;
+                    for _ in 0..3 {
+                        seed += 1;
+                        if basis.push(pseudo_random(dim, seed), &apply_b) {
+                            pushed = true;
+                            break;
+                        }
+                    }
+                    if !pushed {
+                        break;
+                    }
+                }
+            }
+            let mut image = vec![N::zero(); dim];
+            apply_a(&basis.vecs[j], &mut image);
+            images.push(image);
+        }
+
+        // Rayleigh-Ritz on the current subspace
+        let size = images.len();
+        let mut proj = vec![N::zero(); size * size];
+        for i in 0..size {
+            for j in 0..size {
+                proj[i * size + j] = dot(&basis.vecs[i], &images[j]);
+            }
+        }
+        let two = N::one() + N::one();
+        for i in 0..size {
+            for j in (i + 1)..size {
+                let sym = (proj[i * size + j] + proj[j * size + i]) / two;
+                proj[i * size + j] = sym;
+                proj[j * size + i] = sym;
+            }
+        }
+        let (ritz_vals, ritz_coeffs) = symmetric_eigen(size, proj);
+        let mut order: Vec<usize> = (0..size).collect();
+        order.sort_by(|&i, &j| which.order(ritz_vals[i], ritz_vals[j]));
+        let scale = ritz_vals.iter()
+                             .fold(N::zero(), |acc, v| acc.max(v.abs()));
+
+        let wanted = &order[..k];
+        let ritz_vecs = combine(&basis.vecs[..size], &ritz_coeffs, wanted);
+        let ritz_b_vecs = combine(&basis.b_vecs[..size], &ritz_coeffs, wanted);
+        let ritz_images = combine(&images, &ritz_coeffs, wanted);
+        let converged = wanted.iter().enumerate().all(|(ind, &j)| {
+            let res_norm = ritz_images[ind].iter()
+                                           .zip(&ritz_b_vecs[ind])
+                                           .fold(N::zero(), |acc, (&a, &b)| {
+                let r = a - ritz_vals[j] * b;
+                acc + r * r
+            }).sqrt();
+            res_norm <= tol * scale
+        });
+        if converged || size == dim {
+            let mut vectors = Array2::zeros((dim, k));
+            for (col, vec) in ritz_vecs.iter().enumerate() {
+                for (row, &val) in vec.iter().enumerate() {
+                    vectors[[row, col]] = val;
+                }
+            }
+            return Ok(EigenPairs {
+                values: wanted.iter().map(|&j| ritz_vals[j]).collect(),
+                vectors: vectors,
+            });
+        }
+
+        // continuation vector, orthogonal to the current subspace
+        basis.vecs.truncate(size);
+        basis.b_vecs.truncate(size);
+        if !solve_b(&images[size - 1], &mut cand) {
+            return Err(SprsError::NotConverged);
+        }
+        if !basis.push(cand.clone(), &apply_b) {
+            seed += 1;
+            while !basis.push(pseudo_random(dim, seed), &apply_b) {
+                seed += 1;
+            }
+        }
+        let next = basis.pop().unwrap();
+
+        // restart with the most wanted Ritz vectors
+        let nb_kept = cmp::min(k + (size - k) / 2, size - 1);
+        let kept = &order[..nb_kept];
+        basis.vecs = combine(&basis.vecs, &ritz_coeffs, kept);
+        basis.b_vecs = combine(&basis.b_vecs, &ritz_coeffs, kept);
+        images = combine(&images, &ritz_coeffs, kept);
+        basis.vecs.push(next.0);
+        basis.b_vecs.push(next.1);
+    }
+    Err(SprsError::NotConverged)
+}
+
+/// Compute `k` eigenpairs of the symmetric operator `op`, selected by
+/// `which`, using the thick restart Lanczos method.
+///
+/// An eigenpair `(lambda, x)` is considered converged when
+/// `||op x - lambda x|| <= tol * |lambda_max|`, where `lambda_max` is the
+/// largest eigenvalue magnitude found. Eigenvectors are normalized.
+///
+/// Convergence is fastest for eigenvalues at the ends of the spectrum;
+/// eigenvalues of smallest magnitude can be slow to converge when they lie
+/// inside the spectrum.
+///
+/// # Errors
+///
+/// `NotConverged` if the eigenpairs did not converge in the maximum number
+/// of restarts.
+///
+/// # Panics
+///
+/// - if the operator is not square
+/// - unless `0 < k <= op.rows()`
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::linalg::{eigsh, Which};
+/// let mat = CsMat::new((3, 3),
+///                      vec![0, 2, 5, 7],
+///                      vec![0, 1, 0, 1, 2, 1, 2],
+///                      vec![2., -1., -1., 2., -1., -1., 2.]);
+/// let eig = eigsh(&mat, 1, Which::LargestAlgebraic, 1e-10).unwrap();
+/// assert!((eig.values[0] - (2. + 2f64.sqrt())).abs() < 1e-8);
+/// ```
+pub fn eigsh<N, Op>(op: &Op, k: usize, which: Which, tol: N)
+                    -> SpRes<EigenPairs<N>>
+where N: Float,
+      Op: LinearOperator<N> + ?Sized,
+{
+    assert_eq!(op.rows(), op.cols(), "Non square operator");
+    let identity = |x: &[N], y: &mut [N]| y.copy_from_slice(x);
+    thick_restart_lanczos(op.rows(),
+                          k,
+                          which,
+                          tol,
+                          |x: &[N], y: &mut [N]| op.apply(x, y),
+                          identity,
+                          |x: &[N], y: &mut [N]| {
+                              y.copy_from_slice(x);
+                              true
+                          })
+}
+
+/// Compute `k` eigenpairs of the generalized symmetric definite problem
+/// `op x = lambda b x`, selected by `which`, using the thick restart
+/// Lanczos method on `b^-1 op`.
+///
+/// `b` should be a symmetric positive definite matrix, eg a mass matrix.
+/// Systems with `b` are solved iteratively with the Jacobi preconditioned
+/// conjugate gradient, which is efficient when `b` is well conditioned.
+///
+/// An eigenpair `(lambda, x)` is considered converged when
+/// `||op x - lambda b x|| <= tol * |lambda_max|`, where `lambda_max` is the
+/// largest eigenvalue magnitude found. Eigenvectors are normalized such
+/// that `x^T b x = 1`.
+///
+/// # Errors
+///
+/// - `SingularMatrix` if `b` has a zero diagonal entry
+/// - `NotConverged` if the eigenpairs or the solves with `b` did not
+///   converge
+///
+/// # Panics
+///
+/// - if `op` or `b` is not square or their dimensions differ
+/// - unless `0 < k <= op.rows()`
+pub fn eigsh_generalized<N, Op, I, IpS, IS, DS>(op: &Op,
+                                                b: &CsMatBase<N, I, IpS,
+                                                              IS, DS>,
+                                                k: usize,
+                                                which: Which,
+                                                tol: N)
+                                                -> SpRes<EigenPairs<N>>
+where N: Float,
+      Op: LinearOperator<N> + ?Sized,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    let dim = op.rows();
+    assert_eq!(dim, op.cols(), "Non square operator");
+    if b.rows() != dim || b.cols() != dim {
+        panic!("Dimension mismatch");
+    }
+    let precond = try!(Jacobi::new(b));
+    let inner_tol = N::epsilon() * N::from(1e3).unwrap();
+    thick_restart_lanczos(dim,
+                          k,
+                          which,
+                          tol,
+                          |x: &[N], y: &mut [N]| op.apply(x, y),
+                          |x: &[N], y: &mut [N]| b.apply(x, y),
+                          |x: &[N], y: &mut [N]| {
+                              for yi in y.iter_mut() {
+                                  *yi = N::zero();
+                              }
+                              pcg(b, &precond, x, y, inner_tol, 10 * dim + 100)
+                                  .converged
+                          })
+}
+
+/// Krylov-Schur iteration for a general real operator.
+///
+/// The subspace is restarted with the invariant subspace of the projected
+/// matrix associated to the most wanted Ritz values, computed from its
+/// ordered Schur form. Complex Ritz values are kept along with their
+/// conjugate, so that this subspace has a real basis and all computations
+/// on vectors stay in real arithmetic.
+fn krylov_schur<N, A>(dim: usize,
+                      k: usize,
+                      which: Which,
+                      tol: N,
+                      apply: A) -> SpRes<ComplexEigenPairs<N>>
+where N: Float,
+      A: Fn(&[N], &mut [N]),
+{
+    assert!(k > 0 && k <= dim, "invalid number of eigenpairs requested");
+    let identity = |x: &[N], y: &mut [N]| y.copy_from_slice(x);
+    let zero = Complex::new(N::zero(), N::zero());
+    let max_size = cmp::min(dim, cmp::max(2 * k + 1, k + 20));
+    let mut basis = Basis {
+        vecs: Vec::with_capacity(max_size + 1),
+        b_vecs: Vec::with_capacity(max_size + 1),
+    };
+    let mut seed = 0;
+    while !basis.push(pseudo_random(dim, seed), &identity) {
+        seed += 1;
+    }
+    let mut images: Vec<Vec<N>> = Vec::with_capacity(max_size);
+    for _ in 0..MAX_RESTARTS {
+        // expand the Krylov subspace
+        while images.len() < max_size {
+            let j = images.len();
+            if j == basis.len() {
+                let cand = images[j - 1].clone();
+                if !basis.push(cand, &identity) {
+                    // invariant subspace found, continue in a new direction
+                    let mut pushed = false;
+                    for _ in 0..3 {
+                        seed += 1;
+                        if basis.push(pseudo_random(dim, seed), &identity) {
+                            pushed = true;
+                            break;
+                        }
+                    }
+                    if !pushed {
+                        break;
+                    }
+                }
+            }
+            let mut image = vec![N::zero(); dim];
+            apply(&basis.vecs[j], &mut image);
+            images.push(image);
+        }
+
+        // Rayleigh-Ritz on the current subspace
+        let size = images.len();
+        let mut proj = vec![N::zero(); size * size];
+        for i in 0..size {
+            for j in 0..size {
+                proj[i * size + j] = dot(&basis.vecs[i], &images[j]);
+            }
+        }
+        let (ritz_vals, ritz_coeffs) = try!(general_eigen(size,
+                                                           proj.clone()));
+        let mut order: Vec<usize> = (0..size).collect();
+        order.sort_by(|&i, &j| {
+            which.order_complex(ritz_vals[i], ritz_vals[j])
+        });
+        let scale = ritz_vals.iter()
+                             .fold(N::zero(), |acc, v| acc.max(v.norm()));
+
+        let wanted = &order[..k];
+        let mut ritz_vecs = Vec::with_capacity(k);
+        let mut converged = true;
+        for &j in wanted {
+            let mut vec = vec![zero; dim];
+            let mut image = vec![zero; dim];
+            for i in 0..size {
+                let coeff = ritz_coeffs[i * size + j];
+                for ((v, im), (&b, &w)) in vec.iter_mut()
+                                              .zip(image.iter_mut())
+                                              .zip(basis.vecs[i].iter()
+                                                   .zip(&images[i])) {
+                    *v = *v + coeff * b;
+                    *im = *im + coeff * w;
+                }
+            }
+            let res_norm = image.iter().zip(&vec).fold(N::zero(), |acc, pair| {
+                acc + (*pair.0 - ritz_vals[j] * *pair.1).norm_sqr()
+            }).sqrt();
+            converged = converged && res_norm <= tol * scale;
+            ritz_vecs.push(vec);
+        }
+        if converged || size == dim {
+            let mut vectors = Array2::from_elem((dim, k), zero);
+            for (col, vec) in ritz_vecs.iter().enumerate() {
+                for (row, &val) in vec.iter().enumerate() {
+                    vectors[[row, col]] = val;
+                }
+            }
+            return Ok(ComplexEigenPairs {
+                values: wanted.iter().map(|&j| ritz_vals[j]).collect(),
+                vectors: vectors,
+            });
+        }
+
+        // continuation vector, orthogonal to the current subspace
+        basis.vecs.truncate(size);
+        basis.b_vecs.truncate(size);
+        if !basis.push(images[size - 1].clone(), &identity) {
+            seed += 1;
+            while !basis.push(pseudo_random(dim, seed), &identity) {
+                seed += 1;
+            }
+        }
+        let next = basis.pop().unwrap();
+
+        // select the most wanted Ritz values, along with their conjugates
+        let nb_wanted = cmp::min(k + (size - k) / 2, size - 1);
+        let (mut schur, mut schur_vecs) = try!(complex_schur(size, proj));
+        let schur_vals: Vec<_> = (0..size).map(|i| schur[i * size + i])
+                                          .collect();
+        let mut schur_order: Vec<usize> = (0..size).collect();
+        schur_order.sort_by(|&i, &j| {
+            which.order_complex(schur_vals[i], schur_vals[j])
+        });
+        let mut select = vec![false; size];
+        let mut nb_selected = 0;
+        for &j in &schur_order {
+            if select[j] {
+                continue;
+            }
+            let mut group = vec![j];
+            if schur_vals[j].im.abs() > N::epsilon().sqrt() * scale {
+                let conj = schur_vals[j].conj();
+                let partner = (0..size).filter(|&i| i != j && !select[i])
+                                       .fold(None, |best: Option<usize>, i| {
+                    match best {
+                        Some(b) if (schur_vals[b] - conj).norm()
+                                   <= (schur_vals[i] - conj).norm() => best,
+                        _ => Some(i),
+                    }
+                });
+                group.extend(partner);
+            }
+            if nb_selected + group.len() > size - 1 {
+                break;
+            }
+            for i in group {
+                select[i] = true;
+                nb_selected += 1;
+            }
+            if nb_selected >= nb_wanted {
+                break;
+            }
+        }
+        // the leading Schur vectors span the wanted invariant subspace,
+        // which is closed under conjugation: its orthogonal projector is
+        // real, and gives a real orthonormal basis of the subspace
+        reorder_schur(size, &mut schur, &mut schur_vecs, &select);
+        let mut projector = vec![N::zero(); size * size];
+        for i in 0..size {
+            for j in 0..size {
+                projector[i * size + j] = (0..nb_selected).fold(N::zero(),
+                                                                |acc, l| {
+                    let prod = schur_vecs[i * size + l]
+                               * schur_vecs[j * size + l].conj();
+                    acc + prod.re
+                });
+            }
+        }
+        let (proj_vals, proj_vecs) = symmetric_eigen(size, projector);
+        let half = N::one() / (N::one() + N::one());
+        let mut kept = Basis {
+            vecs: Vec::with_capacity(nb_selected),
+            b_vecs: Vec::with_capacity(nb_selected),
+        };
+        for (j, &val) in proj_vals.iter().enumerate() {
+            if val <= half {
+                continue;
+            }
+            let vec = (0..size).map(|i| proj_vecs[i * size + j]).collect();
+            kept.push(vec, &identity);
+        }
+        let coeffs: Vec<N> = (0..size).flat_map(|i| {
+            kept.vecs.iter().map(move |vec| vec[i])
+        }).collect();
+        let nb_kept = kept.len();
+        let selected: Vec<usize> = (0..nb_kept).collect();
+        basis.vecs = combine_rect(&basis.vecs, &coeffs, nb_kept, &selected);
+        basis.b_vecs = basis.vecs.clone();
+        images = combine_rect(&images, &coeffs, nb_kept, &selected);
+        basis.vecs.push(next.0);
+        basis.b_vecs.push(next.1);
+    }
+    Err(SprsError::NotConverged)
+}
+
+/// Compute `k` eigenpairs of the general square operator `op`, selected by
+/// `which`, using the Krylov-Schur restarted Arnoldi method.
+///
+/// An eigenpair `(lambda, x)` is considered converged when
+/// `||op x - lambda x|| <= tol * |lambda_max|`, where `lambda_max` is the
+/// largest eigenvalue magnitude found. Eigenvectors have a unit norm.
+///
+/// Eigenvalues of smallest magnitude usually converge slowly, prefer
+/// `eigs_shift_invert` to compute them.
+///
+/// # Errors
+///
+/// `NotConverged` if the eigenpairs did not converge in the maximum number
+/// of restarts.
+///
+/// # Panics
+///
+/// - if the operator is not square
+/// - unless `0 < k <= op.rows()`
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::linalg::{eigs, Which};
+/// // a rotation by 90 degrees scaled by 2, and a unit eigenvalue
+/// let mat: CsMat<f64> = CsMat::new((3, 3),
+///                                   vec![0, 1, 2, 3],
+///                                   vec![1, 0, 2],
+///                                   vec![-2., 2., 1.]);
+/// let eig = eigs(&mat, 2, Which::LargestMagnitude, 1e-10).unwrap();
+/// for val in &eig.values {
+///     assert!((val.re).abs() < 1e-10);
+///     assert!((val.im.abs() - 2.).abs() < 1e-10);
+/// }
+/// ```
+pub fn eigs<N, Op>(op: &Op, k: usize, which: Which, tol: N)
+                   -> SpRes<ComplexEigenPairs<N>>
+where N: Float,
+      Op: LinearOperator<N> + ?Sized,
+{
+    assert_eq!(op.rows(), op.cols(), "Non square operator");
+    krylov_schur(op.rows(), k, which, tol, |x: &[N], y: &mut [N]| {
+        op.apply(x, y)
+    })
+}
+
+/// Compute the `k` eigenpairs of a general square matrix `A` whose
+/// eigenvalues are closest to the shift `sigma`, in shift-invert mode.
+///
+/// `solver` should solve systems with `A - sigma I`, typically through a
+/// factorization of this matrix. The eigenvalues `mu` of largest magnitude
+/// of `(A - sigma I)^-1` are computed with the Krylov-Schur method, and
+/// transformed back as `lambda = sigma + 1 / mu`. Eigenvalues are sorted by
+/// increasing distance to `sigma`.
+///
+/// The tolerance `tol` applies to the shifted and inverted problem.
+///
+/// # Errors
+///
+/// `NotConverged` if the eigenpairs did not converge in the maximum number
+/// of restarts.
+///
+/// # Panics
+///
+/// Unless `0 < k <= solver.dim()`
+pub fn eigs_shift_invert<N, S>(solver: &S, sigma: N, k: usize, tol: N)
+                               -> SpRes<ComplexEigenPairs<N>>
+where N: Float,
+      S: LinearSolver<N> + ?Sized,
+{
+    let mut eig = try!(krylov_schur(solver.dim(),
+                                    k,
+                                    Which::LargestMagnitude,
+                                    tol,
+                                    |x: &[N], y: &mut [N]| {
+        solver.solve(x, y)
+    }));
+    let one = Complex::new(N::one(), N::zero());
+    for val in eig.values.iter_mut() {
+        *val = one / *val + sigma;
+    }
+    Ok(eig)
+}
+
+/// Compute the eigenpair of largest magnitude of a square operator with the
+/// power iteration, performing at most `max_iter` products with `op`.
+///
+/// The eigenvalue is estimated by the Rayleigh quotient, and the eigenpair
+/// `(lambda, x)` is considered converged when
+/// `||op x - lambda x|| <= tol * |lambda|`. The convergence is linear, with
+/// a rate given by the ratio of the two largest eigenvalue magnitudes, so
+/// the method is only practical when the dominant eigenvalue is well
+/// separated, eg for the Perron eigenvector of a nonnegative matrix. The
+/// dominant eigenvalue must be real and unique.
+///
+/// # Errors
+///
+/// `NotConverged` if the eigenpair did not converge in `max_iter`
+/// iterations.
+///
+/// # Panics
+///
+/// If the operator is not square.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::linalg::power_iteration;
+/// let mat: CsMat<f64> = CsMat::new((2, 2),
+///                                   vec![0, 2, 4],
+///                                   vec![0, 1, 0, 1],
+///                                   vec![2., 1., 1., 2.]);
+/// let eig = power_iteration(&mat, 1e-10, 100).unwrap();
+/// assert!((eig.value - 3.).abs() < 1e-10);
+/// let expected = 0.5f64.sqrt();
+/// assert!(eig.vector.iter().all(|&x| (x - expected).abs() < 1e-8));
+/// ```
+pub fn power_iteration<N, Op>(op: &Op, tol: N, max_iter: usize)
+                              -> SpRes<DominantEigenPair<N>>
+where N: Float,
+      Op: LinearOperator<N> + ?Sized,
+{
+    assert_eq!(op.rows(), op.cols(), "Non square operator");
+    let mut x: Vec<N> = pseudo_random(op.rows(), 0);
+    let x_norm = dot(&x, &x).sqrt();
+    for xi in &mut x {
+        *xi = *xi / x_norm;
+    }
+    let mut ax = vec![N::zero(); op.rows()];
+    for iter in 0..max_iter {
+        op.apply(&x, &mut ax);
+        let value = dot(&x, &ax);
+        let res_norm = x.iter().zip(&ax).fold(N::zero(), |acc, (&xi, &axi)| {
+            let res = axi - value * xi;
+            acc + res * res
+        }).sqrt();
+        if res_norm <= tol * value.abs() {
+            // the sign of an eigenvector is arbitrary, pick a positive
+            // largest entry
+            let largest = x.iter().fold(N::zero(), |acc, &xi| {
+                if xi.abs() > acc.abs() { xi } else { acc }
+            });
+            if largest < N::zero() {
+                for xi in &mut x {
+                    *xi = -*xi;
+                }
+            }
+            return Ok(DominantEigenPair {
+                value: value,
+                vector: x,
+                iterations: iter,
+            });
+        }
+        let ax_norm = dot(&ax, &ax).sqrt();
+        if ax_norm.is_zero() {
+            break;
+        }
+        for (xi, &axi) in x.iter_mut().zip(&ax) {
+            *xi = axi / ax_norm;
+        }
+    }
+    Err(SprsError::NotConverged)
+}
+
+#[cfg(test)]
+mod test {
+    use std::f64::consts::PI;
+    use num_complex::Complex;
+    use sparse::{CsMat, TriMat};
+    use sparse::linalg::dense::DenseLu;
+    use test_data::laplacian_1d;
+    use errors::SprsError;
+    use super::{eigsh, eigsh_generalized, eigs, eigs_shift_invert, Which,
+                power_iteration};
+
+    fn laplacian_eigenvalue(n: usize, j: usize) -> f64 {
+        2. - 2. * (j as f64 * PI / (n as f64 + 1.)).cos()
+    }
+
+    #[test]
+    fn largest_and_smallest() {
+        let n = 100;
+        let mat = laplacian_1d(n);
+        let eig = eigsh(&mat, 4, Which::LargestAlgebraic, 1e-10).unwrap();
+        for (j, val) in eig.values.iter().enumerate() {
+            assert!((val - laplacian_eigenvalue(n, n - j)).abs() < 1e-8);
+        }
+        let eig = eigsh(&mat, 3, Which::SmallestAlgebraic, 1e-10).unwrap();
+        for (j, val) in eig.values.iter().enumerate() {
+            assert!((val - laplacian_eigenvalue(n, j + 1)).abs() < 1e-8);
+        }
+        // eigenvectors are orthonormal eigenvectors
+        for j in 0..3 {
+            let x = eig.vectors.column(j).to_owned();
+            let ax = &mat * &x;
+            let res = &ax - &(&x * eig.values[j]);
+            assert!(res.dot(&res).sqrt() < 1e-8);
+            for l in 0..3 {
+                let dot = x.dot(&eig.vectors.column(l));
+                let expected = if j == l { 1. } else { 0. };
+                assert!((dot - expected).abs() < 1e-10);
+            }
+        }
+    }
+
+    #[test]
+    fn magnitude_selection() {
+        let diag = [-7., 1., 3., -0.5, 6.];
+        let mat: CsMat<f64> = CsMat::new((5, 5),
+                                         vec![0, 1, 2, 3, 4, 5],
+                                         vec![0, 1, 2, 3, 4],
+                                         diag.to_vec());
+        let eig = eigsh(&mat, 2, Which::LargestMagnitude, 1e-12).unwrap();
+        assert!((eig.values[0] + 7.).abs() < 1e-10);
+        assert!((eig.values[1] - 6.).abs() < 1e-10);
+        let eig = eigsh(&mat, 2, Which::SmallestMagnitude, 1e-12).unwrap();
+        assert!((eig.values[0] + 0.5).abs() < 1e-10);
+        assert!((eig.values[1] - 1.).abs() < 1e-10);
+        let eig = eigsh(&mat, 5, Which::SmallestAlgebraic, 1e-12).unwrap();
+        let expected = [-7., -0.5, 1., 3., 6.];
+        for (val, exp) in eig.values.iter().zip(&expected) {
+            assert!((val - exp).abs() < 1e-10);
+        }
+    }
+
+    #[test]
+    fn generalized_problem() {
+        let n = 60;
+        let a = laplacian_1d(n);
+        let b_diag: Vec<f64> = (0..n).map(|i| 1. + (i % 3) as f64).collect();
+        let b = CsMat::new((n, n),
+                           (0..n + 1).collect(),
+                           (0..n).collect(),
+                           b_diag.clone());
+        let eig = eigsh_generalized(&a, &b, 3, Which::SmallestAlgebraic,
+                                    1e-10).unwrap();
+        for j in 0..3 {
+            let x = eig.vectors.column(j).to_owned();
+            let ax = &a * &x;
+            let bx = &b * &x;
+            let res = &ax - &(&bx * eig.values[j]);
+            assert!(res.dot(&res).sqrt() < 1e-8);
+            assert!((x.dot(&bx) - 1.).abs() < 1e-10);
+        }
+
+        // scaling the identity scales the eigenvalues
+        let two_id = CsMat::new((n, n),
+                                (0..n + 1).collect(),
+                                (0..n).collect(),
+                                vec![2.; n]);
+        let eig = eigsh_generalized(&a, &two_id, 2, Which::LargestAlgebraic,
+                                    1e-10).unwrap();
+        for (j, val) in eig.values.iter().enumerate() {
+            assert!((val - laplacian_eigenvalue(n, n - j) / 2.).abs() < 1e-8);
+        }
+    }
+
+    /// Non symmetric tridiagonal matrix tridiag(-1 - c, 2, -1 + c), whose
+    /// eigenvalues are 2 + 2 sqrt(1 - c^2) cos(j pi / (n + 1))
+    fn convection_diffusion(n: usize, c: f64) -> CsMat<f64> {
+        let mut tri = TriMat::new((n, n));
+        for i in 0..n {
+            tri.add_triplet(i, i, 2.);
+            if i + 1 < n {
+                tri.add_triplet(i, i + 1, -1. + c);
+                tri.add_triplet(i + 1, i, -1. - c);
+            }
+        }
+        tri.to_csr()
+    }
+
+    fn convection_diffusion_eigenvalue(n: usize, c: f64, j: usize) -> f64 {
+        let angle = j as f64 * PI / (n as f64 + 1.);
+        2. + 2. * (1. - c * c).sqrt() * angle.cos()
+    }
+
+    #[test]
+    fn eigs_real_spectrum() {
+        let n = 50;
+        let mat = convection_diffusion(n, 0.1);
+        let eig = eigs(&mat, 3, Which::LargestAlgebraic, 1e-10).unwrap();
+        for (j, val) in eig.values.iter().enumerate() {
+            let expected = convection_diffusion_eigenvalue(n, 0.1, j + 1);
+            assert!((val.re - expected).abs() < 1e-7);
+            assert!(val.im.abs() < 1e-7);
+        }
+    }
+
+    #[test]
+    fn eigs_complex_pairs() {
+        // 2x2 blocks [[a, -b], [b, a]] with eigenvalues a +- ib
+        let nb_blocks = 30;
+        let mut tri = TriMat::new((2 * nb_blocks, 2 * nb_blocks));
+        for blk in 0..nb_blocks {
+            let a = 1. + 0.1 * blk as f64;
+            let b = 0.5 + 0.05 * blk as f64;
+            let i = 2 * blk;
+            tri.add_triplet(i, i, a);
+            tri.add_triplet(i, i + 1, -b);
+            tri.add_triplet(i + 1, i, b);
+            tri.add_triplet(i + 1, i + 1, a);
+            // couple the blocks to make the matrix non normal
+            if i + 2 < 2 * nb_blocks {
+                tri.add_triplet(i, i + 2, 0.3);
+            }
+        }
+        let mat: CsMat<f64> = tri.to_csr();
+        let eig = eigs(&mat, 4, Which::LargestMagnitude, 1e-10).unwrap();
+        let last = nb_blocks - 1;
+        let expected = [Complex::new(1. + 0.1 * last as f64,
+                                     0.5 + 0.05 * last as f64),
+                        Complex::new(1. + 0.1 * (last - 1) as f64,
+                                     0.5 + 0.05 * (last - 1) as f64)];
+        for val in &eig.values {
+            assert!(expected.iter().any(|exp| {
+                (val - exp).norm() < 1e-8 || (val - exp.conj()).norm() < 1e-8
+            }));
+        }
+        for (j, val) in eig.values.iter().enumerate() {
+            let x = eig.vectors.column(j).to_owned();
+            let mut ax = vec![Complex::new(0., 0.); 2 * nb_blocks];
+            for (row, vec) in mat.outer_iterator().enumerate() {
+                for (col, &a) in vec.iter() {
+                    ax[row] = ax[row] + x[col] * a;
+                }
+            }
+            let res: f64 = ax.iter().zip(x.iter()).map(|(&axi, &xi)| {
+                (axi - val * xi).norm_sqr()
+            }).sum();
+            assert!(res.sqrt() < 1e-8);
+        }
+    }
+
+    #[test]
+    fn eigs_shift_invert_interior() {
+        let n = 40;
+        let c = 0.1;
+        let mat = convection_diffusion(n, c);
+        let sigma = 1.;
+        let mut dense = vec![0.; n * n];
+        for (row, vec) in mat.outer_iterator().enumerate() {
+            for (col, &val) in vec.iter() {
+                dense[row * n + col] = val;
+            }
+            dense[row * n + row] -= sigma;
+        }
+        let lu = DenseLu::factorize(n, dense).unwrap();
+        let eig = eigs_shift_invert(&lu, sigma, 2, 1e-10).unwrap();
+        let mut expected: Vec<f64> = (1..n + 1).map(|j| {
+            convection_diffusion_eigenvalue(n, c, j)
+        }).collect();
+        expected.sort_by(|a, b| {
+            (a - sigma).abs().partial_cmp(&(b - sigma).abs()).unwrap()
+        });
+        for (val, exp) in eig.values.iter().zip(&expected) {
+            assert!((val.re - exp).abs() < 1e-8);
+            assert!(val.im.abs() < 1e-8);
+        }
+    }
+
+    #[test]
+    fn dominant_eigenpair() {
+        // the eigenvalues of the laplacian are well separated at the top of
+        // the spectrum for a small matrix
+        let n = 5;
+        let mat = laplacian_1d(n);
+        let eig = power_iteration(&mat, 1e-10, 1000).unwrap();
+        assert!((eig.value - laplacian_eigenvalue(n, n)).abs() < 1e-12);
+        let x = ::ndarray::arr1(&eig.vector);
+        let res = &(&mat * &x) - &(&x * eig.value);
+        assert!(res.dot(&res).sqrt() <= 1e-10 * eig.value);
+        assert!((x.dot(&x) - 1.).abs() < 1e-12);
+
+        // too few iterations
+        let res = power_iteration(&mat, 1e-10, 3);
+        assert_eq!(res, Err(SprsError::NotConverged));
+    }
+}
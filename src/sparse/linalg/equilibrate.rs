@@ -0,0 +1,206 @@
+///! Equilibration of sparse matrices
+///!
+///! Badly scaled matrices, eg coming from equations expressed in different
+///! units, can be needlessly ill conditioned, which hurts the accuracy of
+///! factorizations and the convergence of Krylov methods. Equilibration
+///! computes diagonal scalings `Dr` and `Dc` such that all the rows and
+///! columns of `Dr A Dc` have an infinity norm close to one. The system
+///! `A x = b` is then solved as `(Dr A Dc) y = Dr b`, with `x = Dc y`.
+
+use std::ops::Deref;
+use num_traits::Float;
+
+use indexing::SpIndex;
+use sparse::prelude::*;
+
+/// A scaled matrix `Dr A Dc` along with its scaling vectors
+#[derive(Debug, PartialEq)]
+pub struct Equilibration<N, I: SpIndex> {
+    /// The diagonal of `Dr`
+    pub row_scaling: Vec<N>,
+    /// The diagonal of `Dc`
+    pub col_scaling: Vec<N>,
+    /// The scaled matrix `Dr A Dc`, with the storage of `A`
+    pub mat: CsMatI<N, I>,
+}
+
+impl<N: Float, I: SpIndex> Equilibration<N, I> {
+    /// The right hand side `Dr b` of the scaled system
+    ///
+    /// # Panics
+    ///
+    /// If `b` does not have as many elements as the matrix has rows.
+    pub fn scale_rhs(&self, b: &[N]) -> Vec<N> {
+        if b.len() != self.row_scaling.len() {
+            panic!("Dimension mismatch");
+        }
+        b.iter().zip(&self.row_scaling).map(|(&bi, &di)| bi * di).collect()
+    }
+
+    /// The solution `x = Dc y` of the original system, given the solution
+    /// `y` of the scaled system
+    ///
+    /// # Panics
+    ///
+    /// If `y` does not have as many elements as the matrix has columns.
+    pub fn unscale_solution(&self, y: &[N]) -> Vec<N> {
+        if y.len() != self.col_scaling.len() {
+            panic!("Dimension mismatch");
+        }
+        y.iter().zip(&self.col_scaling).map(|(&yi, &di)| yi * di).collect()
+    }
+}
+
+/// Equilibrate a matrix with the iterative method of Ruiz, which scales
+/// the rows and columns until their infinity norms are within 1 percent of
+/// one, or for at most 20 iterations.
+///
+/// Rows and columns without non-zero elements are left unscaled. The
+/// scaling factors are not rounded to powers of two, so the scaled matrix
+/// can differ from the original one by rounding errors.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::linalg::equilibrate::equilibrate;
+/// let mat: CsMat<f64> = CsMat::new((2, 2), vec![0, 2, 4], vec![0, 1, 0, 1],
+///                                  vec![1e6, 2e3, 3., 4e-3]);
+/// let eq = equilibrate(&mat);
+/// for vec in eq.mat.outer_iterator() {
+///     let row_max = vec.data().iter().fold(0., |acc: f64, v| {
+///         acc.max(v.abs())
+///     });
+///     assert!((row_max - 1.).abs() < 0.01);
+/// }
+/// // the right hand side is scaled as the rows of the matrix
+/// let b = eq.scale_rhs(&[1e6, 3.]);
+/// assert_eq!(b, vec![1e6 * eq.row_scaling[0], 3. * eq.row_scaling[1]]);
+/// ```
+pub fn equilibrate<N, I, IpS, IS, DS>(mat: &CsMatBase<N, I, IpS, IS, DS>)
+                                      -> Equilibration<N, I>
+where N: Float,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    ruiz(mat, 20, N::from(0.01).unwrap())
+}
+
+/// Equilibrate a matrix with at most `max_iter` iterations of the method
+/// of Ruiz, stopping once the infinity norms of all the non-empty rows and
+/// columns are within `tol` of one.
+///
+/// Each iteration divides the rows and the columns by the square root of
+/// their infinity norm. The iteration converges linearly, with a rate of
+/// one half, and preserves the symmetry of symmetric matrices.
+pub fn ruiz<N, I, IpS, IS, DS>(mat: &CsMatBase<N, I, IpS, IS, DS>,
+                               max_iter: usize,
+                               tol: N) -> Equilibration<N, I>
+where N: Float,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    let mut scaled = mat.to_owned();
+    let mut row_scaling = vec![N::one(); mat.rows()];
+    let mut col_scaling = vec![N::one(); mat.cols()];
+    let is_csr = mat.is_csr();
+    for _ in 0..max_iter {
+        let mut row_norms = vec![N::zero(); mat.rows()];
+        let mut col_norms = vec![N::zero(); mat.cols()];
+        for (outer, vec) in scaled.outer_iterator().enumerate() {
+            for (inner, val) in vec.iter() {
+                let (row, col) = if is_csr { (outer, inner) }
+                                 else { (inner, outer) };
+                row_norms[row] = row_norms[row].max(val.abs());
+                col_norms[col] = col_norms[col].max(val.abs());
+            }
+        }
+        let converged = row_norms.iter().chain(&col_norms).all(|&norm| {
+            norm.is_zero() || (N::one() - norm).abs() <= tol
+        });
+        if converged {
+            break;
+        }
+        let factor = |norm: N| {
+            if norm.is_zero() { N::one() } else { N::one() / norm.sqrt() }
+        };
+        let row_factors: Vec<N> = row_norms.into_iter().map(&factor)
+                                                       .collect();
+        let col_factors: Vec<N> = col_norms.into_iter().map(&factor)
+                                                       .collect();
+        for (outer, mut vec) in scaled.outer_iterator_mut().enumerate() {
+            for (inner, val) in vec.iter_mut() {
+                let (row, col) = if is_csr { (outer, inner) }
+                                 else { (inner, outer) };
+                *val = *val * row_factors[row] * col_factors[col];
+            }
+        }
+        for (scaling, &factor) in row_scaling.iter_mut().zip(&row_factors) {
+            *scaling = *scaling * factor;
+        }
+        for (scaling, &factor) in col_scaling.iter_mut().zip(&col_factors) {
+            *scaling = *scaling * factor;
+        }
+    }
+    Equilibration {
+        row_scaling: row_scaling,
+        col_scaling: col_scaling,
+        mat: scaled,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sparse::{CsMat, TriMat};
+    use super::{equilibrate, ruiz};
+
+    #[test]
+    fn ruiz_equilibration() {
+        // a well conditioned matrix with badly scaled rows and columns
+        let n = 6;
+        let mut tri = TriMat::new((n + 1, n));
+        for i in 0..n {
+            let row_scale = 10f64.powi(i as i32 - 3);
+            let col_scale = 10f64.powi(2 * (i % 3) as i32);
+            tri.add_triplet(i, i, 4. * row_scale * col_scale);
+            if i + 1 < n {
+                let next_col_scale = 10f64.powi(2 * ((i + 1) % 3) as i32);
+                tri.add_triplet(i, i + 1, -row_scale * next_col_scale);
+            }
+        }
+        let mat: CsMat<f64> = tri.to_csc();
+        let eq = equilibrate(&mat);
+        let mut row_norms = vec![0f64; n + 1];
+        let mut col_norms = vec![0f64; n];
+        for (col, vec) in eq.mat.outer_iterator().enumerate() {
+            for (row, &val) in vec.iter() {
+                row_norms[row] = row_norms[row].max(val.abs());
+                col_norms[col] = col_norms[col].max(val.abs());
+                // the scaled matrix is Dr A Dc
+                let orig = *mat.get(row, col).unwrap();
+                let expected = eq.row_scaling[row] * orig
+                               * eq.col_scaling[col];
+                assert!((val - expected).abs() <= 1e-14 * val.abs());
+            }
+        }
+        assert!(eq.mat.is_csc());
+        // the empty last row is not scaled
+        assert_eq!(row_norms[n], 0.);
+        assert_eq!(eq.row_scaling[n], 1.);
+        for &norm in row_norms[..n].iter().chain(&col_norms) {
+            assert!((norm - 1.).abs() <= 0.01);
+        }
+
+        let x = eq.unscale_solution(&vec![1.; n]);
+        assert_eq!(x, eq.col_scaling);
+
+        // no iteration leaves the matrix untouched
+        let eq = ruiz(&mat, 0, 0.01);
+        assert_eq!(eq.mat, mat);
+        assert!(eq.row_scaling.iter().all(|&s| s == 1.));
+    }
+}
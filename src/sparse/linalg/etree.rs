@@ -2,6 +2,7 @@
 ///! etrees arise when considering cholesky factorization, QR factorization, ...
 
 use std::ops::{Deref, DerefMut};
+use std::vec::Vec;
 
 pub type Parent = Option<usize>;
 
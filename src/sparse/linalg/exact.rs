@@ -0,0 +1,571 @@
+///! Sparse LU factorization in exact arithmetic
+///!
+///! This factorization targets scalar types with exact arithmetic, such as
+///! the rationals of `num-rational` or those of `rug` wrapped by
+///! `rug_interop::Rational`, where any non-zero pivot is acceptable.
+///! Pivots are thus chosen to limit the fill-in rather than for numerical
+///! stability: at each step, the remaining row with the fewest non-zeros
+///! among the candidates is selected. The scalar type only needs to be
+///! `Clone`, so arbitrary precision types can be used.
+///!
+///! For floating point matrices, the absence of numerical pivoting makes
+///! this factorization unstable. `ExactLu::factorize_partial_pivoting`
+///! should then be used, which selects the pivots by magnitude.
+///!
+///! Systems with a sparse right hand side can be solved with
+///! `ExactLu::solve_sparse`, whose cost is proportional to the number of
+///! operations on the non-zeros of the solution rather than to the
+///! dimension of the system, which makes computing selected columns of the
+///! inverse affordable.
+
+use std::ops::Deref;
+
+use num_traits::{Num, Signed};
+
+use indexing::SpIndex;
+use errors::SprsError;
+use sparse::{CsMatBase, CsMatI, CsMatViewI, CsVecI, CsVecViewI};
+use sparse::linalg::LinearSolver;
+use sparse::linalg::trisolve::lsolve_csc_reach;
+use stack::{self, DStack, StackVal};
+use ::SpRes;
+
+/// Exact LU factorization `P A = L U` of a sparse square matrix.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExactLu<N, I: SpIndex> {
+    row_perm: Vec<usize>,
+    inv_row_perm: Vec<usize>,
+    lower: CsMatI<N, I>,
+    upper: CsMatI<N, I>,
+    lower_csc: CsMatI<N, I>,
+    upper_csc: CsMatI<N, I>,
+}
+
+/// Workspace for the sparse right hand side solves of `ExactLu`, which
+/// can be reused between solves to avoid allocations proportional to the
+/// dimension of the system.
+#[derive(Debug, Clone)]
+pub struct SparseSolveWorkspace<N> {
+    dstack: DStack<StackVal<usize>>,
+    visited: Vec<bool>,
+    values: Vec<N>,
+}
+
+impl<N: Num + Clone> SparseSolveWorkspace<N> {
+    /// Create a workspace for systems of dimension `dim`
+    pub fn new(dim: usize) -> SparseSolveWorkspace<N> {
+        SparseSolveWorkspace {
+            dstack: DStack::with_capacity((2 * dim).max(2)),
+            visited: vec![false; dim],
+            values: vec![N::zero(); dim],
+        }
+    }
+
+    /// The dimension of the systems this workspace is suited to
+    pub fn dim(&self) -> usize {
+        self.visited.len()
+    }
+
+    /// The pattern of the solution of a sparse triangular solve with
+    /// `tri_mat`, in topological order, from the right hand side pattern
+    /// `roots`. The workspace is left clean.
+    fn reach<I: SpIndex>(&mut self,
+                         tri_mat: CsMatViewI<N, I>,
+                         roots: &[I]) -> Vec<usize> {
+        lsolve_csc_reach(tri_mat, roots, &mut self.dstack, &mut self.visited);
+        let order: Vec<usize> = self.dstack.iter_right()
+                                           .map(stack::extract_stack_val)
+                                           .cloned()
+                                           .collect();
+        self.dstack.clear_right();
+        for &ind in &order {
+            self.visited[ind] = false;
+        }
+        order
+    }
+}
+
+/// Compute `row - factor * pivot` for rows sorted by column, dropping the
+/// leading column and the exact zeros
+fn eliminate<N>(row: &[(usize, N)],
+                pivot: &[(usize, N)],
+                factor: &N) -> Vec<(usize, N)>
+where N: Num + Clone
+{
+    let mut res = Vec::with_capacity(row.len() + pivot.len());
+    let (mut i, mut j) = (1, 1);
+    while i < row.len() || j < pivot.len() {
+        let take_row = j == pivot.len()
+                       || (i < row.len() && row[i].0 <= pivot[j].0);
+        let take_pivot = i == row.len()
+                         || (j < pivot.len() && pivot[j].0 <= row[i].0);
+        let col = if take_row { row[i].0 } else { pivot[j].0 };
+        let mut val = N::zero();
+        if take_row {
+            val = row[i].1.clone();
+            i += 1;
+        }
+        if take_pivot {
+            val = val - factor.clone() * pivot[j].1.clone();
+            j += 1;
+        }
+        if !val.is_zero() {
+            res.push((col, val));
+        }
+    }
+    res
+}
+
+/// Build a CSR matrix from rows sorted by column
+fn csr_from_rows<N, I>(dim: usize, rows: Vec<Vec<(usize, N)>>) -> CsMatI<N, I>
+where N: Clone,
+      I: SpIndex,
+{
+    let mut indptr = Vec::with_capacity(dim + 1);
+    indptr.push(I::zero());
+    let mut indices = Vec::new();
+    let mut data = Vec::new();
+    for row in rows {
+        for (col, val) in row {
+            indices.push(I::from_usize(col));
+            data.push(val);
+        }
+        indptr.push(I::from_usize(indices.len()));
+    }
+    CsMatI::new((dim, dim), indptr, indices, data)
+}
+
+/// Build a CSC matrix from rows sorted by column
+fn csc_from_rows<N, I>(dim: usize, rows: &[Vec<(usize, N)>]) -> CsMatI<N, I>
+where N: Clone,
+      I: SpIndex,
+{
+    let mut cols: Vec<Vec<(usize, N)>> = vec![Vec::new(); dim];
+    for (row, entries) in rows.iter().enumerate() {
+        for &(col, ref val) in entries {
+            cols[col].push((row, val.clone()));
+        }
+    }
+    csr_from_rows(dim, cols).transpose_into()
+}
+
+impl<N, I> ExactLu<N, I>
+where N: Num + Clone,
+      I: SpIndex,
+{
+    /// Factorize a square matrix.
+    ///
+    /// # Errors
+    ///
+    /// `SingularMatrix` if the matrix is singular.
+    ///
+    /// # Panics
+    ///
+    /// If the matrix is not square.
+    pub fn factorize<IpS, IS, DS>(mat: &CsMatBase<N, I, IpS, IS, DS>)
+                                  -> SpRes<ExactLu<N, I>>
+    where IpS: Deref<Target=[I]>,
+          IS: Deref<Target=[I]>,
+          DS: Deref<Target=[N]>,
+    {
+        ExactLu::factorize_by(mat, |row, best| row.len() < best.len())
+    }
+
+    /// Factorize a square matrix, the pivot of each step being the first
+    /// candidate row for which no other candidate is a `better_pivot`.
+    fn factorize_by<IpS, IS, DS, F>(mat: &CsMatBase<N, I, IpS, IS, DS>,
+                                    better_pivot: F)
+                                    -> SpRes<ExactLu<N, I>>
+    where IpS: Deref<Target=[I]>,
+          IS: Deref<Target=[I]>,
+          DS: Deref<Target=[N]>,
+          F: Fn(&[(usize, N)], &[(usize, N)]) -> bool,
+    {
+        if mat.rows() != mat.cols() {
+            panic!("Dimension mismatch");
+        }
+        let dim = mat.rows();
+        let mut rows: Vec<Vec<(usize, N)>> = vec![Vec::new(); dim];
+        let is_csr = mat.is_csr();
+        for (outer, vec) in mat.outer_iterator().enumerate() {
+            for (inner, val) in vec.iter() {
+                if val.is_zero() {
+                    continue;
+                }
+                if is_csr {
+                    rows[outer].push((inner, val.clone()));
+                }
+                else {
+                    rows[inner].push((outer, val.clone()));
+                }
+            }
+        }
+
+        let mut remaining: Vec<usize> = (0..dim).collect();
+        let mut multipliers: Vec<Vec<(usize, N)>> = vec![Vec::new(); dim];
+        let mut row_perm = Vec::with_capacity(dim);
+        let mut upper = Vec::with_capacity(dim);
+        for step in 0..dim {
+            // the columns before step have been eliminated, so the
+            // candidate pivots are the rows starting at column step
+            let mut pos: Option<usize> = None;
+            for (cand_pos, &row) in remaining.iter().enumerate() {
+                if rows[row].first().map_or(true, |e| e.0 != step) {
+                    continue;
+                }
+                if pos.map_or(true, |best| {
+                    better_pivot(&rows[row], &rows[remaining[best]])
+                }) {
+                    pos = Some(cand_pos);
+                }
+            }
+            let pivot_row = match pos {
+                Some(pos) => remaining.swap_remove(pos),
+                None => return Err(SprsError::SingularMatrix),
+            };
+            let pivot = ::std::mem::replace(&mut rows[pivot_row], Vec::new());
+            for &row in &remaining {
+                if rows[row].first().map_or(true, |e| e.0 != step) {
+                    continue;
+                }
+                let factor = rows[row][0].1.clone() / pivot[0].1.clone();
+                let updated = eliminate(&rows[row], &pivot, &factor);
+                rows[row] = updated;
+                multipliers[row].push((step, factor));
+            }
+            row_perm.push(pivot_row);
+            upper.push(pivot);
+        }
+        let lower: Vec<_> = row_perm.iter()
+                                    .map(|&row| ::std::mem::replace(
+                                        &mut multipliers[row], Vec::new()))
+                                    .collect();
+        let mut inv_row_perm = vec![0; dim];
+        for (k, &row) in row_perm.iter().enumerate() {
+            inv_row_perm[row] = k;
+        }
+        Ok(ExactLu {
+            lower_csc: csc_from_rows(dim, &lower),
+            upper_csc: csc_from_rows(dim, &upper),
+            lower: csr_from_rows(dim, lower),
+            upper: csr_from_rows(dim, upper),
+            row_perm: row_perm,
+            inv_row_perm: inv_row_perm,
+        })
+    }
+
+    /// The dimension of the factorized matrix
+    pub fn dim(&self) -> usize {
+        self.row_perm.len()
+    }
+
+    /// The row permutation `P`: row `k` of `P A` is row `row_perm()[k]`
+    /// of `A`.
+    pub fn row_perm(&self) -> &[usize] {
+        &self.row_perm
+    }
+
+    /// The strictly lower triangular part of the unit lower triangular
+    /// factor `L`, in CSR storage
+    pub fn lower(&self) -> &CsMatI<N, I> {
+        &self.lower
+    }
+
+    /// The upper triangular factor `U`, in CSR storage
+    pub fn upper(&self) -> &CsMatI<N, I> {
+        &self.upper
+    }
+
+    /// Solve `A x = rhs`
+    ///
+    /// # Panics
+    ///
+    /// If `rhs` does not have length `self.dim()`.
+    pub fn solve_vec(&self, rhs: &[N]) -> Vec<N> {
+        if rhs.len() != self.dim() {
+            panic!("Dimension mismatch");
+        }
+        let mut x: Vec<N> = self.row_perm.iter()
+                                         .map(|&row| rhs[row].clone())
+                                         .collect();
+        for (k, row) in self.lower.outer_iterator().enumerate() {
+            let mut val = x[k].clone();
+            for (col, l) in row.iter() {
+                val = val - l.clone() * x[col].clone();
+            }
+            x[k] = val;
+        }
+        for (k, row) in self.upper.outer_iterator().enumerate().rev() {
+            let mut val = x[k].clone();
+            let mut diag = N::one();
+            for (col, u) in row.iter() {
+                if col == k {
+                    diag = u.clone();
+                }
+                else {
+                    val = val - u.clone() * x[col].clone();
+                }
+            }
+            x[k] = val / diag;
+        }
+        x
+    }
+
+    /// Solve `A x = rhs` for a sparse right hand side, returning a sparse
+    /// solution.
+    ///
+    /// The non-zero pattern of the solution is first found as the set of
+    /// nodes reachable from the pattern of `rhs` in the graphs of the
+    /// factors, then only these nodes are updated, so the cost of the
+    /// solve is proportional to the number of operations on the non-zeros
+    /// of the solution, not to the dimension of the system. This allows
+    /// computing columns of the inverse with few non-zeros cheaply.
+    ///
+    /// This allocates workspaces of the dimension of the system, when
+    /// performing many solves `solve_sparse_with_workspace` should be
+    /// preferred.
+    ///
+    /// # Panics
+    ///
+    /// If `rhs` does not have dimension `self.dim()`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::{CsMat, CsVec};
+    /// use sprs::linalg::ExactLu;
+    /// // block diagonal matrix, whose inverse is block diagonal as well
+    /// let mat = CsMat::new((4, 4), vec![0, 2, 4, 5, 6],
+    ///                      vec![0, 1, 0, 1, 2, 3],
+    ///                      vec![2., 1., 1., 1., 4., 1.]);
+    /// let lu = ExactLu::factorize(&mat).unwrap();
+    /// let e0 = CsVec::new(4, vec![0], vec![1.]);
+    /// let x = lu.solve_sparse(e0.view());
+    /// assert_eq!(x, CsVec::new(4, vec![0, 1], vec![1., -1.]));
+    /// ```
+    pub fn solve_sparse(&self, rhs: CsVecViewI<N, I>) -> CsVecI<N, I> {
+        let mut workspace = SparseSolveWorkspace::new(self.dim());
+        self.solve_sparse_with_workspace(rhs, &mut workspace)
+    }
+
+    /// Solve `A x = rhs` for a sparse right hand side, returning a sparse
+    /// solution, using `workspace` for the temporary storage, see
+    /// `solve_sparse`.
+    ///
+    /// # Panics
+    ///
+    /// If `rhs` or `workspace` do not have dimension `self.dim()`.
+    pub fn solve_sparse_with_workspace(&self,
+                                       rhs: CsVecViewI<N, I>,
+                                       workspace: &mut SparseSolveWorkspace<N>)
+                                       -> CsVecI<N, I> {
+        let n = self.dim();
+        if rhs.dim() != n || workspace.dim() != n {
+            panic!("Dimension mismatch");
+        }
+        // scatter P rhs in the workspace
+        let mut roots = Vec::with_capacity(rhs.nnz());
+        for (ind, val) in rhs.iter() {
+            let ind = self.inv_row_perm[ind];
+            workspace.values[ind] = val.clone();
+            roots.push(I::from_usize(ind));
+        }
+
+        // forward substitution with the unit lower triangular factor
+        let order = workspace.reach(self.lower_csc.view(), &roots);
+        for &col in &order {
+            let x_col = workspace.values[col].clone();
+            if x_col.is_zero() {
+                continue;
+            }
+            let column = self.lower_csc.outer_view(col).unwrap();
+            for (row, l) in column.iter() {
+                let val = workspace.values[row].clone()
+                          - l.clone() * x_col.clone();
+                workspace.values[row] = val;
+            }
+        }
+
+        // backward substitution with the upper triangular factor
+        let roots: Vec<I> = order.into_iter().map(I::from_usize).collect();
+        let order = workspace.reach(self.upper_csc.view(), &roots);
+        for &col in &order {
+            let column = self.upper_csc.outer_view(col).unwrap();
+            let diag = column.get(col).cloned().unwrap_or_else(N::one);
+            let x_col = workspace.values[col].clone() / diag;
+            workspace.values[col] = x_col.clone();
+            if x_col.is_zero() {
+                continue;
+            }
+            for (row, u) in column.iter().filter(|&(row, _)| row != col) {
+                let val = workspace.values[row].clone()
+                          - u.clone() * x_col.clone();
+                workspace.values[row] = val;
+            }
+        }
+
+        // gather the solution and clean the workspace
+        let mut indices = order;
+        indices.sort();
+        let data = indices.iter().map(|&ind| {
+            ::std::mem::replace(&mut workspace.values[ind], N::zero())
+        }).collect();
+        let indices = indices.into_iter().map(I::from_usize).collect();
+        CsVecI::new(n, indices, data)
+    }
+}
+
+impl<N, I> ExactLu<N, I>
+where N: Num + Clone + Signed + PartialOrd,
+      I: SpIndex,
+{
+    /// Factorize a square matrix with partial pivoting: the pivot of each
+    /// step is the candidate of largest magnitude, the sparsest row
+    /// breaking the ties. This bounds the growth of the multipliers, making
+    /// this factorization suitable for floating point matrices.
+    ///
+    /// # Errors
+    ///
+    /// `SingularMatrix` if the matrix is singular.
+    ///
+    /// # Panics
+    ///
+    /// If the matrix is not square.
+    pub fn factorize_partial_pivoting<IpS, IS, DS>(
+        mat: &CsMatBase<N, I, IpS, IS, DS>) -> SpRes<ExactLu<N, I>>
+    where IpS: Deref<Target=[I]>,
+          IS: Deref<Target=[I]>,
+          DS: Deref<Target=[N]>,
+    {
+        ExactLu::factorize_by(mat, |row, best| {
+            let (mag, best_mag) = (row[0].1.abs(), best[0].1.abs());
+            mag > best_mag || (mag == best_mag && row.len() < best.len())
+        })
+    }
+}
+
+impl<N, I> LinearSolver<N> for ExactLu<N, I>
+where N: Num + Clone,
+      I: SpIndex,
+{
+    fn dim(&self) -> usize {
+        ExactLu::dim(self)
+    }
+
+    fn solve(&self, rhs: &[N], x: &mut [N]) {
+        if x.len() != self.dim() {
+            panic!("Dimension mismatch");
+        }
+        for (xi, val) in x.iter_mut().zip(self.solve_vec(rhs)) {
+            *xi = val;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sparse::{CsMat, CsVec, TriMat};
+    use errors::SprsError;
+    use super::{ExactLu, SparseSolveWorkspace};
+
+    #[test]
+    fn exact_lu_pivoting() {
+        // requires a row exchange, and is solved exactly in floating point
+        let mut tri = TriMat::new((3, 3));
+        tri.add_triplet(0, 1, 2.);
+        tri.add_triplet(0, 2, 1.);
+        tri.add_triplet(1, 0, 1.);
+        tri.add_triplet(1, 2, 1.);
+        tri.add_triplet(2, 0, 1.);
+        tri.add_triplet(2, 1, 1.);
+        let mat: CsMat<f64> = tri.to_csc();
+        let lu = ExactLu::factorize(&mat).unwrap();
+        assert_eq!(lu.row_perm(), &[1, 0, 2]);
+        assert_eq!(lu.solve_vec(&[-1., 4., -1.]), vec![1., -2., 3.]);
+    }
+
+    #[test]
+    fn exact_lu_sparse_rhs() {
+        // two independent blocks, the first one requiring a row exchange
+        let mut tri = TriMat::new((5, 5));
+        tri.add_triplet(0, 1, 2.);
+        tri.add_triplet(1, 0, 1.);
+        tri.add_triplet(1, 1, 1.);
+        tri.add_triplet(2, 2, 2.);
+        tri.add_triplet(3, 2, 1.);
+        tri.add_triplet(3, 3, 1.);
+        tri.add_triplet(4, 3, 1.);
+        tri.add_triplet(4, 4, 4.);
+        let mat: CsMat<f64> = tri.to_csr();
+        let lu = ExactLu::factorize(&mat).unwrap();
+        let mut workspace = SparseSolveWorkspace::new(5);
+        for col in 0..5 {
+            let e = CsVec::new(5, vec![col], vec![1.]);
+            let x = lu.solve_sparse_with_workspace(e.view(), &mut workspace);
+            let mut rhs = vec![0.; 5];
+            rhs[col] = 1.;
+            let mut dense = vec![0.; 5];
+            x.scatter(&mut dense);
+            assert_eq!(dense, lu.solve_vec(&rhs));
+            // the columns of the inverse do not mix the blocks
+            assert!(x.indices().iter().all(|&i| (i < 2) == (col < 2)));
+        }
+        let x = lu.solve_sparse(CsVec::new(5, vec![2], vec![2.]).view());
+        assert_eq!(x, CsVec::new(5, vec![2, 3, 4], vec![1., -1., 0.25]));
+        let x = lu.solve_sparse(CsVec::new(5, vec![], vec![]).view());
+        assert_eq!(x.nnz(), 0);
+    }
+
+    #[test]
+    fn partial_pivoting() {
+        // the sparsity alone selects the tiny leading pivot, whose
+        // elimination loses the solution in floating point
+        let mat: CsMat<f64> = CsMat::new((2, 2),
+                                         vec![0, 2, 4],
+                                         vec![0, 1, 0, 1],
+                                         vec![1e-20, 1., 1., 1.]);
+        let lu = ExactLu::factorize(&mat).unwrap();
+        assert_eq!(lu.row_perm(), &[0, 1]);
+        assert_eq!(lu.solve_vec(&[1., 2.]), vec![0., 1.]);
+        let lu = ExactLu::factorize_partial_pivoting(&mat).unwrap();
+        assert_eq!(lu.row_perm(), &[1, 0]);
+        assert_eq!(lu.solve_vec(&[1., 2.]), vec![1., 1.]);
+    }
+
+    #[test]
+    fn exact_lu_singular() {
+        let mat: CsMat<f64> = CsMat::new((2, 2),
+                                         vec![0, 2, 4],
+                                         vec![0, 1, 0, 1],
+                                         vec![1., 2., 2., 4.]);
+        assert_eq!(ExactLu::factorize(&mat),
+                   Err(SprsError::SingularMatrix));
+    }
+
+    #[cfg(feature = "num-rational")]
+    #[test]
+    fn exact_lu_rationals() {
+        use num_rational::BigRational;
+        use num_traits::{FromPrimitive, Zero};
+        use sparse::{CsMatI, prod};
+        // the Hilbert matrix, notoriously ill conditioned
+        let dim = 6;
+        let mut tri = TriMat::new((dim, dim));
+        for i in 0..dim {
+            for j in 0..dim {
+                let val = BigRational::new(FromPrimitive::from_i64(1).unwrap(),
+                                           FromPrimitive::from_usize(i + j + 1)
+                                               .unwrap());
+                tri.add_triplet(i, j, val);
+            }
+        }
+        let mat: CsMatI<BigRational, usize> = tri.to_csr();
+        let lu = ExactLu::factorize(&mat).unwrap();
+        let x: Vec<BigRational> = (0..dim).map(|i| {
+            FromPrimitive::from_usize(i + 1).unwrap()
+        }).collect();
+        let mut rhs = vec![BigRational::zero(); dim];
+        prod::mul_acc_mat_vec_csr(mat.view(), &x, &mut rhs);
+        assert_eq!(lu.solve_vec(&rhs), x);
+    }
+}
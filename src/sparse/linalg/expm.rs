@@ -0,0 +1,192 @@
+///! Action of the matrix exponential
+///!
+///! Computing `exp(t A) v` is the basic step of the time integration of
+///! linear ordinary differential equations `dx/dt = A x`, such as master
+///! equations or diffusion processes on graphs. The exponential of a sparse
+///! matrix is usually dense, so it is never formed: its action on a vector
+///! is computed from matrix vector products only.
+
+use std::ops::Deref;
+use num_traits::Float;
+
+use indexing::SpIndex;
+use sparse::prelude::*;
+use sparse::prod;
+
+/// Bounds `theta_m` on `||t A||_1` for which the truncated Taylor series of
+/// degree `m` approximates the exponential to double precision, as computed
+/// by Al-Mohy and Higham.
+const THETAS: [(usize, f64); 35] = [
+    (1, 2.29e-16), (2, 2.58e-8), (3, 1.39e-5), (4, 3.40e-4),
+    (5, 2.40e-3), (6, 9.07e-3), (7, 2.38e-2), (8, 5.00e-2),
+    (9, 8.96e-2), (10, 1.44e-1), (11, 2.14e-1), (12, 3.00e-1),
+    (13, 4.00e-1), (14, 5.14e-1), (15, 6.41e-1), (16, 7.81e-1),
+    (17, 9.31e-1), (18, 1.09), (19, 1.26), (20, 1.44),
+    (21, 1.62), (22, 1.82), (23, 2.01), (24, 2.22),
+    (25, 2.43), (26, 2.64), (27, 2.86), (28, 3.08),
+    (29, 3.31), (30, 3.54), (35, 4.7), (40, 6.0),
+    (45, 7.2), (50, 8.5), (55, 9.9),
+];
+
+fn inf_norm<N: Float>(x: &[N]) -> N {
+    x.iter().fold(N::zero(), |acc, v| acc.max(v.abs()))
+}
+
+/// Compute `exp(t A) v` without forming the exponential of `A`.
+///
+/// This is the truncated Taylor method with scaling of Al-Mohy and Higham:
+/// the matrix is first shifted by the mean of its diagonal, then the
+/// interval `[0, t]` is split in `s` steps, and the action of the
+/// exponential on each step is approximated by a Taylor polynomial of
+/// degree `m`. The pair `(s, m)` minimizing the number of products with
+/// `A` is selected from the 1-norm of `A`, and the Taylor series is
+/// truncated early once its terms become negligible.
+///
+/// # Panics
+///
+/// - if `a` is not square
+/// - if `v` does not have the dimension of `a`
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::linalg::expm_multiply;
+/// // generator of the rotations in the plane
+/// let gen = CsMat::new((2, 2), vec![0, 1, 2], vec![1, 0], vec![-1., 1.]);
+/// let half_pi = ::std::f64::consts::FRAC_PI_2;
+/// let rotated = expm_multiply(&gen, &[1., 0.], half_pi);
+/// assert!(rotated[0].abs() < 1e-14);
+/// assert!((rotated[1] - 1.).abs() < 1e-14);
+/// ```
+pub fn expm_multiply<N, I, IpS, IS, DS>(a: &CsMatBase<N, I, IpS, IS, DS>,
+                                        v: &[N],
+                                        t: N) -> Vec<N>
+where N: Float + Default,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    let n = a.rows();
+    if a.cols() != n || v.len() != n {
+        panic!("Dimension mismatch");
+    }
+    if n == 0 || t.is_zero() {
+        return v.to_vec();
+    }
+    let a = a.to_csr();
+
+    // shifting by the mean of the diagonal reduces the norm of the matrix
+    let trace = (0..n).fold(N::zero(), |acc, i| {
+        acc + a.get(i, i).cloned().unwrap_or(N::zero())
+    });
+    let mu = trace / N::from(n).unwrap();
+    let mut col_sums = vec![N::zero(); n];
+    for (row, vec) in a.outer_iterator().enumerate() {
+        for (col, &val) in vec.iter() {
+            let val = if row == col { val - mu } else { val };
+            col_sums[col] = col_sums[col] + val.abs();
+        }
+        if vec.get(row).is_none() {
+            col_sums[row] = col_sums[row] + mu.abs();
+        }
+    }
+    let norm = t.abs() * col_sums.iter().fold(N::zero(), |acc, &x| acc.max(x));
+    let shift_factor = |s: N| (t * mu / s).exp();
+    if norm.is_zero() {
+        let factor = shift_factor(N::one());
+        return v.iter().map(|&x| x * factor).collect();
+    }
+
+    // select the number of steps and the degree of the Taylor polynomial
+    let (degree, nb_steps) = THETAS.iter().map(|&(m, theta)| {
+        let theta = N::from(theta).unwrap();
+        let steps = (norm / theta).ceil()
+                                  .to_usize()
+                                  .unwrap_or(usize::max_value());
+        (m, steps.max(1))
+    }).min_by_key(|&(m, steps)| m.saturating_mul(steps)).unwrap();
+
+    let tol = N::epsilon() / (N::one() + N::one());
+    let steps = N::from(nb_steps).unwrap();
+    let eta = shift_factor(steps);
+    let mut res = v.to_vec();
+    let mut term = v.to_vec();
+    let mut prod = vec![N::zero(); n];
+    for _ in 0..nb_steps {
+        let mut prev_norm = inf_norm(&term);
+        for j in 1..(degree + 1) {
+            for p in prod.iter_mut() {
+                *p = N::zero();
+            }
+            prod::mul_acc_mat_vec_csr(a.view(), &term, &mut prod);
+            let coeff = t / (steps * N::from(j).unwrap());
+            for ((tj, &pj), ri) in term.iter_mut().zip(&prod).zip(&mut res) {
+                *tj = coeff * (pj - mu * *tj);
+                *ri = *ri + *tj;
+            }
+            let term_norm = inf_norm(&term);
+            if prev_norm + term_norm <= tol * inf_norm(&res) {
+                break;
+            }
+            prev_norm = term_norm;
+        }
+        for ri in res.iter_mut() {
+            *ri = *ri * eta;
+        }
+        term.copy_from_slice(&res);
+    }
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use sparse::{CsMat, TriMat};
+    use super::expm_multiply;
+
+    #[test]
+    fn diagonal() {
+        let mat = CsMat::new((3, 3),
+                             vec![0, 1, 2, 3],
+                             vec![0, 1, 2],
+                             vec![-1., 0.5, 3.]);
+        let res = expm_multiply(&mat, &[1., 2., 3.], 2.);
+        let expected = [(-2f64).exp(), 2. * 1f64.exp(), 3. * 6f64.exp()];
+        for (r, e) in res.iter().zip(&expected) {
+            assert!((r - e).abs() <= 1e-13 * e.abs());
+        }
+    }
+
+    #[test]
+    fn graph_diffusion() {
+        // heat diffusion on a path graph, exp(-t L) v
+        let n = 30;
+        let mut tri = TriMat::new((n, n));
+        for i in 0..n {
+            let degree = if i == 0 || i == n - 1 { -1. } else { -2. };
+            tri.add_triplet(i, i, degree);
+            if i + 1 < n {
+                tri.add_triplet(i, i + 1, 1.);
+                tri.add_triplet(i + 1, i, 1.);
+            }
+        }
+        let mat: CsMat<f64> = tri.to_csr();
+        let mut v = vec![0.; n];
+        v[0] = 1.;
+        // a long time integration needs many scaling steps
+        let res = expm_multiply(&mat, &v, 2000.);
+        let total: f64 = res.iter().sum();
+        assert!((total - 1.).abs() < 1e-12);
+        for &x in &res {
+            assert!((x - 1. / n as f64).abs() < 1e-3);
+        }
+        // semigroup property
+        let half = expm_multiply(&mat, &v, 0.5);
+        let twice = expm_multiply(&mat, &half, 0.5);
+        let once = expm_multiply(&mat, &v, 1.);
+        for (x, y) in twice.iter().zip(&once) {
+            assert!((x - y).abs() < 1e-14);
+        }
+    }
+}
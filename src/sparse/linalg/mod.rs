@@ -9,6 +9,88 @@ use std::iter::IntoIterator;
 
 pub mod trisolve;
 pub mod etree;
+#[cfg(feature = "std")]
+pub mod operator;
+#[cfg(feature = "std")]
+pub mod precond;
+#[cfg(feature = "std")]
+pub mod solver;
+#[cfg(feature = "std")]
+pub mod bordered;
+#[cfg(feature = "std")]
+pub mod cg;
+#[cfg(feature = "std")]
+pub mod amg;
+#[cfg(feature = "std")]
+pub mod eigen;
+#[cfg(feature = "std")]
+pub mod expm;
+#[cfg(feature = "std")]
+pub mod ordering;
+#[cfg(feature = "std")]
+pub mod condest;
+#[cfg(feature = "std")]
+pub mod refine;
+#[cfg(feature = "std")]
+pub mod relax;
+#[cfg(feature = "std")]
+pub mod chebyshev;
+#[cfg(feature = "std")]
+pub mod equilibrate;
+#[cfg(feature = "std")]
+pub mod dmperm;
+#[cfg(feature = "std")]
+pub mod coloring;
+#[cfg(feature = "std")]
+pub mod exact;
+#[cfg(feature = "std")]
+pub mod boundary;
+#[cfg(feature = "std")]
+pub mod symmetric;
+#[cfg(feature = "std")]
+pub mod selinv;
+#[cfg(feature = "std")]
+mod dense;
+
+#[cfg(feature = "std")]
+pub use self::operator::LinearOperator;
+#[cfg(feature = "std")]
+pub use self::precond::Preconditioner;
+#[cfg(feature = "std")]
+pub use self::solver::LinearSolver;
+#[cfg(feature = "std")]
+pub use self::eigen::{eigsh, eigsh_generalized, eigs, eigs_shift_invert,
+                      power_iteration, EigenPairs, ComplexEigenPairs,
+                      DominantEigenPair, Which};
+#[cfg(feature = "std")]
+pub use self::expm::expm_multiply;
+#[cfg(feature = "std")]
+pub use self::condest::{onenormest, condest};
+#[cfg(feature = "std")]
+pub use self::equilibrate::equilibrate;
+#[cfg(feature = "std")]
+pub use self::dmperm::dmperm;
+#[cfg(feature = "std")]
+pub use self::coloring::{jacobian_compression, hessian_compression};
+#[cfg(feature = "std")]
+pub use self::exact::{ExactLu, SparseSolveWorkspace};
+#[cfg(feature = "std")]
+pub use self::boundary::apply_dirichlet;
+#[cfg(feature = "std")]
+pub use self::symmetric::{sym_spmv, herm_spmv};
+#[cfg(feature = "std")]
+pub use self::selinv::selected_inverse;
+
+/// Outcome of an iterative solve
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Convergence<N> {
+    /// The number of iterations performed
+    pub iterations: usize,
+    /// The euclidean norm of the final residual
+    pub residual_norm: N,
+    /// Whether the requested tolerance was reached
+    pub converged: bool,
+}
 
 /// Diagonal solve
 pub fn diag_solve<'a, N, I1, I2>(diag: I1, x: I2)
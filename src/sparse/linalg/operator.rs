@@ -0,0 +1,579 @@
+///! Abstraction over linear operators
+///!
+///! Iterative solvers and eigensolvers only need to know how to apply a
+///! matrix to a vector. The `LinearOperator` trait captures this, which
+///! enables using them with sparse matrices, dense matrices, lazy
+///! compositions of operators, or matrix-free operators defined by closures.
+
+use std::ops::Deref;
+use num_traits::Num;
+use ndarray::{self, ArrayBase};
+
+use indexing::SpIndex;
+use sparse::prelude::*;
+use sparse::prod;
+use ::{Ix2, Shape};
+
+/// A linear operator, ie a type able to compute matrix-vector products
+/// `y = A x` without necessarily storing the matrix `A`.
+pub trait LinearOperator<N> {
+    /// The shape of the operator, as a `(rows, cols)` tuple
+    fn shape(&self) -> Shape;
+
+    /// Compute `y = A x`, overwriting the contents of `y`.
+    ///
+    /// # Panics
+    ///
+    /// If `x.len() != self.shape().1` or `y.len() != self.shape().0`.
+    fn apply(&self, x: &[N], y: &mut [N]);
+
+    /// Whether this operator implements `apply_transpose`
+    fn has_transpose(&self) -> bool {
+        false
+    }
+
+    /// Compute `y = A^T x`, overwriting the contents of `y`.
+    ///
+    /// # Panics
+    ///
+    /// - if `x.len() != self.shape().0` or `y.len() != self.shape().1`.
+    /// - if the operator does not support the transposed product, which
+    ///   can be checked with `has_transpose`.
+    fn apply_transpose(&self, x: &[N], y: &mut [N]) {
+        let _ = (x, y);
+        panic!("transposed product not supported by this operator");
+    }
+
+    /// The number of rows of the operator
+    fn rows(&self) -> usize {
+        self.shape().0
+    }
+
+    /// The number of columns of the operator
+    fn cols(&self) -> usize {
+        self.shape().1
+    }
+}
+
+fn check_apply_dims(shape: Shape, x_len: usize, y_len: usize) {
+    if shape.1 != x_len || shape.0 != y_len {
+        panic!("Dimension mismatch");
+    }
+}
+
+impl<'a, N, T: ?Sized> LinearOperator<N> for &'a T
+where T: LinearOperator<N>
+{
+    fn shape(&self) -> Shape {
+        (**self).shape()
+    }
+
+    fn apply(&self, x: &[N], y: &mut [N]) {
+        (**self).apply(x, y)
+    }
+
+    fn has_transpose(&self) -> bool {
+        (**self).has_transpose()
+    }
+
+    fn apply_transpose(&self, x: &[N], y: &mut [N]) {
+        (**self).apply_transpose(x, y)
+    }
+}
+
+fn csmat_mul_acc<N, I>(mat: CsMatViewI<N, I>, x: &[N], y: &mut [N])
+where N: Num + Copy,
+      I: SpIndex,
+{
+    if mat.is_csr() {
+        prod::mul_acc_mat_vec_csr(mat, x, y);
+    }
+    else {
+        prod::mul_acc_mat_vec_csc(mat, x, y);
+    }
+}
+
+impl<N, I, IpS, IS, DS> LinearOperator<N> for CsMatBase<N, I, IpS, IS, DS>
+where N: Num + Copy,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    fn shape(&self) -> Shape {
+        self.shape()
+    }
+
+    fn apply(&self, x: &[N], y: &mut [N]) {
+        check_apply_dims(self.shape(), x.len(), y.len());
+        for yi in y.iter_mut() {
+            *yi = N::zero();
+        }
+        csmat_mul_acc(self.view(), x, y);
+    }
+
+    fn has_transpose(&self) -> bool {
+        true
+    }
+
+    fn apply_transpose(&self, x: &[N], y: &mut [N]) {
+        check_apply_dims((self.cols(), self.rows()), x.len(), y.len());
+        for yi in y.iter_mut() {
+            *yi = N::zero();
+        }
+        csmat_mul_acc(self.transpose_view(), x, y);
+    }
+}
+
+impl<N, S> LinearOperator<N> for ArrayBase<S, Ix2>
+where N: Num + Copy,
+      S: ndarray::Data<Elem=N>,
+{
+    fn shape(&self) -> Shape {
+        (self.shape()[0], self.shape()[1])
+    }
+
+    fn apply(&self, x: &[N], y: &mut [N]) {
+        check_apply_dims(LinearOperator::shape(self), x.len(), y.len());
+        for (row, yi) in self.outer_iter().zip(y.iter_mut()) {
+            *yi = row.iter()
+                     .zip(x.iter())
+                     .fold(N::zero(), |acc, (&a, &b)| acc + a * b);
+        }
+    }
+
+    fn has_transpose(&self) -> bool {
+        true
+    }
+
+    fn apply_transpose(&self, x: &[N], y: &mut [N]) {
+        let (rows, cols) = LinearOperator::shape(self);
+        check_apply_dims((cols, rows), x.len(), y.len());
+        for yi in y.iter_mut() {
+            *yi = N::zero();
+        }
+        for (row, &xi) in self.outer_iter().zip(x.iter()) {
+            for (yj, &a) in y.iter_mut().zip(row.iter()) {
+                *yj = *yj + a * xi;
+            }
+        }
+    }
+}
+
+/// The identity operator, which stores nothing but its shape.
+///
+/// A rectangular identity copies the first entries of its input and pads
+/// its output with zeros, like `CsMat::eye_k(shape, 0)`.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::linalg::operator::{Identity, LinearOperator};
+/// let id = Identity::rectangular((3, 2));
+/// let mut y = [5.; 3];
+/// id.apply(&[1., 2.], &mut y);
+/// assert_eq!(y, [1., 2., 0.]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Identity {
+    shape: Shape,
+}
+
+impl Identity {
+    /// The identity operator of dimension `dim`
+    pub fn new(dim: usize) -> Identity {
+        Identity {
+            shape: (dim, dim),
+        }
+    }
+
+    /// The rectangular identity operator of shape `shape`
+    pub fn rectangular(shape: Shape) -> Identity {
+        Identity {
+            shape: shape,
+        }
+    }
+}
+
+impl<N> LinearOperator<N> for Identity
+where N: Num + Copy,
+{
+    fn shape(&self) -> Shape {
+        self.shape
+    }
+
+    fn apply(&self, x: &[N], y: &mut [N]) {
+        check_apply_dims(self.shape, x.len(), y.len());
+        for yi in y.iter_mut() {
+            *yi = N::zero();
+        }
+        for (yi, &xi) in y.iter_mut().zip(x.iter()) {
+            *yi = xi;
+        }
+    }
+
+    fn has_transpose(&self) -> bool {
+        true
+    }
+
+    fn apply_transpose(&self, x: &[N], y: &mut [N]) {
+        check_apply_dims((self.shape.1, self.shape.0), x.len(), y.len());
+        for yi in y.iter_mut() {
+            *yi = N::zero();
+        }
+        for (yi, &xi) in y.iter_mut().zip(x.iter()) {
+            *yi = xi;
+        }
+    }
+}
+
+/// Lazy sum of two operators of the same shape, `A + B`
+#[derive(Debug, Clone)]
+pub struct Sum<A, B> {
+    lhs: A,
+    rhs: B,
+}
+
+impl<A, B> Sum<A, B> {
+    /// Create the lazy sum `lhs + rhs`
+    ///
+    /// # Panics
+    ///
+    /// If the operators do not have the same shape.
+    pub fn new<N>(lhs: A, rhs: B) -> Sum<A, B>
+    where A: LinearOperator<N>,
+          B: LinearOperator<N>,
+    {
+        assert_eq!(lhs.shape(), rhs.shape(), "Dimension mismatch");
+        Sum {
+            lhs: lhs,
+            rhs: rhs,
+        }
+    }
+}
+
+impl<N, A, B> LinearOperator<N> for Sum<A, B>
+where N: Num + Copy,
+      A: LinearOperator<N>,
+      B: LinearOperator<N>,
+{
+    fn shape(&self) -> Shape {
+        self.lhs.shape()
+    }
+
+    fn apply(&self, x: &[N], y: &mut [N]) {
+        let mut tmp = vec![N::zero(); y.len()];
+        self.lhs.apply(x, y);
+        self.rhs.apply(x, &mut tmp);
+        for (yi, &ti) in y.iter_mut().zip(tmp.iter()) {
+            *yi = *yi + ti;
+        }
+    }
+
+    fn has_transpose(&self) -> bool {
+        self.lhs.has_transpose() && self.rhs.has_transpose()
+    }
+
+    fn apply_transpose(&self, x: &[N], y: &mut [N]) {
+        let mut tmp = vec![N::zero(); y.len()];
+        self.lhs.apply_transpose(x, y);
+        self.rhs.apply_transpose(x, &mut tmp);
+        for (yi, &ti) in y.iter_mut().zip(tmp.iter()) {
+            *yi = *yi + ti;
+        }
+    }
+}
+
+/// Lazy product of two operators, `A B`
+#[derive(Debug, Clone)]
+pub struct Product<A, B> {
+    lhs: A,
+    rhs: B,
+}
+
+impl<A, B> Product<A, B> {
+    /// Create the lazy product `lhs * rhs`
+    ///
+    /// # Panics
+    ///
+    /// If the number of columns of `lhs` differs from the number of rows
+    /// of `rhs`.
+    pub fn new<N>(lhs: A, rhs: B) -> Product<A, B>
+    where A: LinearOperator<N>,
+          B: LinearOperator<N>,
+    {
+        assert_eq!(lhs.cols(), rhs.rows(), "Dimension mismatch");
+        Product {
+            lhs: lhs,
+            rhs: rhs,
+        }
+    }
+}
+
+impl<N, A, B> LinearOperator<N> for Product<A, B>
+where N: Num + Copy,
+      A: LinearOperator<N>,
+      B: LinearOperator<N>,
+{
+    fn shape(&self) -> Shape {
+        (self.lhs.rows(), self.rhs.cols())
+    }
+
+    fn apply(&self, x: &[N], y: &mut [N]) {
+        let mut tmp = vec![N::zero(); self.rhs.rows()];
+        self.rhs.apply(x, &mut tmp);
+        self.lhs.apply(&tmp, y);
+    }
+
+    fn has_transpose(&self) -> bool {
+        self.lhs.has_transpose() && self.rhs.has_transpose()
+    }
+
+    fn apply_transpose(&self, x: &[N], y: &mut [N]) {
+        let mut tmp = vec![N::zero(); self.lhs.cols()];
+        self.lhs.apply_transpose(x, &mut tmp);
+        self.rhs.apply_transpose(&tmp, y);
+    }
+}
+
+/// Lazy scaling of an operator by a scalar, `alpha A`
+#[derive(Debug, Clone)]
+pub struct Scaled<N, A> {
+    alpha: N,
+    op: A,
+}
+
+impl<N, A> Scaled<N, A> {
+    /// Create the lazy scaled operator `alpha * op`
+    pub fn new(alpha: N, op: A) -> Scaled<N, A> {
+        Scaled {
+            alpha: alpha,
+            op: op,
+        }
+    }
+}
+
+impl<N, A> LinearOperator<N> for Scaled<N, A>
+where N: Num + Copy,
+      A: LinearOperator<N>,
+{
+    fn shape(&self) -> Shape {
+        self.op.shape()
+    }
+
+    fn apply(&self, x: &[N], y: &mut [N]) {
+        self.op.apply(x, y);
+        for yi in y.iter_mut() {
+            *yi = self.alpha * *yi;
+        }
+    }
+
+    fn has_transpose(&self) -> bool {
+        self.op.has_transpose()
+    }
+
+    fn apply_transpose(&self, x: &[N], y: &mut [N]) {
+        self.op.apply_transpose(x, y);
+        for yi in y.iter_mut() {
+            *yi = self.alpha * *yi;
+        }
+    }
+}
+
+/// A matrix-free operator defined by closures computing its products.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::linalg::operator::{FnOperator, LinearOperator};
+/// // the 1D laplacian stencil, without ever storing the matrix
+/// let n = 4;
+/// let lap = FnOperator::new((n, n), |x: &[f64], y: &mut [f64]| {
+///     for i in 0..n {
+///         let left = if i > 0 { x[i - 1] } else { 0. };
+///         let right = if i + 1 < n { x[i + 1] } else { 0. };
+///         y[i] = 2. * x[i] - left - right;
+///     }
+/// });
+/// let x = [1., 1., 1., 1.];
+/// let mut y = [0.; 4];
+/// lap.apply(&x, &mut y);
+/// assert_eq!(y, [1., 0., 0., 1.]);
+/// ```
+pub struct FnOperator<F, G> {
+    shape: Shape,
+    apply: F,
+    apply_transpose: Option<G>,
+}
+
+impl<F> FnOperator<F, F> {
+    /// Create an operator of shape `shape` whose product is computed by
+    /// `apply(x, y)`. The resulting operator does not support transposed
+    /// products.
+    pub fn new<N>(shape: Shape, apply: F) -> FnOperator<F, F>
+    where F: Fn(&[N], &mut [N])
+    {
+        FnOperator {
+            shape: shape,
+            apply: apply,
+            apply_transpose: None,
+        }
+    }
+}
+
+impl<F, G> FnOperator<F, G> {
+    /// Create an operator of shape `shape` whose product is computed by
+    /// `apply(x, y)` and whose transposed product is computed by
+    /// `apply_transpose(x, y)`.
+    pub fn with_transpose<N>(shape: Shape,
+                             apply: F,
+                             apply_transpose: G) -> FnOperator<F, G>
+    where F: Fn(&[N], &mut [N]),
+          G: Fn(&[N], &mut [N]),
+    {
+        FnOperator {
+            shape: shape,
+            apply: apply,
+            apply_transpose: Some(apply_transpose),
+        }
+    }
+}
+
+impl<N, F, G> LinearOperator<N> for FnOperator<F, G>
+where F: Fn(&[N], &mut [N]),
+      G: Fn(&[N], &mut [N]),
+{
+    fn shape(&self) -> Shape {
+        self.shape
+    }
+
+    fn apply(&self, x: &[N], y: &mut [N]) {
+        check_apply_dims(self.shape, x.len(), y.len());
+        (self.apply)(x, y)
+    }
+
+    fn has_transpose(&self) -> bool {
+        self.apply_transpose.is_some()
+    }
+
+    fn apply_transpose(&self, x: &[N], y: &mut [N]) {
+        check_apply_dims((self.shape.1, self.shape.0), x.len(), y.len());
+        match self.apply_transpose {
+            Some(ref apply_transpose) => apply_transpose(x, y),
+            None => panic!("transposed product not supported by this operator"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::arr2;
+    use sparse::CsMat;
+    use test_data::mat1;
+    use super::{LinearOperator, Sum, Product, Scaled, FnOperator, Identity};
+
+    fn apply_vec<Op: LinearOperator<f64>>(op: &Op, x: &[f64]) -> Vec<f64> {
+        let mut y = vec![0.; op.rows()];
+        op.apply(x, &mut y);
+        y
+    }
+
+    fn apply_t_vec<Op: LinearOperator<f64>>(op: &Op, x: &[f64]) -> Vec<f64> {
+        let mut y = vec![0.; op.cols()];
+        op.apply_transpose(x, &mut y);
+        y
+    }
+
+    #[test]
+    fn csmat_operator() {
+        let x = [1., 2., 3., 4., 5.];
+        let csr = mat1();
+        let csc = mat1().to_csc();
+        let expected = vec![25., 33., 15., 16., 28.];
+        assert_eq!(apply_vec(&csr, &x), expected);
+        assert_eq!(apply_vec(&csc, &x), expected);
+        assert_eq!(apply_vec(&csr.view(), &x), expected);
+
+        let expected_t = vec![0., 32., 18., 43., 10.];
+        assert_eq!(apply_t_vec(&csr, &x), expected_t);
+        assert_eq!(apply_t_vec(&csc, &x), expected_t);
+    }
+
+    #[test]
+    fn dense_operator() {
+        let a = arr2(&[[1., 2.], [3., 4.], [5., 6.]]);
+        assert_eq!(LinearOperator::shape(&a), (3, 2));
+        assert_eq!(apply_vec(&a, &[1., 1.]), vec![3., 7., 11.]);
+        assert_eq!(apply_t_vec(&a, &[1., 1., 1.]), vec![9., 12.]);
+        let at = a.t();
+        assert_eq!(apply_vec(&at, &[1., 1., 1.]), vec![9., 12.]);
+    }
+
+    #[test]
+    fn lazy_compositions() {
+        let eye: CsMat<f64> = CsMat::eye(5);
+        let a = mat1();
+        let x = [1., 2., 3., 4., 5.];
+
+        let sum = Sum::new(&a, &eye);
+        assert_eq!(apply_vec(&sum, &x), vec![26., 35., 18., 20., 33.]);
+        assert_eq!(apply_t_vec(&sum, &x), vec![1., 34., 21., 47., 15.]);
+
+        let scaled = Scaled::new(2., &a);
+        assert_eq!(apply_vec(&scaled, &x), vec![50., 66., 30., 32., 56.]);
+
+        let prod = Product::new(&a, &a);
+        let expected = &(&a * &a) * &::ndarray::arr1(&x);
+        assert_eq!(apply_vec(&prod, &x), expected.to_vec());
+        let expected_t = &(&a * &a).transpose_view() * &::ndarray::arr1(&x);
+        assert_eq!(apply_t_vec(&prod, &x), expected_t.to_vec());
+        assert!(prod.has_transpose());
+    }
+
+    #[test]
+    fn identity_operator() {
+        let x = [1., 2., 3.];
+        assert_eq!(apply_vec(&Identity::new(3), &x), x.to_vec());
+        for &shape in &[(3, 2), (2, 3), (0, 3)] {
+            let id = Identity::rectangular(shape);
+            let eye: CsMat<f64> = CsMat::eye_k(shape, 0);
+            let x: Vec<f64> = (0..shape.1).map(|i| i as f64 + 1.).collect();
+            assert_eq!(apply_vec(&id, &x), apply_vec(&eye, &x));
+            let x: Vec<f64> = (0..shape.0).map(|i| i as f64 + 1.).collect();
+            assert_eq!(apply_t_vec(&id, &x), apply_t_vec(&eye, &x));
+        }
+    }
+
+    #[test]
+    fn closure_operator() {
+        let diag = FnOperator::new((3, 3), |x: &[f64], y: &mut [f64]| {
+            for (i, (yi, xi)) in y.iter_mut().zip(x.iter()).enumerate() {
+                *yi = (i + 1) as f64 * xi;
+            }
+        });
+        assert!(!diag.has_transpose());
+        assert_eq!(apply_vec(&diag, &[1., 1., 1.]), vec![1., 2., 3.]);
+
+        // lazy compositions also accept matrix-free operators
+        let eye: CsMat<f64> = CsMat::eye(3);
+        let sum = Sum::new(&diag, &eye);
+        assert_eq!(apply_vec(&sum, &[1., 1., 1.]), vec![2., 3., 4.]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn closure_operator_no_transpose() {
+        let op = FnOperator::new((2, 2), |x: &[f64], y: &mut [f64]| {
+            y.copy_from_slice(x);
+        });
+        apply_t_vec(&op, &[1., 1.]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn operator_dimension_mismatch() {
+        let a = mat1();
+        let mut y = vec![0.; 5];
+        a.apply(&[1., 2.], &mut y);
+    }
+}
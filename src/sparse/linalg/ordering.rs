@@ -0,0 +1,779 @@
+///! Fill reducing orderings
+///!
+///! Factorizing a sparse matrix creates new non-zero entries (fill-in),
+///! whose number depends heavily on the order in which the rows and columns
+///! are eliminated. This module computes permutations reducing fill-in:
+///!
+///! - `amd` orders symmetric matrices for Cholesky or LDLT factorizations,
+///! - `colamd` orders the columns of general matrices for LU or QR
+///!   factorizations,
+///! - `reverse_cuthill_mckee` reduces the bandwidth of symmetric matrices,
+///! - `nested_dissection` recursively splits the graph of symmetric
+///!   matrices, which is well suited to matrices coming from meshes.
+///!
+///! Orderings are returned as `Permutation` objects, where `perm.at(k)` is
+///! the index of the `k`-th eliminated row or column, as expected by
+///! factorizations such as `LdlSymbolic::new_perm`. The symmetric orderings
+///! can also be selected at runtime through `ReorderMethod`.
+
+use std::cmp::{self, Reverse};
+use std::collections::{BinaryHeap, VecDeque};
+use std::ops::Deref;
+
+use indexing::SpIndex;
+use sparse::prelude::*;
+use sparse::permutation::PermOwnedI;
+
+/// Rows and columns with more entries than this threshold are considered
+/// dense, they are removed from the graph and ordered last.
+fn dense_threshold(n: usize) -> usize {
+    cmp::max(16, 10 * (n as f64).sqrt() as usize)
+}
+
+/// Minimum degree ordering on a quotient graph.
+///
+/// The graph of the matrix being eliminated is represented implicitly by
+/// variables, adjacent to other variables and to elements, which stand for
+/// the cliques created by the elimination of previous pivots. Eliminating a
+/// pivot absorbs its adjacent elements into a new element, so the memory
+/// used by the graph never grows. Degrees are approximated by the upper
+/// bound of Amestoy, Davis and Duff, which can be updated cheaply.
+///
+/// `var_adj` gives the variables adjacent to each variable, and `elems` the
+/// variables of the initial elements. Variables marked in `dense` are
+/// ordered last.
+fn minimum_degree(mut var_adj: Vec<Vec<usize>>,
+                  mut elems: Vec<Vec<usize>>,
+                  dense: &[bool]) -> Vec<usize> {
+    let n = var_adj.len();
+    let mut var_elems = vec![Vec::new(); n];
+    for (e, vars) in elems.iter().enumerate() {
+        for &i in vars {
+            var_elems[i].push(e);
+        }
+    }
+    let mut absorbed = vec![false; elems.len()];
+    let mut eliminated = dense.to_vec();
+    let nb_sparse = dense.iter().filter(|&&d| !d).count();
+
+    let mut degree = vec![0; n];
+    let mut heap = BinaryHeap::with_capacity(n);
+    for i in (0..n).filter(|&i| !dense[i]) {
+        let d = var_adj[i].len() + var_elems[i].iter().fold(0, |acc, &e| {
+            acc + elems[e].len() - 1
+        });
+        degree[i] = cmp::min(d, nb_sparse - 1);
+        heap.push(Reverse((degree[i], i)));
+    }
+
+    // stamps marking the variables of the current pivot's element, and
+    // the elements whose external degree has been computed
+    let mut stamp = 0;
+    let mut var_mark = vec![0; n];
+    let mut elem_mark = vec![0; elems.len()];
+    let mut external = vec![0; elems.len()];
+
+    let mut order = Vec::with_capacity(n);
+    while let Some(Reverse((d, pivot))) = heap.pop() {
+        if eliminated[pivot] || d != degree[pivot] {
+            continue;
+        }
+        eliminated[pivot] = true;
+        order.push(pivot);
+        stamp += 1;
+
+        // the variables of the new element
+        let mut pivot_elem = Vec::new();
+        for &j in &var_adj[pivot] {
+            if !eliminated[j] && var_mark[j] != stamp {
+                var_mark[j] = stamp;
+                pivot_elem.push(j);
+            }
+        }
+        for &e in &var_elems[pivot] {
+            for &j in &elems[e] {
+                if !eliminated[j] && var_mark[j] != stamp {
+                    var_mark[j] = stamp;
+                    pivot_elem.push(j);
+                }
+            }
+            absorbed[e] = true;
+            elems[e] = Vec::new();
+        }
+        var_adj[pivot] = Vec::new();
+        var_elems[pivot] = Vec::new();
+        let new_elem = elems.len();
+        elems.push(pivot_elem.clone());
+        absorbed.push(false);
+        elem_mark.push(0);
+        external.push(0);
+
+        // update the neighbourhoods, variables of the new element are now
+        // connected through it
+        for &i in &pivot_elem {
+            var_adj[i].retain(|&j| !eliminated[j] && var_mark[j] != stamp);
+            var_elems[i].retain(|&e| !absorbed[e]);
+            var_elems[i].push(new_elem);
+        }
+
+        // approximate degrees: d_i = |A_i| + |L_p \ i| + sum |L_e \ L_p|
+        let remaining = nb_sparse - order.len();
+        for &i in &pivot_elem {
+            let mut d = var_adj[i].len() + pivot_elem.len() - 1;
+            for &e in &var_elems[i] {
+                if e == new_elem {
+                    continue;
+                }
+                if elem_mark[e] != stamp {
+                    elem_mark[e] = stamp;
+                    external[e] = elems[e].iter()
+                                          .filter(|&&j| var_mark[j] != stamp)
+                                          .count();
+                }
+                d += external[e];
+            }
+            degree[i] = cmp::min(d, remaining.saturating_sub(1));
+            heap.push(Reverse((degree[i], i)));
+        }
+    }
+    order.extend((0..n).filter(|&i| dense[i]));
+    order
+}
+
+fn to_perm<I: SpIndex>(order: Vec<usize>) -> PermOwnedI<I> {
+    PermOwnedI::new(order.into_iter().map(I::from_usize).collect())
+}
+
+/// The adjacency lists of the graph of the non-zero pattern of `A + A^T`,
+/// without self loops.
+///
+/// # Panics
+///
+/// If the matrix is not square.
+fn symmetric_graph<N, I, IpS, IS, DS>(mat: &CsMatBase<N, I, IpS, IS, DS>)
+                                      -> Vec<Vec<usize>>
+where I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    let n = mat.rows();
+    assert_eq!(n, mat.cols(), "Non square matrix");
+    let mut adj = vec![Vec::new(); n];
+    for (outer, vec) in mat.outer_iterator().enumerate() {
+        for (inner, _) in vec.iter() {
+            if inner != outer {
+                adj[outer].push(inner);
+                adj[inner].push(outer);
+            }
+        }
+    }
+    for nodes in &mut adj {
+        nodes.sort();
+        nodes.dedup();
+    }
+    adj
+}
+
+/// Breadth first traversals restricted to the nodes sharing a label
+struct LevelSets<'a> {
+    adj: &'a [Vec<usize>],
+    mark: Vec<usize>,
+    stamp: usize,
+}
+
+impl<'a> LevelSets<'a> {
+    fn new(adj: &'a [Vec<usize>]) -> LevelSets<'a> {
+        LevelSets {
+            adj: adj,
+            mark: vec![0; adj.len()],
+            stamp: 0,
+        }
+    }
+
+    /// The level structure rooted at `root`, among the nodes having the
+    /// same label as `root`
+    fn levels(&mut self, root: usize, labels: &[usize]) -> Vec<Vec<usize>> {
+        self.stamp += 1;
+        self.mark[root] = self.stamp;
+        let mut levels = vec![vec![root]];
+        loop {
+            let mut next = Vec::new();
+            for &node in levels.last().unwrap() {
+                for &neighbour in &self.adj[node] {
+                    if self.mark[neighbour] != self.stamp
+                       && labels[neighbour] == labels[root] {
+                        self.mark[neighbour] = self.stamp;
+                        next.push(neighbour);
+                    }
+                }
+            }
+            if next.is_empty() {
+                return levels;
+            }
+            levels.push(next);
+        }
+    }
+
+    /// Find a pseudo-peripheral node of the component containing `start`
+    /// with the algorithm of George and Liu, returning its level structure
+    fn pseudo_peripheral(&mut self,
+                         start: usize,
+                         labels: &[usize]) -> Vec<Vec<usize>> {
+        let mut levels = self.levels(start, labels);
+        loop {
+            let adj = self.adj;
+            let candidate = *levels.last()
+                                   .unwrap()
+                                   .iter()
+                                   .min_by_key(|&&i| adj[i].len())
+                                   .unwrap();
+            let candidate_levels = self.levels(candidate, labels);
+            if candidate_levels.len() <= levels.len() {
+                return levels;
+            }
+            levels = candidate_levels;
+        }
+    }
+}
+
+/// Reverse Cuthill-McKee ordering of a symmetric matrix.
+///
+/// This ordering reduces the bandwidth and the profile of the matrix,
+/// which benefits banded and skyline solvers as well as the cache
+/// efficiency of matrix vector products. Each connected component of the
+/// graph of `A + A^T` is traversed breadth first from a pseudo-peripheral
+/// node, visiting neighbours by increasing degree, and the resulting order
+/// is reversed.
+///
+/// # Panics
+///
+/// If the matrix is not square.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::linalg::ordering::reverse_cuthill_mckee;
+/// // a path graph with shuffled nodes: 0 - 2 - 4 - 1 - 3
+/// let mat = CsMat::new((5, 5),
+///                      vec![0, 1, 3, 5, 6, 8],
+///                      vec![2, 3, 4, 0, 4, 1, 1, 2],
+///                      vec![1.; 8]);
+/// let perm = reverse_cuthill_mckee(&mat);
+/// // the reordered matrix is tridiagonal
+/// for k in 0..4 {
+///     let (i, j) = (perm.at(k), perm.at(k + 1));
+///     assert!(mat.get(i, j).is_some());
+/// }
+/// ```
+pub fn reverse_cuthill_mckee<N, I, IpS, IS, DS>(mat: &CsMatBase<N, I, IpS,
+                                                                IS, DS>)
+                                                -> PermOwnedI<I>
+where I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    let adj = symmetric_graph(mat);
+    let n = adj.len();
+    let labels = vec![0; n];
+    let mut level_sets = LevelSets::new(&adj);
+    let mut visited = vec![false; n];
+    let mut order = Vec::with_capacity(n);
+    let mut by_degree: Vec<usize> = (0..n).collect();
+    by_degree.sort_by_key(|&i| adj[i].len());
+    for &start in &by_degree {
+        if visited[start] {
+            continue;
+        }
+        let root = level_sets.pseudo_peripheral(start, &labels)[0][0];
+        visited[root] = true;
+        let mut queue = VecDeque::new();
+        queue.push_back(root);
+        while let Some(node) = queue.pop_front() {
+            order.push(node);
+            let mut neighbours: Vec<usize> = adj[node].iter()
+                                                      .cloned()
+                                                      .filter(|&j| !visited[j])
+                                                      .collect();
+            neighbours.sort_by_key(|&j| adj[j].len());
+            for j in neighbours {
+                visited[j] = true;
+                queue.push_back(j);
+            }
+        }
+    }
+    order.reverse();
+    to_perm(order)
+}
+
+/// Subgraphs with at most this number of nodes are not dissected further,
+/// but ordered by minimum degree.
+const DISSECTION_LEAF_SIZE: usize = 64;
+
+struct Dissection<'a> {
+    adj: &'a [Vec<usize>],
+    level_sets: LevelSets<'a>,
+    labels: Vec<usize>,
+    nb_labels: usize,
+    order: Vec<usize>,
+}
+
+impl<'a> Dissection<'a> {
+    fn relabel(&mut self, nodes: &[usize]) {
+        self.nb_labels += 1;
+        for &i in nodes {
+            self.labels[i] = self.nb_labels;
+        }
+    }
+
+    /// Order the subgraph induced by `nodes`, which all share their label
+    fn dissect(&mut self, nodes: Vec<usize>) {
+        if nodes.len() <= DISSECTION_LEAF_SIZE {
+            return self.order_leaf(&nodes);
+        }
+        let levels = self.level_sets.pseudo_peripheral(nodes[0],
+                                                       &self.labels);
+        let component: Vec<usize> = levels.iter()
+                                          .flat_map(|level| level.iter())
+                                          .cloned()
+                                          .collect();
+        if component.len() < nodes.len() {
+            // disconnected subgraph, order the components separately
+            let stamp = self.level_sets.stamp;
+            let rest: Vec<usize> = nodes.into_iter().filter(|&i| {
+                self.level_sets.mark[i] != stamp
+            }).collect();
+            self.relabel(&component);
+            self.relabel(&rest);
+            self.dissect(component);
+            return self.dissect(rest);
+        }
+        if levels.len() < 3 {
+            return self.order_leaf(&nodes);
+        }
+        // the separator is the level splitting the nodes in halves
+        let mut count = 0;
+        let mut middle = 1;
+        for (ind, level) in levels.iter().enumerate().skip(1) {
+            if count + level.len() > nodes.len() / 2
+               || ind == levels.len() - 2 {
+                middle = ind;
+                break;
+            }
+            count += level.len();
+        }
+        let mut first: Vec<usize> = levels[..middle].iter()
+                                                    .flat_map(|l| l.iter())
+                                                    .cloned()
+                                                    .collect();
+        let second: Vec<usize> = levels[middle + 1..].iter()
+                                                     .flat_map(|l| l.iter())
+                                                     .cloned()
+                                                     .collect();
+        self.relabel(&second);
+        let second_label = self.nb_labels;
+        // separator nodes not adjacent to the second part are not needed
+        let mut separator = Vec::with_capacity(levels[middle].len());
+        for &i in &levels[middle] {
+            let labels = &self.labels;
+            if self.adj[i].iter().any(|&j| labels[j] == second_label) {
+                separator.push(i);
+            }
+            else {
+                first.push(i);
+            }
+        }
+        self.relabel(&first);
+        self.relabel(&separator);
+        self.dissect(first);
+        self.dissect(second);
+        self.order.extend(separator);
+    }
+
+    /// Order a small subgraph by minimum degree
+    fn order_leaf(&mut self, nodes: &[usize]) {
+        let label = match nodes.first() {
+            Some(&i) => self.labels[i],
+            None => return,
+        };
+        let mut local = vec![0; self.adj.len()];
+        for (k, &i) in nodes.iter().enumerate() {
+            local[i] = k;
+        }
+        let local_adj = nodes.iter().map(|&i| {
+            self.adj[i].iter()
+                       .filter(|&&j| self.labels[j] == label)
+                       .map(|&j| local[j])
+                       .collect()
+        }).collect();
+        let dense = vec![false; nodes.len()];
+        let leaf_order = minimum_degree(local_adj, Vec::new(), &dense);
+        self.order.extend(leaf_order.into_iter().map(|k| nodes[k]));
+    }
+}
+
+/// Nested dissection ordering of a symmetric matrix.
+///
+/// The graph of `A + A^T` is split in two parts by a vertex separator,
+/// found as a level of a breadth first traversal from a pseudo-peripheral
+/// node. Both parts are ordered recursively, followed by the separator, so
+/// that the elimination of one part never creates fill in the other one.
+/// Small subgraphs are ordered by minimum degree.
+///
+/// This ordering produces little fill-in for matrices coming from the
+/// discretization of PDEs on two or three dimensional meshes.
+///
+/// # Panics
+///
+/// If the matrix is not square.
+pub fn nested_dissection<N, I, IpS, IS, DS>(mat: &CsMatBase<N, I, IpS,
+                                                            IS, DS>)
+                                            -> PermOwnedI<I>
+where I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    let adj = symmetric_graph(mat);
+    let n = adj.len();
+    let mut dissection = Dissection {
+        adj: &adj,
+        level_sets: LevelSets::new(&adj),
+        labels: vec![0; n],
+        nb_labels: 0,
+        order: Vec::with_capacity(n),
+    };
+    dissection.dissect((0..n).collect());
+    to_perm(dissection.order)
+}
+
+/// The available symmetric fill reducing orderings, for use by symmetric
+/// factorizations such as `LdlSymbolic::new_reordered`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReorderMethod {
+    /// Keep the rows and columns in their original order
+    Natural,
+    /// Approximate minimum degree, see `amd`
+    Amd,
+    /// Reverse Cuthill-McKee, see `reverse_cuthill_mckee`
+    ReverseCuthillMcKee,
+    /// Nested dissection, see `nested_dissection`
+    NestedDissection,
+}
+
+impl ReorderMethod {
+    /// Compute the ordering of the square matrix `mat` with this method.
+    ///
+    /// # Panics
+    ///
+    /// If the matrix is not square.
+    pub fn ordering<N, I, IpS, IS, DS>(self,
+                                       mat: &CsMatBase<N, I, IpS, IS, DS>)
+                                       -> PermOwnedI<I>
+    where I: SpIndex,
+          IpS: Deref<Target=[I]>,
+          IS: Deref<Target=[I]>,
+          DS: Deref<Target=[N]>,
+    {
+        match self {
+            ReorderMethod::Natural => {
+                assert_eq!(mat.rows(), mat.cols(), "Non square matrix");
+                PermOwnedI::identity(mat.rows())
+            }
+            ReorderMethod::Amd => amd(mat),
+            ReorderMethod::ReverseCuthillMcKee => reverse_cuthill_mckee(mat),
+            ReorderMethod::NestedDissection => nested_dissection(mat),
+        }
+    }
+}
+
+/// Approximate minimum degree ordering of a symmetric matrix.
+///
+/// The ordering is computed on the non-zero pattern of `A + A^T`, so only
+/// the pattern of the matrix matters and it does not need to be exactly
+/// symmetric. Rows with many more non-zeros than average are considered
+/// dense and ordered last.
+///
+/// Contrary to the reference implementation of Amestoy, Davis and Duff,
+/// indistinguishable nodes are not merged into supervariables, which makes
+/// this implementation slower on matrices with many such nodes.
+///
+/// # Panics
+///
+/// If the matrix is not square.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::linalg::ordering::amd;
+/// // an arrow matrix: eliminating the first node first fills the whole
+/// // matrix, eliminating it after the other ones causes no fill-in
+/// let mat = CsMat::new((4, 4),
+///                      vec![0, 4, 6, 8, 10],
+///                      vec![0, 1, 2, 3, 0, 1, 0, 2, 0, 3],
+///                      vec![4., 1., 1., 1., 1., 4., 1., 4., 1., 4.]);
+/// let perm = amd(&mat);
+/// assert!(perm.at_inv(0) >= 2);
+/// ```
+pub fn amd<N, I, IpS, IS, DS>(mat: &CsMatBase<N, I, IpS, IS, DS>)
+                              -> PermOwnedI<I>
+where I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    let mut adj = symmetric_graph(mat);
+    let n = adj.len();
+    let threshold = dense_threshold(n);
+    let dense: Vec<bool> = adj.iter().map(|vars| vars.len() > threshold)
+                                     .collect();
+    for vars in &mut adj {
+        vars.retain(|&j| !dense[j]);
+    }
+    to_perm(minimum_degree(adj, Vec::new(), &dense))
+}
+
+/// Approximate minimum degree column ordering of a general matrix.
+///
+/// The fill-in of the LU factorization with partial pivoting of `A Q`, as
+/// well as the fill-in of its QR factorization, is bounded by the fill-in
+/// of the Cholesky factorization of `Q^T A^T A Q`. This function computes
+/// an approximate minimum degree ordering `Q` of `A^T A` without forming
+/// this product: each row of `A` is seen as a clique over its columns.
+/// Dense rows are ignored, and dense columns are ordered last.
+///
+/// The returned permutation acts on the columns of the matrix.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::linalg::ordering::colamd;
+/// let mat = CsMat::new((3, 4),
+///                      vec![0, 3, 5, 7],
+///                      vec![0, 1, 3, 0, 2, 0, 3],
+///                      vec![1., 2., 3., 4., 5., 6., 7.]);
+/// let perm = colamd(&mat);
+/// // the third column, present in only one row, is eliminated first
+/// assert_eq!(perm.at(0), 2);
+/// ```
+pub fn colamd<N, I, IpS, IS, DS>(mat: &CsMatBase<N, I, IpS, IS, DS>)
+                                 -> PermOwnedI<I>
+where I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    let (nrows, ncols) = mat.shape();
+    let mut rows = vec![Vec::new(); nrows];
+    let mut col_counts = vec![0; ncols];
+    for (outer, vec) in mat.outer_iterator().enumerate() {
+        for (inner, _) in vec.iter() {
+            let (row, col) = if mat.is_csr() { (outer, inner) }
+                             else { (inner, outer) };
+            rows[row].push(col);
+            col_counts[col] += 1;
+        }
+    }
+    let col_threshold = dense_threshold(nrows);
+    let dense: Vec<bool> = col_counts.iter().map(|&c| c > col_threshold)
+                                            .collect();
+    let row_threshold = dense_threshold(ncols);
+    let elems = rows.into_iter().filter_map(|mut cols| {
+        cols.sort();
+        cols.dedup();
+        cols.retain(|&j| !dense[j]);
+        if cols.is_empty() || cols.len() > row_threshold {
+            None
+        }
+        else {
+            Some(cols)
+        }
+    }).collect();
+    to_perm(minimum_degree(vec![Vec::new(); ncols], elems, &dense))
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+    use sparse::{CsMat, TriMat};
+    use sparse::permutation::PermOwned;
+    use test_data::laplacian_2d;
+    use super::{amd, colamd, reverse_cuthill_mckee, nested_dissection,
+                ReorderMethod};
+
+    /// Number of fill-in entries created by the symmetric elimination of
+    /// the graph `adj` in the order given by `perm`
+    fn fill_in(adj: &[BTreeSet<usize>], perm: &PermOwned) -> usize {
+        let mut adj = adj.to_vec();
+        let mut fill = 0;
+        for k in 0..adj.len() {
+            let pivot = perm.at(k);
+            let neighbours: Vec<usize> = adj[pivot].iter().cloned().collect();
+            for &i in &neighbours {
+                adj[i].remove(&pivot);
+                for &j in &neighbours {
+                    if i != j && adj[i].insert(j) {
+                        fill += 1;
+                    }
+                }
+            }
+        }
+        fill
+    }
+
+    fn is_permutation(perm: &PermOwned, n: usize) -> bool {
+        let set: BTreeSet<usize> = (0..n).map(|k| perm.at(k)).collect();
+        set.len() == n && set.iter().all(|&i| i < n)
+    }
+
+    fn graph(mat: &CsMat<f64>) -> Vec<BTreeSet<usize>> {
+        mat.outer_iterator().enumerate().map(|(i, vec)| {
+            vec.iter().map(|(j, _)| j).filter(|&j| j != i).collect()
+        }).collect()
+    }
+
+    #[test]
+    fn amd_reduces_fill() {
+        let mat = laplacian_2d(15);
+        let perm = amd(&mat);
+        assert!(is_permutation(&perm, mat.rows()));
+        let adj = graph(&mat);
+        let natural = fill_in(&adj, &PermOwned::identity(mat.rows()));
+        let ordered = fill_in(&adj, &perm);
+        assert!(2 * ordered < natural);
+    }
+
+    #[test]
+    fn amd_dense_row_last() {
+        // a dense node connected to a long path
+        let n = 300;
+        let mut tri = TriMat::new((n, n));
+        for i in 0..n {
+            tri.add_triplet(i, i, 1.);
+            if i > 0 {
+                tri.add_triplet(0, i, 1.);
+                tri.add_triplet(i, 0, 1.);
+            }
+            if i > 1 {
+                tri.add_triplet(i - 1, i, 1.);
+                tri.add_triplet(i, i - 1, 1.);
+            }
+        }
+        let mat: CsMat<f64> = tri.to_csc();
+        let perm = amd(&mat);
+        assert!(is_permutation(&perm, n));
+        assert_eq!(perm.at(n - 1), 0);
+        assert_eq!(fill_in(&graph(&mat.to_csr()), &perm), 0);
+    }
+
+    #[test]
+    fn colamd_reduces_fill() {
+        // A^T A of the incidence matrix of a grid is its laplacian
+        let side = 12;
+        let n = side * side;
+        let mut tri = TriMat::new((2 * n, n));
+        let mut nb_edges = 0;
+        for i in 0..side {
+            for j in 0..side {
+                let node = i * side + j;
+                if i + 1 < side {
+                    tri.add_triplet(nb_edges, node, 1.);
+                    tri.add_triplet(nb_edges, node + side, -1.);
+                    nb_edges += 1;
+                }
+                if j + 1 < side {
+                    tri.add_triplet(nb_edges, node, 1.);
+                    tri.add_triplet(nb_edges, node + 1, -1.);
+                    nb_edges += 1;
+                }
+            }
+        }
+        let inc: CsMat<f64> = tri.to_csc();
+        let perm = colamd(&inc);
+        assert!(is_permutation(&perm, n));
+        let adj = graph(&laplacian_2d(side));
+        let natural = fill_in(&adj, &PermOwned::identity(n));
+        assert!(2 * fill_in(&adj, &perm) < natural);
+    }
+
+    fn bandwidth(mat: &CsMat<f64>, perm: &PermOwned) -> usize {
+        let n = mat.rows();
+        let mut bandwidth = 0;
+        for (i, vec) in mat.outer_iterator().enumerate() {
+            for (j, _) in vec.iter() {
+                let (pi, pj) = (perm.at_inv(i), perm.at_inv(j));
+                bandwidth = ::std::cmp::max(bandwidth,
+                                            if pi > pj { pi - pj }
+                                            else { pj - pi });
+            }
+        }
+        assert!(bandwidth < n);
+        bandwidth
+    }
+
+    #[test]
+    fn rcm_reduces_bandwidth() {
+        // shuffle the nodes of a grid with a multiplicative permutation
+        let side = 13;
+        let n = side * side;
+        let mat = laplacian_2d(side);
+        let shuffle = PermOwned::new((0..n).map(|i| (i * 23) % n).collect());
+        let mut tri = TriMat::new((n, n));
+        for (i, vec) in mat.outer_iterator().enumerate() {
+            for (j, &val) in vec.iter() {
+                tri.add_triplet(shuffle.at(i), shuffle.at(j), val);
+            }
+        }
+        let shuffled: CsMat<f64> = tri.to_csr();
+        let natural = bandwidth(&shuffled, &PermOwned::identity(n));
+        assert!(natural > 2 * side);
+        let perm = reverse_cuthill_mckee(&shuffled);
+        assert!(is_permutation(&perm, n));
+        assert!(bandwidth(&shuffled, &perm) <= side + 1);
+    }
+
+    #[test]
+    fn rcm_disconnected() {
+        // two disjoint paths and an isolated node
+        let mut tri = TriMat::new((7, 7));
+        for &(i, j) in &[(0, 3), (3, 5), (1, 6), (6, 4)] {
+            tri.add_triplet(i, j, 1.);
+            tri.add_triplet(j, i, 1.);
+        }
+        let mat: CsMat<f64> = tri.to_csr();
+        let perm = reverse_cuthill_mckee(&mat);
+        assert!(is_permutation(&perm, 7));
+        assert_eq!(bandwidth(&mat, &perm), 1);
+    }
+
+    #[test]
+    fn nested_dissection_reduces_fill() {
+        let mat = laplacian_2d(30);
+        let n = mat.rows();
+        let perm = nested_dissection(&mat);
+        assert!(is_permutation(&perm, n));
+        let adj = graph(&mat);
+        let natural = fill_in(&adj, &PermOwned::identity(n));
+        assert!(2 * fill_in(&adj, &perm) < natural);
+    }
+
+    #[test]
+    fn reorder_methods() {
+        let mat = laplacian_2d(9);
+        let n = mat.rows();
+        let methods = [ReorderMethod::Natural,
+                       ReorderMethod::Amd,
+                       ReorderMethod::ReverseCuthillMcKee,
+                       ReorderMethod::NestedDissection];
+        for &method in &methods {
+            assert!(is_permutation(&method.ordering(&mat), n));
+        }
+        let natural = ReorderMethod::Natural.ordering(&mat);
+        assert!((0..n).all(|k| natural.at(k) == k));
+        assert_eq!(ReorderMethod::Amd.ordering(&mat).at(0), amd(&mat).at(0));
+    }
+}
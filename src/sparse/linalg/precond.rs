@@ -0,0 +1,619 @@
+///! Preconditioners for iterative solvers
+///!
+///! A preconditioner approximates the action of the inverse of a matrix,
+///! `z = M^-1 r`. Iterative solvers accept any type implementing the
+///! `Preconditioner` trait, so that they don't need specific parameters
+///! for each preconditioning strategy.
+///!
+///! This module provides the classical Jacobi, block-Jacobi, SSOR and
+///! Chebyshev polynomial preconditioners, along with helpers to compose
+///! preconditioners.
+
+use std::ops::Deref;
+use num_traits::{Num, Float};
+
+use indexing::SpIndex;
+use sparse::prelude::*;
+use sparse::linalg::operator::LinearOperator;
+use sparse::linalg::dense::DenseLu;
+use errors::SprsError;
+use ::SpRes;
+
+/// A preconditioner, approximating the action of the inverse of a
+/// square matrix.
+pub trait Preconditioner<N> {
+    /// The dimension of the vectors this preconditioner operates on
+    fn dim(&self) -> usize;
+
+    /// Compute `z = M^-1 r`, overwriting the contents of `z`.
+    ///
+    /// # Panics
+    ///
+    /// If `r` or `z` do not have length `self.dim()`.
+    fn apply(&self, r: &[N], z: &mut [N]);
+}
+
+impl<'a, N, P: ?Sized> Preconditioner<N> for &'a P
+where P: Preconditioner<N>
+{
+    fn dim(&self) -> usize {
+        (**self).dim()
+    }
+
+    fn apply(&self, r: &[N], z: &mut [N]) {
+        (**self).apply(r, z)
+    }
+}
+
+fn check_precond_dims(dim: usize, r_len: usize, z_len: usize) {
+    if dim != r_len || dim != z_len {
+        panic!("Dimension mismatch");
+    }
+}
+
+fn check_square<N, I, IpS, IS, DS>(mat: &CsMatBase<N, I, IpS, IS, DS>)
+where I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    if mat.rows() != mat.cols() {
+        panic!("Non square matrix passed to preconditioner");
+    }
+}
+
+/// Extract the diagonal of a square matrix, failing on zero entries
+fn nonzero_diagonal<N, I, IpS, IS, DS>(mat: &CsMatBase<N, I, IpS, IS, DS>)
+                                       -> SpRes<Vec<N>>
+where N: Num + Copy,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    let mut diag = vec![N::zero(); mat.outer_dims()];
+    for (outer, vec) in mat.outer_iterator().enumerate() {
+        if let Some(&val) = vec.get(outer) {
+            diag[outer] = val;
+        }
+    }
+    if diag.iter().any(|d| d.is_zero()) {
+        return Err(SprsError::SingularMatrix);
+    }
+    Ok(diag)
+}
+
+/// The identity preconditioner, ie no preconditioning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Identity {
+    dim: usize,
+}
+
+impl Identity {
+    /// Create the identity preconditioner for vectors of dimension `dim`
+    pub fn new(dim: usize) -> Identity {
+        Identity {
+            dim: dim,
+        }
+    }
+}
+
+impl<N: Copy> Preconditioner<N> for Identity {
+    fn dim(&self) -> usize {
+        self.dim
+    }
+
+    fn apply(&self, r: &[N], z: &mut [N]) {
+        check_precond_dims(self.dim, r.len(), z.len());
+        z.copy_from_slice(r);
+    }
+}
+
+/// The Jacobi (diagonal) preconditioner, `M = diag(A)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Jacobi<N> {
+    inv_diag: Vec<N>,
+}
+
+impl<N: Num + Copy> Jacobi<N> {
+    /// Build the Jacobi preconditioner of a square matrix.
+    ///
+    /// # Errors
+    ///
+    /// `SingularMatrix` if a diagonal entry is zero or missing.
+    ///
+    /// # Panics
+    ///
+    /// If the matrix is not square.
+    pub fn new<I, IpS, IS, DS>(mat: &CsMatBase<N, I, IpS, IS, DS>)
+                               -> SpRes<Jacobi<N>>
+    where I: SpIndex,
+          IpS: Deref<Target=[I]>,
+          IS: Deref<Target=[I]>,
+          DS: Deref<Target=[N]>,
+    {
+        check_square(mat);
+        let diag = try!(nonzero_diagonal(mat));
+        Ok(Jacobi {
+            inv_diag: diag.iter().map(|&d| N::one() / d).collect(),
+        })
+    }
+
+    /// The inverse of the diagonal
+    pub fn inv_diag(&self) -> &[N] {
+        &self.inv_diag
+    }
+}
+
+impl<N: Num + Copy> Preconditioner<N> for Jacobi<N> {
+    fn dim(&self) -> usize {
+        self.inv_diag.len()
+    }
+
+    fn apply(&self, r: &[N], z: &mut [N]) {
+        check_precond_dims(self.dim(), r.len(), z.len());
+        for ((zi, &ri), &di) in z.iter_mut().zip(r).zip(&self.inv_diag) {
+            *zi = ri * di;
+        }
+    }
+}
+
+/// The block-Jacobi preconditioner, where `M` is the block diagonal part of
+/// `A` for contiguous blocks of a given size. Each diagonal block is
+/// factorized densely, so the block size should stay small.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockJacobi<N> {
+    dim: usize,
+    block_size: usize,
+    blocks: Vec<DenseLu<N>>,
+}
+
+impl<N: Float> BlockJacobi<N> {
+    /// Build the block-Jacobi preconditioner of a square matrix, using
+    /// diagonal blocks of size `block_size` (the last block may be smaller).
+    ///
+    /// # Errors
+    ///
+    /// `SingularMatrix` if a diagonal block is singular.
+    ///
+    /// # Panics
+    ///
+    /// - if the matrix is not square
+    /// - if `block_size` is zero
+    pub fn new<I, IpS, IS, DS>(mat: &CsMatBase<N, I, IpS, IS, DS>,
+                               block_size: usize)
+                               -> SpRes<BlockJacobi<N>>
+    where I: SpIndex,
+          IpS: Deref<Target=[I]>,
+          IS: Deref<Target=[I]>,
+          DS: Deref<Target=[N]>,
+    {
+        check_square(mat);
+        assert!(block_size > 0, "block size should be positive");
+        let dim = mat.rows();
+        let nb_blocks = (dim + block_size - 1) / block_size;
+        let mut dense_blocks: Vec<Vec<N>> = (0..nb_blocks).map(|b| {
+            let size = block_len(dim, block_size, b);
+            vec![N::zero(); size * size]
+        }).collect();
+        for (outer, vec) in mat.outer_iterator().enumerate() {
+            let block = outer / block_size;
+            let start = block * block_size;
+            let size = block_len(dim, block_size, block);
+            for (inner, &val) in vec.iter() {
+                if inner < start || inner >= start + size {
+                    continue;
+                }
+                let (i, j) = if mat.is_csr() {
+                    (outer - start, inner - start)
+                }
+                else {
+                    (inner - start, outer - start)
+                };
+                dense_blocks[block][i * size + j] = val;
+            }
+        }
+        let mut blocks = Vec::with_capacity(nb_blocks);
+        for (b, dense) in dense_blocks.into_iter().enumerate() {
+            let size = block_len(dim, block_size, b);
+            blocks.push(try!(DenseLu::factorize(size, dense)));
+        }
+        Ok(BlockJacobi {
+            dim: dim,
+            block_size: block_size,
+            blocks: blocks,
+        })
+    }
+
+    /// The size of the diagonal blocks
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+}
+
+fn block_len(dim: usize, block_size: usize, block: usize) -> usize {
+    let start = block * block_size;
+    if start + block_size > dim { dim - start } else { block_size }
+}
+
+impl<N: Float> Preconditioner<N> for BlockJacobi<N> {
+    fn dim(&self) -> usize {
+        self.dim
+    }
+
+    fn apply(&self, r: &[N], z: &mut [N]) {
+        check_precond_dims(self.dim, r.len(), z.len());
+        z.copy_from_slice(r);
+        for (z_block, lu) in z.chunks_mut(self.block_size).zip(&self.blocks) {
+            lu.solve(z_block);
+        }
+    }
+}
+
+/// The symmetric successive over-relaxation (SSOR) preconditioner,
+///
+/// `M = (D + ωL) D^-1 (D + ωU) / (ω (2 - ω))`
+///
+/// where `D`, `L` and `U` are the diagonal, strictly lower and strictly upper
+/// parts of `A`, and `0 < ω < 2` is the relaxation parameter. For `ω = 1`
+/// this is the symmetric Gauss-Seidel preconditioner.
+#[derive(Debug, PartialEq)]
+pub struct Ssor<N, I: SpIndex> {
+    mat: CsMatI<N, I>,
+    diag: Vec<N>,
+    omega: N,
+}
+
+impl<N: Float + Default, I: SpIndex> Ssor<N, I> {
+    /// Build the SSOR preconditioner of a square matrix.
+    ///
+    /// # Errors
+    ///
+    /// `SingularMatrix` if a diagonal entry is zero or missing.
+    ///
+    /// # Panics
+    ///
+    /// - if the matrix is not square
+    /// - if `omega` is not in the open interval `(0, 2)`
+    pub fn new<IpS, IS, DS>(mat: &CsMatBase<N, I, IpS, IS, DS>,
+                            omega: N) -> SpRes<Ssor<N, I>>
+    where IpS: Deref<Target=[I]>,
+          IS: Deref<Target=[I]>,
+          DS: Deref<Target=[N]>,
+    {
+        check_square(mat);
+        let two = N::one() + N::one();
+        assert!(omega > N::zero() && omega < two,
+                "relaxation parameter should be in (0, 2)");
+        let diag = try!(nonzero_diagonal(mat));
+        Ok(Ssor {
+            mat: mat.to_csr(),
+            diag: diag,
+            omega: omega,
+        })
+    }
+
+    /// The relaxation parameter
+    pub fn omega(&self) -> N {
+        self.omega
+    }
+}
+
+impl<N: Float, I: SpIndex> Preconditioner<N> for Ssor<N, I> {
+    fn dim(&self) -> usize {
+        self.diag.len()
+    }
+
+    fn apply(&self, r: &[N], z: &mut [N]) {
+        check_precond_dims(self.dim(), r.len(), z.len());
+        let omega = self.omega;
+        let scale = omega * (N::one() + N::one() - omega);
+        // forward sweep: (D + ωL) y = ω (2 - ω) r, y stored in z
+        for (i, row) in self.mat.outer_iterator().enumerate() {
+            let mut acc = scale * r[i];
+            for (j, &val) in row.iter() {
+                if j < i {
+                    acc = acc - omega * val * z[j];
+                }
+            }
+            z[i] = acc / self.diag[i];
+        }
+        // backward sweep: (D + ωU) z = D y
+        for i in (0..self.dim()).rev() {
+            let row = self.mat.outer_view(i).unwrap();
+            let mut acc = self.diag[i] * z[i];
+            for (j, &val) in row.iter() {
+                if j > i {
+                    acc = acc - omega * val * z[j];
+                }
+            }
+            z[i] = acc / self.diag[i];
+        }
+    }
+}
+
+/// Chebyshev polynomial preconditioner.
+///
+/// Approximates `A^-1 r` by `p(A) r`, where `p` is the polynomial given by
+/// a fixed number of steps of the Chebyshev iteration with a zero initial
+/// guess. This requires bounds `[lambda_min, lambda_max]` enclosing the
+/// spectrum of `A`, which should be symmetric positive definite. Only matrix
+/// vector products are needed, so any `LinearOperator` can be used.
+#[derive(Debug, Clone)]
+pub struct Chebyshev<N, Op> {
+    op: Op,
+    lambda_min: N,
+    lambda_max: N,
+    degree: usize,
+}
+
+impl<N: Float, Op: LinearOperator<N>> Chebyshev<N, Op> {
+    /// Build a Chebyshev preconditioner of degree `degree` for the operator
+    /// `op`, whose eigenvalues lie in `[lambda_min, lambda_max]`.
+    ///
+    /// # Panics
+    ///
+    /// - if the operator is not square
+    /// - unless `0 < lambda_min < lambda_max`
+    /// - if `degree` is zero
+    pub fn new(op: Op,
+               lambda_min: N,
+               lambda_max: N,
+               degree: usize) -> Chebyshev<N, Op> {
+        assert_eq!(op.rows(), op.cols(), "Non square operator");
+        assert!(lambda_min > N::zero() && lambda_min < lambda_max,
+                "invalid eigenvalue bounds");
+        assert!(degree > 0, "polynomial degree should be positive");
+        Chebyshev {
+            op: op,
+            lambda_min: lambda_min,
+            lambda_max: lambda_max,
+            degree: degree,
+        }
+    }
+
+    /// The degree of the polynomial
+    pub fn degree(&self) -> usize {
+        self.degree
+    }
+}
+
+impl<N: Float, Op: LinearOperator<N>> Preconditioner<N> for Chebyshev<N, Op> {
+    fn dim(&self) -> usize {
+        self.op.rows()
+    }
+
+    fn apply(&self, r: &[N], z: &mut [N]) {
+        check_precond_dims(self.dim(), r.len(), z.len());
+        let two = N::one() + N::one();
+        let theta = (self.lambda_max + self.lambda_min) / two;
+        let delta = (self.lambda_max - self.lambda_min) / two;
+        let sigma = theta / delta;
+        let mut rho = N::one() / sigma;
+        let mut res = r.to_vec();
+        let mut dir: Vec<N> = r.iter().map(|&x| x / theta).collect();
+        let mut a_dir = vec![N::zero(); r.len()];
+        for zi in z.iter_mut() {
+            *zi = N::zero();
+        }
+        for step in 0..self.degree {
+            for (zi, &di) in z.iter_mut().zip(&dir) {
+                *zi = *zi + di;
+            }
+            if step + 1 == self.degree {
+                break;
+            }
+            self.op.apply(&dir, &mut a_dir);
+            for (ri, &adi) in res.iter_mut().zip(&a_dir) {
+                *ri = *ri - adi;
+            }
+            let rho_next = N::one() / (two * sigma - rho);
+            for (di, &ri) in dir.iter_mut().zip(&res) {
+                *di = rho_next * rho * *di + two * rho_next / delta * ri;
+            }
+            rho = rho_next;
+        }
+    }
+}
+
+/// Multiplicative composition of two preconditioners,
+/// `M^-1 = M_second^-1 M_first^-1`: `first` is applied, then `second`.
+#[derive(Debug, Clone)]
+pub struct Composed<P1, P2> {
+    first: P1,
+    second: P2,
+}
+
+impl<P1, P2> Composed<P1, P2> {
+    /// Compose two preconditioners, applying `first` then `second`
+    ///
+    /// # Panics
+    ///
+    /// If the preconditioners do not have the same dimension.
+    pub fn new<N>(first: P1, second: P2) -> Composed<P1, P2>
+    where P1: Preconditioner<N>,
+          P2: Preconditioner<N>,
+    {
+        assert_eq!(first.dim(), second.dim(), "Dimension mismatch");
+        Composed {
+            first: first,
+            second: second,
+        }
+    }
+}
+
+impl<N, P1, P2> Preconditioner<N> for Composed<P1, P2>
+where N: Num + Copy,
+      P1: Preconditioner<N>,
+      P2: Preconditioner<N>,
+{
+    fn dim(&self) -> usize {
+        self.first.dim()
+    }
+
+    fn apply(&self, r: &[N], z: &mut [N]) {
+        let mut tmp = vec![N::zero(); r.len()];
+        self.first.apply(r, &mut tmp);
+        self.second.apply(&tmp, z);
+    }
+}
+
+/// Additive combination of two preconditioners,
+/// `M^-1 = M_lhs^-1 + M_rhs^-1`.
+#[derive(Debug, Clone)]
+pub struct Additive<P1, P2> {
+    lhs: P1,
+    rhs: P2,
+}
+
+impl<P1, P2> Additive<P1, P2> {
+    /// Combine two preconditioners additively
+    ///
+    /// # Panics
+    ///
+    /// If the preconditioners do not have the same dimension.
+    pub fn new<N>(lhs: P1, rhs: P2) -> Additive<P1, P2>
+    where P1: Preconditioner<N>,
+          P2: Preconditioner<N>,
+    {
+        assert_eq!(lhs.dim(), rhs.dim(), "Dimension mismatch");
+        Additive {
+            lhs: lhs,
+            rhs: rhs,
+        }
+    }
+}
+
+impl<N, P1, P2> Preconditioner<N> for Additive<P1, P2>
+where N: Num + Copy,
+      P1: Preconditioner<N>,
+      P2: Preconditioner<N>,
+{
+    fn dim(&self) -> usize {
+        self.lhs.dim()
+    }
+
+    fn apply(&self, r: &[N], z: &mut [N]) {
+        let mut tmp = vec![N::zero(); r.len()];
+        self.lhs.apply(r, z);
+        self.rhs.apply(r, &mut tmp);
+        for (zi, &ti) in z.iter_mut().zip(&tmp) {
+            *zi = *zi + ti;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{arr1, arr2, Array};
+    use sparse::CsMat;
+    use errors::SprsError;
+    use super::{Preconditioner, Identity, Jacobi, BlockJacobi, Ssor,
+                Chebyshev, Composed, Additive};
+
+    fn spd_mat() -> CsMat<f64> {
+        // | 4 -1  0  1|
+        // |-1  4 -1  0|
+        // | 0 -1  4 -1|
+        // | 1  0 -1  4|
+        CsMat::new((4, 4),
+                   vec![0, 3, 6, 9, 12],
+                   vec![0, 1, 3, 0, 1, 2, 1, 2, 3, 0, 2, 3],
+                   vec![4., -1., 1., -1., 4., -1., -1., 4., -1., 1., -1., 4.])
+    }
+
+    fn apply<P: Preconditioner<f64>>(p: &P, r: &[f64]) -> Vec<f64> {
+        let mut z = vec![0.; r.len()];
+        p.apply(r, &mut z);
+        z
+    }
+
+    fn assert_close(a: &[f64], b: &[f64]) {
+        assert_eq!(a.len(), b.len());
+        for (x, y) in a.iter().zip(b) {
+            assert!((x - y).abs() < 1e-10, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn identity_and_jacobi() {
+        let r = [1., 2., 3., 4.];
+        assert_eq!(apply(&Identity::new(4), &r), r.to_vec());
+        let jac = Jacobi::new(&spd_mat()).unwrap();
+        assert_eq!(apply(&jac, &r), vec![0.25, 0.5, 0.75, 1.]);
+        let singular = CsMat::new((2, 2), vec![0, 1, 2], vec![1, 0],
+                                  vec![1., 1.]);
+        assert_eq!(Jacobi::new(&singular), Err(SprsError::SingularMatrix));
+    }
+
+    #[test]
+    fn block_jacobi() {
+        let a = spd_mat();
+        let r = [1., 2., 3., 4.];
+        // a single block is an exact solve
+        let exact = BlockJacobi::new(&a, 4).unwrap();
+        let z = apply(&exact, &r);
+        assert_close(&(&a * &arr1(&z)).to_vec(), &r);
+        // blocks of size 3 then 1, also checking csc input
+        let bj = BlockJacobi::new(&a.to_csc(), 3).unwrap();
+        let z = apply(&bj, &r);
+        let block = arr2(&[[4., -1., 0.], [-1., 4., -1.], [0., -1., 4.]]);
+        assert_close(&block.dot(&arr1(&z[..3])).to_vec(), &r[..3]);
+        assert_close(&z[3..], &[1.]);
+    }
+
+    #[test]
+    fn ssor() {
+        let a = spd_mat();
+        let omega = 1.2;
+        let ssor = Ssor::new(&a, omega).unwrap();
+        let r = [1., -2., 3., 0.5];
+        let z = apply(&ssor, &r);
+        // check that M z = r, with M = (D + ωL) D^-1 (D + ωU) / (ω (2 - ω))
+        let dense = a.to_dense();
+        let mut lower = Array::zeros((4, 4));
+        let mut upper = Array::zeros((4, 4));
+        let mut diag_inv = Array::zeros((4, 4));
+        for i in 0..4 {
+            for j in 0..4 {
+                if i == j {
+                    lower[[i, j]] = dense[[i, j]];
+                    upper[[i, j]] = dense[[i, j]];
+                    diag_inv[[i, j]] = 1. / dense[[i, j]];
+                }
+                else if j < i {
+                    lower[[i, j]] = omega * dense[[i, j]];
+                }
+                else {
+                    upper[[i, j]] = omega * dense[[i, j]];
+                }
+            }
+        }
+        let m = lower.dot(&diag_inv).dot(&upper) / (omega * (2. - omega));
+        assert_close(&m.dot(&arr1(&z)).to_vec(), &r);
+    }
+
+    #[test]
+    fn chebyshev_converges_to_inverse() {
+        let a = CsMat::new((3, 3), vec![0, 1, 2, 3], vec![0, 1, 2],
+                           vec![1., 2., 3.]);
+        let cheb = Chebyshev::new(&a, 1., 3., 30);
+        assert_eq!(cheb.degree(), 30);
+        let z = apply(&cheb, &[1., 1., 1.]);
+        assert_close(&z, &[1., 0.5, 1. / 3.]);
+        // degree one is a scaling by the inverse of the spectrum center
+        let cheb = Chebyshev::new(&a, 1., 3., 1);
+        assert_close(&apply(&cheb, &[2., 4., 6.]), &[1., 2., 3.]);
+    }
+
+    #[test]
+    fn compositions() {
+        let a = spd_mat();
+        let jac = Jacobi::new(&a).unwrap();
+        let r = [4., 4., 4., 4.];
+        let composed = Composed::new(&jac, &jac);
+        assert_eq!(apply(&composed, &r), vec![0.25; 4]);
+        let additive = Additive::new(&jac, Identity::new(4));
+        assert_eq!(apply(&additive, &r), vec![5.; 4]);
+    }
+}
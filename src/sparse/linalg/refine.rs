@@ -0,0 +1,291 @@
+///! Iterative refinement of direct solves
+///!
+///! A solution computed from a factorization can be inaccurate when the
+///! matrix is ill conditioned, or when the factorization itself is
+///! approximate, eg computed in lower precision or with a perturbed pivot.
+///! Iterative refinement corrects the solution by solving for the residual
+///! `b - A x` with the same factorization, and monitors the componentwise
+///! backward error of the solution to decide when to stop.
+
+use num_traits::Float;
+
+use indexing::SpIndex;
+use sparse::prelude::*;
+use sparse::linalg::LinearSolver;
+
+/// Parameters controlling an iterative refinement.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RefinementOptions<N> {
+    /// The maximum number of correction steps
+    pub max_iter: usize,
+    /// Stop once the componentwise backward error is at most this value
+    pub tol: N,
+    /// Compute the residuals in about twice the working precision, using
+    /// compensated arithmetic
+    pub extended_precision: bool,
+}
+
+impl<N: Float> Default for RefinementOptions<N> {
+    fn default() -> RefinementOptions<N> {
+        RefinementOptions {
+            max_iter: 10,
+            tol: N::epsilon(),
+            extended_precision: false,
+        }
+    }
+}
+
+/// Outcome of an iterative refinement
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Refinement<N> {
+    /// The number of correction steps performed
+    pub iterations: usize,
+    /// The componentwise backward error of the final solution, ie the
+    /// smallest `w` such that the solution solves exactly a system whose
+    /// matrix and right hand side are perturbed by at most `w` relatively
+    /// to each of their entries
+    pub backward_error: N,
+    /// Whether the requested backward error was reached
+    pub converged: bool,
+}
+
+/// Error free transformation of a sum, `a + b = s + e`
+fn two_sum<N: Float>(a: N, b: N) -> (N, N) {
+    let s = a + b;
+    let z = s - a;
+    (s, (a - (s - z)) + (b - z))
+}
+
+/// A direct solver whose solutions are improved by iterative refinement.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::linalg::LinearSolver;
+/// use sprs::linalg::refine::{RefinedSolver, RefinementOptions};
+/// // a crude solver, only inverting the diagonal of the matrix
+/// struct DiagSolver(Vec<f64>);
+/// impl LinearSolver<f64> for DiagSolver {
+///     fn dim(&self) -> usize { self.0.len() }
+///     fn solve(&self, rhs: &[f64], x: &mut [f64]) {
+///         for ((xi, bi), di) in x.iter_mut().zip(rhs).zip(&self.0) {
+///             *xi = bi / di;
+///         }
+///     }
+/// }
+/// let mat = CsMat::new((3, 3),
+///                      vec![0, 2, 5, 7],
+///                      vec![0, 1, 0, 1, 2, 1, 2],
+///                      vec![4., -1., -1., 4., -1., -1., 4.]);
+/// let mut options = RefinementOptions::default();
+/// options.max_iter = 100;
+/// let solver = RefinedSolver::new(mat.view(),
+///                                 DiagSolver(vec![4., 4., 4.]),
+///                                 options);
+/// let mut x = [0.; 3];
+/// let res = solver.solve_refined(&[3., 2., 3.], &mut x);
+/// assert!(res.converged);
+/// assert!(x.iter().all(|&xi| (xi - 1.).abs() < 1e-14));
+/// ```
+pub struct RefinedSolver<'a, N: 'a, I: 'a + SpIndex, S> {
+    mat: CsMatViewI<'a, N, I>,
+    solver: S,
+    options: RefinementOptions<N>,
+}
+
+impl<'a, N, I, S> RefinedSolver<'a, N, I, S>
+where N: Float,
+      I: SpIndex,
+      S: LinearSolver<N>,
+{
+    /// Refine the solutions of `solver`, which solves systems involving the
+    /// matrix `mat`.
+    ///
+    /// # Panics
+    ///
+    /// - if `mat` is not square
+    /// - if `solver` does not have the dimension of `mat`
+    pub fn new(mat: CsMatViewI<'a, N, I>,
+               solver: S,
+               options: RefinementOptions<N>) -> RefinedSolver<'a, N, I, S> {
+        assert_eq!(mat.rows(), mat.cols(), "Non square matrix");
+        if solver.dim() != mat.rows() {
+            panic!("Dimension mismatch");
+        }
+        RefinedSolver {
+            mat: mat,
+            solver: solver,
+            options: options,
+        }
+    }
+
+    /// The wrapped solver
+    pub fn inner(&self) -> &S {
+        &self.solver
+    }
+
+    /// Solve `A x = rhs`, overwriting the contents of `x`, and report the
+    /// backward error of the solution.
+    ///
+    /// The refinement stops when the backward error reaches the requested
+    /// tolerance, when it stops decreasing, or after `max_iter` steps. In
+    /// the latter cases, the most accurate solution found is returned.
+    ///
+    /// # Panics
+    ///
+    /// If `rhs` or `x` do not have length `self.dim()`.
+    pub fn solve_refined(&self, rhs: &[N], x: &mut [N]) -> Refinement<N> {
+        let n = self.mat.rows();
+        if rhs.len() != n || x.len() != n {
+            panic!("Dimension mismatch");
+        }
+        self.solver.solve(rhs, x);
+        let mut res = vec![N::zero(); n];
+        let mut correction = vec![N::zero(); n];
+        let mut prev_x = x.to_vec();
+        let mut backward_error = self.residual(rhs, x, &mut res);
+        let mut iterations = 0;
+        while backward_error > self.options.tol
+              && iterations < self.options.max_iter {
+            self.solver.solve(&res, &mut correction);
+            prev_x.copy_from_slice(x);
+            for (xi, &ci) in x.iter_mut().zip(&correction) {
+                *xi = *xi + ci;
+            }
+            iterations += 1;
+            let error = self.residual(rhs, x, &mut res);
+            if !(error < backward_error) {
+                // no more progress, keep the previous solution
+                x.copy_from_slice(&prev_x);
+                break;
+            }
+            backward_error = error;
+        }
+        Refinement {
+            iterations: iterations,
+            backward_error: backward_error,
+            converged: backward_error <= self.options.tol,
+        }
+    }
+
+    /// Compute the residual `rhs - A x` and return the componentwise
+    /// backward error `max_i |r_i| / (|A| |x| + |rhs|)_i`
+    fn residual(&self, rhs: &[N], x: &[N], res: &mut [N]) -> N {
+        let n = rhs.len();
+        let mut scale: Vec<N> = rhs.iter().map(|b| b.abs()).collect();
+        let mut errors = vec![N::zero(); n];
+        res.copy_from_slice(rhs);
+        let is_csr = self.mat.is_csr();
+        for (outer, vec) in self.mat.outer_iterator().enumerate() {
+            for (inner, &val) in vec.iter() {
+                let (row, col) = if is_csr { (outer, inner) }
+                                 else { (inner, outer) };
+                let prod = val * x[col];
+                scale[row] = scale[row] + prod.abs();
+                if self.options.extended_precision {
+                    let prod_error = val.mul_add(x[col], -prod);
+                    let (sum, sum_error) = two_sum(res[row], -prod);
+                    res[row] = sum;
+                    errors[row] = errors[row] + (sum_error - prod_error);
+                }
+                else {
+                    res[row] = res[row] - prod;
+                }
+            }
+        }
+        let mut backward_error = N::zero();
+        for ((ri, &ei), &si) in res.iter_mut().zip(&errors).zip(&scale) {
+            *ri = *ri + ei;
+            let error = if si > N::zero() { ri.abs() / si }
+                        else if ri.is_zero() { N::zero() }
+                        else { N::infinity() };
+            backward_error = backward_error.max(error);
+        }
+        backward_error
+    }
+}
+
+impl<'a, N, I, S> LinearSolver<N> for RefinedSolver<'a, N, I, S>
+where N: Float,
+      I: SpIndex,
+      S: LinearSolver<N>,
+{
+    fn dim(&self) -> usize {
+        self.mat.rows()
+    }
+
+    fn solve(&self, rhs: &[N], x: &mut [N]) {
+        self.solve_refined(rhs, x);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sparse::{CsMat, TriMat};
+    use sparse::linalg::LinearSolver;
+    use sparse::linalg::dense::DenseLu;
+    use super::{RefinedSolver, RefinementOptions};
+
+    fn test_mat(n: usize) -> CsMat<f64> {
+        let mut tri = TriMat::new((n, n));
+        for i in 0..n {
+            tri.add_triplet(i, i, 2. + (i as f64).sin());
+            if i + 1 < n {
+                tri.add_triplet(i, i + 1, -1.);
+                tri.add_triplet(i + 1, i, -0.7);
+            }
+        }
+        tri.to_csc()
+    }
+
+    fn perturbed_lu(mat: &CsMat<f64>, perturbation: f64) -> DenseLu<f64> {
+        let n = mat.rows();
+        let mut dense = vec![0.; n * n];
+        for (col, vec) in mat.outer_iterator().enumerate() {
+            for (row, &val) in vec.iter() {
+                dense[row * n + col] = val * (1. + perturbation);
+            }
+        }
+        DenseLu::factorize(n, dense).unwrap()
+    }
+
+    #[test]
+    fn refinement_of_inexact_factorization() {
+        let n = 30;
+        let mat = test_mat(n);
+        let lu = perturbed_lu(&mat, 1e-6);
+        let x0: Vec<f64> = (0..n).map(|i| (i as f64).cos()).collect();
+        let mut b = vec![0.; n];
+        for (col, vec) in mat.outer_iterator().enumerate() {
+            for (row, &val) in vec.iter() {
+                b[row] += val * x0[col];
+            }
+        }
+
+        let mut options = RefinementOptions::default();
+        options.max_iter = 0;
+        let unrefined = RefinedSolver::new(mat.view(), &lu, options);
+        let mut x = vec![0.; n];
+        let res = unrefined.solve_refined(&b, &mut x);
+        assert!(!res.converged);
+        assert!(res.backward_error > 1e-8);
+
+        for &extended in &[false, true] {
+            let mut options = RefinementOptions::default();
+            options.extended_precision = extended;
+            options.tol = 4. * ::std::f64::EPSILON;
+            let refined = RefinedSolver::new(mat.view(), &lu, options);
+            let res = refined.solve_refined(&b, &mut x);
+            assert!(res.converged);
+            assert!(res.iterations > 0 && res.iterations <= 5);
+            for (xi, x0i) in x.iter().zip(&x0) {
+                assert!((xi - x0i).abs() < 1e-12);
+            }
+            // as a LinearSolver
+            let mut y = vec![0.; n];
+            refined.solve(&b, &mut y);
+            assert_eq!(x, y);
+        }
+    }
+}
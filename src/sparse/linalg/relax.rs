@@ -0,0 +1,393 @@
+///! Stationary relaxation methods
+///!
+///! Relaxation sweeps update the unknowns of `A x = b` one equation at a
+///! time: weighted Jacobi uses the previous iterate for every unknown, while
+///! Gauss-Seidel and successive over relaxation (SOR) use the values already
+///! updated during the sweep. They converge slowly when used alone, but
+///! they damp the oscillatory components of the error very quickly, which
+///! makes them the smoothers of choice for multigrid methods.
+///!
+///! The sweeps require CSR matrices with non-zero diagonal entries.
+
+use std::ops::Deref;
+use num_traits::Float;
+
+use indexing::SpIndex;
+use sparse::prelude::*;
+use sparse::prod;
+use sparse::linalg::Convergence;
+use sparse::linalg::precond::Jacobi;
+use sparse::linalg::chebyshev::{chebyshev, estimate_max_eigenvalue};
+use errors::SprsError;
+use ::SpRes;
+
+/// The order in which the unknowns are updated by a Gauss-Seidel sweep
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SweepDirection {
+    /// Update the unknowns by increasing index
+    Forward,
+    /// Update the unknowns by decreasing index
+    Backward,
+    /// A forward sweep followed by a backward sweep, which gives a
+    /// symmetric iteration for symmetric matrices
+    Symmetric,
+}
+
+fn check_dims<N, I, IpS, IS, DS>(a: &CsMatBase<N, I, IpS, IS, DS>,
+                                 x_len: usize,
+                                 b_len: usize)
+where I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    if !a.is_csr() {
+        panic!("Storage mismatch");
+    }
+    if a.rows() != a.cols() || x_len != a.rows() || b_len != a.rows() {
+        panic!("Dimension mismatch");
+    }
+}
+
+/// Update `x[row]` from its equation, using the current values of the
+/// other unknowns
+fn relax_row<N: Float, I: SpIndex>(row: usize,
+                                   vec: CsVecViewI<N, I>,
+                                   x: &mut [N],
+                                   b: &[N],
+                                   omega: N) -> SpRes<()> {
+    let mut diag = N::zero();
+    let mut acc = b[row];
+    for (col, &val) in vec.iter() {
+        if col == row {
+            diag = val;
+        }
+        else {
+            acc = acc - val * x[col];
+        }
+    }
+    if diag.is_zero() {
+        return Err(SprsError::SingularMatrix);
+    }
+    x[row] = (N::one() - omega) * x[row] + omega * acc / diag;
+    Ok(())
+}
+
+/// Perform one Gauss-Seidel sweep with relaxation parameter `omega` on the
+/// system `a x = b`, updating `x` in place.
+///
+/// `omega = 1` gives the Gauss-Seidel method, while other values give the
+/// successive over relaxation (SOR) method, which converges for symmetric
+/// positive definite matrices when `0 < omega < 2`.
+///
+/// # Errors
+///
+/// `SingularMatrix` if a diagonal entry is zero or missing, in which case
+/// `x` is left partially updated.
+///
+/// # Panics
+///
+/// - if `a` is not a square CSR matrix
+/// - if `x` or `b` do not have the dimension of `a`
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::linalg::relax::{gauss_seidel_sweep, SweepDirection};
+/// let mat = CsMat::new((2, 2), vec![0, 2, 4], vec![0, 1, 0, 1],
+///                      vec![2., -1., -1., 2.]);
+/// let mut x = [0., 0.];
+/// gauss_seidel_sweep(&mat, &mut x, &[1., 1.], 1.,
+///                    SweepDirection::Forward).unwrap();
+/// assert_eq!(x, [0.5, 0.75]);
+/// ```
+pub fn gauss_seidel_sweep<N, I, IpS, IS, DS>(a: &CsMatBase<N, I, IpS,
+                                                           IS, DS>,
+                                             x: &mut [N],
+                                             b: &[N],
+                                             omega: N,
+                                             direction: SweepDirection)
+                                             -> SpRes<()>
+where N: Float,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    check_dims(a, x.len(), b.len());
+    if direction != SweepDirection::Backward {
+        for (row, vec) in a.outer_iterator().enumerate() {
+            try!(relax_row(row, vec, x, b, omega));
+        }
+    }
+    if direction != SweepDirection::Forward {
+        for row in (0..a.rows()).rev() {
+            let vec = a.outer_view(row).unwrap();
+            try!(relax_row(row, vec, x, b, omega));
+        }
+    }
+    Ok(())
+}
+
+/// Perform one weighted Jacobi sweep, `x += omega D^-1 (b - a x)`, where
+/// `D` is the diagonal of `a`.
+///
+/// # Errors
+///
+/// `SingularMatrix` if a diagonal entry is zero or missing, in which case
+/// `x` is left unchanged.
+///
+/// # Panics
+///
+/// - if `a` is not a square CSR matrix
+/// - if `x` or `b` do not have the dimension of `a`
+pub fn jacobi_sweep<N, I, IpS, IS, DS>(a: &CsMatBase<N, I, IpS, IS, DS>,
+                                       x: &mut [N],
+                                       b: &[N],
+                                       omega: N) -> SpRes<()>
+where N: Float,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    check_dims(a, x.len(), b.len());
+    let mut diag = Vec::with_capacity(a.rows());
+    for (row, vec) in a.outer_iterator().enumerate() {
+        match vec.get(row) {
+            Some(&d) if !d.is_zero() => diag.push(d),
+            _ => return Err(SprsError::SingularMatrix),
+        }
+    }
+    let mut ax = vec![N::zero(); a.rows()];
+    prod::mul_acc_mat_vec_csr(a.view(), &*x, &mut ax);
+    for (((xi, &axi), &bi), &di) in x.iter_mut().zip(&ax).zip(b).zip(&diag) {
+        *xi = *xi + omega * (bi - axi) / di;
+    }
+    Ok(())
+}
+
+/// A relaxation method, usable as a stand-alone solver with
+/// `stationary_solve` or as a multigrid smoother.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Smoother<N> {
+    /// Weighted Jacobi with the given weight
+    Jacobi(N),
+    /// Gauss-Seidel, or SOR, with the given relaxation parameter
+    GaussSeidel(N, SweepDirection),
+    /// Chebyshev iteration with the given number of steps, preconditioned
+    /// by the diagonal `D` of the matrix. It damps the eigenvalues of
+    /// `D^-1 A` in `[rho / 30, 1.1 rho]`, where `rho` is the spectral
+    /// radius of `D^-1 A`.
+    Chebyshev(usize),
+}
+
+/// Ratio between the largest and the smallest eigenvalues damped by
+/// Chebyshev smoothing
+const CHEBYSHEV_RATIO: f64 = 30.;
+
+/// Safety factor enlarging the estimates of the spectral radius
+const CHEBYSHEV_SAFETY: f64 = 1.1;
+
+impl<N: Float> Smoother<N> {
+    /// Perform one sweep of this method on `a x = b`, see `jacobi_sweep`
+    /// and `gauss_seidel_sweep`. Chebyshev smoothing estimates the
+    /// spectral radius of `D^-1 A` with a few power iterations.
+    pub fn sweep<I, IpS, IS, DS>(&self,
+                                 a: &CsMatBase<N, I, IpS, IS, DS>,
+                                 x: &mut [N],
+                                 b: &[N]) -> SpRes<()>
+    where I: SpIndex,
+          IpS: Deref<Target=[I]>,
+          IS: Deref<Target=[I]>,
+          DS: Deref<Target=[N]>,
+    {
+        self.sweep_impl(a, x, b, None)
+    }
+
+    /// Perform one sweep of this method on `a x = b`, given an estimate of
+    /// the spectral radius of `D^-1 A`, which Chebyshev smoothing would
+    /// otherwise compute at each sweep.
+    pub fn sweep_with_bound<I, IpS, IS, DS>(&self,
+                                            a: &CsMatBase<N, I, IpS, IS, DS>,
+                                            x: &mut [N],
+                                            b: &[N],
+                                            spectral_radius: N) -> SpRes<()>
+    where I: SpIndex,
+          IpS: Deref<Target=[I]>,
+          IS: Deref<Target=[I]>,
+          DS: Deref<Target=[N]>,
+    {
+        self.sweep_impl(a, x, b, Some(spectral_radius))
+    }
+
+    fn sweep_impl<I, IpS, IS, DS>(&self,
+                                  a: &CsMatBase<N, I, IpS, IS, DS>,
+                                  x: &mut [N],
+                                  b: &[N],
+                                  spectral_radius: Option<N>) -> SpRes<()>
+    where I: SpIndex,
+          IpS: Deref<Target=[I]>,
+          IS: Deref<Target=[I]>,
+          DS: Deref<Target=[N]>,
+    {
+        match *self {
+            Smoother::Jacobi(omega) => jacobi_sweep(a, x, b, omega),
+            Smoother::GaussSeidel(omega, direction) => {
+                gauss_seidel_sweep(a, x, b, omega, direction)
+            }
+            Smoother::Chebyshev(degree) => {
+                check_dims(a, x.len(), b.len());
+                let jacobi = try!(Jacobi::new(a));
+                let rho = spectral_radius.unwrap_or_else(|| {
+                    estimate_max_eigenvalue(a, &jacobi, 10)
+                });
+                let upper = N::from(CHEBYSHEV_SAFETY).unwrap() * rho;
+                let lower = upper / N::from(CHEBYSHEV_RATIO).unwrap();
+                chebyshev(a, &jacobi, b, x, lower, upper, N::zero(), degree);
+                Ok(())
+            }
+        }
+    }
+
+    /// The method whose sweeps are the adjoints of the sweeps of this one,
+    /// ie a forward Gauss-Seidel becomes a backward one. Pre-smoothing with
+    /// a method and post-smoothing with its transpose keeps a multigrid
+    /// cycle symmetric.
+    pub fn transposed(&self) -> Smoother<N> {
+        match *self {
+            Smoother::GaussSeidel(omega, SweepDirection::Forward) => {
+                Smoother::GaussSeidel(omega, SweepDirection::Backward)
+            }
+            Smoother::GaussSeidel(omega, SweepDirection::Backward) => {
+                Smoother::GaussSeidel(omega, SweepDirection::Forward)
+            }
+            other => other,
+        }
+    }
+}
+
+fn norm<N: Float>(x: &[N]) -> N {
+    x.iter().fold(N::zero(), |acc, &xi| acc + xi * xi).sqrt()
+}
+
+/// Solve `a x = b` by repeating the sweeps of `smoother`.
+///
+/// `x` holds the initial guess on entry and the solution on exit. The
+/// iteration stops when the euclidean norm of the residual falls below
+/// `tol` times the norm of `b`, or after `max_iter` sweeps.
+///
+/// # Errors
+///
+/// `SingularMatrix` if a diagonal entry is zero or missing.
+///
+/// # Panics
+///
+/// - if `a` is not a square CSR matrix
+/// - if `x` or `b` do not have the dimension of `a`
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::linalg::relax::{stationary_solve, Smoother, SweepDirection};
+/// let mat = CsMat::new((3, 3),
+///                      vec![0, 2, 5, 7],
+///                      vec![0, 1, 0, 1, 2, 1, 2],
+///                      vec![4., -1., -1., 4., -1., -1., 4.]);
+/// let smoother = Smoother::GaussSeidel(1., SweepDirection::Symmetric);
+/// let mut x = [0.; 3];
+/// let res = stationary_solve(&mat, &[3., 2., 3.], &mut x, smoother,
+///                            1e-12, 50).unwrap();
+/// assert!(res.converged);
+/// assert!(x.iter().all(|&xi| (xi - 1f64).abs() < 1e-10));
+/// ```
+pub fn stationary_solve<N, I, IpS, IS, DS>(a: &CsMatBase<N, I, IpS, IS, DS>,
+                                           b: &[N],
+                                           x: &mut [N],
+                                           smoother: Smoother<N>,
+                                           tol: N,
+                                           max_iter: usize)
+                                           -> SpRes<Convergence<N>>
+where N: Float,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    check_dims(a, x.len(), b.len());
+    let b_norm = norm(b);
+    let threshold = if b_norm > N::zero() { tol * b_norm } else { tol };
+    let mut res = vec![N::zero(); b.len()];
+    let residual_norm = |x: &[N], res: &mut [N]| {
+        res.copy_from_slice(b);
+        for (row, vec) in a.outer_iterator().enumerate() {
+            for (col, &val) in vec.iter() {
+                res[row] = res[row] - val * x[col];
+            }
+        }
+        norm(res)
+    };
+    let mut res_norm = residual_norm(x, &mut res);
+    let mut iterations = 0;
+    while res_norm > threshold && iterations < max_iter {
+        try!(smoother.sweep(a, x, b));
+        iterations += 1;
+        res_norm = residual_norm(x, &mut res);
+    }
+    Ok(Convergence {
+        iterations: iterations,
+        residual_norm: res_norm,
+        converged: res_norm <= threshold,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use sparse::CsMat;
+    use test_data::laplacian_1d;
+    use errors::SprsError;
+    use super::{stationary_solve, gauss_seidel_sweep, Smoother,
+                SweepDirection};
+
+    #[test]
+    fn relaxation_solvers_converge() {
+        let n = 10;
+        let mat = laplacian_1d(n);
+        let b: Vec<f64> = (0..n).map(|i| 1. + i as f64).collect();
+        let smoothers = [
+            Smoother::Jacobi(1.),
+            Smoother::Jacobi(2. / 3.),
+            Smoother::GaussSeidel(1., SweepDirection::Forward),
+            Smoother::GaussSeidel(1., SweepDirection::Backward),
+            Smoother::GaussSeidel(1., SweepDirection::Symmetric),
+            Smoother::GaussSeidel(1.5, SweepDirection::Forward),
+            Smoother::Chebyshev(3),
+        ];
+        let iterations: Vec<usize> = smoothers.iter().map(|&smoother| {
+            let mut x = vec![0.; n];
+            let res = stationary_solve(&mat, &b, &mut x, smoother,
+                                       1e-10, 2000).unwrap();
+            assert!(res.converged);
+            res.iterations
+        }).collect();
+        // Gauss-Seidel converges about twice as fast as Jacobi, and is
+        // accelerated by symmetric sweeps and by over relaxation
+        assert!(iterations[2] < iterations[0]);
+        assert!(iterations[4] < iterations[2]);
+        assert!(iterations[5] < iterations[2]);
+    }
+
+    #[test]
+    fn zero_diagonal() {
+        let mat = CsMat::new((2, 2), vec![0, 1, 2], vec![1, 0],
+                             vec![1., 1.]);
+        let mut x = [0., 0.];
+        let res = gauss_seidel_sweep(&mat, &mut x, &[1., 1.], 1.,
+                                     SweepDirection::Symmetric);
+        assert_eq!(res, Err(SprsError::SingularMatrix));
+        let res = Smoother::Jacobi(1.).sweep(&mat, &mut x, &[1., 1.]);
+        assert_eq!(res, Err(SprsError::SingularMatrix));
+    }
+}
@@ -0,0 +1,184 @@
+///! Selected inversion of sparse symmetric matrices
+///!
+///! The inverse of a sparse matrix is usually dense, but some applications
+///! only need a few of its entries: the diagonal of the inverse of a
+///! covariance matrix gives the marginal variances of a gaussian model, and
+///! the diagonal of the inverse of a hamiltonian gives the local density of
+///! states in electronic structure computations.
+///!
+///! Given the factorization `A = L D L^T` of a symmetric matrix, the
+///! entries of `Z = A^-1` on the non-zero pattern of `L` satisfy the
+///! Takahashi recurrences, obtained from `Z = D^-1 L^-1 + (I - L^T) Z`:
+///!
+///! ```text
+///! Z_ij = - sum_{k > j, L_kj != 0} Z_ik L_kj          for i > j
+///! Z_jj = 1 / D_j - sum_{k > j, L_kj != 0} L_kj Z_kj
+///! ```
+///!
+///! Since the pattern of each column of `L` is a clique in the graph of the
+///! filled matrix, every `Z_ik` involved lies on the pattern of `L` as well,
+///! and the recurrences can be solved from the last column to the first
+///! without computing any other entry of the inverse.
+///!
+///! A Cholesky factor `R`, with `A = R R^T`, gives such a factorization with
+///! `L = R diag(R)^-1` and `D = diag(R)^2`.
+
+use num_traits::Num;
+
+use indexing::SpIndex;
+use sparse::{CsMatI, CsMatViewI};
+
+/// Compute the entries of `A^-1` on the lower triangular pattern of the
+/// factor `l` and on the diagonal, given the factorization `A = L D L^T`.
+///
+/// `l` is the strictly lower triangular part of the unit lower triangular
+/// factor `L`, in CSC storage with sorted indices, as produced by an LDL^T
+/// factorization, and `diag` is the diagonal of `D`. The result is the
+/// lower triangle of the selected inverse, in CSC storage, where each
+/// column `j` holds the diagonal entry followed by the entries on the
+/// pattern of column `j` of `l`.
+///
+/// # Panics
+///
+/// - if `l` is not square or `diag` does not have its dimension
+/// - if `l` is not in CSC storage
+/// - if `l` has entries on or above its diagonal
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::linalg::selected_inverse;
+/// // A = | 4 2 | = | 1   | | 4   | | 1 .5 |
+/// //     | 2 2 |   | .5 1 | |   1 | |    1 |
+/// let l = CsMat::new_csc((2, 2), vec![0, 1, 1], vec![1], vec![0.5]);
+/// let z = selected_inverse(l.view(), &[4., 1.]);
+/// // A^-1 = | .5  -.5 |
+/// //        | -.5   1 |
+/// assert_eq!(z.data(), &[0.5, -0.5, 1.]);
+/// ```
+pub fn selected_inverse<N, I>(l: CsMatViewI<N, I>, diag: &[N]) -> CsMatI<N, I>
+where N: Num + Copy,
+      I: SpIndex,
+{
+    let n = l.rows();
+    if l.cols() != n || diag.len() != n {
+        panic!("Dimension mismatch");
+    }
+    if !l.is_csc() {
+        panic!("Storage mismatch");
+    }
+
+    // the pattern of the result is the pattern of l with the diagonal
+    let mut indptr = Vec::with_capacity(n + 1);
+    let mut indices = Vec::with_capacity(l.nnz() + n);
+    indptr.push(I::zero());
+    for (col, vec) in l.outer_iterator().enumerate() {
+        indices.push(I::from_usize(col));
+        for &row in vec.indices() {
+            if row.index() <= col {
+                panic!("Non strictly lower triangular factor");
+            }
+            indices.push(row);
+        }
+        indptr.push(I::from_usize(indices.len()));
+    }
+    let mut data = vec![N::zero(); indices.len()];
+
+    for col in (0..n).rev() {
+        let start = indptr[col].index();
+        let end = indptr[col + 1].index();
+        let l_col = l.outer_view(col).expect("col in bounds");
+        let (l_rows, l_vals) = (l_col.indices(), l_col.data());
+        // off diagonal entries, using the columns already computed
+        for (pos, &row) in l_rows.iter().enumerate() {
+            let row = row.index();
+            let mut val = N::zero();
+            for (&k, &l_kj) in l_rows.iter().zip(l_vals) {
+                let k = k.index();
+                let z_ik = {
+                    let (i, j) = if row >= k { (row, k) } else { (k, row) };
+                    lower_entry(&indptr, &indices, &data, i, j)
+                };
+                val = val - z_ik * l_kj;
+            }
+            data[start + 1 + pos] = val;
+        }
+        // diagonal entry
+        let mut val = N::one() / diag[col];
+        for (&l_kj, &z_kj) in l_vals.iter().zip(&data[start + 1..end]) {
+            val = val - l_kj * z_kj;
+        }
+        data[start] = val;
+    }
+
+    CsMatI::new_csc((n, n), indptr, indices, data)
+}
+
+/// The entry `(i, j)`, `i >= j`, of the lower triangular selected inverse
+/// being built. It is always in the pattern by the clique property of the
+/// columns of `L`.
+fn lower_entry<N, I>(indptr: &[I],
+                     indices: &[I],
+                     data: &[N],
+                     i: usize,
+                     j: usize) -> N
+where N: Copy,
+      I: SpIndex,
+{
+    let start = indptr[j].index();
+    let end = indptr[j + 1].index();
+    let pos = indices[start..end].binary_search(&I::from_usize(i))
+                                 .expect("entry in the filled pattern");
+    data[start + pos]
+}
+
+#[cfg(test)]
+mod test {
+    use sparse::CsMat;
+    use super::selected_inverse;
+
+    #[test]
+    fn selected_inverse_tridiagonal() {
+        // A = L D L^T with L = I + 0.5 (subdiagonal), D = 2 I, for which
+        // A is tridiagonal with 2, 2.5, 2.5 on the diagonal and 1 off it
+        let l: CsMat<f64> = CsMat::new_csc((3, 3), vec![0, 1, 2, 2],
+                                           vec![1, 2],
+                                           vec![0.5, 0.5]);
+        let z = selected_inverse(l.view(), &[2., 2., 2.]);
+        assert_eq!(z.indptr(), &[0, 2, 4, 5]);
+        assert_eq!(z.indices(), &[0, 1, 1, 2, 2]);
+        // A^-1 = L^-T D^-1 L^-1, with L^-1 = I - 0.5 sub + 0.25 subsub
+        let expected = [0.65625, -0.3125, 0.625, -0.25, 0.5];
+        for (&val, &exp) in z.data().iter().zip(&expected) {
+            assert!((val - exp).abs() < 1e-14);
+        }
+    }
+
+    #[test]
+    fn selected_inverse_fill() {
+        // arrowhead matrix whose first column connects all the others, so
+        // the recurrence reads the entries of later columns
+        let l: CsMat<f64> = CsMat::new_csc((3, 3), vec![0, 2, 3, 3],
+                                           vec![1, 2, 2],
+                                           vec![0.5, 0.25, 0.5]);
+        let diag = [1., 2., 4.];
+        let z = selected_inverse(l.view(), &diag);
+        // dense reference A^-1 = L^-T D^-1 L^-1
+        let linv = [[1., 0., 0.], [-0.5, 1., 0.], [0., -0.5, 1.]];
+        let mut inv = [[0.; 3]; 3];
+        for i in 0..3 {
+            for j in 0..3 {
+                for k in 0..3 {
+                    inv[i][j] += linv[k][i] * linv[k][j] / diag[k];
+                }
+            }
+        }
+        for (col, vec) in z.outer_iterator().enumerate() {
+            for (row, &val) in vec.iter() {
+                assert!((val - inv[row][col]).abs() < 1e-14);
+            }
+        }
+        assert_eq!(z.nnz(), 6);
+    }
+}
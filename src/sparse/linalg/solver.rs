@@ -0,0 +1,87 @@
+///! Abstraction over linear system solvers
+///!
+///! A `LinearSolver` computes the exact (up to rounding) solution of a
+///! square linear system, typically from a factorization of the matrix. Code
+///! building on repeated solves with the same matrix, such as bordered
+///! system solvers, can then use any factorization.
+
+use num_traits::Float;
+
+use sparse::linalg::dense::DenseLu;
+
+/// A solver for square linear systems `A x = rhs`, usually backed by a
+/// factorization of `A`.
+pub trait LinearSolver<N> {
+    /// The dimension of the system
+    fn dim(&self) -> usize;
+
+    /// Solve `A x = rhs`, overwriting the contents of `x`.
+    ///
+    /// # Panics
+    ///
+    /// If `rhs` or `x` do not have length `self.dim()`.
+    fn solve(&self, rhs: &[N], x: &mut [N]);
+
+    /// Whether this solver implements `solve_transpose`
+    fn has_transpose_solve(&self) -> bool {
+        false
+    }
+
+    /// Solve `A^T x = rhs`, overwriting the contents of `x`.
+    ///
+    /// # Panics
+    ///
+    /// - if `rhs` or `x` do not have length `self.dim()`.
+    /// - if the solver does not support transposed solves, which can be
+    ///   checked with `has_transpose_solve`.
+    fn solve_transpose(&self, rhs: &[N], x: &mut [N]) {
+        let _ = (rhs, x);
+        panic!("transposed solve not supported by this solver");
+    }
+}
+
+impl<'a, N, S: ?Sized> LinearSolver<N> for &'a S
+where S: LinearSolver<N>
+{
+    fn dim(&self) -> usize {
+        (**self).dim()
+    }
+
+    fn solve(&self, rhs: &[N], x: &mut [N]) {
+        (**self).solve(rhs, x)
+    }
+
+    fn has_transpose_solve(&self) -> bool {
+        (**self).has_transpose_solve()
+    }
+
+    fn solve_transpose(&self, rhs: &[N], x: &mut [N]) {
+        (**self).solve_transpose(rhs, x)
+    }
+}
+
+impl<N: Float> LinearSolver<N> for DenseLu<N> {
+    fn dim(&self) -> usize {
+        DenseLu::dim(self)
+    }
+
+    fn solve(&self, rhs: &[N], x: &mut [N]) {
+        if rhs.len() != self.dim() || x.len() != self.dim() {
+            panic!("Dimension mismatch");
+        }
+        x.copy_from_slice(rhs);
+        DenseLu::solve(self, x);
+    }
+
+    fn has_transpose_solve(&self) -> bool {
+        true
+    }
+
+    fn solve_transpose(&self, rhs: &[N], x: &mut [N]) {
+        if rhs.len() != self.dim() || x.len() != self.dim() {
+            panic!("Dimension mismatch");
+        }
+        x.copy_from_slice(rhs);
+        DenseLu::solve_transpose(self, x);
+    }
+}
@@ -0,0 +1,159 @@
+///! Products with symmetric and hermitian matrices stored by their lower
+///! triangle
+///!
+///! A symmetric matrix is determined by its lower triangle, so storing only
+///! this triangle halves the memory used by the matrix, and the memory
+///! read by a product, which is the bottleneck of sparse matrix vector
+///! products. The upper triangle is never formed: each stored off-diagonal
+///! entry contributes to the product twice, once as itself and once
+///! mirrored.
+
+use std::ops::Deref;
+use num_traits::Num;
+
+use indexing::SpIndex;
+use sparse::prelude::*;
+use sparse::complex::RealOrComplex;
+
+/// Compute `A x` for the symmetric matrix `A` whose lower triangle is
+/// `lower_a`, in CSR or CSC storage.
+///
+/// The entries of `lower_a` above the diagonal are ignored, so `A` can
+/// also be given in full.
+///
+/// # Panics
+///
+/// - if `lower_a` is not square
+/// - if `x` does not have the dimension of `lower_a`
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::linalg::sym_spmv;
+/// // lower triangle of | 2 1 |
+/// //                   | 1 3 |
+/// let lower = CsMat::new((2, 2), vec![0, 1, 3], vec![0, 0, 1],
+///                        vec![2., 1., 3.]);
+/// assert_eq!(sym_spmv(&lower, &[1., 2.]), vec![4., 7.]);
+/// ```
+pub fn sym_spmv<N, I, IpS, IS, DS>(lower_a: &CsMatBase<N, I, IpS, IS, DS>,
+                                   x: &[N]) -> Vec<N>
+where N: Num + Copy,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    mirrored_spmv(lower_a, x, |&val| val)
+}
+
+/// Compute `A x` for the hermitian matrix `A` whose lower triangle is
+/// `lower_a`, in CSR or CSC storage. The mirrored entries are conjugated,
+/// and the imaginary parts of the diagonal are assumed to be zero.
+///
+/// The entries of `lower_a` above the diagonal are ignored, so `A` can
+/// also be given in full.
+///
+/// # Panics
+///
+/// - if `lower_a` is not square
+/// - if `x` does not have the dimension of `lower_a`
+pub fn herm_spmv<N, I, IpS, IS, DS>(lower_a: &CsMatBase<N, I, IpS, IS, DS>,
+                                    x: &[N]) -> Vec<N>
+where N: RealOrComplex,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    mirrored_spmv(lower_a, x, |val| val.conj())
+}
+
+fn mirrored_spmv<N, I, IpS, IS, DS, F>(lower_a: &CsMatBase<N, I, IpS, IS, DS>,
+                                       x: &[N],
+                                       mirror: F) -> Vec<N>
+where N: Num + Copy,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+      F: Fn(&N) -> N,
+{
+    let n = lower_a.rows();
+    if lower_a.cols() != n {
+        panic!("Non square matrix passed to sym_spmv");
+    }
+    if x.len() != n {
+        panic!("Dimension mismatch");
+    }
+    let is_csr = lower_a.is_csr();
+    let mut res = vec![N::zero(); n];
+    for (outer, vec) in lower_a.outer_iterator().enumerate() {
+        for (inner, val) in vec.iter() {
+            let (row, col) = if is_csr {
+                (outer, inner)
+            } else {
+                (inner, outer)
+            };
+            if col > row {
+                continue;
+            }
+            res[row] = res[row] + *val * x[col];
+            if col != row {
+                res[col] = res[col] + mirror(val) * x[row];
+            }
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use num_complex::Complex64;
+    use sparse::CsMat;
+    use super::{sym_spmv, herm_spmv};
+
+    #[test]
+    fn sym_spmv_lower() {
+        // | 4 1 0 2 |
+        // | 1 3 0 0 |
+        // | 0 0 5 1 |
+        // | 2 0 1 6 |
+        let full = CsMat::new((4, 4),
+                              vec![0, 3, 5, 7, 10],
+                              vec![0, 1, 3, 0, 1, 2, 3, 0, 2, 3],
+                              vec![4., 1., 2., 1., 3., 5., 1., 2., 1., 6.]);
+        let lower = CsMat::new((4, 4),
+                               vec![0, 1, 3, 4, 7],
+                               vec![0, 0, 1, 2, 0, 2, 3],
+                               vec![4., 1., 3., 5., 2., 1., 6.]);
+        let x = [1., -1., 2., 0.5];
+        let expected = &full * &::ndarray::arr1(&x);
+        let expected = expected.to_vec();
+        assert_eq!(sym_spmv(&lower, &x), expected);
+        assert_eq!(sym_spmv(&lower.to_csc(), &x), expected);
+        assert_eq!(sym_spmv(&full, &x), expected);
+    }
+
+    #[test]
+    fn herm_spmv_lower() {
+        let c = Complex64::new;
+        // | 2     1 - i |
+        // | 1 + i   3   |
+        let lower = CsMat::new((2, 2), vec![0, 1, 3], vec![0, 0, 1],
+                               vec![c(2., 0.), c(1., 1.), c(3., 0.)]);
+        let x = [c(1., 0.), c(0., 1.)];
+        let expected = vec![c(3., 1.), c(1., 4.)];
+        assert_eq!(herm_spmv(&lower, &x), expected);
+        assert_eq!(herm_spmv(&lower.to_csc(), &x), expected);
+    }
+
+    #[test]
+    #[should_panic]
+    fn sym_spmv_dimension_mismatch() {
+        let lower = CsMat::new((2, 2), vec![0, 1, 3], vec![0, 0, 1],
+                               vec![2., 1., 3.]);
+        sym_spmv(&lower, &[1.]);
+    }
+}
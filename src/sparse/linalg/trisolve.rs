@@ -1,10 +1,13 @@
 /// Sparse triangular solves
 
 use std::ops::IndexMut;
+use std::vec::Vec;
+#[cfg(feature = "std")]
+use ndarray::{ArrayViewMut, Axis, Ix2};
 use num_traits::Num;
 use sparse::CsMatViewI;
 use sparse::vec;
-use sparse::CsVecViewI;
+use sparse::{CsVecViewI, CsVecI};
 use errors::SprsError;
 use stack::{self, StackVal, DStack};
 use indexing::SpIndex;
@@ -24,6 +27,55 @@ where N: Copy + Num,
     }
 }
 
+#[cfg(feature = "std")]
+fn check_solver_dimensions_mat<N, I>(tri_mat: &CsMatViewI<N, I>,
+                                     rhs: &ArrayViewMut<N, Ix2>)
+where N: Copy + Num,
+      I: SpIndex,
+{
+    let (cols, rows) = (tri_mat.cols(), tri_mat.rows());
+    if cols != rows {
+        panic!("Non square matrix passed to solver");
+    }
+    if cols != rhs.shape()[0] {
+        panic!("Dimension mismatch");
+    }
+}
+
+/// Perform `rhs[dst, :] -= val * rhs[src, :]` on a dense matrix of right
+/// hand sides
+#[cfg(feature = "std")]
+fn sub_scaled_row<N>(rhs: &mut ArrayViewMut<N, Ix2>,
+                     dst: usize,
+                     src: usize,
+                     val: N)
+where N: Copy + Num,
+{
+    for k in 0..rhs.shape()[1] {
+        let x = rhs[[src, k]];
+        let b = rhs[[dst, k]];
+        rhs[[dst, k]] = b - val * x;
+    }
+}
+
+/// Perform `rhs[row, :] /= diag_val`, checking the diagonal is not zero
+#[cfg(feature = "std")]
+fn div_row<N>(rhs: &mut ArrayViewMut<N, Ix2>,
+              row: usize,
+              diag_val: Option<N>) -> Result<(), SprsError>
+where N: Copy + Num,
+{
+    match diag_val {
+        Some(diag_val) if diag_val != N::zero() => {
+            for x in rhs.subview_mut(Axis(0), row).iter_mut() {
+                *x = *x / diag_val;
+            }
+            Ok(())
+        }
+        _ => Err(SprsError::SingularMatrix),
+    }
+}
+
 /// Solve a sparse lower triangular matrix system, with a csr matrix
 /// and a dense vector as inputs
 ///
@@ -264,6 +316,42 @@ pub fn lsolve_csc_sparse_rhs<N, I>(lower_tri_mat: CsMatViewI<N, I>,
                                   ) -> Result<(), SprsError>
 where N: Copy + Num,
       I: SpIndex,
+{
+    let n = lower_tri_mat.rows();
+    assert!(x_workspace.len() == n, "x should be of len n");
+    lsolve_csc_reach(lower_tri_mat.view(), rhs.indices(), dstack, visited);
+
+    // solve for the non-zero values into dense workspace
+    rhs.scatter(x_workspace);
+    for &ind in dstack.iter_right().map(stack::extract_stack_val) {
+        let col = lower_tri_mat.outer_view(ind).expect("ind not in bounds");
+        try!(lspsolve_csc_process_col(col, ind, x_workspace));
+    }
+    Ok(())
+}
+
+/// Compute the non-zero pattern of the solution of a sparse triangular CSC /
+/// sparse vector solve, ie the set of nodes reachable from the non-zero
+/// indices of the right hand side `rhs_pattern` in the graph of
+/// `lower_tri_mat` (Gilbert-Peierls).
+///
+/// dstack is a double stack with capacity 2*n, and visited is a workspace
+/// vector of length n, which should be all false on input.
+///
+/// On return, dstack holds the non-zero pattern in its right stack, in a
+/// topological order: iterating over `dstack.iter_right()` gives each index
+/// before the indices depending on it. The pattern is marked in `visited`.
+///
+/// # Panics
+///
+/// * if lower_tri_mat is not CSC
+/// * if dstack.capacity() is too small
+/// * if dstack is not empty
+pub fn lsolve_csc_reach<N, I>(lower_tri_mat: CsMatViewI<N, I>,
+                              rhs_pattern: &[I],
+                              dstack: &mut DStack<StackVal<usize>>,
+                              visited: &mut [bool])
+where I: SpIndex,
 {
     if !lower_tri_mat.is_csc() {
         panic!("Storage mismatch");
@@ -272,7 +360,6 @@ where N: Copy + Num,
     assert!(dstack.capacity() >= 2 * n, "dstack cap should be 2*n");
     assert!(dstack.is_left_empty() && dstack.is_right_empty(),
             "dstack should be empty");
-    assert!(x_workspace.len() == n, "x should be of len n");
 
     // the solve works out the sparsity of the solution using depth first
     // search on the matrix's graph
@@ -286,7 +373,7 @@ where N: Copy + Num,
     // |      f       7| | w |     | c |     w = c / l7
 
     // compute the non-zero elements of the result by dfs traversal
-    for (root_ind, _) in rhs.iter() {
+    for root_ind in rhs_pattern.iter().map(|i| i.index()) {
         if visited[root_ind] {
             continue;
         }
@@ -313,13 +400,158 @@ where N: Copy + Num,
             }
         }
     }
+}
 
-    // solve for the non-zero values into dense workspace
-    rhs.scatter(x_workspace);
-    for &ind in dstack.iter_right().map(stack::extract_stack_val) {
-        println!("ind: {}", ind);
-        let col = lower_tri_mat.outer_view(ind).expect("ind not in bounds");
-        try!(lspsolve_csc_process_col(col, ind, x_workspace));
+/// Sparse triangular CSC / sparse vector solve, returning the solution as
+/// a sparse vector.
+///
+/// This is a convenience wrapper around `lsolve_csc_sparse_rhs` allocating
+/// the required workspaces. When performing many solves, prefer
+/// `lsolve_csc_sparse_rhs` to reuse the workspaces.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::{CsMat, CsVec};
+/// use sprs::linalg::trisolve::lsolve_csc_sparse_rhs_vec;
+/// // |1    | |1|   |1|
+/// // |  2  | | | = | |
+/// // |1   3| |2|   |7|
+/// let l = CsMat::new_csc((3, 3),
+///                        vec![0, 2, 3, 4],
+///                        vec![0, 2, 1, 2],
+///                        vec![1., 1., 2., 3.]);
+/// let b = CsVec::new(3, vec![0, 2], vec![1., 7.]);
+/// let x = lsolve_csc_sparse_rhs_vec(l.view(), b.view()).unwrap();
+/// assert_eq!(x, CsVec::new(3, vec![0, 2], vec![1., 2.]));
+/// ```
+pub fn lsolve_csc_sparse_rhs_vec<N, I>(lower_tri_mat: CsMatViewI<N, I>,
+                                       rhs: CsVecViewI<N, I>
+                                      ) -> Result<CsVecI<N, I>, SprsError>
+where N: Copy + Num,
+      I: SpIndex,
+{
+    let n = lower_tri_mat.rows();
+    let mut dstack = DStack::with_capacity(2 * n);
+    let mut x_workspace = vec![N::zero(); n];
+    let mut visited = vec![false; n];
+    try!(lsolve_csc_sparse_rhs(lower_tri_mat,
+                               rhs,
+                               &mut dstack,
+                               &mut x_workspace,
+                               &mut visited));
+    let mut indices: Vec<usize> = dstack.iter_right()
+                                        .map(stack::extract_stack_val)
+                                        .cloned()
+                                        .collect();
+    indices.sort();
+    let data = indices.iter().map(|&i| x_workspace[i]).collect();
+    let indices = indices.into_iter().map(I::from_usize).collect();
+    Ok(CsVecI::new(n, indices, data))
+}
+
+/// Solve a sparse lower triangular matrix system, with a csr matrix and a
+/// dense matrix of right hand sides, stored as the columns of `rhs`.
+///
+/// The solve results are written into `rhs`. The triangular matrix is
+/// traversed only once for all the right hand sides.
+///
+/// This solve does not assume the input matrix to actually be
+/// triangular, instead it ignores the upper triangular part.
+#[cfg(feature = "std")]
+pub fn lsolve_csr_dense_mat_rhs<N, I>(lower_tri_mat: CsMatViewI<N, I>,
+                                      mut rhs: ArrayViewMut<N, Ix2>)
+                                      -> Result<(), SprsError>
+where N: Copy + Num,
+      I: SpIndex,
+{
+    check_solver_dimensions_mat(&lower_tri_mat, &rhs);
+    if !lower_tri_mat.is_csr() {
+        panic!("Storage mismatch");
+    }
+    for (row_ind, row) in lower_tri_mat.outer_iterator().enumerate() {
+        for (col_ind, &val) in row.iter().filter(|&(j, _)| j < row_ind) {
+            sub_scaled_row(&mut rhs, row_ind, col_ind, val);
+        }
+        try!(div_row(&mut rhs, row_ind, row.get(row_ind).cloned()));
+    }
+    Ok(())
+}
+
+/// Solve a sparse lower triangular matrix system, with a csc matrix and a
+/// dense matrix of right hand sides, stored as the columns of `rhs`.
+///
+/// The solve results are written into `rhs`. The triangular matrix is
+/// traversed only once for all the right hand sides.
+#[cfg(feature = "std")]
+pub fn lsolve_csc_dense_mat_rhs<N, I>(lower_tri_mat: CsMatViewI<N, I>,
+                                      mut rhs: ArrayViewMut<N, Ix2>)
+                                      -> Result<(), SprsError>
+where N: Copy + Num,
+      I: SpIndex,
+{
+    check_solver_dimensions_mat(&lower_tri_mat, &rhs);
+    if !lower_tri_mat.is_csc() {
+        panic!("Storage mismatch");
+    }
+    for (col_ind, col) in lower_tri_mat.outer_iterator().enumerate() {
+        try!(div_row(&mut rhs, col_ind, col.get(col_ind).cloned()));
+        for (row_ind, &val) in col.iter().filter(|&(i, _)| i > col_ind) {
+            sub_scaled_row(&mut rhs, row_ind, col_ind, val);
+        }
+    }
+    Ok(())
+}
+
+/// Solve a sparse upper triangular matrix system, with a csc matrix and a
+/// dense matrix of right hand sides, stored as the columns of `rhs`.
+///
+/// The solve results are written into `rhs`. The triangular matrix is
+/// traversed only once for all the right hand sides.
+#[cfg(feature = "std")]
+pub fn usolve_csc_dense_mat_rhs<N, I>(upper_tri_mat: CsMatViewI<N, I>,
+                                      mut rhs: ArrayViewMut<N, Ix2>)
+                                      -> Result<(), SprsError>
+where N: Copy + Num,
+      I: SpIndex,
+{
+    check_solver_dimensions_mat(&upper_tri_mat, &rhs);
+    if !upper_tri_mat.is_csc() {
+        panic!("Storage mismatch");
+    }
+    for (col_ind, col) in upper_tri_mat.outer_iterator().enumerate().rev() {
+        try!(div_row(&mut rhs, col_ind, col.get(col_ind).cloned()));
+        for (row_ind, &val) in col.iter().filter(|&(i, _)| i < col_ind) {
+            sub_scaled_row(&mut rhs, row_ind, col_ind, val);
+        }
+    }
+    Ok(())
+}
+
+/// Solve a sparse upper triangular matrix system, with a csr matrix and a
+/// dense matrix of right hand sides, stored as the columns of `rhs`.
+///
+/// The solve results are written into `rhs`. The triangular matrix is
+/// traversed only once for all the right hand sides.
+///
+/// This solve does not assume the input matrix to actually be
+/// triangular, instead it ignores the lower triangular part.
+#[cfg(feature = "std")]
+pub fn usolve_csr_dense_mat_rhs<N, I>(upper_tri_mat: CsMatViewI<N, I>,
+                                      mut rhs: ArrayViewMut<N, Ix2>)
+                                      -> Result<(), SprsError>
+where N: Copy + Num,
+      I: SpIndex,
+{
+    check_solver_dimensions_mat(&upper_tri_mat, &rhs);
+    if !upper_tri_mat.is_csr() {
+        panic!("Storage mismatch");
+    }
+    for (row_ind, row) in upper_tri_mat.outer_iterator().enumerate().rev() {
+        for (col_ind, &val) in row.iter().filter(|&(j, _)| j > row_ind) {
+            sub_scaled_row(&mut rhs, row_ind, col_ind, val);
+        }
+        try!(div_row(&mut rhs, row_ind, row.get(row_ind).cloned()));
     }
     Ok(())
 }
@@ -330,6 +562,8 @@ mod test {
     use sparse::{CsMat, CsVec};
     use stack::{self, DStack};
     use std::collections::HashSet;
+    use ndarray::arr2;
+    use errors::SprsError;
 
     #[test]
     fn lsolve_csr_dense_rhs() {
@@ -464,4 +698,59 @@ mod test {
 
         assert_eq!(x, expected_output);
     }
+
+    #[test]
+    fn dense_mat_rhs() {
+        // |1    |       |3 1|   |3  1|
+        // |1 2  | |X| = |5 3| , |1 1| = X
+        // |1 0 3|       |6 4|   |1 1|
+        let l = CsMat::new((3, 3),
+                           vec![0, 1, 3, 5],
+                           vec![0, 0, 1, 0, 2],
+                           vec![1, 1, 2, 1, 3]);
+        let b = arr2(&[[3, 1], [5, 3], [6, 4]]);
+        let expected = arr2(&[[3, 1], [1, 1], [1, 1]]);
+        let mut x = b.clone();
+        super::lsolve_csr_dense_mat_rhs(l.view(), x.view_mut()).unwrap();
+        assert_eq!(x, expected);
+        let mut x = b.clone();
+        super::lsolve_csc_dense_mat_rhs(l.to_csc().view(), x.view_mut())
+            .unwrap();
+        assert_eq!(x, expected);
+
+        // the transpose is upper triangular
+        // |1 1 1|       |5 3|
+        // |  2 0| |X| = |2 2|
+        // |    3|       |3 3|
+        let u = l.transpose_view();
+        let b = arr2(&[[5, 3], [2, 2], [3, 3]]);
+        let mut x = b.clone();
+        super::usolve_csc_dense_mat_rhs(u.view(), x.view_mut()).unwrap();
+        assert_eq!(x, expected);
+        let mut x = b.clone();
+        super::usolve_csr_dense_mat_rhs(u.to_csr().view(), x.view_mut())
+            .unwrap();
+        assert_eq!(x, expected);
+
+        let singular = CsMat::new((2, 2), vec![0, 1, 2], vec![0, 0],
+                                  vec![1, 1]);
+        let mut x = arr2(&[[1], [1]]);
+        let res = super::lsolve_csr_dense_mat_rhs(singular.view(),
+                                                  x.view_mut());
+        assert_eq!(res, Err(SprsError::SingularMatrix));
+    }
+
+    #[test]
+    fn lspsolve_csc_vec() {
+        // same system as the second one of lspsolve_csc
+        let l = CsMat::new_csc((7, 7),
+                               vec![0, 2, 4, 6, 7, 9, 10, 11],
+                               vec![0, 2, 1, 6, 2, 5, 3, 4, 6, 5, 6],
+                               vec![1, 1, 2, 3, 3, 1, 7, 5, 2, 1, 2]);
+        let b = CsVec::new(7,
+                           vec![0, 2, 3, 5],
+                           vec![1, 7, 7, 3]);
+        let x = super::lsolve_csc_sparse_rhs_vec(l.view(), b.view()).unwrap();
+        assert_eq!(x, CsVec::new(7, vec![0, 2, 3, 5], vec![1, 2, 1, 1]));
+    }
 }
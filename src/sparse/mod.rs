@@ -1,4 +1,6 @@
 use std::ops::Deref;
+use std::sync::Arc;
+use std::vec::Vec;
 use indexing::SpIndex;
 use array_backend::Array2;
 
@@ -25,12 +27,25 @@ pub use self::csmat::{CompressedStorage};
 /// [`CsMatViewMutI`] can be used to choose an index type different from the
 /// default `usize`.
 ///
+/// Matrices sharing the same sparsity pattern, such as the jacobians of a
+/// model at different points, can share their structure using
+/// [`CsMatShared`], whose index arrays are reference counted. Cloning it only
+/// copies its values.
+///
+/// More generally, any container dereferencing to a slice can be used as a
+/// storage backend, eg `Box<[T]>` or a container using a custom allocator.
+/// Such matrices are created with [`from_containers`], or by moving the
+/// arrays of another matrix with [`convert_containers`].
+///
 /// [`CsMat`]: type.CsMat.html
 /// [`CsMatView`]: type.CsMatView.html
 /// [`CsMatViewMut`]: type.CsMatViewMut.html
 /// [`CsMatI`]: type.CsMatI.html
 /// [`CsMatViewI`]: type.CsMatViewI.html
 /// [`CsMatViewMutI`]: type.CsMatViewMutI.html
+/// [`CsMatShared`]: type.CsMatShared.html
+/// [`from_containers`]: struct.CsMatBase.html#method.from_containers
+/// [`convert_containers`]: struct.CsMatBase.html#method.convert_containers
 ///
 /// ## Storage format
 ///
@@ -93,12 +108,16 @@ pub type CsMatI<N, I> = CsMatBase<N, I, Vec<I>, Vec<I>, Vec<N>>;
 pub type CsMatViewI<'a, N, I> = CsMatBase<N, I, &'a [I], &'a [I], &'a [N]>;
 pub type CsMatViewMutI<'a, N, I> = CsMatBase<N, I, &'a [I], &'a [I], &'a mut [N]>;
 pub type CsMatVecView_<'a, N, I> = CsMatBase<N, I, Array2<I>, &'a [I], &'a [N]>;
+pub type CsMatBlockMutI<'a, N, I> = CsMatBase<N, I, Vec<I>, &'a [I], &'a mut [N]>;
+pub type CsMatSharedI<N, I> = CsMatBase<N, I, Arc<[I]>, Arc<[I]>, Vec<N>>;
 
 pub type CsMat<N> = CsMatI<N, usize>;
 pub type CsMatView<'a, N> = CsMatViewI<'a, N, usize>;
 pub type CsMatViewMut<'a, N> = CsMatViewMutI<'a, N, usize>;
 // FIXME: a fixed size array would be better, but no Deref impl
 pub type CsMatVecView<'a, N> = CsMatVecView_<'a, N, usize>;
+pub type CsMatBlockMut<'a, N> = CsMatBlockMutI<'a, N, usize>;
+pub type CsMatShared<N> = CsMatSharedI<N, usize>;
 
 /// A sparse vector, storing the indices of its non-zero data.
 ///
@@ -213,6 +232,10 @@ mod prelude {
         CsMat,
         CsMatVecView_,
         CsMatVecView,
+        CsMatBlockMutI,
+        CsMatBlockMut,
+        CsMatSharedI,
+        CsMatShared,
         CsVecBase,
         CsVecViewI,
         CsVecView,
@@ -231,11 +254,40 @@ mod prelude {
 }
 
 mod utils {
+    use std::vec::Vec;
     use indexing::SpIndex;
 
-    pub fn sort_indices_data_slices<N: Copy, I:SpIndex>(indices: &mut [I],
-                                                        data: &mut [N],
-                                                        buf: &mut Vec<(I, N)>) {
+    /// Merge two sorted index slices, appending to `out` the indices for
+    /// which `keep(in_lhs, in_rhs)` is true.
+    pub fn merge_patterns<I, F>(lhs: &[I], rhs: &[I], out: &mut Vec<I>,
+                                keep: F)
+    where I: SpIndex,
+          F: Fn(bool, bool) -> bool
+    {
+        let (mut l, mut r) = (0, 0);
+        while l < lhs.len() || r < rhs.len() {
+            let (ind, in_lhs, in_rhs) = match (lhs.get(l), rhs.get(r)) {
+                (Some(&li), Some(&ri)) if li == ri => (li, true, true),
+                (Some(&li), Some(&ri)) if li < ri => (li, true, false),
+                (Some(&li), None) => (li, true, false),
+                (_, Some(&ri)) => (ri, false, true),
+                (None, None) => unreachable!(),
+            };
+            if in_lhs {
+                l += 1;
+            }
+            if in_rhs {
+                r += 1;
+            }
+            if keep(in_lhs, in_rhs) {
+                out.push(ind);
+            }
+        }
+    }
+
+    pub fn sort_indices_data_slices<N: Clone, I:SpIndex>(indices: &mut [I],
+                                                         data: &mut [N],
+                                                         buf: &mut Vec<(I, N)>) {
         let len = indices.len();
         assert_eq!(len, data.len());
         let indices = &mut indices[..len];
@@ -243,14 +295,14 @@ mod utils {
         buf.clear();
         buf.reserve_exact(len);
         for i in 0..len {
-            buf.push((indices[i], data[i]));
+            buf.push((indices[i], data[i].clone()));
         }
 
         buf.sort_by_key(|x| x.0);
 
-        for (i, &(ind, x)) in buf.iter().enumerate() {
+        for (i, &(ind, ref x)) in buf.iter().enumerate() {
             indices[i] = ind;
-            data[i] = x;
+            data[i] = x.clone();
         }
     }
 }
@@ -261,8 +313,46 @@ pub mod vec;
 pub mod permutation;
 pub mod prod;
 pub mod binop;
+#[cfg(feature = "std")]
 pub mod construct;
 pub mod linalg;
+#[cfg(feature = "std")]
 pub mod symmetric;
 pub mod compressed;
+#[cfg(feature = "std")]
 pub mod to_dense;
+#[cfg(feature = "std")]
+pub mod display;
+#[cfg(feature = "std")]
+pub mod shards;
+#[cfg(feature = "std")]
+pub mod partition;
+#[cfg(feature = "std")]
+pub mod assemble;
+pub mod triplet_iter;
+#[cfg(feature = "std")]
+pub mod graph;
+#[cfg(feature = "std")]
+pub mod mutation_log;
+#[cfg(feature = "std")]
+pub mod typed;
+#[cfg(feature = "std")]
+pub mod backend;
+pub mod validation;
+#[cfg(feature = "std")]
+pub mod checked;
+#[cfg(feature = "std")]
+pub mod norms;
+#[cfg(feature = "std")]
+pub mod complex;
+#[cfg(feature = "std")]
+pub mod stochastic;
+#[cfg(feature = "std")]
+pub mod pattern;
+#[cfg(feature = "std")]
+pub mod elementwise;
+pub mod accumulator;
+#[cfg(feature = "rayon")]
+pub mod parallel;
+#[cfg(feature = "cuda")]
+pub mod cuda;
@@ -0,0 +1,235 @@
+///! Replayable log of matrix mutations
+///!
+///! A `LoggedMat` wraps an owned compressed matrix and records every
+///! mutation applied through it (insertions, value assignments, scalings
+///! and diagonal shifts) in a log. Along with the initial matrix, which is
+///! kept aside, this log makes it possible to reconstruct any intermediate
+///! state of the matrix, eg to reproduce a simulation state or to bisect
+///! the mutation which introduced a bug.
+///!
+///! Mutations performed directly on the inner matrix are not recorded, this
+///! is why the wrapper only hands out immutable views.
+
+use std::cmp;
+use num_traits::Num;
+
+use indexing::SpIndex;
+use sparse::prelude::*;
+
+/// A mutation applied to a compressed matrix.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mutation<N> {
+    /// Insertion of a value, overwriting any value already present at this
+    /// location, see `CsMatBase::insert`
+    Insert {
+        row: usize,
+        col: usize,
+        val: N,
+    },
+    /// Assignment of an existing non-zero value, see `CsMatBase::set`
+    Set {
+        row: usize,
+        col: usize,
+        val: N,
+    },
+    /// Multiplication of all the non-zero values by a scalar
+    Scale(N),
+    /// Addition of a scalar to all the diagonal elements, ie `A + s I`.
+    /// Missing diagonal elements are inserted.
+    Shift(N),
+}
+
+impl<N: Num + Copy> Mutation<N> {
+    /// Apply this mutation to a matrix
+    ///
+    /// # Panics
+    ///
+    /// As the corresponding method of `CsMatBase`, eg for a `Set` mutation
+    /// targeting a location without a non-zero value.
+    pub fn apply<I: SpIndex>(&self, mat: &mut CsMatI<N, I>) {
+        match *self {
+            Mutation::Insert { row, col, val } => mat.insert(row, col, val),
+            Mutation::Set { row, col, val } => mat.set(row, col, val),
+            Mutation::Scale(val) => mat.scale(val),
+            Mutation::Shift(val) => {
+                let diag_len = cmp::min(mat.rows(), mat.cols());
+                for i in 0..diag_len {
+                    let missing = match mat.get_mut(i, i) {
+                        Some(elem) => {
+                            *elem = *elem + val;
+                            false
+                        }
+                        None => true,
+                    };
+                    if missing {
+                        mat.insert(i, i, val);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Reconstruct a matrix state by applying the mutations of `log`, in order,
+/// to the matrix `initial`.
+pub fn replay<N, I>(mut initial: CsMatI<N, I>,
+                    log: &[Mutation<N>]) -> CsMatI<N, I>
+where N: Num + Copy,
+      I: SpIndex,
+{
+    for mutation in log {
+        mutation.apply(&mut initial);
+    }
+    initial
+}
+
+/// A compressed matrix recording the mutations applied to it.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::mutation_log::{LoggedMat, Mutation};
+/// let mut mat = LoggedMat::new(CsMat::<f64>::eye(3));
+/// mat.insert(0, 2, 4.);
+/// mat.scale(2.);
+/// mat.shift(-1.);
+/// assert_eq!(mat.log()[1], Mutation::Scale(2.));
+/// assert_eq!(mat.view().get(0, 2), Some(&8.));
+/// assert_eq!(mat.view().get(1, 1), Some(&1.));
+/// // state of the matrix after the first two mutations
+/// let state = mat.replay(2);
+/// assert_eq!(state.get(1, 1), Some(&2.));
+/// assert_eq!(mat.replay(mat.log().len()).view(), mat.view());
+/// ```
+#[derive(Debug, PartialEq)]
+pub struct LoggedMat<N, I: SpIndex = usize> {
+    initial: CsMatI<N, I>,
+    mat: CsMatI<N, I>,
+    log: Vec<Mutation<N>>,
+}
+
+impl<N, I> LoggedMat<N, I>
+where N: Num + Copy,
+      I: SpIndex,
+{
+    /// Start recording the mutations of `mat`, keeping a copy of it as
+    /// the initial state.
+    pub fn new(mat: CsMatI<N, I>) -> LoggedMat<N, I> {
+        LoggedMat {
+            initial: mat.to_owned(),
+            mat: mat,
+            log: Vec::new(),
+        }
+    }
+
+    /// A view of the current state of the matrix
+    pub fn view(&self) -> CsMatViewI<N, I> {
+        self.mat.view()
+    }
+
+    /// The initial state of the matrix
+    pub fn initial(&self) -> CsMatViewI<N, I> {
+        self.initial.view()
+    }
+
+    /// The mutations applied since the creation of the log, in order
+    pub fn log(&self) -> &[Mutation<N>] {
+        &self.log
+    }
+
+    /// Record and apply a mutation
+    ///
+    /// # Panics
+    ///
+    /// If the mutation cannot be applied, in which case it is not recorded.
+    pub fn apply(&mut self, mutation: Mutation<N>) {
+        mutation.apply(&mut self.mat);
+        self.log.push(mutation);
+    }
+
+    /// Insert an element in the matrix, see `CsMatBase::insert`
+    pub fn insert(&mut self, row: usize, col: usize, val: N) {
+        self.apply(Mutation::Insert { row: row, col: col, val: val });
+    }
+
+    /// Set the value of an existing non-zero element, see `CsMatBase::set`
+    ///
+    /// # Panics
+    ///
+    /// - on out-of-bounds access
+    /// - if no non-zero element exists at the given location
+    pub fn set(&mut self, row: usize, col: usize, val: N) {
+        self.apply(Mutation::Set { row: row, col: col, val: val });
+    }
+
+    /// Multiply all the non-zero values by `val`
+    pub fn scale(&mut self, val: N) {
+        self.apply(Mutation::Scale(val));
+    }
+
+    /// Add `val` to all the diagonal elements
+    pub fn shift(&mut self, val: N) {
+        self.apply(Mutation::Shift(val));
+    }
+
+    /// Reconstruct the state of the matrix after its first `nb_mutations`
+    /// recorded mutations.
+    ///
+    /// # Panics
+    ///
+    /// If `nb_mutations` is larger than the length of the log.
+    pub fn replay(&self, nb_mutations: usize) -> CsMatI<N, I> {
+        replay(self.initial.to_owned(), &self.log[..nb_mutations])
+    }
+
+    /// Stop recording, returning the current matrix along with the log.
+    pub fn into_inner(self) -> (CsMatI<N, I>, Vec<Mutation<N>>) {
+        (self.mat, self.log)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sparse::CsMat;
+    use super::{LoggedMat, Mutation, replay};
+
+    #[test]
+    fn replay_reproduces_states() {
+        let mat: CsMat<f64> = CsMat::new((3, 3),
+                                         vec![0, 2, 3, 3],
+                                         vec![0, 2, 1],
+                                         vec![1., 2., 3.]);
+        let mut logged = LoggedMat::new(mat);
+        logged.shift(1.);
+        logged.set(0, 2, -2.);
+        logged.insert(2, 0, 5.);
+        logged.scale(0.5);
+        let expected = CsMat::new((3, 3),
+                                  vec![0, 2, 3, 5],
+                                  vec![0, 2, 1, 0, 2],
+                                  vec![1., -1., 2., 2.5, 0.5]);
+        assert_eq!(logged.view(), expected.view());
+
+        let after_shift = logged.replay(1);
+        assert_eq!(after_shift.get(2, 2), Some(&1.));
+        assert_eq!(after_shift.get(0, 2), Some(&2.));
+        assert_eq!(logged.replay(0).view(), logged.initial());
+
+        let (mat, log) = logged.into_inner();
+        assert_eq!(log.len(), 4);
+        assert_eq!(log[2], Mutation::Insert { row: 2, col: 0, val: 5. });
+        let initial = CsMat::new((3, 3),
+                                 vec![0, 2, 3, 3],
+                                 vec![0, 2, 1],
+                                 vec![1., 2., 3.]);
+        assert_eq!(replay(initial, &log), mat);
+    }
+
+    #[test]
+    #[should_panic]
+    fn set_missing_element() {
+        let mut logged = LoggedMat::new(CsMat::<f64>::eye(2));
+        logged.set(0, 1, 1.);
+    }
+}
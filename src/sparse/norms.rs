@@ -0,0 +1,153 @@
+///! Norms of the rows and columns of sparse matrices
+///!
+///! Normalizing the rows of a matrix is a standard preprocessing step, eg
+///! for tf-idf matrices where each document is scaled to unit euclidean
+///! norm, or to get a row stochastic matrix, whose rows sum to one, from a
+///! matrix of non-negative weights.
+///!
+///! # Example
+///!
+///! ```rust
+///! use sprs::{CsMat, NormType};
+///! let mut mat = CsMat::new((2, 2), vec![0, 2, 3], vec![0, 1, 1],
+///!                          vec![1., 3., 2.]);
+///! mat.normalize_outer(NormType::L1);
+///! assert_eq!(mat.data(), &[0.25, 0.75, 1.]);
+///! ```
+
+use std::ops::{Deref, DerefMut};
+use num_traits::{Float, Zero};
+use ndarray::Array;
+
+use indexing::SpIndex;
+use sparse::prelude::*;
+use sparse::complex::RealOrComplex;
+use ::Ix1;
+
+/// The vector norms available for the rows and columns of a matrix
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NormType {
+    /// Sum of the absolute values
+    L1,
+    /// Euclidean norm
+    L2,
+    /// Largest absolute value
+    Linf,
+}
+
+impl NormType {
+    /// The norm of a vector given by its non-zero values, which can be
+    /// complex
+    fn of<'a, N, It>(self, values: It) -> N::Real
+    where N: 'a + RealOrComplex,
+          It: Iterator<Item=&'a N>,
+    {
+        let zero = <N::Real as Zero>::zero();
+        match self {
+            NormType::L1 => values.fold(zero, |acc, x| acc + x.modulus()),
+            NormType::L2 => {
+                values.fold(zero, |acc, x| acc + x.modulus_sqr()).sqrt()
+            },
+            NormType::Linf => values.fold(zero, |acc, x| acc.max(x.modulus())),
+        }
+    }
+}
+
+impl<N, I, IpS, IS, DS> CsMatBase<N, I, IpS, IS, DS>
+where I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    /// The norms of the outer dimensions of this matrix, ie the norms of
+    /// its rows for a CSR matrix, of its columns for a CSC matrix. The
+    /// norms of complex matrices use the modulus of their values.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate ndarray;
+    /// extern crate sprs;
+    /// use ndarray::arr1;
+    /// use sprs::{CsMat, NormType};
+    /// # fn main() {
+    /// let mat = CsMat::new((2, 2), vec![0, 2, 2], vec![0, 1],
+    ///                      vec![3., -4.]);
+    /// assert_eq!(mat.outer_norms(NormType::L2), arr1(&[5., 0.]));
+    /// assert_eq!(mat.outer_norms(NormType::Linf), arr1(&[4., 0.]));
+    /// # }
+    /// ```
+    pub fn outer_norms(&self, norm: NormType) -> Array<N::Real, Ix1>
+    where N: RealOrComplex
+    {
+        let norms: Vec<N::Real> = self.outer_iterator()
+                                .map(|vec| norm.of(vec.data().iter()))
+                                .collect();
+        Array::from_vec(norms)
+    }
+}
+
+impl<N, I, IpS, IS, DS> CsMatBase<N, I, IpS, IS, DS>
+where I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: DerefMut<Target=[N]>,
+{
+    /// Scale each outer dimension of this matrix to unit norm, ie its rows
+    /// for a CSR matrix, its columns for a CSC matrix. The outer dimensions
+    /// of zero norm are left untouched.
+    ///
+    /// With `NormType::L1`, the rows of a CSR matrix of non-negative
+    /// weights sum to one afterwards, ie the matrix is row stochastic.
+    pub fn normalize_outer(&mut self, norm: NormType)
+    where N: RealOrComplex
+    {
+        for mut vec in self.outer_iterator_mut() {
+            let vec_norm = norm.of(vec.data().iter());
+            if vec_norm != <N::Real as Zero>::zero() {
+                let vec_norm = N::from_real(vec_norm);
+                vec.map_inplace(|&x| x / vec_norm);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::arr1;
+    use sparse::CsMat;
+    use test_data::{mat1, mat1_csc};
+    use super::NormType;
+
+    #[test]
+    fn outer_norms() {
+        let mat = mat1();
+        assert_eq!(mat.outer_norms(NormType::L1),
+                   arr1(&[7., 7., 5., 8., 7.]));
+        assert_eq!(mat.outer_norms(NormType::L2),
+                   arr1(&[5., 29f64.sqrt(), 5., 8., 7.]));
+        assert_eq!(mat.outer_norms(NormType::Linf),
+                   arr1(&[4., 5., 5., 8., 7.]));
+        // the outer dims of a CSC matrix are its columns
+        assert_eq!(mat1_csc().outer_norms(NormType::L1),
+                   arr1(&[0., 8., 8., 13., 5.]));
+    }
+
+    #[test]
+    fn normalize_outer() {
+        let mut mat = mat1_csc();
+        mat.normalize_outer(NormType::L1);
+        let sums: Vec<f64> = mat.outer_iterator()
+                                .map(|vec| vec.data().iter().sum())
+                                .collect();
+        assert_eq!(sums[0], 0.);
+        assert!(sums[1..].iter().all(|&sum| (sum - 1.).abs() < 1e-12));
+
+        let mut mat = CsMat::new((2, 2), vec![0, 2, 2], vec![0, 1],
+                                 vec![3., -4.]);
+        mat.normalize_outer(NormType::Linf);
+        assert_eq!(mat.data(), &[0.75, -1.]);
+        mat.normalize_outer(NormType::L2);
+        assert_eq!(mat.data(), &[0.6, -0.8]);
+    }
+}
@@ -0,0 +1,324 @@
+///! Parallel kernels and their reproducibility
+///!
+///! This module is available with the `rayon` feature. Kernels whose
+///! results are accumulated by several threads, such as the product of a
+///! CSC matrix by a vector or the dot product, sum floating point values in
+///! an order that depends on the scheduling of the work, and thus on the
+///! number of threads and on the load of the machine. Their results can
+///! then differ by rounding errors from one run to another.
+///!
+///! When bitwise reproducible results are required, eg to validate
+///! simulations, these kernels take a `ParallelMode::Deterministic` mode,
+///! where the work is split in chunks that only depend on the size of the
+///! inputs, whose partial results are combined in a fixed order. This costs
+///! some performance, as the chunks cannot be balanced between the threads.
+///!
+///! The kernels where each output element is computed by a single thread,
+///! such as `prod::par_mul_acc_mat_vec_csr` or the parallel constructors,
+///! are deterministic in any case.
+///!
+///! # Thread pools
+///!
+///! The parallel kernels called from a rayon thread pool run in this pool.
+///! Otherwise, they run in the pool given to `set_thread_pool` if any, and
+///! in the global rayon pool by default. This lets applications which are
+///! already parallel keep control of the number of threads used by sprs,
+///! to avoid oversubscribing the machine. `with_num_threads` runs a closure
+///! in a dedicated pool of a given size.
+///!
+///! # Example
+///!
+///! ```rust
+///! use sprs::parallel::{par_dot, ParallelMode};
+///! let x: Vec<f64> = (0..10000).map(|i| 1. / (1 + i) as f64).collect();
+///! let dot = par_dot(&x, &x, ParallelMode::Deterministic);
+///! assert_eq!(dot, par_dot(&x, &x, ParallelMode::Deterministic));
+///! ```
+
+use std::sync::{Arc, Mutex};
+use num_traits::Num;
+use rayon::{self, ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
+use rayon::prelude::*;
+
+use indexing::SpIndex;
+use sparse::prelude::*;
+
+/// The number of elements of the chunks of the deterministic reductions
+/// over dense vectors
+pub const DETERMINISTIC_CHUNK_LEN: usize = 4096;
+
+/// The number of chunks of columns of the deterministic product of a CSC
+/// matrix by a vector, each of them needing a dense partial result
+pub const DETERMINISTIC_CSC_CHUNKS: usize = 32;
+
+/// How the parallel kernels perform their reductions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParallelMode {
+    /// Let rayon balance the work between the threads, which makes the
+    /// order of the reductions, and the rounding errors, vary between runs
+    Dynamic,
+    /// Split the work in fixed chunks reduced in a fixed order, so that the
+    /// results are bitwise reproducible, whatever the number of threads
+    Deterministic,
+}
+
+impl Default for ParallelMode {
+    fn default() -> ParallelMode {
+        ParallelMode::Dynamic
+    }
+}
+
+lazy_static! {
+    /// The pool set by `set_thread_pool`
+    static ref POOL: Mutex<Option<Arc<ThreadPool>>> = Mutex::new(None);
+}
+
+/// Run the parallel kernels of sprs in `pool` when they are not called
+/// from a rayon thread pool, instead of the global rayon pool.
+pub fn set_thread_pool(pool: Arc<ThreadPool>) {
+    *POOL.lock().unwrap() = Some(pool);
+}
+
+/// Run the parallel kernels of sprs in the global rayon pool again when
+/// they are not called from a rayon thread pool.
+pub fn reset_thread_pool() {
+    *POOL.lock().unwrap() = None;
+}
+
+/// The thread pool set by `set_thread_pool`, if any
+pub fn thread_pool() -> Option<Arc<ThreadPool>> {
+    POOL.lock().unwrap().clone()
+}
+
+/// Run `f` in a new thread pool of `nb_threads` threads, in which the
+/// parallel kernels called by `f` run.
+///
+/// As creating a pool spawns its threads, this is meant to run sizeable
+/// computations. Reusing a pool with `ThreadPool::install` is cheaper.
+///
+/// # Errors
+///
+/// If the threads of the pool cannot be created.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::parallel::{par_dot, with_num_threads, ParallelMode};
+/// let x = vec![1.; 1000];
+/// let dot = with_num_threads(2, || {
+///     par_dot(&x, &x, ParallelMode::Dynamic)
+/// }).unwrap();
+/// assert_eq!(dot, 1000.);
+/// ```
+pub fn with_num_threads<R, F>(nb_threads: usize,
+                              f: F) -> Result<R, ThreadPoolBuildError>
+where R: Send,
+      F: FnOnce() -> R + Send,
+{
+    let pool = try!(ThreadPoolBuilder::new().num_threads(nb_threads).build());
+    Ok(pool.install(f))
+}
+
+/// Run `f` in the thread pool where the parallel kernels of sprs run: the
+/// current rayon pool if called from one, or else the pool given to
+/// `set_thread_pool`, or else the global rayon pool.
+pub fn install<R, F>(f: F) -> R
+where R: Send,
+      F: FnOnce() -> R + Send,
+{
+    install_in(thread_pool().as_ref().map(|pool| &**pool), f)
+}
+
+/// Run `f` in the current rayon pool if called from one, or else in `pool`
+/// if any, or else in the global rayon pool.
+fn install_in<R, F>(pool: Option<&ThreadPool>, f: F) -> R
+where R: Send,
+      F: FnOnce() -> R + Send,
+{
+    if rayon::current_thread_index().is_some() {
+        return f();
+    }
+    match pool {
+        Some(pool) => pool.install(f),
+        None => f(),
+    }
+}
+
+/// Parallel dot product of two dense vectors.
+///
+/// # Panics
+///
+/// If the vectors do not have the same length.
+pub fn par_dot<N>(x: &[N], y: &[N], mode: ParallelMode) -> N
+where N: Num + Copy + Send + Sync
+{
+    if x.len() != y.len() {
+        panic!("Dimension mismatch");
+    }
+    install(|| match mode {
+        ParallelMode::Dynamic => {
+            x.par_iter()
+             .zip(y)
+             .map(|(&xi, &yi)| xi * yi)
+             .reduce(N::zero, |a, b| a + b)
+        },
+        ParallelMode::Deterministic => {
+            let partials: Vec<N> = x.par_chunks(DETERMINISTIC_CHUNK_LEN)
+                                    .zip(y.par_chunks(DETERMINISTIC_CHUNK_LEN))
+                                    .map(|(x, y)| dot_seq(x, y))
+                                    .collect();
+            partials.into_iter().fold(N::zero(), |a, b| a + b)
+        },
+    })
+}
+
+fn dot_seq<N: Num + Copy>(x: &[N], y: &[N]) -> N {
+    x.iter().zip(y).fold(N::zero(), |acc, (&xi, &yi)| acc + xi * yi)
+}
+
+/// Parallel version of `prod::mul_acc_mat_vec_csc`, multiplying a CSC
+/// matrix with a dense vector and accumulating the result into another
+/// dense vector.
+///
+/// The columns are processed in parallel, each thread accumulating the
+/// products in a dense vector, which are then summed. In deterministic
+/// mode, there are `DETERMINISTIC_CSC_CHUNKS` such partial vectors at
+/// most, summed in the order of the columns.
+///
+/// # Panics
+///
+/// On dimension or storage mismatch.
+pub fn par_mul_acc_mat_vec_csc<N, I>(mat: CsMatViewI<N, I>,
+                                     in_vec: &[N],
+                                     res_vec: &mut [N],
+                                     mode: ParallelMode)
+where N: Num + Copy + Send + Sync,
+      I: SpIndex + Sync,
+{
+    if mat.cols() != in_vec.len() || mat.rows() != res_vec.len() {
+        panic!("Dimension mismatch");
+    }
+    if !mat.is_csc() {
+        panic!("Storage mismatch");
+    }
+    let rows = mat.rows();
+    let accumulate = |mut acc: Vec<N>, col: usize| {
+        let multiplier = in_vec[col];
+        for (row, &value) in mat.outer_view(col).unwrap().iter() {
+            acc[row] = acc[row] + value * multiplier;
+        }
+        acc
+    };
+    install(|| match mode {
+        ParallelMode::Dynamic => {
+            let add = |mut a: Vec<N>, b: Vec<N>| {
+                for (ai, bi) in a.iter_mut().zip(b) {
+                    *ai = *ai + bi;
+                }
+                a
+            };
+            let prod = (0..mat.cols()).into_par_iter()
+                                      .fold(|| vec![N::zero(); rows],
+                                            &accumulate)
+                                      .reduce(|| vec![N::zero(); rows], add);
+            for (res, p) in res_vec.iter_mut().zip(prod) {
+                *res = *res + p;
+            }
+        },
+        ParallelMode::Deterministic => {
+            let cols = mat.cols();
+            let chunk_cols = (cols + DETERMINISTIC_CSC_CHUNKS - 1)
+                             / DETERMINISTIC_CSC_CHUNKS;
+            let nb_chunks = if cols == 0 { 0 } else {
+                (cols + chunk_cols - 1) / chunk_cols
+            };
+            let partials: Vec<Vec<N>> = (0..nb_chunks).into_par_iter()
+                                                      .map(|chunk| {
+                let start = chunk * chunk_cols;
+                let stop = (start + chunk_cols).min(cols);
+                (start..stop).fold(vec![N::zero(); rows], &accumulate)
+            }).collect();
+            res_vec.par_iter_mut().enumerate().for_each(|(row, res)| {
+                for partial in &partials {
+                    *res = *res + partial[row];
+                }
+            });
+        },
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use rayon::{self, ThreadPoolBuilder};
+    use sparse::CsMat;
+    use sparse::prod::mul_acc_mat_vec_csc;
+    use super::{par_dot, par_mul_acc_mat_vec_csc, ParallelMode, install_in,
+                with_num_threads};
+    use super::ParallelMode::{Deterministic, Dynamic};
+
+    fn with_threads<T: Send, F: FnOnce() -> T + Send>(n: usize, f: F) -> T {
+        with_num_threads(n, f).unwrap()
+    }
+
+    #[test]
+    fn thread_pool_selection() {
+        let pool = ThreadPoolBuilder::new().num_threads(3).build().unwrap();
+        let pool = Some(&pool);
+        assert_eq!(install_in(pool, || rayon::current_num_threads()), 3);
+        // the pool of the caller has precedence
+        assert_eq!(with_threads(2, || {
+            install_in(pool, rayon::current_num_threads)
+        }), 2);
+        assert!(install_in(None, || rayon::current_thread_index()).is_none());
+    }
+
+    #[test]
+    fn deterministic_dot() {
+        let x: Vec<f64> = (0..50000).map(|i| (i as f64).sin() * 1e3)
+                                    .collect();
+        let y: Vec<f64> = (0..50000).map(|i| 1. / (1 + i) as f64).collect();
+        let dot = with_threads(1, || par_dot(&x, &y, Deterministic));
+        for &threads in &[2, 3, 8] {
+            let other = with_threads(threads, || {
+                par_dot(&x, &y, Deterministic)
+            });
+            assert_eq!(dot.to_bits(), other.to_bits());
+        }
+        let seq = x.iter().zip(&y).fold(0., |acc, (a, b)| acc + a * b);
+        assert!((dot - seq).abs() < 1e-9 * seq.abs());
+        assert!((par_dot(&x, &y, Dynamic) - seq).abs() < 1e-9 * seq.abs());
+        assert_eq!(ParallelMode::default(), Dynamic);
+    }
+
+    #[test]
+    fn deterministic_csc_matvec() {
+        let n = 300;
+        let mut tri = ::sparse::TriMat::new((n, n));
+        for i in 0..n {
+            for j in 0..n {
+                if (i * 7 + j * 13) % 11 == 0 {
+                    tri.add_triplet(i, j, ((i + 2 * j) as f64).cos());
+                }
+            }
+        }
+        let mat: CsMat<f64> = tri.to_csc();
+        let x: Vec<f64> = (0..n).map(|i| 1. / (1 + i) as f64).collect();
+        let mut expected = vec![1.; n];
+        mul_acc_mat_vec_csc(mat.view(), &x, &mut expected);
+
+        let run = |threads, mode| with_threads(threads, || {
+            let mut res = vec![1.; n];
+            par_mul_acc_mat_vec_csc(mat.view(), &x, &mut res, mode);
+            res
+        });
+        let res = run(1, Deterministic);
+        for &threads in &[2, 5] {
+            assert_eq!(run(threads, Deterministic), res);
+        }
+        for res in &[res, run(4, Dynamic)] {
+            for (a, b) in res.iter().zip(&expected) {
+                assert!((a - b).abs() < 1e-12);
+            }
+        }
+    }
+}
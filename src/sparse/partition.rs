@@ -0,0 +1,367 @@
+///! Row-block partitioned matrices for distributed computations
+///!
+///! A `PartitionedCsMat` splits a matrix into contiguous blocks of rows, each
+///! owned by a worker, eg an MPI rank or a thread. Vectors are partitioned
+///! alongside, each worker owning a contiguous range of the entries of the
+///! input vectors, given by the column offsets, and of the output vectors,
+///! given by the row offsets.
+///!
+///! A matrix-vector product needs, besides the owned entries of the input
+///! vector, the entries owned by other workers that are referenced by the
+///! columns of the block. These form the halo of the block. Each `RowBlock`
+///! stores its rows with a local column numbering, where the owned columns
+///! come first, followed by the halo columns, and the metadata describing
+///! which entries to receive from which worker. The product of a block with
+///! its owned and halo entries can then be computed independently of the
+///! other blocks.
+///!
+///! The `halo_exchange` and `mul_vec` methods of `PartitionedCsMat` perform
+///! the communication in memory, and serve as a reference for message
+///! passing implementations.
+
+use std::ops::{Deref, Range};
+use num_traits::Num;
+
+use indexing::SpIndex;
+use sparse::prelude::*;
+use sparse::csmat::CompressedStorage::CSR;
+use ::Shape;
+
+/// The entries of the input vector a block receives from another worker
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HaloRecv {
+    /// The worker owning the entries
+    pub from: usize,
+    /// The positions of the received entries in the halo of the block
+    pub halo_range: Range<usize>,
+    /// The indices of the entries, local to the sending worker
+    pub remote_indices: Vec<usize>,
+}
+
+/// A block of contiguous rows of a partitioned matrix
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowBlock<N, I: SpIndex> {
+    rows: Range<usize>,
+    cols: Range<usize>,
+    local: CsMatI<N, I>,
+    halo: Vec<usize>,
+    recvs: Vec<HaloRecv>,
+}
+
+impl<N, I: SpIndex> RowBlock<N, I> {
+    /// The global rows of this block
+    pub fn rows(&self) -> Range<usize> {
+        self.rows.clone()
+    }
+
+    /// The global columns, and entries of the input vectors, owned by the
+    /// worker of this block
+    pub fn owned_cols(&self) -> Range<usize> {
+        self.cols.clone()
+    }
+
+    /// The rows of the block in the local column numbering: the owned
+    /// columns first, then the halo columns
+    pub fn local(&self) -> &CsMatI<N, I> {
+        &self.local
+    }
+
+    /// The global indices of the halo columns, sorted
+    pub fn halo(&self) -> &[usize] {
+        &self.halo
+    }
+
+    /// The entries to receive from other workers to fill the halo, one
+    /// message per sending worker
+    pub fn recvs(&self) -> &[HaloRecv] {
+        &self.recvs
+    }
+
+    /// Compute `y = A_block x`, where `owned` holds the owned entries of
+    /// `x` and `halo` holds its halo entries.
+    ///
+    /// # Panics
+    ///
+    /// On dimension mismatch.
+    pub fn mul_local(&self, owned: &[N], halo: &[N], y: &mut [N])
+    where N: Num + Copy
+    {
+        if owned.len() != self.cols.len() || halo.len() != self.halo.len()
+           || y.len() != self.rows.len() {
+            panic!("Dimension mismatch");
+        }
+        let nb_owned = owned.len();
+        for (yi, row) in y.iter_mut().zip(self.local.outer_iterator()) {
+            let mut acc = N::zero();
+            for (col, &val) in row.iter() {
+                let x = if col < nb_owned { owned[col] }
+                        else { halo[col - nb_owned] };
+                acc = acc + val * x;
+            }
+            *yi = acc;
+        }
+    }
+}
+
+/// A matrix partitioned in blocks of contiguous rows
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartitionedCsMat<N, I: SpIndex> {
+    shape: Shape,
+    row_offsets: Vec<usize>,
+    col_offsets: Vec<usize>,
+    blocks: Vec<RowBlock<N, I>>,
+}
+
+/// The worker owning `ind` for the given offsets
+fn owner(offsets: &[usize], ind: usize) -> usize {
+    match offsets.binary_search(&ind) {
+        Ok(mut part) => {
+            // skip the empty partitions starting at ind
+            while offsets[part + 1] == ind {
+                part += 1;
+            }
+            part
+        }
+        Err(part) => part - 1,
+    }
+}
+
+fn check_offsets(offsets: &[usize], dim: usize) {
+    assert!(offsets.len() >= 2, "at least one partition is required");
+    assert!(offsets[0] == 0 && offsets[offsets.len() - 1] == dim,
+            "offsets should span the dimension");
+    assert!(offsets.windows(2).all(|w| w[0] <= w[1]),
+            "offsets should be sorted");
+}
+
+/// Offsets splitting `dim` in `nb_parts` balanced partitions
+fn uniform_offsets(dim: usize, nb_parts: usize) -> Vec<usize> {
+    (0..(nb_parts + 1)).map(|part| part * dim / nb_parts).collect()
+}
+
+impl<N, I> PartitionedCsMat<N, I>
+where N: Num + Copy + Default,
+      I: SpIndex,
+{
+    /// Partition a matrix, the rows of the `k`-th block being
+    /// `row_offsets[k]..row_offsets[k + 1]`, and its worker owning the
+    /// entries `col_offsets[k]..col_offsets[k + 1]` of the input vectors.
+    ///
+    /// # Panics
+    ///
+    /// If the offsets do not have the same length, are not sorted, or do
+    /// not span the dimensions of the matrix.
+    pub fn new<IpS, IS, DS>(mat: &CsMatBase<N, I, IpS, IS, DS>,
+                            row_offsets: Vec<usize>,
+                            col_offsets: Vec<usize>)
+                            -> PartitionedCsMat<N, I>
+    where IpS: Deref<Target=[I]>,
+          IS: Deref<Target=[I]>,
+          DS: Deref<Target=[N]>,
+    {
+        check_offsets(&row_offsets, mat.rows());
+        check_offsets(&col_offsets, mat.cols());
+        assert_eq!(row_offsets.len(), col_offsets.len(),
+                   "row and column partitions differ in number");
+        let csr;
+        let mat = if mat.is_csr() { mat.view() } else {
+            csr = mat.to_csr();
+            csr.view()
+        };
+        let nb_parts = row_offsets.len() - 1;
+        let blocks = (0..nb_parts).map(|part| {
+            let rows = row_offsets[part]..row_offsets[part + 1];
+            let cols = col_offsets[part]..col_offsets[part + 1];
+            let mut halo: Vec<usize> = Vec::new();
+            for row in rows.clone() {
+                let vec = mat.outer_view(row).unwrap();
+                halo.extend(vec.indices().iter()
+                               .map(|&j| j.index())
+                               .filter(|j| *j < cols.start || *j >= cols.end));
+            }
+            halo.sort();
+            halo.dedup();
+
+            let nb_owned = cols.len();
+            let mut indptr = Vec::with_capacity(rows.len() + 1);
+            indptr.push(I::zero());
+            let mut indices = Vec::new();
+            let mut data = Vec::new();
+            for row in rows.clone() {
+                let vec = mat.outer_view(row).unwrap();
+                let mut entries: Vec<(usize, N)> = vec.iter().map(|(j, &v)| {
+                    let local = if j >= cols.start && j < cols.end {
+                        j - cols.start
+                    }
+                    else {
+                        nb_owned + halo.binary_search(&j).unwrap()
+                    };
+                    (local, v)
+                }).collect();
+                entries.sort_by_key(|&(j, _)| j);
+                for (j, v) in entries {
+                    indices.push(I::from_usize(j));
+                    data.push(v);
+                }
+                indptr.push(I::from_usize(indices.len()));
+            }
+            let local = CsMatI {
+                storage: CSR,
+                nrows: rows.len(),
+                ncols: nb_owned + halo.len(),
+                indptr: indptr,
+                indices: indices,
+                data: data,
+            };
+
+            // halo entries are sorted, hence grouped by owner
+            let mut recvs: Vec<HaloRecv> = Vec::new();
+            for (pos, &j) in halo.iter().enumerate() {
+                let from = owner(&col_offsets, j);
+                let remote = j - col_offsets[from];
+                let new_msg = recvs.last().map_or(true, |r| r.from != from);
+                if new_msg {
+                    recvs.push(HaloRecv {
+                        from: from,
+                        halo_range: pos..pos,
+                        remote_indices: Vec::new(),
+                    });
+                }
+                let recv = recvs.last_mut().unwrap();
+                recv.halo_range.end = pos + 1;
+                recv.remote_indices.push(remote);
+            }
+
+            RowBlock {
+                rows: rows,
+                cols: cols,
+                local: local,
+                halo: halo,
+                recvs: recvs,
+            }
+        }).collect();
+        PartitionedCsMat {
+            shape: mat.shape(),
+            row_offsets: row_offsets,
+            col_offsets: col_offsets,
+            blocks: blocks,
+        }
+    }
+
+    /// Partition a matrix in `nb_parts` blocks of balanced numbers of rows,
+    /// the input vectors being partitioned in balanced ranges as well.
+    pub fn uniform<IpS, IS, DS>(mat: &CsMatBase<N, I, IpS, IS, DS>,
+                                nb_parts: usize) -> PartitionedCsMat<N, I>
+    where IpS: Deref<Target=[I]>,
+          IS: Deref<Target=[I]>,
+          DS: Deref<Target=[N]>,
+    {
+        assert!(nb_parts > 0, "at least one partition is required");
+        PartitionedCsMat::new(mat,
+                              uniform_offsets(mat.rows(), nb_parts),
+                              uniform_offsets(mat.cols(), nb_parts))
+    }
+
+    /// The shape of the global matrix
+    pub fn shape(&self) -> Shape {
+        self.shape
+    }
+
+    /// The blocks of rows
+    pub fn blocks(&self) -> &[RowBlock<N, I>] {
+        &self.blocks
+    }
+
+    /// The number of partitions
+    pub fn nb_parts(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Split a global input vector in the parts owned by each worker
+    pub fn split_input(&self, x: &[N]) -> Vec<Vec<N>> {
+        assert_eq!(x.len(), self.shape.1, "Dimension mismatch");
+        self.col_offsets.windows(2).map(|w| x[w[0]..w[1]].to_vec()).collect()
+    }
+
+    /// Join the parts of an output vector into the global vector
+    pub fn join_output(&self, parts: &[Vec<N>]) -> Vec<N> {
+        assert_eq!(parts.len(), self.nb_parts(), "Dimension mismatch");
+        let mut res = Vec::with_capacity(self.shape.0);
+        for part in parts {
+            res.extend_from_slice(part);
+        }
+        assert_eq!(res.len(), self.shape.0, "Dimension mismatch");
+        res
+    }
+
+    /// Gather the halo of each block from the owned parts of an input
+    /// vector, as a message passing implementation would do by sending
+    /// the requested entries.
+    pub fn halo_exchange(&self, parts: &[Vec<N>]) -> Vec<Vec<N>> {
+        assert_eq!(parts.len(), self.nb_parts(), "Dimension mismatch");
+        self.blocks.iter().map(|block| {
+            let mut halo = Vec::with_capacity(block.halo.len());
+            for recv in &block.recvs {
+                let from = &parts[recv.from];
+                halo.extend(recv.remote_indices.iter().map(|&i| from[i]));
+            }
+            halo
+        }).collect()
+    }
+
+    /// Partitioned matrix-vector product: given the owned parts of the
+    /// input vector, compute the owned parts of the output vector.
+    pub fn mul_vec(&self, parts: &[Vec<N>]) -> Vec<Vec<N>> {
+        let halos = self.halo_exchange(parts);
+        self.blocks.iter().zip(parts.iter().zip(halos.iter()))
+                   .map(|(block, (owned, halo))| {
+            let mut y = vec![N::zero(); block.rows.len()];
+            block.mul_local(owned, halo, &mut y);
+            y
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sparse::CsMat;
+    use test_data::{mat1, mat1_csc};
+    use super::PartitionedCsMat;
+
+    #[test]
+    fn partitioned_matvec() {
+        let x = vec![1., 2., 3., 4., 5.];
+        let expected = &mat1() * &::ndarray::arr1(&x);
+        for nb_parts in 1..6 {
+            let part = PartitionedCsMat::uniform(&mat1(), nb_parts);
+            let y = part.join_output(&part.mul_vec(&part.split_input(&x)));
+            assert_eq!(y, expected.to_vec());
+        }
+        let part = PartitionedCsMat::new(&mat1_csc(),
+                                         vec![0, 0, 3, 5],
+                                         vec![0, 2, 2, 5]);
+        let y = part.join_output(&part.mul_vec(&part.split_input(&x)));
+        assert_eq!(y, expected.to_vec());
+    }
+
+    #[test]
+    fn halo_metadata() {
+        let mat: CsMat<f64> = mat1();
+        let part = PartitionedCsMat::uniform(&mat, 2);
+        // rows 0 and 1 reference columns 2, 3 and 4, columns 0 and 1 are
+        // owned by the first worker
+        let block = &part.blocks()[0];
+        assert_eq!(block.rows(), 0..2);
+        assert_eq!(block.owned_cols(), 0..2);
+        assert_eq!(block.halo(), &[2, 3, 4]);
+        assert_eq!(block.recvs().len(), 1);
+        assert_eq!(block.recvs()[0].from, 1);
+        assert_eq!(block.recvs()[0].remote_indices, vec![0, 1, 2]);
+        assert_eq!(block.local().cols(), 5);
+        // rows 2 to 4 reference columns 1, 2 and 3, only 1 is remote
+        let block = &part.blocks()[1];
+        assert_eq!(block.halo(), &[1]);
+        assert_eq!(block.recvs()[0].from, 0);
+        assert_eq!(block.recvs()[0].remote_indices, vec![1]);
+    }
+}
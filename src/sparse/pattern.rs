@@ -0,0 +1,369 @@
+///! Sparsity patterns, ie matrices storing only their structure
+///!
+///! Many computations only depend on the positions of the non-zeros of a
+///! matrix: reachability in a graph, symbolic analysis of a factorization,
+///! masks of the masked products. A `PatternMat` stores the compressed
+///! structure of a matrix without allocating any value, which takes half
+///! the memory of a `CsMat<bool>` with `usize` indices, and offers the
+///! logical operations on patterns.
+///!
+///! Internally, a pattern is a `CsMatI<(), I>`, whose zero-sized values
+///! require no allocation. Its `view` can thus be passed wherever the
+///! values of a matrix are ignored, such as the mask of
+///! `prod::csr_mul_csr_masked`.
+///!
+///! # Example
+///!
+///! ```rust
+///! use sprs::{CsMat, PatternMat};
+///! // the edges of the path graph 0 -> 1 -> 2
+///! let edges = PatternMat::new((3, 3), vec![0, 1, 2, 2], vec![1, 2]);
+///! let two_steps = edges.matmul(&edges);
+///! assert!(two_steps.contains(0, 2));
+///! assert_eq!(two_steps.nnz(), 1);
+///! let either = &edges | &two_steps;
+///! assert_eq!(either.to_bool(), CsMat::new((3, 3), vec![0, 2, 3, 3],
+///!                                         vec![1, 2, 2],
+///!                                         vec![true; 3]));
+///! ```
+
+use std::ops::{Deref, BitAnd, BitOr, BitXor};
+
+use indexing::SpIndex;
+use sparse::prelude::*;
+use sparse::csmat::CompressedStorage::{self, CSR};
+use ::Shape;
+
+/// A sparsity pattern, ie a compressed matrix without values
+#[derive(PartialEq, Debug, Clone)]
+pub struct PatternMatI<I: SpIndex> {
+    mat: CsMatI<(), I>,
+}
+
+pub type PatternMat = PatternMatI<usize>;
+
+impl<I: SpIndex> PatternMatI<I> {
+    /// Create a CSR pattern from its `indptr` and `indices` arrays. The
+    /// indices are sorted if necessary.
+    ///
+    /// # Panics
+    ///
+    /// If the structure is invalid, see `CsMat::new`.
+    pub fn new(shape: Shape, indptr: Vec<I>, indices: Vec<I>)
+               -> PatternMatI<I> {
+        let nnz = indices.len();
+        PatternMatI {
+            mat: CsMatI::new(shape, indptr, indices, vec![(); nnz]),
+        }
+    }
+
+    /// Create a CSC pattern from its `indptr` and `indices` arrays. The
+    /// indices are sorted if necessary.
+    ///
+    /// # Panics
+    ///
+    /// If the structure is invalid, see `CsMat::new_csc`.
+    pub fn new_csc(shape: Shape, indptr: Vec<I>, indices: Vec<I>)
+                   -> PatternMatI<I> {
+        let nnz = indices.len();
+        PatternMatI {
+            mat: CsMatI::new_csc(shape, indptr, indices, vec![(); nnz]),
+        }
+    }
+
+    /// The pattern of the non-zeros of `mat`, which are all kept whatever
+    /// their value.
+    pub fn from_csmat<N, IpS, IS, DS>(mat: &CsMatBase<N, I, IpS, IS, DS>)
+                                      -> PatternMatI<I>
+    where IpS: Deref<Target=[I]>,
+          IS: Deref<Target=[I]>,
+          DS: Deref<Target=[N]>,
+    {
+        let data = vec![(); mat.indices().len()];
+        let structure = CsMatViewI {
+            storage: mat.storage(),
+            nrows: mat.rows(),
+            ncols: mat.cols(),
+            indptr: mat.indptr(),
+            indices: mat.indices(),
+            data: &data[..],
+        };
+        PatternMatI {
+            mat: structure.to_owned(),
+        }
+    }
+
+    /// The pattern of the `true` entries of a boolean matrix
+    pub fn from_bool<IpS, IS, DS>(mat: &CsMatBase<bool, I, IpS, IS, DS>)
+                                  -> PatternMatI<I>
+    where IpS: Deref<Target=[I]>,
+          IS: Deref<Target=[I]>,
+          DS: Deref<Target=[bool]>,
+    {
+        let mut indptr = Vec::with_capacity(mat.outer_dims() + 1);
+        indptr.push(I::zero());
+        let mut indices = Vec::new();
+        for vec in mat.outer_iterator() {
+            for (inner, &val) in vec.iter() {
+                if val {
+                    indices.push(I::from_usize(inner));
+                }
+            }
+            indptr.push(I::from_usize(indices.len()));
+        }
+        PatternMatI::from_parts(mat.storage(), mat.shape(), indptr, indices)
+    }
+
+    /// Build a pattern from a structure known to be valid
+    fn from_parts(storage: CompressedStorage,
+                  shape: Shape,
+                  indptr: Vec<I>,
+                  indices: Vec<I>) -> PatternMatI<I> {
+        let nnz = indices.len();
+        PatternMatI {
+            mat: CsMatI {
+                storage: storage,
+                nrows: shape.0,
+                ncols: shape.1,
+                indptr: indptr,
+                indices: indices,
+                data: vec![(); nnz],
+            },
+        }
+    }
+
+    /// The boolean matrix which is `true` on this pattern
+    pub fn to_bool(&self) -> CsMatI<bool, I> {
+        CsMatI {
+            storage: self.storage(),
+            nrows: self.rows(),
+            ncols: self.cols(),
+            indptr: self.indptr().to_vec(),
+            indices: self.indices().to_vec(),
+            data: vec![true; self.nnz()],
+        }
+    }
+
+    /// A view of this pattern as a matrix of `()` values, which can be
+    /// passed as the mask of the masked kernels.
+    pub fn view(&self) -> CsMatViewI<(), I> {
+        self.mat.view()
+    }
+
+    pub fn shape(&self) -> Shape {
+        self.mat.shape()
+    }
+
+    pub fn rows(&self) -> usize {
+        self.mat.rows()
+    }
+
+    pub fn cols(&self) -> usize {
+        self.mat.cols()
+    }
+
+    pub fn nnz(&self) -> usize {
+        self.mat.nnz()
+    }
+
+    pub fn storage(&self) -> CompressedStorage {
+        self.mat.storage()
+    }
+
+    pub fn indptr(&self) -> &[I] {
+        self.mat.indptr()
+    }
+
+    pub fn indices(&self) -> &[I] {
+        self.mat.indices()
+    }
+
+    /// Whether the position `(row, col)` is part of the pattern
+    pub fn contains(&self, row: usize, col: usize) -> bool {
+        self.mat.get(row, col).is_some()
+    }
+
+    /// The same pattern in the other storage
+    pub fn to_other_storage(&self) -> PatternMatI<I> {
+        PatternMatI {
+            mat: self.mat.to_other_storage(),
+        }
+    }
+
+    /// The transposed pattern, in the other storage. This is a copy of the
+    /// structure, without any sorting.
+    pub fn transpose(&self) -> PatternMatI<I> {
+        PatternMatI {
+            mat: self.mat.transpose_view().to_owned(),
+        }
+    }
+
+    /// The pattern of the product `self * rhs`, ie the boolean product of
+    /// the patterns: `(i, j)` is in the result when some `k` has `(i, k)`
+    /// in `self` and `(k, j)` in `rhs`. For adjacency patterns, this is
+    /// the reachability in two steps. The result is in CSR storage.
+    ///
+    /// # Panics
+    ///
+    /// If `self.cols() != rhs.rows()`.
+    pub fn matmul(&self, rhs: &PatternMatI<I>) -> PatternMatI<I> {
+        if self.cols() != rhs.rows() {
+            panic!("Dimension mismatch");
+        }
+        let (lhs_csr, rhs_csr);
+        let lhs = if self.storage() == CSR { self } else {
+            lhs_csr = self.to_other_storage();
+            &lhs_csr
+        };
+        let rhs = if rhs.storage() == CSR { rhs } else {
+            rhs_csr = rhs.to_other_storage();
+            &rhs_csr
+        };
+        let mut seen = vec![usize::max_value(); rhs.cols()];
+        let mut indptr = Vec::with_capacity(lhs.rows() + 1);
+        indptr.push(I::zero());
+        let mut indices = Vec::new();
+        for (row, lvec) in lhs.mat.outer_iterator().enumerate() {
+            let start = indices.len();
+            for &k in lvec.indices() {
+                let rvec = rhs.mat.outer_view(k.index()).unwrap();
+                for &col in rvec.indices() {
+                    if seen[col.index()] != row {
+                        seen[col.index()] = row;
+                        indices.push(col);
+                    }
+                }
+            }
+            indices[start..].sort();
+            indptr.push(I::from_usize(indices.len()));
+        }
+        PatternMatI::from_parts(CSR, (lhs.rows(), rhs.cols()), indptr, indices)
+    }
+
+    /// Combine two patterns of the same shape, keeping the positions for
+    /// which `keep(in_self, in_rhs)` is true. The result has the storage of
+    /// `self`.
+    fn combine<F>(&self, rhs: &PatternMatI<I>, keep: F) -> PatternMatI<I>
+    where F: Fn(bool, bool) -> bool
+    {
+        if self.shape() != rhs.shape() {
+            panic!("Dimension mismatch");
+        }
+        let rhs_conv;
+        let rhs = if rhs.storage() == self.storage() { rhs } else {
+            rhs_conv = rhs.to_other_storage();
+            &rhs_conv
+        };
+        let mut indptr = Vec::with_capacity(self.mat.outer_dims() + 1);
+        indptr.push(I::zero());
+        let mut indices = Vec::new();
+        for (lvec, rvec) in self.mat.outer_iterator()
+                                .zip(rhs.mat.outer_iterator()) {
+            let (lind, rind) = (lvec.indices(), rvec.indices());
+            let (mut i, mut j) = (0, 0);
+            while i < lind.len() || j < rind.len() {
+                let (ind, in_l, in_r) = if j == rind.len()
+                                           || (i < lind.len()
+                                               && lind[i] < rind[j]) {
+                    i += 1;
+                    (lind[i - 1], true, false)
+                } else if i == lind.len() || rind[j] < lind[i] {
+                    j += 1;
+                    (rind[j - 1], false, true)
+                } else {
+                    i += 1;
+                    j += 1;
+                    (lind[i - 1], true, true)
+                };
+                if keep(in_l, in_r) {
+                    indices.push(ind);
+                }
+            }
+            indptr.push(I::from_usize(indices.len()));
+        }
+        PatternMatI::from_parts(self.storage(), self.shape(), indptr, indices)
+    }
+}
+
+impl<'a, 'b, I: SpIndex> BitAnd<&'b PatternMatI<I>> for &'a PatternMatI<I> {
+    type Output = PatternMatI<I>;
+    /// The intersection of two patterns
+    fn bitand(self, rhs: &'b PatternMatI<I>) -> PatternMatI<I> {
+        self.combine(rhs, |l, r| l && r)
+    }
+}
+
+impl<'a, 'b, I: SpIndex> BitOr<&'b PatternMatI<I>> for &'a PatternMatI<I> {
+    type Output = PatternMatI<I>;
+    /// The union of two patterns
+    fn bitor(self, rhs: &'b PatternMatI<I>) -> PatternMatI<I> {
+        self.combine(rhs, |l, r| l || r)
+    }
+}
+
+impl<'a, 'b, I: SpIndex> BitXor<&'b PatternMatI<I>> for &'a PatternMatI<I> {
+    type Output = PatternMatI<I>;
+    /// The symmetric difference of two patterns
+    fn bitxor(self, rhs: &'b PatternMatI<I>) -> PatternMatI<I> {
+        self.combine(rhs, |l, r| l != r)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sparse::CsMat;
+    use sparse::prod;
+    use test_data::{mat1, mat1_csc, mat2};
+    use super::PatternMat;
+
+    #[test]
+    fn conversions() {
+        let pat = PatternMat::from_csmat(&mat1());
+        assert_eq!(pat.indices(), mat1().indices());
+        assert_eq!(pat.nnz(), 7);
+        assert!(pat.contains(3, 1));
+        assert!(!pat.contains(1, 2));
+        let bools = pat.to_bool();
+        assert_eq!(PatternMat::from_bool(&bools), pat);
+        let mixed = CsMat::new((1, 3), vec![0, 2], vec![0, 2],
+                               vec![false, true]);
+        assert_eq!(PatternMat::from_bool(&mixed).indices(), &[2]);
+        assert_eq!(pat.transpose(),
+                   PatternMat::from_csmat(&mat1().transpose_view()));
+        assert!(pat.transpose().contains(1, 3));
+    }
+
+    #[test]
+    fn logical_ops() {
+        let a = PatternMat::from_csmat(&mat1());
+        let b = PatternMat::from_csmat(&mat2());
+        let b_csc = b.to_other_storage();
+        let and = &a & &b_csc;
+        let or = &a | &b;
+        let xor = &a ^ &b;
+        assert_eq!(and.nnz() + or.nnz(), a.nnz() + b.nnz());
+        assert_eq!(xor.nnz(), or.nnz() - and.nnz());
+        for i in 0..5 {
+            for j in 0..5 {
+                let (in_a, in_b) = (a.contains(i, j), b.contains(i, j));
+                assert_eq!(and.contains(i, j), in_a && in_b);
+                assert_eq!(or.contains(i, j), in_a || in_b);
+                assert_eq!(xor.contains(i, j), in_a != in_b);
+            }
+        }
+    }
+
+    #[test]
+    fn boolean_product() {
+        let a = PatternMat::from_csmat(&mat1());
+        let b = PatternMat::from_csmat(&mat1_csc());
+        let prod = a.matmul(&b);
+        let expected = PatternMat::from_csmat(&(&mat1() * &mat1()));
+        assert_eq!(prod, expected);
+
+        // the pattern can be used as a mask
+        let masked = prod::csr_mul_csr_masked(mat1().view(), mat1().view(),
+                                              prod.view());
+        assert_eq!(masked, &mat1() * &mat1());
+    }
+}
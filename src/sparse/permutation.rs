@@ -1,9 +1,19 @@
 /// Representation of permutation matrices
 ///
 /// Both the permutation matrices and its inverse are stored
+///
+/// A permutation `P` given by the vector `perm` maps a vector `x` to the
+/// vector `y = P x` such that `y[i] = x[perm[i]]`.
 
 use std::ops::{Deref, Mul};
+use std::vec::Vec;
+#[cfg(feature = "std")]
+use ndarray::{ArrayView, Array};
 use indexing::SpIndex;
+use sparse::prelude::*;
+use errors::{SprsError, StructureError};
+#[cfg(feature = "std")]
+use ::Ix1;
 
 #[derive(Debug, Clone)]
 enum PermStorage<I, IndStorage>
@@ -34,18 +44,80 @@ pub type PermViewI<'a, I> = Permutation<I, &'a [I]>;
 
 impl<I: SpIndex> Permutation<I, Vec<I>> {
 
+    /// Create a permutation from its vector representation. The inverse
+    /// permutation is computed and stored alongside.
+    ///
+    /// # Panics
+    ///
+    /// If `perm` is not a permutation of `0..perm.len()`.
     pub fn new(perm: Vec<I>) -> PermOwnedI<I> {
-        let mut perm_inv = perm.clone();
+        PermOwnedI::new_checked(perm).expect("Invalid permutation")
+    }
+
+    /// Create a permutation from its vector representation, returning an
+    /// error if `perm` is not a permutation of `0..perm.len()`, ie if an
+    /// index is out of bounds or repeated.
+    pub fn new_checked(perm: Vec<I>) -> Result<PermOwnedI<I>, SprsError> {
+        let dim = perm.len();
+        let mut perm_inv = vec![I::zero(); dim];
+        let mut seen = vec![false; dim];
         for (ind, val) in perm.iter().enumerate() {
-            perm_inv[val.index()] = I::from_usize(ind);
+            let val = val.index();
+            if val >= dim {
+                return Err(StructureError::OutOfBoundsIndex.into());
+            }
+            if seen[val] {
+                return Err(StructureError::NotAPermutation.into());
+            }
+            seen[val] = true;
+            perm_inv[val] = I::from_usize(ind);
         }
-        PermOwnedI {
-            dim: perm.len(),
+        Ok(PermOwnedI {
+            dim: dim,
             storage: FinitePerm {
                 perm: perm,
                 perm_inv: perm_inv
             },
+        })
+    }
+
+    /// Create a permutation of dimension `dim` from its cycles. The cycle
+    /// `[a, b, c]` maps `a` to `b`, `b` to `c` and `c` to `a`, ie
+    /// `perm[a] = b`, `perm[b] = c` and `perm[c] = a`. The indices not
+    /// appearing in any cycle are fixed points.
+    ///
+    /// # Errors
+    ///
+    /// If an index is out of bounds or appears more than once.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::PermOwned;
+    /// let perm = PermOwned::from_cycles(5, &[vec![0, 2, 3]]).unwrap();
+    /// assert_eq!(perm.vec(), vec![2, 1, 3, 0, 4]);
+    /// assert_eq!(perm.to_cycles(), vec![vec![0, 2, 3]]);
+    /// ```
+    pub fn from_cycles<C>(dim: usize, cycles: &[C])
+                          -> Result<PermOwnedI<I>, SprsError>
+    where C: AsRef<[usize]>
+    {
+        let mut perm: Vec<usize> = (0..dim).collect();
+        let mut seen = vec![false; dim];
+        for cycle in cycles {
+            let cycle = cycle.as_ref();
+            for (pos, &ind) in cycle.iter().enumerate() {
+                if ind >= dim {
+                    return Err(StructureError::OutOfBoundsIndex.into());
+                }
+                if seen[ind] {
+                    return Err(StructureError::NotAPermutation.into());
+                }
+                seen[ind] = true;
+                perm[ind] = cycle[(pos + 1) % cycle.len()];
+            }
         }
+        PermOwnedI::new_checked(perm.into_iter().map(I::from_usize).collect())
     }
 }
 
@@ -91,6 +163,8 @@ where IndStorage: Deref<Target=[I]> {
         }
     }
 
+    /// A view of the inverse permutation. As the inverse is stored with the
+    /// permutation, this is free.
     pub fn inv(&self) -> PermViewI<I> {
         match &self.storage {
             &Identity => PermViewI {
@@ -173,6 +247,137 @@ where IndStorage: Deref<Target=[I]> {
         }
     }
 
+    /// The dimension of the permutation
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    /// Whether this is the identity permutation
+    pub fn is_identity(&self) -> bool {
+        match &self.storage {
+            &Identity => true,
+            &FinitePerm { perm: ref p, perm_inv: _ } => {
+                p.iter().enumerate().all(|(i, pi)| pi.index() == i)
+            },
+        }
+    }
+
+    /// The composition of this permutation with `other`, ie the permutation
+    /// of the matrix product `self * other`, which applies `other` first.
+    ///
+    /// # Panics
+    ///
+    /// If the dimensions of the permutations differ.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::PermOwned;
+    /// let p = PermOwned::new(vec![2, 0, 1]);
+    /// let q = PermOwned::new(vec![1, 0, 2]);
+    /// let x = [10, 20, 30];
+    /// let pq = p.compose(&q);
+    /// assert_eq!(&pq * &x[..], &p * &(&q * &x[..])[..]);
+    /// ```
+    pub fn compose<IS2>(&self, other: &Permutation<I, IS2>) -> PermOwnedI<I>
+    where IS2: Deref<Target=[I]>
+    {
+        assert_eq!(self.dim, other.dim, "Dimension mismatch");
+        match (&self.storage, &other.storage) {
+            (&Identity, _) => other.owned_clone(),
+            (_, &Identity) => self.owned_clone(),
+            _ => {
+                let perm = (0..self.dim).map(|i| {
+                    I::from_usize(other.at(self.at(i)))
+                }).collect();
+                PermOwnedI::new(perm)
+            },
+        }
+    }
+
+    /// The cycles of this permutation, each starting with its smallest
+    /// index, in increasing order of their first index. Fixed points are
+    /// omitted. See `PermOwnedI::from_cycles` for the convention.
+    pub fn to_cycles(&self) -> Vec<Vec<usize>> {
+        let mut visited = vec![false; self.dim];
+        let mut cycles = Vec::new();
+        for start in 0..self.dim {
+            if visited[start] || self.at(start) == start {
+                continue;
+            }
+            let mut cycle = Vec::new();
+            let mut cur = start;
+            while !visited[cur] {
+                visited[cur] = true;
+                cycle.push(cur);
+                cur = self.at(cur);
+            }
+            cycles.push(cycle);
+        }
+        cycles
+    }
+
+    /// Apply this permutation to a slice in place, ie replace `x` by
+    /// `P x`, using a buffer of the size of the slice.
+    ///
+    /// # Panics
+    ///
+    /// If the dimensions do not match.
+    pub fn apply_in_place<N: Clone>(&self, x: &mut [N]) {
+        assert_eq!(self.dim, x.len(), "Dimension mismatch");
+        if let &FinitePerm { perm: ref p, perm_inv: _ } = &self.storage {
+            let permuted: Vec<N> = p.iter()
+                                    .map(|pi| x[pi.index()].clone())
+                                    .collect();
+            for (xi, val) in x.iter_mut().zip(permuted) {
+                *xi = val;
+            }
+        }
+    }
+
+    /// Apply this permutation to a dense array, returning `P x`
+    ///
+    /// # Panics
+    ///
+    /// If the dimensions do not match.
+    #[cfg(feature = "std")]
+    pub fn apply_dense<N: Clone>(&self, x: ArrayView<N, Ix1>)
+                                 -> Array<N, Ix1> {
+        assert_eq!(self.dim, x.len(), "Dimension mismatch");
+        Array::from_vec((0..self.dim).map(|i| x[self.at(i)].clone())
+                                     .collect())
+    }
+
+    /// Apply this permutation to a sparse vector, returning `P x`
+    ///
+    /// # Panics
+    ///
+    /// If the dimensions do not match.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::{CsVec, PermOwned};
+    /// let p = PermOwned::new(vec![2, 1, 3, 0, 4]);
+    /// let x = CsVec::new(5, vec![0, 2], vec![1., 2.]);
+    /// let y = p.apply_csvec(&x);
+    /// assert_eq!(y, CsVec::new(5, vec![0, 3], vec![2., 1.]));
+    /// ```
+    pub fn apply_csvec<N, IS, DS>(&self, x: &CsVecBase<IS, DS>) -> CsVecI<N, I>
+    where N: Copy,
+          IS: Deref<Target=[I]>,
+          DS: Deref<Target=[N]>,
+    {
+        assert_eq!(self.dim, x.dim(), "Dimension mismatch");
+        // the entry at j in x moves to perm_inv[j] in P x, the constructor
+        // sorts the moved entries
+        let indices = x.indices()
+                       .iter()
+                       .map(|j| I::from_usize(self.at_inv(j.index())))
+                       .collect();
+        CsVecI::new(self.dim, indices, x.data().to_vec())
+    }
+
     pub fn to_other_idx_type<I2>(&self) -> PermOwnedI<I2>
     where I2: SpIndex
     {
@@ -221,7 +426,7 @@ where IndStorage: 'b + Deref<Target=[I]>,
     }
 }
 
-
+#[cfg(test)]
 mod test {
 
     #[test]
@@ -237,4 +442,48 @@ mod test {
         let y = &p * &x;
         assert_eq!(&y, &[2, 1, 3, 5, 4]);
     }
+
+    #[test]
+    fn perm_validation() {
+        use errors::StructureError;
+        use super::PermOwned;
+        let res = PermOwned::new_checked(vec![0, 2, 2]);
+        assert_eq!(res.err(), Some(StructureError::NotAPermutation.into()));
+        let res = PermOwned::new_checked(vec![0, 3, 1]);
+        assert_eq!(res.err(), Some(StructureError::OutOfBoundsIndex.into()));
+        assert!(PermOwned::from_cycles(3, &[vec![0, 1], vec![1, 2]]).is_err());
+    }
+
+    #[test]
+    fn perm_compose_and_cycles() {
+        use super::PermOwned;
+        let p = PermOwned::new(vec![2, 1, 3, 0, 4]);
+        let q = PermOwned::new(vec![4, 3, 2, 1, 0]);
+        let x = vec![5, 1, 2, 3, 4];
+        let pq = p.compose(&q);
+        assert_eq!(&pq * &x, &p * &(&q * &x));
+        assert!(p.compose(&p.inv()).is_identity());
+        assert!(p.compose(&PermOwned::identity(5)).vec() == p.vec());
+
+        assert_eq!(q.to_cycles(), vec![vec![0, 4], vec![1, 3]]);
+        let q2 = PermOwned::from_cycles(5, &q.to_cycles()[..]).unwrap();
+        assert_eq!(q2.vec(), q.vec());
+        assert!(PermOwned::identity(3).to_cycles().is_empty());
+    }
+
+    #[test]
+    fn perm_apply() {
+        use ndarray::arr1;
+        use sparse::CsVec;
+        use super::PermOwned;
+        let p = PermOwned::new(vec![2, 1, 3, 0, 4]);
+        let mut x = vec![5, 1, 2, 3, 4];
+        p.apply_in_place(&mut x);
+        assert_eq!(x, vec![2, 1, 3, 5, 4]);
+        let dense = arr1(&[5., 1., 2., 3., 4.]);
+        assert_eq!(p.apply_dense(dense.view()), arr1(&[2., 1., 3., 5., 4.]));
+        let sparse = CsVec::new(5, vec![0, 3], vec![5., 3.]);
+        let expected = CsVec::new(5, vec![2, 3], vec![3., 5.]);
+        assert_eq!(p.apply_csvec(&sparse), expected);
+    }
 }
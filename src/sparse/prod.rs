@@ -1,10 +1,16 @@
 ///! Sparse matrix product
 
+use std::ops::Deref;
+use std::vec::Vec;
 use sparse::prelude::*;
 use indexing::SpIndex;
 use num_traits::Num;
 use sparse::compressed::SpMatView;
+use sparse::accumulator::SparseAccumulator;
+use sparse::CompressedStorage::CSR;
+#[cfg(feature = "std")]
 use ndarray::{ArrayView, ArrayViewMut, Axis};
+#[cfg(feature = "std")]
 use ::Ix2;
 
 /// Multiply a sparse CSC matrix with a dense vector and accumulate the result
@@ -12,7 +18,7 @@ use ::Ix2;
 pub fn mul_acc_mat_vec_csc<N, I>(mat: CsMatViewI<N, I>,
                                  in_vec: &[N],
                                  res_vec: &mut[N])
-where N: Num + Copy,
+where N: Num + Clone,
       I: SpIndex,
 {
     let mat = mat.view();
@@ -25,10 +31,10 @@ where N: Num + Copy,
 
     for (col_ind, vec) in mat.outer_iterator().enumerate() {
         let multiplier = &in_vec[col_ind];
-        for (row_ind, &value) in vec.iter() {
+        for (row_ind, value) in vec.iter() {
             // TODO: unsafe access to value? needs bench
-            res_vec[row_ind] =
-                res_vec[row_ind] + *multiplier * value;
+            let prod = multiplier.clone() * value.clone();
+            res_vec[row_ind] = res_vec[row_ind].clone() + prod;
         }
     }
 }
@@ -38,7 +44,7 @@ where N: Num + Copy,
 pub fn mul_acc_mat_vec_csr<N, I>(mat: CsMatViewI<N, I>,
                                  in_vec: &[N],
                                  res_vec: &mut[N])
-where N: Num + Copy,
+where N: Num + Clone,
       I: SpIndex,
 {
     if mat.cols() != in_vec.len() || mat.rows() != res_vec.len() {
@@ -49,14 +55,322 @@ where N: Num + Copy,
     }
 
     for (row_ind, vec) in mat.outer_iterator().enumerate() {
-        for (col_ind, &value) in vec.iter() {
+        for (col_ind, value) in vec.iter() {
             // TODO: unsafe access to value? needs bench
-            res_vec[row_ind] =
-                res_vec[row_ind] + in_vec[col_ind] * value;
+            let prod = in_vec[col_ind].clone() * value.clone();
+            res_vec[row_ind] = res_vec[row_ind].clone() + prod;
         }
     }
 }
 
+/// Compute the residual `r = b - a * x` of a linear system, in a single
+/// pass over the vectors for CSR matrices.
+///
+/// # Panics
+///
+/// On dimension mismatch.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::prod::residual;
+/// let a = CsMat::new((2, 2), vec![0, 2, 3], vec![0, 1, 1],
+///                    vec![2., 1., 3.]);
+/// let mut r = vec![0.; 2];
+/// residual(&a, &[1., 1.], &[4., 4.], &mut r);
+/// assert_eq!(r, vec![1., 1.]);
+/// ```
+pub fn residual<N, I, IpS, IS, DS>(a: &CsMatBase<N, I, IpS, IS, DS>,
+                                   x: &[N],
+                                   b: &[N],
+                                   r: &mut [N])
+where N: Num + Clone,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    if a.cols() != x.len() || a.rows() != b.len() || a.rows() != r.len() {
+        panic!("Dimension mismatch");
+    }
+    if a.is_csr() {
+        for ((ri, bi), vec) in r.iter_mut().zip(b).zip(a.outer_iterator()) {
+            *ri = vec.iter().fold(bi.clone(), |acc, (col, val)| {
+                acc - val.clone() * x[col].clone()
+            });
+        }
+    } else {
+        r.clone_from_slice(b);
+        for (col, vec) in a.outer_iterator().enumerate() {
+            let xj = &x[col];
+            for (row, val) in vec.iter() {
+                r[row] = r[row].clone() - val.clone() * xj.clone();
+            }
+        }
+    }
+}
+
+/// Compute `y = alpha * a * x + beta * y`, in a single pass over the
+/// vectors for CSR matrices. As in BLAS, `y` is not read when `beta` is
+/// zero, so it does not need to be initialized.
+///
+/// # Panics
+///
+/// On dimension mismatch.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// use sprs::prod::spmv_axpby;
+/// let a = CsMat::new((2, 2), vec![0, 2, 3], vec![0, 1, 1],
+///                    vec![2., 1., 3.]);
+/// let mut y = vec![1., 1.];
+/// spmv_axpby(2., &a, &[1., 1.], -1., &mut y);
+/// assert_eq!(y, vec![5., 5.]);
+/// ```
+pub fn spmv_axpby<N, I, IpS, IS, DS>(alpha: N,
+                                     a: &CsMatBase<N, I, IpS, IS, DS>,
+                                     x: &[N],
+                                     beta: N,
+                                     y: &mut [N])
+where N: Num + Clone,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    if a.cols() != x.len() || a.rows() != y.len() {
+        panic!("Dimension mismatch");
+    }
+    let scale = |yi: &N| if beta.is_zero() {
+        N::zero()
+    } else {
+        beta.clone() * yi.clone()
+    };
+    if a.is_csr() {
+        for (yi, vec) in y.iter_mut().zip(a.outer_iterator()) {
+            let ax = vec.iter().fold(N::zero(), |acc, (col, val)| {
+                acc + val.clone() * x[col].clone()
+            });
+            *yi = alpha.clone() * ax + scale(&*yi);
+        }
+    } else {
+        for yi in y.iter_mut() {
+            *yi = scale(&*yi);
+        }
+        for (col, vec) in a.outer_iterator().enumerate() {
+            let axj = alpha.clone() * x[col].clone();
+            for (row, val) in vec.iter() {
+                y[row] = y[row].clone() + val.clone() * axj.clone();
+            }
+        }
+    }
+}
+
+/// The traversal used by `mul_acc_mat_vec_csr_policy` for the product of a
+/// CSR matrix with a dense vector.
+///
+/// All policies accumulate the products of a row in the same order, so
+/// they give the same results. They only differ in their memory accesses,
+/// which dominate the cost of the product for large matrices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatVecPolicy {
+    /// Traverse the rows one after the other, as `mul_acc_mat_vec_csr`
+    Simple,
+    /// Traverse the rows one after the other, prefetching the elements of
+    /// the input vector needed `distance` non-zeros ahead, which hides the
+    /// latency of the irregular accesses. Prefetching is only performed on
+    /// x86 targets supporting SSE, this is the simple traversal otherwise.
+    Prefetch {
+        distance: usize,
+    },
+    /// Traverse the matrix by blocks of `block_cols` columns, so that the
+    /// part of the input vector used by a block can stay in cache while all
+    /// the rows are traversed. This pays off when the input vector does not
+    /// fit in the cache, as long as the rows are not too sparse.
+    Blocked {
+        block_cols: usize,
+    },
+}
+
+/// The size of the cache the input vector should fit in, a common size
+/// for the L2 cache
+const CACHE_BYTES: usize = 256 * 1024;
+
+impl MatVecPolicy {
+    /// A policy suited to the product of `mat` by a vector: the matrix is
+    /// traversed by blocks using half of a typical L2 cache when the input
+    /// vector does not fit in it, and row by row otherwise.
+    pub fn for_matrix<N, I>(mat: CsMatViewI<N, I>) -> MatVecPolicy
+    where I: SpIndex
+    {
+        let scalar_size = ::std::mem::size_of::<N>().max(1);
+        if mat.cols() * scalar_size > CACHE_BYTES {
+            MatVecPolicy::Blocked { block_cols: CACHE_BYTES / 2 / scalar_size }
+        } else {
+            MatVecPolicy::Simple
+        }
+    }
+}
+
+impl Default for MatVecPolicy {
+    fn default() -> MatVecPolicy {
+        MatVecPolicy::Simple
+    }
+}
+
+/// Multiply a sparse CSR matrix with a dense vector and accumulate the
+/// result into another dense vector, traversing the matrix as specified by
+/// `policy`.
+///
+/// # Panics
+///
+/// - on dimension or storage mismatch
+/// - if the blocks of `MatVecPolicy::Blocked` are empty
+pub fn mul_acc_mat_vec_csr_policy<N, I>(mat: CsMatViewI<N, I>,
+                                        in_vec: &[N],
+                                        res_vec: &mut [N],
+                                        policy: MatVecPolicy)
+where N: Num + Clone,
+      I: SpIndex,
+{
+    if mat.cols() != in_vec.len() || mat.rows() != res_vec.len() {
+        panic!("Dimension mismatch");
+    }
+    if !mat.is_csr() {
+        panic!("Storage mismatch");
+    }
+    // the positions in indptr are absolute, even for views of some rows
+    let indptr = mat.indptr();
+    let indices = mat.indices();
+    let data = mat.data();
+    match policy {
+        MatVecPolicy::Simple => {
+            mul_acc_mat_vec_csr(mat.view(), in_vec, res_vec)
+        },
+        MatVecPolicy::Prefetch { distance } => {
+            let nnz_end = indptr[indptr.len() - 1].index();
+            for (res, window) in res_vec.iter_mut().zip(indptr.windows(2)) {
+                let mut acc = res.clone();
+                for k in window[0].index()..window[1].index() {
+                    if k + distance < nnz_end {
+                        let ahead = indices[k + distance].index();
+                        prefetch(&in_vec[ahead]);
+                    }
+                    let x = in_vec[indices[k].index()].clone();
+                    acc = acc + x * data[k].clone();
+                }
+                *res = acc;
+            }
+        },
+        MatVecPolicy::Blocked { block_cols } => {
+            assert!(block_cols > 0, "Empty blocks");
+            let mut cursors: Vec<_> = indptr.iter().map(|k| k.index())
+                                            .collect();
+            let mut block_start = 0;
+            while block_start < mat.cols() {
+                let block_end = (block_start + block_cols).min(mat.cols());
+                for (row, res) in res_vec.iter_mut().enumerate() {
+                    let stop = indptr[row + 1].index();
+                    let mut k = cursors[row];
+                    let mut acc = res.clone();
+                    while k < stop && indices[k].index() < block_end {
+                        let x = in_vec[indices[k].index()].clone();
+                        acc = acc + x * data[k].clone();
+                        k += 1;
+                    }
+                    cursors[row] = k;
+                    *res = acc;
+                }
+                block_start = block_end;
+            }
+        },
+    }
+}
+
+/// Hint the processor to load `val` into the cache
+#[inline(always)]
+fn prefetch<N>(val: &N) {
+    #[cfg(all(target_arch = "x86_64", target_feature = "sse"))]
+    unsafe {
+        use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+        _mm_prefetch(val as *const N as *const i8, _MM_HINT_T0);
+    }
+    #[cfg(all(target_arch = "x86", target_feature = "sse"))]
+    unsafe {
+        use std::arch::x86::{_mm_prefetch, _MM_HINT_T0};
+        _mm_prefetch(val as *const N as *const i8, _MM_HINT_T0);
+    }
+    #[cfg(not(all(any(target_arch = "x86", target_arch = "x86_64"),
+                  target_feature = "sse")))]
+    let _ = val;
+}
+
+/// Parallel version of `mul_acc_mat_vec_csr`, available with the `rayon`
+/// feature. The rows are processed in parallel, each element of the result
+/// being accumulated by a single thread, so the result does not depend on
+/// the number of threads. See the `parallel` module for the kernels whose
+/// reproducibility has to be requested.
+#[cfg(feature = "rayon")]
+pub fn par_mul_acc_mat_vec_csr<N, I>(mat: CsMatViewI<N, I>,
+                                     in_vec: &[N],
+                                     res_vec: &mut[N])
+where N: Num + Clone + Send + Sync,
+      I: SpIndex + Sync,
+{
+    use rayon::prelude::*;
+    use sparse::parallel::install;
+    if mat.cols() != in_vec.len() || mat.rows() != res_vec.len() {
+        panic!("Dimension mismatch");
+    }
+    if !mat.is_csr() {
+        panic!("Storage mismatch");
+    }
+
+    install(|| {
+        res_vec.par_iter_mut().enumerate().for_each(|(row_ind, res)| {
+            let vec = mat.outer_view(row_ind).unwrap();
+            for (col_ind, value) in vec.iter() {
+                *res = res.clone() + in_vec[col_ind].clone() * value.clone();
+            }
+        })
+    });
+}
+
+/// Multiply a sparse matrix with a dense vector and accumulate the result
+/// into another dense vector, using a wider scalar type `A` for the
+/// accumulation than the storage type `N` of the matrix.
+///
+/// This enables storing the matrix values in a compact type, eg `f16` or
+/// `f32`, to save memory bandwidth, while keeping the accuracy of the
+/// accumulation in `f32` or `f64`.
+pub fn mul_acc_mat_vec_mixed<N, A, I>(mat: CsMatViewI<N, I>,
+                                      in_vec: &[A],
+                                      res_vec: &mut[A])
+where N: Copy + Into<A>,
+      A: Num + Copy,
+      I: SpIndex,
+{
+    if mat.cols() != in_vec.len() || mat.rows() != res_vec.len() {
+        panic!("Dimension mismatch");
+    }
+    let is_csr = mat.is_csr();
+    for (outer_ind, vec) in mat.outer_iterator().enumerate() {
+        for (inner_ind, &value) in vec.iter() {
+            let value: A = value.into();
+            if is_csr {
+                res_vec[outer_ind] =
+                    res_vec[outer_ind] + in_vec[inner_ind] * value;
+            }
+            else {
+                res_vec[inner_ind] =
+                    res_vec[inner_ind] + in_vec[outer_ind] * value;
+            }
+        }
+    }
+}
 
 /// Perform a matrix multiplication for matrices sharing the same storage order.
 ///
@@ -73,7 +387,7 @@ pub fn csr_mul_csr<N, I, Mat1, Mat2>(lhs: &Mat1,
                                      workspace: &mut[N]
                                     ) -> CsMatI<N, I>
 where
-N: Num + Copy,
+N: Num + Clone,
 I: SpIndex,
 Mat1: SpMatView<N, I>,
 Mat2: SpMatView<N, I>
@@ -95,7 +409,7 @@ pub fn csc_mul_csc<N, I, Mat1, Mat2>(lhs: &Mat1,
                                      workspace: &mut[N]
                                     ) -> CsMatI<N, I>
 where
-N: Num + Copy,
+N: Num + Clone,
 I: SpIndex,
 Mat1: SpMatView<N, I>,
 Mat2: SpMatView<N, I>
@@ -107,7 +421,7 @@ Mat2: SpMatView<N, I>
 
 /// Allocate the appropriate workspace for a CSR-CSR product
 pub fn workspace_csr<N, I, Mat1, Mat2>(_: &Mat1, rhs: &Mat2) -> Vec<N>
-where N: Copy + Num,
+where N: Clone + Num,
       I: SpIndex,
       Mat1: SpMatView<N, I>,
       Mat2: SpMatView<N, I> {
@@ -117,7 +431,7 @@ where N: Copy + Num,
 
 /// Allocate the appropriate workspace for a CSC-CSC product
 pub fn workspace_csc<N, I, Mat1, Mat2>(lhs: &Mat1, _: &Mat2) -> Vec<N>
-where N: Copy + Num,
+where N: Clone + Num,
       I: SpIndex,
       Mat1: SpMatView<N, I>,
       Mat2: SpMatView<N, I> {
@@ -131,7 +445,7 @@ pub fn csr_mul_csr_impl<N, I>(lhs: CsMatViewI<N, I>,
                               rhs: CsMatViewI<N, I>,
                               workspace: &mut[N]
                              ) -> CsMatI<N, I>
-where N: Num + Copy,
+where N: Num + Clone,
       I: SpIndex
 {
     let res_rows = lhs.rows();
@@ -154,15 +468,15 @@ where N: Num + Copy,
             *wval = N::zero();
         }
         // accumulate the resulting row
-        for (lcol, &lval) in lvec.iter() {
+        for (lcol, lval) in lvec.iter() {
             // we can't be out of bounds thanks to the checks of dimension
             // compatibility and the structure check of CsMat. Therefore it
             // should be safe to call into an unsafe version of outer_view
             let rvec = rhs.outer_view(lcol).unwrap();
-            for (rcol, &rval) in rvec.iter() {
+            for (rcol, rval) in rvec.iter() {
                 let wval = &mut workspace[rcol];
-                let prod = lval * rval;
-                *wval = *wval + prod;
+                let prod = lval.clone() * rval.clone();
+                *wval = wval.clone() + prod;
             }
         }
         // compress the row into the resulting matrix
@@ -173,10 +487,152 @@ where N: Num + Copy,
     res
 }
 
+/// CSR-CSR multiplication with values stored as `N` and accumulated, as
+/// well as returned, in the wider type `A`, eg `f16` inputs with an `f32`
+/// result.
+pub fn csr_mul_csr_mixed<N, A, I>(lhs: CsMatViewI<N, I>,
+                                  rhs: CsMatViewI<N, I>) -> CsMatI<A, I>
+where N: Copy + Into<A>,
+      A: Num + Copy,
+      I: SpIndex,
+{
+    if lhs.cols() != rhs.rows() {
+        panic!("Dimension mismatch");
+    }
+    if !lhs.is_csr() || !rhs.is_csr() {
+        panic!("Storage mismatch");
+    }
+    let mut workspace = vec![A::zero(); rhs.cols()];
+    let mut res = CsMatI::empty(CSR, rhs.cols());
+    res.reserve_nnz_exact(lhs.nnz() + rhs.nnz());
+    for lvec in lhs.outer_iterator() {
+        for wval in workspace.iter_mut() {
+            *wval = A::zero();
+        }
+        for (lcol, &lval) in lvec.iter() {
+            let lval: A = lval.into();
+            let rvec = rhs.outer_view(lcol).unwrap();
+            for (rcol, &rval) in rvec.iter() {
+                let wval = &mut workspace[rcol];
+                *wval = *wval + lval * rval.into();
+            }
+        }
+        res = res.append_outer(&workspace);
+    }
+    res
+}
+
+/// CSR-CSR multiplication over a semiring, where the sum and the product
+/// of the values are replaced by `add` and `mul`.
+///
+/// The entries absent from the operands stand for the zero of the
+/// semiring, which is the identity of `add` and annihilates `mul`. An entry
+/// of the result is stored when at least one product contributes to it,
+/// whatever its value. For instance, the min-plus product, where
+/// `add = min` and `mul = +`, combines the lengths of paths in a graph and
+/// its zero is the infinity.
+pub fn csr_mul_csr_semiring<N, I, A, M>(lhs: CsMatViewI<N, I>,
+                                        rhs: CsMatViewI<N, I>,
+                                        add: A,
+                                        mul: M) -> CsMatI<N, I>
+where N: Clone,
+      I: SpIndex,
+      A: Fn(N, N) -> N,
+      M: Fn(N, N) -> N,
+{
+    if lhs.cols() != rhs.rows() {
+        panic!("Dimension mismatch");
+    }
+    if !lhs.is_csr() || !rhs.is_csr() {
+        panic!("Storage mismatch");
+    }
+    let res_cols = rhs.cols();
+    let mut workspace: Vec<Option<N>> = vec![None; res_cols];
+    let mut touched = Vec::new();
+    let mut indptr = vec![I::zero()];
+    let mut indices = Vec::new();
+    let mut data = Vec::new();
+    for lvec in lhs.outer_iterator() {
+        for (lcol, lval) in lvec.iter() {
+            let rvec = rhs.outer_view(lcol).unwrap();
+            for (rcol, rval) in rvec.iter() {
+                let prod = mul(lval.clone(), rval.clone());
+                workspace[rcol] = match workspace[rcol].take() {
+                    Some(acc) => Some(add(acc, prod)),
+                    None => {
+                        touched.push(rcol);
+                        Some(prod)
+                    }
+                };
+            }
+        }
+        touched.sort();
+        for &col in &touched {
+            indices.push(I::from_usize(col));
+            data.push(workspace[col].take().unwrap());
+        }
+        touched.clear();
+        indptr.push(I::from_usize(indices.len()));
+    }
+    CsMatI::new((lhs.rows(), res_cols), indptr, indices, data)
+}
+
+/// CSR-CSR multiplication restricted to the sparsity pattern of a mask.
+///
+/// Only the entries of `lhs * rhs` at the positions of the non-zeros of
+/// `mask`, whose values are ignored, are computed. An entry of the mask
+/// is stored in the result when at least one product contributes to it.
+/// Skipping the unneeded entries saves the memory and most of the work of
+/// the full product when the mask is much sparser than the product, eg
+/// when counting the triangles of a graph.
+pub fn csr_mul_csr_masked<N, I, M>(lhs: CsMatViewI<N, I>,
+                                   rhs: CsMatViewI<N, I>,
+                                   mask: CsMatViewI<M, I>) -> CsMatI<N, I>
+where N: Num + Clone,
+      I: SpIndex,
+{
+    if lhs.cols() != rhs.rows() || mask.shape() != (lhs.rows(), rhs.cols()) {
+        panic!("Dimension mismatch");
+    }
+    if !lhs.is_csr() || !rhs.is_csr() || !mask.is_csr() {
+        panic!("Storage mismatch");
+    }
+    // the columns allowed by the mask are stamped with their row
+    let mut allowed = vec![usize::max_value(); rhs.cols()];
+    let mut workspace: Vec<Option<N>> = vec![None; rhs.cols()];
+    let mut indptr = vec![I::zero()];
+    let mut indices = Vec::new();
+    let mut data = Vec::new();
+    for (row, (lvec, mvec)) in lhs.outer_iterator()
+                                  .zip(mask.outer_iterator())
+                                  .enumerate() {
+        for &col in mvec.indices() {
+            allowed[col.index()] = row;
+        }
+        for (lcol, lval) in lvec.iter() {
+            let rvec = rhs.outer_view(lcol).unwrap();
+            for (rcol, rval) in rvec.iter() {
+                if allowed[rcol] == row {
+                    let acc = workspace[rcol].take().unwrap_or_else(N::zero);
+                    workspace[rcol] = Some(acc + lval.clone() * rval.clone());
+                }
+            }
+        }
+        for &col in mvec.indices() {
+            if let Some(val) = workspace[col.index()].take() {
+                indices.push(col);
+                data.push(val);
+            }
+        }
+        indptr.push(I::from_usize(indices.len()));
+    }
+    CsMatI::new((lhs.rows(), rhs.cols()), indptr, indices, data)
+}
+
 /// CSR-vector multiplication
 pub fn csr_mul_csvec<N, I>(lhs: CsMatViewI<N, I>,
                            rhs: CsVecViewI<N, I>) -> CsVecI<N, I>
-where N: Copy + Num,
+where N: Clone + Num,
       I: SpIndex,
 {
     if rhs.dim == 0 {
@@ -195,14 +651,75 @@ where N: Copy + Num,
     res
 }
 
+/// Multiplication of a matrix by a batch of sparse vectors, returning the
+/// sparse products `lhs * rhs[k]`.
+///
+/// This is specialized for very sparse right-hand sides, such as the
+/// frontiers of a graph traversal or the unit vectors selecting columns:
+/// each product combines the columns of `lhs` selected by the non-zeros of
+/// the vector, so its cost is proportional to the number of non-zeros of
+/// these columns, instead of the number of non-zeros of `lhs` for
+/// `csr_mul_csvec`. A CSR `lhs` is converted to CSC once for the batch.
+///
+/// Exact zeros, eg from cancellations, are not stored in the results.
+///
+/// # Panics
+///
+/// If the dimension of a vector is not the number of columns of `lhs`.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::{CsMat, CsVec};
+/// use sprs::prod::mul_csvecs;
+/// let mat = CsMat::new_csc((3, 3), vec![0, 1, 3, 4], vec![0, 0, 2, 1],
+///                          vec![1., 2., 3., 4.]);
+/// let e1 = CsVec::new(3, vec![1], vec![1.]);
+/// let e02 = CsVec::new(3, vec![0, 2], vec![1., 1.]);
+/// let res = mul_csvecs(mat.view(), &[e1.view(), e02.view()]);
+/// assert_eq!(res[0], CsVec::new(3, vec![0, 2], vec![2., 3.]));
+/// assert_eq!(res[1], CsVec::new(3, vec![0, 1], vec![1., 4.]));
+/// ```
+pub fn mul_csvecs<N, I>(lhs: CsMatViewI<N, I>,
+                        rhs: &[CsVecViewI<N, I>]) -> Vec<CsVecI<N, I>>
+where N: Clone + Num + Default,
+      I: SpIndex,
+{
+    if rhs.iter().any(|vec| vec.dim() != lhs.cols()) {
+        panic!("Dimension mismatch");
+    }
+    let lhs_csc;
+    let lhs = if lhs.is_csc() { lhs } else {
+        lhs_csc = lhs.to_csc();
+        lhs_csc.view()
+    };
+    let mut spa = SparseAccumulator::new(lhs.rows());
+    rhs.iter().map(|vec| {
+        for (col, rval) in vec.iter() {
+            spa.scatter_csvec(rval.clone(), lhs.outer_view(col).unwrap());
+        }
+        let mut indices = Vec::with_capacity(spa.nnz());
+        let mut data = Vec::with_capacity(spa.nnz());
+        spa.gather_sorted(&mut indices, &mut data);
+        let mut res = CsVecI::empty(lhs.rows());
+        for (row, val) in indices.into_iter().zip(data) {
+            if val != N::zero() {
+                res.append(row, val);
+            }
+        }
+        res
+    }).collect()
+}
+
 /// CSR-dense rowmaj multiplication
 ///
 /// Performs better if out is rowmaj.
+#[cfg(feature = "std")]
 pub fn csr_mulacc_dense_rowmaj<'a, N, I>(lhs: CsMatViewI<N, I>,
                                          rhs: ArrayView<N, Ix2>,
                                          mut out: ArrayViewMut<'a, N, Ix2>
                                         )
-where N: 'a + Num + Copy,
+where N: 'a + Num + Clone,
       I: 'a + SpIndex,
 {
     if lhs.cols() != rhs.shape()[0] {
@@ -234,7 +751,7 @@ where N: 'a + Num + Copy,
 
             for (line, mut oline) in lblock.outer_iterator()
                                            .zip(oblock.axis_iter_mut(axis0)) {
-                'col_block: for (col_ind, &lval) in line.iter() {
+                'col_block: for (col_ind, lval) in line.iter() {
                     if col_ind < col_start {
                         continue 'col_block;
                     }
@@ -243,9 +760,9 @@ where N: 'a + Num + Copy,
                     }
                     let k_inblock = col_ind - col_start;
                     let rline = rblock.subview(axis0, k_inblock);
-                    for (oval, &rval) in oline.iter_mut().zip(rline.iter()) {
-                        let prev = *oval;
-                        *oval = prev + lval * rval;
+                    for (oval, rval) in oline.iter_mut().zip(rline.iter()) {
+                        let prev = oval.clone();
+                        *oval = prev + lval.clone() * rval.clone();
                     }
                 }
             }
@@ -256,11 +773,12 @@ where N: 'a + Num + Copy,
 /// CSC-dense rowmaj multiplication
 ///
 /// Performs better if out is rowmaj
+#[cfg(feature = "std")]
 pub fn csc_mulacc_dense_rowmaj<'a, N, I>(lhs: CsMatViewI<N, I>,
                                          rhs: ArrayView<N, Ix2>,
                                          mut out: ArrayViewMut<'a, N, Ix2>
                                         )
-where N: 'a + Num + Copy,
+where N: 'a + Num + Clone,
       I: 'a + SpIndex,
 {
     if lhs.cols() != rhs.shape()[0] {
@@ -280,11 +798,11 @@ where N: 'a + Num + Copy,
     }
 
     for (lcol, rline) in lhs.outer_iterator().zip(rhs.outer_iter()) {
-        for (orow, &lval) in lcol.iter() {
+        for (orow, lval) in lcol.iter() {
             let mut oline = out.row_mut(orow);
-            for (oval, &rval) in oline.iter_mut().zip(rline.iter()) {
-                let prev = *oval;
-                *oval = prev + lval * rval;
+            for (oval, rval) in oline.iter_mut().zip(rline.iter()) {
+                let prev = oval.clone();
+                *oval = prev + lval.clone() * rval.clone();
             }
         }
     }
@@ -293,11 +811,12 @@ where N: 'a + Num + Copy,
 /// CSC-dense colmaj multiplication
 ///
 /// Performs better if out is colmaj
+#[cfg(feature = "std")]
 pub fn csc_mulacc_dense_colmaj<'a, N, I>(lhs: CsMatViewI<N, I>,
                                          rhs: ArrayView<N, Ix2>,
                                          mut out: ArrayViewMut<'a, N, Ix2>
                                         )
-where N: 'a + Num + Copy,
+where N: 'a + Num + Clone,
       I: 'a + SpIndex,
 {
     if lhs.cols() != rhs.shape()[0] {
@@ -319,10 +838,10 @@ where N: 'a + Num + Copy,
     let axis1 = Axis(1);
     for (mut ocol, rcol) in out.axis_iter_mut(axis1).zip(rhs.axis_iter(axis1)) {
         for (rrow, lcol) in lhs.outer_iterator().enumerate() {
-            let rval = rcol[[rrow]];
-            for (orow, &lval) in lcol.iter() {
-                let prev = ocol[[orow]];
-                ocol[[orow]] = prev + lval * rval;
+            let rval = &rcol[[rrow]];
+            for (orow, lval) in lcol.iter() {
+                let prev = ocol[[orow]].clone();
+                ocol[[orow]] = prev + lval.clone() * rval.clone();
             }
         }
     }
@@ -332,11 +851,12 @@ where N: 'a + Num + Copy,
 /// CSR-dense colmaj multiplication
 ///
 /// Performs better if out is colmaj
+#[cfg(feature = "std")]
 pub fn csr_mulacc_dense_colmaj<'a, N, I>(lhs: CsMatViewI<N, I>,
                                          rhs: ArrayView<N, Ix2>,
                                          mut out: ArrayViewMut<'a, N, Ix2>
                                         )
-where N: 'a + Num + Copy,
+where N: 'a + Num + Clone,
       I: 'a + SpIndex,
 {
     if lhs.cols() != rhs.shape()[0] {
@@ -357,10 +877,10 @@ where N: 'a + Num + Copy,
     let axis1 = Axis(1);
     for (mut ocol, rcol) in out.axis_iter_mut(axis1).zip(rhs.axis_iter(axis1)) {
         for (orow, lrow) in lhs.outer_iterator().enumerate() {
-            let mut prev = ocol[[orow]];
-            for (rrow, &lval) in lrow.iter() {
-                let rval = rcol[[rrow]];
-                prev = prev + lval * rval;
+            let mut prev = ocol[[orow]].clone();
+            for (rrow, lval) in lrow.iter() {
+                let rval = rcol[[rrow]].clone();
+                prev = prev + lval.clone() * rval;
             }
             ocol[[orow]] = prev;
         }
@@ -371,7 +891,9 @@ where N: 'a + Num + Copy,
 mod test {
     use sparse::{CsMatView, CsMat, CsVec};
     use sparse::csmat::CompressedStorage::{CSC, CSR};
-    use super::{mul_acc_mat_vec_csc, mul_acc_mat_vec_csr, csr_mul_csr};
+    use super::{mul_acc_mat_vec_csc, mul_acc_mat_vec_csr, csr_mul_csr,
+                csr_mul_csr_semiring, csr_mul_csr_masked,
+                mul_acc_mat_vec_mixed, csr_mul_csr_mixed};
     use test_data::{mat1, mat2, mat1_self_matprod, mat1_matprod_mat2,
                     mat1_csc, mat4, mat1_csc_matprod_mat4, mat_dense1,
                     mat5, mat_dense1_colmaj, mat_dense2};
@@ -429,6 +951,44 @@ mod test {
             |(x,y)| (*x-*y).abs() < epsilon));
     }
 
+    #[test]
+    fn mixed_precision_products() {
+        let mat: CsMat<f32> = CsMat::new((5, 5),
+                                         vec![0, 2, 4, 5, 6, 7],
+                                         vec![2, 3, 3, 4, 2, 1, 3],
+                                         vec![3., 4., 2., 5., 5., 8., 7.]);
+        let vector = [1., 2., 3., 4., 5.];
+        let mut res_vec = [0.; 5];
+        mul_acc_mat_vec_mixed(mat.view(), &vector, &mut res_vec);
+        assert_eq!(res_vec, [25f64, 33., 15., 16., 28.]);
+        let mut res_vec = [0.; 5];
+        mul_acc_mat_vec_mixed(mat.to_csc().view(), &vector, &mut res_vec);
+        assert_eq!(res_vec, [25f64, 33., 15., 16., 28.]);
+
+        let res: CsMat<f64> = csr_mul_csr_mixed(mat.view(), mat.view());
+        assert_eq!(res, mat1_self_matprod());
+    }
+
+    #[cfg(feature = "half")]
+    #[test]
+    fn half_precision_products() {
+        use half::f16;
+        let data = [3., 4., 2., 5., 5., 8., 7.].iter()
+                                                .map(|&x| f16::from_f32(x))
+                                                .collect();
+        let mat: CsMat<f16> = CsMat::new((5, 5),
+                                         vec![0, 2, 4, 5, 6, 7],
+                                         vec![2, 3, 3, 4, 2, 1, 3],
+                                         data);
+        let vector = [1., 2., 3., 4., 5.];
+        let mut res_vec = [0.; 5];
+        mul_acc_mat_vec_mixed(mat.view(), &vector, &mut res_vec);
+        assert_eq!(res_vec, [25f32, 33., 15., 16., 28.]);
+        let res: CsMat<f32> = csr_mul_csr_mixed(mat.view(), mat.view());
+        let widened: CsMat<f32> = mat.cast_values();
+        assert_eq!(res, &widened * &widened);
+    }
+
     #[test]
     fn mul_csr_csr_identity() {
         let eye: CsMat<i32> = CsMat::eye(10);
@@ -453,6 +1013,56 @@ mod test {
         assert_eq!(expected_output, res);
     }
 
+    #[test]
+    fn mul_csr_csr_semiring() {
+        // the usual ring gives the usual product
+        let a = mat1();
+        let b = mat2();
+        let res = csr_mul_csr_semiring(a.view(), b.view(),
+                                       |x, y| x + y, |x, y| x * y);
+        assert_eq!(res, mat1_matprod_mat2());
+
+        // min-plus product: shortest paths of two edges, which keeps the
+        // zero length paths
+        let a = CsMat::new((3, 3),
+                           vec![0, 2, 3, 4],
+                           vec![1, 2, 2, 0],
+                           vec![1., 5., 0., 2.]);
+        let res = csr_mul_csr_semiring(a.view(), a.view(),
+                                       |x: f64, y| x.min(y), |x, y| x + y);
+        let expected = CsMat::new((3, 3),
+                                  vec![0, 2, 3, 5],
+                                  vec![0, 2, 0, 1, 2],
+                                  vec![7., 1., 2., 3., 7.]);
+        assert_eq!(res, expected);
+    }
+
+    #[test]
+    fn mul_csr_csr_masked() {
+        let a = mat1();
+        let b = mat2();
+        let full = &a * &b;
+        // the full product as a mask gives the full product
+        let res = csr_mul_csr_masked(a.view(), b.view(), full.view());
+        assert_eq!(res, full);
+
+        // a diagonal mask only keeps the diagonal entries
+        let n = a.rows();
+        let eye: CsMat<u8> = CsMat::new((n, n),
+                                        (0..n + 1).collect(),
+                                        (0..n).collect(),
+                                        vec![1; n]);
+        let res = csr_mul_csr_masked(a.view(), b.view(), eye.view());
+        for (row, vec) in res.outer_iterator().enumerate() {
+            for (col, &val) in vec.iter() {
+                assert_eq!(row, col);
+                assert_eq!(Some(&val), full.get(row, col));
+            }
+        }
+        let nb_diag = (0..n).filter(|&i| full.get(i, i).is_some()).count();
+        assert_eq!(res.nnz(), nb_diag);
+    }
+
     #[test]
     fn mul_csc_csc() {
         let a = mat1_csc();
@@ -485,6 +1095,85 @@ mod test {
         assert_eq!(expected_output, res);
     }
 
+    #[test]
+    fn fused_residual_axpby() {
+        use super::{residual, spmv_axpby};
+        let x = vec![1., -2., 0.5, 1., 3.];
+        let b = vec![10., 20., 30., 40., 50.];
+        let mut ax = vec![0.; 5];
+        mul_acc_mat_vec_csr(mat1().view(), &x, &mut ax);
+        for mat in &[mat1(), mat1_csc()] {
+            let mut r = vec![::std::f64::NAN; 5];
+            residual(mat, &x, &b, &mut r);
+            for ((ri, bi), axi) in r.iter().zip(&b).zip(&ax) {
+                assert_eq!(*ri, bi - axi);
+            }
+            // y is not read when beta is zero
+            let mut y = vec![::std::f64::NAN; 5];
+            spmv_axpby(2., mat, &x, 0., &mut y);
+            assert_eq!(y, ax.iter().map(|v| 2. * v).collect::<Vec<_>>());
+            let mut y = b.clone();
+            spmv_axpby(-1., mat, &x, 0.5, &mut y);
+            for ((yi, bi), axi) in y.iter().zip(&b).zip(&ax) {
+                assert_eq!(*yi, 0.5 * bi - axi);
+            }
+        }
+    }
+
+    #[test]
+    fn mul_csr_vec_policies() {
+        use super::{mul_acc_mat_vec_csr_policy, MatVecPolicy};
+        let mat = mat1();
+        let x = vec![0.1, 0.2, 0.3, 0.4, 0.5];
+        let mut expected = vec![1.; 5];
+        mul_acc_mat_vec_csr(mat.view(), &x, &mut expected);
+        for &policy in &[MatVecPolicy::Simple,
+                         MatVecPolicy::Prefetch { distance: 2 },
+                         MatVecPolicy::Blocked { block_cols: 1 },
+                         MatVecPolicy::Blocked { block_cols: 3 },
+                         MatVecPolicy::Blocked { block_cols: 10 }] {
+            let mut res = vec![1.; 5];
+            mul_acc_mat_vec_csr_policy(mat.view(), &x, &mut res, policy);
+            assert_eq!(res, expected);
+        }
+        // views of some rows have an offset indptr
+        let view = mat.view().middle_outer_views(1, 3);
+        let mut res = vec![0.; 3];
+        mul_acc_mat_vec_csr_policy(view, &x, &mut res,
+                                   MatVecPolicy::Blocked { block_cols: 2 });
+        assert_eq!(res, vec![0.4 * 2. + 0.5 * 5., 0.3 * 5., 0.2 * 8.]);
+        assert_eq!(MatVecPolicy::for_matrix(mat.view()), MatVecPolicy::Simple);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_mul_acc_mat_vec_csr() {
+        let mat = mat1();
+        let x = vec![1., 2., 3., 4., 5.];
+        let mut expected = vec![1.; 5];
+        let mut res = vec![1.; 5];
+        super::mul_acc_mat_vec_csr(mat.view(), &x, &mut expected);
+        super::par_mul_acc_mat_vec_csr(mat.view(), &x, &mut res);
+        assert_eq!(res, expected);
+    }
+
+    #[test]
+    fn mul_batch_csvecs() {
+        let a = mat1();
+        let vecs = vec![CsVec::new(5, vec![3], vec![1.]),
+                        CsVec::new(5, vec![0, 2, 4], vec![2., -1., 3.]),
+                        CsVec::new(5, vec![], vec![])];
+        let views: Vec<_> = vecs.iter().map(|v| v.view()).collect();
+        let res = super::mul_csvecs(a.view(), &views);
+        let res_csc = super::mul_csvecs(a.to_csc().view(), &views);
+        assert_eq!(res, res_csc);
+        for (vec, prod) in vecs.iter().zip(&res) {
+            assert_eq!(prod, &(&a * vec));
+        }
+        assert_eq!(res[0], CsVec::new(5, vec![0, 1, 4], vec![4., 2., 7.]));
+        assert_eq!(res[2].nnz(), 0);
+    }
+
     #[test]
     fn mul_csr_zero_csvec() {
         let zero = CsVec::new(0, vec![], vec![]);
@@ -624,4 +1313,114 @@ mod test {
         let c = &a * &b;
         assert_eq!(c, expected_output);
     }
+
+    #[cfg(feature = "num-rational")]
+    #[test]
+    fn big_rational_products() {
+        use num_rational::BigRational;
+        use num_traits::Zero;
+        use ndarray::{Array2, Ix2};
+        use sparse::binop::add_dense_mat_same_ordering;
+        use super::{residual, spmv_axpby, mul_acc_mat_vec_csr_policy,
+                    MatVecPolicy, csr_mul_csvec, mul_csvecs,
+                    csr_mulacc_dense_rowmaj, csr_mulacc_dense_colmaj,
+                    csc_mulacc_dense_rowmaj, csc_mulacc_dense_colmaj};
+        // the test values are dyadic, hence exact in both types
+        let big = |x: &f64| BigRational::from_float(*x).unwrap();
+        let big_vec = |v: &[f64]| v.iter().map(&big).collect::<Vec<_>>();
+        let big_mat = |mat: &CsMat<f64>| {
+            CsMat::new(mat.shape(), mat.indptr().to_vec(),
+                       mat.indices().to_vec(), big_vec(mat.data()))
+        };
+        let big_dense = |mat: &Array<f64, Ix2>, colmaj: bool| {
+            let mut res: Array2<BigRational> = if colmaj {
+                Array::zeros((5, 5).f())
+            } else {
+                Array::zeros((5, 5))
+            };
+            res.assign(&mat.map(&big));
+            res
+        };
+        let a = mat1();
+        let a_big = big_mat(&a);
+        let a_csc = a_big.to_csc();
+        let x = vec![0.5, -2., 0.25, 1., 3.];
+        let b = vec![10., 20., 30., 40., 50.];
+        let (x_big, b_big) = (big_vec(&x), big_vec(&b));
+
+        // residual and axpby, for both storages
+        let mut r = vec![0.; 5];
+        residual(&a, &x, &b, &mut r);
+        let mut y = b.clone();
+        spmv_axpby(-1.5, &a, &x, 0.25, &mut y);
+        for mat in &[&a_big, &a_csc] {
+            let mut r_big = vec![BigRational::zero(); 5];
+            residual(*mat, &x_big, &b_big, &mut r_big);
+            assert_eq!(r_big, big_vec(&r));
+            let mut y_big = b_big.clone();
+            spmv_axpby(big(&-1.5), *mat, &x_big, big(&0.25), &mut y_big);
+            assert_eq!(y_big, big_vec(&y));
+        }
+
+        // matrix vector product policies
+        let mut expected = vec![1.; 5];
+        mul_acc_mat_vec_csr(a.view(), &x, &mut expected);
+        for &policy in &[MatVecPolicy::Prefetch { distance: 2 },
+                         MatVecPolicy::Blocked { block_cols: 2 }] {
+            let mut res = big_vec(&[1.; 5]);
+            mul_acc_mat_vec_csr_policy(a_big.view(), &x_big, &mut res,
+                                       policy);
+            assert_eq!(res, big_vec(&expected));
+        }
+
+        // sparse products
+        let mut workspace = big_vec(&[0.; 5]);
+        let prod = csr_mul_csr(&a_big, &a_big, &mut workspace);
+        assert_eq!(prod, big_mat(&mat1_self_matprod()));
+        let semiring = csr_mul_csr_semiring(a_big.view(), a_big.view(),
+                                            |l, r| l + r, |l, r| l * r);
+        assert_eq!(semiring, prod);
+        let masked = csr_mul_csr_masked(a_big.view(), a_big.view(),
+                                        a.view());
+        let expected = csr_mul_csr_masked(a.view(), a.view(), a.view());
+        assert_eq!(masked, big_mat(&expected));
+        let v = CsVec::new(5, vec![0, 2, 4], vec![1., -0.5, 2.]);
+        let v_big = CsVec::new(5, v.indices().to_vec(),
+                               big_vec(v.data()));
+        let expected = csr_mul_csvec(a.view(), v.view());
+        let expected = CsVec::new(5, expected.indices().to_vec(),
+                                  big_vec(expected.data()));
+        assert_eq!(csr_mul_csvec(a_big.view(), v_big.view()), expected);
+        let batch = mul_csvecs(a_big.view(), &[v_big.view()]);
+        assert_eq!(batch, vec![expected]);
+
+        // dense products and sum, for all storages and layouts
+        let dense = mat_dense1();
+        for &colmaj in &[false, true] {
+            let dense_big = big_dense(&dense, colmaj);
+            let mut res = big_dense(&Array::zeros((5, 5)), colmaj);
+            if colmaj {
+                csr_mulacc_dense_colmaj(a_big.view(), dense_big.view(),
+                                        res.view_mut());
+            } else {
+                csr_mulacc_dense_rowmaj(a_big.view(), dense_big.view(),
+                                        res.view_mut());
+            }
+            assert_eq!(res, (&a * &dense).map(&big));
+            let mut res = big_dense(&Array::zeros((5, 5)), colmaj);
+            if colmaj {
+                csc_mulacc_dense_colmaj(a_csc.view(), dense_big.view(),
+                                        res.view_mut());
+            } else {
+                csc_mulacc_dense_rowmaj(a_csc.view(), dense_big.view(),
+                                        res.view_mut());
+            }
+            assert_eq!(res, (&a * &dense).map(&big));
+            let lhs = if colmaj { &a_csc } else { &a_big };
+            let sum = add_dense_mat_same_ordering(lhs, &dense_big,
+                                                  big(&1.), big(&2.));
+            let expected = add_dense_mat_same_ordering(&a, &dense, 1., 2.);
+            assert_eq!(sum, expected.map(&big));
+        }
+    }
 }
@@ -0,0 +1,389 @@
+///! Assembly of a global matrix from distributed triplet shards
+///!
+///! In map-reduce style assembly pipelines, each worker produces a triplet
+///! matrix expressed in its own local numbering. A `Shard` attaches to such a
+///! local triplet matrix the maps sending its local row and column indices
+///! to the global numbering, and `assemble` merges a set of shards into the
+///! global compressed matrix.
+///!
+///! As for `TriMat`, entries located at the same global position are summed,
+///! be they repeated inside one shard or present in several shards.
+
+use std::ops::Deref;
+use num_traits::Num;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use indexing::SpIndex;
+use sparse::prelude::*;
+use sparse::csmat::CompressedStorage::CSR;
+use sparse::vec::NnzEither::{Left, Right, Both};
+use sparse::vec::SparseIterTools;
+#[cfg(feature = "rayon")]
+use sparse::parallel;
+use ::Shape;
+
+/// Map from the local numbering of a shard to the global numbering.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OffsetMap {
+    /// The local index `i` maps to the global index `i + offset`
+    Offset(usize),
+    /// The local index `i` maps to the global index `map[i]`
+    Explicit(Vec<usize>),
+}
+
+impl OffsetMap {
+    /// Global index corresponding to the local index `local`
+    ///
+    /// # Panics
+    ///
+    /// If `local` is out of bounds for an explicit map.
+    pub fn global(&self, local: usize) -> usize {
+        match *self {
+            OffsetMap::Offset(offset) => local + offset,
+            OffsetMap::Explicit(ref map) => map[local],
+        }
+    }
+
+    /// Check that the local dimension `local_dim` maps inside the global
+    /// dimension `global_dim`.
+    fn fits(&self, local_dim: usize, global_dim: usize) -> bool {
+        match *self {
+            OffsetMap::Offset(offset) => local_dim + offset <= global_dim,
+            OffsetMap::Explicit(ref map) => {
+                map.len() >= local_dim
+                && map[..local_dim].iter().all(|&i| i < global_dim)
+            }
+        }
+    }
+}
+
+/// A triplet matrix expressed in a local numbering, along with the maps
+/// sending its local row and column indices to the global numbering.
+#[derive(Debug, PartialEq)]
+pub struct Shard<N, I: SpIndex> {
+    mat: TriMatI<N, I>,
+    row_map: OffsetMap,
+    col_map: OffsetMap,
+}
+
+impl<N, I: SpIndex> Shard<N, I> {
+    /// Create a shard from a local triplet matrix and its row and column maps.
+    ///
+    /// # Panics
+    ///
+    /// If an explicit map is shorter than the corresponding local dimension.
+    pub fn new(mat: TriMatI<N, I>,
+               row_map: OffsetMap,
+               col_map: OffsetMap) -> Shard<N, I> {
+        if let OffsetMap::Explicit(ref map) = row_map {
+            assert!(map.len() >= mat.rows(), "row map is too short");
+        }
+        if let OffsetMap::Explicit(ref map) = col_map {
+            assert!(map.len() >= mat.cols(), "col map is too short");
+        }
+        Shard {
+            mat: mat,
+            row_map: row_map,
+            col_map: col_map,
+        }
+    }
+
+    /// The local triplet matrix
+    pub fn local(&self) -> &TriMatI<N, I> {
+        &self.mat
+    }
+
+    /// The map from local row indices to global row indices
+    pub fn row_map(&self) -> &OffsetMap {
+        &self.row_map
+    }
+
+    /// The map from local column indices to global column indices
+    pub fn col_map(&self) -> &OffsetMap {
+        &self.col_map
+    }
+
+    /// Express this shard in the global numbering, as a CSR matrix of
+    /// shape `shape`.
+    ///
+    /// # Panics
+    ///
+    /// If the shard maps outside of `shape`.
+    pub fn to_global_csr(&self, shape: Shape) -> CsMatI<N, I>
+    where N: Num + Clone
+    {
+        assert!(self.row_map.fits(self.mat.rows(), shape.0),
+                "shard rows map outside of the global shape");
+        assert!(self.col_map.fits(self.mat.cols(), shape.1),
+                "shard cols map outside of the global shape");
+        let row_inds = self.mat.row_inds().iter()
+            .map(|&i| I::from_usize(self.row_map.global(i.index())))
+            .collect();
+        let col_inds = self.mat.col_inds().iter()
+            .map(|&j| I::from_usize(self.col_map.global(j.index())))
+            .collect();
+        let global = TriMatI::from_triplets(shape,
+                                            row_inds,
+                                            col_inds,
+                                            self.mat.data().to_vec());
+        global.to_csr()
+    }
+}
+
+/// Assemble the global CSR matrix of shape `shape` from a set of shards.
+///
+/// Each shard is converted to the global numbering and compressed, then the
+/// partial matrices are summed two by two until only the global matrix
+/// remains. With the `rayon` feature, the shards are compressed and the
+/// pairs are summed in parallel, in the thread pool of the parallel
+/// kernels, see `parallel::install`. Contrary to the addition of `CsMat`,
+/// this summation preserves the non-zero structure even when values
+/// cancel out, so the result is the same as the one obtained by gathering
+/// every global triplet into a single `TriMat`.
+///
+/// # Panics
+///
+/// If a shard maps outside of `shape`.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::TriMat;
+/// use sprs::shards::{assemble, Shard, OffsetMap};
+/// // two workers each owning a 2x2 diagonal block of a 3x3 matrix,
+/// // overlapping at (1, 1)
+/// let mut a = TriMat::new((2, 2));
+/// a.add_triplet(0, 0, 1.);
+/// a.add_triplet(1, 1, 1.);
+/// let mut b = TriMat::new((2, 2));
+/// b.add_triplet(0, 0, 2.);
+/// b.add_triplet(1, 1, 2.);
+/// b.add_triplet(1, 0, 5.);
+/// let shards = vec![
+///     Shard::new(a, OffsetMap::Offset(0), OffsetMap::Offset(0)),
+///     Shard::new(b, OffsetMap::Offset(1), OffsetMap::Offset(1)),
+/// ];
+/// let mat = assemble((3, 3), shards);
+/// assert_eq!(mat.get(1, 1), Some(&3.));
+/// assert_eq!(mat.get(2, 1), Some(&5.));
+/// assert_eq!(mat.nnz(), 4);
+/// ```
+pub fn assemble<N, I>(shape: Shape, shards: Vec<Shard<N, I>>) -> CsMatI<N, I>
+where N: Num + Copy + Send,
+      I: SpIndex + Send,
+{
+    let mut partials = compress_shards(shape, shards);
+    while partials.len() > 1 {
+        let leftover = if partials.len() % 2 == 1 {
+            partials.pop()
+        } else {
+            None
+        };
+        let mut pairs = Vec::with_capacity(partials.len() / 2);
+        let mut remaining = partials.into_iter();
+        while let Some(lhs) = remaining.next() {
+            let rhs = remaining.next().expect("even number of partials");
+            pairs.push((lhs, rhs));
+        }
+        partials = sum_pairs(pairs);
+        partials.extend(leftover);
+    }
+
+    partials.pop().unwrap_or_else(|| CsMatI {
+        storage: CSR,
+        nrows: shape.0,
+        ncols: shape.1,
+        indptr: vec![I::zero(); shape.0 + 1],
+        indices: Vec::new(),
+        data: Vec::new(),
+    })
+}
+
+/// Express each shard in the global numbering
+#[cfg(feature = "rayon")]
+fn compress_shards<N, I>(shape: Shape,
+                         shards: Vec<Shard<N, I>>) -> Vec<CsMatI<N, I>>
+where N: Num + Copy + Send,
+      I: SpIndex + Send,
+{
+    parallel::install(|| {
+        shards.into_par_iter()
+              .map(|shard| shard.to_global_csr(shape))
+              .collect()
+    })
+}
+
+/// Express each shard in the global numbering
+#[cfg(not(feature = "rayon"))]
+fn compress_shards<N, I>(shape: Shape,
+                         shards: Vec<Shard<N, I>>) -> Vec<CsMatI<N, I>>
+where N: Num + Copy,
+      I: SpIndex,
+{
+    shards.iter().map(|shard| shard.to_global_csr(shape)).collect()
+}
+
+/// Sum each pair of partial matrices
+#[cfg(feature = "rayon")]
+fn sum_pairs<N, I>(pairs: Vec<(CsMatI<N, I>, CsMatI<N, I>)>)
+                   -> Vec<CsMatI<N, I>>
+where N: Num + Copy + Send,
+      I: SpIndex + Send,
+{
+    parallel::install(|| {
+        pairs.into_par_iter()
+             .map(|(lhs, rhs)| sum_keep_structure(&lhs, &rhs))
+             .collect()
+    })
+}
+
+/// Sum each pair of partial matrices
+#[cfg(not(feature = "rayon"))]
+fn sum_pairs<N, I>(pairs: Vec<(CsMatI<N, I>, CsMatI<N, I>)>)
+                   -> Vec<CsMatI<N, I>>
+where N: Num + Copy,
+      I: SpIndex,
+{
+    pairs.iter().map(|&(ref lhs, ref rhs)| sum_keep_structure(lhs, rhs))
+         .collect()
+}
+
+/// Sum two CSR matrices of the same shape, keeping every structural non-zero
+/// even when the sum of the values is zero.
+fn sum_keep_structure<N, I, IpS, IS, DS>(lhs: &CsMatBase<N, I, IpS, IS, DS>,
+                                         rhs: &CsMatBase<N, I, IpS, IS, DS>)
+                                         -> CsMatI<N, I>
+where N: Num + Copy,
+      I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    assert_eq!(lhs.shape(), rhs.shape());
+    assert!(lhs.is_csr() && rhs.is_csr());
+    let max_nnz = lhs.nnz() + rhs.nnz();
+    let mut indptr = Vec::with_capacity(lhs.rows() + 1);
+    let mut indices = Vec::with_capacity(max_nnz);
+    let mut data = Vec::with_capacity(max_nnz);
+    indptr.push(I::zero());
+    for (lrow, rrow) in lhs.outer_iterator().zip(rhs.outer_iterator()) {
+        for elem in lrow.iter().nnz_or_zip(rrow.iter()) {
+            let (ind, val) = match elem {
+                Left((ind, &val)) => (ind, val),
+                Right((ind, &val)) => (ind, val),
+                Both((ind, &lval, &rval)) => (ind, lval + rval),
+            };
+            indices.push(I::from_usize(ind));
+            data.push(val);
+        }
+        indptr.push(I::from_usize(indices.len()));
+    }
+    CsMatI {
+        storage: CSR,
+        nrows: lhs.rows(),
+        ncols: lhs.cols(),
+        indptr: indptr,
+        indices: indices,
+        data: data,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sparse::{TriMat, TriMatI, CsMatI};
+    use super::{assemble, Shard, OffsetMap};
+
+    #[test]
+    fn assemble_matches_global_triplets() {
+        // global 5x4 matrix split over three workers
+        let mut global = TriMat::new((5, 4));
+
+        let mut a = TriMat::new((2, 2));
+        a.add_triplet(0, 0, 1.);
+        a.add_triplet(1, 1, 2.);
+        a.add_triplet(1, 1, 1.);
+        global.add_triplet(0, 0, 1.);
+        global.add_triplet(1, 1, 3.);
+
+        let mut b = TriMat::new((3, 2));
+        b.add_triplet(0, 0, 4.);
+        b.add_triplet(2, 1, 5.);
+        global.add_triplet(1, 1, 4.);
+        global.add_triplet(3, 2, 5.);
+
+        // explicit maps: local rows [0, 1, 2] -> [4, 2, 0],
+        // local cols [0, 1] -> [3, 1]
+        let mut c = TriMat::new((3, 2));
+        c.add_triplet(0, 0, 6.);
+        c.add_triplet(2, 1, 7.);
+        c.add_triplet(1, 0, -2.);
+        global.add_triplet(4, 3, 6.);
+        global.add_triplet(0, 1, 7.);
+        global.add_triplet(2, 3, -2.);
+
+        let shards = vec![
+            Shard::new(a, OffsetMap::Offset(0), OffsetMap::Offset(0)),
+            Shard::new(b, OffsetMap::Offset(1), OffsetMap::Offset(1)),
+            Shard::new(c,
+                       OffsetMap::Explicit(vec![4, 2, 0]),
+                       OffsetMap::Explicit(vec![3, 1])),
+        ];
+        let res = assemble((5, 4), shards);
+        assert_eq!(res, global.to_csr());
+    }
+
+    #[test]
+    fn assemble_keeps_cancelled_entries() {
+        let mut a = TriMat::new((1, 1));
+        a.add_triplet(0, 0, 1.);
+        let mut b = TriMat::new((1, 1));
+        b.add_triplet(0, 0, -1.);
+        let shards = vec![
+            Shard::new(a, OffsetMap::Offset(1), OffsetMap::Offset(1)),
+            Shard::new(b, OffsetMap::Offset(1), OffsetMap::Offset(1)),
+        ];
+        let res = assemble((2, 2), shards);
+        let expected = CsMatI::new((2, 2), vec![0, 0, 1], vec![1], vec![0.]);
+        assert_eq!(res, expected);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn assemble_in_bounded_pool() {
+        use sparse::parallel::with_num_threads;
+        // many more shards than threads
+        let nb_shards = 64;
+        let mut global = TriMat::new((nb_shards + 1, nb_shards + 1));
+        let shards: Vec<_> = (0..nb_shards).map(|i| {
+            let mut local = TriMat::new((2, 2));
+            local.add_triplet(0, 0, 1.);
+            local.add_triplet(1, 0, i as f64);
+            global.add_triplet(i, i, 1.);
+            global.add_triplet(i + 1, i, i as f64);
+            Shard::new(local, OffsetMap::Offset(i), OffsetMap::Offset(i))
+        }).collect();
+        let shape = (nb_shards + 1, nb_shards + 1);
+        let res = with_num_threads(2, || assemble(shape, shards)).unwrap();
+        assert_eq!(res, global.to_csr());
+    }
+
+    #[test]
+    fn assemble_no_shards() {
+        let shards: Vec<Shard<f64, u32>> = Vec::new();
+        let res = assemble((3, 2), shards);
+        assert_eq!(res.shape(), (3, 2));
+        assert_eq!(res.nnz(), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn assemble_out_of_bounds_shard() {
+        let mut a: TriMatI<f64, usize> = TriMatI::new((2, 2));
+        a.add_triplet(1, 1, 1.);
+        let shards = vec![
+            Shard::new(a, OffsetMap::Offset(2), OffsetMap::Offset(0)),
+        ];
+        assemble((3, 3), shards);
+    }
+}
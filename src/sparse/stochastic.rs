@@ -0,0 +1,212 @@
+///! Stochastic matrices, as used for Markov chains and PageRank
+///!
+///! A matrix is row stochastic when its entries are non-negative and each
+///! of its rows sums to one, ie its rows are probability distributions. It
+///! is column stochastic when its transpose is row stochastic.
+///!
+///! The rows (resp. columns) of a matrix of non-negative weights can be
+///! scaled to sum to one, except for the rows summing to zero, eg the
+///! dangling nodes of a graph. These are handled following a
+///! `ZeroSumPolicy`.
+///!
+///! # Example
+///!
+///! ```rust
+///! use sprs::CsMat;
+///! use sprs::stochastic::ZeroSumPolicy;
+///! let adj = CsMat::new((3, 3), vec![0, 2, 3, 3], vec![1, 2, 2],
+///!                      vec![1., 3., 2.]);
+///! let transitions = adj.to_row_stochastic(ZeroSumPolicy::SelfLoop);
+///! assert_eq!(transitions, CsMat::new((3, 3), vec![0, 2, 3, 4],
+///!                                    vec![1, 2, 2, 2],
+///!                                    vec![0.25, 0.75, 1., 1.]));
+///! assert!(transitions.is_row_stochastic(1e-12));
+///! ```
+
+use std::ops::Deref;
+use num_traits::Float;
+
+use indexing::SpIndex;
+use sparse::prelude::*;
+
+/// How the rows (resp. columns) summing to zero are handled when making a
+/// matrix row (resp. column) stochastic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZeroSumPolicy {
+    /// Leave them as they are, the result is then only substochastic
+    Leave,
+    /// Replace them by a unit diagonal entry, ie a self loop of the chain.
+    /// This requires a square matrix.
+    SelfLoop,
+    /// Replace them by the uniform distribution, ie a jump to any state.
+    /// This stores a dense row (resp. column) for each of them.
+    Uniform,
+}
+
+impl<N, I, IpS, IS, DS> CsMatBase<N, I, IpS, IS, DS>
+where I: SpIndex,
+      IpS: Deref<Target=[I]>,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>,
+{
+    /// Scale the rows of this matrix, whose entries should be non-negative,
+    /// to sum to one. The rows summing to zero are handled according to
+    /// `policy`. The result has the storage of this matrix.
+    ///
+    /// # Panics
+    ///
+    /// If `policy` is `ZeroSumPolicy::SelfLoop` and the matrix is not
+    /// square.
+    pub fn to_row_stochastic(&self, policy: ZeroSumPolicy) -> CsMatI<N, I>
+    where N: Float
+    {
+        self.stochastic(true, policy)
+    }
+
+    /// Scale the columns of this matrix, whose entries should be
+    /// non-negative, to sum to one. The columns summing to zero are handled
+    /// according to `policy`. The result has the storage of this matrix.
+    ///
+    /// # Panics
+    ///
+    /// If `policy` is `ZeroSumPolicy::SelfLoop` and the matrix is not
+    /// square.
+    pub fn to_column_stochastic(&self, policy: ZeroSumPolicy)
+                                -> CsMatI<N, I>
+    where N: Float
+    {
+        self.stochastic(false, policy)
+    }
+
+    /// Check whether this matrix is row stochastic, ie whether its entries
+    /// are not lower than `-tol` and its rows sum to one up to `tol`.
+    pub fn is_row_stochastic(&self, tol: N) -> bool
+    where N: Float
+    {
+        self.is_stochastic(true, tol)
+    }
+
+    /// Check whether this matrix is column stochastic, ie whether its
+    /// entries are not lower than `-tol` and its columns sum to one up to
+    /// `tol`.
+    pub fn is_column_stochastic(&self, tol: N) -> bool
+    where N: Float
+    {
+        self.is_stochastic(false, tol)
+    }
+
+    /// The sums of the rows (`by_rows`) or of the columns of this matrix
+    fn axis_sums(&self, by_rows: bool) -> Vec<N>
+    where N: Float
+    {
+        let len = if by_rows { self.rows() } else { self.cols() };
+        let mut sums = vec![N::zero(); len];
+        for (outer, vec) in self.outer_iterator().enumerate() {
+            for (inner, &val) in vec.iter() {
+                let (row, col) = if self.is_csr() { (outer, inner) }
+                                 else { (inner, outer) };
+                let ind = if by_rows { row } else { col };
+                sums[ind] = sums[ind] + val;
+            }
+        }
+        sums
+    }
+
+    fn stochastic(&self, by_rows: bool, policy: ZeroSumPolicy)
+                  -> CsMatI<N, I>
+    where N: Float
+    {
+        if policy == ZeroSumPolicy::SelfLoop && self.rows() != self.cols() {
+            panic!("Dimension mismatch");
+        }
+        let sums = self.axis_sums(by_rows);
+        let mut res = self.to_owned();
+        let is_csr = res.is_csr();
+        for (outer, mut vec) in res.outer_iterator_mut().enumerate() {
+            for (inner, val) in vec.iter_mut() {
+                let (row, col) = if is_csr { (outer, inner) }
+                                 else { (inner, outer) };
+                let sum = sums[if by_rows { row } else { col }];
+                if sum != N::zero() {
+                    *val = *val / sum;
+                }
+            }
+        }
+        let other_dim = if by_rows { self.cols() } else { self.rows() };
+        let mut entries = Vec::new();
+        for (ind, _) in sums.iter().enumerate().filter(|&(_, s)| s.is_zero()) {
+            match policy {
+                ZeroSumPolicy::Leave => (),
+                ZeroSumPolicy::SelfLoop => entries.push((ind, ind, N::one())),
+                ZeroSumPolicy::Uniform => {
+                    let prob = N::one() / N::from(other_dim).unwrap();
+                    for other in 0..other_dim {
+                        if by_rows {
+                            entries.push((ind, other, prob));
+                        }
+                        else {
+                            entries.push((other, ind, prob));
+                        }
+                    }
+                },
+            }
+        }
+        res.insert_many(entries);
+        res
+    }
+
+    fn is_stochastic(&self, by_rows: bool, tol: N) -> bool
+    where N: Float
+    {
+        self.data().iter().all(|&val| val >= -tol)
+        && self.axis_sums(by_rows)
+               .iter()
+               .all(|&sum| (sum - N::one()).abs() <= tol)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sparse::CsMat;
+    use test_data::{mat1, mat1_csc};
+    use super::ZeroSumPolicy;
+
+    #[test]
+    fn row_stochastic() {
+        // mat1 has no zero row, but an empty first column
+        for mat in &[mat1(), mat1_csc()] {
+            let res = mat.to_row_stochastic(ZeroSumPolicy::Leave);
+            assert_eq!(res.storage(), mat.storage());
+            assert!(res.is_row_stochastic(1e-12));
+            assert!(!res.is_column_stochastic(1e-12));
+            assert_eq!(res.to_csr().data(),
+                       &[3. / 7., 4. / 7., 2. / 7., 5. / 7., 1., 1., 1.][..]);
+        }
+        assert!(!mat1().is_row_stochastic(1e-12));
+    }
+
+    #[test]
+    fn column_stochastic_policies() {
+        let mat = mat1();
+        let res = mat.to_column_stochastic(ZeroSumPolicy::Leave);
+        assert!(!res.is_column_stochastic(1e-12));
+        assert_eq!(res.nnz(), 7);
+
+        let res = mat.to_column_stochastic(ZeroSumPolicy::SelfLoop);
+        assert!(res.is_column_stochastic(1e-12));
+        assert_eq!(res.get(0, 0), Some(&1.));
+        assert_eq!(res.nnz(), 8);
+
+        let res = mat.to_column_stochastic(ZeroSumPolicy::Uniform);
+        assert!(res.is_column_stochastic(1e-12));
+        assert_eq!(res.nnz(), 12);
+        assert_eq!(res.get(3, 0), Some(&0.2));
+    }
+
+    #[test]
+    #[should_panic]
+    fn self_loop_non_square() {
+        let mat: CsMat<f64> = CsMat::zero((2, 3));
+        mat.to_row_stochastic(ZeroSumPolicy::SelfLoop);
+    }
+}
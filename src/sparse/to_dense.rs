@@ -13,7 +13,7 @@ pub fn assign_to_dense<N, I>(mut array: ArrayViewMut<N, Ix2>,
                              spmat: CsMatViewI<N, I>)
 where N: Clone, I: SpIndex
 {
-    if spmat.cols() != array.shape()[0] {
+    if spmat.cols() != array.shape()[1] {
         panic!("Dimension mismatch");
     }
     if spmat.rows() != array.shape()[0] {
@@ -60,4 +60,11 @@ mod test {
                               [0., 0., 0., 7., 0.]]);
         assert_eq!(expected, res);
     }
+
+    #[test]
+    #[should_panic]
+    fn to_dense_shape_fail() {
+        let mut dense = Array::zeros((5, 4));
+        super::assign_to_dense(dense.view_mut(), mat1().view());
+    }
 }
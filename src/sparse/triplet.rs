@@ -10,8 +10,10 @@
 ///! into CsMat.
 
 use std::ops::{Deref, DerefMut};
-use sparse::csmat;
+use std::vec::Vec;
+use sparse::csmat::CompressedStorage;
 use sparse::prelude::*;
+use sparse::triplet_iter::{TriMatIter, Triplets};
 use num_traits::Num;
 use indexing::SpIndex;
 
@@ -97,6 +99,114 @@ impl<N, I: SpIndex> TriMatBase<Vec<I>, Vec<N>> {
         self.col_inds.reserve_exact(cap);
         self.data.reserve_exact(cap);
     }
+
+    /// Shrink the backing storage to the number of non-zeros
+    pub fn shrink_to_fit(&mut self) {
+        self.row_inds.shrink_to_fit();
+        self.col_inds.shrink_to_fit();
+        self.data.shrink_to_fit();
+    }
+
+    /// Append a non-zero triplet to this matrix, without checking that it
+    /// is inside the matrix.
+    ///
+    /// This is meant for assembly loops whose indices are known to be
+    /// valid. Out of bounds triplets are only detected in debug builds,
+    /// they make the conversions to compressed matrices panic otherwise.
+    pub fn add_triplet_unchecked(&mut self, row: usize, col: usize, val: N) {
+        debug_assert!(row < self.rows && col < self.cols,
+                      "Out of bounds index");
+        self.row_inds.push(I::from_usize(row));
+        self.col_inds.push(I::from_usize(col));
+        self.data.push(val);
+    }
+
+    /// Append the `(row, col, value)` triplets of `triplets` to this matrix,
+    /// reserving the storage for them up front when their number is known.
+    ///
+    /// # Panics
+    ///
+    /// If a triplet is out of bounds.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::TriMat;
+    /// let mut tri = TriMat::new((3, 3));
+    /// tri.extend_from_triplets((0..3).map(|i| (i, i, 1.)));
+    /// assert_eq!(tri.to_csr(), sprs::CsMat::eye(3));
+    /// ```
+    pub fn extend_from_triplets<It>(&mut self, triplets: It)
+    where It: IntoIterator<Item=(usize, usize, N)>
+    {
+        let triplets = triplets.into_iter();
+        self.reserve(triplets.size_hint().0);
+        let (rows, cols) = (self.rows, self.cols);
+        for (row, col, val) in triplets {
+            if row >= rows || col >= cols {
+                panic!("Out of bounds index");
+            }
+            self.add_triplet_unchecked(row, col, val);
+        }
+    }
+
+    /// Remove the triplet at the given index, returning it as a
+    /// `(row, col, value)` tuple. Indices can be obtained using
+    /// find_locations.
+    ///
+    /// The last triplet is moved to the removed index, which invalidates
+    /// the indices of the last location.
+    ///
+    /// # Panics
+    ///
+    /// If the index is out of bounds.
+    pub fn remove_triplet(&mut self,
+                          TripletIndex(triplet_ind): TripletIndex)
+                          -> (usize, usize, N) {
+        let row = self.row_inds.swap_remove(triplet_ind);
+        let col = self.col_inds.swap_remove(triplet_ind);
+        let val = self.data.swap_remove(triplet_ind);
+        (row.index(), col.index(), val)
+    }
+
+    /// Keep only the triplets for which `keep(row, col, &value)` returns
+    /// true, preserving their order.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::TriMat;
+    /// let mut tri = TriMat::new((3, 3));
+    /// tri.extend_from_triplets(vec![(0, 0, 1.), (1, 2, 2.), (2, 2, 3.)]);
+    /// // drop the entries of the last column
+    /// tri.retain(|_, col, _| col != 2);
+    /// assert_eq!(tri.nnz(), 1);
+    /// ```
+    pub fn retain<F>(&mut self, mut keep: F)
+    where F: FnMut(usize, usize, &N) -> bool
+    {
+        let mut len = 0;
+        for k in 0..self.data.len() {
+            let (row, col) = (self.row_inds[k], self.col_inds[k]);
+            if keep(row.index(), col.index(), &self.data[k]) {
+                self.row_inds.swap(len, k);
+                self.col_inds.swap(len, k);
+                self.data.swap(len, k);
+                len += 1;
+            }
+        }
+        self.row_inds.truncate(len);
+        self.col_inds.truncate(len);
+        self.data.truncate(len);
+    }
+}
+
+impl<N, I: SpIndex> Extend<(usize, usize, N)> for TriMatBase<Vec<I>, Vec<N>> {
+    fn extend<It>(&mut self, triplets: It)
+    where It: IntoIterator<Item=(usize, usize, N)>
+    {
+        self.extend_from_triplets(triplets);
+    }
 }
 
 
@@ -140,6 +250,14 @@ where IStorage: Deref<Target=[I]>,
         &self.data[..]
     }
 
+    /// Iterate over the `(row, col, &value)` triplets of this matrix,
+    /// in storage order.
+    pub fn triplet_iter(&self) -> TriMatIter<Triplets<N, I>> {
+        TriMatIter::new(Triplets::new(&self.row_inds[..],
+                                      &self.col_inds[..],
+                                      &self.data[..]))
+    }
+
     /// Find all non-zero entries at the location given by `row` and `col`
     pub fn find_locations(&self, row: usize, col: usize) -> Vec<TripletIndex> {
         self.row_inds
@@ -166,100 +284,84 @@ where IStorage: Deref<Target=[I]>,
     pub fn to_csc(&self) -> CsMatI<N, I>
     where N: Clone + Num
     {
-        let mut row_counts = vec![I::zero(); self.rows() + 1];
-        for &i in self.row_inds.iter() {
-            row_counts[i.index() + 1] += I::one();
-        }
-        let mut indptr = row_counts.clone();
-        // cum sum
-        for i in 1..(self.rows() + 1) {
-            indptr[i] += indptr[i - 1];
-        }
-        let nnz_max = indptr[self.rows()].index();
-        let mut indices = vec![I::zero(); nnz_max];
-        let mut data = vec![N::zero(); nnz_max];
-
-        // reset row counts to 0
-        for count in row_counts.iter_mut() {
-            *count = I::zero();
-        }
-
-        for (val, (&i, &j)) in self.data
-                                   .iter()
-                                   .zip(self.row_inds
-                                            .iter()
-                                            .zip(self.col_inds.iter())) {
-            let i = i.index();
-            let j = j.index();
-            let start = indptr[i].index();
-            let stop = start + row_counts[i].index();
-            let col_exists = {
-                let mut col_exists = false;
-                let iter = indices[start..stop]
-                               .iter()
-                               .zip(data[start..stop].iter_mut());
-                for (&col_cell, data_cell) in iter {
-                    if col_cell.index() == j {
-                        *data_cell = data_cell.clone() + val.clone();
-                        col_exists = true;
-                        break;
-                    }
-                }
-                col_exists
-            };
-            if !col_exists {
-                indices[stop] = I::from_usize(j);
-                data[stop] = val.clone();
-                row_counts[i] += I::one();
-            }
-        }
-
-        // compress the nonzero entries
-        let mut dst_start = indptr[0].index();
-        for i in 0..self.rows() {
-            let start = indptr[i].index();
-            let col_nnz = row_counts[i].index();
-            if start != dst_start {
-                for k in 0..col_nnz {
-                    indices[dst_start + k] = indices[start + k];
-                    data[dst_start + k] = data[start + k].clone();
-                }
-            }
-            indptr[i] = I::from_usize(dst_start);
-            dst_start += col_nnz;
-        }
-        indptr[self.rows()] = I::from_usize(dst_start);
-
-        // at this point we have a CSR matrix with unsorted columns
-        // transposing it will yield the desired CSC matrix with sorted rows
-        let nnz = indptr[self.rows()].index();
-        let mut out_indptr = vec![I::zero(); self.cols() + 1];
-        let mut out_indices = vec![I::zero(); nnz];
-        let mut out_data = vec![N::zero(); nnz];
-        csmat::raw::convert_storage(csmat::CompressedStorage::CSR,
-                                    self.shape(),
-                                    &indptr,
-                                    &indices[..nnz],
-                                    &data[..nnz],
-                                    &mut out_indptr,
-                                    &mut out_indices,
-                                    &mut out_data);
-        CsMatI {
-            storage: csmat::CompressedStorage::CSC,
-            nrows: self.rows,
-            ncols: self.cols,
-            indptr: out_indptr,
-            indices: out_indices,
-            data: out_data
-        }
+        self.to_csc_with_workspace(&mut TriMatWorkspace::new())
     }
 
     /// Create a CSR matrix from this triplet matrix
     pub fn to_csr(&self) -> CsMatI<N, I>
     where N: Clone + Num
     {
-        let res = self.transpose_view().to_csc();
-        res.transpose_into()
+        self.to_csr_with_workspace(&mut TriMatWorkspace::new())
+    }
+
+    /// Create a CSC matrix from this triplet matrix, using the buffers of
+    /// `workspace` for the intermediate arrays of the conversion.
+    ///
+    /// Reusing a workspace avoids allocating these arrays when converting
+    /// many triplet matrices.
+    pub fn to_csc_with_workspace(&self,
+                                 workspace: &mut TriMatWorkspace<N, I>)
+                                 -> CsMatI<N, I>
+    where N: Clone + Num
+    {
+        compress(CompressedStorage::CSC, self.shape(), &self.col_inds[..],
+                 &self.row_inds[..], &self.data[..], workspace)
+    }
+
+    /// Create a CSR matrix from this triplet matrix, using the buffers of
+    /// `workspace` for the intermediate arrays of the conversion.
+    ///
+    /// Reusing a workspace avoids allocating these arrays when converting
+    /// many triplet matrices.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::{TriMat, TriMatWorkspace};
+    /// let mut workspace = TriMatWorkspace::new();
+    /// for step in 1..4 {
+    ///     let mut tri = TriMat::new((2, 2));
+    ///     tri.add_triplet(1, 0, step as f64);
+    ///     tri.add_triplet(1, 0, 1.);
+    ///     let mat = tri.to_csr_with_workspace(&mut workspace);
+    ///     assert_eq!(mat.get(1, 0), Some(&(step as f64 + 1.)));
+    /// }
+    /// ```
+    pub fn to_csr_with_workspace(&self,
+                                 workspace: &mut TriMatWorkspace<N, I>)
+                                 -> CsMatI<N, I>
+    where N: Clone + Num
+    {
+        compress(CompressedStorage::CSR, self.shape(), &self.row_inds[..],
+                 &self.col_inds[..], &self.data[..], workspace)
+    }
+
+    /// Parallel version of `to_csc`, available with the `rayon` feature.
+    ///
+    /// The triplets are bucketed by column, then the buckets are sorted and
+    /// their duplicates summed in parallel. The duplicates are summed in the
+    /// order of the triplets, so the result is the same as `to_csc`.
+    #[cfg(feature = "rayon")]
+    pub fn par_to_csc(&self) -> CsMatI<N, I>
+    where N: Copy + Num + Send + Sync,
+          I: Send + Sync,
+    {
+        par_compress(CompressedStorage::CSC, self.shape(), &self.col_inds[..],
+                     &self.row_inds[..], &self.data[..])
+    }
+
+    /// Parallel version of `to_csr`, available with the `rayon` feature.
+    ///
+    /// The triplets are bucketed by row, then the buckets are sorted and
+    /// their duplicates summed in parallel. The duplicates are summed in the
+    /// order of the triplets, so the result is the same as `to_csr`.
+    #[cfg(feature = "rayon")]
+    pub fn par_to_csr(&self) -> CsMatI<N, I>
+    where N: Copy + Num + Send + Sync,
+          I: Send + Sync,
+    {
+        par_compress(CompressedStorage::CSR, self.shape(), &self.row_inds[..],
+                     &self.col_inds[..], &self.data[..])
     }
 
     pub fn view(&self) -> TriMatViewI<N, I>
@@ -303,10 +405,245 @@ where IStorage: DerefMut<Target=[I]>,
     }
 }
 
+/// Reusable buffers for the conversion of triplet matrices into compressed
+/// matrices, see `TriMatBase::to_csr_with_workspace`.
+#[derive(Debug)]
+pub struct TriMatWorkspace<N, I> {
+    inner_ends: Vec<usize>,
+    outer_ends: Vec<usize>,
+    outer_inds: Vec<I>,
+    data: Vec<N>,
+}
+
+impl<N, I> TriMatWorkspace<N, I> {
+    /// Create an empty workspace, whose buffers grow as needed by the
+    /// conversions
+    pub fn new() -> TriMatWorkspace<N, I> {
+        TriMatWorkspace {
+            inner_ends: Vec::new(),
+            outer_ends: Vec::new(),
+            outer_inds: Vec::new(),
+            data: Vec::new(),
+        }
+    }
+}
+
+impl<N, I> Default for TriMatWorkspace<N, I> {
+    fn default() -> TriMatWorkspace<N, I> {
+        TriMatWorkspace::new()
+    }
+}
+
+/// Turn a cumulative count of elements, where `counts[k + 1]` counts the
+/// elements of bucket `k`, into the start of each bucket
+fn cumsum(counts: &mut [usize]) {
+    for k in 1..counts.len() {
+        counts[k] += counts[k - 1];
+    }
+}
+
+/// Convert triplets into a compressed matrix of the given storage, whose
+/// outer and inner indices are given by `outer_inds` and `inner_inds`.
+///
+/// This is a two pass counting sort: the triplets are first bucketed by
+/// inner index, then by outer index, which yields sorted inner indices in
+/// each outer dimension, where the duplicates of a location are adjacent
+/// and summed in place. This runs in `O(nnz + rows + cols)`.
+fn compress<N, I>(storage: CompressedStorage,
+                  shape: (usize, usize),
+                  outer_inds: &[I],
+                  inner_inds: &[I],
+                  vals: &[N],
+                  workspace: &mut TriMatWorkspace<N, I>) -> CsMatI<N, I>
+where N: Clone + Num,
+      I: SpIndex,
+{
+    let (outer_dims, inner_dims) = match storage {
+        CompressedStorage::CSR => shape,
+        CompressedStorage::CSC => (shape.1, shape.0),
+    };
+    let nnz = vals.len();
+    let ws = workspace;
+
+    // bucket the triplets by inner index, keeping their outer index
+    ws.inner_ends.clear();
+    ws.inner_ends.resize(inner_dims + 1, 0);
+    for &j in inner_inds {
+        ws.inner_ends[j.index() + 1] += 1;
+    }
+    cumsum(&mut ws.inner_ends);
+    ws.outer_inds.clear();
+    ws.outer_inds.resize(nnz, I::zero());
+    ws.data.clear();
+    ws.data.resize(nnz, N::zero());
+    for ((&i, &j), val) in outer_inds.iter().zip(inner_inds).zip(vals) {
+        let pos = ws.inner_ends[j.index()];
+        ws.outer_inds[pos] = i;
+        ws.data[pos] = val.clone();
+        ws.inner_ends[j.index()] += 1;
+    }
+
+    // bucket them by outer index, in inner index order
+    ws.outer_ends.clear();
+    ws.outer_ends.resize(outer_dims + 1, 0);
+    for &i in outer_inds {
+        ws.outer_ends[i.index() + 1] += 1;
+    }
+    cumsum(&mut ws.outer_ends);
+    let mut indptr: Vec<I> = ws.outer_ends.iter()
+                                          .map(|&k| I::from_usize(k))
+                                          .collect();
+    let mut indices = vec![I::zero(); nnz];
+    let mut data = vec![N::zero(); nnz];
+    let mut start = 0;
+    for j in 0..inner_dims {
+        let stop = ws.inner_ends[j];
+        for k in start..stop {
+            let i = ws.outer_inds[k].index();
+            let pos = ws.outer_ends[i];
+            let is_duplicate = pos > indptr[i].index()
+                               && indices[pos - 1].index() == j;
+            if is_duplicate {
+                data[pos - 1] = data[pos - 1].clone() + ws.data[k].clone();
+            } else {
+                indices[pos] = I::from_usize(j);
+                data[pos] = ws.data[k].clone();
+                ws.outer_ends[i] += 1;
+            }
+        }
+        start = stop;
+    }
+
+    // remove the gaps left by the duplicates
+    let mut dst = 0;
+    for i in 0..outer_dims {
+        let (src, stop) = (indptr[i].index(), ws.outer_ends[i]);
+        indptr[i] = I::from_usize(dst);
+        for k in src..stop {
+            indices.swap(dst, k);
+            data.swap(dst, k);
+            dst += 1;
+        }
+    }
+    indptr[outer_dims] = I::from_usize(dst);
+    indices.truncate(dst);
+    data.truncate(dst);
+
+    CsMatI {
+        storage: storage,
+        nrows: shape.0,
+        ncols: shape.1,
+        indptr: indptr,
+        indices: indices,
+        data: data,
+    }
+}
+
+/// Parallel version of `compress`: the triplets are bucketed by outer index,
+/// then each bucket is sorted and its duplicates summed in parallel.
+#[cfg(feature = "rayon")]
+fn par_compress<N, I>(storage: CompressedStorage,
+                      shape: (usize, usize),
+                      outer_inds: &[I],
+                      inner_inds: &[I],
+                      vals: &[N]) -> CsMatI<N, I>
+where N: Copy + Num + Send + Sync,
+      I: SpIndex + Send + Sync,
+{
+    use rayon::prelude::*;
+    use sparse::parallel::install;
+    use sparse::utils::sort_indices_data_slices;
+
+    let outer_dims = match storage {
+        CompressedStorage::CSR => shape.0,
+        CompressedStorage::CSC => shape.1,
+    };
+    let nnz = vals.len();
+    let mut indptr = vec![0; outer_dims + 1];
+    for &i in outer_inds {
+        indptr[i.index() + 1] += 1;
+    }
+    cumsum(&mut indptr);
+    let mut next = indptr.clone();
+    let mut indices = vec![I::zero(); nnz];
+    let mut data = vec![N::zero(); nnz];
+    for ((&i, &j), &val) in outer_inds.iter().zip(inner_inds).zip(vals) {
+        let pos = next[i.index()];
+        indices[pos] = j;
+        data[pos] = val;
+        next[i.index()] += 1;
+    }
+
+    let lens: Vec<usize> = {
+        let mut buckets = Vec::with_capacity(outer_dims);
+        let mut rem_indices = &mut indices[..];
+        let mut rem_data = &mut data[..];
+        for window in indptr.windows(2) {
+            let len = window[1] - window[0];
+            let (bucket_indices, tail_indices) = {rem_indices}.split_at_mut(len);
+            let (bucket_data, tail_data) = {rem_data}.split_at_mut(len);
+            buckets.push((bucket_indices, bucket_data));
+            rem_indices = tail_indices;
+            rem_data = tail_data;
+        }
+        install(|| {
+            buckets.into_par_iter().map_with(Vec::new(), |buf, bucket| {
+                let (bucket_indices, bucket_data) = bucket;
+                // the sort is stable, keeping the duplicates in order
+                sort_indices_data_slices(bucket_indices, bucket_data, buf);
+                sum_duplicates(bucket_indices, bucket_data)
+            }).collect()
+        })
+    };
+
+    let mut dst = 0;
+    for i in 0..outer_dims {
+        let src = indptr[i];
+        indptr[i] = dst;
+        for k in src..src + lens[i] {
+            indices[dst] = indices[k];
+            data[dst] = data[k];
+            dst += 1;
+        }
+    }
+    indptr[outer_dims] = dst;
+    indices.truncate(dst);
+    data.truncate(dst);
+
+    CsMatI {
+        storage: storage,
+        nrows: shape.0,
+        ncols: shape.1,
+        indptr: indptr.into_iter().map(I::from_usize).collect(),
+        indices: indices,
+        data: data,
+    }
+}
+
+/// Sum the adjacent duplicates of sorted indices in place, returning the
+/// number of distinct indices, which are moved to the front of the slices
+#[cfg(feature = "rayon")]
+fn sum_duplicates<N, I>(indices: &mut [I], data: &mut [N]) -> usize
+where N: Copy + Num,
+      I: SpIndex,
+{
+    let mut len = 0;
+    for k in 0..indices.len() {
+        if len > 0 && indices[len - 1] == indices[k] {
+            data[len - 1] = data[len - 1] + data[k];
+        } else {
+            indices[len] = indices[k];
+            data[len] = data[k];
+            len += 1;
+        }
+    }
+    len
+}
+
 #[cfg(test)]
 mod test {
 
-    use super::{TriMat, TriMatI};
+    use super::{TriMat, TriMatI, TriMatWorkspace, TripletIndex};
     use sparse::{CsMat, CsMatI};
 
     #[test]
@@ -510,4 +847,77 @@ mod test {
 
         assert_eq!(csr, expected.to_csr());
     }
+
+    #[test]
+    fn triplet_workspace() {
+        let mut workspace = TriMatWorkspace::new();
+        // duplicates, an empty row and an empty column
+        let tri = TriMat::from_triplets((4, 5),
+                                        vec![3, 0, 3, 2, 0, 3],
+                                        vec![1, 4, 1, 0, 4, 2],
+                                        vec![1., 2., 3., 4., 5., 6.]);
+        let expected = CsMat::new((4, 5),
+                                  vec![0, 1, 1, 2, 4],
+                                  vec![4, 0, 1, 2],
+                                  vec![7., 4., 4., 6.]);
+        assert_eq!(tri.to_csr_with_workspace(&mut workspace), expected);
+        assert_eq!(tri.to_csc_with_workspace(&mut workspace),
+                   expected.to_csc());
+        // the workspace adapts to a smaller matrix
+        let tri = TriMat::from_triplets((1, 2), vec![0], vec![1], vec![1.]);
+        assert_eq!(tri.to_csr_with_workspace(&mut workspace),
+                   CsMat::new((1, 2), vec![0, 1], vec![1], vec![1.]));
+        let empty: TriMat<f64> = TriMat::new((3, 0));
+        assert_eq!(empty.to_csc().indptr(), &[0]);
+        assert_eq!(empty.to_csr().indptr(), &[0, 0, 0, 0]);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn triplet_par_conversion() {
+        let mut tri = TriMat::with_capacity((50, 40), 500);
+        for k in 0..500 {
+            tri.add_triplet((k * 7) % 50, (k * 13) % 40, k as f64 * 0.1);
+        }
+        assert_eq!(tri.par_to_csr(), tri.to_csr());
+        assert_eq!(tri.par_to_csc(), tri.to_csc());
+    }
+
+    #[test]
+    fn triplet_bulk_append() {
+        let mut tri = TriMat::new((3, 4));
+        tri.add_triplet_unchecked(2, 3, 1.);
+        tri.extend_from_triplets(vec![(0, 1, 2.), (2, 3, 3.)]);
+        tri.extend((0..3).map(|i| (i, 0, -1.)));
+        assert_eq!(tri.nnz(), 6);
+        tri.reserve(100);
+        tri.shrink_to_fit();
+        let expected = CsMat::new((3, 4),
+                                  vec![0, 2, 3, 5],
+                                  vec![0, 1, 0, 0, 3],
+                                  vec![-1., 2., -1., -1., 4.]);
+        assert_eq!(tri.to_csr(), expected);
+    }
+
+    #[test]
+    #[should_panic]
+    fn triplet_extend_out_of_bounds() {
+        let mut tri = TriMat::new((3, 4));
+        tri.extend_from_triplets(vec![(0, 4, 2.)]);
+    }
+
+    #[test]
+    fn triplet_remove_retain() {
+        let mut tri = TriMat::new((3, 3));
+        tri.extend_from_triplets(vec![(0, 0, 1.), (1, 2, 2.), (0, 0, 3.),
+                                      (2, 1, 4.), (1, 1, 0.)]);
+        let locations = tri.find_locations(0, 0);
+        assert_eq!(locations, vec![TripletIndex(0), TripletIndex(2)]);
+        assert_eq!(tri.remove_triplet(locations[1]), (0, 0, 3.));
+        assert_eq!(tri.find_locations(1, 1), vec![TripletIndex(2)]);
+        tri.retain(|row, col, &val| row != 1 || (col == 2 && val != 0.));
+        assert_eq!(tri.row_inds(), &[0, 1, 2]);
+        assert_eq!(tri.col_inds(), &[0, 2, 1]);
+        assert_eq!(tri.data(), &[1., 2., 4.]);
+    }
 }
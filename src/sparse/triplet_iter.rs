@@ -0,0 +1,462 @@
+///! Iteration over triplets
+///!
+///! A `TriMatIter` wraps any iterator yielding `(row, col, value)` triplets,
+///! be it the triplets of a `TriMat` or a stream read from a file. It
+///! provides adaptors to inspect the triplets in a single streaming pass,
+///! without materializing the matrix, which enables validating very large
+///! inputs before committing memory to their construction.
+///!
+///! The triplets can also be transformed lazily, by chaining the adaptors
+///! `map_values`, `filter`, `transposed` and `with_offset`, before being
+///! collected into a triplet matrix with `into_trimat`.
+
+#[cfg(feature = "std")]
+use std::collections::HashSet;
+use std::slice;
+use indexing::SpIndex;
+use sparse::TriMatI;
+use ::Shape;
+
+/// An iterator over `(row, col, value)` triplets.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::TriMatIter;
+/// let triplets = vec![(0, 0, 1.), (2, 1, -3.), (0, 0, 2.)];
+/// let stats = TriMatIter::new(triplets.into_iter()).stats();
+/// assert_eq!(stats.nnz, 3);
+/// assert_eq!(stats.shape_bounds, (3, 2));
+/// assert_eq!(stats.duplicates, 1);
+/// assert_eq!(stats.value_range, Some((-3., 2.)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct TriMatIter<It> {
+    triplets: It,
+}
+
+impl<It> TriMatIter<It> {
+    /// Wrap an iterator yielding `(row, col, value)` triplets
+    pub fn new(triplets: It) -> TriMatIter<It> {
+        TriMatIter {
+            triplets: triplets,
+        }
+    }
+
+    /// Get back the wrapped iterator
+    pub fn into_inner(self) -> It {
+        self.triplets
+    }
+}
+
+impl<V, It> Iterator for TriMatIter<It>
+where It: Iterator<Item=(usize, usize, V)>
+{
+    type Item = (usize, usize, V);
+
+    fn next(&mut self) -> Option<(usize, usize, V)> {
+        self.triplets.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.triplets.size_hint()
+    }
+}
+
+impl<V, It> TriMatIter<It>
+where It: Iterator<Item=(usize, usize, V)>
+{
+    /// Lazily apply `f` to the values of the triplets
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::{TriMat, TriMatIter};
+    /// let triplets = vec![(0, 0, 1.), (2, 1, -3.), (1, 2, 0.)];
+    /// let mat: TriMat<f64> = TriMatIter::new(triplets.into_iter())
+    ///     .filter(|_, _, &val| val != 0.)
+    ///     .map_values(|val| 2. * val)
+    ///     .transposed()
+    ///     .with_offset(1, 0)
+    ///     .into_trimat((3, 4));
+    /// assert_eq!(mat.to_csr().get(2, 2), Some(&-6.));
+    /// ```
+    pub fn map_values<W, F>(self, f: F) -> TriMatIter<MapValues<It, F>>
+    where F: FnMut(V) -> W
+    {
+        TriMatIter::new(MapValues {
+            triplets: self.triplets,
+            f: f,
+        })
+    }
+
+    /// Lazily keep the triplets for which `keep(row, col, &value)` returns
+    /// true
+    pub fn filter<F>(self, keep: F) -> TriMatIter<FilterTriplets<It, F>>
+    where F: FnMut(usize, usize, &V) -> bool
+    {
+        TriMatIter::new(FilterTriplets {
+            triplets: self.triplets,
+            keep: keep,
+        })
+    }
+
+    /// Lazily swap the row and column of the triplets
+    pub fn transposed(self) -> TriMatIter<Transposed<It>> {
+        TriMatIter::new(Transposed {
+            triplets: self.triplets,
+        })
+    }
+
+    /// Lazily shift the triplets by `row_offset` rows and `col_offset`
+    /// columns, eg to place them in a block of a larger matrix
+    pub fn with_offset(self,
+                       row_offset: usize,
+                       col_offset: usize) -> TriMatIter<WithOffset<It>> {
+        TriMatIter::new(WithOffset {
+            triplets: self.triplets,
+            row_offset: row_offset,
+            col_offset: col_offset,
+        })
+    }
+
+    /// Collect the triplets into a triplet matrix of shape `shape`
+    ///
+    /// # Panics
+    ///
+    /// If a triplet is out of bounds.
+    pub fn into_trimat<I: SpIndex>(self, shape: Shape) -> TriMatI<V, I> {
+        let mut mat = TriMatI::new(shape);
+        mat.extend_from_triplets(self);
+        mat
+    }
+}
+
+/// Adaptor mapping the values of triplets, see `TriMatIter::map_values`
+#[derive(Debug, Clone)]
+pub struct MapValues<It, F> {
+    triplets: It,
+    f: F,
+}
+
+impl<V, W, It, F> Iterator for MapValues<It, F>
+where It: Iterator<Item=(usize, usize, V)>,
+      F: FnMut(V) -> W,
+{
+    type Item = (usize, usize, W);
+
+    fn next(&mut self) -> Option<(usize, usize, W)> {
+        match self.triplets.next() {
+            Some((row, col, val)) => Some((row, col, (self.f)(val))),
+            None => None,
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.triplets.size_hint()
+    }
+}
+
+/// Adaptor filtering triplets, see `TriMatIter::filter`
+#[derive(Debug, Clone)]
+pub struct FilterTriplets<It, F> {
+    triplets: It,
+    keep: F,
+}
+
+impl<V, It, F> Iterator for FilterTriplets<It, F>
+where It: Iterator<Item=(usize, usize, V)>,
+      F: FnMut(usize, usize, &V) -> bool,
+{
+    type Item = (usize, usize, V);
+
+    fn next(&mut self) -> Option<(usize, usize, V)> {
+        while let Some((row, col, val)) = self.triplets.next() {
+            if (self.keep)(row, col, &val) {
+                return Some((row, col, val));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.triplets.size_hint().1)
+    }
+}
+
+/// Adaptor transposing triplets, see `TriMatIter::transposed`
+#[derive(Debug, Clone)]
+pub struct Transposed<It> {
+    triplets: It,
+}
+
+impl<V, It> Iterator for Transposed<It>
+where It: Iterator<Item=(usize, usize, V)>
+{
+    type Item = (usize, usize, V);
+
+    fn next(&mut self) -> Option<(usize, usize, V)> {
+        self.triplets.next().map(|(row, col, val)| (col, row, val))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.triplets.size_hint()
+    }
+}
+
+/// Adaptor shifting the locations of triplets, see `TriMatIter::with_offset`
+#[derive(Debug, Clone)]
+pub struct WithOffset<It> {
+    triplets: It,
+    row_offset: usize,
+    col_offset: usize,
+}
+
+impl<V, It> Iterator for WithOffset<It>
+where It: Iterator<Item=(usize, usize, V)>
+{
+    type Item = (usize, usize, V);
+
+    fn next(&mut self) -> Option<(usize, usize, V)> {
+        let (row_offset, col_offset) = (self.row_offset, self.col_offset);
+        self.triplets.next().map(|(row, col, val)| {
+            (row + row_offset, col + col_offset, val)
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.triplets.size_hint()
+    }
+}
+
+/// Iterator over the triplets stored in a triplet matrix, yielding
+/// `(row, col, &value)` tuples in storage order.
+#[derive(Debug, Clone)]
+pub struct Triplets<'a, N: 'a, I: 'a> {
+    row_inds: slice::Iter<'a, I>,
+    col_inds: slice::Iter<'a, I>,
+    data: slice::Iter<'a, N>,
+}
+
+impl<'a, N: 'a, I: 'a + SpIndex> Triplets<'a, N, I> {
+    /// Iterate over triplets stored as three slices of equal length
+    ///
+    /// # Panics
+    ///
+    /// If the slices don't have the same length.
+    pub fn new(row_inds: &'a [I],
+               col_inds: &'a [I],
+               data: &'a [N]) -> Triplets<'a, N, I> {
+        assert!(row_inds.len() == data.len() && col_inds.len() == data.len(),
+                "all inputs should have the same length");
+        Triplets {
+            row_inds: row_inds.iter(),
+            col_inds: col_inds.iter(),
+            data: data.iter(),
+        }
+    }
+}
+
+impl<'a, N: 'a, I: 'a + SpIndex> Iterator for Triplets<'a, N, I> {
+    type Item = (usize, usize, &'a N);
+
+    fn next(&mut self) -> Option<(usize, usize, &'a N)> {
+        match (self.row_inds.next(), self.col_inds.next(), self.data.next()) {
+            (Some(row), Some(col), Some(val)) => {
+                Some((row.index(), col.index(), val))
+            }
+            _ => None,
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.data.size_hint()
+    }
+}
+
+/// Statistics gathered in a single pass over a triplet stream
+#[derive(Debug, Clone, PartialEq)]
+pub struct TripletStats<V> {
+    /// The number of triplets, duplicates included
+    pub nnz: usize,
+    /// The smallest shape able to hold all the triplets, ie the largest
+    /// row and column indices plus one. `(0, 0)` for an empty stream.
+    pub shape_bounds: Shape,
+    /// The number of triplets located at an already seen position. These
+    /// triplets will be summed when converting to a compressed matrix.
+    pub duplicates: usize,
+    /// The smallest and largest values, `None` for an empty stream.
+    /// Values that cannot be compared (eg `NaN`) are ignored.
+    pub value_range: Option<(V, V)>,
+}
+
+impl<V> TripletStats<V> {
+    /// Check whether all the triplets fit in a matrix of shape `shape`
+    pub fn fits(&self, shape: Shape) -> bool {
+        self.shape_bounds.0 <= shape.0 && self.shape_bounds.1 <= shape.1
+    }
+
+    /// The number of non-zeros of the compressed matrix built from
+    /// these triplets
+    pub fn unique_nnz(&self) -> usize {
+        self.nnz - self.duplicates
+    }
+
+    fn empty() -> TripletStats<V> {
+        TripletStats {
+            nnz: 0,
+            shape_bounds: (0, 0),
+            duplicates: 0,
+            value_range: None,
+        }
+    }
+
+    fn push(&mut self, row: usize, col: usize, val: V)
+    where V: PartialOrd + Clone
+    {
+        self.nnz += 1;
+        if row >= self.shape_bounds.0 {
+            self.shape_bounds.0 = row + 1;
+        }
+        if col >= self.shape_bounds.1 {
+            self.shape_bounds.1 = col + 1;
+        }
+        if val.partial_cmp(&val).is_none() {
+            return;
+        }
+        self.value_range = match self.value_range.take() {
+            None => Some((val.clone(), val)),
+            Some((min, max)) => {
+                if val < min {
+                    Some((val, max))
+                }
+                else if val > max {
+                    Some((min, val))
+                }
+                else {
+                    Some((min, max))
+                }
+            }
+        };
+    }
+}
+
+impl<V, It> TriMatIter<It>
+where It: Iterator<Item=(usize, usize, V)>,
+      V: PartialOrd + Clone,
+{
+    /// Compute the statistics of the triplet stream in a single pass.
+    ///
+    /// Detecting duplicates requires remembering the locations already seen,
+    /// hence memory proportional to the number of distinct locations (but
+    /// not to the values). Use `stats_sorted` for sorted streams to avoid
+    /// this cost.
+    #[cfg(feature = "std")]
+    pub fn stats(self) -> TripletStats<V> {
+        let mut stats = TripletStats::empty();
+        let mut seen = HashSet::new();
+        for (row, col, val) in self {
+            if !seen.insert((row, col)) {
+                stats.duplicates += 1;
+            }
+            stats.push(row, col, val);
+        }
+        stats
+    }
+
+    /// Compute the statistics of a triplet stream sorted by row then column,
+    /// in a single pass and constant memory.
+    ///
+    /// Duplicates are only detected when they are consecutive, which is
+    /// always the case for sorted streams.
+    pub fn stats_sorted(self) -> TripletStats<V> {
+        let mut stats = TripletStats::empty();
+        let mut prev = None;
+        for (row, col, val) in self {
+            if prev == Some((row, col)) {
+                stats.duplicates += 1;
+            }
+            prev = Some((row, col));
+            stats.push(row, col, val);
+        }
+        stats
+    }
+
+    /// Compute the smallest shape able to hold all triplets, in constant
+    /// memory. Returns `(0, 0)` for an empty stream.
+    pub fn shape_bounds(self) -> Shape {
+        self.fold((0, 0), |(rows, cols), (row, col, _)| {
+            (if row >= rows { row + 1 } else { rows },
+             if col >= cols { col + 1 } else { cols })
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sparse::TriMat;
+    use super::{TriMatIter, TripletStats};
+
+    #[test]
+    fn stats_of_trimat() {
+        let mut mat = TriMat::new((5, 6));
+        mat.add_triplet(3, 2, 1.5);
+        mat.add_triplet(0, 4, -2.);
+        mat.add_triplet(3, 2, 0.5);
+        mat.add_triplet(1, 1, 7.);
+        mat.add_triplet(3, 2, 1.);
+        let stats = mat.triplet_iter().stats();
+        assert_eq!(stats, TripletStats {
+            nnz: 5,
+            shape_bounds: (4, 5),
+            duplicates: 2,
+            value_range: Some((&-2., &7.)),
+        });
+        assert_eq!(stats.unique_nnz(), 3);
+        assert!(stats.fits(mat.shape()));
+        assert!(!stats.fits((3, 6)));
+    }
+
+    #[test]
+    fn stats_sorted_stream() {
+        let triplets = vec![(0, 1, 3), (0, 1, 2), (1, 0, 1), (4, 2, 9)];
+        let iter = TriMatIter::new(triplets.iter().cloned());
+        assert_eq!(iter.clone().stats(), iter.clone().stats_sorted());
+        assert_eq!(iter.shape_bounds(), (5, 3));
+    }
+
+    #[test]
+    fn stats_empty_and_nan() {
+        let empty: Vec<(usize, usize, f64)> = Vec::new();
+        let stats = TriMatIter::new(empty.into_iter()).stats();
+        assert_eq!(stats.nnz, 0);
+        assert_eq!(stats.shape_bounds, (0, 0));
+        assert_eq!(stats.value_range, None);
+
+        let triplets = vec![(0, 0, ::std::f64::NAN), (1, 1, 2.)];
+        let stats = TriMatIter::new(triplets.into_iter()).stats();
+        assert_eq!(stats.nnz, 2);
+        assert_eq!(stats.value_range, Some((2., 2.)));
+    }
+
+    #[test]
+    fn lazy_adaptors() {
+        let mut mat = TriMat::new((2, 3));
+        mat.add_triplet(0, 2, 1);
+        mat.add_triplet(1, 0, -4);
+        mat.add_triplet(1, 1, 0);
+        let moved: TriMat<i64> = mat.triplet_iter()
+                                    .map_values(|&val| val as i64 * 10)
+                                    .filter(|row, _, &val| row == 1 && val != 0)
+                                    .transposed()
+                                    .with_offset(2, 1)
+                                    .into_trimat((3, 3));
+        assert_eq!(moved.row_inds(), &[2]);
+        assert_eq!(moved.col_inds(), &[2]);
+        assert_eq!(moved.data(), &[-40]);
+
+        let iter = TriMatIter::new(vec![(0, 0, 1.); 4].into_iter());
+        assert_eq!(iter.clone().transposed().size_hint(), (4, Some(4)));
+        assert_eq!(iter.filter(|_, _, _| true).size_hint(), (0, Some(4)));
+    }
+}
@@ -0,0 +1,281 @@
+///! Compressed matrices with a storage kind known at compile time
+///!
+///! `CsMat` stores its storage kind (CSR or CSC) at runtime, which means
+///! that an algorithm expecting rows from `outer_iterator()` will silently
+///! iterate over columns when given a CSC matrix. `CsrMat` and `CscMat` wrap
+///! a `CsMat` whose storage is guaranteed by the type, and only expose the
+///! iteration matching it: rows for `CsrMat` and columns for `CscMat`.
+///!
+///! Conversions to the dynamic `CsMat` type are free, and transposition
+///! swaps the storage kind without any copy.
+
+use std::ops::Deref;
+
+use indexing::SpIndex;
+use sparse::prelude::*;
+use sparse::csmat::OuterIterator;
+use sparse::csmat::CompressedStorage::{CSR, CSC};
+use ::Shape;
+
+/// A compressed sparse matrix statically known to be stored in the CSR
+/// format.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::{CsMat, CsrMat};
+/// let mat = CsrMat::new((2, 3), vec![0, 2, 3], vec![0, 2, 1],
+///                       vec![1., 2., 3.]);
+/// let row_sums: Vec<f64> = mat.row_iter().map(|row| row.data().iter().sum())
+///                             .collect();
+/// assert_eq!(row_sums, vec![3., 3.]);
+/// // a CSC matrix is rejected without copy
+/// let csc = CsMat::new_csc((2, 2), vec![0, 1, 2], vec![0, 1], vec![1., 1.]);
+/// assert!(CsrMat::try_from_csmat(csc).is_err());
+/// // transposing gives a CSC matrix for free
+/// let transposed = mat.transpose_into();
+/// assert_eq!(transposed.col(0).unwrap().nnz(), 2);
+/// ```
+#[derive(Debug, PartialEq)]
+pub struct CsrMat<N, I: SpIndex = usize> {
+    mat: CsMatI<N, I>,
+}
+
+/// A compressed sparse matrix statically known to be stored in the CSC
+/// format.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::{CsMat, CscMat};
+/// let mat = CscMat::new((3, 2), vec![0, 2, 3], vec![0, 2, 1],
+///                       vec![1., 2., 3.]);
+/// let col_nnz: Vec<usize> = mat.col_iter().map(|col| col.nnz()).collect();
+/// assert_eq!(col_nnz, vec![2, 1]);
+/// let dynamic: CsMat<f64> = mat.into_csmat();
+/// assert!(dynamic.is_csc());
+/// ```
+#[derive(Debug, PartialEq)]
+pub struct CscMat<N, I: SpIndex = usize> {
+    mat: CsMatI<N, I>,
+}
+
+macro_rules! typed_mat_impl {
+    ($Typed: ident, $Other: ident, $storage: expr, $to_storage: ident) => (
+
+impl<N, I: SpIndex> $Typed<N, I> {
+    /// Wrap a compressed matrix, without any copy, if it has the storage
+    /// of this type. Otherwise, the matrix is returned as is.
+    pub fn try_from_csmat(mat: CsMatI<N, I>)
+                          -> Result<$Typed<N, I>, CsMatI<N, I>> {
+        if mat.storage() == $storage {
+            Ok($Typed {
+                mat: mat,
+            })
+        }
+        else {
+            Err(mat)
+        }
+    }
+
+    /// Create a matrix with the storage of this type from any compressed
+    /// matrix. The storage is converted if needed.
+    pub fn from_csmat<IpS, IS, DS>(mat: &CsMatBase<N, I, IpS, IS, DS>)
+                                   -> $Typed<N, I>
+    where N: Clone + Default,
+          IpS: Deref<Target=[I]>,
+          IS: Deref<Target=[I]>,
+          DS: Deref<Target=[N]>,
+    {
+        $Typed {
+            mat: mat.$to_storage(),
+        }
+    }
+
+    /// Access the underlying compressed matrix
+    pub fn as_csmat(&self) -> &CsMatI<N, I> {
+        &self.mat
+    }
+
+    /// Get back the underlying compressed matrix
+    pub fn into_csmat(self) -> CsMatI<N, I> {
+        self.mat
+    }
+
+    /// A view of the underlying compressed matrix
+    pub fn view(&self) -> CsMatViewI<N, I> {
+        self.mat.view()
+    }
+
+    /// The number of rows of this matrix
+    pub fn rows(&self) -> usize {
+        self.mat.rows()
+    }
+
+    /// The number of columns of this matrix
+    pub fn cols(&self) -> usize {
+        self.mat.cols()
+    }
+
+    /// The shape of this matrix
+    pub fn shape(&self) -> Shape {
+        self.mat.shape()
+    }
+
+    /// The number of non-zero elements of this matrix
+    pub fn nnz(&self) -> usize {
+        self.mat.nnz()
+    }
+
+    /// Access the element located at row i and column j, see
+    /// `CsMatBase::get`
+    pub fn get(&self, i: usize, j: usize) -> Option<&N> {
+        self.mat.get(i, j)
+    }
+
+    /// Mutable access to the element located at row i and column j, see
+    /// `CsMatBase::get_mut`
+    pub fn get_mut(&mut self, i: usize, j: usize) -> Option<&mut N> {
+        self.mat.get_mut(i, j)
+    }
+
+    /// The non zero values, in storage order
+    pub fn data(&self) -> &[N] {
+        self.mat.data()
+    }
+
+    /// Mutable access to the non zero values, in storage order
+    pub fn data_mut(&mut self) -> &mut [N] {
+        self.mat.data_mut()
+    }
+
+    /// Transpose this matrix, which changes its storage kind.
+    /// No allocation required.
+    pub fn transpose_into(self) -> $Other<N, I> {
+        $Other {
+            mat: self.mat.transpose_into(),
+        }
+    }
+}
+
+impl<N, I: SpIndex> From<$Typed<N, I>> for CsMatI<N, I> {
+    fn from(mat: $Typed<N, I>) -> CsMatI<N, I> {
+        mat.mat
+    }
+}
+
+    )
+}
+
+typed_mat_impl!(CsrMat, CscMat, CSR, to_csr);
+typed_mat_impl!(CscMat, CsrMat, CSC, to_csc);
+
+impl<N, I: SpIndex> CsrMat<N, I> {
+    /// Create a CSR matrix from moved data, see `CsMatBase::new`.
+    ///
+    /// # Panics
+    ///
+    /// As `CsMatBase::new`, for malformed data.
+    pub fn new(shape: Shape,
+               indptr: Vec<I>,
+               indices: Vec<I>,
+               data: Vec<N>) -> CsrMat<N, I>
+    where N: Copy
+    {
+        CsrMat {
+            mat: CsMatI::new(shape, indptr, indices, data),
+        }
+    }
+
+    /// The row of index `i`, or `None` if out of bounds
+    pub fn row(&self, i: usize) -> Option<CsVecViewI<N, I>> {
+        self.mat.outer_view(i)
+    }
+
+    /// Iterate over the rows of this matrix
+    pub fn row_iter(&self) -> OuterIterator<N, I> {
+        self.mat.outer_iterator()
+    }
+
+    /// Create the CSC matrix equal to this one
+    pub fn to_csc(&self) -> CscMat<N, I>
+    where N: Clone + Default
+    {
+        CscMat {
+            mat: self.mat.to_csc(),
+        }
+    }
+}
+
+impl<N, I: SpIndex> CscMat<N, I> {
+    /// Create a CSC matrix from moved data, see `CsMatBase::new_csc`.
+    ///
+    /// # Panics
+    ///
+    /// As `CsMatBase::new_csc`, for malformed data.
+    pub fn new(shape: Shape,
+               indptr: Vec<I>,
+               indices: Vec<I>,
+               data: Vec<N>) -> CscMat<N, I>
+    where N: Copy
+    {
+        CscMat {
+            mat: CsMatI::new_csc(shape, indptr, indices, data),
+        }
+    }
+
+    /// The column of index `j`, or `None` if out of bounds
+    pub fn col(&self, j: usize) -> Option<CsVecViewI<N, I>> {
+        self.mat.outer_view(j)
+    }
+
+    /// Iterate over the columns of this matrix
+    pub fn col_iter(&self) -> OuterIterator<N, I> {
+        self.mat.outer_iterator()
+    }
+
+    /// Create the CSR matrix equal to this one
+    pub fn to_csr(&self) -> CsrMat<N, I>
+    where N: Clone + Default
+    {
+        CsrMat {
+            mat: self.mat.to_csr(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sparse::CsMat;
+    use super::{CsrMat, CscMat};
+
+    fn mat() -> CsMat<f64> {
+        CsMat::new((3, 3),
+                   vec![0, 2, 3, 4],
+                   vec![0, 2, 1, 0],
+                   vec![1., 2., 3., 4.])
+    }
+
+    #[test]
+    fn storage_conversions() {
+        let csr = CsrMat::try_from_csmat(mat()).unwrap();
+        let csc = csr.to_csc();
+        assert!(csc.as_csmat().is_csc());
+        assert_eq!(csc.col(0).unwrap().nnz(), 2);
+        assert_eq!(csc.get(2, 0), Some(&4.));
+        assert_eq!(csc.to_csr(), csr);
+
+        let csc = CscMat::try_from_csmat(mat()).unwrap_err();
+        let csc = CscMat::from_csmat(&csc);
+        let rows: Vec<_> = csc.to_csr().row_iter()
+                                       .map(|row| row.nnz())
+                                       .collect();
+        assert_eq!(rows, vec![2, 1, 1]);
+
+        let transposed = csr.transpose_into();
+        assert_eq!(transposed.shape(), (3, 3));
+        assert_eq!(transposed.get(2, 0), Some(&2.));
+        let dynamic: CsMat<f64> = transposed.into();
+        assert_eq!(dynamic, mat().transpose_into());
+    }
+}
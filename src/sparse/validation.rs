@@ -0,0 +1,60 @@
+///! Validation of the structures given to the checked constructors
+///!
+///! The checked constructors of sprs, such as `CsMat::new`, `CsMat::new_view`
+///! or `CsVec::new`, verify that the structure they are given respects the
+///! invariants of the compressed formats. Some of these checks are linear in
+///! the number of non-zeros, which can be prohibitive when large matrices
+///! coming from a trusted source are wrapped repeatedly.
+///!
+///! The `_with_validation` variants of these constructors, such as
+///! `CsMat::new_with_validation` or `CsMatView::new_view_with_validation`,
+///! take a `ValidationLevel` controlling which checks are performed on the
+///! structure being constructed:
+///!
+///! - `ValidationLevel::Full` performs all the checks, as the constructors
+///!   without a level do;
+///! - `ValidationLevel::CheapOnly` only performs the checks whose cost does
+///!   not depend on the number of non-zeros, ie the checks on the lengths of
+///!   the arrays and on the `indptr` array;
+///! - `ValidationLevel::None` skips the checks.
+///!
+///! As the level is given for each construction, trusted and untrusted
+///! inputs can be handled side by side, from any thread.
+///!
+///! In debug builds, the skipped checks are still performed with
+///! `debug_assert!`, so tests keep catching invalid structures whatever the
+///! level. In release builds, it is up to the caller to only skip the checks
+///! of valid structures: the kernels of sprs may panic or return wrong
+///! results on invalid ones. The kernels handing a structure to foreign
+///! code, which does not check its accesses, such as the MKL and CUDA
+///! backends, fully validate it beforehand.
+///!
+///! # Example
+///!
+///! ```rust
+///! use sprs::{CsMat, CompressedStorage};
+///! use sprs::validation::ValidationLevel;
+///! let eye = CsMat::new_with_validation(CompressedStorage::CSR, (2, 2),
+///!                                      vec![0, 1, 2], vec![0, 1],
+///!                                      vec![1., 1.],
+///!                                      ValidationLevel::CheapOnly);
+///! assert_eq!(eye.unwrap().nnz(), 2);
+///! ```
+
+/// The amount of validation performed by the checked constructors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationLevel {
+    /// Perform all the checks
+    Full,
+    /// Only perform the checks whose cost does not depend on the number of
+    /// non-zeros
+    CheapOnly,
+    /// Skip the checks, except with `debug_assert!` in debug builds
+    None,
+}
+
+impl Default for ValidationLevel {
+    fn default() -> ValidationLevel {
+        ValidationLevel::Full
+    }
+}
@@ -17,26 +17,34 @@
 /// ```
 
 use std::iter::{Zip, Peekable, FilterMap, IntoIterator, Enumerate};
-use std::ops::{Deref, DerefMut, Mul, Add, Sub, Index, IndexMut, Neg};
+use std::ops::{Deref, DerefMut, Mul, Div, Add, Sub, Index, IndexMut, Neg};
 use std::convert::AsRef;
 use std::cmp;
 use std::slice::{self, Iter, IterMut};
+#[cfg(feature = "std")]
 use std::collections::HashSet;
+#[cfg(feature = "std")]
 use std::hash::Hash;
 use std::marker::PhantomData;
-use ndarray::{self, ArrayBase};
+use std::vec::Vec;
+#[cfg(feature = "std")]
+use ndarray::{self, ArrayBase, ArrayView, ArrayViewMut};
+#[cfg(feature = "std")]
 use ::{Ix1};
 
 use num_traits::{Num, Zero};
+#[cfg(feature = "std")]
+use num_traits::Signed;
 
 use indexing::SpIndex;
 use array_backend::Array2;
 use sparse::permutation::PermViewI;
 use sparse::{prod, binop};
 use sparse::utils;
+use sparse::validation::ValidationLevel;
 use sparse::prelude::*;
 use sparse::csmat::CompressedStorage::{CSR, CSC};
-use errors::SprsError;
+use errors::{SprsError, StructureError};
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 /// Hold the index of a non-zero element in the compressed storage
@@ -271,6 +279,7 @@ impl<'a, N: 'a> IntoSparseVecIter<&'a N> for &'a Vec<N> {
     }
 }
 
+#[cfg(feature = "std")]
 impl<'a, N: 'a, S> IntoSparseVecIter<&'a N> for &'a ArrayBase<S, Ix1>
 where S: ndarray::Data<Elem=N>
 {
@@ -360,6 +369,103 @@ where Ite1: Iterator<Item=(usize, &'a N1)>,
     }
 }
 
+/// Incremental builder for sparse vectors, accepting indices in any order.
+///
+/// Pushing an element is O(1), the sorting being deferred to `build`,
+/// where the values pushed at the same index are summed.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsVec;
+/// use sprs::vec::CsVecBuilder;
+/// let mut builder = CsVecBuilder::new(5);
+/// builder.push(3, 1.);
+/// builder.push(0, 2.);
+/// builder.push(3, 4.);
+/// assert_eq!(builder.build(), CsVec::new(5, vec![0, 3], vec![2., 5.]));
+/// ```
+#[derive(PartialEq, Debug, Clone)]
+pub struct CsVecBuilderI<N, I> {
+    dim: usize,
+    indices: Vec<I>,
+    data: Vec<N>,
+}
+
+pub type CsVecBuilder<N> = CsVecBuilderI<N, usize>;
+
+impl<N, I: SpIndex> CsVecBuilderI<N, I> {
+    /// Create a builder for a sparse vector of dimension `dim`
+    pub fn new(dim: usize) -> CsVecBuilderI<N, I> {
+        CsVecBuilderI {
+            dim: dim,
+            indices: Vec::new(),
+            data: Vec::new(),
+        }
+    }
+
+    /// Create a builder for a sparse vector of dimension `dim`, with room
+    /// for `capacity` pushed elements
+    pub fn with_capacity(dim: usize, capacity: usize) -> CsVecBuilderI<N, I> {
+        CsVecBuilderI {
+            dim: dim,
+            indices: Vec::with_capacity(capacity),
+            data: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// The dimension of the vector being built
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    /// The number of elements pushed so far, duplicates included
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// Whether no element has been pushed yet
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Push an element at the index `ind`, in any order.
+    ///
+    /// # Panics
+    ///
+    /// If `ind` is out of bounds.
+    pub fn push(&mut self, ind: usize, val: N) {
+        assert!(ind < self.dim, "out of bounds index");
+        self.indices.push(I::from_usize(ind));
+        self.data.push(val);
+    }
+
+    /// Sort the pushed elements and sum the duplicates, giving the sparse
+    /// vector.
+    pub fn build(self) -> CsVecI<N, I>
+    where N: Num + Copy
+    {
+        let CsVecBuilderI { dim, mut indices, mut data } = self;
+        let mut buf = Vec::with_capacity(indices.len());
+        utils::sort_indices_data_slices(&mut indices[..],
+                                        &mut data[..],
+                                        &mut buf);
+        let mut res = CsVecI::empty(dim);
+        res.reserve_exact(indices.len());
+        for (ind, val) in indices.into_iter().zip(data.into_iter()) {
+            if res.indices.last() == Some(&ind) {
+                let last = res.data.last_mut().unwrap();
+                *last = *last + val;
+            }
+            else {
+                res.indices.push(ind);
+                res.data.push(val);
+            }
+        }
+        res
+    }
+}
+
 /// # Methods operating on owning sparse vectors
 impl<N, I: SpIndex> CsVecBase<Vec<I>, Vec<N>> {
     /// Create an owning CsVec from vector data.
@@ -372,7 +478,7 @@ impl<N, I: SpIndex> CsVecBase<Vec<I>, Vec<N>> {
                mut indices: Vec<I>,
                mut data: Vec<N>
               ) -> CsVecI<N, I>
-    where N: Copy
+    where N: Clone
     {
         let mut buf = Vec::with_capacity(indices.len());
         utils::sort_indices_data_slices(&mut indices[..],
@@ -386,6 +492,70 @@ impl<N, I: SpIndex> CsVecBase<Vec<I>, Vec<N>> {
         v.check_structure().and(Ok(v)).unwrap()
     }
 
+    /// Create a sparse vector from a dense vector, ignoring elements
+    /// lower than `epsilon` in absolute value.
+    ///
+    /// If epsilon is negative, it will be clamped to zero.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate ndarray;
+    /// extern crate sprs;
+    /// use sprs::CsVec;
+    /// use ndarray::arr1;
+    /// # fn main() {
+    /// let dense = arr1(&[0., 1e-12, -2., 0., 3.]);
+    /// let v = CsVec::from_dense(dense.view(), 1e-10);
+    /// assert_eq!(v, CsVec::new(5, vec![2, 4], vec![-2., 3.]));
+    /// # }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn from_dense(v: ArrayView<N, Ix1>, epsilon: N) -> CsVecI<N, I>
+    where N: Num + Copy + cmp::PartialOrd + Signed
+    {
+        let epsilon = if epsilon > N::zero() { epsilon } else { N::zero() };
+        let mut res = CsVecI::empty(v.shape()[0]);
+        for (ind, &x) in v.iter().enumerate() {
+            if x.abs() > epsilon {
+                res.indices.push(I::from_usize(ind));
+                res.data.push(x);
+            }
+        }
+        res
+    }
+
+    /// Create a sparse vector holding the values of a dense vector at the
+    /// given `indices`, which need not be sorted.
+    ///
+    /// # Panics
+    ///
+    /// - if an index is out of bounds for `dense`
+    /// - if an index is repeated
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate ndarray;
+    /// extern crate sprs;
+    /// use sprs::CsVec;
+    /// use ndarray::arr1;
+    /// # fn main() {
+    /// let dense = arr1(&[1., 2., 3., 4., 5.]);
+    /// let v = CsVec::gather_from(dense.view(), &[3, 0]);
+    /// assert_eq!(v, CsVec::new(5, vec![0, 3], vec![1., 4.]));
+    /// # }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn gather_from(dense: ArrayView<N, Ix1>,
+                       indices: &[usize]) -> CsVecI<N, I>
+    where N: Copy
+    {
+        let data = indices.iter().map(|&ind| dense[[ind]]).collect();
+        let indices = indices.iter().map(|&ind| I::from_usize(ind)).collect();
+        CsVecI::new(dense.shape()[0], indices, data)
+    }
+
     /// Create an empty CsVec, which can be used for incremental construction
     pub fn empty(dim: usize) -> CsVecI<N, I> {
         CsVecI {
@@ -508,7 +678,7 @@ where I: SpIndex,
     /// - indices are lower than dims()
     pub fn check_structure(&self) -> Result<(), SprsError> {
         if ! self.indices.windows(2).all(|x| x[0] < x[1]) {
-            return Err(SprsError::NonSortedIndices);
+            return Err(StructureError::NonSortedIndices.into());
         }
 
         if self.dim == 0 && self.indices.len() == 0 && self.data.len() == 0 {
@@ -523,6 +693,24 @@ where I: SpIndex,
         Ok(())
     }
 
+    /// Check the sparse structure as specified by `level`, see the
+    /// `validation` module. As a vector has no `indptr`,
+    /// `ValidationLevel::CheapOnly` only checks the lengths of its arrays.
+    pub fn validate(&self, level: ValidationLevel) -> Result<(), SprsError> {
+        match level {
+            ValidationLevel::Full => self.check_structure(),
+            level => {
+                if level == ValidationLevel::CheapOnly
+                   && self.indices.len() != self.data.len() {
+                    panic!("Indices and data lengths do not match");
+                }
+                debug_assert!(self.check_structure().is_ok(),
+                              "Invalid sparse vector structure");
+                Ok(())
+            },
+        }
+    }
+
     /// Allocate a new vector equal to this one.
     pub fn to_owned(&self) -> CsVecI<N, I>
     where N: Clone
@@ -628,16 +816,205 @@ where I: SpIndex,
     /// assert_eq!(16., v2.dot(&v2));
     /// ```
     pub fn dot<'b, T: IntoSparseVecIter<&'b N>>(&'b self, rhs: T) -> N
-    where N: 'b + Num + Copy,
+    where N: 'b + Num + Clone,
           I: 'b,
           <T as IntoSparseVecIter<&'b N>>::IterType: Iterator<Item=(usize, &'b N)>
     {
         assert_eq!(self.dim(), rhs.dim());
         self.iter().nnz_zip(rhs.into_sparse_vec_iter())
-                   .map(|(_, &lval, &rval)| lval * rval)
+                   .map(|(_, lval, rval)| lval.clone() * rval.clone())
                    .fold(N::zero(), |x, y| x + y)
     }
 
+    /// Dot product with a dense vector.
+    ///
+    /// This is faster than `dot` for dense right-hand-sides as it only
+    /// iterates over the non-zeros of `self`, using an unrolled loop
+    /// when `rhs` is contiguous. Slices can be used through
+    /// `ndarray::aview1`.
+    ///
+    /// # Panics
+    ///
+    /// If the dimension of the vectors do not match.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate ndarray;
+    /// extern crate sprs;
+    /// use sprs::CsVec;
+    /// use ndarray::aview1;
+    /// # fn main() {
+    /// let v = CsVec::new(5, vec![0, 2, 4], vec![1., 2., 3.]);
+    /// let dense = [1., 2., 3., 4., 5.];
+    /// assert_eq!(v.dot_dense(aview1(&dense)), 22.);
+    /// assert_eq!(v.dot_dense(aview1(&dense)), v.dot(&dense[..]));
+    /// # }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn dot_dense(&self, rhs: ArrayView<N, Ix1>) -> N
+    where N: Num + Copy
+    {
+        assert_eq!(self.dim(), rhs.shape()[0]);
+        match rhs.as_slice() {
+            Some(rhs) => dot_dense_slice(&self.indices, &self.data, rhs),
+            None => {
+                self.iter().fold(N::zero(),
+                                 |acc, (ind, &val)| acc + val * rhs[[ind]])
+            }
+        }
+    }
+
+    /// Squared euclidean distance to a dense vector, `||self - rhs||^2`.
+    ///
+    /// # Panics
+    ///
+    /// If the dimension of the vectors do not match.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate ndarray;
+    /// extern crate sprs;
+    /// use sprs::CsVec;
+    /// use ndarray::arr1;
+    /// # fn main() {
+    /// let v = CsVec::new(4, vec![0, 2], vec![1., 2.]);
+    /// let dense = arr1(&[2., 1., 2., 0.]);
+    /// assert_eq!(v.squared_l2_distance_dense(dense.view()), 2.);
+    /// # }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn squared_l2_distance_dense(&self, rhs: ArrayView<N, Ix1>) -> N
+    where N: Num + Copy
+    {
+        assert_eq!(self.dim(), rhs.shape()[0]);
+        // ||x - y||^2 = ||y||^2 + sum over the non-zeros of x_i (x_i - 2 y_i)
+        let rhs_sq = rhs.iter().fold(N::zero(), |acc, &y| acc + y * y);
+        self.iter().fold(rhs_sq, |acc, (ind, &x)| {
+            let y = rhs[[ind]];
+            acc + x * (x - y - y)
+        })
+    }
+
+    /// The `k` largest elements of the vector, implicit zeros included,
+    /// as a sparse vector. Implicit zeros are thus preferred to negative
+    /// non-zeros, and are not stored in the result. Ties are resolved in
+    /// favor of the smallest index.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::CsVec;
+    /// let v = CsVec::new(6, vec![0, 1, 3, 5], vec![2., -1., 5., 3.]);
+    /// assert_eq!(v.top_k(2), CsVec::new(6, vec![3, 5], vec![5., 3.]));
+    /// // the implicit zeros are larger than -1
+    /// assert_eq!(v.top_k(5), CsVec::new(6, vec![0, 3, 5], vec![2., 5., 3.]));
+    /// assert_eq!(v.top_k(6), v);
+    /// ```
+    pub fn top_k(&self, k: usize) -> CsVecI<N, I>
+    where N: Num + Copy + PartialOrd
+    {
+        let mut order: Vec<usize> = (0..self.nnz()).collect();
+        order.sort_by(|&a, &b| {
+            self.data[b].partial_cmp(&self.data[a])
+                        .unwrap_or(cmp::Ordering::Equal)
+        });
+        let positives = order.iter()
+                             .take_while(|&&nz| self.data[nz] > N::zero())
+                             .count();
+        let explicit_zeros = order[positives..]
+            .iter()
+            .take_while(|&&nz| self.data[nz] == N::zero())
+            .count();
+        let zeros = self.dim - self.nnz() + explicit_zeros;
+        let negatives = &order[positives + explicit_zeros..];
+        let mut kept: Vec<usize> = order[..cmp::min(k, positives)].to_vec();
+        kept.extend(negatives.iter()
+                             .take(k.saturating_sub(positives + zeros)));
+        kept.sort();
+        CsVecI {
+            dim: self.dim,
+            indices: kept.iter().map(|&nz| self.indices[nz]).collect(),
+            data: kept.iter().map(|&nz| self.data[nz]).collect(),
+        }
+    }
+
+    /// The index of the largest element of the vector, implicit zeros
+    /// included, or `None` if the vector has dimension zero. Ties are
+    /// resolved in favor of the smallest index.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::CsVec;
+    /// let v = CsVec::new(4, vec![1, 3], vec![-1., 2.]);
+    /// assert_eq!(v.argmax(), Some(3));
+    /// let v = CsVec::new(4, vec![0, 3], vec![-1., -2.]);
+    /// assert_eq!(v.argmax(), Some(1));
+    /// ```
+    pub fn argmax(&self) -> Option<usize>
+    where N: Num + Copy + PartialOrd
+    {
+        self.arg_extremum(|x, y| x > y)
+    }
+
+    /// The index of the smallest element of the vector, implicit zeros
+    /// included, or `None` if the vector has dimension zero. Ties are
+    /// resolved in favor of the smallest index.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::CsVec;
+    /// let v = CsVec::new(4, vec![1, 3], vec![-1., 2.]);
+    /// assert_eq!(v.argmin(), Some(1));
+    /// let v = CsVec::new(4, vec![1, 2], vec![1., 2.]);
+    /// assert_eq!(v.argmin(), Some(0));
+    /// ```
+    pub fn argmin(&self) -> Option<usize>
+    where N: Num + Copy + PartialOrd
+    {
+        self.arg_extremum(|x, y| x < y)
+    }
+
+    /// The first index whose value is preferred by `better` over all
+    /// others, implicit zeros included
+    fn arg_extremum<F>(&self, better: F) -> Option<usize>
+    where N: Num + Copy,
+          F: Fn(N, N) -> bool
+    {
+        if self.dim == 0 {
+            return None;
+        }
+        // the first implicit zero is the first index not stored
+        let first_zero = self.indices.iter()
+                                     .enumerate()
+                                     .find(|&(nz, ind)| ind.index() != nz)
+                                     .map(|(nz, _)| nz)
+                                     .or(if self.nnz() < self.dim {
+                                         Some(self.nnz())
+                                     } else {
+                                         None
+                                     });
+        let mut best = first_zero.map(|ind| (ind, N::zero()));
+        for (ind, &val) in self.iter() {
+            best = match best {
+                Some((best_ind, best_val)) => {
+                    if better(val, best_val)
+                       || (ind < best_ind && !better(best_val, val)) {
+                        Some((ind, val))
+                    }
+                    else {
+                        Some((best_ind, best_val))
+                    }
+                }
+                None => Some((ind, val)),
+            };
+        }
+        best.map(|(ind, _)| ind)
+    }
+
     /// Fill a dense vector with our values
     pub fn scatter(&self, out: &mut [N])
     where N: Clone {
@@ -646,7 +1023,157 @@ where I: SpIndex,
         }
     }
 
+    /// The union of the sparsity patterns of two vectors, as a vector
+    /// holding `true` at each location where either vector has a non-zero.
+    ///
+    /// # Panics
+    ///
+    /// If the dimension of the vectors do not match.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::CsVec;
+    /// let v1 = CsVec::new(5, vec![0, 2], vec![1., 2.]);
+    /// let v2 = CsVec::new(5, vec![2, 3], vec![4, 5]);
+    /// let union = v1.union_pattern(&v2);
+    /// assert_eq!(union.indices(), &[0, 2, 3]);
+    /// assert_eq!(v1.intersect_pattern(&v2).indices(), &[2]);
+    /// assert_eq!(v1.difference_pattern(&v2).indices(), &[0]);
+    /// ```
+    pub fn union_pattern<N2, IS2, DS2>(&self, rhs: &CsVecBase<IS2, DS2>)
+                                       -> CsVecI<bool, I>
+    where IS2: Deref<Target=[I]>,
+          DS2: Deref<Target=[N2]>
+    {
+        self.pattern_op(rhs, |in_lhs, in_rhs| in_lhs || in_rhs)
+    }
+
+    /// The intersection of the sparsity patterns of two vectors, as a
+    /// vector holding `true` at each location where both vectors have a
+    /// non-zero.
+    ///
+    /// # Panics
+    ///
+    /// If the dimension of the vectors do not match.
+    pub fn intersect_pattern<N2, IS2, DS2>(&self, rhs: &CsVecBase<IS2, DS2>)
+                                           -> CsVecI<bool, I>
+    where IS2: Deref<Target=[I]>,
+          DS2: Deref<Target=[N2]>
+    {
+        self.pattern_op(rhs, |in_lhs, in_rhs| in_lhs && in_rhs)
+    }
+
+    /// The difference of the sparsity patterns of two vectors, as a vector
+    /// holding `true` at each location where this vector has a non-zero
+    /// but `rhs` has not.
+    ///
+    /// # Panics
+    ///
+    /// If the dimension of the vectors do not match.
+    pub fn difference_pattern<N2, IS2, DS2>(&self,
+                                            rhs: &CsVecBase<IS2, DS2>)
+                                            -> CsVecI<bool, I>
+    where IS2: Deref<Target=[I]>,
+          DS2: Deref<Target=[N2]>
+    {
+        self.pattern_op(rhs, |in_lhs, in_rhs| in_lhs && !in_rhs)
+    }
+
+    fn pattern_op<N2, IS2, DS2, F>(&self, rhs: &CsVecBase<IS2, DS2>, keep: F)
+                                   -> CsVecI<bool, I>
+    where IS2: Deref<Target=[I]>,
+          DS2: Deref<Target=[N2]>,
+          F: Fn(bool, bool) -> bool
+    {
+        assert_eq!(self.dim, rhs.dim, "Dimension mismatch");
+        let mut indices = Vec::new();
+        utils::merge_patterns(&self.indices, &rhs.indices, &mut indices, keep);
+        CsVecI {
+            dim: self.dim,
+            data: vec![true; indices.len()],
+            indices: indices,
+        }
+    }
+
+    /// Accumulate `alpha` times this vector into a dense vector, ie
+    /// `y += alpha * self`.
+    ///
+    /// # Panics
+    ///
+    /// If the dimension of the vectors do not match.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate ndarray;
+    /// extern crate sprs;
+    /// use sprs::CsVec;
+    /// use ndarray::arr1;
+    /// # fn main() {
+    /// let v = CsVec::new(4, vec![0, 3], vec![1., 2.]);
+    /// let mut y = arr1(&[1., 1., 1., 1.]);
+    /// v.axpy(2., &mut y);
+    /// assert_eq!(y, arr1(&[3., 1., 1., 5.]));
+    /// # }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn axpy<DS2>(&self, alpha: N, y: &mut ArrayBase<DS2, Ix1>)
+    where N: Num + Copy,
+          DS2: ndarray::DataMut<Elem=N>
+    {
+        assert_eq!(self.dim(), y.shape()[0]);
+        for (ind, &val) in self.iter() {
+            y[[ind]] = y[[ind]] + alpha * val;
+        }
+    }
+
+    /// Element-wise product of two sparse vectors, whose non-zeros are
+    /// located at the intersection of both sparsity patterns.
+    ///
+    /// # Panics
+    ///
+    /// If the dimension of the vectors do not match.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::CsVec;
+    /// let v1 = CsVec::new(5, vec![0, 2, 4], vec![1., 2., 3.]);
+    /// let v2 = CsVec::new(5, vec![2, 3, 4], vec![4., 5., 6.]);
+    /// let prod = v1.mul_elementwise(&v2);
+    /// assert_eq!(prod, CsVec::new(5, vec![2, 4], vec![8., 18.]));
+    /// ```
+    pub fn mul_elementwise<IS2, DS2>(&self, rhs: &CsVecBase<IS2, DS2>)
+                                     -> CsVecI<N, I>
+    where N: Num + Copy,
+          IS2: Deref<Target=[I]>,
+          DS2: Deref<Target=[N]>
+    {
+        binop::csvec_binop_intersection(self.view(),
+                                        rhs.view(),
+                                        |&x, &y| x * y
+                                       ).unwrap()
+    }
+
+    /// Write the non-zero values into a dense vector, leaving its other
+    /// elements untouched.
+    ///
+    /// # Panics
+    ///
+    /// If the dimension of the vectors do not match.
+    #[cfg(feature = "std")]
+    pub fn scatter_into(&self, mut out: ArrayViewMut<N, Ix1>)
+    where N: Clone
+    {
+        assert_eq!(self.dim(), out.shape()[0]);
+        for (ind, val) in self.iter() {
+            out[[ind]] = val.clone();
+        }
+    }
+
     /// Transform this vector into a set of (index, value) tuples
+    #[cfg(feature = "std")]
     pub fn to_set(self) -> HashSet<(usize, N)>
     where N: Hash + Eq + Clone {
         self.indices().iter().map(|i| i.index())
@@ -666,6 +1193,34 @@ where I: SpIndex,
     }
 }
 
+/// Dot product of the sparse vector given by `indices` and `data` with a
+/// dense slice, unrolled by four to expose instruction level parallelism.
+#[cfg(feature = "std")]
+fn dot_dense_slice<N, I>(indices: &[I], data: &[N], rhs: &[N]) -> N
+where N: Num + Copy,
+      I: SpIndex
+{
+    let mut acc = [N::zero(); 4];
+    let mut ind_chunks = indices.chunks(4);
+    let mut data_chunks = data.chunks(4);
+    let mut rem = N::zero();
+    while let (Some(inds), Some(vals)) = (ind_chunks.next(),
+                                           data_chunks.next()) {
+        if inds.len() == 4 {
+            acc[0] = acc[0] + vals[0] * rhs[inds[0].index()];
+            acc[1] = acc[1] + vals[1] * rhs[inds[1].index()];
+            acc[2] = acc[2] + vals[2] * rhs[inds[2].index()];
+            acc[3] = acc[3] + vals[3] * rhs[inds[3].index()];
+        }
+        else {
+            for (ind, &val) in inds.iter().zip(vals.iter()) {
+                rem = rem + val * rhs[ind.index()];
+            }
+        }
+    }
+    (acc[0] + acc[1]) + (acc[2] + acc[3]) + rem
+}
+
 /// # Methods on sparse vectors with mutable access to their data
 impl<'a, N, I, IStorage, DStorage> CsVecBase<IStorage, DStorage>
 where N: 'a,
@@ -733,6 +1288,22 @@ impl<'a, N: 'a, I: 'a + SpIndex> CsVecBase<&'a [I], &'a [N]> {
         v.check_structure().and(Ok(v))
     }
 
+    /// Create a borrowed CsVec over slice data, checking their validity as
+    /// specified by `level`, see the `validation` module.
+    pub fn new_view_with_validation(n: usize,
+                                    indices: &'a [I],
+                                    data: &'a [N],
+                                    level: ValidationLevel)
+                                    -> Result<CsVecViewI<'a, N, I>, SprsError>
+    {
+        let v = CsVecViewI {
+            dim: n,
+            indices: indices,
+            data: data,
+        };
+        v.validate(level).and(Ok(v))
+    }
+
     /// Access element at given index, with logarithmic complexity
     ///
     /// Re-borrowing version of `at()`.
@@ -905,17 +1476,66 @@ where N: Copy + Num,
     }
 }
 
-impl<'a, 'b, N, IS1, DS1, IS2, DS2> Sub<&'b CsVecBase<IS2, DS2>>
+impl<N, I, IS1, DS1, IS2, DS2> Sub<CsVecBase<IS2, DS2>>
+for CsVecBase<IS1, DS1>
+where N: Copy + Num,
+      I: SpIndex,
+      IS1: Deref<Target=[I]>,
+      DS1: Deref<Target=[N]>,
+      IS2: Deref<Target=[I]>,
+      DS2: Deref<Target=[N]>
+{
+    type Output = CsVecI<N, I>;
+
+    fn sub(self, rhs: CsVecBase<IS2, DS2>) -> CsVecI<N, I> {
+        &self - &rhs
+    }
+}
+
+impl<'a, N, I, IS1, DS1, IS2, DS2> Sub<&'a CsVecBase<IS2, DS2>>
+for CsVecBase<IS1, DS1>
+where N: Copy + Num,
+      I: SpIndex,
+      IS1: Deref<Target=[I]>,
+      DS1: Deref<Target=[N]>,
+      IS2: Deref<Target=[I]>,
+      DS2: Deref<Target=[N]>
+{
+    type Output = CsVecI<N, I>;
+
+    fn sub(self, rhs: &CsVecBase<IS2, DS2>) -> CsVecI<N, I> {
+        &self - rhs
+    }
+}
+
+impl<'a, N, I, IS1, DS1, IS2, DS2> Sub<CsVecBase<IS2, DS2>>
 for &'a CsVecBase<IS1, DS1>
 where N: Copy + Num,
-      IS1: Deref<Target=[usize]>,
+      I: SpIndex,
+      IS1: Deref<Target=[I]>,
       DS1: Deref<Target=[N]>,
-      IS2: Deref<Target=[usize]>,
+      IS2: Deref<Target=[I]>,
+      DS2: Deref<Target=[N]>
+{
+    type Output = CsVecI<N, I>;
+
+    fn sub(self, rhs: CsVecBase<IS2, DS2>) -> CsVecI<N, I> {
+        self - &rhs
+    }
+}
+
+impl<'a, 'b, N, I, IS1, DS1, IS2, DS2> Sub<&'b CsVecBase<IS2, DS2>>
+for &'a CsVecBase<IS1, DS1>
+where N: Copy + Num,
+      I: SpIndex,
+      IS1: Deref<Target=[I]>,
+      DS1: Deref<Target=[N]>,
+      IS2: Deref<Target=[I]>,
       DS2: Deref<Target=[N]> {
 
-    type Output = CsVec<N>;
+    type Output = CsVecI<N, I>;
 
-    fn sub(self, rhs: &CsVecBase<IS2, DS2>) -> CsVec<N> {
+    fn sub(self, rhs: &CsVecBase<IS2, DS2>) -> CsVecI<N, I> {
         binop::csvec_binop(self.view(),
                            rhs.view(),
                            |&x, &y| x - y
@@ -934,6 +1554,72 @@ impl<N: Num + Copy + Neg<Output=N>, I: SpIndex> Neg for CsVecI<N, I> {
     }
 }
 
+impl<'a, N, I, IS, DS> Neg for &'a CsVecBase<IS, DS>
+where N: Num + Copy + Neg<Output=N>,
+      I: SpIndex,
+      IS: Deref<Target=[I]>,
+      DS: Deref<Target=[N]>
+{
+    type Output = CsVecI<N, I>;
+
+    fn neg(self) -> CsVecI<N, I> {
+        self.map(|&x| -x)
+    }
+}
+
+macro_rules! sparse_vec_scalar_ops {
+    ($scalar: ident) => (
+        impl<'a, I, IS, DS> Mul<$scalar> for &'a CsVecBase<IS, DS>
+        where I: 'a + SpIndex,
+              IS: 'a + Deref<Target=[I]>,
+              DS: 'a + Deref<Target=[$scalar]> {
+            type Output = CsVecI<$scalar, I>;
+
+            fn mul(self, rhs: $scalar) -> CsVecI<$scalar, I> {
+                self.map(|&x| x * rhs)
+            }
+        }
+
+        impl<I: SpIndex> Mul<$scalar> for CsVecI<$scalar, I> {
+            type Output = CsVecI<$scalar, I>;
+
+            fn mul(mut self, rhs: $scalar) -> CsVecI<$scalar, I> {
+                self.map_inplace(|&x| x * rhs);
+                self
+            }
+        }
+
+        impl<'a, I, IS, DS> Div<$scalar> for &'a CsVecBase<IS, DS>
+        where I: 'a + SpIndex,
+              IS: 'a + Deref<Target=[I]>,
+              DS: 'a + Deref<Target=[$scalar]> {
+            type Output = CsVecI<$scalar, I>;
+
+            fn div(self, rhs: $scalar) -> CsVecI<$scalar, I> {
+                self.map(|&x| x / rhs)
+            }
+        }
+
+        impl<I: SpIndex> Div<$scalar> for CsVecI<$scalar, I> {
+            type Output = CsVecI<$scalar, I>;
+
+            fn div(mut self, rhs: $scalar) -> CsVecI<$scalar, I> {
+                self.map_inplace(|&x| x / rhs);
+                self
+            }
+        }
+    )
+}
+
+sparse_vec_scalar_ops!(u32);
+sparse_vec_scalar_ops!(i32);
+sparse_vec_scalar_ops!(u64);
+sparse_vec_scalar_ops!(i64);
+sparse_vec_scalar_ops!(isize);
+sparse_vec_scalar_ops!(usize);
+sparse_vec_scalar_ops!(f32);
+sparse_vec_scalar_ops!(f64);
+
 impl<N, IS, DS> Index<usize> for CsVecBase<IS, DS>
 where IS: Deref<Target=[usize]>,
       DS: Deref<Target=[N]> {
@@ -1055,8 +1741,8 @@ mod alga_impls {
 #[cfg(test)]
 mod test {
     use sparse::{CsVec, CsVecI};
-    use super::SparseIterTools;
-    use ndarray::Array;
+    use super::{SparseIterTools, CsVecBuilder, CsVecBuilderI};
+    use ndarray::{Array, arr1};
     use num_traits::Zero;
 
     fn test_vec1() -> CsVec<f64> {
@@ -1266,6 +1952,200 @@ mod test {
         assert_eq!(-vector, negated);
     }
 
+    #[test]
+    fn subtracts_vectors() {
+        let (a, b, _) = addition_sample();
+        let expected = CsVec::new(8,
+                                  vec![0, 1, 3, 4, 5, 7],
+                                  vec![2., -4., -5., 3., 6., -1.]);
+        assert_eq!(expected, &a - &b);
+        assert_eq!(expected, &a - b.clone());
+        assert_eq!(expected, a.clone() - &b);
+        assert_eq!(expected, a - b);
+    }
+
+    #[test]
+    fn negates_vectors_by_reference() {
+        let vector = CsVec::new(4, vec![0, 3], vec![2., -3.]);
+        let negated = CsVec::new(4, vec![0, 3], vec![-2., 3.]);
+        assert_eq!(-&vector, negated);
+    }
+
+    #[test]
+    fn scalar_mul_and_div() {
+        let vector = CsVec::new(4, vec![0, 3], vec![2., -3.]);
+        let scaled = CsVec::new(4, vec![0, 3], vec![4., -6.]);
+        assert_eq!(&vector * 2., scaled);
+        assert_eq!(vector.clone() * 2., scaled);
+        assert_eq!(&scaled / 2., vector);
+        assert_eq!(scaled / 2., vector);
+        let vector = CsVecI::<i32, u32>::new(4, vec![1, 2], vec![4, 7]);
+        let divided = CsVecI::new(4, vec![1, 2], vec![2, 3]);
+        assert_eq!(&vector / 2, divided);
+    }
+
+    #[test]
+    fn axpy() {
+        let vector = test_vec1();
+        let mut dense = Array::from_elem(8, 1.);
+        vector.axpy(-1., &mut dense);
+        let expected = arr1(&[1., 0., 1., 1., -3., -4., 1., -6.]);
+        assert_eq!(dense, expected);
+    }
+
+    #[test]
+    #[should_panic]
+    fn axpy_panics() {
+        let vector = test_vec1();
+        let mut dense = Array::from_elem(7, 1.);
+        vector.axpy(-1., &mut dense);
+    }
+
+    #[test]
+    fn mul_elementwise() {
+        let res = test_vec1().mul_elementwise(&test_vec2());
+        let expected = CsVec::new(8, vec![0, 4, 7], vec![0., 18., 52.5]);
+        assert_eq!(res, expected);
+        let empty = CsVec::new(8, vec![], vec![]);
+        assert_eq!(test_vec1().mul_elementwise(&empty), empty);
+    }
+
+    #[test]
+    fn dot_dense() {
+        let vec1 = test_vec1();
+        let dense = Array::from_iter((0..8).map(|x| x as f64));
+        let expected = 1. + 16. + 25. + 49.;
+        assert_eq!(vec1.dot_dense(dense.view()), expected);
+        assert_eq!(vec1.dot(&dense), expected);
+        // a non contiguous view
+        let mat = Array::from_shape_fn((8, 2), |(i, _)| i as f64);
+        assert_eq!(vec1.dot_dense(mat.column(0)), expected);
+        let short = CsVec::new(8, vec![1, 6], vec![2., 3.]);
+        assert_eq!(short.dot_dense(dense.view()), 20.);
+    }
+
+    #[test]
+    #[should_panic]
+    fn dot_dense_panics() {
+        let dense = Array::from_elem(7, 1.);
+        test_vec1().dot_dense(dense.view());
+    }
+
+    #[test]
+    fn squared_l2_distance_dense() {
+        let vec1 = test_vec1();
+        let dense = Array::from_elem(8, 1.);
+        let expected = 1. + 0. + 1. + 1. + 9. + 16. + 1. + 36.;
+        assert_eq!(vec1.squared_l2_distance_dense(dense.view()), expected);
+        let zero = CsVec::new(8, vec![], vec![]);
+        assert_eq!(zero.squared_l2_distance_dense(dense.view()), 8.);
+    }
+
+    #[test]
+    fn top_k() {
+        let vec1 = test_vec1();
+        assert_eq!(vec1.top_k(0), CsVec::empty(8));
+        assert_eq!(vec1.top_k(2), CsVec::new(8, vec![5, 7], vec![5., 7.]));
+        // the stored zero ties with the implicit ones
+        assert_eq!(vec1.top_k(5).nnz(), 4);
+        assert_eq!(vec1.top_k(20), CsVec::new(8,
+                                              vec![1, 4, 5, 7],
+                                              vec![1., 4., 5., 7.]));
+        let vec = CsVec::new(4, vec![0, 1, 2], vec![-3., -1., -2.]);
+        assert_eq!(vec.top_k(1), CsVec::empty(4));
+        assert_eq!(vec.top_k(2), CsVec::new(4, vec![1], vec![-1.]));
+        assert_eq!(vec.top_k(3), CsVec::new(4, vec![1, 2], vec![-1., -2.]));
+        assert_eq!(vec.top_k(4), vec);
+    }
+
+    #[test]
+    fn argmax_argmin() {
+        let vec1 = test_vec1();
+        assert_eq!(vec1.argmax(), Some(7));
+        assert_eq!(vec1.argmin(), Some(0));
+        let vec = CsVec::new(4, vec![0, 1, 3], vec![-3., -1., -3.]);
+        assert_eq!(vec.argmax(), Some(2));
+        assert_eq!(vec.argmin(), Some(0));
+        let vec = CsVec::new(3, vec![0, 1, 2], vec![2., 1., 2.]);
+        assert_eq!(vec.argmax(), Some(0));
+        assert_eq!(vec.argmin(), Some(1));
+        let vec = CsVec::new(3, vec![1], vec![0.]);
+        assert_eq!(vec.argmax(), Some(0));
+        assert_eq!(CsVec::<f64>::empty(0).argmax(), None);
+        assert_eq!(CsVec::<f64>::empty(2).argmin(), Some(0));
+    }
+
+    #[test]
+    fn dense_round_trip() {
+        let vec1 = test_vec1();
+        let mut dense = Array::from_elem(8, -1.);
+        vec1.scatter_into(dense.view_mut());
+        let expected = arr1(&[0., 1., -1., -1., 4., 5., -1., 7.]);
+        assert_eq!(dense, expected);
+        let sparse = CsVec::from_dense(dense.view(), 0.);
+        assert_eq!(sparse.indices(), &[1, 2, 3, 4, 5, 6, 7]);
+        let sparse = CsVec::from_dense(dense.view(), 2.);
+        assert_eq!(sparse, CsVec::new(8, vec![4, 5, 7], vec![4., 5., 7.]));
+        let gathered = CsVec::gather_from(dense.view(), &[7, 0, 1, 4, 5]);
+        assert_eq!(gathered, vec1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn scatter_into_panics() {
+        let mut dense = Array::zeros(7);
+        test_vec1().scatter_into(dense.view_mut());
+    }
+
+    #[test]
+    #[should_panic]
+    fn gather_from_repeated_index_panics() {
+        let dense = Array::from_elem(8, 1.);
+        let _: CsVec<f64> = CsVec::gather_from(dense.view(), &[1, 3, 1]);
+    }
+
+    #[test]
+    fn builder() {
+        let mut builder = CsVecBuilderI::<f64, u32>::with_capacity(8, 6);
+        assert!(builder.is_empty());
+        for &(ind, val) in &[(7, 3.5), (4, 4.), (0, 0.), (5, 5.),
+                             (1, 1.), (7, 3.5)] {
+            builder.push(ind, val);
+        }
+        assert_eq!(builder.len(), 6);
+        assert_eq!(builder.dim(), 8);
+        assert_eq!(builder.build(), test_vec1().to_other_types());
+        let empty: CsVec<f64> = CsVecBuilder::new(3).build();
+        assert_eq!(empty, CsVec::empty(3));
+    }
+
+    #[test]
+    #[should_panic]
+    fn builder_panics_out_of_bounds() {
+        let mut builder = CsVecBuilder::new(3);
+        builder.push(3, 1.);
+    }
+
+    #[test]
+    fn pattern_ops() {
+        let (vec1, vec2) = (test_vec1(), test_vec2());
+        let union = vec1.union_pattern(&vec2);
+        assert_eq!(union.indices(), &[0, 1, 2, 4, 5, 6, 7]);
+        assert!(union.data().iter().all(|&x| x));
+        assert_eq!(vec1.intersect_pattern(&vec2).indices(), &[0, 4, 7]);
+        assert_eq!(vec1.difference_pattern(&vec2).indices(), &[1, 5]);
+        assert_eq!(vec2.difference_pattern(&vec1).indices(), &[2, 6]);
+        let empty = CsVec::<i32>::empty(8);
+        assert_eq!(vec1.union_pattern(&empty).indices(), vec1.indices());
+        assert_eq!(empty.intersect_pattern(&vec1).nnz(), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn pattern_ops_panic() {
+        test_vec1().union_pattern(&CsVec::<f64>::empty(7));
+    }
+
     #[test]
     fn can_construct_zero_sized_vectors() {
         CsVec::<f64>::new(0, vec![], vec![]);
@@ -1288,4 +2168,21 @@ mod test {
         let vector = CsVec::new(3, vec![1, 2], vec![0., 0.]);
         assert!(vector.is_zero());
     }
+
+    #[test]
+    fn new_view_with_validation() {
+        use sparse::CsVecView;
+        use sparse::validation::ValidationLevel;
+        use errors::StructureError;
+        let indices = [3, 1];
+        let data = [1., 2.];
+        let res = CsVecView::new_view_with_validation(4, &indices, &data,
+                                                      ValidationLevel::Full);
+        assert_eq!(res, Err(StructureError::NonSortedIndices.into()));
+        if !cfg!(debug_assertions) {
+            let res = CsVecView::new_view_with_validation(
+                4, &indices, &data, ValidationLevel::None);
+            assert_eq!(res.unwrap().nnz(), 2);
+        }
+    }
 }
@@ -3,6 +3,7 @@
 
 use std::default::Default;
 use std::slice;
+use std::vec::Vec;
 
 /// A double stack of fixed capacity, growing from the left to the right
 /// or conversely.
@@ -0,0 +1,125 @@
+///! Strategies generating arbitrary sparse matrices and vectors for
+///! property based testing with `proptest`
+///!
+///! This module is available with the `proptest` feature. Its strategies
+///! generate valid values of the sprs types, of bounded shape and number of
+///! non-zeros, with values drawn from a user-provided strategy, so that
+///! downstream crates can check that their own sparse code holds for any
+///! input respecting the invariants of sprs, and not only for a few hand
+///! written examples. Failing values are shrunk towards smaller shapes and
+///! fewer non-zeros.
+///!
+///! # Example
+///!
+///! ```rust
+///! extern crate proptest;
+///! extern crate sprs;
+///! use proptest::test_runner::TestRunner;
+///! use sprs::strategies::csmat;
+///! # fn main() {
+///! let mut runner = TestRunner::default();
+///! runner.run(&csmat((10, 10), 30, -10i32..10), |mat| {
+///!     assert_eq!(mat.transpose_view().transpose_view(), mat.view());
+///!     Ok(())
+///! }).unwrap();
+///! # }
+///! ```
+
+use std::fmt::Debug;
+use num_traits::Num;
+use proptest::prelude::*;
+use proptest::collection;
+
+use sparse::{CsMat, CsVec, TriMat};
+use ::Shape;
+
+/// Generate triplet matrices with at most `max_shape.0` rows and
+/// `max_shape.1` columns, and at most `max_nnz` triplets, whose values
+/// are generated by `values`. Triplets can be repeated, as is allowed for
+/// triplet matrices, and empty dimensions can be generated.
+pub fn trimat<S>(max_shape: Shape,
+                 max_nnz: usize,
+                 values: S) -> BoxedStrategy<TriMat<S::Value>>
+where S: Strategy + Clone + 'static,
+      S::Value: 'static,
+{
+    (0..max_shape.0 + 1, 0..max_shape.1 + 1).prop_flat_map(move |shape| {
+        let (rows, cols) = shape;
+        if rows == 0 || cols == 0 {
+            return Just(shape).prop_map(TriMat::new).boxed();
+        }
+        let triplet = (0..rows, 0..cols, values.clone());
+        collection::vec(triplet, 0..max_nnz + 1).prop_map(move |triplets| {
+            let mut tri = TriMat::with_capacity(shape, triplets.len());
+            for (row, col, val) in triplets {
+                tri.add_triplet(row, col, val);
+            }
+            tri
+        }).boxed()
+    }).boxed()
+}
+
+/// Generate compressed matrices in either storage, with at most
+/// `max_shape.0` rows and `max_shape.1` columns, and at most `max_nnz`
+/// non-zeros, whose values are generated by `values`.
+///
+/// The matrices are built by summing the duplicates of generated triplets,
+/// so they can store explicit zeros.
+pub fn csmat<S>(max_shape: Shape,
+                max_nnz: usize,
+                values: S) -> BoxedStrategy<CsMat<S::Value>>
+where S: Strategy + Clone + 'static,
+      S::Value: Num + Clone + Debug + 'static,
+{
+    (trimat(max_shape, max_nnz, values), any::<bool>()).prop_map(|args| {
+        let (tri, is_csr) = args;
+        if is_csr { tri.to_csr() } else { tri.to_csc() }
+    }).boxed()
+}
+
+/// Generate sparse vectors of dimension at most `max_dim`, with at most
+/// `max_nnz` non-zeros, whose values are generated by `values`.
+pub fn csvec<S>(max_dim: usize,
+                max_nnz: usize,
+                values: S) -> BoxedStrategy<CsVec<S::Value>>
+where S: Strategy + Clone + 'static,
+      S::Value: Copy + 'static,
+{
+    (0..max_dim + 1).prop_flat_map(move |dim| {
+        let max_nnz = max_nnz.min(dim);
+        let indices = collection::btree_set(0..dim.max(1), 0..max_nnz + 1);
+        let values = values.clone();
+        indices.prop_flat_map(move |indices| {
+            let indices: Vec<_> = indices.into_iter().collect();
+            let nnz = indices.len();
+            collection::vec(values.clone(), nnz).prop_map(move |data| {
+                CsVec::new(dim, indices.clone(), data)
+            })
+        })
+    }).boxed()
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::test_runner::TestRunner;
+    use sparse::CsMatView;
+    use super::{csmat, csvec};
+
+    #[test]
+    fn generated_values_are_valid() {
+        let mut runner = TestRunner::default();
+        runner.run(&csmat((8, 6), 20, -5i64..5), |mat| {
+            assert!(mat.rows() <= 8 && mat.cols() <= 6 && mat.nnz() <= 20);
+            assert!(CsMatView::from_raw_parts(mat.storage(), mat.shape(),
+                                              mat.indptr(), mat.indices(),
+                                              mat.data()).is_ok());
+            Ok(())
+        }).unwrap();
+        runner.run(&csvec(10, 4, 0f64..1.), |vec| {
+            assert!(vec.dim() <= 10 && vec.nnz() <= 4);
+            assert!(vec.indices().windows(2).all(|w| w[0] < w[1]));
+            assert!(vec.indices().iter().all(|&i| i < vec.dim()));
+            Ok(())
+        }).unwrap();
+    }
+}
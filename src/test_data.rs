@@ -1,7 +1,17 @@
 //! Some matrices used in tests
 
-use sparse::CsMat;
+use sparse::{CsMat, TriMat};
 use ndarray::{arr2, Array, Ix2, ShapeBuilder};
+#[cfg(feature = "std")]
+use std::path::PathBuf;
+
+/// A path named after `name` in the temporary directory, unique to this
+/// test process so that concurrent test runs do not collide
+#[cfg(feature = "std")]
+pub fn temp_path(name: &str) -> PathBuf {
+    let name = format!("{}_{}", name, ::std::process::id());
+    ::std::env::temp_dir().join(name)
+}
 
 pub fn mat1() -> CsMat<f64> {
     let indptr = vec![0, 2, 4, 5, 6, 7];
@@ -114,3 +124,39 @@ pub fn mat_dense2() -> Array<f64, Ix2> {
                  [8.1, 1.9, 6.3, 0.2, 0.3, 5.9, 0. ]]);
     m.to_owned()
 }
+
+/// The `n x n` matrix of the 1D Laplacian with Dirichlet boundaries,
+/// tridiagonal with `2` on the diagonal and `-1` off the diagonal
+pub fn laplacian_1d(n: usize) -> CsMat<f64> {
+    let mut tri = TriMat::new((n, n));
+    for i in 0..n {
+        tri.add_triplet(i, i, 2.);
+        if i + 1 < n {
+            tri.add_triplet(i, i + 1, -1.);
+            tri.add_triplet(i + 1, i, -1.);
+        }
+    }
+    tri.to_csr()
+}
+
+/// The 5-point Laplacian on a `side x side` grid with Dirichlet boundaries,
+/// with the nodes numbered row by row
+pub fn laplacian_2d(side: usize) -> CsMat<f64> {
+    let n = side * side;
+    let mut tri = TriMat::new((n, n));
+    for i in 0..side {
+        for j in 0..side {
+            let node = i * side + j;
+            tri.add_triplet(node, node, 4.);
+            if i + 1 < side {
+                tri.add_triplet(node, node + side, -1.);
+                tri.add_triplet(node + side, node, -1.);
+            }
+            if j + 1 < side {
+                tri.add_triplet(node, node + 1, -1.);
+                tri.add_triplet(node + 1, node, -1.);
+            }
+        }
+    }
+    tri.to_csr()
+}
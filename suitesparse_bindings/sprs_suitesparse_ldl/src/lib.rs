@@ -5,6 +5,7 @@ extern crate num_traits;
 use std::ops::Deref;
 
 use sprs::{CsMatViewI, CsMatI, SpIndex, PermOwnedI};
+use sprs::linalg::LinearSolver;
 use suitesparse_ldl_sys::*;
 use num_traits::Num;
 
@@ -275,6 +276,26 @@ macro_rules! ldl_impl {
                 self.symbolic.nnz()
             }
         }
+
+        impl LinearSolver<f64> for $Numeric {
+            fn dim(&self) -> usize {
+                self.problem_size()
+            }
+
+            fn solve(&self, rhs: &[f64], x: &mut [f64]) {
+                assert_eq!(x.len(), rhs.len());
+                x.copy_from_slice(&self.solve(&rhs)[..]);
+            }
+
+            fn has_transpose_solve(&self) -> bool {
+                true
+            }
+
+            fn solve_transpose(&self, rhs: &[f64], x: &mut [f64]) {
+                // the factorized matrix is symmetric
+                LinearSolver::solve(self, rhs, x)
+            }
+        }
     )
 }
 
@@ -307,7 +328,8 @@ ldl_impl!(ldl_long,
 #[cfg(test)]
 mod tests {
     use sprs::{CsMatI, PermOwnedI};
-    use super::{LdlSymbolic, LdlLongSymbolic};
+    use sprs::linalg::LinearSolver;
+    use super::{LdlSymbolic, LdlLongSymbolic, LdlNumeric};
 
     #[test]
     fn ldl_symbolic() {
@@ -338,4 +360,22 @@ mod tests {
         let x = ldlt.solve(&b);
         assert_eq!(x, x0);
     }
+
+    #[test]
+    fn ldl_linear_solver() {
+        let mat = CsMatI::new_csc((4, 4),
+                                  vec![0, 2, 4, 6, 8],
+                                  vec![0, 3, 1, 2, 1, 2, 0, 3],
+                                  vec![1., 2., 21., 6., 6., 2., 2., 8.]);
+        let perm = PermOwnedI::new(vec![0, 2, 1, 3]);
+        let ldlt: LdlNumeric = LdlSymbolic::new_perm(mat.view(), perm)
+            .factor(mat.view());
+        let solver: &dyn LinearSolver<f64> = &ldlt;
+        assert_eq!(solver.dim(), 4);
+        let mut x = vec![0.; 4];
+        solver.solve(&[9., 60., 18., 34.], &mut x);
+        assert_eq!(x, vec![1., 2., 3., 4.]);
+        solver.solve_transpose(&[9., 60., 18., 34.], &mut x);
+        assert_eq!(x, vec![1., 2., 3., 4.]);
+    }
 }
@@ -0,0 +1,116 @@
+extern crate libc;
+
+use libc::{c_int, c_void, size_t};
+
+// itype
+pub const CHOLMOD_INT: c_int = 0;
+pub const CHOLMOD_LONG: c_int = 2;
+
+// xtype
+pub const CHOLMOD_PATTERN: c_int = 0;
+pub const CHOLMOD_REAL: c_int = 1;
+
+// dtype
+pub const CHOLMOD_DOUBLE: c_int = 0;
+
+// systems solved by cholmod_solve
+/// Solve `A x = b`
+pub const CHOLMOD_A: c_int = 0;
+
+/// A sparse matrix in compressed sparse column format
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct cholmod_sparse {
+    pub nrow: size_t,
+    pub ncol: size_t,
+    pub nzmax: size_t,
+    pub p: *mut c_void,
+    pub i: *mut c_void,
+    pub nz: *mut c_void,
+    pub x: *mut c_void,
+    pub z: *mut c_void,
+    /// 0 for an unsymmetric matrix, > 0 if only the upper triangle is used,
+    /// < 0 if only the lower triangle is used
+    pub stype: c_int,
+    pub itype: c_int,
+    pub xtype: c_int,
+    pub dtype: c_int,
+    pub sorted: c_int,
+    pub packed: c_int,
+}
+
+/// A dense matrix in column major format
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct cholmod_dense {
+    pub nrow: size_t,
+    pub ncol: size_t,
+    pub nzmax: size_t,
+    /// leading dimension
+    pub d: size_t,
+    pub x: *mut c_void,
+    pub z: *mut c_void,
+    pub xtype: c_int,
+    pub dtype: c_int,
+}
+
+/// The leading fields of a factorization, whose layout is shared by all
+/// versions of CHOLMOD. Factorizations are allocated by CHOLMOD and must only
+/// be handled through pointers.
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct cholmod_factor {
+    pub n: size_t,
+    /// The column at which the factorization failed, `n` on success
+    pub minor: size_t,
+}
+
+/// Number of 8 bytes words reserved for `cholmod_common`
+pub const CHOLMOD_COMMON_WORDS: usize = 2048;
+
+/// The parameters and workspace of CHOLMOD, initialized by `cholmod_start`.
+///
+/// Its layout depends on the version of CHOLMOD, so it is only exposed as a
+/// buffer larger than it is in all versions, to be allocated by the caller
+/// and only accessed by CHOLMOD.
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct cholmod_common {
+    _opaque: [f64; CHOLMOD_COMMON_WORDS],
+}
+
+/// An uninitialized common, to be passed to `cholmod_start` before any other
+/// use
+impl Default for cholmod_common {
+    fn default() -> cholmod_common {
+        cholmod_common {
+            _opaque: [0.; CHOLMOD_COMMON_WORDS],
+        }
+    }
+}
+
+#[link(name = "cholmod")]
+extern "C" {
+    pub fn cholmod_start(common: *mut cholmod_common) -> c_int;
+
+    pub fn cholmod_finish(common: *mut cholmod_common) -> c_int;
+
+    pub fn cholmod_analyze(a: *mut cholmod_sparse,
+                           common: *mut cholmod_common)
+                           -> *mut cholmod_factor;
+
+    pub fn cholmod_factorize(a: *mut cholmod_sparse,
+                             l: *mut cholmod_factor,
+                             common: *mut cholmod_common) -> c_int;
+
+    pub fn cholmod_solve(sys: c_int,
+                         l: *mut cholmod_factor,
+                         b: *mut cholmod_dense,
+                         common: *mut cholmod_common) -> *mut cholmod_dense;
+
+    pub fn cholmod_free_factor(l: *mut *mut cholmod_factor,
+                               common: *mut cholmod_common) -> c_int;
+
+    pub fn cholmod_free_dense(x: *mut *mut cholmod_dense,
+                              common: *mut cholmod_common) -> c_int;
+}
@@ -0,0 +1,99 @@
+extern crate libc;
+
+use libc::c_void;
+
+#[allow(non_camel_case_types)]
+pub type umfpack_int = libc::c_int;
+#[allow(non_camel_case_types)]
+pub type umfpack_long = libc::c_long;
+#[allow(non_camel_case_types)]
+pub type umfpack_double = libc::c_double;
+
+/// Length of the `control` arrays
+pub const UMFPACK_CONTROL: usize = 20;
+/// Length of the `info` arrays
+pub const UMFPACK_INFO: usize = 90;
+
+// status codes
+pub const UMFPACK_OK: umfpack_int = 0;
+pub const UMFPACK_WARNING_SINGULAR_MATRIX: umfpack_int = 1;
+pub const UMFPACK_ERROR_OUT_OF_MEMORY: umfpack_int = -1;
+pub const UMFPACK_ERROR_INVALID_MATRIX: umfpack_int = -8;
+
+// systems solved by umfpack_*_solve
+/// Solve `A x = b`
+pub const UMFPACK_A: umfpack_int = 0;
+/// Solve `A^T x = b`
+pub const UMFPACK_AT: umfpack_int = 1;
+
+#[link(name = "umfpack")]
+extern "C" {
+    pub fn umfpack_di_defaults(control: *mut umfpack_double);
+
+    pub fn umfpack_di_symbolic(n_row: umfpack_int,
+                               n_col: umfpack_int,
+                               ap: *const umfpack_int,
+                               ai: *const umfpack_int,
+                               ax: *const umfpack_double,
+                               symbolic: *mut *mut c_void,
+                               control: *const umfpack_double,
+                               info: *mut umfpack_double) -> umfpack_int;
+
+    pub fn umfpack_di_numeric(ap: *const umfpack_int,
+                              ai: *const umfpack_int,
+                              ax: *const umfpack_double,
+                              symbolic: *mut c_void,
+                              numeric: *mut *mut c_void,
+                              control: *const umfpack_double,
+                              info: *mut umfpack_double) -> umfpack_int;
+
+    pub fn umfpack_di_solve(sys: umfpack_int,
+                            ap: *const umfpack_int,
+                            ai: *const umfpack_int,
+                            ax: *const umfpack_double,
+                            x: *mut umfpack_double,
+                            b: *const umfpack_double,
+                            numeric: *mut c_void,
+                            control: *const umfpack_double,
+                            info: *mut umfpack_double) -> umfpack_int;
+
+    pub fn umfpack_di_free_symbolic(symbolic: *mut *mut c_void);
+
+    pub fn umfpack_di_free_numeric(numeric: *mut *mut c_void);
+
+    ////////////////////////////////////////////////////////////////////////////
+    //////////////////          long version           /////////////////////////
+    ////////////////////////////////////////////////////////////////////////////
+    pub fn umfpack_dl_defaults(control: *mut umfpack_double);
+
+    pub fn umfpack_dl_symbolic(n_row: umfpack_long,
+                               n_col: umfpack_long,
+                               ap: *const umfpack_long,
+                               ai: *const umfpack_long,
+                               ax: *const umfpack_double,
+                               symbolic: *mut *mut c_void,
+                               control: *const umfpack_double,
+                               info: *mut umfpack_double) -> umfpack_long;
+
+    pub fn umfpack_dl_numeric(ap: *const umfpack_long,
+                              ai: *const umfpack_long,
+                              ax: *const umfpack_double,
+                              symbolic: *mut c_void,
+                              numeric: *mut *mut c_void,
+                              control: *const umfpack_double,
+                              info: *mut umfpack_double) -> umfpack_long;
+
+    pub fn umfpack_dl_solve(sys: umfpack_long,
+                            ap: *const umfpack_long,
+                            ai: *const umfpack_long,
+                            ax: *const umfpack_double,
+                            x: *mut umfpack_double,
+                            b: *const umfpack_double,
+                            numeric: *mut c_void,
+                            control: *const umfpack_double,
+                            info: *mut umfpack_double) -> umfpack_long;
+
+    pub fn umfpack_dl_free_symbolic(symbolic: *mut *mut c_void);
+
+    pub fn umfpack_dl_free_numeric(numeric: *mut *mut c_void);
+}